@@ -16,6 +16,7 @@
 //! Command line options for classic Durs nodes (no specialization).
 
 use durs_core::commands::dbex::DbExOpt;
+use durs_core::commands::export::ExportOpt;
 use durs_core::commands::keys::KeysOpt;
 use durs_core::commands::modules::{DisableOpt, EnableOpt, ListModulesOpt};
 use durs_core::commands::reset::ResetOpt;
@@ -100,6 +101,10 @@ impl DursCliOpt {
                 options,
                 command: DursCommandEnum::Core(DursCoreCommand::EnableOpt(opts)),
             },
+            DursCliSubCommand::ExportOpt(opts) => DursCommand {
+                options,
+                command: DursCommandEnum::Core(DursCoreCommand::ExportOpt(opts)),
+            },
             DursCliSubCommand::KeysOpt(opts) => DursCommand {
                 options,
                 command: DursCommandEnum::Core(DursCoreCommand::KeysOpt(opts)),
@@ -143,6 +148,12 @@ pub enum DursCliSubCommand {
     /// Enable a module
     #[structopt(name = "enable")]
     EnableOpt(EnableOpt),
+    /// Export blockchain into json chunk files
+    #[structopt(
+        name = "export",
+        raw(setting = "structopt::clap::AppSettings::ColoredHelp")
+    )]
+    ExportOpt(ExportOpt),
     /// Keys operations
     #[structopt(
         name = "keys",
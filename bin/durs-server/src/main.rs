@@ -36,6 +36,9 @@ use durs_core::durs_plug;
 use log::error;
 use structopt::StructOpt;
 
+pub use durs_block_generator::GeneratorModule;
+pub use durs_mempool::MempoolModule;
+pub use durs_prover::ProverModule;
 #[cfg(unix)]
 pub use durs_tui::TuiModule;
 //pub use durs_skeleton::SkeletonModule;
@@ -63,7 +66,10 @@ macro_rules! durs_cli_main {
 fn main() {
     durs_cli_main!(durs_plug!(
         [WS2Pv1Module, WS2PModule],
-        [TuiModule /*, SkeletonModule ,DasaModule*/]
+        [
+            MempoolModule,
+            TuiModule /*, SkeletonModule ,DasaModule*/
+        ]
     ))
 }
 #[cfg(unix)]
@@ -71,10 +77,17 @@ fn main() {
 fn main() {
     durs_cli_main!(durs_plug!(
         [WS2Pv1Module, WS2PModule],
-        [TuiModule /*, SkeletonModule*/]
+        [
+            MempoolModule,
+            ProverModule,
+            TuiModule /*, SkeletonModule*/
+        ]
     ))
 }
 #[cfg(windows)]
 fn main() {
-    durs_cli_main!(durs_plug!([WS2Pv1Module, WS2PModule], []))
+    durs_cli_main!(durs_plug!(
+        [WS2Pv1Module, WS2PModule],
+        [GeneratorModule, MempoolModule, ProverModule]
+    ))
 }
@@ -28,4 +28,4 @@ pub static KEYPAIRS_FILENAME: &'static str = "keypairs.json";
 pub static DEFAULT_CURRRENCY: &'static str = "g1";
 
 /// Default value for `default_sync_module` conf field
-pub static DEFAULT_DEFAULT_SYNC_MODULE: &'static str = "ws2p1";
+pub static DEFAULT_DEFAULT_SYNC_MODULE: &'static str = "ws2p";
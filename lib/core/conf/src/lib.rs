@@ -220,6 +220,16 @@ pub enum DuRsGlobalConf {
     V2(DuRsConfV2),
 }
 
+impl DuRsGlobalConf {
+    /// Get ressources usage
+    pub fn ressources_usage(&self) -> ResourcesUsage {
+        match *self {
+            DuRsGlobalConf::V1(_) => ResourcesUsage::default(),
+            DuRsGlobalConf::V2(ref conf_v2) => conf_v2.ressources_usage,
+        }
+    }
+}
+
 impl DursGlobalConfTrait for DuRsGlobalConf {
     fn currency(&self) -> CurrencyName {
         match *self {
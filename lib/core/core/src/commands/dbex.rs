@@ -19,7 +19,11 @@ use crate::commands::DursExecutableCoreCommand;
 use crate::dbex;
 use crate::errors::DursCoreError;
 use crate::DursCore;
-use durs_blockchain::{DBExQuery, DBExTxQuery, DBExWotQuery};
+use dubp_documents::BlockNumber;
+use durs_blockchain::{
+    DALIdentityStateKind, DBExBlockQuery, DBExQuery, DBExTxQuery, DBExWotQuery, IdentitiesFilter,
+    PagingFilter, UidFilter,
+};
 use durs_conf::DuRsConf;
 
 #[derive(StructOpt, Debug, Clone)]
@@ -58,12 +62,60 @@ pub enum DbExSubCommand {
     )]
     /// Member explorer
     MemberOpt(MemberOpt),
+    #[structopt(
+        name = "idties",
+        raw(setting = "structopt::clap::AppSettings::ColoredHelp")
+    )]
+    /// Identities search
+    IdentitiesOpt(IdentitiesOpt),
+    #[structopt(
+        name = "certs",
+        raw(setting = "structopt::clap::AppSettings::ColoredHelp")
+    )]
+    /// Certifications explorer
+    CertsOpt(CertsOpt),
     #[structopt(
         name = "balance",
         raw(setting = "structopt::clap::AppSettings::ColoredHelp")
     )]
     /// Pubkeys’ balances explorer
     BalanceOpt(BalanceOpt),
+    #[structopt(
+        name = "block",
+        raw(setting = "structopt::clap::AppSettings::ColoredHelp")
+    )]
+    /// Block explorer
+    BlockOpt(BlockOpt),
+    #[structopt(
+        name = "blocks",
+        raw(setting = "structopt::clap::AppSettings::ColoredHelp")
+    )]
+    /// Blocks headers explorer
+    BlocksOpt(BlocksOpt),
+    #[structopt(
+        name = "forks",
+        raw(setting = "structopt::clap::AppSettings::ColoredHelp")
+    )]
+    /// Fork tree explorer
+    ForksOpt(ForksOpt),
+    #[structopt(
+        name = "tx",
+        raw(setting = "structopt::clap::AppSettings::ColoredHelp")
+    )]
+    /// Transaction explorer
+    TxOpt(TxOpt),
+    #[structopt(
+        name = "txs",
+        raw(setting = "structopt::clap::AppSettings::ColoredHelp")
+    )]
+    /// Transactions history explorer
+    TxsHistoryOpt(TxsHistoryOpt),
+    #[structopt(
+        name = "sources",
+        raw(setting = "structopt::clap::AppSettings::ColoredHelp")
+    )]
+    /// Sources (UTXOs and UDs) explorer
+    SourcesOpt(SourcesOpt),
 }
 
 #[derive(StructOpt, Debug, Copy, Clone)]
@@ -92,6 +144,45 @@ pub struct MemberOpt {
     pub uid: String,
 }
 
+#[derive(StructOpt, Debug, Clone)]
+/// CertsOpt
+pub struct CertsOpt {
+    /// public key or uid
+    pub address: String,
+}
+
+#[derive(StructOpt, Debug, Clone)]
+/// IdentitiesOpt
+pub struct IdentitiesOpt {
+    #[structopt(short = "u", long = "uid")]
+    /// show only the identity with this uid
+    pub uid: Option<String>,
+    #[structopt(long = "uid-prefix")]
+    /// show only the identities whose uid begins with this prefix
+    pub uid_prefix: Option<String>,
+    #[structopt(
+        long = "state",
+        raw(possible_values = r#"&["member", "expired", "revoked"]"#)
+    )]
+    /// show only the identities in this state
+    pub state: Option<String>,
+    #[structopt(long = "expire-before")]
+    /// show only the identities whose membership expires before this median time
+    pub expire_before: Option<u64>,
+    #[structopt(long = "expire-before-block")]
+    /// show only the identities whose membership expires before the median time of this block
+    pub expire_before_block: Option<u32>,
+    #[structopt(long = "certs-below")]
+    /// show only the identities that have received fewer certifications than this number
+    pub certs_below: Option<usize>,
+    #[structopt(short = "p", long = "page", default_value = "0")]
+    /// page number
+    pub page: usize,
+    #[structopt(short = "s", long = "page-size", default_value = "50")]
+    /// number of identities per page
+    pub page_size: usize,
+}
+
 #[derive(StructOpt, Debug, Clone)]
 /// BalanceOpt
 pub struct BalanceOpt {
@@ -99,6 +190,63 @@ pub struct BalanceOpt {
     pub address: String,
 }
 
+#[derive(StructOpt, Debug, Clone)]
+/// BlockOpt
+pub struct BlockOpt {
+    /// block number or hash
+    pub block: String,
+}
+
+#[derive(StructOpt, Debug, Copy, Clone)]
+/// BlocksOpt
+pub struct BlocksOpt {
+    /// first block number
+    pub begin: u32,
+    /// last block number
+    pub end: u32,
+}
+
+#[derive(StructOpt, Debug, Copy, Clone)]
+/// ForksOpt
+pub struct ForksOpt {
+    /// List the orphan blocks instead of the fork tree
+    #[structopt(short = "o", long = "orphans")]
+    pub orphans: bool,
+}
+
+#[derive(StructOpt, Debug, Clone)]
+/// TxOpt
+pub struct TxOpt {
+    /// transaction hash
+    pub hash: String,
+}
+
+#[derive(StructOpt, Debug, Clone)]
+/// TxsHistoryOpt
+pub struct TxsHistoryOpt {
+    /// public key or uid
+    pub address: String,
+    #[structopt(short = "f", long = "from", default_value = "0")]
+    /// show only the transactions written from this block
+    pub from: u32,
+    #[structopt(short = "t", long = "to")]
+    /// show only the transactions written until this block
+    pub to: Option<u32>,
+    #[structopt(short = "p", long = "page", default_value = "0")]
+    /// page number
+    pub page: usize,
+    #[structopt(short = "s", long = "page-size", default_value = "50")]
+    /// number of transactions per page
+    pub page_size: usize,
+}
+
+#[derive(StructOpt, Debug, Clone)]
+/// SourcesOpt
+pub struct SourcesOpt {
+    /// public key or uid
+    pub address: String,
+}
+
 impl DursExecutableCoreCommand for DbExOpt {
     fn execute(self, durs_core: DursCore<DuRsConf>) -> Result<(), DursCoreError> {
         let profile_path = durs_core.soft_meta_datas.profile_path;
@@ -116,6 +264,46 @@ impl DursExecutableCoreCommand for DbExOpt {
                 self.csv,
                 &DBExQuery::WotQuery(DBExWotQuery::MemberDatas(member_opts.uid)),
             ),
+            DbExSubCommand::CertsOpt(certs_opts) => dbex(
+                profile_path,
+                &durs_core.soft_meta_datas.conf,
+                self.csv,
+                &DBExQuery::WotQuery(DBExWotQuery::Certs(certs_opts.address)),
+            ),
+            DbExSubCommand::IdentitiesOpt(idties_opts) => {
+                let by_uid = if let Some(uid) = idties_opts.uid {
+                    Some(UidFilter::Exact(uid))
+                } else if let Some(uid_prefix) = idties_opts.uid_prefix {
+                    Some(UidFilter::Prefix(uid_prefix))
+                } else {
+                    None
+                };
+                let by_state = idties_opts.state.map(|state| match state.as_str() {
+                    "member" => DALIdentityStateKind::Member,
+                    "expired" => DALIdentityStateKind::Expired,
+                    _ => DALIdentityStateKind::Revoked,
+                });
+                dbex(
+                    profile_path,
+                    &durs_core.soft_meta_datas.conf,
+                    self.csv,
+                    &DBExQuery::WotQuery(DBExWotQuery::Identities(
+                        IdentitiesFilter {
+                            paging: PagingFilter {
+                                page_size: idties_opts.page_size,
+                                page_number: idties_opts.page,
+                                ..PagingFilter::default()
+                            },
+                            by_pubkey: None,
+                            by_uid,
+                            by_state,
+                            ms_expires_before: idties_opts.expire_before,
+                            certs_count_below: idties_opts.certs_below,
+                        },
+                        idties_opts.expire_before_block.map(BlockNumber),
+                    )),
+                )
+            }
             DbExSubCommand::MembersOpt(members_opts) => {
                 if members_opts.expire {
                     dbex(
@@ -139,6 +327,50 @@ impl DursExecutableCoreCommand for DbExOpt {
                 self.csv,
                 &DBExQuery::TxQuery(DBExTxQuery::Balance(balance_opts.address)),
             ),
+            DbExSubCommand::BlockOpt(block_opts) => dbex(
+                profile_path,
+                &durs_core.soft_meta_datas.conf,
+                self.csv,
+                &DBExQuery::BlockQuery(DBExBlockQuery::Block(block_opts.block)),
+            ),
+            DbExSubCommand::BlocksOpt(blocks_opts) => dbex(
+                profile_path,
+                &durs_core.soft_meta_datas.conf,
+                self.csv,
+                &DBExQuery::BlockQuery(DBExBlockQuery::Headers(blocks_opts.begin, blocks_opts.end)),
+            ),
+            DbExSubCommand::ForksOpt(forks_opts) => dbex(
+                profile_path,
+                &durs_core.soft_meta_datas.conf,
+                self.csv,
+                &DBExQuery::BlockQuery(DBExBlockQuery::ForkTree(forks_opts.orphans)),
+            ),
+            DbExSubCommand::TxOpt(tx_opts) => dbex(
+                profile_path,
+                &durs_core.soft_meta_datas.conf,
+                self.csv,
+                &DBExQuery::TxQuery(DBExTxQuery::Tx(tx_opts.hash)),
+            ),
+            DbExSubCommand::TxsHistoryOpt(txs_opts) => dbex(
+                profile_path,
+                &durs_core.soft_meta_datas.conf,
+                self.csv,
+                &DBExQuery::TxQuery(DBExTxQuery::History(
+                    txs_opts.address,
+                    PagingFilter {
+                        from: BlockNumber(txs_opts.from),
+                        to: txs_opts.to.map(BlockNumber),
+                        page_size: txs_opts.page_size,
+                        page_number: txs_opts.page,
+                    },
+                )),
+            ),
+            DbExSubCommand::SourcesOpt(sources_opts) => dbex(
+                profile_path,
+                &durs_core.soft_meta_datas.conf,
+                self.csv,
+                &DBExQuery::TxQuery(DBExTxQuery::Sources(sources_opts.address)),
+            ),
         }
 
         Ok(())
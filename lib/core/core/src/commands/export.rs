@@ -0,0 +1,54 @@
+//  Copyright (C) 2018  The Duniter Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Durs-core cli : export subcommand.
+
+use crate::commands::DursExecutableCoreCommand;
+use crate::errors::DursCoreError;
+use crate::DursCore;
+use durs_blockchain::BlockchainModule;
+use durs_conf::DuRsConf;
+use std::path::PathBuf;
+
+#[derive(StructOpt, Debug, Clone)]
+#[structopt(
+    name = "export",
+    raw(setting = "structopt::clap::AppSettings::ColoredHelp")
+)]
+/// Export the local blockchain into json chunk files (readable by `durs sync -t ts`)
+pub struct ExportOpt {
+    /// Folder where the json chunk files are written
+    #[structopt(parse(from_os_str))]
+    pub output: PathBuf,
+    /// First block to export (default: 0)
+    #[structopt(short = "s", long = "start")]
+    pub start: Option<u32>,
+    /// Last block to export (default: current block)
+    #[structopt(short = "e", long = "end")]
+    pub end: Option<u32>,
+}
+
+impl DursExecutableCoreCommand for ExportOpt {
+    fn execute(self, durs_core: DursCore<DuRsConf>) -> Result<(), DursCoreError> {
+        BlockchainModule::export(
+            durs_core.soft_meta_datas.profile_path,
+            &durs_core.soft_meta_datas.conf,
+            &self.output,
+            self.start,
+            self.end,
+        );
+        Ok(())
+    }
+}
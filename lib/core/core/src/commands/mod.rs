@@ -16,6 +16,7 @@
 //! Define durs-core cli subcommands options.
 
 pub mod dbex;
+pub mod export;
 pub mod keys;
 pub mod modules;
 pub mod reset;
@@ -26,6 +27,7 @@ use crate::DursCore;
 pub use dbex::*;
 use durs_conf::DuRsConf;
 pub use durs_network::cli::sync::SyncOpt;
+pub use export::ExportOpt;
 pub use keys::KeysOpt;
 use log::Level;
 pub use modules::*;
@@ -117,6 +119,8 @@ pub enum DursCoreCommand {
     ResetOpt(ResetOpt),
     /// Database explorer
     DbExOpt(DbExOpt),
+    /// Export blockchain
+    ExportOpt(ExportOpt),
     /// Keys operations
     KeysOpt(KeysOpt),
 }
@@ -99,7 +99,7 @@ enum ServerMode {
     /// Start
     Start(),
     /// Sync (SyncEndpoint)
-    _Sync(SyncOpt),
+    Sync(SyncOpt),
     /// List modules
     ListModules(ListModulesOpt),
 }
@@ -197,13 +197,38 @@ impl DursCore<DuRsConf> {
                 durs_core.start()
             }
             DursCoreCommand::SyncOpt(opts) => match opts.source_type {
-                SyncSourceType::Network => unimplemented!(),
+                SyncSourceType::Network => {
+                    durs_core.server_command = Some(ServerMode::Sync(opts.clone()));
+
+                    // The blocks downloaded by the sync module are sent directly to the blockchain
+                    let (network_sender, network_receiver) = mpsc::channel();
+                    durs_core.router_sender = Some(network_sender);
+                    plug_modules(&mut durs_core)?;
+                    // Drop the core sender, so that the blockchain detects the end of the sync module
+                    durs_core.router_sender = None;
+
+                    BlockchainModule::sync_network(
+                        profile_path.clone(),
+                        &durs_core.soft_meta_datas.conf,
+                        opts,
+                        network_receiver,
+                    );
+
+                    // Wait until the sync module thread is finished
+                    for (module_static_name, module_thread_handler) in durs_core.threads.drain() {
+                        if let Err(err) = module_thread_handler.join() {
+                            error!("'{}' module thread panic : {:?}", module_static_name.0, err);
+                        }
+                    }
+                    Ok(())
+                }
                 SyncSourceType::LocalDuniter => {
                     sync_ts(profile_path.clone(), &durs_core.soft_meta_datas.conf, opts);
                     Ok(())
                 }
             },
             DursCoreCommand::DbExOpt(opts) => opts.execute(durs_core),
+            DursCoreCommand::ExportOpt(opts) => opts.execute(durs_core),
             DursCoreCommand::ResetOpt(opts) => opts.execute(durs_core),
             DursCoreCommand::KeysOpt(opts) => opts.execute(durs_core),
         }
@@ -289,7 +314,10 @@ impl DursCore<DuRsConf> {
                 static_name: BlockchainModule::name(),
                 sender: blockchain_sender,
                 roles: vec![ModuleRole::BlockchainDatas, ModuleRole::BlockValidation],
-                events_subscription: vec![ModuleEvent::NewBlockFromNetwork],
+                events_subscription: vec![
+                    ModuleEvent::NewBlockFromNetwork,
+                    ModuleEvent::NewBlockFromSelf,
+                ],
                 reserved_apis_parts: vec![],
                 endpoints: vec![],
             })
@@ -346,7 +374,7 @@ impl DursCore<DuRsConf> {
         let enabled = enabled::<DuRsConf, DursMsg, NM>(&self.soft_meta_datas.conf);
         if enabled {
             self.network_modules_count += 1;
-            if let Some(ServerMode::_Sync(ref network_sync)) = self.server_command {
+            if let Some(ServerMode::Sync(ref network_sync)) = self.server_command {
                 if NM::name().0
                     == self
                         .soft_meta_datas
@@ -390,10 +418,11 @@ impl DursCore<DuRsConf> {
                                     router_sender,
                                     sync_params,
                                 )
-                                .unwrap_or_else(|_| {
+                                .unwrap_or_else(|e| {
                                     fatal_error!(
-                                        "Fatal error : fail to load module '{}' !",
-                                        NM::name().to_string()
+                                        "Fail to sync from module '{}' : {}",
+                                        NM::name(),
+                                        e
                                     )
                                 });
                             })
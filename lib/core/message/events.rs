@@ -48,8 +48,10 @@ pub enum MemPoolEvent {
 pub enum BlockchainEvent {
     /// Stack up new valid block in local blockchain
     StackUpValidBlock(Box<BlockDocument>),
-    /// Revert blocks in local blockchain
-    RevertBlocks(Vec<BlockDocument>),
+    /// New fork branch in the local fork tree
+    NewFork(ForkBranch),
+    /// Revert blocks in local blockchain (switch to another branch)
+    RevertBlocks(Box<BlockchainReorg>),
     /// Receive new valid pending document
     NewValidPendingDoc(DUBPDocument),
     /// Receive new refused pending document
@@ -57,3 +59,34 @@ pub enum BlockchainEvent {
     /// Receive new refused pending block
     RefusedBlock(Blockstamp),
 }
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Fork branch of the local fork tree
+pub struct ForkBranch {
+    /// Last block shared with the main branch
+    pub fork_point: Blockstamp,
+    /// Blockstamps of the fork branch (from the oldest to the newest)
+    pub blockstamps: Vec<Blockstamp>,
+}
+
+#[derive(Debug, Clone)]
+/// Switch of the local blockchain from its main branch to a fork branch
+pub struct BlockchainReorg {
+    /// Last block shared by the old and the new branch
+    pub fork_point: Blockstamp,
+    /// Blockstamps of the old branch (from the oldest to the newest)
+    pub old_branch: Vec<Blockstamp>,
+    /// Blockstamps of the new branch (from the oldest to the newest)
+    pub new_branch: Vec<Blockstamp>,
+    /// Reverted blocks (from the newest to the oldest)
+    pub reverted_blocks: Vec<BlockDocument>,
+    /// Applied blocks (from the oldest to the newest)
+    pub applied_blocks: Vec<BlockDocument>,
+}
+
+impl BlockchainReorg {
+    /// Number of reverted blocks
+    pub fn depth(&self) -> usize {
+        self.old_branch.len()
+    }
+}
@@ -14,25 +14,25 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::*;
+use dubp_documents::documents::block::BlockDocument;
 use dubp_documents::BlockNumber;
-use dup_crypto::hashs::Hash;
 use dup_crypto::keys::*;
 use durs_blockchain_dal::filters::identities::IdentitiesFilter;
-use durs_network::requests::OldNetworkRequest;
+use durs_network::requests::{NetworkRequest, OldNetworkRequest};
 
 #[derive(Debug, Clone)]
 /// Modules request content
 pub enum DursReqContent {
     /// Request to the old network module
     OldNetworkRequest(OldNetworkRequest),
-    /// Network request (Not yet implemented)
-    NetworkRequest(),
+    /// Request to the network module
+    NetworkRequest(NetworkRequest),
     /// Blockchain datas request
     BlockchainRequest(BlockchainRequest),
     /// Mem pool datas request
     MemPoolRequest(MemPoolRequest),
     /// Request to the pow module
-    ProverRequest(BlockNumber, Hash),
+    ProverRequest(ProverRequest),
     /// Arbitrary datas
     ArbitraryDatas(ArbitraryDatas),
 }
@@ -60,6 +60,8 @@ pub enum BlockchainRequest {
     UIDs(Vec<PubKey>),
     /// Get identities
     GetIdentities(IdentitiesFilter),
+    /// Forks statistics
+    ForksStats,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -71,4 +73,15 @@ pub enum MemPoolRequest {
     AllPendingIdentitiesWithoutCerts(usize),
     /// All pending datas for given pubkey
     PendingWotDatasForPubkey(PubKey),
+    /// All pending documents (to generate the next block)
+    AllPendingDocs,
+}
+
+#[derive(Debug, Clone)]
+/// Proof of work request
+pub struct ProverRequest {
+    /// Block template (inner hash computed, without nonce nor signature)
+    pub block: Box<BlockDocument>,
+    /// Personalized difficulty of the block issuer (pow_min plus the issuer handicap)
+    pub difficulty: usize,
 }
@@ -18,6 +18,7 @@ use dubp_documents::documents::certification::CertificationDocument;
 use dubp_documents::documents::identity::IdentityDocument;
 use dubp_documents::documents::membership::MembershipDocument;
 use dubp_documents::documents::revocation::RevocationDocument;
+use dubp_documents::documents::transaction::TransactionDocument;
 use dubp_documents::BlockNumber;
 use dubp_documents::Blockstamp;
 use dup_crypto::hashs::Hash;
@@ -54,6 +55,21 @@ pub struct PendingIdtyDatas {
     pub revocation: Option<RevocationDocument>,
 }
 
+#[derive(Debug, Clone, Default)]
+/// Pending documents
+pub struct PendingDocs {
+    /// Identities documents
+    pub identities: Vec<IdentityDocument>,
+    /// Memberships documents
+    pub memberships: Vec<MembershipDocument>,
+    /// Certifications documents
+    pub certifications: Vec<CertificationDocument>,
+    /// Revocations documents
+    pub revocations: Vec<RevocationDocument>,
+    /// Transactions documents
+    pub transactions: Vec<TransactionDocument>,
+}
+
 #[derive(Debug, Clone)]
 /// Response to a BlockchainReqBlockchain request
 pub enum BlockchainResponse {
@@ -69,6 +85,33 @@ pub enum BlockchainResponse {
     UIDs(HashMap<PubKey, Option<String>>),
     /// Identities
     Identities(Vec<IdentityDocument>),
+    /// Forks statistics
+    ForksStats(ForksStats),
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+/// Statistics on the reorganizations of the local blockchain
+pub struct ForksStats {
+    /// Number of reorganizations
+    pub reorgs_count: usize,
+    /// Total number of reverted blocks
+    pub reverted_blocks_count: usize,
+    /// Depth of the last reorganization
+    pub last_reorg_depth: usize,
+    /// Depth of the deepest reorganization
+    pub max_reorg_depth: usize,
+}
+
+impl ForksStats {
+    /// Take into account a new reorganization of the given depth
+    pub fn add_reorg(&mut self, depth: usize) {
+        self.reorgs_count += 1;
+        self.reverted_blocks_count += depth;
+        self.last_reorg_depth = depth;
+        if depth > self.max_reorg_depth {
+            self.max_reorg_depth = depth;
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -80,4 +123,6 @@ pub enum MemPoolResponse {
     AllPendingIdentitiesWithoutCerts(ModuleReqId, HashMap<Hash, PendingIdtyDatas>),
     /// All pending datas for given pubkey
     PendingWotDatasForPubkey(ModuleReqId, Box<PendingIdtyDatas>),
+    /// All pending documents
+    AllPendingDocs(ModuleReqId, Box<PendingDocs>),
 }
@@ -186,6 +186,8 @@ pub enum ModuleRole {
     CurrencyPool,
     /// Manages the network between nodes implementing the DUP protocol
     InterNodesNetwork,
+    /// Computes the proof of work of the blocks generated by the local node
+    Prover,
     /// Communicates with the node user
     UserInterface,
     /// Manage pending data for the wot
@@ -202,6 +204,8 @@ pub enum ModuleEvent {
     NewTxFromNetwork,
     /// A new wot document has been received from a network.
     NewWotDocFromNetwork,
+    /// The local node has found the proof of work of a new block
+    NewBlockFromSelf,
     /// A new valid block has been added to the local blockchain
     NewValidBlock,
     /// A new valid block issued by the local node has been added to the local blockchain
@@ -20,6 +20,7 @@ use crate::network_head::NetworkHead;
 use crate::network_peer::PeerCard;
 use crate::NodeFullId;
 use dubp_documents::documents::block::BlockDocument;
+use dubp_documents::{Blockstamp, CurrencyName};
 
 #[derive(Debug, Clone)]
 /// Type containing a network event, each time a network event occurs it's relayed to all modules
@@ -32,6 +33,8 @@ pub enum NetworkEvent {
     ReceiveDocuments(Vec<BlockchainDocument>),
     /// Receiving blocks
     ReceiveBlocks(Vec<BlockDocument>),
+    /// Synchronization target chosen by the network module (last block to be synchronized)
+    SyncTarget(CurrencyName, Blockstamp),
     /// Receipt of peer cards
     ReceivePeers(Vec<PeerCard>),
     /// Receiving heads
@@ -91,4 +91,12 @@ pub enum SyncError {
         /// Source
         source: String,
     },
+    /// The source repeatedly provides invalid datas
+    #[fail(display = "invalid datas from source {}: {}", source, reason)]
+    InvalidSourceDatas {
+        /// Source
+        source: String,
+        /// Reason
+        reason: String,
+    },
 }
@@ -18,7 +18,8 @@
 use crate::documents::*;
 use crate::*;
 use dubp_documents::documents::block::BlockDocument;
-use dubp_documents::Blockstamp;
+use dubp_documents::{BlockNumber, Blockstamp};
+use dup_crypto::hashs::Hash;
 
 #[derive(Debug, Copy, Clone)]
 /// Type containing a request addressed to the network module
@@ -56,6 +57,38 @@ impl OldNetworkRequest {
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// Type containing a request addressed to a remote node through the network module
+pub struct NetworkRequest {
+    /// Remote node to query (if none, the network module choose one of its connections)
+    pub receiver: Option<NodeFullId>,
+    /// Request content
+    pub content: NetworkRequestContent,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// Content of a request addressed to a remote node
+pub enum NetworkRequestContent {
+    /// Get current blockstamp of remote node
+    GetCurrent,
+    /// Get hashs of `count` blocks from block number `from`
+    GetBlocksHashs {
+        /// First block number
+        from: BlockNumber,
+        /// Number of blocks
+        count: u16,
+    },
+    /// Get a chunk of `count` blocks from block number `from`
+    GetChunk {
+        /// First block number
+        from: BlockNumber,
+        /// Number of blocks
+        count: u16,
+    },
+    /// Get the chunk containing the given block
+    GetChunkByHash(Blockstamp),
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 /// Type returned when the network module does not get a satisfying answer to a request
 pub enum OldNetworkRequestError {
@@ -84,6 +117,12 @@ pub enum NetworkResponse {
     Consensus(ModuleReqFullId, Result<Blockstamp, NetworkConsensusError>),
     /// HeadsCache
     HeadsCache(ModuleReqFullId, Box<NetworkHead>),
+    /// Current blockstamp of a remote node
+    Current(ModuleReqFullId, NodeFullId, Blockstamp),
+    /// Blocks hashs
+    BlocksHashs(ModuleReqFullId, NodeFullId, Vec<Hash>),
+    /// The request could not be satisfied
+    Error(ModuleReqFullId, OldNetworkRequestError),
 }
 
 impl NetworkResponse {
@@ -95,7 +134,10 @@ impl NetworkResponse {
             | NetworkResponse::Chunk(ref req_id, _, _)
             | NetworkResponse::PendingDocuments(ref req_id, _)
             | NetworkResponse::Consensus(ref req_id, _)
-            | NetworkResponse::HeadsCache(ref req_id, _) => *req_id,
+            | NetworkResponse::HeadsCache(ref req_id, _)
+            | NetworkResponse::Current(ref req_id, _, _)
+            | NetworkResponse::BlocksHashs(ref req_id, _, _)
+            | NetworkResponse::Error(ref req_id, _) => *req_id,
         }
     }
     /// Get request identifier
@@ -0,0 +1,26 @@
+//  Copyright (C) 2018  The Duniter Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+/// Module name
+pub static MODULE_NAME: &'static str = "block-generator";
+
+/// Default maximum number of transactions in a generated block
+pub static DEFAULT_MAX_TXS_PER_BLOCK: &'static usize = &100;
+
+/// Frequency of the block generator main loop when it does not receive any message
+pub static GENERATOR_LOOP_FREQ_IN_MS: &'static u64 = &1_000;
+
+/// Delay before generating the next block again after a failed generation
+pub static GENERATION_RETRY_DELAY_IN_SECS: &'static u64 = &10;
@@ -0,0 +1,684 @@
+//  Copyright (C) 2018  The Duniter Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Generation of the next block from the local blockchain and the pending documents.
+
+use dubp_documents::documents::block::{BlockDocument, TxDocOrTxHash};
+use dubp_documents::documents::membership::{MembershipDocument, MembershipType};
+use dubp_documents::text_document_traits::TextDocumentFormat;
+use dubp_documents::*;
+use dup_crypto::keys::PubKey;
+use durs_blockchain::{
+    expected_issuers_frame, expected_median_time, expected_members_count, expected_pow_min,
+    issuer_difficulty, max_time_acceleration, verify_block_template_validity, BlockError,
+    InvalidBlockError, UdState,
+};
+use durs_blockchain_dal::entities::currency_params::CurrencyParameters;
+use durs_blockchain_dal::entities::identity::{DALIdentity, DALIdentityState};
+use durs_blockchain_dal::*;
+use durs_message::responses::PendingDocs;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::ops::Deref;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+/// Databases read to generate the next block (written by the blockchain module)
+pub struct GeneratorDBs {
+    /// Blocks databases
+    pub blocks_dbs: BlocksV10DBs,
+    /// Wot databases
+    pub wot_dbs: WotsV10DBs,
+    /// Currency databases
+    pub currency_dbs: CurrencyV10DBs,
+}
+
+impl GeneratorDBs {
+    /// Open databases from their respective files in read-only mode (in memory if `db_path` is `None`)
+    pub fn open(db_path: Option<&PathBuf>) -> GeneratorDBs {
+        GeneratorDBs {
+            blocks_dbs: BlocksV10DBs::open_read_only(db_path),
+            wot_dbs: WotsV10DBs::open_read_only(db_path),
+            currency_dbs: CurrencyV10DBs::open_read_only(db_path),
+        }
+    }
+    /// Reload databases from their respective files
+    pub fn load(&self) -> Result<(), DALError> {
+        self.blocks_dbs.load()?;
+        self.wot_dbs.load()?;
+        self.currency_dbs.load()
+    }
+    /// Get current blockstamp (`None` if the local blockchain is empty)
+    pub fn current_blockstamp(&self) -> Result<Option<Blockstamp>, DALError> {
+        let blocks_count = self.blocks_dbs.blockchain_db.len()? as u32;
+        Ok(self
+            .blocks_dbs
+            .blockchain_db
+            .get(&BlockNumber(blocks_count.saturating_sub(1)))?
+            .map(|dal_block| dal_block.blockstamp()))
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+/// Reason why the next block can't be generated
+pub enum GenerationError {
+    /// The local blockchain is empty
+    EmptyBlockchain,
+    /// The issuer of the next block is not a member
+    IssuerNotMember,
+    /// Fail to read databases
+    DALError(DALError),
+    /// The generated block does not comply with the protocol
+    InvalidBlock(BlockError),
+}
+
+impl From<DALError> for GenerationError {
+    fn from(err: DALError) -> Self {
+        GenerationError::DALError(err)
+    }
+}
+
+impl From<BlockError> for GenerationError {
+    fn from(err: BlockError) -> Self {
+        GenerationError::InvalidBlock(err)
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Next block, ready for the proof of work
+pub struct BlockTemplate {
+    /// Block (inner hash computed, without nonce nor signature)
+    pub block: BlockDocument,
+    /// Personalized difficulty of the block issuer
+    pub difficulty: usize,
+}
+
+/// Check if an identity is member
+fn is_member(idty: &DALIdentity) -> bool {
+    if let DALIdentityState::Member(_) = idty.state {
+        true
+    } else {
+        false
+    }
+}
+
+/// Check if an identity is revoked
+fn is_revoked(idty: &DALIdentity) -> bool {
+    match idty.state {
+        DALIdentityState::ExplicitRevoked(_)
+        | DALIdentityState::ExplicitExpireRevoked(_)
+        | DALIdentityState::ImplicitRevoked(_) => true,
+        DALIdentityState::Member(_) | DALIdentityState::ExpireMember(_) => false,
+    }
+}
+
+/// Fill the wot part of the block (identities, memberships, revocations, exclusions and
+/// certifications) with the pending documents that may be written
+///
+/// Returns the members that must be excluded (expired membership or not enough certifications).
+fn fill_wot_docs(
+    block: &mut BlockDocument,
+    dbs: &GeneratorDBs,
+    currency_params: &CurrencyParameters,
+    pending_docs: &PendingDocs,
+) -> Result<HashSet<PubKey>, DALError> {
+    let mut required_exclusions: HashSet<PubKey> = readers::identity::get_expired_members(
+        &dbs.wot_dbs.idties_indexes_db,
+        &dbs.blocks_dbs.blockchain_db,
+        currency_params.ms_validity,
+        block.median_time,
+    )?
+    .into_iter()
+    .collect();
+    // The certifications to the excluded members are not written, so the members losing
+    // certifications are those that do not keep enough of them without new certifications
+    let blocks_expiring = readers::certs::get_blocks_expiring_certs(
+        &dbs.blocks_dbs.blockchain_db,
+        &dbs.wot_dbs.certs_db,
+        currency_params.sig_validity,
+        block.median_time,
+    )?;
+    let expire_certs = readers::certs::find_expire_certs(&dbs.wot_dbs.certs_db, blocks_expiring)?;
+    required_exclusions.extend(readers::certs::get_members_losing_certs(
+        &dbs.wot_dbs,
+        &expire_certs,
+        &[],
+        currency_params.sig_qty,
+    )?);
+    // Identities of the issuers and targets of the pending documents
+    let mut identities: HashMap<PubKey, DALIdentity> = HashMap::new();
+    let pending_pubkeys = pending_docs
+        .revocations
+        .iter()
+        .map(|revocation| revocation.issuers()[0])
+        .chain(pending_docs.memberships.iter().map(|ms| ms.issuers()[0]))
+        .chain(
+            pending_docs
+                .certifications
+                .iter()
+                .flat_map(|cert| vec![*cert.source(), *cert.target()]),
+        );
+    for pubkey in pending_pubkeys {
+        if let Entry::Vacant(entry) = identities.entry(pubkey) {
+            if let Some(idty) = dbs.wot_dbs.identities_db.get(&pubkey)? {
+                entry.insert(idty);
+            }
+        }
+    }
+    let is_member_pubkey =
+        |pubkey: &PubKey| identities.get(pubkey).map_or(false, |idty| is_member(idty));
+
+    // Revocations (revoked members are excluded)
+    let mut excluded = required_exclusions.clone();
+    for revocation in &pending_docs.revocations {
+        let pubkey = revocation.issuers()[0];
+        if let Some(idty) = identities.get(&pubkey) {
+            if !is_revoked(idty) {
+                block
+                    .revoked
+                    .push(TextDocumentFormat::Complete(revocation.clone()));
+                if is_member(idty) {
+                    excluded.insert(pubkey);
+                }
+            }
+        }
+    }
+    block.excluded = excluded.iter().cloned().collect();
+
+    // Memberships: only the most recent membership of each pubkey
+    let mut memberships: Vec<&MembershipDocument> = pending_docs.memberships.iter().collect();
+    memberships.sort_by(|ms1, ms2| ms2.blockstamp().id.cmp(&ms1.blockstamp().id));
+    let mut ms_pubkeys = HashSet::new();
+    let mut newcomers_uids = HashSet::new();
+    for ms in memberships {
+        let pubkey = ms.issuers()[0];
+        if excluded.contains(&pubkey) || !ms_pubkeys.insert(pubkey) {
+            continue;
+        }
+        match (ms.membership(), identities.get(&pubkey)) {
+            (MembershipType::In(), Some(idty)) if is_member(idty) => block.actives.push(ms.clone()),
+            (MembershipType::In(), Some(idty)) if !is_revoked(idty) => {
+                block.joiners.push(ms.clone())
+            }
+            (MembershipType::In(), None) => {
+                let uid = ms.identity_username();
+                if newcomers_uids.contains(uid)
+                    || readers::identity::get_pubkey_from_uid(&dbs.wot_dbs.idties_indexes_db, uid)?
+                        .is_some()
+                {
+                    continue;
+                }
+                if let Some(idty) = pending_docs
+                    .identities
+                    .iter()
+                    .find(|idty| idty.issuers()[0] == pubkey && idty.username() == uid)
+                {
+                    newcomers_uids.insert(uid);
+                    block.identities.push(idty.clone());
+                    block.joiners.push(ms.clone());
+                }
+            }
+            (MembershipType::Out(), Some(idty)) if is_member(idty) => {
+                block.leavers.push(ms.clone())
+            }
+            _ => {}
+        }
+    }
+
+    // Certifications between members and joiners
+    let joiners: HashSet<PubKey> = block.joiners.iter().map(|ms| ms.issuers()[0]).collect();
+    let mut links = HashSet::new();
+    for cert in &pending_docs.certifications {
+        let (issuer, target) = (*cert.source(), *cert.target());
+        if issuer != target
+            && is_member_pubkey(&issuer)
+            && !excluded.contains(&issuer)
+            && ((is_member_pubkey(&target) && !excluded.contains(&target))
+                || joiners.contains(&target))
+            && links.insert((issuer, target))
+        {
+            block
+                .certifications
+                .push(TextDocumentFormat::Complete(cert.clone()));
+        }
+    }
+
+    Ok(required_exclusions)
+}
+
+/// Remove the memberships of a pubkey (and its identity and received certifications if it
+/// was a joiner)
+fn remove_memberships(block: &mut BlockDocument, pubkey: PubKey) {
+    if block.joiners.iter().any(|ms| ms.issuers()[0] == pubkey) {
+        block.joiners.retain(|ms| ms.issuers()[0] != pubkey);
+        block
+            .certifications
+            .retain(|cert| cert.to_compact_document().target != pubkey);
+    }
+    block.identities.retain(|idty| idty.issuers()[0] != pubkey);
+    block.actives.retain(|ms| ms.issuers()[0] != pubkey);
+    block.leavers.retain(|ms| ms.issuers()[0] != pubkey);
+}
+
+/// Remove the certifications matching `filter`
+fn remove_certs<F: Fn(PubKey, PubKey) -> bool>(block: &mut BlockDocument, filter: F) {
+    block.certifications.retain(|cert| {
+        let compact_cert = cert.to_compact_document();
+        !filter(compact_cert.issuer, compact_cert.target)
+    });
+}
+
+/// Number of documents in the block
+fn docs_count(block: &BlockDocument) -> usize {
+    block.identities.len()
+        + block.joiners.len()
+        + block.actives.len()
+        + block.leavers.len()
+        + block.revoked.len()
+        + block.excluded.len()
+        + block.certifications.len()
+        + block.transactions.len()
+}
+
+/// Remove from the block the documents that break a rule
+///
+/// Returns `false` if no document can be blamed.
+fn remove_invalid_docs(
+    block: &mut BlockDocument,
+    required_exclusions: &HashSet<PubKey>,
+    err: InvalidBlockError,
+) -> bool {
+    let docs_count_before = docs_count(block);
+    match err {
+        InvalidBlockError::DuplicateIdentityPubkey(pubkey)
+        | InvalidBlockError::DuplicateIdentityUid(pubkey)
+        | InvalidBlockError::IdentityWithoutJoiner(pubkey)
+        | InvalidBlockError::DuplicateMembership(pubkey)
+        | InvalidBlockError::IdentityAlreadyExist(pubkey)
+        | InvalidBlockError::UidAlreadyUsed(pubkey)
+        | InvalidBlockError::IdentityOutOfWindow(pubkey)
+        | InvalidBlockError::JoinerWithoutIdentity(pubkey)
+        | InvalidBlockError::JoinerAlreadyMember(pubkey)
+        | InvalidBlockError::JoinerRevoked(pubkey)
+        | InvalidBlockError::MembershipOutOfWindow(pubkey)
+        | InvalidBlockError::MembershipNotChainable(pubkey)
+        | InvalidBlockError::NotEnoughCertifications(pubkey)
+        | InvalidBlockError::Outdistanced(pubkey)
+        | InvalidBlockError::ActiveOutdistanced(pubkey)
+        | InvalidBlockError::ActiveNotMember(pubkey)
+        | InvalidBlockError::LeaverNotMember(pubkey) => remove_memberships(block, pubkey),
+        InvalidBlockError::SelfCertification(pubkey) => {
+            remove_certs(block, |issuer, target| issuer == pubkey && target == pubkey)
+        }
+        InvalidBlockError::DuplicateCertification(issuer, target)
+        | InvalidBlockError::CertOutOfWindow(issuer, target)
+        | InvalidBlockError::CertReplay(issuer, target) => {
+            remove_certs(block, |i, t| i == issuer && t == target)
+        }
+        InvalidBlockError::CertIssuerNotMember(pubkey)
+        | InvalidBlockError::CertNotChainable(pubkey)
+        | InvalidBlockError::CertStockExhausted(pubkey) => {
+            remove_certs(block, |issuer, _| issuer == pubkey)
+        }
+        InvalidBlockError::CertTargetNotMember(pubkey) => {
+            remove_certs(block, |_, target| target == pubkey)
+        }
+        InvalidBlockError::RevokedUnknownIdentity(pubkey)
+        | InvalidBlockError::AlreadyRevoked(pubkey)
+        | InvalidBlockError::InvalidRevocationSignature(pubkey) => {
+            block
+                .revoked
+                .retain(|revocation| revocation.to_compact_document().issuer != pubkey);
+            if !required_exclusions.contains(&pubkey) {
+                block.excluded.retain(|excluded| *excluded != pubkey);
+            }
+        }
+        InvalidBlockError::ExcludedNotMember(pubkey) => {
+            block.excluded.retain(|excluded| *excluded != pubkey)
+        }
+        InvalidBlockError::InvalidTransaction(hash, _) => {
+            block.transactions.retain(|tx_doc| match *tx_doc {
+                TxDocOrTxHash::TxDoc(ref tx) => {
+                    tx.get_hash_opt()
+                        .unwrap_or_else(|| tx.deref().clone().compute_hash())
+                        != hash
+                }
+                TxDocOrTxHash::TxHash(tx_hash) => tx_hash != hash,
+            })
+        }
+        _ => {}
+    }
+    docs_count(block) < docs_count_before
+}
+
+/// Get the blocks needed to compute the header of the next block (from the oldest to the newest)
+fn get_previous_blocks(
+    dbs: &GeneratorDBs,
+    currency_params: &CurrencyParameters,
+) -> Result<Vec<BlockDocument>, GenerationError> {
+    let blocks_count = dbs.blocks_dbs.blockchain_db.len()? as u32;
+    let current_block = dbs
+        .blocks_dbs
+        .blockchain_db
+        .get(&BlockNumber(blocks_count.saturating_sub(1)))?
+        .map(|dal_block| dal_block.block)
+        .ok_or(GenerationError::EmptyBlockchain)?;
+    let count = std::cmp::max(
+        std::cmp::max(
+            currency_params.median_time_blocks,
+            currency_params.dt_diff_eval,
+        ),
+        std::cmp::max(current_block.issuers_frame, 0) as usize,
+    ) as u32;
+    let next_number = current_block.number.0 + 1;
+    Ok(readers::block::get_blocks_in_local_blockchain(
+        &dbs.blocks_dbs.blockchain_db,
+        BlockNumber(next_number - std::cmp::min(count, next_number)),
+        count,
+    )?)
+}
+
+/// Generate the next block of the local blockchain, issued by `issuer` at `now`
+pub fn generate_next_block(
+    dbs: &GeneratorDBs,
+    currency_params: &CurrencyParameters,
+    issuer: PubKey,
+    pending_docs: &PendingDocs,
+    max_txs: usize,
+    now: u64,
+) -> Result<BlockTemplate, GenerationError> {
+    let issuer_is_member = dbs
+        .wot_dbs
+        .identities_db
+        .get(&issuer)?
+        .map_or(false, |idty| is_member(&idty));
+    if !issuer_is_member {
+        return Err(GenerationError::IssuerNotMember);
+    }
+    let previous_blocks = get_previous_blocks(dbs, currency_params)?;
+    let previous_block = previous_blocks.last().expect("safe unwrap");
+
+    let (issuers_count, issuers_frame, issuers_frame_var) =
+        expected_issuers_frame(&previous_blocks);
+    let median_time =
+        expected_median_time(&previous_blocks, currency_params).unwrap_or(previous_block.time);
+    let mut block = BlockDocument {
+        version: 10,
+        nonce: 0,
+        number: BlockNumber(previous_block.number.0 + 1),
+        pow_min: previous_block.pow_min,
+        time: now
+            .max(median_time)
+            .min(median_time + max_time_acceleration(currency_params)),
+        median_time,
+        members_count: previous_block.members_count,
+        monetary_mass: previous_block.monetary_mass,
+        unit_base: previous_block.unit_base,
+        issuers_count,
+        issuers_frame,
+        issuers_frame_var,
+        currency: previous_block.currency.clone(),
+        issuers: vec![issuer],
+        signatures: vec![],
+        hash: None,
+        parameters: None,
+        previous_hash: previous_block.hash.map(|hash| hash.0).unwrap_or_default(),
+        previous_issuer: Some(previous_block.issuers[0]),
+        inner_hash: None,
+        dividend: None,
+        identities: vec![],
+        joiners: vec![],
+        actives: vec![],
+        leavers: vec![],
+        revoked: vec![],
+        excluded: vec![],
+        certifications: vec![],
+        transactions: pending_docs
+            .transactions
+            .iter()
+            .take(max_txs)
+            .map(|tx| TxDocOrTxHash::TxDoc(Box::new(tx.clone())))
+            .collect(),
+        inner_hash_and_nonce_str: String::new(),
+    };
+    if let Some(pow_min) = expected_pow_min(&block, &previous_blocks, currency_params) {
+        block.pow_min = pow_min;
+    }
+    let required_exclusions = fill_wot_docs(&mut block, dbs, currency_params, pending_docs)?;
+
+    // Universal dividend state after the current block
+    let ud_state = UdState::local_state(
+        &dbs.blocks_dbs.blockchain_db,
+        &dbs.blocks_dbs.ud_states_db,
+        BlockNumber(block.number.0 - 1),
+    )?
+    .ok_or(GenerationError::EmptyBlockchain)?;
+
+    // Remove the documents that break the rules until the block is valid
+    loop {
+        block.members_count = expected_members_count(&block, &previous_blocks);
+        let (next_ud_state, dividend) =
+            ud_state.next(block.median_time, block.members_count, currency_params);
+        block.dividend = dividend;
+        block.unit_base = next_ud_state.unit_base;
+        block.monetary_mass = next_ud_state.monetary_mass;
+        match verify_block_template_validity(
+            &block,
+            currency_params,
+            &dbs.blocks_dbs,
+            &dbs.wot_dbs,
+            &dbs.currency_dbs,
+        ) {
+            Ok(()) => break,
+            Err(BlockError::InvalidBlockRule(rule_number, err)) => {
+                debug!(
+                    "Generator: remove documents breaking rule n°{}: {:?}",
+                    rule_number.0, err
+                );
+                if !remove_invalid_docs(&mut block, &required_exclusions, err) {
+                    return Err(BlockError::InvalidBlockRule(rule_number, err).into());
+                }
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    block.compute_inner_hash();
+    let difficulty = issuer_difficulty(&issuer, block.pow_min, &previous_blocks, currency_params);
+    Ok(BlockTemplate { block, difficulty })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dubp_documents::documents::block::BlockV10Parameters;
+    use dubp_documents::documents::membership::MembershipDocumentBuilder;
+    use dup_crypto::hashs::Hash;
+    use dup_crypto::keys::{ed25519, Sig};
+    use durs_blockchain_dal::entities::block::DALBlock;
+    use durs_wot::{NodeId, WebOfTrust};
+
+    fn blockstamp(number: u32) -> Blockstamp {
+        Blockstamp {
+            id: BlockNumber(number),
+            hash: BlockHash(dup_crypto_tests_tools::mocks::hash_from_byte(number as u8)),
+        }
+    }
+
+    fn gen_membership(pubkey: PubKey, membership: MembershipType) -> MembershipDocument {
+        MembershipDocumentBuilder {
+            currency: "",
+            issuer: &pubkey,
+            blockstamp: &blockstamp(0),
+            membership,
+            identity_username: "",
+            identity_blockstamp: &blockstamp(0),
+        }
+        .build_with_signature(vec![Sig::Ed25519(ed25519::Signature([0u8; 64]))])
+    }
+
+    /// Blockchain with only a genesis block issued by the member 'A'
+    fn gen_dbs() -> GeneratorDBs {
+        let dbs = GeneratorDBs::open(None);
+        let issuer = dup_crypto_tests_tools::mocks::pubkey('A');
+        let mut genesis = dubp_documents_tests_tools::mocks::gen_empty_timed_block(
+            blockstamp(0),
+            1_000,
+            Hash::default(),
+        );
+        genesis.time = 1_000;
+        genesis.issuers = vec![issuer];
+        genesis.members_count = 1;
+        genesis.issuers_frame = 1;
+        genesis.parameters = Some(BlockV10Parameters::default());
+        dbs.blocks_dbs
+            .blockchain_db
+            .insert(
+                &BlockNumber(0),
+                &DALBlock {
+                    block: genesis,
+                    expire_certs: None,
+                },
+            )
+            .expect("Fail to write blockchain db");
+        let currency_params =
+            readers::currency_params::get_currency_params(&dbs.blocks_dbs.blockchain_db)
+                .expect("Fail to read currency params")
+                .expect("Currency params must exist");
+        dbs.blocks_dbs
+            .ud_states_db
+            .insert(
+                &BlockNumber(0),
+                &UdState::genesis(&currency_params).to_dal(),
+            )
+            .expect("Fail to write ud states db");
+        dbs.wot_dbs
+            .identities_db
+            .insert(
+                &issuer,
+                &DALIdentity {
+                    hash: String::new(),
+                    state: DALIdentityState::Member(vec![]),
+                    joined_on: blockstamp(0),
+                    expired_on: None,
+                    revoked_on: None,
+                    idty_doc: dubp_documents_tests_tools::mocks::identity::gen_mock_idty(
+                        issuer,
+                        BlockNumber(0),
+                    ),
+                    wot_id: NodeId(0),
+                    ms_created_block_id: BlockNumber(0),
+                    ms_chainable_on: vec![],
+                    cert_chainable_on: vec![],
+                },
+            )
+            .expect("Fail to write identities db");
+        dbs.wot_dbs
+            .wot_db
+            .write(|wot| {
+                wot.add_node();
+            })
+            .expect("Fail to write wot db");
+        dbs
+    }
+
+    #[test]
+    fn test_generate_next_block() {
+        let dbs = gen_dbs();
+        let currency_params =
+            readers::currency_params::get_currency_params(&dbs.blocks_dbs.blockchain_db)
+                .expect("Fail to read currency params")
+                .expect("Currency params must exist");
+        let issuer = dup_crypto_tests_tools::mocks::pubkey('A');
+
+        assert!(
+            if let Err(GenerationError::IssuerNotMember) = generate_next_block(
+                &dbs,
+                &currency_params,
+                dup_crypto_tests_tools::mocks::pubkey('B'),
+                &PendingDocs::default(),
+                10,
+                1_010,
+            ) {
+                true
+            } else {
+                false
+            }
+        );
+
+        // The leaver 'B' is not a member: his membership is not written
+        let pending_docs = PendingDocs {
+            memberships: vec![
+                gen_membership(issuer, MembershipType::In()),
+                gen_membership(
+                    dup_crypto_tests_tools::mocks::pubkey('B'),
+                    MembershipType::Out(),
+                ),
+            ],
+            ..PendingDocs::default()
+        };
+        let template =
+            generate_next_block(&dbs, &currency_params, issuer, &pending_docs, 10, 1_010)
+                .expect("Fail to generate next block");
+        let block = template.block;
+        assert_eq!(BlockNumber(1), block.number);
+        assert_eq!(Some(issuer), block.previous_issuer);
+        assert_eq!(1_000, block.median_time);
+        assert_eq!(1_010, block.time);
+        assert_eq!(1, block.members_count);
+        assert_eq!(
+            vec![gen_membership(issuer, MembershipType::In())],
+            block.actives
+        );
+        assert!(block.leavers.is_empty());
+        assert!(block.verify_inner_hash());
+        assert!(template.difficulty >= block.pow_min);
+    }
+
+    #[test]
+    fn test_remove_invalid_docs() {
+        let joiner = dup_crypto_tests_tools::mocks::pubkey('B');
+        let mut block = dubp_documents_tests_tools::mocks::gen_empty_timed_block(
+            blockstamp(1),
+            0,
+            Hash::default(),
+        );
+        block.identities = vec![dubp_documents_tests_tools::mocks::identity::gen_mock_idty(
+            joiner,
+            BlockNumber(0),
+        )];
+        block.joiners = vec![gen_membership(joiner, MembershipType::In())];
+        block.excluded = vec![dup_crypto_tests_tools::mocks::pubkey('C')];
+
+        assert!(!remove_invalid_docs(
+            &mut block,
+            &HashSet::new(),
+            InvalidBlockError::TimeOutOfBounds
+        ));
+        assert!(remove_invalid_docs(
+            &mut block,
+            &HashSet::new(),
+            InvalidBlockError::NotEnoughCertifications(joiner)
+        ));
+        assert!(block.identities.is_empty());
+        assert!(block.joiners.is_empty());
+        assert!(remove_invalid_docs(
+            &mut block,
+            &HashSet::new(),
+            InvalidBlockError::ExcludedNotMember(dup_crypto_tests_tools::mocks::pubkey('C'))
+        ));
+        assert!(block.excluded.is_empty());
+    }
+}
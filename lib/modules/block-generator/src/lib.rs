@@ -0,0 +1,365 @@
+//  Copyright (C) 2018  The Duniter Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Block generator module: generates the next block of a member node from the local blockchain
+//! and the pending documents, then sends it to the prover.
+
+#![deny(
+    missing_docs,
+    missing_debug_implementations,
+    missing_copy_implementations,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_import_braces,
+    unused_qualifications
+)]
+
+#[macro_use]
+extern crate log;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate structopt;
+
+mod constants;
+pub mod generator;
+
+use crate::constants::*;
+use crate::generator::*;
+use dubp_documents::*;
+use dup_crypto::keys::*;
+use durs_blockchain_dal::entities::currency_params::CurrencyParameters;
+use durs_blockchain_dal::readers;
+use durs_common_tools::fatal_error;
+use durs_common_tools::traits::merge::Merge;
+use durs_conf::DuRsConf;
+use durs_message::events::*;
+use durs_message::requests::{DursReqContent, MemPoolRequest, ProverRequest};
+use durs_message::responses::{DursResContent, MemPoolResponse, PendingDocs};
+use durs_message::*;
+use durs_module::*;
+use std::sync::mpsc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+/// Block generator Configuration
+pub struct GeneratorConf {
+    /// Maximum number of transactions in a generated block
+    pub max_txs_per_block: usize,
+}
+
+impl Default for GeneratorConf {
+    fn default() -> Self {
+        GeneratorConf {
+            max_txs_per_block: *DEFAULT_MAX_TXS_PER_BLOCK,
+        }
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// Block generator user Configuration
+pub struct GeneratorUserConf {
+    /// Maximum number of transactions in a generated block
+    pub max_txs_per_block: Option<usize>,
+}
+
+impl Merge for GeneratorUserConf {
+    fn merge(self, other: Self) -> Self {
+        GeneratorUserConf {
+            max_txs_per_block: self.max_txs_per_block.or(other.max_txs_per_block),
+        }
+    }
+}
+
+#[derive(StructOpt, Debug, Copy, Clone)]
+#[structopt(
+    name = "block-generator",
+    raw(setting = "structopt::clap::AppSettings::ColoredHelp")
+)]
+/// Block generator subcommand options
+pub struct GeneratorOpt {}
+
+#[derive(Debug, Copy, Clone)]
+/// Block generator module
+pub struct GeneratorModule {}
+
+impl Default for GeneratorModule {
+    fn default() -> GeneratorModule {
+        GeneratorModule {}
+    }
+}
+
+/// Current time (in seconds since UNIX EPOCH)
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("SystemTime before UNIX EPOCH!")
+        .as_secs()
+}
+
+#[derive(Debug)]
+/// Data that the block generator module needs to cache
+pub struct GeneratorModuleDatas {
+    /// Router sender
+    pub router_sender: mpsc::Sender<RouterThreadMessage<DursMsg>>,
+    /// Member public key (`None` if the node is not a member node)
+    pub issuer: Option<PubKey>,
+    /// Configuration
+    pub conf: GeneratorConf,
+    /// Databases (written by the blockchain module)
+    pub dbs: GeneratorDBs,
+    /// Current blockstamp announced by the blockchain module, not yet reached by the databases
+    pub expected_blockstamp: Option<Blockstamp>,
+    /// The next block must be generated
+    pub need_generation: bool,
+    /// Time (in seconds since UNIX EPOCH) from which a failed generation is retried
+    pub retry_on: Option<u64>,
+    /// Id of the last request sent to the mempool
+    pub pending_req_id: Option<ModuleReqId>,
+    /// Number of requests sent
+    pub requests_count: u32,
+}
+
+impl GeneratorModuleDatas {
+    /// Reload databases until they reach the current block announced by the blockchain module,
+    /// then ask the pending documents to the mempool
+    pub fn refresh_state(&mut self) {
+        if let Some(expected_blockstamp) = self.expected_blockstamp {
+            // The blockchain module may not have saved its databases yet
+            if self.dbs.load().is_err() {
+                return;
+            }
+            match self.dbs.current_blockstamp() {
+                Ok(current_blockstamp) => {
+                    if current_blockstamp == Some(expected_blockstamp) {
+                        self.expected_blockstamp = None;
+                    }
+                }
+                Err(e) => warn!("Generator: fail to read databases: {:?}", e),
+            }
+        }
+        if let Some(retry_on) = self.retry_on {
+            if now() >= retry_on {
+                self.retry_on = None;
+                self.need_generation = true;
+            }
+        }
+        if self.need_generation && self.expected_blockstamp.is_none() && self.issuer.is_some() {
+            self.need_generation = false;
+            self.ask_pending_docs();
+        }
+    }
+    /// Ask all pending documents to the mempool
+    fn ask_pending_docs(&mut self) {
+        let req_id = ModuleReqId(self.requests_count);
+        self.requests_count += 1;
+        self.pending_req_id = Some(req_id);
+        self.router_sender
+            .send(RouterThreadMessage::ModuleMessage(DursMsg::Request {
+                req_from: ModuleStaticName(MODULE_NAME),
+                req_to: ModuleRole::WotPool,
+                req_id,
+                req_content: DursReqContent::MemPoolRequest(MemPoolRequest::AllPendingDocs),
+            }))
+            .unwrap_or_else(|_| fatal_error!("Generator: fail to send request to router"));
+    }
+    /// Generate the next block again after a delay
+    fn schedule_retry(&mut self) {
+        self.retry_on = Some(now() + *GENERATION_RETRY_DELAY_IN_SECS);
+    }
+    /// Generate the next block at the reception of pending documents and send it to the prover
+    pub fn receive_pending_docs(&mut self, req_id: ModuleReqId, pending_docs: &PendingDocs) {
+        if self.pending_req_id != Some(req_id) {
+            return;
+        }
+        self.pending_req_id = None;
+        let issuer = if let Some(issuer) = self.issuer {
+            issuer
+        } else {
+            return;
+        };
+        let currency_params: CurrencyParameters =
+            match readers::currency_params::get_currency_params(&self.dbs.blocks_dbs.blockchain_db)
+            {
+                Ok(Some(currency_params)) => currency_params,
+                Ok(None) => return,
+                Err(e) => {
+                    warn!("Generator: fail to read databases: {:?}", e);
+                    self.schedule_retry();
+                    return;
+                }
+            };
+        match generate_next_block(
+            &self.dbs,
+            &currency_params,
+            issuer,
+            pending_docs,
+            self.conf.max_txs_per_block,
+            now(),
+        ) {
+            Ok(BlockTemplate { block, difficulty }) => {
+                info!(
+                    "Generator: send block #{} to the prover (difficulty {})",
+                    block.number.0, difficulty
+                );
+                let req_id = ModuleReqId(self.requests_count);
+                self.requests_count += 1;
+                self.router_sender
+                    .send(RouterThreadMessage::ModuleMessage(DursMsg::Request {
+                        req_from: ModuleStaticName(MODULE_NAME),
+                        req_to: ModuleRole::Prover,
+                        req_id,
+                        req_content: DursReqContent::ProverRequest(ProverRequest {
+                            block: Box::new(block),
+                            difficulty,
+                        }),
+                    }))
+                    .unwrap_or_else(|_| fatal_error!("Generator: fail to send request to router"));
+            }
+            Err(GenerationError::IssuerNotMember) => {
+                debug!("Generator: the local node is not a member, no block to generate.")
+            }
+            Err(e) => {
+                warn!("Generator: fail to generate next block: {:?}", e);
+                self.schedule_retry();
+            }
+        }
+    }
+    /// Generate a new block after each change of the local blockchain
+    pub fn receive_event(&mut self, event: &DursEvent) {
+        if let DursEvent::BlockchainEvent(ref blockchain_event) = *event {
+            match **blockchain_event {
+                BlockchainEvent::StackUpValidBlock(ref block) => {
+                    self.expected_blockstamp = Some(block.blockstamp());
+                    self.need_generation = true;
+                }
+                BlockchainEvent::RevertBlocks(ref reorg) => {
+                    self.expected_blockstamp = Some(reorg.fork_point);
+                    self.need_generation = true;
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl DursModule<DuRsConf, DursMsg> for GeneratorModule {
+    type ModuleUserConf = GeneratorUserConf;
+    type ModuleConf = GeneratorConf;
+    type ModuleOpt = GeneratorOpt;
+
+    fn name() -> ModuleStaticName {
+        ModuleStaticName(MODULE_NAME)
+    }
+    fn priority() -> ModulePriority {
+        ModulePriority::Recommended()
+    }
+    fn ask_required_keys() -> RequiredKeys {
+        RequiredKeys::MemberPublicKey()
+    }
+    fn generate_module_conf(
+        _global_conf: &<DuRsConf as DursConfTrait>::GlobalConf,
+        module_user_conf: Option<Self::ModuleUserConf>,
+    ) -> Result<(Self::ModuleConf, Option<Self::ModuleUserConf>), ModuleConfError> {
+        let mut conf = GeneratorConf::default();
+
+        if let Some(module_user_conf) = module_user_conf {
+            if let Some(max_txs_per_block) = module_user_conf.max_txs_per_block {
+                conf.max_txs_per_block = max_txs_per_block;
+            }
+        }
+
+        Ok((conf, module_user_conf))
+    }
+    fn start(
+        soft_meta_datas: &SoftwareMetaDatas<DuRsConf>,
+        keys: RequiredKeysContent,
+        conf: GeneratorConf,
+        router_sender: mpsc::Sender<RouterThreadMessage<DursMsg>>,
+    ) -> Result<(), failure::Error> {
+        // Create module channel
+        let (module_sender, module_receiver) = mpsc::channel();
+
+        // Registration with the rooter
+        if router_sender
+            .send(RouterThreadMessage::ModuleRegistration {
+                static_name: ModuleStaticName(MODULE_NAME),
+                sender: module_sender,
+                roles: vec![ModuleRole::BlockGeneration],
+                events_subscription: vec![ModuleEvent::NewValidBlock, ModuleEvent::RevertBlocks],
+                reserved_apis_parts: vec![],
+                endpoints: vec![],
+            })
+            .is_err()
+        {
+            fatal_error!("Block generator module fail to send registration to router !")
+        }
+
+        let issuer = if let RequiredKeysContent::MemberPublicKey(issuer) = keys {
+            issuer
+        } else {
+            None
+        };
+
+        // Open databases
+        let currency = soft_meta_datas.conf.currency();
+        let dbs_path =
+            durs_conf::get_blockchain_db_path(soft_meta_datas.profile_path.clone(), &currency);
+
+        let mut datas = GeneratorModuleDatas {
+            router_sender,
+            issuer,
+            conf,
+            dbs: GeneratorDBs::open(Some(&dbs_path)),
+            expected_blockstamp: None,
+            need_generation: true,
+            retry_on: None,
+            pending_req_id: None,
+            requests_count: 0,
+        };
+
+        loop {
+            match module_receiver.recv_timeout(Duration::from_millis(*GENERATOR_LOOP_FREQ_IN_MS)) {
+                Ok(durs_message) => match durs_message {
+                    DursMsg::Stop => break,
+                    DursMsg::Event {
+                        ref event_content, ..
+                    } => datas.receive_event(event_content),
+                    DursMsg::Response {
+                        req_id,
+                        res_content:
+                            DursResContent::MemPoolResponse(MemPoolResponse::AllPendingDocs(
+                                _,
+                                ref pending_docs,
+                            )),
+                        ..
+                    } => datas.receive_pending_docs(req_id, pending_docs),
+                    _ => {} // Others DursMsg variants
+                },
+                Err(e) => match e {
+                    mpsc::RecvTimeoutError::Disconnected => {
+                        fatal_error!("Disconnected block generator module !");
+                    }
+                    mpsc::RecvTimeoutError::Timeout => {}
+                },
+            }
+            datas.refresh_state();
+        }
+
+        Ok(())
+    }
+}
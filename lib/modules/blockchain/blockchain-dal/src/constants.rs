@@ -19,8 +19,23 @@ pub static DEFAULT_SIG_RENEW_PERIOD: &'static u64 = &5_259_600;
 pub static DEFAULT_MS_PERIOD: &'static u64 = &5_259_600;
 /// Default value for tx_window parameter
 pub static DEFAULT_TX_WINDOW: &'static u64 = &604_800;
-/// Maximum roolback length
+/// Maximum roolback length (default value of the fork window size)
 pub static FORK_WINDOW_SIZE: &'static usize = &200;
+/// Default maximum number of orphan blocks
+pub static DEFAULT_MAX_ORPHANS_COUNT: &'static usize = &1_000;
+/// Default maximum age of an orphan block (in blocks behind the current block)
+pub static DEFAULT_MAX_ORPHAN_AGE: &'static u32 = &200;
 
 /// Default page size for requests responses
 pub static DEFAULT_PAGE_SIZE: &'static usize = &50;
+
+/// File of the blocks databases
+pub static BLOCKS_DBS_FILE_NAME: &'static str = "blocks.kv";
+/// File of the forks databases
+pub static FORKS_DBS_FILE_NAME: &'static str = "forks.kv";
+/// File of the wot databases
+pub static WOTS_DBS_FILE_NAME: &'static str = "wot.kv";
+/// File of the currency databases
+pub static CURRENCY_DBS_FILE_NAME: &'static str = "currency.kv";
+/// Collection of the meta datas of a databases file (migration marker, last commit blockstamp)
+pub static META_COLLECTION: &'static str = "meta";
@@ -0,0 +1,33 @@
+//  Copyright (C) 2018  The Durs Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use dubp_documents::{BlockNumber, Blockstamp};
+use dup_crypto::keys::PubKey;
+use durs_wot::NodeId;
+
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq, Eq, Hash)]
+/// Certification in database
+pub struct DALCertification {
+    /// Public key of the issuer
+    pub issuer: PubKey,
+    /// Wot id of the issuer
+    pub source: NodeId,
+    /// Wot id of the target
+    pub target: NodeId,
+    /// Number of the block referenced by the certification (its expiry depends on this block)
+    pub created_block_id: BlockNumber,
+    /// Block in which the certification is written
+    pub written_on: Blockstamp,
+}
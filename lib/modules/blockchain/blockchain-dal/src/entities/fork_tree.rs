@@ -156,6 +156,10 @@ impl ForkTree {
     pub fn get_removed_blockstamps(&self) -> Vec<Blockstamp> {
         self.removed_blockstamps.clone()
     }
+    /// Get the number of the last block of the main branch
+    pub fn get_current_block_id(&self) -> Option<BlockNumber> {
+        self.main_branch.keys().max().cloned()
+    }
     /// Get specific tree node
     #[inline]
     fn get_node(&self, id: TreeNodeId) -> TreeNode {
@@ -330,12 +334,13 @@ impl ForkTree {
         None
     }
     /// Insert new node with specified identifier,
-    /// return blockstamps of deleted blocks
+    /// the main branch is pruned to keep at most `fork_window_size` blocks
     pub fn insert_new_node(
         &mut self,
         data: Blockstamp,
         parent: Option<TreeNodeId>,
         main_branch: bool,
+        fork_window_size: usize,
     ) {
         let new_node = TreeNode::new(parent, data);
         let mut new_node_id = self.get_free_node_id();
@@ -364,7 +369,7 @@ impl ForkTree {
         self.removed_blockstamps.clear();
         if main_branch {
             self.main_branch.insert(data.id, new_node_id);
-            if self.main_branch.len() > *crate::constants::FORK_WINDOW_SIZE {
+            while self.main_branch.len() > fork_window_size {
                 self.pruning();
             }
         }
@@ -448,7 +453,12 @@ mod tests {
             id: BlockNumber(0),
             hash: BlockHash(dup_crypto_tests_tools::mocks::hash('A')),
         };
-        tree.insert_new_node(root_blockstamp, None, true);
+        tree.insert_new_node(
+            root_blockstamp,
+            None,
+            true,
+            *crate::constants::FORK_WINDOW_SIZE,
+        );
         assert_eq!(1, tree.size());
         assert_eq!(
             TreeNodeId(0),
@@ -488,9 +498,24 @@ mod tests {
             },
         ];
 
-        tree.insert_new_node(blockstamps[0], None, true);
-        tree.insert_new_node(blockstamps[1], Some(TreeNodeId(0)), true);
-        tree.insert_new_node(blockstamps[2], Some(TreeNodeId(1)), true);
+        tree.insert_new_node(
+            blockstamps[0],
+            None,
+            true,
+            *crate::constants::FORK_WINDOW_SIZE,
+        );
+        tree.insert_new_node(
+            blockstamps[1],
+            Some(TreeNodeId(0)),
+            true,
+            *crate::constants::FORK_WINDOW_SIZE,
+        );
+        tree.insert_new_node(
+            blockstamps[2],
+            Some(TreeNodeId(1)),
+            true,
+            *crate::constants::FORK_WINDOW_SIZE,
+        );
         assert_eq!(3, tree.size());
         assert_eq!(
             TreeNodeId(0),
@@ -520,9 +545,19 @@ mod tests {
         let mut tree = ForkTree::default();
         let blockstamps: Vec<Blockstamp> =
             dubp_documents_tests_tools::mocks::generate_blockstamps(10);
-        tree.insert_new_node(blockstamps[0], None, true);
+        tree.insert_new_node(
+            blockstamps[0],
+            None,
+            true,
+            *crate::constants::FORK_WINDOW_SIZE,
+        );
         for i in 1..10 {
-            tree.insert_new_node(blockstamps[i], Some(TreeNodeId(i - 1)), true);
+            tree.insert_new_node(
+                blockstamps[i],
+                Some(TreeNodeId(i - 1)),
+                true,
+                *crate::constants::FORK_WINDOW_SIZE,
+            );
         }
         assert_eq!(10, tree.size());
 
@@ -535,6 +570,7 @@ mod tests {
             fork_blockstamp,
             tree.get_main_branch_node_id(BlockNumber(5)),
             false,
+            *crate::constants::FORK_WINDOW_SIZE,
         );
 
         // Check that the tree is indeed 2 sheets
@@ -569,7 +605,12 @@ mod tests {
             id: BlockNumber(7),
             hash: BlockHash(dup_crypto_tests_tools::mocks::hash('C')),
         };
-        tree.insert_new_node(child_fork_blockstamp, Some(TreeNodeId(10)), false);
+        tree.insert_new_node(
+            child_fork_blockstamp,
+            Some(TreeNodeId(10)),
+            false,
+            *crate::constants::FORK_WINDOW_SIZE,
+        );
 
         // Check that the tree still has 2 leaves
         let sheets = tree.get_sheets();
@@ -610,9 +651,19 @@ mod tests {
         );
 
         // Fill tree with FORK_WINDOW_SIZE nodes
-        tree.insert_new_node(blockstamps[0], None, true);
+        tree.insert_new_node(
+            blockstamps[0],
+            None,
+            true,
+            *crate::constants::FORK_WINDOW_SIZE,
+        );
         for i in 1..*crate::constants::FORK_WINDOW_SIZE {
-            tree.insert_new_node(blockstamps[i], Some(TreeNodeId(i - 1)), true);
+            tree.insert_new_node(
+                blockstamps[i],
+                Some(TreeNodeId(i - 1)),
+                true,
+                *crate::constants::FORK_WINDOW_SIZE,
+            );
         }
 
         // The tree-root must not have been shifted yet
@@ -625,6 +676,7 @@ mod tests {
             blockstamps[*crate::constants::FORK_WINDOW_SIZE],
             Some(TreeNodeId(*crate::constants::FORK_WINDOW_SIZE - 1)),
             true,
+            *crate::constants::FORK_WINDOW_SIZE,
         );
         assert_eq!(*crate::constants::FORK_WINDOW_SIZE, tree.size());
         assert_eq!(Some(TreeNodeId(1)), tree.get_root_id());
@@ -635,6 +687,7 @@ mod tests {
             blockstamps[*crate::constants::FORK_WINDOW_SIZE + 1],
             Some(TreeNodeId(*crate::constants::FORK_WINDOW_SIZE)),
             true,
+            *crate::constants::FORK_WINDOW_SIZE,
         );
         assert_eq!(*crate::constants::FORK_WINDOW_SIZE, tree.size());
         assert_eq!(Some(TreeNodeId(2)), tree.get_root_id());
@@ -648,9 +701,19 @@ mod tests {
         );
 
         // Fill tree with FORK_WINDOW_SIZE nodes
-        tree.insert_new_node(blockstamps[0], None, true);
+        tree.insert_new_node(
+            blockstamps[0],
+            None,
+            true,
+            *crate::constants::FORK_WINDOW_SIZE,
+        );
         for i in 1..*crate::constants::FORK_WINDOW_SIZE {
-            tree.insert_new_node(blockstamps[i], Some(TreeNodeId(i - 1)), true);
+            tree.insert_new_node(
+                blockstamps[i],
+                Some(TreeNodeId(i - 1)),
+                true,
+                *crate::constants::FORK_WINDOW_SIZE,
+            );
         }
 
         // Insert 2 forks blocks after block (FORK_WINDOW_SIZE - 2)
@@ -664,6 +727,7 @@ mod tests {
                 *crate::constants::FORK_WINDOW_SIZE as u32 - 2,
             )),
             false,
+            *crate::constants::FORK_WINDOW_SIZE,
         );
         let fork_blockstamp_2 = Blockstamp {
             id: BlockNumber(*crate::constants::FORK_WINDOW_SIZE as u32),
@@ -673,6 +737,7 @@ mod tests {
             fork_blockstamp_2,
             Some(TreeNodeId(*crate::constants::FORK_WINDOW_SIZE)),
             false,
+            *crate::constants::FORK_WINDOW_SIZE,
         );
 
         // Check tree size
@@ -720,5 +785,4 @@ mod tests {
         // Check that the root of the tree has shifted
         assert_eq!(Some(TreeNodeId(1)), tree.get_root_id());
     }
-
 }
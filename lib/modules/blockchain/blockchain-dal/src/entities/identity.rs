@@ -13,9 +13,14 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::storage::collection::prefix_end;
+use crate::storage::{KvMapDB, KvStore, StagedKvDB};
+use crate::DALError;
 use dubp_documents::documents::identity::IdentityDocument;
-use dubp_documents::{BlockNumber, Blockstamp};
+use dubp_documents::{BlockNumber, Blockstamp, Document};
+use dup_crypto::keys::PubKey;
 use durs_wot::NodeId;
+use std::sync::Arc;
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, Hash)]
 /// Identity state
@@ -32,6 +37,30 @@ pub enum DALIdentityState {
     ImplicitRevoked(Vec<usize>),
 }
 
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq, Eq, Hash)]
+/// Identity state, regardless of renewals and of the revocation kind
+pub enum DALIdentityStateKind {
+    /// Member
+    Member,
+    /// Expire member (excluded)
+    Expired,
+    /// Revoked (explicitly or implicitly)
+    Revoked,
+}
+
+impl DALIdentityState {
+    /// Get the kind of this state
+    pub fn kind(&self) -> DALIdentityStateKind {
+        match *self {
+            DALIdentityState::Member(_) => DALIdentityStateKind::Member,
+            DALIdentityState::ExpireMember(_) => DALIdentityStateKind::Expired,
+            DALIdentityState::ExplicitRevoked(_)
+            | DALIdentityState::ExplicitExpireRevoked(_)
+            | DALIdentityState::ImplicitRevoked(_) => DALIdentityStateKind::Revoked,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, Hash)]
 /// Identity in database
 pub struct DALIdentity {
@@ -49,10 +78,140 @@ pub struct DALIdentity {
     pub idty_doc: IdentityDocument,
     /// Identity wot id
     pub wot_id: NodeId,
-    /// Membership created block number
+    /// Created block number of the current membership
     pub ms_created_block_id: BlockNumber,
     /// Timestamp from which membership can be renewed
     pub ms_chainable_on: Vec<u64>,
     /// Timestamp from which the identity can write a new certification
     pub cert_chainable_on: Vec<u64>,
 }
+
+#[derive(Debug)]
+/// Secondary indexes of identities
+pub struct IdentitiesIndexesDB {
+    db: Arc<StagedKvDB>,
+    /// Identities in each state
+    by_state: KvMapDB<(DALIdentityStateKind, PubKey), ()>,
+    /// Identities indexed by the created block number of their current membership
+    by_ms_created_block: KvMapDB<(BlockNumber, PubKey), ()>,
+    /// Public key of each wot id
+    by_wot_id: KvMapDB<NodeId, PubKey>,
+    /// Revoked identities indexed by their revocation block number
+    by_revoked_block: KvMapDB<(BlockNumber, PubKey), ()>,
+}
+
+/// Collection of the public key of each uid (keyed by the uid bytes, to be sorted by uid)
+static BY_UID: &'static str = "by_uid";
+
+impl IdentitiesIndexesDB {
+    /// Indexes stored in `db`
+    pub fn new(db: Arc<StagedKvDB>) -> IdentitiesIndexesDB {
+        IdentitiesIndexesDB {
+            by_state: KvMapDB::new(db.clone(), "by_state"),
+            by_ms_created_block: KvMapDB::new(db.clone(), "by_ms_created_block"),
+            by_wot_id: KvMapDB::new(db.clone(), "by_wot_id"),
+            by_revoked_block: KvMapDB::new(db.clone(), "by_revoked_block"),
+            db,
+        }
+    }
+    /// Database storing the indexes
+    pub fn db(&self) -> &StagedKvDB {
+        &self.db
+    }
+    /// Return true if no identity is indexed (in one of the indexes always filled)
+    pub fn is_empty(&self) -> Result<bool, DALError> {
+        Ok(self.db.count(BY_UID)? == 0 || self.by_wot_id.is_empty()?)
+    }
+    /// Index an identity
+    pub fn insert(&self, idty: &DALIdentity) -> Result<(), DALError> {
+        let pubkey = idty.idty_doc.issuers()[0];
+        self.db.put(
+            BY_UID,
+            idty.idty_doc.username().as_bytes(),
+            &bincode::serialize(&pubkey).map_err(|_| DALError::WriteError)?,
+        )?;
+        self.by_state.insert(&(idty.state.kind(), pubkey), &())?;
+        self.by_wot_id.insert(&idty.wot_id, &pubkey)?;
+        if let Some(revoked_on) = idty.revoked_on {
+            self.by_revoked_block
+                .insert(&(revoked_on.id, pubkey), &())?;
+        }
+        self.by_ms_created_block
+            .insert(&(idty.ms_created_block_id, pubkey), &())
+    }
+    /// Remove an identity from indexes
+    pub fn remove(&self, idty: &DALIdentity) -> Result<(), DALError> {
+        let pubkey = idty.idty_doc.issuers()[0];
+        self.db
+            .delete(BY_UID, idty.idty_doc.username().as_bytes())?;
+        self.by_state.delete(&(idty.state.kind(), pubkey))?;
+        self.by_wot_id.delete(&idty.wot_id)?;
+        if let Some(revoked_on) = idty.revoked_on {
+            self.by_revoked_block.delete(&(revoked_on.id, pubkey))?;
+        }
+        self.by_ms_created_block
+            .delete(&(idty.ms_created_block_id, pubkey))
+    }
+    /// Get the public key of the identity `uid`
+    pub fn get_pubkey(&self, uid: &str) -> Result<Option<PubKey>, DALError> {
+        if let Some(bytes) = self.db.get(BY_UID, uid.as_bytes())? {
+            Ok(Some(
+                bincode::deserialize(&bytes).map_err(|_| DALError::ReadError)?,
+            ))
+        } else {
+            Ok(None)
+        }
+    }
+    /// Get the public keys of the identities whose uid starts with `prefix`
+    pub fn get_pubkeys_by_uid_prefix(&self, prefix: &str) -> Result<Vec<PubKey>, DALError> {
+        let to = prefix_end(prefix.as_bytes());
+        self.db
+            .range(
+                BY_UID,
+                Some(prefix.as_bytes()),
+                to.as_ref().map(Vec::as_slice),
+            )?
+            .into_iter()
+            .map(|(_, bytes)| bincode::deserialize(&bytes).map_err(|_| DALError::ReadError))
+            .collect()
+    }
+    /// Get the public key of the identity `wot_id`
+    pub fn get_pubkey_by_wot_id(&self, wot_id: NodeId) -> Result<Option<PubKey>, DALError> {
+        self.by_wot_id.get(&wot_id)
+    }
+    /// Get the public keys of the identities revoked in the block `block_id`
+    pub fn get_pubkeys_revoked_on(&self, block_id: BlockNumber) -> Result<Vec<PubKey>, DALError> {
+        Ok(self
+            .by_revoked_block
+            .range_prefix(&block_id)?
+            .into_iter()
+            .map(|((_, pubkey), ())| pubkey)
+            .collect())
+    }
+    /// Get the public keys of the identities in the state `state`
+    pub fn get_pubkeys_by_state(
+        &self,
+        state: DALIdentityStateKind,
+    ) -> Result<Vec<PubKey>, DALError> {
+        Ok(self
+            .by_state
+            .range_prefix(&state)?
+            .into_iter()
+            .map(|((_, pubkey), ())| pubkey)
+            .collect())
+    }
+    /// Get the public keys of the identities whose current membership has been created
+    /// in a block before `last_block_id` (included)
+    pub fn get_pubkeys_by_ms_created_until(
+        &self,
+        last_block_id: BlockNumber,
+    ) -> Result<Vec<PubKey>, DALError> {
+        let to = last_block_id.0.checked_add(1).map(BlockNumber);
+        Ok(self
+            .by_ms_created_block
+            .range_by_prefix(None, to.as_ref())?
+            .into_iter()
+            .map(|((_, pubkey), ())| pubkey)
+            .collect())
+    }
+}
@@ -16,6 +16,9 @@
 /// Block
 pub mod block;
 
+/// Certifications
+pub mod certification;
+
 /// Currency paramenters
 pub mod currency_params;
 
@@ -77,6 +77,32 @@ impl Sub for SourceAmount {
     }
 }
 
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+/// Universal dividend created by a block
+pub struct DALDividend {
+    /// Block creating the dividend
+    pub block_number: BlockNumber,
+    /// Median time of the block
+    pub median_time: u64,
+    /// Amount of the dividend
+    pub amount: SourceAmount,
+    /// Number of members receiving the dividend
+    pub members_count: usize,
+}
+
+#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+/// Universal dividend state after a block (the part that the block document does not carry)
+pub struct DALUdState {
+    /// Current amount of the dividend (the block carries it only when it creates a dividend)
+    pub dividend: usize,
+    /// Monetary mass at the last re-evaluation of the dividend
+    pub mass_reeval: usize,
+    /// Median time from which the next dividend is created
+    pub ud_time: u64,
+    /// Median time from which the dividend is re-evaluated
+    pub ud_reeval_time: u64,
+}
+
 #[derive(Copy, Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 /// UTXOIndexV10
 pub struct UTXOIndexV10(pub Hash, pub TxIndex);
@@ -133,3 +159,14 @@ pub enum SourceIndexV10 {
     /// universal Dividend
     UD(PubKey, BlockNumber),
 }
+
+impl From<TransactionInput> for SourceIndexV10 {
+    fn from(input: TransactionInput) -> Self {
+        match input {
+            TransactionInput::D(_, _, pubkey, block_id) => SourceIndexV10::UD(pubkey, block_id),
+            TransactionInput::T(_, _, hash, tx_index) => {
+                SourceIndexV10::UTXO(UTXOIndexV10(hash, tx_index))
+            }
+        }
+    }
+}
@@ -16,15 +16,33 @@
 //! Define all filters applicable to identities
 
 use super::PagingFilter;
+use crate::entities::identity::DALIdentityStateKind;
 use dup_crypto::keys::*;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Filter identities by uid
+pub enum UidFilter {
+    /// Uid equal to this string
+    Exact(String),
+    /// Uid beginning with this string
+    Prefix(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 /// Identities filter
 pub struct IdentitiesFilter {
     /// Pagination parameters
     pub paging: PagingFilter,
     /// Filter identities by public key
     pub by_pubkey: Option<PubKey>,
+    /// Filter identities by uid
+    pub by_uid: Option<UidFilter>,
+    /// Filter identities by state
+    pub by_state: Option<DALIdentityStateKind>,
+    /// Retrieve only the identities whose current membership expires before this median time
+    pub ms_expires_before: Option<u64>,
+    /// Retrieve only the identities that have received fewer than this number of certifications
+    pub certs_count_below: Option<usize>,
 }
 
 impl Default for IdentitiesFilter {
@@ -32,6 +50,10 @@ impl Default for IdentitiesFilter {
         IdentitiesFilter {
             paging: PagingFilter::default(),
             by_pubkey: None,
+            by_uid: None,
+            by_state: None,
+            ms_expires_before: None,
+            certs_count_below: None,
         }
     }
 }
@@ -40,8 +62,22 @@ impl IdentitiesFilter {
     /// Create "by pubkey" filter
     pub fn by_pubkey(pubkey: PubKey) -> Self {
         IdentitiesFilter {
-            paging: PagingFilter::default(),
             by_pubkey: Some(pubkey),
+            ..IdentitiesFilter::default()
+        }
+    }
+    /// Create "by uid" filter
+    pub fn by_uid(uid_filter: UidFilter) -> Self {
+        IdentitiesFilter {
+            by_uid: Some(uid_filter),
+            ..IdentitiesFilter::default()
+        }
+    }
+    /// Create "by state" filter
+    pub fn by_state(state: DALIdentityStateKind) -> Self {
+        IdentitiesFilter {
+            by_state: Some(state),
+            ..IdentitiesFilter::default()
         }
     }
 }
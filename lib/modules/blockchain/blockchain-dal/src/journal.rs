@@ -0,0 +1,397 @@
+//  Copyright (C) 2018  The Duniter Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Write-ahead journal making the save of several databases atomic.
+//!
+//! The write batches staged in each key-value database (and the content of the other files to
+//! save) are first written in a journal file. Once the journal is complete, the batches are
+//! appended to the databases files and the journal is removed. If the node stops in between,
+//! the journal is replayed on the next start (roll forward). If it stops while the journal is
+//! written, the incomplete journal is discarded and the databases files are those of the
+//! previous commit (roll back).
+//!
+//! Whole files (the journal and the other files) are written in a temporary file, synced, then
+//! renamed, so they are never partially written. Replaying a batch is harmless: a batch appended
+//! twice leaves the same entries.
+//!
+//! Each commit also records in each database the blockstamp of its current block, which allows
+//! to check on startup that all the databases agree on the same current block.
+
+use crate::constants::META_COLLECTION;
+use crate::storage::{FileKvStore, KvStore, StagedKvDB, WriteBatch};
+use crate::DALError;
+use dubp_documents::Blockstamp;
+use std::fs::{self, File};
+use std::hash::Hasher;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Name of the journal file
+static JOURNAL_FILE_NAME: &'static str = "journal.db";
+/// Extension of a file while it is written
+static TMP_FILE_EXTENSION: &'static str = "tmp";
+/// Key of the blockstamp of the last commit (in the meta collection of each database)
+static BLOCKSTAMP_KEY: &'static [u8] = b"blockstamp";
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+/// Content of a database file
+pub struct DBFileDatas {
+    /// File name (in the databases folder)
+    pub file_name: String,
+    /// Serialized database
+    pub datas: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+/// Modifications of a key-value database
+struct DBBatch {
+    /// File name of the database (in the databases folder)
+    file_name: String,
+    /// Modifications staged in the database
+    batch: WriteBatch,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+/// Content of the journal
+struct JournalDatas {
+    blockstamp: Blockstamp,
+    batches: Vec<DBBatch>,
+    files: Vec<DBFileDatas>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// Result of the recovery of an interrupted commit
+pub enum JournalRecovery {
+    /// The last commit was complete
+    Clean,
+    /// An interrupted commit has been completed
+    RolledForward(Blockstamp),
+    /// An incomplete commit has been discarded
+    RolledBack,
+}
+
+fn checksum(datas: &[u8]) -> u64 {
+    let mut hasher = fnv::FnvHasher::default();
+    hasher.write(datas);
+    hasher.finish()
+}
+
+fn file_path(dbs_folder_path: &Path, file_name: &str) -> PathBuf {
+    let mut path = dbs_folder_path.to_path_buf();
+    path.push(file_name);
+    path
+}
+
+fn tmp_file_path(path: &Path) -> PathBuf {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".");
+    tmp_path.push(TMP_FILE_EXTENSION);
+    PathBuf::from(tmp_path)
+}
+
+/// Replace the content of a file: the datas are written in a temporary file which is synced
+/// then renamed (the renaming is durable once the folder is synced)
+fn write_file_sync(path: &Path, datas: &[u8]) -> Result<(), DALError> {
+    let tmp_path = tmp_file_path(path);
+    {
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(datas)?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+/// Make the files renaming of a folder durable
+fn sync_folder(dbs_folder_path: &Path) -> Result<(), DALError> {
+    if cfg!(unix) {
+        File::open(dbs_folder_path)?.sync_all()?;
+    }
+    Ok(())
+}
+
+/// Write the journal (commit point)
+fn write_journal(dbs_folder_path: &Path, journal: &JournalDatas) -> Result<(), DALError> {
+    let payload = bincode::serialize(journal).map_err(|_| DALError::WriteError)?;
+    let mut datas = Vec::with_capacity(payload.len() + 8);
+    datas.extend_from_slice(&checksum(&payload).to_le_bytes());
+    datas.extend_from_slice(&payload);
+    write_file_sync(&file_path(dbs_folder_path, JOURNAL_FILE_NAME), &datas)?;
+    sync_folder(dbs_folder_path)
+}
+
+/// Read the journal, return `None` if it is corrupted
+fn read_journal(journal_path: &Path) -> Result<Option<JournalDatas>, DALError> {
+    let mut datas = Vec::new();
+    File::open(journal_path)?.read_to_end(&mut datas)?;
+    if datas.len() < 8 {
+        return Ok(None);
+    }
+    let mut checksum_bytes = [0u8; 8];
+    checksum_bytes.copy_from_slice(&datas[..8]);
+    if checksum(&datas[8..]) != u64::from_le_bytes(checksum_bytes) {
+        return Ok(None);
+    }
+    Ok(bincode::deserialize(&datas[8..]).ok())
+}
+
+/// Append the journal batches to the databases files, then finish the journal
+///
+/// Each batch is appended as a single checksummed record and synced: a record interrupted by a
+/// crash is discarded when opening the database, and the journal is replayed again.
+fn apply_journal(dbs_folder_path: &Path, journal: &JournalDatas) -> Result<(), DALError> {
+    for db_batch in &journal.batches {
+        let store = FileKvStore::open(file_path(dbs_folder_path, &db_batch.file_name))?;
+        store.write_batch(db_batch.batch.clone())?;
+        store.save()?;
+    }
+    finish_journal(dbs_folder_path, journal)
+}
+
+/// Write the other files, then remove the journal
+fn finish_journal(dbs_folder_path: &Path, journal: &JournalDatas) -> Result<(), DALError> {
+    for file in &journal.files {
+        write_file_sync(&file_path(dbs_folder_path, &file.file_name), &file.datas)?;
+    }
+    // The new files must be durable before the journal removal
+    sync_folder(dbs_folder_path)?;
+    fs::remove_file(file_path(dbs_folder_path, JOURNAL_FILE_NAME))?;
+    sync_folder(dbs_folder_path)
+}
+
+/// Commit atomically the modifications staged in `dbs` and the files `files`,
+/// the current block of the databases being `blockstamp`
+pub fn commit_dbs(
+    dbs_folder_path: &PathBuf,
+    blockstamp: Blockstamp,
+    dbs: &[&StagedKvDB],
+    files: Vec<DBFileDatas>,
+) -> Result<(), DALError> {
+    let blockstamp_bytes = bincode::serialize(&blockstamp).map_err(|_| DALError::WriteError)?;
+    let mut batches = Vec::with_capacity(dbs.len());
+    for db in dbs {
+        // Each database records its current block in the same batch as its modifications
+        db.put(META_COLLECTION, BLOCKSTAMP_KEY, &blockstamp_bytes)?;
+        batches.push(DBBatch {
+            file_name: db.file_name().to_owned(),
+            batch: db.staged_batch()?,
+        });
+    }
+    let journal = JournalDatas {
+        blockstamp,
+        batches,
+        files,
+    };
+    write_journal(dbs_folder_path, &journal)?;
+    for db in dbs {
+        db.commit()?;
+    }
+    finish_journal(dbs_folder_path, &journal)
+}
+
+/// Complete or discard an interrupted commit.
+///
+/// Must be called before opening the databases.
+pub fn recover_dbs(dbs_folder_path: &PathBuf) -> Result<JournalRecovery, DALError> {
+    let journal_path = file_path(dbs_folder_path, JOURNAL_FILE_NAME);
+    let tmp_path = tmp_file_path(&journal_path);
+    let incomplete_journal = tmp_path.exists();
+    if incomplete_journal {
+        fs::remove_file(&tmp_path)?;
+    }
+    if journal_path.exists() {
+        if let Some(journal) = read_journal(&journal_path)? {
+            apply_journal(dbs_folder_path, &journal)?;
+            return Ok(JournalRecovery::RolledForward(journal.blockstamp));
+        }
+        fs::remove_file(&journal_path)?;
+        return Ok(JournalRecovery::RolledBack);
+    }
+    Ok(if incomplete_journal {
+        JournalRecovery::RolledBack
+    } else {
+        JournalRecovery::Clean
+    })
+}
+
+/// Get the blockstamp of the last commit of a database (`None` if it has never been committed)
+pub fn get_db_blockstamp(db: &StagedKvDB) -> Result<Option<Blockstamp>, DALError> {
+    if let Some(datas) = db.get(META_COLLECTION, BLOCKSTAMP_KEY)? {
+        Ok(Some(
+            bincode::deserialize(&datas).map_err(|_| DALError::DBCorrupted)?,
+        ))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Check that the last commit of each database is at the current block of the blockchain
+pub fn check_dbs_consistency(
+    dbs: &[&StagedKvDB],
+    current_blockstamp: Blockstamp,
+) -> Result<(), DALError> {
+    for db in dbs {
+        match get_db_blockstamp(db)? {
+            Some(db_blockstamp) if db_blockstamp != current_blockstamp => {
+                error!(
+                    "BLOCKCHAIN-DAL: databases disagree on the current block: {}={}, blockchain={}.",
+                    db.file_name(),
+                    db_blockstamp,
+                    current_blockstamp
+                );
+                return Err(DALError::InconsistentDBs);
+            }
+            Some(_) => {}
+            None => debug!(
+                "BLOCKCHAIN-DAL: {} has never been committed, skip its consistency check.",
+                db.file_name()
+            ),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dubp_documents::{BlockHash, BlockNumber};
+    use dup_crypto::hashs::Hash;
+
+    fn tmp_dbs_path(name: &str) -> PathBuf {
+        let mut path = std::env::temp_dir();
+        path.push(format!("durs-journal-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).expect("fail to create dbs folder");
+        path
+    }
+
+    fn blockstamp(number: u32) -> Blockstamp {
+        Blockstamp {
+            id: BlockNumber(number),
+            hash: BlockHash(Hash::default()),
+        }
+    }
+
+    fn journal(number: u32) -> JournalDatas {
+        let mut batch = WriteBatch::new();
+        batch.put("c", vec![0], vec![number as u8]);
+        batch.put(
+            META_COLLECTION,
+            BLOCKSTAMP_KEY.to_vec(),
+            bincode::serialize(&blockstamp(number)).expect("fail to serialize blockstamp"),
+        );
+        JournalDatas {
+            blockstamp: blockstamp(number),
+            batches: vec![DBBatch {
+                file_name: "a.kv".to_owned(),
+                batch,
+            }],
+            files: vec![DBFileDatas {
+                file_name: "b.db".to_owned(),
+                datas: vec![number as u8; 5],
+            }],
+        }
+    }
+
+    fn read_file(dbs_path: &Path, file_name: &str) -> Vec<u8> {
+        fs::read(file_path(dbs_path, file_name)).expect("fail to read db file")
+    }
+
+    fn read_kv_value(dbs_path: &PathBuf) -> (Option<Vec<u8>>, Option<Blockstamp>) {
+        let db = StagedKvDB::open_read_only(Some(dbs_path), "a.kv").expect("fail to open db");
+        (
+            db.get("c", &[0]).expect("fail to read db"),
+            get_db_blockstamp(&db).expect("fail to read db"),
+        )
+    }
+
+    #[test]
+    fn test_commit_dbs() {
+        let dbs_path = tmp_dbs_path("commit");
+        let db_a = StagedKvDB::open(Some(&dbs_path), "a.kv").expect("fail to open db");
+        let db_c = StagedKvDB::open(Some(&dbs_path), "c.kv").expect("fail to open db");
+        assert_eq!(Ok(None), get_db_blockstamp(&db_a));
+        assert_eq!(
+            Ok(()),
+            check_dbs_consistency(&[&db_a, &db_c], blockstamp(1))
+        );
+
+        db_a.put("c", &[0], &[1]).expect("fail to write db");
+        let JournalDatas { files, .. } = journal(1);
+        commit_dbs(&dbs_path, blockstamp(1), &[&db_a, &db_c], files).expect("fail to commit");
+        assert!(db_a.staged_batch().expect("fail to read db").is_empty());
+        assert_eq!(
+            (Some(vec![1u8]), Some(blockstamp(1))),
+            read_kv_value(&dbs_path)
+        );
+        assert_eq!(vec![1u8; 5], read_file(&dbs_path, "b.db"));
+        assert!(!file_path(&dbs_path, "b.db.tmp").exists());
+        assert!(!file_path(&dbs_path, JOURNAL_FILE_NAME).exists());
+        assert_eq!(Ok(Some(blockstamp(1))), get_db_blockstamp(&db_c));
+        assert_eq!(
+            Ok(()),
+            check_dbs_consistency(&[&db_a, &db_c], blockstamp(1))
+        );
+        assert_eq!(
+            Err(DALError::InconsistentDBs),
+            check_dbs_consistency(&[&db_a, &db_c], blockstamp(2))
+        );
+
+        // A database committed alone disagrees with the others
+        commit_dbs(&dbs_path, blockstamp(2), &[&db_c], vec![]).expect("fail to commit");
+        assert_eq!(Ok(()), check_dbs_consistency(&[&db_c], blockstamp(2)));
+        assert_eq!(
+            Err(DALError::InconsistentDBs),
+            check_dbs_consistency(&[&db_a, &db_c], blockstamp(2))
+        );
+        assert_eq!(Ok(JournalRecovery::Clean), recover_dbs(&dbs_path));
+        let _ = fs::remove_dir_all(&dbs_path);
+    }
+
+    #[test]
+    fn test_recover_dbs() {
+        let dbs_path = tmp_dbs_path("recover");
+        {
+            let db = StagedKvDB::open(Some(&dbs_path), "a.kv").expect("fail to open db");
+            db.put("c", &[0], &[1]).expect("fail to write db");
+            let JournalDatas { files, .. } = journal(1);
+            commit_dbs(&dbs_path, blockstamp(1), &[&db], files).expect("fail to commit");
+        }
+
+        // Interrupted after the commit point: roll forward
+        write_journal(&dbs_path, &journal(2)).expect("fail to write journal");
+        assert_eq!(
+            Ok(JournalRecovery::RolledForward(blockstamp(2))),
+            recover_dbs(&dbs_path)
+        );
+        assert_eq!(
+            (Some(vec![2u8]), Some(blockstamp(2))),
+            read_kv_value(&dbs_path)
+        );
+        assert_eq!(vec![2u8; 5], read_file(&dbs_path, "b.db"));
+
+        // Interrupted while writing the journal: roll back
+        let journal_tmp_path = tmp_file_path(&file_path(&dbs_path, JOURNAL_FILE_NAME));
+        fs::write(&journal_tmp_path, &[3u8; 7]).expect("fail to write journal");
+        assert_eq!(Ok(JournalRecovery::RolledBack), recover_dbs(&dbs_path));
+        assert_eq!(
+            (Some(vec![2u8]), Some(blockstamp(2))),
+            read_kv_value(&dbs_path)
+        );
+        assert_eq!(vec![2u8; 5], read_file(&dbs_path, "b.db"));
+        assert!(!journal_tmp_path.exists());
+        let _ = fs::remove_dir_all(&dbs_path);
+    }
+}
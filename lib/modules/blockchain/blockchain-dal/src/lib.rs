@@ -42,9 +42,18 @@ pub mod entities;
 /// Define all filters applicable to entities
 pub mod filters;
 
+/// Write-ahead journal of databases commits
+pub mod journal;
+
+/// Migration of the databases files of previous versions
+mod migration;
+
 /// Contains all read databases functions
 pub mod readers;
 
+/// Key-value storage backends
+pub mod storage;
+
 /// Tools
 pub mod tools;
 
@@ -59,50 +68,67 @@ use dup_crypto::hashs::Hash;
 use dup_crypto::keys::*;
 use durs_common_tools::fatal_error;
 use durs_wot::data::{rusty::RustyWebOfTrust, NodeId};
-use fnv::FnvHashMap;
 use rustbreak::backend::{FileBackend, MemoryBackend};
 use rustbreak::error::{RustbreakError, RustbreakErrorKind};
 use rustbreak::{deser::Bincode, Database, FileDatabase, MemoryDatabase};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 use std::default::Default;
 use std::fmt::Debug;
 use std::fs;
 use std::panic::UnwindSafe;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 use crate::entities::block::DALBlock;
-use crate::entities::identity::DALIdentity;
-use crate::entities::sources::{SourceAmount, UTXOContentV10, UTXOIndexV10};
+use crate::entities::certification::DALCertification;
+use crate::entities::fork_tree::ForkTree;
+use crate::entities::identity::{DALIdentity, IdentitiesIndexesDB};
+use crate::entities::sources::{DALUdState, SourceAmount, UTXOContentV10, UTXOIndexV10};
+use crate::journal::DBFileDatas;
+use crate::storage::{KvMapDB, KvValueDB, StagedKvDB};
 use crate::writers::transaction::DALTxV10;
 
 /// Currency parameters (Protocol V10)
 pub type CurrencyParamsV10Datas = (CurrencyName, BlockV10Parameters);
 /// All blocks of local blockchain indexed by block number
-pub type LocalBlockchainV10Datas = FnvHashMap<BlockNumber, DALBlock>;
+pub type LocalBlockchainV10DB = KvMapDB<BlockNumber, DALBlock>;
+/// Universal dividend state after each block of local blockchain
+pub type UdStatesV10DB = KvMapDB<BlockNumber, DALUdState>;
 /// Forks tree meta datas (block number and hash only)
-pub type ForksTreeV10Datas = entities::fork_tree::ForkTree;
+pub type ForksTreeV10DB = KvValueDB<ForkTree>;
 /// Forks blocks referenced in tree indexed by their blockstamp
-pub type ForksBlocksV10Datas = HashMap<Blockstamp, DALBlock>;
+pub type ForksBlocksV10DB = KvMapDB<Blockstamp, DALBlock>;
 /// Blocks orphaned (no parent block) indexed by their previous blockstamp
-pub type OrphanBlocksV10Datas = HashMap<PreviousBlockstamp, Vec<DALBlock>>;
-/// Database containing the wot graph (each node of the graph in an u32)
+pub type OrphanBlocksV10DB = KvMapDB<PreviousBlockstamp, Vec<DALBlock>>;
+/// Wot graph (each node of the graph in an u32)
 pub type WotDB = RustyWebOfTrust;
+/// Database containing the wot graph
+pub type WotV10DB = KvValueDB<WotDB>;
 /// V10 Identities indexed by public key
-pub type IdentitiesV10Datas = HashMap<PubKey, DALIdentity>;
+pub type IdentitiesV10DB = KvMapDB<PubKey, DALIdentity>;
 /// Memberships sorted by created block
-pub type MsExpirV10Datas = FnvHashMap<BlockNumber, HashSet<NodeId>>;
+pub type MsExpirV10DB = KvMapDB<BlockNumber, HashSet<NodeId>>;
 /// Certifications sorted by created block
-pub type CertsExpirV10Datas = FnvHashMap<BlockNumber, HashSet<(NodeId, NodeId)>>;
+pub type CertsExpirV10DB = KvMapDB<BlockNumber, HashSet<(NodeId, NodeId)>>;
+/// Certifications not yet expired indexed by issuer, target and created block
+pub type CertsLinksV10DB = KvMapDB<(NodeId, NodeId, BlockNumber), ()>;
+/// Certifications issued and received by each identity (sorted by written block),
+/// indexed by identity, written block, issuer and target
+pub type CertsHistoryV10DB = KvMapDB<(NodeId, BlockNumber, NodeId, NodeId), DALCertification>;
 /// V10 Transactions indexed by their hashs
-pub type TxV10Datas = HashMap<Hash, DALTxV10>;
+pub type TxV10DB = KvMapDB<Hash, DALTxV10>;
 /// V10 Unused Transaction Output (=sources)
-pub type UTXOsV10Datas = HashMap<UTXOIndexV10, UTXOContentV10>;
+pub type UTXOsV10DB = KvMapDB<UTXOIndexV10, UTXOContentV10>;
 /// V10 UDs sources
-pub type UDsV10Datas = HashMap<PubKey, HashSet<BlockNumber>>;
+pub type UDsV10DB = KvMapDB<PubKey, HashSet<BlockNumber>>;
 /// V10 Balances accounts
-pub type BalancesV10Datas = HashMap<UTXOConditionsGroup, (SourceAmount, HashSet<UTXOIndexV10>)>;
+pub type BalancesV10DB = KvMapDB<UTXOConditionsGroup, (SourceAmount, HashSet<UTXOIndexV10>)>;
+/// V10 Transactions issued or received by each public key (sorted by block number)
+pub type PubkeysTxsV10DB = KvMapDB<(PubKey, BlockNumber, Hash), ()>;
+/// V10 Transactions consuming or supplying each address (sorted by block number)
+pub type AddressesTxsV10DB = KvMapDB<(UTXOConditionsGroup, BlockNumber, Hash), ()>;
 
 #[derive(Debug)]
 /// Database
@@ -160,67 +186,146 @@ impl<D: Serialize + DeserializeOwned + Debug + Default + Clone + Send> BinDB<D>
             BinDB::Mem(ref mem_db) => mem_db.load(),
         }
     }
+    /// Serialize the Data as it is saved in the database file `file_name`
+    pub fn to_db_file(&self, file_name: &str) -> Result<DBFileDatas, DALError> {
+        let datas = self
+            .read(|db| bincode::serialize(db))?
+            .map_err(|_| DALError::WriteError)?;
+        Ok(DBFileDatas {
+            file_name: file_name.to_owned(),
+            datas,
+        })
+    }
 }
 
 #[derive(Debug)]
 /// Set of databases storing block information
 pub struct BlocksV10DBs {
     /// Local blockchain database
-    pub blockchain_db: BinDB<LocalBlockchainV10Datas>,
+    pub blockchain_db: LocalBlockchainV10DB,
+    /// Universal dividend state after each block
+    pub ud_states_db: UdStatesV10DB,
 }
 
 impl BlocksV10DBs {
-    /// Open blocks databases from their respective files
-    pub fn open(db_path: Option<&PathBuf>) -> BlocksV10DBs {
+    fn new(db: StagedKvDB) -> BlocksV10DBs {
+        let db = Arc::new(db);
         BlocksV10DBs {
-            blockchain_db: open_db::<LocalBlockchainV10Datas>(db_path, "blockchain.db")
-                .expect("Fail to open LocalBlockchainV10DB"),
+            blockchain_db: KvMapDB::new(db.clone(), "blocks"),
+            ud_states_db: KvMapDB::new(db, "ud_states"),
+        }
+    }
+    /// Open blocks databases from their file (and migrate the former databases files)
+    pub fn open(db_path: Option<&PathBuf>) -> BlocksV10DBs {
+        let blocks_dbs = BlocksV10DBs::new(
+            StagedKvDB::open(db_path, constants::BLOCKS_DBS_FILE_NAME)
+                .expect("Fail to open BlocksV10DBs"),
+        );
+        if let Some(db_path) = db_path {
+            migration::migrate_blocks_dbs(db_path, &blocks_dbs)
+                .expect("Fail to migrate LocalBlockchainV10DB");
         }
+        blocks_dbs
+    }
+    /// Open blocks databases in read-only mode (to read the datas written by the blockchain module)
+    pub fn open_read_only(db_path: Option<&PathBuf>) -> BlocksV10DBs {
+        BlocksV10DBs::new(
+            StagedKvDB::open_read_only(db_path, constants::BLOCKS_DBS_FILE_NAME)
+                .expect("Fail to open BlocksV10DBs"),
+        )
     }
-    /// Save blocks databases in their respective files
+    /// Get the database to commit (with its staged modifications)
+    pub fn staged_db(&self) -> Result<&StagedKvDB, DALError> {
+        Ok(self.blockchain_db.db())
+    }
+    /// Save blocks databases in their file
     pub fn save_dbs(&self) {
         info!("BLOCKCHAIN-DAL: Save LocalBlockchainV10DB.");
         self.blockchain_db
             .save()
             .expect("Fatal error : fail to save LocalBlockchainV10DB !");
     }
+    /// Drop the modifications not saved
+    pub fn discard(&self) -> Result<(), DALError> {
+        self.blockchain_db.db().discard()
+    }
+    /// Reload blocks databases (for databases opened in read-only mode)
+    pub fn load(&self) -> Result<(), DALError> {
+        self.blockchain_db.refresh()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+/// Limits on the blocks kept in the forks databases
+pub struct ForksLimits {
+    /// Maximum rollback length (number of main branch blocks kept in the fork tree)
+    pub fork_window_size: usize,
+    /// Maximum number of orphan blocks
+    pub max_orphans_count: usize,
+    /// Maximum age of an orphan block (in blocks behind the current block)
+    pub max_orphan_age: u32,
+}
+
+impl Default for ForksLimits {
+    fn default() -> Self {
+        ForksLimits {
+            fork_window_size: *constants::FORK_WINDOW_SIZE,
+            max_orphans_count: *constants::DEFAULT_MAX_ORPHANS_COUNT,
+            max_orphan_age: *constants::DEFAULT_MAX_ORPHAN_AGE,
+        }
+    }
 }
 
 #[derive(Debug)]
 /// Set of databases storing forks informations
 pub struct ForksDBs {
     /// Fork tree (store only blockstamp)
-    pub fork_tree_db: BinDB<ForksTreeV10Datas>,
+    pub fork_tree_db: ForksTreeV10DB,
     /// Blocks in fork tree
-    pub fork_blocks_db: BinDB<ForksBlocksV10Datas>,
+    pub fork_blocks_db: ForksBlocksV10DB,
     /// Orphan blocks
-    pub orphan_blocks_db: BinDB<OrphanBlocksV10Datas>,
+    pub orphan_blocks_db: OrphanBlocksV10DB,
+    /// Limits enforced at each insertion
+    pub limits: ForksLimits,
 }
 
 impl ForksDBs {
-    /// Open fork databases from their respective files
+    fn new(db: StagedKvDB) -> Result<ForksDBs, DALError> {
+        let db = Arc::new(db);
+        Ok(ForksDBs {
+            fork_tree_db: KvValueDB::new(db.clone(), "fork_tree")?,
+            fork_blocks_db: KvMapDB::new(db.clone(), "fork_blocks"),
+            orphan_blocks_db: KvMapDB::new(db, "orphan_blocks"),
+            limits: ForksLimits::default(),
+        })
+    }
+    /// Open fork databases from their file (and migrate the former databases files)
     pub fn open(db_path: Option<&PathBuf>) -> ForksDBs {
-        ForksDBs {
-            fork_tree_db: open_db::<ForksTreeV10Datas>(db_path, "fork_tree.db")
-                .expect("Fail to open ForksTreeV10Datas"),
-            fork_blocks_db: open_db::<ForksBlocksV10Datas>(db_path, "fork_blocks.db")
-                .expect("Fail to open ForkForksBlocksV10DatassV10DB"),
-            orphan_blocks_db: open_db::<OrphanBlocksV10Datas>(db_path, "orphan_blocks.db")
-                .expect("Fail to open OrphanBlocksV10Datas"),
+        let forks_dbs = StagedKvDB::open(db_path, constants::FORKS_DBS_FILE_NAME)
+            .and_then(ForksDBs::new)
+            .expect("Fail to open ForksDBs");
+        if let Some(db_path) = db_path {
+            migration::migrate_forks_dbs(db_path, &forks_dbs).expect("Fail to migrate ForksDBs");
         }
+        forks_dbs
+    }
+    /// Get the database to commit (with its staged modifications)
+    pub fn staged_db(&self) -> Result<&StagedKvDB, DALError> {
+        self.fork_tree_db.stage()?;
+        Ok(self.fork_blocks_db.db())
     }
-    /// Save fork databases in their respective files
+    /// Save fork databases in their file
     pub fn save_dbs(&self) {
         info!("BLOCKCHAIN-DAL: Save ForksDBs.");
-        self.fork_tree_db
-            .save()
-            .expect("Fatal error : fail to save ForksTreeV10Datas !");
-        self.fork_blocks_db
-            .save()
-            .expect("Fatal error : fail to save ForkForksBlocksV10DatassV10DB !");
-        self.orphan_blocks_db
-            .save()
-            .expect("Fatal error : fail to save OrphanBlocksV10Datas !");
+        self.staged_db()
+            .and_then(StagedKvDB::commit)
+            .expect("Fatal error : fail to save ForksDBs !");
+    }
+    /// Drop the modifications not saved
+    pub fn discard(&self) -> Result<(), DALError> {
+        self.fork_blocks_db.db().discard()?;
+        self.fork_tree_db.load()
     }
 }
 
@@ -228,46 +333,77 @@ impl ForksDBs {
 /// Set of databases storing web of trust information
 pub struct WotsV10DBs {
     /// Store wot graph
-    pub wot_db: BinDB<WotDB>,
+    pub wot_db: WotV10DB,
     /// Store idrntities
-    pub identities_db: BinDB<IdentitiesV10Datas>,
+    pub identities_db: IdentitiesV10DB,
+    /// Store identities secondary indexes
+    pub idties_indexes_db: IdentitiesIndexesDB,
     /// Store memberships created_block_id (Use only to detect expirations)
-    pub ms_db: BinDB<MsExpirV10Datas>,
+    pub ms_db: MsExpirV10DB,
     /// Store certifications created_block_id (Use only to detect expirations)
-    pub certs_db: BinDB<CertsExpirV10Datas>,
+    pub certs_db: CertsExpirV10DB,
+    /// Store certifications created_block_id by link (Use to find the last certification of a link)
+    pub certs_links_db: CertsLinksV10DB,
+    /// Store certifications history of each identity
+    pub certs_history_db: CertsHistoryV10DB,
 }
 
 impl WotsV10DBs {
-    /// Open wot databases from their respective files
+    fn new(db: StagedKvDB) -> Result<WotsV10DBs, DALError> {
+        let db = Arc::new(db);
+        Ok(WotsV10DBs {
+            wot_db: KvValueDB::new(db.clone(), "wot")?,
+            identities_db: KvMapDB::new(db.clone(), "identities"),
+            idties_indexes_db: IdentitiesIndexesDB::new(db.clone()),
+            ms_db: KvMapDB::new(db.clone(), "ms"),
+            certs_db: KvMapDB::new(db.clone(), "certs"),
+            certs_links_db: KvMapDB::new(db.clone(), "certs_links"),
+            certs_history_db: KvMapDB::new(db, "certs_history"),
+        })
+    }
+    /// Open wot databases from their file (and migrate the former databases files)
     pub fn open(db_path: Option<&PathBuf>) -> WotsV10DBs {
-        WotsV10DBs {
-            wot_db: open_db::<RustyWebOfTrust>(db_path, "wot.db").expect("Fail to open WotDB"),
-            identities_db: open_db::<IdentitiesV10Datas>(db_path, "identities.db")
-                .expect("Fail to open IdentitiesV10DB"),
-            ms_db: open_db::<MsExpirV10Datas>(db_path, "ms.db").expect("Fail to open MsExpirV10DB"),
-            certs_db: open_db::<CertsExpirV10Datas>(db_path, "certs.db")
-                .expect("Fail to open CertsExpirV10DB"),
+        let wot_dbs = StagedKvDB::open(db_path, constants::WOTS_DBS_FILE_NAME)
+            .and_then(WotsV10DBs::new)
+            .expect("Fail to open WotsV10DBs");
+        if let Some(db_path) = db_path {
+            migration::migrate_wots_dbs(db_path, &wot_dbs).expect("Fail to migrate WotsV10DBs");
         }
+        wot_dbs
+    }
+    /// Open wot databases in read-only mode (to read the datas written by the blockchain module)
+    pub fn open_read_only(db_path: Option<&PathBuf>) -> WotsV10DBs {
+        StagedKvDB::open_read_only(db_path, constants::WOTS_DBS_FILE_NAME)
+            .and_then(WotsV10DBs::new)
+            .expect("Fail to open WotsV10DBs")
     }
-    /// Save wot databases from their respective files
+    /// Get the database to commit (with its staged modifications)
+    pub fn staged_db(&self) -> Result<&StagedKvDB, DALError> {
+        self.wot_db.stage()?;
+        Ok(self.identities_db.db())
+    }
+    /// Save wot databases in their file
     pub fn save_dbs(&self) {
         info!("BLOCKCHAIN-DAL: Save WotsV10DBs.");
-        self.wot_db
-            .save()
-            .expect("Fatal error : fail to save WotDB !");
-        self.save_dbs_except_graph();
+        self.staged_db()
+            .and_then(StagedKvDB::commit)
+            .expect("Fatal error : fail to save WotsV10DBs !");
     }
-    /// Save wot databases from their respective files (except wot graph)
+    /// Save wot databases in their file (except wot graph)
     pub fn save_dbs_except_graph(&self) {
         self.identities_db
             .save()
-            .expect("Fatal error : fail to save IdentitiesV10DB !");
-        self.ms_db
-            .save()
-            .expect("Fatal error : fail to save MsExpirV10DB !");
-        self.certs_db
-            .save()
-            .expect("Fatal error : fail to save CertsExpirV10DB !");
+            .expect("Fatal error : fail to save WotsV10DBs !");
+    }
+    /// Drop the modifications not saved
+    pub fn discard(&self) -> Result<(), DALError> {
+        self.identities_db.db().discard()?;
+        self.wot_db.load()
+    }
+    /// Reload wot databases (for databases opened in read-only mode)
+    pub fn load(&self) -> Result<(), DALError> {
+        self.identities_db.refresh()?;
+        self.wot_db.load()
     }
 }
 
@@ -275,47 +411,68 @@ impl WotsV10DBs {
 /// Set of databases storing currency information
 pub struct CurrencyV10DBs {
     /// Store all UD sources
-    pub du_db: BinDB<UDsV10Datas>,
+    pub du_db: UDsV10DB,
     /// Store all Transactions
-    pub tx_db: BinDB<TxV10Datas>,
+    pub tx_db: TxV10DB,
     /// Store all UTXOs
-    pub utxos_db: BinDB<UTXOsV10Datas>,
+    pub utxos_db: UTXOsV10DB,
     /// Store balances of all address (and theirs UTXOs indexs)
-    pub balances_db: BinDB<BalancesV10Datas>,
+    pub balances_db: BalancesV10DB,
+    /// Store the transactions history of all public keys
+    pub pubkeys_txs_db: PubkeysTxsV10DB,
+    /// Store the transactions history of all address
+    pub addresses_txs_db: AddressesTxsV10DB,
 }
 
 impl CurrencyV10DBs {
-    /// Open currency databases from their respective files
-    pub fn open(db_path: Option<&PathBuf>) -> CurrencyV10DBs {
+    fn new(db: StagedKvDB) -> CurrencyV10DBs {
+        let db = Arc::new(db);
         CurrencyV10DBs {
-            du_db: open_db::<UDsV10Datas>(db_path, "du.db").expect("Fail to open UDsV10DB"),
-            tx_db: open_db::<TxV10Datas>(db_path, "tx.db")
-                .unwrap_or_else(|_| fatal_error!("Fail to open TxV10DB")),
-            utxos_db: open_db::<UTXOsV10Datas>(db_path, "sources.db")
-                .expect("Fail to open UTXOsV10DB"),
-            balances_db: open_db::<BalancesV10Datas>(db_path, "balances.db")
-                .expect("Fail to open BalancesV10DB"),
+            du_db: KvMapDB::new(db.clone(), "du"),
+            tx_db: KvMapDB::new(db.clone(), "tx"),
+            utxos_db: KvMapDB::new(db.clone(), "sources"),
+            balances_db: KvMapDB::new(db.clone(), "balances"),
+            pubkeys_txs_db: KvMapDB::new(db.clone(), "pubkeys_txs"),
+            addresses_txs_db: KvMapDB::new(db, "addresses_txs"),
         }
     }
-    /// Save currency databases in their respective files
-    pub fn save_dbs(&self, tx: bool, du: bool) {
-        if tx {
-            info!("BLOCKCHAIN-DAL: Save CurrencyV10DBs.");
-            self.tx_db
-                .save()
-                .expect("Fatal error : fail to save LocalBlockchainV10DB !");
-            self.utxos_db
-                .save()
-                .expect("Fatal error : fail to save UTXOsV10DB !");
-            self.balances_db
-                .save()
-                .expect("Fatal error : fail to save BalancesV10DB !");
-        }
-        if du {
-            self.du_db
-                .save()
-                .expect("Fatal error : fail to save UDsV10DB !");
+    /// Open currency databases from their file (and migrate the former databases files)
+    pub fn open(db_path: Option<&PathBuf>) -> CurrencyV10DBs {
+        let currency_dbs = CurrencyV10DBs::new(
+            StagedKvDB::open(db_path, constants::CURRENCY_DBS_FILE_NAME)
+                .unwrap_or_else(|_| fatal_error!("Fail to open CurrencyV10DBs")),
+        );
+        if let Some(db_path) = db_path {
+            migration::migrate_currency_dbs(db_path, &currency_dbs)
+                .expect("Fail to migrate CurrencyV10DBs");
         }
+        currency_dbs
+    }
+    /// Open currency databases in read-only mode (to read the datas written by the blockchain module)
+    pub fn open_read_only(db_path: Option<&PathBuf>) -> CurrencyV10DBs {
+        CurrencyV10DBs::new(
+            StagedKvDB::open_read_only(db_path, constants::CURRENCY_DBS_FILE_NAME)
+                .unwrap_or_else(|_| fatal_error!("Fail to open CurrencyV10DBs")),
+        )
+    }
+    /// Get the database to commit (with its staged modifications)
+    pub fn staged_db(&self) -> Result<&StagedKvDB, DALError> {
+        Ok(self.tx_db.db())
+    }
+    /// Save currency databases in their file
+    pub fn save_dbs(&self) {
+        info!("BLOCKCHAIN-DAL: Save CurrencyV10DBs.");
+        self.tx_db
+            .save()
+            .expect("Fatal error : fail to save CurrencyV10DBs !");
+    }
+    /// Drop the modifications not saved
+    pub fn discard(&self) -> Result<(), DALError> {
+        self.tx_db.db().discard()
+    }
+    /// Reload currency databases (for databases opened in read-only mode)
+    pub fn load(&self) -> Result<(), DALError> {
+        self.tx_db.refresh()
     }
 }
 
@@ -332,6 +489,8 @@ pub enum DALError {
     FileSystemError,
     /// Capturing a panic signal during a write operation
     WritePanic,
+    /// Databases do not agree on the current block
+    InconsistentDBs,
     /// Unknown error
     UnknowError,
 }
@@ -349,6 +508,12 @@ impl From<RustbreakError> for DALError {
     }
 }
 
+impl From<std::io::Error> for DALError {
+    fn from(_: std::io::Error) -> DALError {
+        DALError::FileSystemError
+    }
+}
+
 /*#[derive(Debug, Clone)]
 pub struct WotStats {
     pub block_number: u32,
@@ -0,0 +1,310 @@
+//  Copyright (C) 2018  The Duniter Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! One-time migration of the rustbreak databases files (one file per database, entirely
+//! rewritten on each save) into the key-value databases files.
+//!
+//! The entries are copied by chunks, each chunk being committed on its own. Copying an entry
+//! twice is harmless, so an interrupted migration is simply restarted from the beginning.
+//! A marker is committed with the last chunk, the former files are removed afterwards.
+
+use crate::storage::{KvMapDB, KvStore, KvValueDB, StagedKvDB};
+use crate::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::hash::Hash as StdHash;
+use std::path::Path;
+
+/// Key of the migration marker
+static MIGRATED_KEY: &'static [u8] = b"rustbreak_migrated";
+/// Number of entries copied between two commits
+static MIGRATION_CHUNK_SIZE: &'static usize = &10_000;
+
+/// Read a former database file (`None` if it does not exist or is empty)
+fn read_old_file<D: DeserializeOwned>(
+    dbs_folder_path: &Path,
+    file_name: &str,
+) -> Result<Option<D>, DALError> {
+    let mut path = dbs_folder_path.to_path_buf();
+    path.push(file_name);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let datas = fs::read(&path)?;
+    if datas.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(
+        bincode::deserialize(&datas).map_err(|_| DALError::DBCorrupted)?,
+    ))
+}
+
+/// Copy the entries of a former database file in a map
+fn migrate_map<K, V>(
+    dbs_folder_path: &Path,
+    file_name: &str,
+    map: &KvMapDB<K, V>,
+) -> Result<(), DALError>
+where
+    K: Serialize + DeserializeOwned + Eq + StdHash,
+    V: Serialize + DeserializeOwned,
+{
+    if let Some(entries) = read_old_file::<HashMap<K, V>>(dbs_folder_path, file_name)? {
+        let mut entries = entries.into_iter().peekable();
+        while entries.peek().is_some() {
+            map.extend(entries.by_ref().take(*MIGRATION_CHUNK_SIZE))?;
+            map.save()?;
+        }
+    }
+    Ok(())
+}
+
+/// Copy a former database file in a value
+fn migrate_value<D>(
+    dbs_folder_path: &Path,
+    file_name: &str,
+    value_db: &KvValueDB<D>,
+) -> Result<(), DALError>
+where
+    D: Serialize + DeserializeOwned + Default,
+{
+    if let Some(value) = read_old_file::<D>(dbs_folder_path, file_name)? {
+        value_db.write(|db| *db = value)?;
+        value_db.stage()?;
+    }
+    Ok(())
+}
+
+/// Migrate the former files `old_files` into `db` with `migrate_files`, then remove them
+fn migrate<F>(
+    dbs_folder_path: &Path,
+    db: &StagedKvDB,
+    old_files: &[&str],
+    migrate_files: F,
+) -> Result<(), DALError>
+where
+    F: FnOnce() -> Result<(), DALError>,
+{
+    let old_paths: Vec<PathBuf> = old_files
+        .iter()
+        .map(|file_name| {
+            let mut path = dbs_folder_path.to_path_buf();
+            path.push(file_name);
+            path
+        })
+        .filter(|path| path.exists())
+        .collect();
+    if old_paths.is_empty() {
+        return Ok(());
+    }
+    if !db.contains(constants::META_COLLECTION, MIGRATED_KEY)? {
+        info!(
+            "BLOCKCHAIN-DAL: Migrate {:?} into {}...",
+            old_files,
+            db.file_name()
+        );
+        migrate_files()?;
+        db.put(constants::META_COLLECTION, MIGRATED_KEY, &[])?;
+        db.commit()?;
+        info!("BLOCKCHAIN-DAL: Migrate {}...finish", db.file_name());
+    }
+    for path in old_paths {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// Migrate the former blocks databases files
+pub fn migrate_blocks_dbs(
+    dbs_folder_path: &PathBuf,
+    blocks_dbs: &BlocksV10DBs,
+) -> Result<(), DALError> {
+    migrate(
+        dbs_folder_path,
+        blocks_dbs.blockchain_db.db(),
+        &["blockchain.db"],
+        || migrate_map(dbs_folder_path, "blockchain.db", &blocks_dbs.blockchain_db),
+    )
+}
+
+/// Migrate the former forks databases files
+pub fn migrate_forks_dbs(dbs_folder_path: &PathBuf, forks_dbs: &ForksDBs) -> Result<(), DALError> {
+    migrate(
+        dbs_folder_path,
+        forks_dbs.fork_blocks_db.db(),
+        &["fork_tree.db", "fork_blocks.db", "orphan_blocks.db"],
+        || {
+            migrate_value(dbs_folder_path, "fork_tree.db", &forks_dbs.fork_tree_db)?;
+            migrate_map(dbs_folder_path, "fork_blocks.db", &forks_dbs.fork_blocks_db)?;
+            migrate_map(
+                dbs_folder_path,
+                "orphan_blocks.db",
+                &forks_dbs.orphan_blocks_db,
+            )
+        },
+    )
+}
+
+/// Migrate the former wot databases files (identities and certifications indexes are rebuilt)
+pub fn migrate_wots_dbs(dbs_folder_path: &PathBuf, wot_dbs: &WotsV10DBs) -> Result<(), DALError> {
+    migrate(
+        dbs_folder_path,
+        wot_dbs.identities_db.db(),
+        &[
+            "wot.db",
+            "identities.db",
+            "identities_indexes.db",
+            "ms.db",
+            "certs.db",
+        ],
+        || {
+            migrate_value(dbs_folder_path, "wot.db", &wot_dbs.wot_db)?;
+            migrate_map(dbs_folder_path, "identities.db", &wot_dbs.identities_db)?;
+            migrate_map(dbs_folder_path, "ms.db", &wot_dbs.ms_db)?;
+            migrate_map(dbs_folder_path, "certs.db", &wot_dbs.certs_db)?;
+            writers::certification::build_missing_certs_links(
+                &wot_dbs.certs_db,
+                &wot_dbs.certs_links_db,
+            )?;
+            writers::identity::build_missing_identities_indexes(
+                &wot_dbs.identities_db,
+                &wot_dbs.idties_indexes_db,
+            )
+        },
+    )
+}
+
+/// Migrate the former currency databases files
+pub fn migrate_currency_dbs(
+    dbs_folder_path: &PathBuf,
+    currency_dbs: &CurrencyV10DBs,
+) -> Result<(), DALError> {
+    migrate(
+        dbs_folder_path,
+        currency_dbs.tx_db.db(),
+        &["du.db", "tx.db", "sources.db", "balances.db"],
+        || {
+            migrate_map(dbs_folder_path, "du.db", &currency_dbs.du_db)?;
+            migrate_map(dbs_folder_path, "tx.db", &currency_dbs.tx_db)?;
+            migrate_map(dbs_folder_path, "sources.db", &currency_dbs.utxos_db)?;
+            migrate_map(dbs_folder_path, "balances.db", &currency_dbs.balances_db)
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::identity::{DALIdentity, DALIdentityState};
+    use dup_crypto_tests_tools::mocks::pubkey;
+    use durs_wot::data::WebOfTrust;
+    use std::collections::HashSet;
+
+    fn tmp_dbs_path(name: &str) -> PathBuf {
+        let mut path = std::env::temp_dir();
+        path.push(format!("durs-migration-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).expect("fail to create dbs folder");
+        path
+    }
+
+    #[test]
+    fn test_migrate_currency_dbs() -> Result<(), DALError> {
+        let dbs_path = tmp_dbs_path("currency");
+        // Write former databases files
+        let uds: HashMap<PubKey, HashSet<BlockNumber>> = (0..25_000u32)
+            .map(|i| {
+                (
+                    PubKey::Ed25519(ed25519::PublicKey(pubkey_bytes(i))),
+                    vec![BlockNumber(i)].into_iter().collect(),
+                )
+            })
+            .collect();
+        let du_db = open_file_db::<HashMap<PubKey, HashSet<BlockNumber>>>(&dbs_path, "du.db")?;
+        du_db.write(|db| *db = uds)?;
+        du_db.save()?;
+        fs::write(dbs_path.join("tx.db"), &[])?;
+
+        let currency_dbs = CurrencyV10DBs::open(Some(&dbs_path));
+        assert_eq!(25_000, currency_dbs.du_db.len()?);
+        assert_eq!(
+            Some(vec![BlockNumber(7)].into_iter().collect()),
+            currency_dbs
+                .du_db
+                .get(&PubKey::Ed25519(ed25519::PublicKey(pubkey_bytes(7))))?
+        );
+        assert!(!dbs_path.join("du.db").exists());
+        assert!(!dbs_path.join("tx.db").exists());
+        assert_eq!(None, currency_dbs.du_db.get(&pubkey('A'))?);
+        drop(currency_dbs);
+
+        // The migrated datas are kept on reopening
+        let currency_dbs = CurrencyV10DBs::open(Some(&dbs_path));
+        assert_eq!(25_000, currency_dbs.du_db.len()?);
+        let _ = fs::remove_dir_all(&dbs_path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate_wots_dbs() -> Result<(), DALError> {
+        let dbs_path = tmp_dbs_path("wots");
+        // Write former databases files (without identities indexes)
+        let idty = DALIdentity {
+            hash: String::new(),
+            state: DALIdentityState::Member(vec![]),
+            joined_on: Blockstamp::default(),
+            expired_on: None,
+            revoked_on: None,
+            idty_doc: dubp_documents_tests_tools::mocks::identity::gen_mock_idty(
+                pubkey('A'),
+                BlockNumber(0),
+            ),
+            wot_id: NodeId(0),
+            ms_created_block_id: BlockNumber(0),
+            ms_chainable_on: vec![],
+            cert_chainable_on: vec![],
+        };
+        let identities_db =
+            open_file_db::<HashMap<PubKey, DALIdentity>>(&dbs_path, "identities.db")?;
+        identities_db.write(|db| {
+            db.insert(pubkey('A'), idty);
+        })?;
+        identities_db.save()?;
+        let wot_db = open_file_db::<WotDB>(&dbs_path, "wot.db")?;
+        wot_db.write(|wot| {
+            wot.add_node();
+        })?;
+        wot_db.save()?;
+
+        let wot_dbs = WotsV10DBs::open(Some(&dbs_path));
+        assert_eq!(Ok(1), wot_dbs.wot_db.read(WebOfTrust::size));
+        assert_eq!(1, wot_dbs.identities_db.len()?);
+        assert_eq!(
+            Some(pubkey('A')),
+            readers::identity::get_pubkey_from_uid(&wot_dbs.idties_indexes_db, "")?
+        );
+        assert!(!dbs_path.join("identities.db").exists());
+        assert!(!dbs_path.join("wot.db").exists());
+        let _ = fs::remove_dir_all(&dbs_path);
+        Ok(())
+    }
+
+    fn pubkey_bytes(i: u32) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        bytes[..4].copy_from_slice(&i.to_be_bytes());
+        bytes
+    }
+}
@@ -18,14 +18,10 @@ use crate::*;
 
 /// Get address balance
 pub fn get_address_balance(
-    balances_db: &BinDB<BalancesV10Datas>,
+    balances_db: &BalancesV10DB,
     address: &UTXOConditionsGroup,
 ) -> Result<Option<SourceAmount>, DALError> {
-    Ok(balances_db.read(|db| {
-        if let Some(balance_and_utxos) = db.get(address) {
-            Some(balance_and_utxos.0)
-        } else {
-            None
-        }
-    })?)
+    Ok(balances_db
+        .get(address)?
+        .map(|balance_and_utxos| balance_and_utxos.0))
 }
@@ -20,36 +20,111 @@ use dubp_documents::{BlockHash, BlockNumber, Blockstamp};
 use dup_crypto::keys::*;
 use std::collections::HashMap;
 
+/// Number of blocks read at once when scanning the local blockchain
+static SCAN_CHUNK_SIZE: &'static u32 = &1_000;
+
+/// Call `f` on each block of the local blockchain before `end` (excluded),
+/// reading the blocks by chunks
+pub fn for_each_block<F: FnMut(DALBlock)>(
+    blockchain_db: &LocalBlockchainV10DB,
+    end: u32,
+    mut f: F,
+) -> Result<(), DALError> {
+    let mut from = 0u32;
+    while from < end {
+        let to = end.min(from.saturating_add(*SCAN_CHUNK_SIZE));
+        for (_, dal_block) in
+            blockchain_db.range(Some(&BlockNumber(from)), Some(&BlockNumber(to)))?
+        {
+            f(dal_block);
+        }
+        from = to;
+    }
+    Ok(())
+}
+
 /// get current blockstamp
 pub fn get_current_blockstamp(blocks_db: &BlocksV10DBs) -> Result<Option<Blockstamp>, DALError> {
-    Ok(blocks_db.blockchain_db.read(|db| {
-        let blockchain_len = db.len() as u32;
-        if blockchain_len == 0 {
-            None
-        } else if let Some(dal_block) = db.get(&BlockNumber(blockchain_len - 1)) {
-            Some(dal_block.blockstamp())
-        } else {
-            None
+    let blockchain_len = blocks_db.blockchain_db.len()? as u32;
+    if blockchain_len == 0 {
+        Ok(None)
+    } else {
+        Ok(blocks_db
+            .blockchain_db
+            .get(&BlockNumber(blockchain_len - 1))?
+            .map(|dal_block| dal_block.blockstamp()))
+    }
+}
+
+/// Get the current blockstamp of the local blockchain database alone: the blockstamp recorded by
+/// its last commit, or its last block if it has never been committed
+pub fn get_blockchain_db_blockstamp(
+    blockchain_db: &LocalBlockchainV10DB,
+) -> Result<Option<Blockstamp>, DALError> {
+    if let Some(blockstamp) = crate::journal::get_db_blockstamp(blockchain_db.db())? {
+        return Ok(Some(blockstamp));
+    }
+    let blockchain_len = blockchain_db.len()? as u32;
+    if blockchain_len == 0 {
+        Ok(None)
+    } else {
+        Ok(blockchain_db
+            .get(&BlockNumber(blockchain_len - 1))?
+            .map(|dal_block| dal_block.blockstamp()))
+    }
+}
+
+/// Get the number of the last block whose median time is lower than `median_time`
+/// (`None` if there is no such block in the local blockchain)
+pub fn get_last_block_before_time(
+    db: &LocalBlockchainV10DB,
+    median_time: u64,
+) -> Result<Option<BlockNumber>, DALError> {
+    // The median time never decreases, so the first block not older than median_time
+    // can be found by dichotomy
+    let (mut begin, mut end) = (0u32, db.len()? as u32);
+    while begin < end {
+        let middle = begin + (end - begin) / 2;
+        match db.get(&BlockNumber(middle))? {
+            Some(ref dal_block) if dal_block.block.median_time < median_time => begin = middle + 1,
+            _ => end = middle,
         }
-    })?)
+    }
+    if begin == 0 {
+        Ok(None)
+    } else {
+        Ok(Some(BlockNumber(begin - 1)))
+    }
+}
+
+/// Get the number of the last block whose median time is at least `validity` before `median_time`,
+/// i.e. the last block whose datas valid for `validity` have expired at `median_time`
+/// (`None` if there is no such block in the local blockchain)
+pub fn get_last_block_expired_at(
+    db: &LocalBlockchainV10DB,
+    validity: u64,
+    median_time: u64,
+) -> Result<Option<BlockNumber>, DALError> {
+    if median_time < validity {
+        Ok(None)
+    } else {
+        get_last_block_before_time(db, median_time - validity + 1)
+    }
 }
 
 /// Get block hash
 pub fn get_block_hash(
-    db: &BinDB<LocalBlockchainV10Datas>,
+    db: &LocalBlockchainV10DB,
     block_number: BlockNumber,
 ) -> Result<Option<BlockHash>, DALError> {
-    Ok(db.read(|db| {
-        if let Some(dal_block) = db.get(&block_number) {
-            dal_block.block.hash
-        } else {
-            None
-        }
-    })?)
+    Ok(db
+        .get(&block_number)?
+        .and_then(|dal_block| dal_block.block.hash))
 }
+
 /// Return true if the node already knows this block
 pub fn already_have_block(
-    blockchain_db: &BinDB<LocalBlockchainV10Datas>,
+    blockchain_db: &LocalBlockchainV10DB,
     forks_dbs: &ForksDBs,
     blockstamp: Blockstamp,
     previous_hash: Hash,
@@ -59,34 +134,16 @@ pub fn already_have_block(
         hash: BlockHash(previous_hash),
     };
 
-    if forks_dbs
-        .fork_blocks_db
-        .read(|db| db.contains_key(&blockstamp))?
-    {
+    if forks_dbs.fork_blocks_db.contains_key(&blockstamp)? {
         return Ok(true);
-    } else if let Some(orphan_blockstamps) = forks_dbs.orphan_blocks_db.read(|db| {
-        if let Some(orphan_blocks) = db.get(&previous_blockstamp) {
-            let orphan_blockstamps: Vec<Blockstamp> =
-                orphan_blocks.iter().map(DALBlock::blockstamp).collect();
-            Some(orphan_blockstamps)
-        } else {
-            None
-        }
-    })? {
-        for orphan_blockstamp in orphan_blockstamps {
-            if orphan_blockstamp == blockstamp {
+    } else if let Some(orphan_blocks) = forks_dbs.orphan_blocks_db.get(&previous_blockstamp)? {
+        for orphan_block in orphan_blocks {
+            if orphan_block.blockstamp() == blockstamp {
                 return Ok(true);
             }
         }
-    } else {
-        return Ok(blockchain_db.read(|db| {
-            if let Some(dal_block) = db.get(&blockstamp.id) {
-                if dal_block.block.hash.unwrap_or_default() == blockstamp.hash {
-                    return true;
-                }
-            }
-            false
-        })?);
+    } else if let Some(dal_block) = blockchain_db.get(&blockstamp.id)? {
+        return Ok(dal_block.block.hash.unwrap_or_default() == blockstamp.hash);
     }
 
     Ok(false)
@@ -94,15 +151,35 @@ pub fn already_have_block(
 
 /// Get block
 pub fn get_block(
-    blockchain_db: &BinDB<LocalBlockchainV10Datas>,
-    forks_blocks_db: Option<&BinDB<ForksBlocksV10Datas>>,
+    blockchain_db: &LocalBlockchainV10DB,
+    forks_blocks_db: Option<&ForksBlocksV10DB>,
     blockstamp: &Blockstamp,
 ) -> Result<Option<DALBlock>, DALError> {
-    let dal_block = blockchain_db.read(|db| db.get(&blockstamp.id).cloned())?;
+    let dal_block = blockchain_db.get(&blockstamp.id)?;
     if dal_block.is_none() && forks_blocks_db.is_some() {
+        forks_blocks_db.expect("safe unwrap").get(&blockstamp)
+    } else {
+        Ok(dal_block)
+    }
+}
+
+/// Get block by hash (in local blockchain or in forks)
+pub fn get_block_by_hash(
+    blockchain_db: &LocalBlockchainV10DB,
+    forks_blocks_db: &ForksBlocksV10DB,
+    hash: &BlockHash,
+) -> Result<Option<DALBlock>, DALError> {
+    let dal_block = blockchain_db
+        .entries()?
+        .into_iter()
+        .map(|(_, dal_block)| dal_block)
+        .find(|dal_block| dal_block.block.hash == Some(*hash));
+    if dal_block.is_none() {
         Ok(forks_blocks_db
-            .expect("safe unwrap")
-            .read(|db| db.get(&blockstamp).cloned())?)
+            .entries()?
+            .into_iter()
+            .find(|(blockstamp, _)| blockstamp.hash == *hash)
+            .map(|(_, dal_block)| dal_block))
     } else {
         Ok(dal_block)
     }
@@ -111,58 +188,47 @@ pub fn get_block(
 /// Get block in local blockchain
 #[inline]
 pub fn get_block_in_local_blockchain(
-    db: &BinDB<LocalBlockchainV10Datas>,
+    db: &LocalBlockchainV10DB,
     block_id: BlockNumber,
 ) -> Result<Option<BlockDocument>, DALError> {
-    Ok(db.read(|db| {
-        if let Some(dal_block) = db.get(&block_id) {
-            Some(dal_block.block.clone())
-        } else {
-            None
-        }
-    })?)
+    Ok(db.get(&block_id)?.map(|dal_block| dal_block.block))
 }
 
 /// Get several blocks in local blockchain
 #[inline]
 pub fn get_blocks_in_local_blockchain(
-    db: &BinDB<LocalBlockchainV10Datas>,
+    db: &LocalBlockchainV10DB,
     first_block_number: BlockNumber,
     count: u32,
 ) -> Result<Vec<BlockDocument>, DALError> {
-    Ok(db.read(|db| {
-        let mut blocks = Vec::with_capacity(count as usize);
-        let mut current_block_number = first_block_number;
-        while let Some(dal_block) = db.get(&current_block_number) {
-            blocks.push(dal_block.block.clone());
-            current_block_number = BlockNumber(current_block_number.0 + 1);
+    let to = first_block_number.0.checked_add(count).map(BlockNumber);
+    let mut blocks = Vec::with_capacity(count as usize);
+    let mut current_block_number = first_block_number;
+    // Stop at the first missing block
+    for (block_number, dal_block) in db.range(Some(&first_block_number), to.as_ref())? {
+        if block_number != current_block_number {
+            break;
         }
-        blocks
-    })?)
+        blocks.push(dal_block.block);
+        current_block_number = BlockNumber(current_block_number.0 + 1);
+    }
+    Ok(blocks)
 }
 
 /// Get current frame of calculating members
 pub fn get_current_frame(
     current_block: &DALBlock,
-    db: &BinDB<LocalBlockchainV10Datas>,
+    db: &LocalBlockchainV10DB,
 ) -> Result<HashMap<PubKey, usize>, DALError> {
     let frame_begin = current_block.block.number.0 - current_block.block.issuers_frame as u32;
-    Ok(db.read(|db| {
-        let mut current_frame: HashMap<PubKey, usize> = HashMap::new();
-        for block_number in frame_begin..current_block.block.number.0 {
-            let issuer = db
-                .get(&BlockNumber(block_number))
-                .unwrap_or_else(|| fatal_error!("Fail to get block #{} !", block_number))
-                .block
-                .issuers()[0];
-            let issuer_count_blocks = if let Some(issuer_count_blocks) = current_frame.get(&issuer)
-            {
-                issuer_count_blocks + 1
-            } else {
-                1
-            };
-            current_frame.insert(issuer, issuer_count_blocks);
-        }
-        current_frame
-    })?)
+    let mut current_frame: HashMap<PubKey, usize> = HashMap::new();
+    for block_number in frame_begin..current_block.block.number.0 {
+        let issuer = db
+            .get(&BlockNumber(block_number))?
+            .unwrap_or_else(|| fatal_error!("Fail to get block #{} !", block_number))
+            .block
+            .issuers()[0];
+        *current_frame.entry(issuer).or_insert(0) += 1;
+    }
+    Ok(current_frame)
 }
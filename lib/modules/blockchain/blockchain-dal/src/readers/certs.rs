@@ -13,25 +13,471 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::{BinDB, CertsExpirV10Datas, DALError};
+use crate::entities::certification::DALCertification;
+use crate::{
+    CertsExpirV10DB, CertsHistoryV10DB, CertsLinksV10DB, DALError, LocalBlockchainV10DB, WotsV10DBs,
+};
 use dubp_documents::BlockNumber;
-use durs_wot::NodeId;
-use std::collections::HashMap;
+use dup_crypto::keys::PubKey;
+use durs_wot::data::HasLinkResult;
+use durs_wot::{NodeId, WebOfTrust};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// Certification of the history of an identity, completed with its current state
+pub struct CertificationDatas {
+    /// Certification
+    pub cert: DALCertification,
+    /// Median time at which the certification expires
+    /// (`None` if the block referenced by the certification is not in the local blockchain)
+    pub expires_on: Option<u64>,
+    /// True if the certification is still counted in the wot
+    pub has_link: bool,
+    /// Median time from which the issuer can renew the certification
+    /// (`None` if the issuer identity is unknown)
+    pub chainable_on: Option<u64>,
+}
+
+/// Get the certifications issued and received by an identity (sorted from the oldest to the newest)
+pub fn get_identity_certs(
+    certs_history_db: &CertsHistoryV10DB,
+    wot_id: NodeId,
+) -> Result<Vec<DALCertification>, DALError> {
+    Ok(certs_history_db
+        .range_prefix(&wot_id)?
+        .into_iter()
+        .map(|(_, cert)| cert)
+        .collect())
+}
+
+/// Get the certifications issued and received by an identity, with their expiry time
+/// and their current state (sorted from the oldest to the newest)
+pub fn get_identity_certs_datas(
+    wot_dbs: &WotsV10DBs,
+    blockchain_db: &LocalBlockchainV10DB,
+    sig_validity: u64,
+    wot_id: NodeId,
+) -> Result<Vec<CertificationDatas>, DALError> {
+    let certs = get_identity_certs(&wot_dbs.certs_history_db, wot_id)?;
+    let mut expires_on = Vec::with_capacity(certs.len());
+    let mut chainable_on = Vec::with_capacity(certs.len());
+    for cert in &certs {
+        expires_on.push(
+            blockchain_db
+                .get(&cert.created_block_id)?
+                .map(|dal_block| dal_block.block.median_time + sig_validity),
+        );
+        chainable_on.push(
+            wot_dbs
+                .identities_db
+                .get(&cert.issuer)?
+                .and_then(|idty| idty.cert_chainable_on.last().cloned()),
+        );
+    }
+    // Only the last certification of each link can still be counted in the wot
+    let mut last_certs = HashMap::with_capacity(certs.len());
+    for (i, cert) in certs.iter().enumerate() {
+        last_certs.insert((cert.source, cert.target), i);
+    }
+    let has_link: Vec<bool> = wot_dbs.wot_db.read(|db| {
+        certs
+            .iter()
+            .enumerate()
+            .map(|(i, cert)| {
+                last_certs[&(cert.source, cert.target)] == i
+                    && db.has_link(cert.source, cert.target) == HasLinkResult::Link(true)
+            })
+            .collect()
+    })?;
+    Ok(certs
+        .into_iter()
+        .zip(expires_on)
+        .zip(has_link)
+        .zip(chainable_on)
+        .map(
+            |(((cert, expires_on), has_link), chainable_on)| CertificationDatas {
+                cert,
+                expires_on,
+                has_link,
+                chainable_on,
+            },
+        )
+        .collect())
+}
+
+/// Get the created block of the last certification of the link `source` -> `target` that is
+/// not yet expired
+pub fn get_cert_created_block(
+    certs_links_db: &CertsLinksV10DB,
+    source: NodeId,
+    target: NodeId,
+) -> Result<Option<BlockNumber>, DALError> {
+    // Keys are sorted by created block
+    Ok(certs_links_db
+        .range_prefix(&(source, target))?
+        .into_iter()
+        .last()
+        .map(|((_, _, created_block_id), ())| created_block_id))
+}
+
+/// Get the created blocks of the certifications that expire at `median_time`
+/// (`sig_validity` after the median time of their created block), from the oldest to the newest
+pub fn get_blocks_expiring_certs(
+    blockchain_db: &LocalBlockchainV10DB,
+    certs_db: &CertsExpirV10DB,
+    sig_validity: u64,
+    median_time: u64,
+) -> Result<Vec<BlockNumber>, DALError> {
+    let last_expired_block = if let Some(block_id) =
+        super::block::get_last_block_expired_at(blockchain_db, sig_validity, median_time)?
+    {
+        block_id
+    } else {
+        return Ok(vec![]);
+    };
+    // Keys are sorted by block number
+    Ok(certs_db
+        .range(None, Some(&BlockNumber(last_expired_block.0 + 1)))?
+        .into_iter()
+        .map(|(created_block_id, _)| created_block_id)
+        .collect())
+}
 
 /// Find certifications that emitted in indicated blocks expiring
 pub fn find_expire_certs(
-    certs_db: &BinDB<CertsExpirV10Datas>,
+    certs_db: &CertsExpirV10DB,
     blocks_expiring: Vec<BlockNumber>,
 ) -> Result<HashMap<(NodeId, NodeId), BlockNumber>, DALError> {
-    Ok(certs_db.read(|db| {
-        let mut all_expire_certs = HashMap::new();
-        for expire_block_id in blocks_expiring {
-            if let Some(expire_certs) = db.get(&expire_block_id) {
-                for (source, target) in expire_certs {
-                    all_expire_certs.insert((*source, *target), expire_block_id);
-                }
+    let mut all_expire_certs = HashMap::new();
+    for expire_block_id in blocks_expiring {
+        if let Some(expire_certs) = certs_db.get(&expire_block_id)? {
+            for (source, target) in expire_certs {
+                all_expire_certs.insert((source, target), expire_block_id);
             }
         }
-        all_expire_certs
-    })?)
+    }
+    Ok(all_expire_certs)
+}
+
+/// Get the members that receive less than `sig_qty` certifications once the certifications
+/// `expire_certs` have expired and the certifications `new_certs` are written
+/// (with an unknown issuer if it joins in the same block)
+pub fn get_members_losing_certs(
+    wot_dbs: &WotsV10DBs,
+    expire_certs: &HashMap<(NodeId, NodeId), BlockNumber>,
+    new_certs: &[(Option<NodeId>, NodeId)],
+    sig_qty: usize,
+) -> Result<Vec<PubKey>, DALError> {
+    let targets: HashSet<NodeId> = expire_certs.keys().map(|(_, target)| *target).collect();
+    let losing_certs: Vec<NodeId> = wot_dbs.wot_db.read(|db| {
+        targets
+            .into_iter()
+            .filter(|target| db.is_enabled(*target) == Some(true))
+            .filter(|target| {
+                let mut sources: HashSet<NodeId> = db
+                    .get_links_source(*target)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|source| !expire_certs.contains_key(&(*source, *target)))
+                    .collect();
+                let mut newcomers_certs_count = 0;
+                for (source, _) in new_certs.iter().filter(|(_, t)| t == target) {
+                    if let Some(source) = source {
+                        sources.insert(*source);
+                    } else {
+                        newcomers_certs_count += 1;
+                    }
+                }
+                sources.len() + newcomers_certs_count < sig_qty
+            })
+            .collect()
+    })?;
+    let mut pubkeys = Vec::with_capacity(losing_certs.len());
+    for wot_id in losing_certs {
+        if let Some(pubkey) = wot_dbs.idties_indexes_db.get_pubkey_by_wot_id(wot_id)? {
+            pubkeys.push(pubkey);
+        }
+    }
+    Ok(pubkeys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::block::DALBlock;
+    use crate::entities::identity::{DALIdentity, DALIdentityState};
+    use crate::writers::certification::{
+        expire_certs, remove_cert_link, revert_cert_in_history, write_cert_in_history,
+        write_cert_link,
+    };
+    use dubp_documents::{BlockHash, Blockstamp};
+    use dup_crypto::hashs::Hash;
+    use dup_crypto_tests_tools::mocks::pubkey;
+
+    fn blockstamp(id: u32) -> Blockstamp {
+        Blockstamp {
+            id: BlockNumber(id),
+            hash: BlockHash(Hash::default()),
+        }
+    }
+
+    #[test]
+    fn test_get_identity_certs_datas() -> Result<(), DALError> {
+        let wot_dbs = WotsV10DBs::open(None);
+        let blockchain_db = crate::BlocksV10DBs::open(None).blockchain_db;
+        // Block n has median time n*10
+        for i in 0..4 {
+            blockchain_db.insert(
+                &BlockNumber(i),
+                &DALBlock {
+                    block: dubp_documents_tests_tools::mocks::gen_empty_timed_block(
+                        blockstamp(i),
+                        u64::from(i) * 10,
+                        Hash::default(),
+                    ),
+                    expire_certs: None,
+                },
+            )?;
+        }
+        // A certifies B in block #2, then B certifies A and A renews its certification in block #3
+        wot_dbs.identities_db.insert(
+            &pubkey('A'),
+            &DALIdentity {
+                hash: String::new(),
+                state: DALIdentityState::Member(vec![0]),
+                joined_on: blockstamp(0),
+                expired_on: None,
+                revoked_on: None,
+                idty_doc: dubp_documents_tests_tools::mocks::identity::gen_mock_idty(
+                    pubkey('A'),
+                    BlockNumber(0),
+                ),
+                wot_id: NodeId(0),
+                ms_created_block_id: BlockNumber(0),
+                ms_chainable_on: vec![],
+                cert_chainable_on: vec![25],
+            },
+        )?;
+        wot_dbs.wot_db.write(|db| {
+            db.add_node();
+            db.add_node();
+            db.add_link(NodeId(0), NodeId(1));
+        })?;
+        let a_to_b = DALCertification {
+            issuer: pubkey('A'),
+            source: NodeId(0),
+            target: NodeId(1),
+            created_block_id: BlockNumber(1),
+            written_on: blockstamp(2),
+        };
+        let b_to_a = DALCertification {
+            issuer: pubkey('B'),
+            source: NodeId(1),
+            target: NodeId(0),
+            created_block_id: BlockNumber(3),
+            written_on: blockstamp(3),
+        };
+        let a_to_b_renewed = DALCertification {
+            created_block_id: BlockNumber(2),
+            written_on: blockstamp(3),
+            ..a_to_b
+        };
+        write_cert_in_history(&wot_dbs.certs_history_db, a_to_b)?;
+        write_cert_in_history(&wot_dbs.certs_history_db, b_to_a)?;
+        write_cert_in_history(&wot_dbs.certs_history_db, a_to_b_renewed)?;
+
+        assert_eq!(
+            vec![
+                CertificationDatas {
+                    cert: a_to_b,
+                    expires_on: Some(110),
+                    has_link: false,
+                    chainable_on: Some(25),
+                },
+                CertificationDatas {
+                    cert: a_to_b_renewed,
+                    expires_on: Some(120),
+                    has_link: true,
+                    chainable_on: Some(25),
+                },
+                CertificationDatas {
+                    cert: b_to_a,
+                    expires_on: Some(130),
+                    has_link: false,
+                    chainable_on: None,
+                }
+            ],
+            get_identity_certs_datas(&wot_dbs, &blockchain_db, 100, NodeId(0))?
+        );
+
+        // Revert B -> A
+        revert_cert_in_history(
+            &wot_dbs.certs_history_db,
+            NodeId(1),
+            NodeId(0),
+            blockstamp(3),
+        )?;
+        assert_eq!(
+            vec![a_to_b, a_to_b_renewed],
+            get_identity_certs(&wot_dbs.certs_history_db, NodeId(0))?
+        );
+        assert_eq!(
+            vec![a_to_b, a_to_b_renewed],
+            get_identity_certs(&wot_dbs.certs_history_db, NodeId(1))?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_blocks_expiring_certs() -> Result<(), DALError> {
+        let wot_dbs = WotsV10DBs::open(None);
+        let blockchain_db = crate::BlocksV10DBs::open(None).blockchain_db;
+        // Block n has median time n*10
+        for i in 0..4 {
+            blockchain_db.insert(
+                &BlockNumber(i),
+                &DALBlock {
+                    block: dubp_documents_tests_tools::mocks::gen_empty_timed_block(
+                        blockstamp(i),
+                        u64::from(i) * 10,
+                        Hash::default(),
+                    ),
+                    expire_certs: None,
+                },
+            )?;
+        }
+        // Certifications created in blocks #1 and #3
+        wot_dbs.certs_db.insert(
+            &BlockNumber(1),
+            &vec![(NodeId(0), NodeId(1))].into_iter().collect(),
+        )?;
+        wot_dbs.certs_db.insert(
+            &BlockNumber(3),
+            &vec![(NodeId(1), NodeId(0))].into_iter().collect(),
+        )?;
+        let sig_validity = 100;
+
+        assert!(
+            get_blocks_expiring_certs(&blockchain_db, &wot_dbs.certs_db, sig_validity, 109)?
+                .is_empty()
+        );
+        assert_eq!(
+            vec![BlockNumber(1)],
+            get_blocks_expiring_certs(&blockchain_db, &wot_dbs.certs_db, sig_validity, 110)?
+        );
+        assert_eq!(
+            vec![BlockNumber(1), BlockNumber(3)],
+            get_blocks_expiring_certs(&blockchain_db, &wot_dbs.certs_db, sig_validity, 130)?
+        );
+        assert_eq!(
+            vec![((NodeId(0), NodeId(1)), BlockNumber(1))]
+                .into_iter()
+                .collect::<HashMap<_, _>>(),
+            find_expire_certs(&wot_dbs.certs_db, vec![BlockNumber(1)])?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_cert_created_block() -> Result<(), DALError> {
+        let wot_dbs = WotsV10DBs::open(None);
+        let (a, b, c) = (NodeId(0), NodeId(1), NodeId(2));
+        // A certifies B in blocks #3 and #5, B certifies C in block #3
+        for (source, target, created_block_id) in &[(a, b, 3), (b, c, 3), (a, b, 5)] {
+            wot_dbs
+                .certs_db
+                .update(&BlockNumber(*created_block_id), |certs| {
+                    certs
+                        .get_or_insert_with(Default::default)
+                        .insert((*source, *target));
+                })?;
+            write_cert_link(
+                &wot_dbs.certs_links_db,
+                *source,
+                *target,
+                BlockNumber(*created_block_id),
+            )?;
+        }
+        let created_block =
+            |source, target| get_cert_created_block(&wot_dbs.certs_links_db, source, target);
+        assert_eq!(Some(BlockNumber(5)), created_block(a, b)?);
+        assert_eq!(Some(BlockNumber(3)), created_block(b, c)?);
+        assert_eq!(None, created_block(b, a)?);
+
+        // Certifications created in block #3 expire
+        expire_certs(&wot_dbs.certs_db, &wot_dbs.certs_links_db, BlockNumber(3))?;
+        assert_eq!(Some(BlockNumber(5)), created_block(a, b)?);
+        assert_eq!(None, created_block(b, c)?);
+
+        // The renewal is reverted
+        remove_cert_link(&wot_dbs.certs_links_db, a, b, BlockNumber(5))?;
+        assert_eq!(None, created_block(a, b)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_members_losing_certs() -> Result<(), DALError> {
+        let wot_dbs = WotsV10DBs::open(None);
+        // B, C and D certify A
+        wot_dbs.wot_db.write(|db| {
+            for _ in 0..4 {
+                db.add_node();
+            }
+            for source in 1..4 {
+                db.add_link(NodeId(source), NodeId(0));
+            }
+        })?;
+        wot_dbs.idties_indexes_db.insert(&DALIdentity {
+            hash: String::new(),
+            state: DALIdentityState::Member(vec![0]),
+            joined_on: blockstamp(0),
+            expired_on: None,
+            revoked_on: None,
+            idty_doc: dubp_documents_tests_tools::mocks::identity::gen_mock_idty(
+                pubkey('A'),
+                BlockNumber(0),
+            ),
+            wot_id: NodeId(0),
+            ms_created_block_id: BlockNumber(0),
+            ms_chainable_on: vec![],
+            cert_chainable_on: vec![],
+        })?;
+        let expire_certs: HashMap<(NodeId, NodeId), BlockNumber> =
+            vec![((NodeId(1), NodeId(0)), BlockNumber(0))]
+                .into_iter()
+                .collect();
+
+        assert!(get_members_losing_certs(&wot_dbs, &expire_certs, &[], 2)?.is_empty());
+        assert_eq!(
+            vec![pubkey('A')],
+            get_members_losing_certs(&wot_dbs, &expire_certs, &[], 3)?
+        );
+        // The expiring certification is renewed
+        assert!(get_members_losing_certs(
+            &wot_dbs,
+            &expire_certs,
+            &[(Some(NodeId(1)), NodeId(0))],
+            3
+        )?
+        .is_empty());
+        // A still valid certification is renewed
+        assert_eq!(
+            vec![pubkey('A')],
+            get_members_losing_certs(&wot_dbs, &expire_certs, &[(Some(NodeId(2)), NodeId(0))], 3)?
+        );
+        // A newcomer certifies A
+        assert!(
+            get_members_losing_certs(&wot_dbs, &expire_certs, &[(None, NodeId(0))], 3)?.is_empty()
+        );
+        // Excluded members are not counted
+        wot_dbs.wot_db.write(|db| {
+            db.set_enabled(NodeId(0), false);
+        })?;
+        assert!(get_members_losing_certs(&wot_dbs, &expire_certs, &[], 3)?.is_empty());
+
+        Ok(())
+    }
 }
@@ -18,20 +18,18 @@ use crate::*;
 
 /// Get currency parameters
 pub fn get_currency_params(
-    blockchain_db: &BinDB<LocalBlockchainV10Datas>,
+    blockchain_db: &LocalBlockchainV10DB,
 ) -> Result<Option<CurrencyParameters>, DALError> {
-    Ok(blockchain_db.read(|db| {
-        if let Some(genesis_block) = db.get(&BlockNumber(0)) {
-            if genesis_block.block.parameters.is_some() {
-                Some(CurrencyParameters::from((
-                    genesis_block.block.currency.clone(),
-                    genesis_block.block.parameters.expect("safe unwrap"),
-                )))
-            } else {
-                fatal_error!("The genesis block are None parameters !");
-            }
+    if let Some(genesis_block) = blockchain_db.get(&BlockNumber(0))? {
+        if genesis_block.block.parameters.is_some() {
+            Ok(Some(CurrencyParameters::from((
+                genesis_block.block.currency,
+                genesis_block.block.parameters.expect("safe unwrap"),
+            ))))
         } else {
-            None
+            fatal_error!("The genesis block are None parameters !");
         }
-    })?)
+    } else {
+        Ok(None)
+    }
 }
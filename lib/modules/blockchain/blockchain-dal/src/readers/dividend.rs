@@ -0,0 +1,114 @@
+//  Copyright (C) 2018  The Duniter Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::entities::sources::{DALDividend, SourceAmount};
+use crate::*;
+use dubp_documents::documents::transaction::{TxAmount, TxBase};
+use dubp_documents::BlockNumber;
+
+/// Get the universal dividends created by the local blockchain (from the oldest to the newest)
+pub fn get_ud_history(blockchain_db: &LocalBlockchainV10DB) -> Result<Vec<DALDividend>, DALError> {
+    let mut ud_history = Vec::new();
+    super::block::for_each_block(blockchain_db, blockchain_db.len()? as u32, |dal_block| {
+        if let Some(dividend) = dal_block.block.dividend {
+            ud_history.push(DALDividend {
+                block_number: dal_block.block.number,
+                median_time: dal_block.block.median_time,
+                amount: SourceAmount(
+                    TxAmount(dividend as isize),
+                    TxBase(dal_block.block.unit_base),
+                ),
+                members_count: dal_block.block.members_count,
+            });
+        }
+    })?;
+    Ok(ud_history)
+}
+
+/// Get the monetary mass after the block `block_number` (`None` if there is no such block)
+pub fn get_monetary_mass(
+    blockchain_db: &LocalBlockchainV10DB,
+    block_number: BlockNumber,
+) -> Result<Option<usize>, DALError> {
+    Ok(blockchain_db
+        .get(&block_number)?
+        .map(|dal_block| dal_block.block.monetary_mass))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dubp_documents::{BlockHash, Blockstamp};
+    use dup_crypto::hashs::Hash;
+
+    #[test]
+    fn test_ud_history_and_monetary_mass() -> Result<(), DALError> {
+        let blocks_dbs = BlocksV10DBs::open(None);
+        let blockchain_db = &blocks_dbs.blockchain_db;
+        for n in 0..4u32 {
+            let mut block = dubp_documents_tests_tools::mocks::gen_empty_timed_block(
+                Blockstamp {
+                    id: BlockNumber(n),
+                    hash: BlockHash(dup_crypto_tests_tools::mocks::hash_from_byte(n as u8)),
+                },
+                u64::from(n) * 10,
+                Hash::default(),
+            );
+            block.members_count = 3;
+            if n == 1 {
+                block.dividend = Some(1_000);
+            } else if n == 3 {
+                block.dividend = Some(101);
+                block.unit_base = 1;
+            }
+            block.monetary_mass = [0, 3_000, 3_000, 6_030][n as usize];
+            blockchain_db.insert(
+                &BlockNumber(n),
+                &DALBlock {
+                    block,
+                    expire_certs: None,
+                },
+            )?;
+        }
+        assert_eq!(
+            vec![
+                DALDividend {
+                    block_number: BlockNumber(1),
+                    median_time: 10,
+                    amount: SourceAmount(TxAmount(1_000), TxBase(0)),
+                    members_count: 3,
+                },
+                DALDividend {
+                    block_number: BlockNumber(3),
+                    median_time: 30,
+                    amount: SourceAmount(TxAmount(101), TxBase(1)),
+                    members_count: 3,
+                },
+            ],
+            get_ud_history(blockchain_db)?
+        );
+        assert_eq!(Some(0), get_monetary_mass(blockchain_db, BlockNumber(0))?);
+        assert_eq!(
+            Some(3_000),
+            get_monetary_mass(blockchain_db, BlockNumber(2))?
+        );
+        assert_eq!(
+            Some(6_030),
+            get_monetary_mass(blockchain_db, BlockNumber(3))?
+        );
+        assert_eq!(None, get_monetary_mass(blockchain_db, BlockNumber(4))?);
+        Ok(())
+    }
+}
@@ -21,12 +21,8 @@ pub fn get_stackables_blocks(
     forks_dbs: &ForksDBs,
     current_blockstamp: &Blockstamp,
 ) -> Result<Vec<DALBlock>, DALError> {
-    if let Some(stackables_blocks) = forks_dbs
+    Ok(forks_dbs
         .orphan_blocks_db
-        .read(|db| db.get(&current_blockstamp).cloned())?
-    {
-        Ok(stackables_blocks)
-    } else {
-        Ok(vec![])
-    }
+        .get(current_blockstamp)?
+        .unwrap_or_default())
 }
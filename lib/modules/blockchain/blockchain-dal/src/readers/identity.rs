@@ -13,130 +13,286 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::entities::identity::DALIdentity;
-use crate::filters::identities::IdentitiesFilter;
-use crate::{BinDB, DALError, IdentitiesV10Datas};
-use dubp_documents::{BlockNumber, Document};
+use crate::entities::identity::{
+    DALIdentity, DALIdentityState, DALIdentityStateKind, IdentitiesIndexesDB,
+};
+use crate::filters::identities::{IdentitiesFilter, UidFilter};
+use crate::{DALError, IdentitiesV10DB, LocalBlockchainV10DB, WotsV10DBs};
+use dubp_documents::{BlockNumber, Blockstamp, Document};
 use dup_crypto::keys::*;
-use durs_wot::NodeId;
-use std::collections::HashMap;
+use durs_wot::{NodeId, WebOfTrust};
+use std::collections::{HashMap, HashSet};
 
 /// Get identities in databases
+///
+/// The filters on pubkey, uid, state and membership expiry are evaluated with the identities
+/// indexes, `blockchain_db` and `ms_validity` are only used by the membership expiry filter.
 pub fn get_identities(
-    db: &BinDB<IdentitiesV10Datas>,
-    filters: IdentitiesFilter,
+    wot_dbs: &WotsV10DBs,
+    blockchain_db: &LocalBlockchainV10DB,
+    ms_validity: u64,
+    filters: &IdentitiesFilter,
     current_block_id: BlockNumber,
 ) -> Result<Vec<DALIdentity>, DALError> {
-    if let Some(pubkey) = filters.by_pubkey {
-        if let Some(idty) = db.read(|db| db.get(&pubkey).cloned())? {
-            Ok(vec![idty])
+    // The memberships expiring before ms_expires_before are those created before this block
+    let ms_last_expiring_block = if let Some(ms_expires_before) = filters.ms_expires_before {
+        Some(super::block::get_last_block_before_time(
+            blockchain_db,
+            ms_expires_before.saturating_sub(ms_validity),
+        )?)
+    } else {
+        None
+    };
+    // Select the candidates in indexes
+    let mut candidates: Option<HashSet<PubKey>> = filters
+        .by_pubkey
+        .map(|pubkey| vec![pubkey].into_iter().collect());
+    let indexes_db = &wot_dbs.idties_indexes_db;
+    let mut indexes_candidates: Vec<HashSet<PubKey>> = Vec::new();
+    if let Some(ref uid_filter) = filters.by_uid {
+        indexes_candidates.push(match *uid_filter {
+            UidFilter::Exact(ref uid) => indexes_db.get_pubkey(uid)?.into_iter().collect(),
+            UidFilter::Prefix(ref prefix) => indexes_db
+                .get_pubkeys_by_uid_prefix(prefix)?
+                .into_iter()
+                .collect(),
+        });
+    }
+    if let Some(state) = filters.by_state {
+        indexes_candidates.push(
+            indexes_db
+                .get_pubkeys_by_state(state)?
+                .into_iter()
+                .collect(),
+        );
+    }
+    if let Some(ms_last_expiring_block) = ms_last_expiring_block {
+        indexes_candidates.push(if let Some(last_block_id) = ms_last_expiring_block {
+            indexes_db
+                .get_pubkeys_by_ms_created_until(last_block_id)?
+                .into_iter()
+                .collect()
+        } else {
+            HashSet::with_capacity(0)
+        });
+    }
+    for index_candidates in indexes_candidates {
+        candidates = Some(if let Some(candidates) = candidates {
+            candidates
+                .intersection(&index_candidates)
+                .cloned()
+                .collect()
         } else {
-            Ok(vec![])
+            index_candidates
+        });
+    }
+    // Get candidates identities
+    let identities: Vec<DALIdentity> = if let Some(candidates) = candidates {
+        let mut identities = Vec::with_capacity(candidates.len());
+        for pubkey in candidates {
+            if let Some(idty) = wot_dbs.identities_db.get(&pubkey)? {
+                identities.push(idty);
+            }
         }
+        identities
     } else {
-        Ok(db.read(|db| {
-            let mut identities: Vec<&DALIdentity> = db
-                .values()
-                .filter(|idty| {
-                    filters
-                        .paging
-                        .check_created_on(idty.idty_doc.blockstamp().id, current_block_id)
-                })
-                .collect();
-            identities.sort_by(|i1, i2| {
-                i1.idty_doc
-                    .blockstamp()
-                    .id
-                    .cmp(&i2.idty_doc.blockstamp().id)
-            });
+        wot_dbs
+            .identities_db
+            .entries()?
+            .into_iter()
+            .map(|(_, idty)| idty)
+            .collect()
+    };
+    let mut identities: Vec<DALIdentity> = identities
+        .into_iter()
+        .filter(|idty| {
+            filters
+                .paging
+                .check_created_on(idty.idty_doc.blockstamp().id, current_block_id)
+        })
+        .collect();
+    // Filter by received certifications count
+    if let Some(certs_count_below) = filters.certs_count_below {
+        identities = wot_dbs.wot_db.read(|db| {
             identities
                 .into_iter()
-                .skip(filters.paging.page_size * filters.paging.page_number)
-                .take(filters.paging.page_size)
-                .cloned()
+                .filter(|idty| {
+                    db.get_links_source(idty.wot_id)
+                        .map_or(0, |sources| sources.len())
+                        < certs_count_below
+                })
                 .collect()
-        })?)
+        })?;
     }
+    identities.sort_by(|i1, i2| {
+        i1.idty_doc
+            .blockstamp()
+            .id
+            .cmp(&i2.idty_doc.blockstamp().id)
+    });
+    Ok(identities
+        .into_iter()
+        .skip(filters.paging.page_size * filters.paging.page_number)
+        .take(filters.paging.page_size)
+        .collect())
 }
 
 /// Get identity in databases
 pub fn get_identity(
-    db: &BinDB<IdentitiesV10Datas>,
+    db: &IdentitiesV10DB,
     pubkey: &PubKey,
 ) -> Result<Option<DALIdentity>, DALError> {
-    Ok(db.read(|db| {
-        if let Some(member_datas) = db.get(&pubkey) {
-            Some(member_datas.clone())
-        } else {
-            None
-        }
-    })?)
+    db.get(pubkey)
 }
 
 /// Get uid from pubkey
 pub fn get_uid(
-    identities_db: &BinDB<IdentitiesV10Datas>,
+    identities_db: &IdentitiesV10DB,
     pubkey: PubKey,
 ) -> Result<Option<String>, DALError> {
-    Ok(identities_db.read(|db| {
-        if let Some(dal_idty) = db.get(&pubkey) {
-            Some(String::from(dal_idty.idty_doc.username()))
-        } else {
-            None
-        }
-    })?)
+    Ok(identities_db
+        .get(&pubkey)?
+        .map(|dal_idty| String::from(dal_idty.idty_doc.username())))
 }
 
 /// Get pubkey from uid
 pub fn get_pubkey_from_uid(
-    identities_db: &BinDB<IdentitiesV10Datas>,
+    idties_indexes_db: &IdentitiesIndexesDB,
     uid: &str,
 ) -> Result<Option<PubKey>, DALError> {
-    Ok(identities_db.read(|db| {
-        for (pubkey, dal_idty) in db {
-            if uid == dal_idty.idty_doc.username() {
-                return Some(*pubkey);
+    idties_indexes_db.get_pubkey(uid)
+}
+
+/// Get the identities in `states` whose current membership has been created at least `validity`
+/// before `median_time`
+fn get_identities_with_ms_expired_at(
+    idties_indexes_db: &IdentitiesIndexesDB,
+    blockchain_db: &LocalBlockchainV10DB,
+    states: &[DALIdentityStateKind],
+    validity: u64,
+    median_time: u64,
+) -> Result<Vec<PubKey>, DALError> {
+    let last_expired_block = if let Some(block_id) =
+        super::block::get_last_block_expired_at(blockchain_db, validity, median_time)?
+    {
+        block_id
+    } else {
+        return Ok(vec![]);
+    };
+    let mut in_states: HashSet<PubKey> = HashSet::new();
+    for state in states {
+        in_states.extend(idties_indexes_db.get_pubkeys_by_state(*state)?);
+    }
+    Ok(idties_indexes_db
+        .get_pubkeys_by_ms_created_until(last_expired_block)?
+        .into_iter()
+        .filter(|pubkey| in_states.contains(pubkey))
+        .collect())
+}
+
+/// Get the members whose membership expires at `median_time`
+/// (`ms_validity` after the median time of its created block)
+pub fn get_expired_members(
+    idties_indexes_db: &IdentitiesIndexesDB,
+    blockchain_db: &LocalBlockchainV10DB,
+    ms_validity: u64,
+    median_time: u64,
+) -> Result<Vec<PubKey>, DALError> {
+    get_identities_with_ms_expired_at(
+        idties_indexes_db,
+        blockchain_db,
+        &[DALIdentityStateKind::Member],
+        ms_validity,
+        median_time,
+    )
+}
+
+/// Get the not yet revoked identities that are implicitly revoked at `median_time`
+/// (membership not renewed during `2 * ms_validity` after the median time of its created block)
+pub fn get_implicitly_revoked(
+    idties_indexes_db: &IdentitiesIndexesDB,
+    blockchain_db: &LocalBlockchainV10DB,
+    ms_validity: u64,
+    median_time: u64,
+) -> Result<Vec<PubKey>, DALError> {
+    get_identities_with_ms_expired_at(
+        idties_indexes_db,
+        blockchain_db,
+        &[DALIdentityStateKind::Member, DALIdentityStateKind::Expired],
+        ms_validity * 2,
+        median_time,
+    )
+}
+
+/// Get the identities implicitly revoked by the block `blockstamp`
+pub fn get_implicitly_revoked_on(
+    wot_dbs: &WotsV10DBs,
+    blockstamp: Blockstamp,
+) -> Result<Vec<PubKey>, DALError> {
+    let mut implicitly_revoked = Vec::new();
+    for pubkey in wot_dbs
+        .idties_indexes_db
+        .get_pubkeys_revoked_on(blockstamp.id)?
+    {
+        if let Some(idty) = wot_dbs.identities_db.get(&pubkey)? {
+            if let DALIdentityState::ImplicitRevoked(_) = idty.state {
+                if idty.revoked_on == Some(blockstamp) {
+                    implicitly_revoked.push(pubkey);
+                }
             }
         }
-        None
-    })?)
+    }
+    Ok(implicitly_revoked)
 }
 
 /// Get wot_id index
-pub fn get_wot_index(
-    identities_db: &BinDB<IdentitiesV10Datas>,
-) -> Result<HashMap<PubKey, NodeId>, DALError> {
-    Ok(identities_db.read(|db| {
-        db.iter()
-            .map(|(pubkey, member_datas)| (*pubkey, member_datas.wot_id))
-            .collect()
-    })?)
+pub fn get_wot_index(identities_db: &IdentitiesV10DB) -> Result<HashMap<PubKey, NodeId>, DALError> {
+    Ok(identities_db
+        .entries()?
+        .into_iter()
+        .map(|(pubkey, member_datas)| (pubkey, member_datas.wot_id))
+        .collect())
 }
 
 #[cfg(test)]
 mod test {
 
     use super::*;
+    use crate::entities::block::DALBlock;
     use crate::entities::identity::*;
     use crate::filters::PagingFilter;
     use crate::*;
-    use dubp_documents::Blockstamp;
+    use dubp_documents::documents::identity::IdentityDocumentBuilder;
+    use dubp_documents::{BlockHash, Blockstamp, DocumentBuilder};
+    use dup_crypto::hashs::Hash;
     use dup_crypto_tests_tools::mocks::pubkey;
     use durs_common_tests_tools::collections::slice_same_elems;
 
-    fn gen_mock_dal_idty(pubkey: PubKey, created_block_id: BlockNumber) -> DALIdentity {
+    fn gen_mock_dal_idty(
+        pubkey: PubKey,
+        uid: &str,
+        created_block_id: BlockNumber,
+        wot_id: NodeId,
+        ms_created_block_id: BlockNumber,
+    ) -> DALIdentity {
         DALIdentity {
             hash: "".to_owned(),
             state: DALIdentityState::Member(vec![]),
             joined_on: Blockstamp::default(),
             expired_on: None,
             revoked_on: None,
-            idty_doc: dubp_documents_tests_tools::mocks::identity::gen_mock_idty(
-                pubkey,
-                created_block_id,
-            ),
-            wot_id: NodeId(0),
-            ms_created_block_id: BlockNumber(0),
+            idty_doc: IdentityDocumentBuilder {
+                currency: "",
+                username: uid,
+                blockstamp: &Blockstamp {
+                    id: created_block_id,
+                    hash: BlockHash(Hash::default()),
+                },
+                issuer: &pubkey,
+            }
+            .build_with_signature(vec![]),
+            wot_id,
+            ms_created_block_id,
             ms_chainable_on: vec![],
             cert_chainable_on: vec![],
         }
@@ -145,40 +301,124 @@ mod test {
     #[test]
     fn test_get_identities() -> Result<(), DALError> {
         // Create mock identities
-        let mock_identities = vec![
-            gen_mock_dal_idty(pubkey('A'), BlockNumber(0)),
-            gen_mock_dal_idty(pubkey('B'), BlockNumber(1)),
-            gen_mock_dal_idty(pubkey('C'), BlockNumber(3)),
-            gen_mock_dal_idty(pubkey('D'), BlockNumber(4)),
-            gen_mock_dal_idty(pubkey('E'), BlockNumber(5)),
+        let mut mock_identities = vec![
+            gen_mock_dal_idty(
+                pubkey('A'),
+                "alice",
+                BlockNumber(0),
+                NodeId(0),
+                BlockNumber(0),
+            ),
+            gen_mock_dal_idty(
+                pubkey('B'),
+                "bob",
+                BlockNumber(1),
+                NodeId(1),
+                BlockNumber(1),
+            ),
+            gen_mock_dal_idty(
+                pubkey('C'),
+                "alex",
+                BlockNumber(3),
+                NodeId(2),
+                BlockNumber(3),
+            ),
+            gen_mock_dal_idty(
+                pubkey('D'),
+                "dave",
+                BlockNumber(4),
+                NodeId(3),
+                BlockNumber(4),
+            ),
+            gen_mock_dal_idty(
+                pubkey('E'),
+                "eve",
+                BlockNumber(5),
+                NodeId(4),
+                BlockNumber(5),
+            ),
         ];
+        mock_identities[1].state = DALIdentityState::ExpireMember(vec![]);
+        mock_identities[3].state = DALIdentityState::ImplicitRevoked(vec![]);
 
-        // Write mock identities in DB
-        let identities_db =
-            BinDB::Mem(open_memory_db::<IdentitiesV10Datas>().expect("Fail to create memory DB !"));
+        // Write mock identities and their indexes in DB
+        let wot_dbs = WotsV10DBs::open(None);
         for idty in &mock_identities {
-            identities_db.write(|db| {
-                db.insert(idty.idty_doc.issuers()[0], idty.clone());
-            })?;
+            wot_dbs
+                .identities_db
+                .insert(&idty.idty_doc.issuers()[0], idty)?;
+            writers::identity::update_identities_indexes(
+                &wot_dbs.idties_indexes_db,
+                None,
+                Some(idty),
+            )?;
+        }
+
+        // Write mock wot : A and C certified by B, C certified by D
+        wot_dbs.wot_db.write(|db| {
+            for _ in 0..5 {
+                db.add_node();
+            }
+            db.add_link(NodeId(1), NodeId(0));
+            db.add_link(NodeId(1), NodeId(2));
+            db.add_link(NodeId(3), NodeId(2));
+        })?;
+
+        // Write mock blockchain (block n has median time n*10)
+        let blockchain_db = BlocksV10DBs::open(None).blockchain_db;
+        for i in 0..6 {
+            blockchain_db.insert(
+                &BlockNumber(i),
+                &DALBlock {
+                    block: dubp_documents_tests_tools::mocks::gen_empty_timed_block(
+                        Blockstamp {
+                            id: BlockNumber(i),
+                            hash: BlockHash(Hash::default()),
+                        },
+                        u64::from(i) * 10,
+                        Hash::default(),
+                    ),
+                    expire_certs: None,
+                },
+            )?;
         }
+        let ms_validity = 100;
 
         // Test default filters
         let mut filters = IdentitiesFilter::default();
         assert!(slice_same_elems(
             &mock_identities,
-            &get_identities(&identities_db, filters, BlockNumber(5))?
+            &get_identities(
+                &wot_dbs,
+                &blockchain_db,
+                ms_validity,
+                &filters,
+                BlockNumber(5)
+            )?
         ));
 
         // Test by pubkey filter
         filters = IdentitiesFilter::by_pubkey(pubkey('A'));
         assert_eq!(
             vec![mock_identities[0].clone()],
-            get_identities(&identities_db, filters, BlockNumber(5))?
+            get_identities(
+                &wot_dbs,
+                &blockchain_db,
+                ms_validity,
+                &filters,
+                BlockNumber(5)
+            )?
         );
         filters = IdentitiesFilter::by_pubkey(pubkey('C'));
         assert_eq!(
             vec![mock_identities[2].clone()],
-            get_identities(&identities_db, filters, BlockNumber(5))?
+            get_identities(
+                &wot_dbs,
+                &blockchain_db,
+                ms_validity,
+                &filters,
+                BlockNumber(5)
+            )?
         );
 
         // Test paging filter with little page size
@@ -189,11 +429,17 @@ mod test {
                 page_size: 2,
                 page_number: 1,
             },
-            by_pubkey: None,
+            ..IdentitiesFilter::default()
         };
         assert!(slice_same_elems(
             &vec![mock_identities[2].clone(), mock_identities[3].clone()],
-            &get_identities(&identities_db, filters, BlockNumber(5))?
+            &get_identities(
+                &wot_dbs,
+                &blockchain_db,
+                ms_validity,
+                &filters,
+                BlockNumber(5)
+            )?
         ));
 
         // Test paging filter with limited interval
@@ -204,12 +450,244 @@ mod test {
                 page_size: 50,
                 page_number: 0,
             },
-            by_pubkey: None,
+            ..IdentitiesFilter::default()
         };
         assert_eq!(
             vec![mock_identities[2].clone()],
-            get_identities(&identities_db, filters, BlockNumber(5))?
+            get_identities(
+                &wot_dbs,
+                &blockchain_db,
+                ms_validity,
+                &filters,
+                BlockNumber(5)
+            )?
+        );
+
+        // Test uid filters
+        filters = IdentitiesFilter::by_uid(UidFilter::Exact("bob".to_owned()));
+        assert_eq!(
+            vec![mock_identities[1].clone()],
+            get_identities(
+                &wot_dbs,
+                &blockchain_db,
+                ms_validity,
+                &filters,
+                BlockNumber(5)
+            )?
+        );
+        filters = IdentitiesFilter::by_uid(UidFilter::Exact("al".to_owned()));
+        assert!(get_identities(
+            &wot_dbs,
+            &blockchain_db,
+            ms_validity,
+            &filters,
+            BlockNumber(5)
+        )?
+        .is_empty());
+        filters = IdentitiesFilter::by_uid(UidFilter::Prefix("al".to_owned()));
+        assert_eq!(
+            vec![mock_identities[0].clone(), mock_identities[2].clone()],
+            get_identities(
+                &wot_dbs,
+                &blockchain_db,
+                ms_validity,
+                &filters,
+                BlockNumber(5)
+            )?
+        );
+
+        // Test state filters
+        filters = IdentitiesFilter::by_state(DALIdentityStateKind::Member);
+        assert_eq!(
+            vec![
+                mock_identities[0].clone(),
+                mock_identities[2].clone(),
+                mock_identities[4].clone()
+            ],
+            get_identities(
+                &wot_dbs,
+                &blockchain_db,
+                ms_validity,
+                &filters,
+                BlockNumber(5)
+            )?
+        );
+        filters = IdentitiesFilter::by_state(DALIdentityStateKind::Revoked);
+        assert_eq!(
+            vec![mock_identities[3].clone()],
+            get_identities(
+                &wot_dbs,
+                &blockchain_db,
+                ms_validity,
+                &filters,
+                BlockNumber(5)
+            )?
+        );
+
+        // Test membership expiry filter (memberships created on blocks #0 to #2 expire before 130)
+        filters = IdentitiesFilter {
+            ms_expires_before: Some(130),
+            ..IdentitiesFilter::default()
+        };
+        assert_eq!(
+            vec![mock_identities[0].clone(), mock_identities[1].clone()],
+            get_identities(
+                &wot_dbs,
+                &blockchain_db,
+                ms_validity,
+                &filters,
+                BlockNumber(5)
+            )?
+        );
+        filters = IdentitiesFilter {
+            ms_expires_before: Some(100),
+            ..IdentitiesFilter::default()
+        };
+        assert!(get_identities(
+            &wot_dbs,
+            &blockchain_db,
+            ms_validity,
+            &filters,
+            BlockNumber(5)
+        )?
+        .is_empty());
+
+        // Test received certifications count filter combined with state and uid filters
+        filters = IdentitiesFilter {
+            certs_count_below: Some(2),
+            ..IdentitiesFilter::by_state(DALIdentityStateKind::Member)
+        };
+        assert_eq!(
+            vec![mock_identities[0].clone(), mock_identities[4].clone()],
+            get_identities(
+                &wot_dbs,
+                &blockchain_db,
+                ms_validity,
+                &filters,
+                BlockNumber(5)
+            )?
+        );
+        filters = IdentitiesFilter {
+            certs_count_below: Some(1),
+            by_uid: Some(UidFilter::Prefix("a".to_owned())),
+            ..IdentitiesFilter::default()
+        };
+        assert!(get_identities(
+            &wot_dbs,
+            &blockchain_db,
+            ms_validity,
+            &filters,
+            BlockNumber(5)
+        )?
+        .is_empty());
+
+        // Test uid index after a change of identity state
+        let mut revoked_idty = mock_identities[0].clone();
+        revoked_idty.state = DALIdentityState::ExplicitRevoked(vec![]);
+        writers::identity::update_identities_indexes(
+            &wot_dbs.idties_indexes_db,
+            Some(&mock_identities[0]),
+            Some(&revoked_idty),
+        )?;
+        assert_eq!(
+            Some(pubkey('A')),
+            get_pubkey_from_uid(&wot_dbs.idties_indexes_db, "alice")?
+        );
+        assert_eq!(
+            vec![pubkey('A'), pubkey('D')]
+                .into_iter()
+                .collect::<HashSet<PubKey>>(),
+            wot_dbs
+                .idties_indexes_db
+                .get_pubkeys_by_state(DALIdentityStateKind::Revoked)?
+                .into_iter()
+                .collect::<HashSet<PubKey>>()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_expired_and_implicitly_revoked() -> Result<(), DALError> {
+        let mut mock_identities = vec![
+            gen_mock_dal_idty(pubkey('A'), "a", BlockNumber(0), NodeId(0), BlockNumber(0)),
+            gen_mock_dal_idty(pubkey('B'), "b", BlockNumber(0), NodeId(1), BlockNumber(2)),
+            gen_mock_dal_idty(pubkey('C'), "c", BlockNumber(0), NodeId(2), BlockNumber(1)),
+            gen_mock_dal_idty(pubkey('D'), "d", BlockNumber(0), NodeId(3), BlockNumber(0)),
+        ];
+        let revoked_on = Blockstamp {
+            id: BlockNumber(5),
+            hash: BlockHash(Hash::default()),
+        };
+        mock_identities[2].state = DALIdentityState::ExpireMember(vec![]);
+        mock_identities[3].state = DALIdentityState::ImplicitRevoked(vec![]);
+        mock_identities[3].revoked_on = Some(revoked_on);
+        let wot_dbs = WotsV10DBs::open(None);
+        for idty in &mock_identities {
+            wot_dbs
+                .identities_db
+                .insert(&idty.idty_doc.issuers()[0], idty)?;
+            writers::identity::update_identities_indexes(
+                &wot_dbs.idties_indexes_db,
+                None,
+                Some(idty),
+            )?;
+        }
+        // Block n has median time n*10
+        let blockchain_db = BlocksV10DBs::open(None).blockchain_db;
+        for i in 0..6 {
+            blockchain_db.insert(
+                &BlockNumber(i),
+                &DALBlock {
+                    block: dubp_documents_tests_tools::mocks::gen_empty_timed_block(
+                        Blockstamp {
+                            id: BlockNumber(i),
+                            hash: BlockHash(Hash::default()),
+                        },
+                        u64::from(i) * 10,
+                        Hash::default(),
+                    ),
+                    expire_certs: None,
+                },
+            )?;
+        }
+        let ms_validity = 20;
+        let expired = |median_time| {
+            get_expired_members(
+                &wot_dbs.idties_indexes_db,
+                &blockchain_db,
+                ms_validity,
+                median_time,
+            )
+        };
+        let revoked = |median_time| {
+            get_implicitly_revoked(
+                &wot_dbs.idties_indexes_db,
+                &blockchain_db,
+                ms_validity,
+                median_time,
+            )
+        };
+
+        assert!(expired(5)?.is_empty());
+        assert!(expired(19)?.is_empty());
+        assert_eq!(vec![pubkey('A')], expired(20)?);
+        assert!(slice_same_elems(
+            &vec![pubkey('A'), pubkey('B')],
+            &expired(40)?
+        ));
+        assert!(revoked(39)?.is_empty());
+        assert_eq!(vec![pubkey('A')], revoked(40)?);
+        assert!(slice_same_elems(
+            &vec![pubkey('A'), pubkey('C')],
+            &revoked(50)?
+        ));
+
+        assert_eq!(
+            vec![pubkey('D')],
+            get_implicitly_revoked_on(&wot_dbs, revoked_on)?
         );
+        assert!(get_implicitly_revoked_on(&wot_dbs, Blockstamp::default())?.is_empty());
 
         Ok(())
     }
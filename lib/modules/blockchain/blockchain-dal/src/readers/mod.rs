@@ -25,8 +25,17 @@ pub mod certs;
 /// Currency params read functions
 pub mod currency_params;
 
+/// Universal dividends read functions
+pub mod dividend;
+
 /// Fork tree read functions
 pub mod fork_tree;
 
 /// Identities read functions
 pub mod identity;
+
+/// Sources read functions
+pub mod sources;
+
+/// Transactions read functions
+pub mod transaction;
@@ -0,0 +1,42 @@
+//  Copyright (C) 2018  The Durs Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::entities::sources::{UTXOIndexV10, UTXOV10};
+use crate::readers::transaction::output_involve_pubkey;
+use crate::*;
+use dubp_documents::BlockNumber;
+use dup_crypto::keys::PubKey;
+
+/// Get the unused transaction outputs that can be consumed by a signature of `pubkey`
+pub fn get_pubkey_utxos(utxos_db: &UTXOsV10DB, pubkey: &PubKey) -> Result<Vec<UTXOV10>, DALError> {
+    let mut utxos: Vec<UTXOV10> = utxos_db
+        .entries()?
+        .into_iter()
+        .filter(|(_, output)| output_involve_pubkey(output, pubkey))
+        .map(|(utxo_index, output)| UTXOV10(utxo_index, output))
+        .collect();
+    utxos.sort_by_key(|UTXOV10(UTXOIndexV10(hash, tx_index), _)| (*hash, tx_index.0));
+    Ok(utxos)
+}
+
+/// Get the universal dividends of `pubkey` not yet consumed (sorted by creation block)
+pub fn get_pubkey_uds(du_db: &UDsV10DB, pubkey: &PubKey) -> Result<Vec<BlockNumber>, DALError> {
+    let mut uds: Vec<BlockNumber> = du_db
+        .get(pubkey)?
+        .map(|uds| uds.into_iter().collect())
+        .unwrap_or_default();
+    uds.sort();
+    Ok(uds)
+}
@@ -0,0 +1,101 @@
+//  Copyright (C) 2018  The Durs Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::filters::PagingFilter;
+use crate::writers::transaction::DALTxV10;
+use crate::*;
+use dubp_documents::documents::transaction::{TransactionOutput, UTXOConditionsGroup};
+use dup_crypto::keys::PubKey;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Get a transaction written in the local blockchain
+pub fn get_tx(tx_db: &TxV10DB, hash: &Hash) -> Result<Option<DALTxV10>, DALError> {
+    tx_db.get(hash)
+}
+
+/// Get a page of the transactions history of `pubkey` : block number and hash of the
+/// transactions issued by `pubkey` or sending money to `pubkey` (sorted from the oldest to the newest)
+pub fn get_pubkey_txs_history(
+    pubkeys_txs_db: &PubkeysTxsV10DB,
+    pubkey: &PubKey,
+    paging: PagingFilter,
+    current_block_id: BlockNumber,
+) -> Result<Vec<(BlockNumber, Hash)>, DALError> {
+    get_history_page(pubkeys_txs_db, pubkey, paging, current_block_id)
+}
+
+/// Get a page of the transactions history of an address : block number and hash of the
+/// transactions consuming or supplying `conditions` (sorted from the oldest to the newest)
+pub fn get_address_txs_history(
+    addresses_txs_db: &AddressesTxsV10DB,
+    conditions: &UTXOConditionsGroup,
+    paging: PagingFilter,
+    current_block_id: BlockNumber,
+) -> Result<Vec<(BlockNumber, Hash)>, DALError> {
+    get_history_page(addresses_txs_db, conditions, paging, current_block_id)
+}
+
+/// Scan the history of `account` from the first block of the page filter
+fn get_history_page<K: Serialize + DeserializeOwned>(
+    history_db: &KvMapDB<(K, BlockNumber, Hash), ()>,
+    account: &K,
+    paging: PagingFilter,
+    current_block_id: BlockNumber,
+) -> Result<Vec<(BlockNumber, Hash)>, DALError> {
+    let page_start = paging.page_size * paging.page_number;
+    Ok(history_db
+        .range_between_prefixes(
+            &(account, paging.from),
+            &(account, paging.to.unwrap_or(current_block_id)),
+            page_start + paging.page_size,
+        )?
+        .into_iter()
+        .skip(page_start)
+        .map(|((_, block_id, hash), ())| (block_id, hash))
+        .collect())
+}
+
+/// Get a page of the transactions (and their hashs) issued by `pubkey` or sending money to `pubkey`
+/// (sorted from the oldest to the newest)
+pub fn get_pubkey_txs(
+    currency_dbs: &CurrencyV10DBs,
+    pubkey: &PubKey,
+    paging: PagingFilter,
+    current_block_id: BlockNumber,
+) -> Result<Vec<(Hash, DALTxV10)>, DALError> {
+    let history = get_pubkey_txs_history(
+        &currency_dbs.pubkeys_txs_db,
+        pubkey,
+        paging,
+        current_block_id,
+    )?;
+    let mut txs = Vec::with_capacity(history.len());
+    for (_, hash) in history {
+        if let Some(dal_tx) = currency_dbs.tx_db.get(&hash)? {
+            txs.push((hash, dal_tx));
+        }
+    }
+    Ok(txs)
+}
+
+/// Return true if the output can be consumed by a signature of `pubkey`
+pub fn output_involve_pubkey(output: &TransactionOutput, pubkey: &PubKey) -> bool {
+    output
+        .conditions
+        .conditions
+        .get_sig_pubkeys()
+        .contains(pubkey)
+}
@@ -0,0 +1,174 @@
+//  Copyright (C) 2018  The Duniter Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::marker::PhantomData;
+
+#[derive(Debug, Copy, Clone)]
+/// Collection of entries of type `V` indexed by keys of type `K`.
+///
+/// Keys are serialized in big endian, so that integer keys (such as block numbers) are
+/// sorted by value.
+pub struct KvCollection<K, V> {
+    /// Collection name
+    pub name: &'static str,
+    phantom: PhantomData<(K, V)>,
+}
+
+impl<K, V> KvCollection<K, V> {
+    /// Define a collection
+    pub const fn new(name: &'static str) -> Self {
+        KvCollection {
+            name,
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// Serialize a key, or the first fields of a tuple key
+/// (the result is then a prefix of the serialized keys starting with these fields)
+pub(crate) fn ser_key_prefix<P: Serialize>(prefix: &P) -> Result<KvBytes, DALError> {
+    bincode::config()
+        .big_endian()
+        .serialize(prefix)
+        .map_err(|_| DALError::WriteError)
+}
+
+/// Smallest key greater than all the keys starting with `prefix` (`None` if there is none)
+pub(crate) fn prefix_end(prefix: &[u8]) -> Option<KvBytes> {
+    let mut end = prefix.to_vec();
+    while let Some(last_byte) = end.pop() {
+        if last_byte < u8::max_value() {
+            end.push(last_byte + 1);
+            return Some(end);
+        }
+    }
+    None
+}
+
+impl<K: Serialize + DeserializeOwned, V: Serialize + DeserializeOwned> KvCollection<K, V> {
+    pub(crate) fn ser_key(key: &K) -> Result<KvBytes, DALError> {
+        ser_key_prefix(key)
+    }
+    pub(crate) fn de_key(bytes: &[u8]) -> Result<K, DALError> {
+        bincode::config()
+            .big_endian()
+            .deserialize(bytes)
+            .map_err(|_| DALError::ReadError)
+    }
+    pub(crate) fn ser_value(value: &V) -> Result<KvBytes, DALError> {
+        bincode::serialize(value).map_err(|_| DALError::WriteError)
+    }
+    pub(crate) fn de_value(bytes: &[u8]) -> Result<V, DALError> {
+        bincode::deserialize(bytes).map_err(|_| DALError::ReadError)
+    }
+    /// Get an entry
+    pub fn get<S: KvStore>(&self, db: &S, key: &K) -> Result<Option<V>, DALError> {
+        if let Some(bytes) = db.get(self.name, &Self::ser_key(key)?)? {
+            Ok(Some(Self::de_value(&bytes)?))
+        } else {
+            Ok(None)
+        }
+    }
+    /// Get the entries whose key is in `[from, to[`, sorted by key
+    pub fn range<S: KvStore>(
+        &self,
+        db: &S,
+        from: Option<&K>,
+        to: Option<&K>,
+    ) -> Result<Vec<(K, V)>, DALError> {
+        let from = if let Some(from) = from {
+            Some(Self::ser_key(from)?)
+        } else {
+            None
+        };
+        let to = if let Some(to) = to {
+            Some(Self::ser_key(to)?)
+        } else {
+            None
+        };
+        db.range(
+            self.name,
+            from.as_ref().map(Vec::as_slice),
+            to.as_ref().map(Vec::as_slice),
+        )?
+        .into_iter()
+        .map(|(k, v)| Ok((Self::de_key(&k)?, Self::de_value(&v)?)))
+        .collect()
+    }
+    /// Number of entries
+    pub fn count<S: KvStore>(&self, db: &S) -> Result<usize, DALError> {
+        db.count(self.name)
+    }
+    /// Add the insertion (or replacement) of an entry to a batch
+    pub fn batch_put(&self, batch: &mut WriteBatch, key: &K, value: &V) -> Result<(), DALError> {
+        batch.put(self.name, Self::ser_key(key)?, Self::ser_value(value)?);
+        Ok(())
+    }
+    /// Add the removal of an entry to a batch
+    pub fn batch_delete(&self, batch: &mut WriteBatch, key: &K) -> Result<(), DALError> {
+        batch.delete(self.name, Self::ser_key(key)?);
+        Ok(())
+    }
+    /// Insert or replace an entry
+    pub fn put<S: KvStore>(&self, db: &S, key: &K, value: &V) -> Result<(), DALError> {
+        let mut batch = WriteBatch::new();
+        self.batch_put(&mut batch, key, value)?;
+        db.write_batch(batch)
+    }
+    /// Remove an entry
+    pub fn delete<S: KvStore>(&self, db: &S, key: &K) -> Result<(), DALError> {
+        let mut batch = WriteBatch::new();
+        self.batch_delete(&mut batch, key)?;
+        db.write_batch(batch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dubp_documents::BlockNumber;
+
+    static BLOCKS: KvCollection<BlockNumber, String> = KvCollection::new("blocks");
+
+    #[test]
+    fn test_kv_collection_sorted_keys() {
+        let db = MemKvStore::default();
+        let mut batch = WriteBatch::new();
+        for n in &[256u32, 1, 255, 65_536] {
+            BLOCKS
+                .batch_put(&mut batch, &BlockNumber(*n), &n.to_string())
+                .expect("fail to serialize");
+        }
+        db.write_batch(batch).expect("fail to write batch");
+        assert_eq!(
+            Some("255".to_owned()),
+            BLOCKS.get(&db, &BlockNumber(255)).expect("fail to get")
+        );
+        assert_eq!(
+            vec![
+                (BlockNumber(255), "255".to_owned()),
+                (BlockNumber(256), "256".to_owned())
+            ],
+            BLOCKS
+                .range(&db, Some(&BlockNumber(2)), Some(&BlockNumber(65_536)))
+                .expect("fail to scan")
+        );
+        BLOCKS.delete(&db, &BlockNumber(1)).expect("fail to delete");
+        assert_eq!(3, BLOCKS.count(&db).expect("fail to count"));
+    }
+}
@@ -0,0 +1,746 @@
+//  Copyright (C) 2018  The Duniter Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! The datas are stored in an append-only log: each write batch is appended as a single
+//! checksummed record, so a save only writes the modified entries. Only the keys are kept in
+//! memory, with the position of their value in the log.
+//!
+//! A record interrupted by a crash is detected by its checksum and discarded when opening the
+//! store. Overwritten entries are reclaimed by a compaction of the log.
+//!
+//! Other modules can open a store in read-only mode while its owner writes in it: they only
+//! read the records appended since their last refresh.
+
+use super::memory::{bounds, is_empty_range};
+use super::*;
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::hash::Hasher;
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Magic bytes at the beginning of a key-value store file
+static MAGIC: &'static [u8] = b"DURSKV01";
+/// Size of a record header (payload length and checksum)
+static RECORD_HEADER_SIZE: &'static u64 = &12;
+/// Size of an entry without its collection name, key and value
+static ENTRY_OVERHEAD: &'static u64 = &11;
+/// Minimal size of overwritten entries to trigger a compaction when opening the store
+static COMPACTION_MIN_DEAD_BYTES: &'static u64 = &(16 * 1024 * 1024);
+/// Maximal number of entries in a record written by the compaction
+static COMPACTION_RECORD_MAX_ENTRIES: &'static usize = &1_000;
+
+const OP_PUT: u8 = 0;
+const OP_DELETE: u8 = 1;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// Position of a value in the log
+struct ValuePos {
+    offset: u64,
+    len: u32,
+}
+
+#[derive(Debug)]
+/// Operation decoded from a record
+enum LogOp {
+    Put {
+        collection: String,
+        key: KvBytes,
+        pos: ValuePos,
+    },
+    Delete {
+        collection: String,
+        key: KvBytes,
+    },
+}
+
+#[derive(Debug, Default)]
+/// Keys of all the entries, with the position of their value
+struct LogIndex {
+    collections: BTreeMap<String, BTreeMap<KvBytes, ValuePos>>,
+    live_bytes: u64,
+    dead_bytes: u64,
+}
+
+impl LogIndex {
+    fn apply(&mut self, op: LogOp) {
+        match op {
+            LogOp::Put {
+                collection,
+                key,
+                pos,
+            } => {
+                let entry_size =
+                    *ENTRY_OVERHEAD + (collection.len() + key.len()) as u64 + u64::from(pos.len);
+                let old_pos = self
+                    .collections
+                    .entry(collection.clone())
+                    .or_insert_with(BTreeMap::new)
+                    .insert(key.clone(), pos);
+                if let Some(old_pos) = old_pos {
+                    self.forget(&collection, &key, old_pos);
+                }
+                self.live_bytes += entry_size;
+            }
+            LogOp::Delete { collection, key } => {
+                let old_pos = self
+                    .collections
+                    .get_mut(&collection)
+                    .and_then(|entries| entries.remove(&key));
+                if let Some(old_pos) = old_pos {
+                    self.forget(&collection, &key, old_pos);
+                }
+            }
+        }
+    }
+    fn forget(&mut self, collection: &str, key: &[u8], pos: ValuePos) {
+        let entry_size =
+            *ENTRY_OVERHEAD + (collection.len() + key.len()) as u64 + u64::from(pos.len);
+        self.live_bytes -= entry_size;
+        self.dead_bytes += entry_size;
+    }
+}
+
+#[derive(Debug)]
+struct FileKvInner {
+    /// None in read-only mode while the owner has not created the log yet
+    file: Option<File>,
+    end: u64,
+    index: LogIndex,
+}
+
+impl FileKvInner {
+    fn file(&mut self) -> Result<&mut File, DALError> {
+        self.file.as_mut().ok_or(DALError::ReadError)
+    }
+}
+
+#[derive(Debug)]
+/// Embedded on-disk key-value store
+pub struct FileKvStore {
+    path: PathBuf,
+    read_only: bool,
+    inner: Mutex<FileKvInner>,
+}
+
+fn checksum(payload: &[u8]) -> u64 {
+    let mut hasher = fnv::FnvHasher::default();
+    hasher.write(payload);
+    hasher.finish()
+}
+
+fn encode_bytes(buffer: &mut Vec<u8>, bytes: &[u8]) -> Result<(), DALError> {
+    if bytes.len() > u32::max_value() as usize {
+        return Err(DALError::WriteError);
+    }
+    buffer.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    buffer.extend_from_slice(bytes);
+    Ok(())
+}
+
+fn encode_collection(buffer: &mut Vec<u8>, collection: &str) -> Result<(), DALError> {
+    if collection.len() > u16::max_value() as usize {
+        return Err(DALError::WriteError);
+    }
+    buffer.extend_from_slice(&(collection.len() as u16).to_le_bytes());
+    buffer.extend_from_slice(collection.as_bytes());
+    Ok(())
+}
+
+/// Encode a batch in a record (header included)
+fn encode_record(ops: &[KvOp]) -> Result<Vec<u8>, DALError> {
+    let mut payload = Vec::new();
+    for op in ops {
+        match *op {
+            KvOp::Put {
+                ref collection,
+                ref key,
+                ref value,
+            } => {
+                payload.push(OP_PUT);
+                encode_collection(&mut payload, collection)?;
+                encode_bytes(&mut payload, key)?;
+                encode_bytes(&mut payload, value)?;
+            }
+            KvOp::Delete {
+                ref collection,
+                ref key,
+            } => {
+                payload.push(OP_DELETE);
+                encode_collection(&mut payload, collection)?;
+                encode_bytes(&mut payload, key)?;
+            }
+        }
+    }
+    if payload.len() > u32::max_value() as usize {
+        return Err(DALError::WriteError);
+    }
+    let mut record = Vec::with_capacity(payload.len() + *RECORD_HEADER_SIZE as usize);
+    record.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    record.extend_from_slice(&checksum(&payload).to_le_bytes());
+    record.extend_from_slice(&payload);
+    Ok(record)
+}
+
+/// Cursor on a record payload
+struct PayloadReader<'a> {
+    payload: &'a [u8],
+    cursor: usize,
+}
+
+impl<'a> PayloadReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], DALError> {
+        if self.payload.len() - self.cursor < len {
+            return Err(DALError::DBCorrupted);
+        }
+        let bytes = &self.payload[self.cursor..self.cursor + len];
+        self.cursor += len;
+        Ok(bytes)
+    }
+    fn read_u16(&mut self) -> Result<u16, DALError> {
+        let mut bytes = [0u8; 2];
+        bytes.copy_from_slice(self.take(2)?);
+        Ok(u16::from_le_bytes(bytes))
+    }
+    fn read_u32(&mut self) -> Result<u32, DALError> {
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+    fn read_collection(&mut self) -> Result<String, DALError> {
+        let len = self.read_u16()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| DALError::DBCorrupted)
+    }
+    fn read_bytes(&mut self) -> Result<&'a [u8], DALError> {
+        let len = self.read_u32()? as usize;
+        self.take(len)
+    }
+}
+
+/// Decode the payload of a record written at `payload_offset`
+fn decode_payload(payload: &[u8], payload_offset: u64) -> Result<Vec<LogOp>, DALError> {
+    let mut reader = PayloadReader { payload, cursor: 0 };
+    let mut ops = Vec::new();
+    while reader.cursor < payload.len() {
+        let op_type = reader.take(1)?[0];
+        let collection = reader.read_collection()?;
+        let key = reader.read_bytes()?.to_vec();
+        match op_type {
+            OP_PUT => {
+                let len = reader.read_u32()?;
+                let offset = payload_offset + reader.cursor as u64;
+                reader.take(len as usize)?;
+                ops.push(LogOp::Put {
+                    collection,
+                    key,
+                    pos: ValuePos { offset, len },
+                });
+            }
+            OP_DELETE => ops.push(LogOp::Delete { collection, key }),
+            _ => return Err(DALError::DBCorrupted),
+        }
+    }
+    Ok(ops)
+}
+
+/// Record read in the log
+enum RecordRead {
+    /// Valid record (end offset of the record and its operations)
+    Record(u64, Vec<LogOp>),
+    /// End of the log (or record torn by an interrupted write at the end of the log)
+    End,
+}
+
+/// Read the record written at `record_offset` in a log of `file_len` bytes.
+///
+/// An incomplete or invalid record is a torn write only if it is the last one of the log,
+/// the log is corrupted if there are datas after it.
+fn read_record<R: Read>(
+    reader: &mut R,
+    record_offset: u64,
+    file_len: u64,
+) -> Result<RecordRead, DALError> {
+    if file_len.saturating_sub(record_offset) < *RECORD_HEADER_SIZE {
+        return Ok(RecordRead::End);
+    }
+    let mut header = [0u8; 12];
+    reader.read_exact(&mut header)?;
+    let mut len_bytes = [0u8; 4];
+    len_bytes.copy_from_slice(&header[..4]);
+    let mut checksum_bytes = [0u8; 8];
+    checksum_bytes.copy_from_slice(&header[4..]);
+    let payload_len = u32::from_le_bytes(len_bytes);
+    let payload_offset = record_offset + *RECORD_HEADER_SIZE;
+    let record_end = payload_offset + u64::from(payload_len);
+    if record_end > file_len {
+        // The payload is not entirely written
+        return Ok(RecordRead::End);
+    }
+    let mut payload = vec![0u8; payload_len as usize];
+    reader.read_exact(&mut payload)?;
+    let ops = if checksum(&payload) == u64::from_le_bytes(checksum_bytes) {
+        decode_payload(&payload, payload_offset).ok()
+    } else {
+        None
+    };
+    match ops {
+        Some(ops) => Ok(RecordRead::Record(record_end, ops)),
+        None if record_end == file_len => Ok(RecordRead::End),
+        None => Err(DALError::DBCorrupted),
+    }
+}
+
+/// Read the records written after `end` and apply them to the index, return the new end
+fn read_records(file: &mut File, index: &mut LogIndex, mut end: u64) -> Result<u64, DALError> {
+    let file_len = file.metadata()?.len();
+    file.seek(SeekFrom::Start(end))?;
+    let mut reader = BufReader::new(file);
+    while let RecordRead::Record(record_end, ops) = read_record(&mut reader, end, file_len)? {
+        for op in ops {
+            index.apply(op);
+        }
+        end = record_end;
+    }
+    Ok(end)
+}
+
+/// Return true if `path` is still the file opened in `file` (it changes with a compaction)
+#[cfg(unix)]
+fn is_same_file(file: &File, path: &Path) -> Result<bool, DALError> {
+    use std::os::unix::fs::MetadataExt;
+    let opened = file.metadata()?;
+    let current = fs::metadata(path)?;
+    Ok(opened.dev() == current.dev() && opened.ino() == current.ino())
+}
+
+/// Return true if `path` is still the file opened in `file` (it changes with a compaction)
+#[cfg(not(unix))]
+fn is_same_file(file: &File, path: &Path) -> Result<bool, DALError> {
+    Ok(file.metadata()?.len() <= fs::metadata(path)?.len())
+}
+
+/// Open a log file and rebuild its index.
+///
+/// A record torn by an interrupted write at the end of the log is discarded, except in
+/// read-only mode (it may be a record being written by the owner of the store).
+fn load_log(path: &Path, read_only: bool) -> Result<FileKvInner, DALError> {
+    let mut file = if read_only {
+        match File::open(path) {
+            Ok(file) => file,
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => {
+                return Ok(FileKvInner {
+                    file: None,
+                    end: MAGIC.len() as u64,
+                    index: LogIndex::default(),
+                });
+            }
+            Err(err) => return Err(err.into()),
+        }
+    } else {
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(path)?
+    };
+    let file_len = file.metadata()?.len();
+    if file_len < MAGIC.len() as u64 {
+        if read_only {
+            // The owner of the store is creating it
+            return Ok(FileKvInner {
+                file: None,
+                end: MAGIC.len() as u64,
+                index: LogIndex::default(),
+            });
+        }
+        file.set_len(0)?;
+        file.write_all(MAGIC)?;
+        file.sync_all()?;
+        return Ok(FileKvInner {
+            file: Some(file),
+            end: MAGIC.len() as u64,
+            index: LogIndex::default(),
+        });
+    }
+    let mut magic = [0u8; 8];
+    if file.read_exact(&mut magic).is_err() || magic != MAGIC {
+        return Err(DALError::DBCorrupted);
+    }
+    let mut index = LogIndex::default();
+    let end = read_records(&mut file, &mut index, MAGIC.len() as u64)?;
+    if end < file_len && !read_only {
+        warn!(
+            "Discard the last {} bytes of {:?}: incomplete write.",
+            file_len - end,
+            path
+        );
+        file.set_len(end)?;
+        file.sync_all()?;
+    }
+    Ok(FileKvInner {
+        file: Some(file),
+        end,
+        index,
+    })
+}
+
+/// Read the value at `pos` in a log whose valid records end at `end`
+fn read_value(file: &mut File, end: u64, pos: ValuePos) -> Result<KvBytes, DALError> {
+    if pos
+        .offset
+        .checked_add(u64::from(pos.len))
+        .map_or(true, |value_end| value_end > end)
+    {
+        return Err(DALError::DBCorrupted);
+    }
+    let mut value = vec![0u8; pos.len as usize];
+    file.seek(SeekFrom::Start(pos.offset))?;
+    file.read_exact(&mut value)?;
+    Ok(value)
+}
+
+impl FileKvStore {
+    /// Open (or create) the store at `path`
+    pub fn open(path: PathBuf) -> Result<FileKvStore, DALError> {
+        let inner = load_log(&path, false)?;
+        let store = FileKvStore {
+            path,
+            read_only: false,
+            inner: Mutex::new(inner),
+        };
+        store.compact_if_needed()?;
+        Ok(store)
+    }
+    /// Open (or create) the store at `path` in read-only mode
+    pub fn open_read_only(path: PathBuf) -> Result<FileKvStore, DALError> {
+        let inner = load_log(&path, true)?;
+        Ok(FileKvStore {
+            path,
+            read_only: true,
+            inner: Mutex::new(inner),
+        })
+    }
+    /// Read the records written by the owner of the store since the last refresh
+    pub fn refresh(&self) -> Result<(), DALError> {
+        let mut inner = self.inner.lock().map_err(|_| DALError::DBCorrupted)?;
+        let reload = match inner.file {
+            // The log has been compacted
+            Some(ref file) => !is_same_file(file, &self.path)?,
+            // The log did not exist yet
+            None => true,
+        };
+        if reload {
+            *inner = load_log(&self.path, self.read_only)?;
+            return Ok(());
+        }
+        let inner = &mut *inner;
+        let end = inner.end;
+        let file = inner.file.as_mut().ok_or(DALError::ReadError)?;
+        inner.end = read_records(file, &mut inner.index, end)?;
+        Ok(())
+    }
+    /// Compact the log if the overwritten entries take more space than the current ones
+    fn compact_if_needed(&self) -> Result<(), DALError> {
+        let need_compaction = {
+            let inner = self.inner.lock().map_err(|_| DALError::DBCorrupted)?;
+            inner.index.dead_bytes > *COMPACTION_MIN_DEAD_BYTES
+                && inner.index.dead_bytes > inner.index.live_bytes
+        };
+        if need_compaction {
+            self.compact()?;
+        }
+        Ok(())
+    }
+    /// Size of the overwritten or deleted entries still present in the log
+    pub fn dead_bytes(&self) -> Result<u64, DALError> {
+        let inner = self.inner.lock().map_err(|_| DALError::DBCorrupted)?;
+        Ok(inner.index.dead_bytes)
+    }
+    /// Rewrite the log with only the current entries
+    pub fn compact(&self) -> Result<(), DALError> {
+        let mut inner = self.inner.lock().map_err(|_| DALError::DBCorrupted)?;
+        let mut tmp_path = self.path.clone();
+        tmp_path.set_extension("compact");
+        {
+            let mut tmp_file = File::create(&tmp_path)?;
+            tmp_file.write_all(MAGIC)?;
+            let positions: Vec<(String, KvBytes, ValuePos)> = inner
+                .index
+                .collections
+                .iter()
+                .flat_map(|(collection, entries)| {
+                    entries
+                        .iter()
+                        .map(move |(key, pos)| (collection.clone(), key.clone(), *pos))
+                })
+                .collect();
+            for chunk in positions.chunks(*COMPACTION_RECORD_MAX_ENTRIES) {
+                let mut ops = Vec::with_capacity(chunk.len());
+                for (collection, key, pos) in chunk {
+                    let end = inner.end;
+                    ops.push(KvOp::Put {
+                        collection: collection.clone(),
+                        key: key.clone(),
+                        value: read_value(inner.file()?, end, *pos)?,
+                    });
+                }
+                tmp_file.write_all(&encode_record(&ops)?)?;
+            }
+            tmp_file.sync_all()?;
+        }
+        fs::rename(&tmp_path, &self.path)?;
+        *inner = load_log(&self.path, false)?;
+        Ok(())
+    }
+}
+
+impl KvStore for FileKvStore {
+    fn get(&self, collection: &str, key: &[u8]) -> Result<Option<KvBytes>, DALError> {
+        let mut inner = self.inner.lock().map_err(|_| DALError::DBCorrupted)?;
+        let pos = inner
+            .index
+            .collections
+            .get(collection)
+            .and_then(|entries| entries.get(key))
+            .cloned();
+        if let Some(pos) = pos {
+            let end = inner.end;
+            Ok(Some(read_value(inner.file()?, end, pos)?))
+        } else {
+            Ok(None)
+        }
+    }
+    fn contains(&self, collection: &str, key: &[u8]) -> Result<bool, DALError> {
+        let inner = self.inner.lock().map_err(|_| DALError::DBCorrupted)?;
+        Ok(inner
+            .index
+            .collections
+            .get(collection)
+            .map_or(false, |entries| entries.contains_key(key)))
+    }
+    fn range_limit(
+        &self,
+        collection: &str,
+        from: Option<&[u8]>,
+        to: Option<&[u8]>,
+        limit: Option<usize>,
+    ) -> Result<Vec<(KvBytes, KvBytes)>, DALError> {
+        if is_empty_range(from, to) {
+            return Ok(vec![]);
+        }
+        let mut inner = self.inner.lock().map_err(|_| DALError::DBCorrupted)?;
+        let positions: Vec<(KvBytes, ValuePos)> =
+            if let Some(entries) = inner.index.collections.get(collection) {
+                entries
+                    .range(bounds(from, to))
+                    .take(limit.unwrap_or(usize::max_value()))
+                    .map(|(k, pos)| (k.clone(), *pos))
+                    .collect()
+            } else {
+                return Ok(vec![]);
+            };
+        let mut entries = Vec::with_capacity(positions.len());
+        let end = inner.end;
+        for (key, pos) in positions {
+            entries.push((key, read_value(inner.file()?, end, pos)?));
+        }
+        Ok(entries)
+    }
+    fn count(&self, collection: &str) -> Result<usize, DALError> {
+        let inner = self.inner.lock().map_err(|_| DALError::DBCorrupted)?;
+        Ok(inner
+            .index
+            .collections
+            .get(collection)
+            .map_or(0, BTreeMap::len))
+    }
+    fn write_batch(&self, batch: WriteBatch) -> Result<(), DALError> {
+        if self.read_only {
+            return Err(DALError::WriteError);
+        }
+        if batch.is_empty() {
+            return Ok(());
+        }
+        let record = encode_record(&batch.ops)?;
+        let mut inner = self.inner.lock().map_err(|_| DALError::DBCorrupted)?;
+        let record_offset = inner.end;
+        {
+            let file = inner.file()?;
+            file.seek(SeekFrom::Start(record_offset))?;
+            if let Err(err) = file.write_all(&record) {
+                // Remove the partially written record
+                let _ = file.set_len(record_offset);
+                return Err(err.into());
+            }
+        }
+        inner.end = record_offset + record.len() as u64;
+        let payload_offset = record_offset + *RECORD_HEADER_SIZE;
+        for op in decode_payload(&record[*RECORD_HEADER_SIZE as usize..], payload_offset)? {
+            inner.index.apply(op);
+        }
+        Ok(())
+    }
+    fn save(&self) -> Result<(), DALError> {
+        if self.read_only {
+            return Ok(());
+        }
+        self.inner
+            .lock()
+            .map_err(|_| DALError::DBCorrupted)?
+            .file()?
+            .sync_data()?;
+        self.compact_if_needed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tmp_db_path(name: &str) -> PathBuf {
+        let mut path = std::env::temp_dir();
+        path.push(format!("durs-kv-{}-{}", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn fill(db: &FileKvStore) {
+        let mut batch = WriteBatch::new();
+        for i in 0u8..10 {
+            batch.put("blocks", vec![i], vec![i; 100]);
+        }
+        batch.put("idty", b"alice".to_vec(), b"A".to_vec());
+        db.write_batch(batch).expect("fail to write batch");
+        db.put("blocks", &[3], &[33]).expect("fail to put");
+        db.delete("blocks", &[4]).expect("fail to delete");
+        db.save().expect("fail to save");
+    }
+
+    fn check(db: &FileKvStore) {
+        assert_eq!(9, db.count("blocks").expect("fail to count"));
+        assert_eq!(Some(vec![33]), db.get("blocks", &[3]).expect("fail to get"));
+        assert_eq!(None, db.get("blocks", &[4]).expect("fail to get"));
+        assert_eq!(
+            Some(b"A".to_vec()),
+            db.get("idty", b"alice").expect("fail to get")
+        );
+        let entries = db
+            .range("blocks", Some(&[2]), Some(&[6]))
+            .expect("fail to scan");
+        assert_eq!(
+            vec![
+                (vec![2], vec![2; 100]),
+                (vec![3], vec![33]),
+                (vec![5], vec![5; 100])
+            ],
+            entries
+        );
+    }
+
+    #[test]
+    fn test_file_kv_store_reopen() {
+        let path = tmp_db_path("reopen");
+        fill(&FileKvStore::open(path.clone()).expect("fail to open db"));
+        let db = FileKvStore::open(path.clone()).expect("fail to reopen db");
+        check(&db);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_file_kv_store_discard_incomplete_record() {
+        let path = tmp_db_path("incomplete");
+        let db = FileKvStore::open(path.clone()).expect("fail to open db");
+        fill(&db);
+        db.put("idty", b"bob", b"B").expect("fail to put");
+        drop(db);
+        // Simulate a crash during the last write
+        let file_len = fs::metadata(&path).expect("fail to get db size").len();
+        OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .expect("fail to open db file")
+            .set_len(file_len - 1)
+            .expect("fail to truncate db file");
+        let db = FileKvStore::open(path.clone()).expect("fail to reopen db");
+        check(&db);
+        assert_eq!(None, db.get("idty", b"bob").expect("fail to get"));
+        db.put("idty", b"bob", b"B").expect("fail to put");
+        drop(db);
+        let db = FileKvStore::open(path.clone()).expect("fail to reopen db");
+        assert_eq!(
+            Some(b"B".to_vec()),
+            db.get("idty", b"bob").expect("fail to get")
+        );
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_file_kv_store_detect_corrupted_record() {
+        let path = tmp_db_path("corrupted");
+        fill(&FileKvStore::open(path.clone()).expect("fail to open db"));
+        // Corrupt the payload of the first record (written after the magic number)
+        let mut datas = fs::read(&path).expect("fail to read db file");
+        datas[MAGIC.len() + *RECORD_HEADER_SIZE as usize + 1] ^= 0xff;
+        fs::write(&path, &datas).expect("fail to write db file");
+        assert_eq!(
+            Some(DALError::DBCorrupted),
+            FileKvStore::open(path.clone()).err()
+        );
+        // The records written after the corrupted one are kept
+        assert_eq!(
+            datas.len() as u64,
+            fs::metadata(&path).expect("fail to get db size").len()
+        );
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_file_kv_store_read_only_refresh() {
+        let path = tmp_db_path("read-only");
+        let db = FileKvStore::open(path.clone()).expect("fail to open db");
+        let reader = FileKvStore::open_read_only(path.clone()).expect("fail to open db");
+        assert!(reader.put("idty", b"bob", b"B").is_err());
+        fill(&db);
+        assert_eq!(0, reader.count("blocks").expect("fail to count"));
+        reader.refresh().expect("fail to refresh");
+        check(&reader);
+        db.compact().expect("fail to compact db");
+        db.put("idty", b"bob", b"B").expect("fail to put");
+        reader.refresh().expect("fail to refresh");
+        check(&reader);
+        assert_eq!(
+            Some(b"B".to_vec()),
+            reader.get("idty", b"bob").expect("fail to get")
+        );
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_file_kv_store_compaction() {
+        let path = tmp_db_path("compaction");
+        let db = FileKvStore::open(path.clone()).expect("fail to open db");
+        fill(&db);
+        assert!(db.dead_bytes().expect("fail to get dead bytes") > 0);
+        let file_len = fs::metadata(&path).expect("fail to get db size").len();
+        db.compact().expect("fail to compact db");
+        assert_eq!(0, db.dead_bytes().expect("fail to get dead bytes"));
+        assert!(fs::metadata(&path).expect("fail to get db size").len() < file_len);
+        check(&db);
+        drop(db);
+        check(&FileKvStore::open(path.clone()).expect("fail to reopen db"));
+        let _ = fs::remove_file(&path);
+    }
+}
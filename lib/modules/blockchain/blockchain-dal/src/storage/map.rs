@@ -0,0 +1,265 @@
+//  Copyright (C) 2018  The Duniter Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::collection::{prefix_end, ser_key_prefix};
+use super::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt;
+use std::sync::Arc;
+
+/// Map of entries of type `V` indexed by keys of type `K`, stored in a collection
+/// of a staged key-value database.
+///
+/// Each read or write only concerns the requested entries: the map is never loaded
+/// entirely in memory.
+pub struct KvMapDB<K, V> {
+    db: Arc<StagedKvDB>,
+    collection: KvCollection<K, V>,
+}
+
+impl<K, V> Debug for KvMapDB<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("KvMapDB")
+            .field("file_name", &self.db.file_name())
+            .field("collection", &self.collection.name)
+            .finish()
+    }
+}
+
+impl<K: Serialize + DeserializeOwned, V: Serialize + DeserializeOwned> KvMapDB<K, V> {
+    /// Map stored in the collection `collection` of `db`
+    pub fn new(db: Arc<StagedKvDB>, collection: &'static str) -> Self {
+        KvMapDB {
+            db,
+            collection: KvCollection::new(collection),
+        }
+    }
+    /// Open a map stored alone in the file `db_file_name` (in memory if no folder is given)
+    pub fn open(
+        dbs_folder_path: Option<&PathBuf>,
+        db_file_name: &str,
+        collection: &'static str,
+    ) -> Result<Self, DALError> {
+        Ok(KvMapDB::new(
+            Arc::new(StagedKvDB::open(dbs_folder_path, db_file_name)?),
+            collection,
+        ))
+    }
+    /// Open a map stored alone in the file `db_file_name` in read-only mode
+    pub fn open_read_only(
+        dbs_folder_path: Option<&PathBuf>,
+        db_file_name: &str,
+        collection: &'static str,
+    ) -> Result<Self, DALError> {
+        Ok(KvMapDB::new(
+            Arc::new(StagedKvDB::open_read_only(dbs_folder_path, db_file_name)?),
+            collection,
+        ))
+    }
+    /// Database storing the map
+    pub fn db(&self) -> &StagedKvDB {
+        &self.db
+    }
+    /// Get an entry
+    pub fn get(&self, key: &K) -> Result<Option<V>, DALError> {
+        self.collection.get(&*self.db, key)
+    }
+    /// Return true if the map contains an entry for `key`
+    pub fn contains_key(&self, key: &K) -> Result<bool, DALError> {
+        self.db
+            .contains(self.collection.name, &KvCollection::<K, V>::ser_key(key)?)
+    }
+    /// Number of entries
+    pub fn len(&self) -> Result<usize, DALError> {
+        self.collection.count(&*self.db)
+    }
+    /// Return true if the map has no entry
+    pub fn is_empty(&self) -> Result<bool, DALError> {
+        Ok(self.len()? == 0)
+    }
+    /// Insert or replace an entry
+    pub fn insert(&self, key: &K, value: &V) -> Result<(), DALError> {
+        self.collection.put(&*self.db, key, value)
+    }
+    /// Insert or replace several entries
+    pub fn extend<I: IntoIterator<Item = (K, V)>>(&self, entries: I) -> Result<(), DALError> {
+        let mut batch = WriteBatch::new();
+        for (key, value) in entries {
+            self.collection.batch_put(&mut batch, &key, &value)?;
+        }
+        self.db.write_batch(batch)
+    }
+    /// Remove an entry, return its value
+    pub fn remove(&self, key: &K) -> Result<Option<V>, DALError> {
+        let value = self.get(key)?;
+        if value.is_some() {
+            self.collection.delete(&*self.db, key)?;
+        }
+        Ok(value)
+    }
+    /// Remove an entry (without reading its value)
+    pub fn delete(&self, key: &K) -> Result<(), DALError> {
+        self.collection.delete(&*self.db, key)
+    }
+    /// Modify an entry: `f` gets its current value (`None` if it does not exist) and can
+    /// replace it (with `None` to remove the entry)
+    pub fn update<F, R>(&self, key: &K, f: F) -> Result<R, DALError>
+    where
+        F: FnOnce(&mut Option<V>) -> R,
+    {
+        let mut value = self.get(key)?;
+        let r = f(&mut value);
+        if let Some(value) = value {
+            self.insert(key, &value)?;
+        } else {
+            self.collection.delete(&*self.db, key)?;
+        }
+        Ok(r)
+    }
+    /// Get the entries whose key is in `[from, to[`, sorted by key
+    pub fn range(&self, from: Option<&K>, to: Option<&K>) -> Result<Vec<(K, V)>, DALError> {
+        self.collection.range(&*self.db, from, to)
+    }
+    /// Get the entries whose key starts with the fields `prefix` of a tuple key, sorted by key
+    pub fn range_prefix<P: Serialize>(&self, prefix: &P) -> Result<Vec<(K, V)>, DALError> {
+        let from = ser_key_prefix(prefix)?;
+        let to = prefix_end(&from);
+        self.raw_range(Some(&from), to.as_ref().map(Vec::as_slice))
+    }
+    /// Get the entries whose key first fields are in `[from, to[`, sorted by key
+    pub fn range_by_prefix<P: Serialize>(
+        &self,
+        from: Option<&P>,
+        to: Option<&P>,
+    ) -> Result<Vec<(K, V)>, DALError> {
+        let from = if let Some(from) = from {
+            Some(ser_key_prefix(from)?)
+        } else {
+            None
+        };
+        let to = if let Some(to) = to {
+            Some(ser_key_prefix(to)?)
+        } else {
+            None
+        };
+        self.raw_range(
+            from.as_ref().map(Vec::as_slice),
+            to.as_ref().map(Vec::as_slice),
+        )
+    }
+    /// Get the `limit` first entries whose key first fields are in `[from, to]`, sorted by key
+    pub fn range_between_prefixes<P: Serialize>(
+        &self,
+        from: &P,
+        to: &P,
+        limit: usize,
+    ) -> Result<Vec<(K, V)>, DALError> {
+        let from = ser_key_prefix(from)?;
+        let to = prefix_end(&ser_key_prefix(to)?);
+        self.raw_range_limit(Some(&from), to.as_ref().map(Vec::as_slice), Some(limit))
+    }
+    fn raw_range(&self, from: Option<&[u8]>, to: Option<&[u8]>) -> Result<Vec<(K, V)>, DALError> {
+        self.raw_range_limit(from, to, None)
+    }
+    fn raw_range_limit(
+        &self,
+        from: Option<&[u8]>,
+        to: Option<&[u8]>,
+        limit: Option<usize>,
+    ) -> Result<Vec<(K, V)>, DALError> {
+        self.db
+            .range_limit(self.collection.name, from, to, limit)?
+            .into_iter()
+            .map(|(k, v)| {
+                Ok((
+                    KvCollection::<K, V>::de_key(&k)?,
+                    KvCollection::<K, V>::de_value(&v)?,
+                ))
+            })
+            .collect()
+    }
+    /// Get all the entries, sorted by key
+    pub fn entries(&self) -> Result<Vec<(K, V)>, DALError> {
+        self.range(None, None)
+    }
+    /// Write the staged modifications in the database file
+    pub fn save(&self) -> Result<(), DALError> {
+        self.db.commit()
+    }
+    /// Read the datas written by the owner of the database since the last refresh
+    /// (for databases opened in read-only mode)
+    pub fn refresh(&self) -> Result<(), DALError> {
+        self.db.refresh()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dubp_documents::BlockNumber;
+
+    #[test]
+    fn test_kv_map_db() {
+        let map: KvMapDB<(BlockNumber, u8), String> =
+            KvMapDB::open(None, "", "test").expect("fail to open map");
+        map.extend(vec![
+            ((BlockNumber(1), 1), "a".to_owned()),
+            ((BlockNumber(1), 2), "b".to_owned()),
+            ((BlockNumber(256), 1), "c".to_owned()),
+            ((BlockNumber(300), 1), "d".to_owned()),
+        ])
+        .expect("fail to insert");
+        assert_eq!(4, map.len().expect("fail to count"));
+        assert_eq!(
+            vec!["a".to_owned(), "b".to_owned()],
+            map.range_prefix(&BlockNumber(1))
+                .expect("fail to scan")
+                .into_iter()
+                .map(|(_, v)| v)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![(BlockNumber(256), 1)],
+            map.range_by_prefix(Some(&BlockNumber(2)), Some(&BlockNumber(300)))
+                .expect("fail to scan")
+                .into_iter()
+                .map(|(k, _)| k)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![(BlockNumber(1), 2), (BlockNumber(256), 1)],
+            map.range_between_prefixes(&(BlockNumber(1), 2u8), &(BlockNumber(300), 1u8), 2)
+                .expect("fail to scan")
+                .into_iter()
+                .map(|(k, _)| k)
+                .collect::<Vec<_>>()
+        );
+        map.update(&(BlockNumber(1), 1), |v| *v = None)
+            .expect("fail to update");
+        map.update(&(BlockNumber(1), 2), |v| {
+            if let Some(v) = v {
+                v.push('b')
+            }
+        })
+        .expect("fail to update");
+        assert_eq!(
+            Some("bb".to_owned()),
+            map.remove(&(BlockNumber(1), 2)).expect("fail to remove")
+        );
+        assert!(!map.contains_key(&(BlockNumber(1), 2)).expect("fail to get"));
+        assert_eq!(2, map.len().expect("fail to count"));
+    }
+}
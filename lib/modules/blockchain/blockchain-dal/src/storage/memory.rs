@@ -0,0 +1,141 @@
+//  Copyright (C) 2018  The Duniter Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+use std::collections::BTreeMap;
+use std::ops::Bound;
+use std::sync::RwLock;
+
+#[derive(Debug, Default)]
+/// In-memory key-value store
+pub struct MemKvStore {
+    collections: RwLock<BTreeMap<String, BTreeMap<KvBytes, KvBytes>>>,
+}
+
+/// Bounds of a range scan
+pub(crate) fn bounds(from: Option<&[u8]>, to: Option<&[u8]>) -> (Bound<KvBytes>, Bound<KvBytes>) {
+    (
+        from.map_or(Bound::Unbounded, |k| Bound::Included(k.to_vec())),
+        to.map_or(Bound::Unbounded, |k| Bound::Excluded(k.to_vec())),
+    )
+}
+
+/// Return true if the range `[from, to[` is empty
+pub(crate) fn is_empty_range(from: Option<&[u8]>, to: Option<&[u8]>) -> bool {
+    match (from, to) {
+        (Some(from), Some(to)) => from >= to,
+        _ => false,
+    }
+}
+
+impl KvStore for MemKvStore {
+    fn get(&self, collection: &str, key: &[u8]) -> Result<Option<KvBytes>, DALError> {
+        let collections = self.collections.read().map_err(|_| DALError::DBCorrupted)?;
+        Ok(collections
+            .get(collection)
+            .and_then(|entries| entries.get(key))
+            .cloned())
+    }
+    fn range_limit(
+        &self,
+        collection: &str,
+        from: Option<&[u8]>,
+        to: Option<&[u8]>,
+        limit: Option<usize>,
+    ) -> Result<Vec<(KvBytes, KvBytes)>, DALError> {
+        if is_empty_range(from, to) {
+            return Ok(vec![]);
+        }
+        let collections = self.collections.read().map_err(|_| DALError::DBCorrupted)?;
+        Ok(if let Some(entries) = collections.get(collection) {
+            entries
+                .range(bounds(from, to))
+                .take(limit.unwrap_or(usize::max_value()))
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect()
+        } else {
+            vec![]
+        })
+    }
+    fn count(&self, collection: &str) -> Result<usize, DALError> {
+        let collections = self.collections.read().map_err(|_| DALError::DBCorrupted)?;
+        Ok(collections.get(collection).map_or(0, BTreeMap::len))
+    }
+    fn write_batch(&self, batch: WriteBatch) -> Result<(), DALError> {
+        let mut collections = self
+            .collections
+            .write()
+            .map_err(|_| DALError::DBCorrupted)?;
+        for op in batch.ops {
+            match op {
+                KvOp::Put {
+                    collection,
+                    key,
+                    value,
+                } => {
+                    collections
+                        .entry(collection)
+                        .or_insert_with(BTreeMap::new)
+                        .insert(key, value);
+                }
+                KvOp::Delete { collection, key } => {
+                    if let Some(entries) = collections.get_mut(&collection) {
+                        entries.remove(&key);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+    fn save(&self) -> Result<(), DALError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mem_kv_store() {
+        let db = MemKvStore::default();
+        db.put("c", &[1], &[10]).expect("fail to put");
+        db.put("c", &[2], &[20]).expect("fail to put");
+        db.put("c", &[3], &[30]).expect("fail to put");
+        db.put("d", &[1], &[11]).expect("fail to put");
+        assert_eq!(Some(vec![10]), db.get("c", &[1]).expect("fail to get"));
+        assert_eq!(None, db.get("e", &[1]).expect("fail to get"));
+        assert_eq!(
+            vec![(vec![2], vec![20]), (vec![3], vec![30])],
+            db.range("c", Some(&[2]), None).expect("fail to scan")
+        );
+        assert_eq!(
+            vec![(vec![1], vec![10])],
+            db.range("c", None, Some(&[2])).expect("fail to scan")
+        );
+        assert!(db
+            .range("c", Some(&[3]), Some(&[2]))
+            .expect("fail to scan")
+            .is_empty());
+
+        let mut batch = WriteBatch::new();
+        batch.delete("c", vec![1]);
+        batch.put("c", vec![2], vec![21]);
+        db.write_batch(batch).expect("fail to write batch");
+        assert_eq!(None, db.get("c", &[1]).expect("fail to get"));
+        assert_eq!(Some(vec![21]), db.get("c", &[2]).expect("fail to get"));
+        assert_eq!(2, db.count("c").expect("fail to count"));
+    }
+}
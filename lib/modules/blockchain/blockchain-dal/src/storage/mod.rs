@@ -0,0 +1,249 @@
+//  Copyright (C) 2018  The Duniter Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Key-value storage backends.
+//!
+//! Unlike the rustbreak databases, which serialize their whole content on each save,
+//! a key-value store organizes its datas in collections of independent entries: each write
+//! only touches the modified entries.
+
+/// Typed collections
+pub mod collection;
+
+/// Embedded on-disk key-value store
+pub mod file;
+
+/// Typed map stored in a key-value database
+pub mod map;
+
+/// In-memory key-value store (for tests)
+pub mod memory;
+
+/// Key-value database whose writes are staged until their commit
+pub mod staged;
+
+/// Single value stored in a key-value database
+pub mod value;
+
+pub use self::collection::KvCollection;
+pub use self::file::FileKvStore;
+pub use self::map::KvMapDB;
+pub use self::memory::MemKvStore;
+pub use self::staged::StagedKvDB;
+pub use self::value::KvValueDB;
+
+use crate::DALError;
+use std::fmt::Debug;
+use std::path::PathBuf;
+
+/// Raw key or value
+pub type KvBytes = Vec<u8>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+/// Write operation on a key-value store
+pub enum KvOp {
+    /// Insert or replace an entry
+    Put {
+        /// Collection name
+        collection: String,
+        /// Entry key
+        key: KvBytes,
+        /// Entry value
+        value: KvBytes,
+    },
+    /// Remove an entry
+    Delete {
+        /// Collection name
+        collection: String,
+        /// Entry key
+        key: KvBytes,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+/// Set of write operations applied atomically
+pub struct WriteBatch {
+    /// Operations, in application order
+    pub ops: Vec<KvOp>,
+}
+
+impl WriteBatch {
+    /// Create an empty batch
+    pub fn new() -> WriteBatch {
+        WriteBatch::default()
+    }
+    /// Insert or replace an entry
+    pub fn put(&mut self, collection: &str, key: KvBytes, value: KvBytes) {
+        self.ops.push(KvOp::Put {
+            collection: collection.to_owned(),
+            key,
+            value,
+        });
+    }
+    /// Remove an entry
+    pub fn delete(&mut self, collection: &str, key: KvBytes) {
+        self.ops.push(KvOp::Delete {
+            collection: collection.to_owned(),
+            key,
+        });
+    }
+    /// Number of operations in the batch
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+    /// Return true if the batch contains no operation
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+}
+
+/// Key-value store organized in collections.
+///
+/// Inside a collection, entries are sorted by the bytes of their key.
+pub trait KvStore: Debug + Send + Sync {
+    /// Get the value of an entry
+    fn get(&self, collection: &str, key: &[u8]) -> Result<Option<KvBytes>, DALError>;
+    /// Return true if the entry exists
+    fn contains(&self, collection: &str, key: &[u8]) -> Result<bool, DALError> {
+        Ok(self.get(collection, key)?.is_some())
+    }
+    /// Get the entries whose key is in `[from, to[` (a missing bound is unbounded),
+    /// sorted by key
+    fn range(
+        &self,
+        collection: &str,
+        from: Option<&[u8]>,
+        to: Option<&[u8]>,
+    ) -> Result<Vec<(KvBytes, KvBytes)>, DALError> {
+        self.range_limit(collection, from, to, None)
+    }
+    /// Get the `limit` first entries (all of them if `None`) whose key is in `[from, to[`,
+    /// sorted by key
+    fn range_limit(
+        &self,
+        collection: &str,
+        from: Option<&[u8]>,
+        to: Option<&[u8]>,
+        limit: Option<usize>,
+    ) -> Result<Vec<(KvBytes, KvBytes)>, DALError>;
+    /// Number of entries of a collection
+    fn count(&self, collection: &str) -> Result<usize, DALError>;
+    /// Apply all the operations of the batch, or none of them
+    fn write_batch(&self, batch: WriteBatch) -> Result<(), DALError>;
+    /// Flush written datas to the backend
+    fn save(&self) -> Result<(), DALError>;
+    /// Insert or replace an entry
+    fn put(&self, collection: &str, key: &[u8], value: &[u8]) -> Result<(), DALError> {
+        let mut batch = WriteBatch::new();
+        batch.put(collection, key.to_vec(), value.to_vec());
+        self.write_batch(batch)
+    }
+    /// Remove an entry
+    fn delete(&self, collection: &str, key: &[u8]) -> Result<(), DALError> {
+        let mut batch = WriteBatch::new();
+        batch.delete(collection, key.to_vec());
+        self.write_batch(batch)
+    }
+}
+
+#[derive(Debug)]
+/// Key-value database
+pub enum KvDB {
+    /// File database
+    File(FileKvStore),
+    /// Memory database
+    Mem(MemKvStore),
+}
+
+impl KvStore for KvDB {
+    fn get(&self, collection: &str, key: &[u8]) -> Result<Option<KvBytes>, DALError> {
+        match *self {
+            KvDB::File(ref file_db) => file_db.get(collection, key),
+            KvDB::Mem(ref mem_db) => mem_db.get(collection, key),
+        }
+    }
+    fn contains(&self, collection: &str, key: &[u8]) -> Result<bool, DALError> {
+        match *self {
+            KvDB::File(ref file_db) => file_db.contains(collection, key),
+            KvDB::Mem(ref mem_db) => mem_db.contains(collection, key),
+        }
+    }
+    fn range_limit(
+        &self,
+        collection: &str,
+        from: Option<&[u8]>,
+        to: Option<&[u8]>,
+        limit: Option<usize>,
+    ) -> Result<Vec<(KvBytes, KvBytes)>, DALError> {
+        match *self {
+            KvDB::File(ref file_db) => file_db.range_limit(collection, from, to, limit),
+            KvDB::Mem(ref mem_db) => mem_db.range_limit(collection, from, to, limit),
+        }
+    }
+    fn count(&self, collection: &str) -> Result<usize, DALError> {
+        match *self {
+            KvDB::File(ref file_db) => file_db.count(collection),
+            KvDB::Mem(ref mem_db) => mem_db.count(collection),
+        }
+    }
+    fn write_batch(&self, batch: WriteBatch) -> Result<(), DALError> {
+        match *self {
+            KvDB::File(ref file_db) => file_db.write_batch(batch),
+            KvDB::Mem(ref mem_db) => mem_db.write_batch(batch),
+        }
+    }
+    fn save(&self) -> Result<(), DALError> {
+        match *self {
+            KvDB::File(ref file_db) => file_db.save(),
+            KvDB::Mem(ref mem_db) => mem_db.save(),
+        }
+    }
+}
+
+impl KvDB {
+    /// Read the datas written by the owner of the database since the last refresh
+    /// (for databases opened in read-only mode)
+    pub fn refresh(&self) -> Result<(), DALError> {
+        match *self {
+            KvDB::File(ref file_db) => file_db.refresh(),
+            KvDB::Mem(_) => Ok(()),
+        }
+    }
+}
+
+/// Open key-value database (in memory if no folder is given)
+pub fn open_kv_db(dbs_folder_path: Option<&PathBuf>, db_file_name: &str) -> Result<KvDB, DALError> {
+    if let Some(dbs_folder_path) = dbs_folder_path {
+        let mut db_path = dbs_folder_path.clone();
+        db_path.push(db_file_name);
+        Ok(KvDB::File(FileKvStore::open(db_path)?))
+    } else {
+        Ok(KvDB::Mem(MemKvStore::default()))
+    }
+}
+
+/// Open key-value database in read-only mode (in memory if no folder is given)
+pub fn open_kv_db_read_only(
+    dbs_folder_path: Option<&PathBuf>,
+    db_file_name: &str,
+) -> Result<KvDB, DALError> {
+    if let Some(dbs_folder_path) = dbs_folder_path {
+        let mut db_path = dbs_folder_path.clone();
+        db_path.push(db_file_name);
+        Ok(KvDB::File(FileKvStore::open_read_only(db_path)?))
+    } else {
+        Ok(KvDB::Mem(MemKvStore::default()))
+    }
+}
@@ -0,0 +1,264 @@
+//  Copyright (C) 2018  The Duniter Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Writes in a staged database are kept in memory (and visible to its readers) until
+//! they are committed in the underlying store as a single batch. This allows to write the
+//! modifications of several databases in a journal before applying them.
+
+use super::memory::{bounds, is_empty_range};
+use super::*;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::RwLock;
+
+#[derive(Debug, Default)]
+/// Modifications not yet committed
+struct StagedOps {
+    /// New value of each modified entry (`None` for a removed entry)
+    collections: BTreeMap<String, BTreeMap<KvBytes, Option<KvBytes>>>,
+    /// Difference between the number of entries with and without the staged modifications
+    count_deltas: HashMap<String, isize>,
+}
+
+impl StagedOps {
+    fn to_batch(&self) -> WriteBatch {
+        let mut batch = WriteBatch::new();
+        for (collection, entries) in &self.collections {
+            for (key, value) in entries {
+                if let Some(value) = value {
+                    batch.put(collection, key.clone(), value.clone());
+                } else {
+                    batch.delete(collection, key.clone());
+                }
+            }
+        }
+        batch
+    }
+}
+
+#[derive(Debug)]
+/// Key-value database whose writes are staged until their commit
+pub struct StagedKvDB {
+    file_name: String,
+    store: KvDB,
+    staged: RwLock<StagedOps>,
+}
+
+impl StagedKvDB {
+    /// Stage the writes on `store`, stored in the file `file_name` of the databases folder
+    pub fn new(store: KvDB, file_name: &str) -> StagedKvDB {
+        StagedKvDB {
+            file_name: file_name.to_owned(),
+            store,
+            staged: RwLock::new(StagedOps::default()),
+        }
+    }
+    /// Open staged database (in memory if no folder is given)
+    pub fn open(
+        dbs_folder_path: Option<&PathBuf>,
+        db_file_name: &str,
+    ) -> Result<StagedKvDB, DALError> {
+        Ok(StagedKvDB::new(
+            open_kv_db(dbs_folder_path, db_file_name)?,
+            db_file_name,
+        ))
+    }
+    /// Open staged database in read-only mode (in memory if no folder is given)
+    pub fn open_read_only(
+        dbs_folder_path: Option<&PathBuf>,
+        db_file_name: &str,
+    ) -> Result<StagedKvDB, DALError> {
+        Ok(StagedKvDB::new(
+            open_kv_db_read_only(dbs_folder_path, db_file_name)?,
+            db_file_name,
+        ))
+    }
+    /// Name of the database file
+    pub fn file_name(&self) -> &str {
+        &self.file_name
+    }
+    /// Get the staged modifications
+    pub fn staged_batch(&self) -> Result<WriteBatch, DALError> {
+        Ok(self
+            .staged
+            .read()
+            .map_err(|_| DALError::DBCorrupted)?
+            .to_batch())
+    }
+    /// Write the staged modifications in the store
+    pub fn commit(&self) -> Result<(), DALError> {
+        let mut staged = self.staged.write().map_err(|_| DALError::DBCorrupted)?;
+        self.store.write_batch(staged.to_batch())?;
+        self.store.save()?;
+        *staged = StagedOps::default();
+        Ok(())
+    }
+    /// Drop the staged modifications
+    pub fn discard(&self) -> Result<(), DALError> {
+        *self.staged.write().map_err(|_| DALError::DBCorrupted)? = StagedOps::default();
+        Ok(())
+    }
+    /// Read the datas written by the owner of the database since the last refresh
+    /// (for databases opened in read-only mode)
+    pub fn refresh(&self) -> Result<(), DALError> {
+        self.store.refresh()
+    }
+}
+
+impl KvStore for StagedKvDB {
+    fn get(&self, collection: &str, key: &[u8]) -> Result<Option<KvBytes>, DALError> {
+        let staged = self.staged.read().map_err(|_| DALError::DBCorrupted)?;
+        if let Some(value) = staged
+            .collections
+            .get(collection)
+            .and_then(|entries| entries.get(key))
+        {
+            return Ok(value.clone());
+        }
+        self.store.get(collection, key)
+    }
+    fn contains(&self, collection: &str, key: &[u8]) -> Result<bool, DALError> {
+        let staged = self.staged.read().map_err(|_| DALError::DBCorrupted)?;
+        if let Some(value) = staged
+            .collections
+            .get(collection)
+            .and_then(|entries| entries.get(key))
+        {
+            return Ok(value.is_some());
+        }
+        self.store.contains(collection, key)
+    }
+    fn range_limit(
+        &self,
+        collection: &str,
+        from: Option<&[u8]>,
+        to: Option<&[u8]>,
+        limit: Option<usize>,
+    ) -> Result<Vec<(KvBytes, KvBytes)>, DALError> {
+        let staged = self.staged.read().map_err(|_| DALError::DBCorrupted)?;
+        let staged_entries = match staged.collections.get(collection) {
+            Some(staged_entries) if !is_empty_range(from, to) => staged_entries,
+            _ => return self.store.range_limit(collection, from, to, limit),
+        };
+        // Each staged removal hides at most one stored entry
+        let store_limit = limit.map(|limit| {
+            limit
+                + staged_entries
+                    .range(bounds(from, to))
+                    .filter(|(_, value)| value.is_none())
+                    .count()
+        });
+        let mut entries: BTreeMap<KvBytes, KvBytes> = self
+            .store
+            .range_limit(collection, from, to, store_limit)?
+            .into_iter()
+            .collect();
+        for (key, value) in staged_entries.range(bounds(from, to)) {
+            if let Some(value) = value {
+                entries.insert(key.clone(), value.clone());
+            } else {
+                entries.remove(key);
+            }
+        }
+        Ok(entries
+            .into_iter()
+            .take(limit.unwrap_or(usize::max_value()))
+            .collect())
+    }
+    fn count(&self, collection: &str) -> Result<usize, DALError> {
+        let staged = self.staged.read().map_err(|_| DALError::DBCorrupted)?;
+        let count = self.store.count(collection)? as isize
+            + staged.count_deltas.get(collection).cloned().unwrap_or(0);
+        Ok(count as usize)
+    }
+    fn write_batch(&self, batch: WriteBatch) -> Result<(), DALError> {
+        let mut staged = self.staged.write().map_err(|_| DALError::DBCorrupted)?;
+        for op in batch.ops {
+            let (collection, key, value) = match op {
+                KvOp::Put {
+                    collection,
+                    key,
+                    value,
+                } => (collection, key, Some(value)),
+                KvOp::Delete { collection, key } => (collection, key, None),
+            };
+            let existed = match staged
+                .collections
+                .get(&collection)
+                .and_then(|entries| entries.get(&key))
+            {
+                Some(old_value) => old_value.is_some(),
+                None => self.store.contains(&collection, &key)?,
+            };
+            let delta = match (existed, value.is_some()) {
+                (false, true) => 1,
+                (true, false) => -1,
+                _ => 0,
+            };
+            if delta != 0 {
+                *staged.count_deltas.entry(collection.clone()).or_insert(0) += delta;
+            }
+            staged
+                .collections
+                .entry(collection)
+                .or_insert_with(BTreeMap::new)
+                .insert(key, value);
+        }
+        Ok(())
+    }
+    fn save(&self) -> Result<(), DALError> {
+        self.commit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_staged_kv_db() {
+        let db = StagedKvDB::new(KvDB::Mem(MemKvStore::default()), "test.kv");
+        db.put("c", &[1], &[10]).expect("fail to put");
+        db.put("c", &[2], &[20]).expect("fail to put");
+        db.commit().expect("fail to commit");
+        assert!(db.staged_batch().expect("fail to get batch").is_empty());
+
+        db.delete("c", &[1]).expect("fail to delete");
+        db.put("c", &[3], &[30]).expect("fail to put");
+        db.put("c", &[2], &[21]).expect("fail to put");
+        assert_eq!(None, db.get("c", &[1]).expect("fail to get"));
+        assert_eq!(Some(vec![21]), db.get("c", &[2]).expect("fail to get"));
+        assert_eq!(2, db.count("c").expect("fail to count"));
+        assert_eq!(
+            vec![(vec![2], vec![21]), (vec![3], vec![30])],
+            db.range("c", None, None).expect("fail to scan")
+        );
+        assert_eq!(
+            vec![(vec![2], vec![21])],
+            db.range_limit("c", None, None, Some(1))
+                .expect("fail to scan")
+        );
+        // The store is not modified before the commit
+        assert_eq!(
+            Some(vec![10]),
+            db.store.get("c", &[1]).expect("fail to get")
+        );
+        assert_eq!(3, db.staged_batch().expect("fail to get batch").len());
+
+        db.commit().expect("fail to commit");
+        assert_eq!(None, db.store.get("c", &[1]).expect("fail to get"));
+        assert_eq!(2, db.store.count("c").expect("fail to count"));
+        assert_eq!(2, db.count("c").expect("fail to count"));
+    }
+}
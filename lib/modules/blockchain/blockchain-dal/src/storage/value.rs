@@ -0,0 +1,114 @@
+//  Copyright (C) 2018  The Duniter Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+/// Single value of type `D` stored in a collection of a staged key-value database.
+///
+/// The value is kept in memory: it is only serialized in the database when it has been
+/// modified since the last time it was staged.
+pub struct KvValueDB<D> {
+    db: Arc<StagedKvDB>,
+    collection: KvCollection<(), D>,
+    value: RwLock<(D, bool)>,
+}
+
+impl<D> Debug for KvValueDB<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("KvValueDB")
+            .field("file_name", &self.db.file_name())
+            .field("collection", &self.collection.name)
+            .finish()
+    }
+}
+
+impl<D: Serialize + DeserializeOwned + Default> KvValueDB<D> {
+    /// Value stored in the collection `collection` of `db` (default value if there is none)
+    pub fn new(db: Arc<StagedKvDB>, collection: &'static str) -> Result<Self, DALError> {
+        let collection = KvCollection::new(collection);
+        let value = collection.get(&*db, &())?.unwrap_or_default();
+        Ok(KvValueDB {
+            db,
+            collection,
+            value: RwLock::new((value, false)),
+        })
+    }
+    /// Read lock the value
+    pub fn read<T, R>(&self, task: T) -> Result<R, DALError>
+    where
+        T: FnOnce(&D) -> R,
+    {
+        let value = self.value.read().map_err(|_| DALError::DBCorrupted)?;
+        Ok(task(&value.0))
+    }
+    /// Write lock the value and modify it
+    pub fn write<T>(&self, task: T) -> Result<(), DALError>
+    where
+        T: FnOnce(&mut D),
+    {
+        let mut value = self.value.write().map_err(|_| DALError::DBCorrupted)?;
+        task(&mut value.0);
+        value.1 = true;
+        Ok(())
+    }
+    /// Stage the value in its database if it has been modified
+    pub fn stage(&self) -> Result<(), DALError> {
+        let mut value = self.value.write().map_err(|_| DALError::DBCorrupted)?;
+        if value.1 {
+            self.collection.put(&*self.db, &(), &value.0)?;
+            value.1 = false;
+        }
+        Ok(())
+    }
+    /// Reload the value from its database (dropping the modifications not staged)
+    pub fn load(&self) -> Result<(), DALError> {
+        let new_value = self.collection.get(&*self.db, &())?.unwrap_or_default();
+        *self.value.write().map_err(|_| DALError::DBCorrupted)? = (new_value, false);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kv_value_db() {
+        let db = Arc::new(StagedKvDB::new(KvDB::Mem(MemKvStore::default()), "test.kv"));
+        let value: KvValueDB<Vec<u32>> =
+            KvValueDB::new(db.clone(), "value").expect("fail to open value");
+        value.write(|v| v.push(1)).expect("fail to write");
+        assert!(db.staged_batch().expect("fail to get batch").is_empty());
+        value.stage().expect("fail to stage");
+        assert_eq!(1, db.staged_batch().expect("fail to get batch").len());
+        db.commit().expect("fail to commit");
+
+        // Unmodified value is not staged again
+        value.stage().expect("fail to stage");
+        assert!(db.staged_batch().expect("fail to get batch").is_empty());
+
+        // Modifications not staged are dropped by a reload
+        value.write(|v| v.push(2)).expect("fail to write");
+        value.load().expect("fail to load");
+        assert_eq!(Ok(vec![1]), value.read(Clone::clone));
+        let reopened: KvValueDB<Vec<u32>> =
+            KvValueDB::new(db, "value").expect("fail to open value");
+        assert_eq!(Ok(vec![1]), reopened.read(Clone::clone));
+    }
+}
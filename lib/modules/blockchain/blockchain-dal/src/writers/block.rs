@@ -14,59 +14,82 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::entities::block::DALBlock;
+use crate::entities::fork_tree::ForkTree;
+use crate::entities::sources::DALUdState;
 use crate::*;
-use crate::{BinDB, DALError, LocalBlockchainV10Datas};
-use dubp_documents::Document;
+use crate::{DALError, LocalBlockchainV10DB};
 
 /// Insert new head Block in databases
 pub fn insert_new_head_block(
-    blockchain_db: &BinDB<LocalBlockchainV10Datas>,
+    blockchain_db: &LocalBlockchainV10DB,
     forks_dbs: &ForksDBs,
     dal_block: DALBlock,
 ) -> Result<(), DALError> {
     // Insert head block in blockchain
-    blockchain_db.write(|db| {
-        db.insert(dal_block.block.number, dal_block.clone());
-    })?;
+    blockchain_db.insert(&dal_block.block.number, &dal_block)?;
 
     // Insert head block in fork tree
+    let block_id = dal_block.block.number;
     let removed_blockstamps = crate::writers::fork_tree::insert_new_head_block(
         &forks_dbs.fork_tree_db,
         dal_block.blockstamp(),
+        forks_dbs.limits.fork_window_size,
     )?;
 
     // Insert head block in ForksBlocks
-    forks_dbs.fork_blocks_db.write(|db| {
-        db.insert(dal_block.blockstamp(), dal_block);
-    })?;
+    forks_dbs
+        .fork_blocks_db
+        .insert(&dal_block.blockstamp(), &dal_block)?;
 
     // Remove too old blocks
-    forks_dbs.fork_blocks_db.write(|db| {
-        for blockstamp in removed_blockstamps {
-            db.remove(&blockstamp);
-        }
-    })?;
+    for blockstamp in removed_blockstamps {
+        forks_dbs.fork_blocks_db.delete(&blockstamp)?;
+    }
+
+    // Remove too old orphan blocks
+    prune_orphan_blocks(forks_dbs, block_id)?;
+
+    Ok(())
+}
+
+/// Stack up a block of the selected fork branch in the local blockchain
+/// (the block is already in the fork tree, its main branch is changed after the rollback)
+pub fn stack_up_fork_block(
+    blocks_dbs: &BlocksV10DBs,
+    forks_dbs: &ForksDBs,
+    dal_block: DALBlock,
+    ud_state: DALUdState,
+) -> Result<(), DALError> {
+    // Insert block in blockchain
+    blocks_dbs
+        .blockchain_db
+        .insert(&dal_block.block.number, &dal_block)?;
+    blocks_dbs
+        .ud_states_db
+        .insert(&dal_block.block.number, &ud_state)?;
+
+    // Update block in ForksBlocks (with its expired certifications)
+    forks_dbs
+        .fork_blocks_db
+        .insert(&dal_block.blockstamp(), &dal_block)?;
 
     Ok(())
 }
 
 /// Insert new fork Block in databases
 pub fn insert_new_fork_block(forks_dbs: &ForksDBs, dal_block: DALBlock) -> Result<bool, DALError> {
+    let blockstamp = dal_block.blockstamp();
     if crate::writers::fork_tree::insert_new_fork_block(
         &forks_dbs.fork_tree_db,
-        dal_block.block.blockstamp(),
+        blockstamp,
         dal_block.block.previous_hash,
+        forks_dbs.limits.fork_window_size,
     )? {
         // Insert in ForksBlocks
-        forks_dbs.fork_blocks_db.write(|db| {
-            db.insert(dal_block.blockstamp(), dal_block.clone());
-        })?;
+        forks_dbs.fork_blocks_db.insert(&blockstamp, &dal_block)?;
 
         // As long as orphan blocks can succeed the last inserted block, they are inserted
-        if let Some(stackables_blocks) = forks_dbs
-            .orphan_blocks_db
-            .read(|db| db.get(&dal_block.blockstamp()).cloned())?
-        {
+        if let Some(stackables_blocks) = forks_dbs.orphan_blocks_db.remove(&blockstamp)? {
             for stackable_block in stackables_blocks {
                 let _ = insert_new_fork_block(forks_dbs, stackable_block);
             }
@@ -76,24 +99,203 @@ pub fn insert_new_fork_block(forks_dbs: &ForksDBs, dal_block: DALBlock) -> Resul
     } else {
         let previous_blockstamp = dal_block.previous_blockstamp();
 
-        // Get orphinBlocks vector
-        let mut orphan_blocks = if let Some(orphan_blocks) = forks_dbs
+        // Add orphan block (only once)
+        forks_dbs
             .orphan_blocks_db
-            .read(|db| db.get(&previous_blockstamp).cloned())?
+            .update(&previous_blockstamp, |orphan_blocks| {
+                let orphan_blocks = orphan_blocks.get_or_insert_with(Vec::new);
+                if orphan_blocks
+                    .iter()
+                    .all(|orphan_block| orphan_block.blockstamp() != blockstamp)
+                {
+                    orphan_blocks.push(dal_block);
+                }
+            })?;
+
+        // Enforce orphan blocks limits
+        if let Some(current_block_id) = forks_dbs
+            .fork_tree_db
+            .read(ForkTree::get_current_block_id)?
         {
+            prune_orphan_blocks(forks_dbs, current_block_id)?;
+        }
+
+        Ok(false)
+    }
+}
+
+/// Remove the orphan blocks more than `max_orphan_age` blocks behind the current block,
+/// then the orphan blocks farthest from the current block beyond `max_orphans_count`.
+/// Returns the blockstamps of the removed blocks.
+pub fn prune_orphan_blocks(
+    forks_dbs: &ForksDBs,
+    current_block_id: BlockNumber,
+) -> Result<Vec<Blockstamp>, DALError> {
+    let limits = forks_dbs.limits;
+    let orphans: Vec<(PreviousBlockstamp, Blockstamp)> = forks_dbs
+        .orphan_blocks_db
+        .entries()?
+        .into_iter()
+        .flat_map(|(previous_blockstamp, orphan_blocks)| {
             orphan_blocks
-        } else {
-            Vec::new()
+                .into_iter()
+                .map(move |orphan_block| (previous_blockstamp, orphan_block.blockstamp()))
+        })
+        .collect();
+    let (mut removed, mut kept): (Vec<_>, Vec<_>) =
+        orphans.into_iter().partition(|(_, blockstamp)| {
+            // The block number is chosen by the peer that sent the block
+            blockstamp.id.0.saturating_add(limits.max_orphan_age) < current_block_id.0
+        });
+    if kept.len() > limits.max_orphans_count {
+        kept.sort_by_key(|(_, blockstamp)| {
+            std::cmp::Reverse((i64::from(blockstamp.id.0) - i64::from(current_block_id.0)).abs())
+        });
+        let excess = kept.len() - limits.max_orphans_count;
+        removed.extend(kept.drain(..excess));
+    }
+    if !removed.is_empty() {
+        for (previous_blockstamp, blockstamp) in &removed {
+            forks_dbs
+                .orphan_blocks_db
+                .update(previous_blockstamp, |orphan_blocks| {
+                    if let Some(blocks) = orphan_blocks {
+                        blocks.retain(|orphan_block| orphan_block.blockstamp() != *blockstamp);
+                    }
+                    if orphan_blocks.as_ref().map_or(false, Vec::is_empty) {
+                        *orphan_blocks = None;
+                    }
+                })?;
+        }
+    }
+    Ok(removed
+        .into_iter()
+        .map(|(_, blockstamp)| blockstamp)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use dup_crypto_tests_tools::mocks::hash;
+
+    fn gen_dal_block(id: u32, hash: Hash, previous_hash: Hash) -> DALBlock {
+        DALBlock {
+            block: dubp_documents_tests_tools::mocks::gen_empty_timed_block(
+                Blockstamp {
+                    id: BlockNumber(id),
+                    hash: BlockHash(hash),
+                },
+                0,
+                previous_hash,
+            ),
+            expire_certs: None,
+        }
+    }
+
+    fn get_orphans(forks_dbs: &ForksDBs) -> Result<Vec<BlockNumber>, DALError> {
+        let mut orphans: Vec<BlockNumber> = forks_dbs
+            .orphan_blocks_db
+            .entries()?
+            .into_iter()
+            .flat_map(|(_, orphan_blocks)| orphan_blocks.into_iter().map(|b| b.block.number))
+            .collect();
+        orphans.sort();
+        Ok(orphans)
+    }
+
+    #[test]
+    fn test_orphan_blocks_limits() -> Result<(), DALError> {
+        let blockchain_db = BlocksV10DBs::open(None).blockchain_db;
+        let mut forks_dbs = ForksDBs::open(None);
+        forks_dbs.limits = ForksLimits {
+            fork_window_size: 200,
+            max_orphans_count: 2,
+            max_orphan_age: 5,
         };
 
-        // Add fork block
-        orphan_blocks.push(dal_block);
+        // Insert blocks #0 to #10 in main branch
+        let blockstamps = dubp_documents_tests_tools::mocks::generate_blockstamps(11);
+        for (i, blockstamp) in blockstamps.iter().enumerate() {
+            let previous_hash = if i == 0 {
+                Hash::default()
+            } else {
+                blockstamps[i - 1].hash.0
+            };
+            insert_new_head_block(
+                &blockchain_db,
+                &forks_dbs,
+                gen_dal_block(blockstamp.id.0, blockstamp.hash.0, previous_hash),
+            )?;
+        }
 
-        // Update OrphanBlocks DB
-        forks_dbs.orphan_blocks_db.write(|db| {
-            db.insert(previous_blockstamp, orphan_blocks);
-        })?;
+        // An orphan block is stored only once
+        let orphan_20 = gen_dal_block(20, hash('A'), hash('1'));
+        assert_eq!(
+            Ok(false),
+            insert_new_fork_block(&forks_dbs, orphan_20.clone())
+        );
+        assert_eq!(Ok(false), insert_new_fork_block(&forks_dbs, orphan_20));
+        assert_eq!(vec![BlockNumber(20)], get_orphans(&forks_dbs)?);
 
-        Ok(false)
+        // Beyond max_orphans_count, the orphans farthest from the current block are removed
+        assert_eq!(
+            Ok(false),
+            insert_new_fork_block(&forks_dbs, gen_dal_block(12, hash('C'), hash('B')))
+        );
+        assert_eq!(
+            Ok(false),
+            insert_new_fork_block(&forks_dbs, gen_dal_block(30, hash('D'), hash('2')))
+        );
+        assert_eq!(
+            vec![BlockNumber(12), BlockNumber(20)],
+            get_orphans(&forks_dbs)?
+        );
+
+        // Orphans older than max_orphan_age are removed
+        assert_eq!(
+            Ok(false),
+            insert_new_fork_block(&forks_dbs, gen_dal_block(3, hash('E'), hash('3')))
+        );
+        assert_eq!(
+            vec![BlockNumber(12), BlockNumber(20)],
+            get_orphans(&forks_dbs)?
+        );
+
+        // A stacked orphan is no longer an orphan
+        assert_eq!(
+            Ok(true),
+            insert_new_fork_block(
+                &forks_dbs,
+                gen_dal_block(11, hash('B'), blockstamps[10].hash.0)
+            )
+        );
+        assert_eq!(vec![BlockNumber(20)], get_orphans(&forks_dbs)?);
+        assert!(forks_dbs.fork_blocks_db.contains_key(&Blockstamp {
+            id: BlockNumber(12),
+            hash: BlockHash(hash('C')),
+        })?);
+
+        // Orphans become too old when the current block moves forward
+        for i in 11..=26 {
+            prune_orphan_blocks(&forks_dbs, BlockNumber(i))?;
+        }
+        assert!(get_orphans(&forks_dbs)?.is_empty());
+
+        // An orphan with the greatest block number is kept
+        assert_eq!(
+            Ok(false),
+            insert_new_fork_block(
+                &forks_dbs,
+                gen_dal_block(u32::max_value(), hash('F'), hash('4'))
+            )
+        );
+        assert_eq!(
+            vec![BlockNumber(u32::max_value())],
+            get_orphans(&forks_dbs)?
+        );
+
+        Ok(())
     }
 }
@@ -13,18 +13,19 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::entities::certification::DALCertification;
 use crate::entities::currency_params::CurrencyParameters;
-use crate::{BinDB, CertsExpirV10Datas, DALError, IdentitiesV10Datas};
+use crate::{CertsExpirV10DB, CertsHistoryV10DB, CertsLinksV10DB, DALError, IdentitiesV10DB};
 use dubp_documents::documents::certification::CompactCertificationDocument;
-use dubp_documents::BlockNumber;
+use dubp_documents::{BlockNumber, Blockstamp};
 use dup_crypto::keys::*;
 use durs_wot::NodeId;
 
 /// Apply "certification" event in databases
 pub fn write_certification(
     currency_params: &CurrencyParameters,
-    identities_db: &BinDB<IdentitiesV10Datas>,
-    certs_db: &BinDB<CertsExpirV10Datas>,
+    identities_db: &IdentitiesV10DB,
+    certs_db: &CertsExpirV10DB,
     source_pubkey: PubKey,
     source: NodeId,
     target: NodeId,
@@ -32,50 +33,105 @@ pub fn write_certification(
     written_timestamp: u64,
 ) -> Result<(), DALError> {
     // Get cert_chainable_on
-    let mut member_datas = identities_db.read(|db| {
-        db.get(&source_pubkey)
-            .expect("Database Corrupted, please reset data !")
-            .clone()
-    })?;
+    let mut member_datas = identities_db
+        .get(&source_pubkey)?
+        .expect("Database Corrupted, please reset data !");
     // Push new cert_chainable_on
     member_datas
         .cert_chainable_on
         .push(written_timestamp + currency_params.sig_period);
     // Write new identity datas
-    identities_db.write(|db| {
-        db.insert(source_pubkey, member_datas);
-    })?;
+    identities_db.insert(&source_pubkey, &member_datas)?;
     // Add cert in certs_db
-    certs_db.write(|db| {
-        let mut created_certs = db.get(&created_block_id).cloned().unwrap_or_default();
-        created_certs.insert((source, target));
-        db.insert(created_block_id, created_certs);
+    certs_db.update(&created_block_id, |created_certs| {
+        created_certs
+            .get_or_insert_with(Default::default)
+            .insert((source, target));
     })?;
     Ok(())
 }
 
+/// Index a certification by its link
+pub fn write_cert_link(
+    certs_links_db: &CertsLinksV10DB,
+    source: NodeId,
+    target: NodeId,
+    created_block_id: BlockNumber,
+) -> Result<(), DALError> {
+    certs_links_db.insert(&(source, target, created_block_id), &())
+}
+
+/// Remove a reverted or expired certification from the links index
+pub fn remove_cert_link(
+    certs_links_db: &CertsLinksV10DB,
+    source: NodeId,
+    target: NodeId,
+    created_block_id: BlockNumber,
+) -> Result<(), DALError> {
+    certs_links_db.delete(&(source, target, created_block_id))
+}
+
+/// Index by link the certifications that are not yet expired (databases created by an
+/// older version)
+pub fn build_missing_certs_links(
+    certs_db: &CertsExpirV10DB,
+    certs_links_db: &CertsLinksV10DB,
+) -> Result<(), DALError> {
+    if certs_links_db.is_empty()? {
+        for (created_block_id, links) in certs_db.entries()? {
+            certs_links_db.extend(
+                links
+                    .into_iter()
+                    .map(|(source, target)| ((source, target, created_block_id), ())),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Write certification in the history of its issuer and of its target
+pub fn write_cert_in_history(
+    certs_history_db: &CertsHistoryV10DB,
+    cert: DALCertification,
+) -> Result<(), DALError> {
+    certs_history_db.extend(
+        [cert.source, cert.target]
+            .iter()
+            .map(|node| ((*node, cert.written_on.id, cert.source, cert.target), cert)),
+    )
+}
+
+/// Remove reverted certification from the history of its issuer and of its target
+pub fn revert_cert_in_history(
+    certs_history_db: &CertsHistoryV10DB,
+    source: NodeId,
+    target: NodeId,
+    written_on: Blockstamp,
+) -> Result<(), DALError> {
+    for node in &[source, target] {
+        certs_history_db.delete(&(*node, written_on.id, source, target))?;
+    }
+    Ok(())
+}
+
 /// Revert writtent certification
 pub fn revert_write_cert(
-    identities_db: &BinDB<IdentitiesV10Datas>,
-    certs_db: &BinDB<CertsExpirV10Datas>,
+    identities_db: &IdentitiesV10DB,
+    certs_db: &CertsExpirV10DB,
     compact_doc: CompactCertificationDocument,
     source: NodeId,
     target: NodeId,
 ) -> Result<(), DALError> {
-    // Remove CertsExpirV10Datas entry
-    certs_db.write(|db| {
-        let mut certs = db
-            .get(&compact_doc.block_number)
-            .cloned()
-            .unwrap_or_default();
-        certs.remove(&(source, target));
-        db.insert(compact_doc.block_number, certs);
+    // Remove CertsExpirV10DB entry
+    certs_db.update(&compact_doc.block_number, |certs| {
+        certs
+            .get_or_insert_with(Default::default)
+            .remove(&(source, target));
     })?;
     // Pop last cert_chainable_on
-    identities_db.write(|db| {
-        if let Some(mut member_datas) = db.get(&compact_doc.issuer).cloned() {
+    identities_db.update(&compact_doc.issuer, |member_datas| {
+        if let Some(member_datas) = member_datas {
             member_datas.cert_chainable_on.pop();
-            db.insert(compact_doc.issuer, member_datas);
         }
     })?;
     Ok(())
@@ -83,28 +139,31 @@ pub fn revert_write_cert(
 
 /// Revert "certification expiry" event in databases
 pub fn revert_expire_cert(
-    certs_db: &BinDB<CertsExpirV10Datas>,
+    certs_db: &CertsExpirV10DB,
     source: NodeId,
     target: NodeId,
     created_block_id: BlockNumber,
 ) -> Result<(), DALError> {
-    // Reinsert CertsExpirV10Datas entry
-    certs_db.write(|db| {
-        let mut certs = db.get(&created_block_id).cloned().unwrap_or_default();
-        certs.insert((source, target));
-        db.insert(created_block_id, certs);
+    // Reinsert CertsExpirV10DB entry
+    certs_db.update(&created_block_id, |certs| {
+        certs
+            .get_or_insert_with(Default::default)
+            .insert((source, target));
     })?;
     Ok(())
 }
 
 /// Apply "certification expiry" event in databases
 pub fn expire_certs(
-    certs_db: &BinDB<CertsExpirV10Datas>,
+    certs_db: &CertsExpirV10DB,
+    certs_links_db: &CertsLinksV10DB,
     created_block_id: BlockNumber,
 ) -> Result<(), DALError> {
-    // Remove CertsExpirV10Datas entries
-    certs_db.write(|db| {
-        db.remove(&created_block_id);
-    })?;
+    // Remove CertsExpirV10DB entries
+    if let Some(links) = certs_db.remove(&created_block_id)? {
+        for (source, target) in links {
+            remove_cert_link(certs_links_db, source, target, created_block_id)?;
+        }
+    }
     Ok(())
 }
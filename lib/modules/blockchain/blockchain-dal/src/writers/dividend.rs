@@ -22,8 +22,8 @@ use std::collections::{HashMap, HashSet};
 
 /// Apply UD creation in databases
 pub fn create_du(
-    du_db: &BinDB<UDsV10Datas>,
-    balances_db: &BinDB<BalancesV10Datas>,
+    du_db: &UDsV10DB,
+    balances_db: &BalancesV10DB,
     du_amount: &SourceAmount,
     du_block_id: BlockNumber,
     members: &[PubKey],
@@ -34,33 +34,29 @@ pub fn create_du(
         du_amount, du_block_id.0, members, revert
     );
     // Insert/Remove UD sources in UDsV10DB
-    du_db.write(|db| {
-        for pubkey in members {
-            let mut pubkey_dus = db.get(&pubkey).cloned().unwrap_or_default();
+    for pubkey in members {
+        du_db.update(pubkey, |pubkey_dus| {
+            let pubkey_dus = pubkey_dus.get_or_insert_with(Default::default);
             if revert {
                 pubkey_dus.remove(&du_block_id);
             } else {
                 pubkey_dus.insert(du_block_id);
             }
-            db.insert(*pubkey, pubkey_dus);
-        }
-    })?;
-    // Get members balances
-    let members_balances: HashMap<PubKey, (SourceAmount, HashSet<UTXOIndexV10>)> = balances_db
-        .read(|db| {
-            let mut members_balances = HashMap::new();
-            for pubkey in members {
-                members_balances.insert(
-                    *pubkey,
-                    db.get(&UTXOConditionsGroup::Single(
-                        TransactionOutputCondition::Sig(*pubkey),
-                    ))
-                    .cloned()
-                    .unwrap_or_default(),
-                );
-            }
-            members_balances
         })?;
+    }
+    // Get members balances
+    let mut members_balances: HashMap<PubKey, (SourceAmount, HashSet<UTXOIndexV10>)> =
+        HashMap::new();
+    for pubkey in members {
+        members_balances.insert(
+            *pubkey,
+            balances_db
+                .get(&UTXOConditionsGroup::Single(
+                    TransactionOutputCondition::Sig(*pubkey),
+                ))?
+                .unwrap_or_default(),
+        );
+    }
     // Increase/Decrease members balance
     let members_balances: Vec<(PubKey, (SourceAmount, HashSet<UTXOIndexV10>))> = members_balances
         .iter()
@@ -74,13 +70,11 @@ pub fn create_du(
         })
         .collect();
     // Write new members balance
-    balances_db.write(|db| {
-        for (pubkey, (balance, utxos_indexs)) in members_balances {
-            db.insert(
-                UTXOConditionsGroup::Single(TransactionOutputCondition::Sig(pubkey)),
-                (balance, utxos_indexs),
-            );
-        }
-    })?;
+    balances_db.extend(members_balances.into_iter().map(|(pubkey, balance)| {
+        (
+            UTXOConditionsGroup::Single(TransactionOutputCondition::Sig(pubkey)),
+            balance,
+        )
+    }))?;
     Ok(())
 }
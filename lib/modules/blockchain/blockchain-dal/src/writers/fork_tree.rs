@@ -20,8 +20,9 @@ use dubp_documents::*;
 /// Insert new head Block in fork tree,
 /// return vector of removed blockstamps
 pub fn insert_new_head_block(
-    fork_tree_db: &BinDB<ForksTreeV10Datas>,
+    fork_tree_db: &ForksTreeV10DB,
     blockstamp: Blockstamp,
+    fork_window_size: usize,
 ) -> Result<Vec<Blockstamp>, DALError> {
     fork_tree_db.write(|fork_tree| {
         let parent_id_opt = if blockstamp.id.0 > 0 && fork_tree.size() > 0 {
@@ -30,18 +31,19 @@ pub fn insert_new_head_block(
         } else {
             None
         };
-        fork_tree.insert_new_node(blockstamp, parent_id_opt, true);
+        fork_tree.insert_new_node(blockstamp, parent_id_opt, true, fork_window_size);
     })?;
 
-    Ok(fork_tree_db.read(ForkTree::get_removed_blockstamps)?)
+    fork_tree_db.read(ForkTree::get_removed_blockstamps)
 }
 
 /// Insert new fork block in fork tree only if parent exist in fork tree (orphan block not inserted)
 /// Returns true if block has a parent and has therefore been inserted, return false if block is orphaned
 pub fn insert_new_fork_block(
-    fork_tree_db: &BinDB<ForksTreeV10Datas>,
+    fork_tree_db: &ForksTreeV10DB,
     blockstamp: Blockstamp,
     previous_hash: Hash,
+    fork_window_size: usize,
 ) -> Result<bool, DALError> {
     let previous_blockstamp = Blockstamp {
         id: BlockNumber(blockstamp.id.0 - 1),
@@ -53,7 +55,7 @@ pub fn insert_new_fork_block(
 
     if let Some(parent_id) = parent_id_opt {
         fork_tree_db.write(|fork_tree| {
-            fork_tree.insert_new_node(blockstamp, Some(parent_id), false);
+            fork_tree.insert_new_node(blockstamp, Some(parent_id), false, fork_window_size);
         })?;
         Ok(true)
     } else {
@@ -76,11 +78,9 @@ pub fn change_main_branch(
         .read(ForkTree::get_removed_blockstamps)?;
 
     // Remove too old blocks
-    forks_dbs.fork_blocks_db.write(|db| {
-        for blockstamp in removed_blockstamps {
-            db.remove(&blockstamp);
-        }
-    })?;
+    for blockstamp in removed_blockstamps {
+        forks_dbs.fork_blocks_db.delete(&blockstamp)?;
+    }
 
     Ok(())
 }
@@ -97,12 +97,16 @@ mod test {
         let blockstamps = dubp_documents_tests_tools::mocks::generate_blockstamps(
             *crate::constants::FORK_WINDOW_SIZE + 2,
         );
-        let fork_tree_db = open_db::<ForksTreeV10Datas>(None, "")?;
+        let fork_tree_db = ForksDBs::open(None).fork_tree_db;
 
         // Insert genesis block
         assert_eq!(
             Ok(vec![]),
-            insert_new_head_block(&fork_tree_db, blockstamps[0])
+            insert_new_head_block(
+                &fork_tree_db,
+                blockstamps[0],
+                *crate::constants::FORK_WINDOW_SIZE
+            )
         );
 
         // Check tree state
@@ -116,7 +120,11 @@ mod test {
         for i in 1..*crate::constants::FORK_WINDOW_SIZE {
             assert_eq!(
                 Ok(vec![]),
-                insert_new_head_block(&fork_tree_db, blockstamps[i])
+                insert_new_head_block(
+                    &fork_tree_db,
+                    blockstamps[i],
+                    *crate::constants::FORK_WINDOW_SIZE
+                )
             );
         }
 
@@ -138,14 +146,16 @@ mod test {
             Ok(vec![blockstamps[0]]),
             insert_new_head_block(
                 &fork_tree_db,
-                blockstamps[*crate::constants::FORK_WINDOW_SIZE]
+                blockstamps[*crate::constants::FORK_WINDOW_SIZE],
+                *crate::constants::FORK_WINDOW_SIZE
             )
         );
         assert_eq!(
             Ok(vec![blockstamps[1]]),
             insert_new_head_block(
                 &fork_tree_db,
-                blockstamps[*crate::constants::FORK_WINDOW_SIZE + 1]
+                blockstamps[*crate::constants::FORK_WINDOW_SIZE + 1],
+                *crate::constants::FORK_WINDOW_SIZE
             )
         );
 
@@ -158,13 +168,17 @@ mod test {
         let blockstamps = dubp_documents_tests_tools::mocks::generate_blockstamps(
             *crate::constants::FORK_WINDOW_SIZE + 3,
         );
-        let fork_tree_db = open_db::<ForksTreeV10Datas>(None, "")?;
+        let fork_tree_db = ForksDBs::open(None).fork_tree_db;
 
         // Insert 4 main blocks
         for i in 0..4 {
             assert_eq!(
                 Ok(vec![]),
-                insert_new_head_block(&fork_tree_db, blockstamps[i])
+                insert_new_head_block(
+                    &fork_tree_db,
+                    blockstamps[i],
+                    *crate::constants::FORK_WINDOW_SIZE
+                )
             );
         }
 
@@ -182,7 +196,12 @@ mod test {
         };
         assert_eq!(
             Ok(true),
-            insert_new_fork_block(&fork_tree_db, fork_blockstamp, blockstamps[2].hash.0)
+            insert_new_fork_block(
+                &fork_tree_db,
+                fork_blockstamp,
+                blockstamps[2].hash.0,
+                *crate::constants::FORK_WINDOW_SIZE
+            )
         );
 
         // Check tree state
@@ -202,7 +221,12 @@ mod test {
         };
         assert_eq!(
             Ok(true),
-            insert_new_fork_block(&fork_tree_db, fork_blockstamp_2, fork_blockstamp.hash.0)
+            insert_new_fork_block(
+                &fork_tree_db,
+                fork_blockstamp_2,
+                fork_blockstamp.hash.0,
+                *crate::constants::FORK_WINDOW_SIZE
+            )
         );
 
         // Check tree state
@@ -219,7 +243,11 @@ mod test {
         for i in 4..*crate::constants::FORK_WINDOW_SIZE {
             assert_eq!(
                 Ok(vec![]),
-                insert_new_head_block(&fork_tree_db, blockstamps[i])
+                insert_new_head_block(
+                    &fork_tree_db,
+                    blockstamps[i],
+                    *crate::constants::FORK_WINDOW_SIZE
+                )
             );
         }
 
@@ -245,7 +273,8 @@ mod test {
                 Ok(vec![blockstamps[i]]),
                 insert_new_head_block(
                     &fork_tree_db,
-                    blockstamps[*crate::constants::FORK_WINDOW_SIZE + i]
+                    blockstamps[*crate::constants::FORK_WINDOW_SIZE + i],
+                    *crate::constants::FORK_WINDOW_SIZE
                 )
             );
         }
@@ -255,7 +284,8 @@ mod test {
             Ok(vec![blockstamps[2], fork_blockstamp_2, fork_blockstamp]),
             insert_new_head_block(
                 &fork_tree_db,
-                blockstamps[*crate::constants::FORK_WINDOW_SIZE + 2]
+                blockstamps[*crate::constants::FORK_WINDOW_SIZE + 2],
+                *crate::constants::FORK_WINDOW_SIZE
             )
         );
 
@@ -14,8 +14,8 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::entities::currency_params::CurrencyParameters;
-use crate::entities::identity::{DALIdentity, DALIdentityState};
-use crate::{BinDB, DALError, IdentitiesV10Datas, MsExpirV10Datas};
+use crate::entities::identity::{DALIdentity, DALIdentityState, IdentitiesIndexesDB};
+use crate::{DALError, IdentitiesV10DB, MsExpirV10DB};
 use dubp_documents::documents::identity::IdentityDocument;
 use dubp_documents::Document;
 use dubp_documents::{BlockNumber, Blockstamp};
@@ -25,36 +25,30 @@ use durs_wot::NodeId;
 
 /// Remove identity from databases
 pub fn revert_create_identity(
-    identities_db: &BinDB<IdentitiesV10Datas>,
-    ms_db: &BinDB<MsExpirV10Datas>,
+    identities_db: &IdentitiesV10DB,
+    ms_db: &MsExpirV10DB,
     pubkey: &PubKey,
 ) -> Result<(), DALError> {
-    let dal_idty = identities_db.read(|db| {
-        db.get(&pubkey)
-            .expect("Fatal error : try to revert unknow identity !")
-            .clone()
-    })?;
+    let dal_idty = identities_db
+        .get(&pubkey)?
+        .expect("Fatal error : try to revert unknow identity !");
     // Remove membership
-    ms_db.write(|db| {
-        let mut memberships = db
-            .get(&dal_idty.ms_created_block_id)
-            .cloned()
-            .expect("Try to revert a membership that does not exist !");
-        memberships.remove(&dal_idty.wot_id);
-        db.insert(dal_idty.ms_created_block_id, memberships);
+    ms_db.update(&dal_idty.ms_created_block_id, |memberships| {
+        memberships
+            .as_mut()
+            .expect("Try to revert a membership that does not exist !")
+            .remove(&dal_idty.wot_id);
     })?;
     // Remove identity
-    identities_db.write(|db| {
-        db.remove(&dal_idty.idty_doc.issuers()[0]);
-    })?;
+    identities_db.delete(&dal_idty.idty_doc.issuers()[0])?;
     Ok(())
 }
 
 /// Write identity in databases
 pub fn create_identity(
     currency_params: &CurrencyParameters,
-    identities_db: &BinDB<IdentitiesV10Datas>,
-    ms_db: &BinDB<MsExpirV10Datas>,
+    identities_db: &IdentitiesV10DB,
+    ms_db: &MsExpirV10DB,
     idty_doc: &IdentityDocument,
     ms_created_block_id: BlockNumber,
     wot_id: NodeId,
@@ -76,27 +70,25 @@ pub fn create_identity(
         cert_chainable_on: vec![],
     };
     // Write Identity
-    identities_db.write(|db| {
-        db.insert(idty.idty_doc.issuers()[0], idty.clone());
-    })?;
+    identities_db.insert(&idty.idty_doc.issuers()[0], &idty)?;
     // Write membership
-    ms_db.write(|db| {
-        let mut memberships = db.get(&ms_created_block_id).cloned().unwrap_or_default();
-        memberships.insert(wot_id);
-        db.insert(ms_created_block_id, memberships);
+    ms_db.update(&ms_created_block_id, |memberships| {
+        memberships
+            .get_or_insert_with(Default::default)
+            .insert(wot_id);
     })?;
     Ok(())
 }
 
 /// Apply "exclude identity" event
 pub fn exclude_identity(
-    identities_db: &BinDB<IdentitiesV10Datas>,
+    identities_db: &IdentitiesV10DB,
     pubkey: &PubKey,
     exclusion_blockstamp: &Blockstamp,
     revert: bool,
 ) -> Result<(), DALError> {
     let mut idty_datas = identities_db
-        .read(|db| db.get(pubkey).cloned())?
+        .get(pubkey)?
         .expect("Fatal error : try to renewal unknow identity !");
     idty_datas.state = if revert {
         match idty_datas.state {
@@ -119,22 +111,20 @@ pub fn exclude_identity(
         Some(*exclusion_blockstamp)
     };
     // Write new identity datas
-    identities_db.write(|db| {
-        db.insert(*pubkey, idty_datas);
-    })?;
+    identities_db.insert(pubkey, &idty_datas)?;
     Ok(())
 }
 
 /// Apply "revoke identity" event
 pub fn revoke_identity(
-    identities_db: &BinDB<IdentitiesV10Datas>,
+    identities_db: &IdentitiesV10DB,
     pubkey: &PubKey,
     renewal_blockstamp: &Blockstamp,
     explicit: bool,
     revert: bool,
 ) -> Result<(), DALError> {
     let mut member_datas = identities_db
-        .read(|db| db.get(pubkey).cloned())?
+        .get(pubkey)?
         .expect("Fatal error : Try to revoke unknow idty !");
 
     member_datas.state = if revert {
@@ -151,7 +141,11 @@ pub fn revoke_identity(
     } else {
         match member_datas.state {
             DALIdentityState::ExpireMember(renewed_counts) => {
-                DALIdentityState::ExplicitExpireRevoked(renewed_counts)
+                if explicit {
+                    DALIdentityState::ExplicitExpireRevoked(renewed_counts)
+                } else {
+                    DALIdentityState::ImplicitRevoked(renewed_counts)
+                }
             }
             DALIdentityState::Member(renewed_counts) => {
                 if explicit {
@@ -169,17 +163,15 @@ pub fn revoke_identity(
         Some(*renewal_blockstamp)
     };
 
-    identities_db.write(|db| {
-        db.insert(*pubkey, member_datas);
-    })?;
+    identities_db.insert(pubkey, &member_datas)?;
     Ok(())
 }
 
 /// Apply "renewal identity" event in databases
 pub fn renewal_identity(
     currency_params: &CurrencyParameters,
-    identities_db: &BinDB<IdentitiesV10Datas>,
-    ms_db: &BinDB<MsExpirV10Datas>,
+    identities_db: &IdentitiesV10DB,
+    ms_db: &MsExpirV10DB,
     pubkey: &PubKey,
     idty_wot_id: NodeId,
     renewal_timestamp: u64,
@@ -188,7 +180,7 @@ pub fn renewal_identity(
 ) -> Result<(), DALError> {
     // Get idty_datas
     let mut idty_datas = identities_db
-        .read(|db| db.get(pubkey).cloned())?
+        .get(pubkey)?
         .expect("Fatal error : try to renewal unknow identity !");
     // Calculate new state value
     idty_datas.state = if revert {
@@ -227,23 +219,66 @@ pub fn renewal_identity(
             .ms_chainable_on
             .push(renewal_timestamp + currency_params.ms_period);
     }
+    // Update MsExpirV10DB and the created block of the current membership
+    if revert {
+        ms_db.update(&ms_created_block_id, |memberships| {
+            if let Some(memberships) = memberships {
+                memberships.remove(&idty_wot_id);
+            }
+        })?;
+        if let Some(previous_ms_created_block_id) = ms_db
+            .range(None, Some(&ms_created_block_id))?
+            .into_iter()
+            .rev()
+            .find(|(_, memberships)| memberships.contains(&idty_wot_id))
+            .map(|(block_id, _)| block_id)
+        {
+            idty_datas.ms_created_block_id = previous_ms_created_block_id;
+        }
+    } else {
+        ms_db.update(&ms_created_block_id, |memberships| {
+            memberships
+                .get_or_insert_with(Default::default)
+                .insert(idty_wot_id);
+        })?;
+        idty_datas.ms_created_block_id = ms_created_block_id;
+    }
     // Write new identity datas
-    identities_db.write(|db| {
-        db.insert(*pubkey, idty_datas);
-    })?;
-    // Update MsExpirV10DB
-    ms_db.write(|db| {
-        let mut memberships = db.get(&ms_created_block_id).cloned().unwrap_or_default();
-        memberships.insert(idty_wot_id);
-        db.insert(ms_created_block_id, memberships);
-    })?;
+    identities_db.insert(pubkey, &idty_datas)?;
     Ok(())
 }
 
-/// Remove identity from databases
-pub fn remove_identity(db: &BinDB<IdentitiesV10Datas>, pubkey: PubKey) -> Result<(), DALError> {
-    db.write(|db| {
-        db.remove(&pubkey);
-    })?;
+/// Update identities indexes after a change of an identity (`None` if the identity does not exist)
+pub fn update_identities_indexes(
+    idties_indexes_db: &IdentitiesIndexesDB,
+    old_idty: Option<&DALIdentity>,
+    new_idty: Option<&DALIdentity>,
+) -> Result<(), DALError> {
+    if old_idty != new_idty {
+        if let Some(old_idty) = old_idty {
+            idties_indexes_db.remove(old_idty)?;
+        }
+        if let Some(new_idty) = new_idty {
+            idties_indexes_db.insert(new_idty)?;
+        }
+    }
     Ok(())
 }
+
+/// Build identities indexes if they are missing (databases created by an older version)
+pub fn build_missing_identities_indexes(
+    identities_db: &IdentitiesV10DB,
+    idties_indexes_db: &IdentitiesIndexesDB,
+) -> Result<(), DALError> {
+    if idties_indexes_db.is_empty()? {
+        for (_, idty) in identities_db.entries()? {
+            idties_indexes_db.insert(&idty)?;
+        }
+    }
+    Ok(())
+}
+
+/// Remove identity from databases
+pub fn remove_identity(db: &IdentitiesV10DB, pubkey: PubKey) -> Result<(), DALError> {
+    db.delete(&pubkey)
+}
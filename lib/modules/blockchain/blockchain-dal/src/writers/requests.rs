@@ -14,14 +14,15 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::entities::block::DALBlock;
+use crate::entities::certification::DALCertification;
 use crate::entities::currency_params::CurrencyParameters;
-use crate::entities::sources::SourceAmount;
+use crate::entities::sources::{DALUdState, SourceAmount};
 use crate::writers::transaction::DALTxV10;
 use crate::*;
 use dubp_documents::documents::block::BlockDocument;
 use dubp_documents::documents::certification::CompactCertificationDocument;
 use dubp_documents::documents::identity::IdentityDocument;
-use dubp_documents::Blockstamp;
+use dubp_documents::{Blockstamp, Document};
 use dup_crypto::keys::PubKey;
 use durs_wot::NodeId;
 use std::ops::Deref;
@@ -40,8 +41,8 @@ pub enum DBsWriteRequest {
 #[derive(Debug, Clone)]
 /// Contain a pending write request for blocks databases
 pub enum BlocksDBsWriteQuery {
-    /// Write block
-    WriteBlock(DALBlock),
+    /// Write block (with the universal dividend state after it)
+    WriteBlock(DALBlock, DALUdState),
     /// Revert block
     RevertBlock(DALBlock),
 }
@@ -50,39 +51,41 @@ impl BlocksDBsWriteQuery {
     /// Get copy of block document
     pub fn get_block_doc_copy(&self) -> BlockDocument {
         match self {
-            BlocksDBsWriteQuery::WriteBlock(dal_block) => dal_block.block.clone(),
+            BlocksDBsWriteQuery::WriteBlock(dal_block, _) => dal_block.block.clone(),
             BlocksDBsWriteQuery::RevertBlock(dal_block) => dal_block.block.clone(),
         }
     }
     /// BlocksDBsWriteQuery
     pub fn apply(
         self,
-        blockchain_db: &BinDB<LocalBlockchainV10Datas>,
+        blocks_dbs: &BlocksV10DBs,
         forks_db: &ForksDBs,
         sync_target: Option<Blockstamp>,
     ) -> Result<(), DALError> {
         match self {
-            BlocksDBsWriteQuery::WriteBlock(dal_block) => {
+            BlocksDBsWriteQuery::WriteBlock(dal_block, ud_state) => {
                 let dal_block: DALBlock = dal_block;
                 trace!("BlocksDBsWriteQuery::WriteBlock...");
+                let blockchain_db = &blocks_dbs.blockchain_db;
+                // Insert the universal dividend state after the block
+                blocks_dbs
+                    .ud_states_db
+                    .insert(&dal_block.block.number, &ud_state)?;
                 if sync_target.is_none()
-                    || dal_block.blockstamp().id.0 + *crate::constants::FORK_WINDOW_SIZE as u32
+                    || dal_block.blockstamp().id.0 + forks_db.limits.fork_window_size as u32
                         >= sync_target.expect("safe unwrap").id.0
                 {
                     super::block::insert_new_head_block(blockchain_db, forks_db, dal_block)?;
                 } else {
                     // Insert block in blockchain
-                    blockchain_db.write(|db| {
-                        db.insert(dal_block.block.number, dal_block);
-                    })?;
+                    blockchain_db.insert(&dal_block.block.number, &dal_block)?;
                 }
             }
             BlocksDBsWriteQuery::RevertBlock(dal_block) => {
                 trace!("BlocksDBsWriteQuery::WriteBlock...");
                 // Remove block in blockchain
-                blockchain_db.write(|db| {
-                    db.remove(&dal_block.block.number);
-                })?;
+                blocks_dbs.blockchain_db.delete(&dal_block.block.number)?;
+                blocks_dbs.ud_states_db.delete(&dal_block.block.number)?;
                 trace!("BlocksDBsWriteQuery::WriteBlock...finish");
             }
         }
@@ -120,13 +123,38 @@ pub enum WotsDBsWriteQuery {
 }
 
 impl WotsDBsWriteQuery {
+    /// Get the public key of the identity modified by this query (except certifications changes)
+    fn get_idty_pubkey(&self) -> Option<PubKey> {
+        match *self {
+            WotsDBsWriteQuery::CreateIdentity(_, _, _, ref idty_doc, _) => {
+                Some(idty_doc.issuers()[0])
+            }
+            WotsDBsWriteQuery::RevertCreateIdentity(pubkey)
+            | WotsDBsWriteQuery::RenewalIdentity(pubkey, _, _, _)
+            | WotsDBsWriteQuery::RevertRenewalIdentity(pubkey, _, _, _)
+            | WotsDBsWriteQuery::ExcludeIdentity(pubkey, _)
+            | WotsDBsWriteQuery::RevertExcludeIdentity(pubkey, _)
+            | WotsDBsWriteQuery::RevokeIdentity(pubkey, _, _)
+            | WotsDBsWriteQuery::RevertRevokeIdentity(pubkey, _, _) => Some(pubkey),
+            WotsDBsWriteQuery::CreateCert(..)
+            | WotsDBsWriteQuery::RevertCert(..)
+            | WotsDBsWriteQuery::ExpireCerts(..)
+            | WotsDBsWriteQuery::RevertExpireCert(..) => None,
+        }
+    }
     /// Apply WotsDBsWriteQuery
     pub fn apply(
         &self,
-        _blockstamp: &Blockstamp,
+        written_blockstamp: &Blockstamp,
         currency_params: &CurrencyParameters,
         databases: &WotsV10DBs,
     ) -> Result<(), DALError> {
+        let idty_pubkey = self.get_idty_pubkey();
+        let old_idty = if let Some(ref pubkey) = idty_pubkey {
+            readers::identity::get_identity(&databases.identities_db, pubkey)?
+        } else {
+            None
+        };
         match *self {
             WotsDBsWriteQuery::CreateIdentity(
                 ref wot_id,
@@ -241,6 +269,22 @@ impl WotsDBsWriteQuery {
                     *created_block_id,
                     *median_time,
                 )?;
+                writers::certification::write_cert_link(
+                    &databases.certs_links_db,
+                    *source,
+                    *target,
+                    *created_block_id,
+                )?;
+                writers::certification::write_cert_in_history(
+                    &databases.certs_history_db,
+                    DALCertification {
+                        issuer: *source_pubkey,
+                        source: *source,
+                        target: *target,
+                        created_block_id: *created_block_id,
+                        written_on: *written_blockstamp,
+                    },
+                )?;
                 trace!("WotsDBsWriteQuery::CreateCert...finish");
             }
             WotsDBsWriteQuery::RevertCert(ref compact_doc, ref source, ref target) => {
@@ -252,10 +296,26 @@ impl WotsDBsWriteQuery {
                     *source,
                     *target,
                 )?;
+                writers::certification::remove_cert_link(
+                    &databases.certs_links_db,
+                    *source,
+                    *target,
+                    compact_doc.block_number,
+                )?;
+                writers::certification::revert_cert_in_history(
+                    &databases.certs_history_db,
+                    *source,
+                    *target,
+                    *written_blockstamp,
+                )?;
                 trace!("WotsDBsWriteQuery::CreateCert...finish");
             }
             WotsDBsWriteQuery::ExpireCerts(ref created_block_id) => {
-                super::certification::expire_certs(&databases.certs_db, *created_block_id)?;
+                super::certification::expire_certs(
+                    &databases.certs_db,
+                    &databases.certs_links_db,
+                    *created_block_id,
+                )?;
             }
             WotsDBsWriteQuery::RevertExpireCert(ref source, ref target, ref created_block_id) => {
                 super::certification::revert_expire_cert(
@@ -264,8 +324,23 @@ impl WotsDBsWriteQuery {
                     *target,
                     *created_block_id,
                 )?;
+                super::certification::write_cert_link(
+                    &databases.certs_links_db,
+                    *source,
+                    *target,
+                    *created_block_id,
+                )?;
             }
         }
+        // Update identities indexes
+        if let Some(ref pubkey) = idty_pubkey {
+            let new_idty = readers::identity::get_identity(&databases.identities_db, pubkey)?;
+            writers::identity::update_identities_indexes(
+                &databases.idties_indexes_db,
+                old_idty.as_ref(),
+                new_idty.as_ref(),
+            )?;
+        }
         Ok(())
     }
 }
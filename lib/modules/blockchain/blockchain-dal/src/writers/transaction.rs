@@ -14,10 +14,12 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use dubp_documents::documents::transaction::*;
+use dubp_documents::Document;
 use durs_common_tools::fatal_error;
 
 use crate::entities::sources::{SourceAmount, SourceIndexV10, UTXOIndexV10, UTXOV10};
 use crate::*;
+use std::collections::HashMap;
 
 #[derive(Debug, Copy, Clone)]
 /// Transaction error
@@ -41,6 +43,8 @@ pub struct DALTxV10 {
     pub tx_doc: TransactionDocument,
     /// Index of sources destroyed by this transaction
     pub sources_destroyed: HashSet<UTXOIndexV10>,
+    /// Block in which the transaction is written
+    pub written_on: Blockstamp,
 }
 
 /// Apply transaction backwards
@@ -53,6 +57,17 @@ pub fn revert_tx(
     let tx_hash = tx_doc.get_hash();
     let sources_destroyed = &dal_tx.sources_destroyed;
 
+    // Remove tx from history of involved pubkeys and address
+    let (pubkeys, address) = get_tx_involved_accounts(dbs, &tx_doc)?;
+    let written_on = dal_tx.written_on.id;
+    for pubkey in pubkeys {
+        dbs.pubkeys_txs_db.delete(&(pubkey, written_on, tx_hash))?;
+    }
+    for conditions in address {
+        dbs.addresses_txs_db
+            .delete(&(conditions, written_on, tx_hash))?;
+    }
+
     // Index consumed utxos
     let consumed_utxos: Vec<UTXOV10> = tx_doc
         .get_outputs()
@@ -61,77 +76,54 @@ pub fn revert_tx(
         .map(|(tx_index, output)| UTXOV10(UTXOIndexV10(tx_hash, TxIndex(tx_index)), output.clone()))
         .collect();
     // Recalculate balance of consumed adress
-    let new_balances_consumed_adress = dbs.balances_db.read(|db| {
-        let mut new_balances_consumed_adress: HashMap<
-            UTXOConditionsGroup,
-            (SourceAmount, HashSet<UTXOIndexV10>),
-        > = HashMap::new();
-        for source in &consumed_utxos {
-            let source_amount = source.get_amount();
-            let conditions = source.get_conditions();
-            let (balance, new_sources_index) = if let Some((balance, sources_index)) =
-                new_balances_consumed_adress.get(&conditions)
-            {
-                let mut new_sources_index = sources_index.clone();
-                new_sources_index.remove(&source.0);
-                (*balance, new_sources_index)
-            } else if let Some((balance, sources_index)) = db.get(&conditions) {
-                let mut new_sources_index = sources_index.clone();
-                new_sources_index.remove(&source.0);
-                (*balance, new_sources_index)
+    let mut new_balances_consumed_adress: HashMap<
+        UTXOConditionsGroup,
+        (SourceAmount, HashSet<UTXOIndexV10>),
+    > = HashMap::new();
+    for source in &consumed_utxos {
+        let source_amount = source.get_amount();
+        let conditions = source.get_conditions();
+        let (balance, mut new_sources_index) =
+            if let Some((balance, sources_index)) = new_balances_consumed_adress.get(&conditions) {
+                (*balance, sources_index.clone())
+            } else if let Some((balance, sources_index)) = dbs.balances_db.get(&conditions)? {
+                (balance, sources_index)
             } else {
                 fatal_error!("Fail to revert tx : an output conditions don't exist in BalancesDB.")
             };
-            let new_balance = if balance >= source_amount {
-                balance - source_amount
-            } else {
-                fatal_error!("Fail to revert tx : an output revert cause negative balance.")
-            };
-            new_balances_consumed_adress.insert(conditions, (new_balance, new_sources_index));
-        }
-        new_balances_consumed_adress
-    })?;
+        new_sources_index.remove(&source.0);
+        let new_balance = if balance >= source_amount {
+            balance - source_amount
+        } else {
+            fatal_error!("Fail to revert tx : an output revert cause negative balance.")
+        };
+        new_balances_consumed_adress.insert(conditions, (new_balance, new_sources_index));
+    }
     // Remove consumed UTXOs
-    dbs.utxos_db.write(|db| {
-        for utxo_v10 in consumed_utxos {
-            db.remove(&utxo_v10.0);
-        }
-    })?;
+    for utxo_v10 in consumed_utxos {
+        dbs.utxos_db.delete(&utxo_v10.0)?;
+    }
     // Write new balance of consumed adress
-    dbs.balances_db.write(|db| {
-        for (conditions, (balance, sources_index)) in new_balances_consumed_adress {
-            db.insert(conditions, (balance, sources_index));
-        }
-    })?;
+    dbs.balances_db.extend(new_balances_consumed_adress)?;
     // Complete sources_destroyed
-    let sources_destroyed: HashMap<UTXOConditionsGroup, Vec<(UTXOIndexV10, SourceAmount)>> =
-        if !sources_destroyed.is_empty() {
-            dbs.tx_db.read(|db| {
-                let mut sources_destroyed_completed = HashMap::new();
-                for s_index in sources_destroyed {
-                    let tx_output = db
-                        .get(&s_index.0)
-                        .expect("Not find tx")
-                        .tx_doc
-                        .get_outputs()[(s_index.1).0]
-                        .clone();
-                    let mut sources_destroyed_for_same_address: Vec<(UTXOIndexV10, SourceAmount)> =
-                        sources_destroyed_completed
-                            .get(&tx_output.conditions.conditions)
-                            .cloned()
-                            .unwrap_or_default();
-                    sources_destroyed_for_same_address
-                        .push((*s_index, SourceAmount(tx_output.amount, tx_output.base)));
-                    sources_destroyed_completed.insert(
-                        tx_output.conditions.conditions,
-                        sources_destroyed_for_same_address,
-                    );
-                }
-                sources_destroyed_completed
-            })?
-        } else {
-            HashMap::with_capacity(0)
-        };
+    let mut sources_destroyed_completed: HashMap<
+        UTXOConditionsGroup,
+        Vec<(UTXOIndexV10, SourceAmount)>,
+    > = HashMap::new();
+    for s_index in sources_destroyed {
+        let tx_output = dbs
+            .tx_db
+            .get(&s_index.0)?
+            .expect("Not find tx")
+            .tx_doc
+            .get_outputs()[(s_index.1).0]
+            .clone();
+        sources_destroyed_completed
+            .entry(tx_output.conditions.conditions)
+            .or_insert_with(Vec::new)
+            .push((*s_index, SourceAmount(tx_output.amount, tx_output.base)));
+    }
+    let sources_destroyed = sources_destroyed_completed;
     // Index recreated sources
     let recreated_sources: HashMap<SourceIndexV10, SourceAmount> = tx_doc
         .get_inputs()
@@ -148,96 +140,67 @@ pub fn revert_tx(
         })
         .collect();
     // Find adress of recreated sources
-    let recreated_adress: HashMap<UTXOConditionsGroup, (SourceAmount, HashSet<UTXOIndexV10>)> =
-        dbs.utxos_db.read(|db| {
-            let mut recreated_adress: HashMap<
-                UTXOConditionsGroup,
-                (SourceAmount, HashSet<UTXOIndexV10>),
-            > = HashMap::new();
-            for (source_index, source_amount) in &recreated_sources {
-                if let SourceIndexV10::UTXO(utxo_index) = source_index {
-                    // Get utxo
-                    let utxo = db.get(&utxo_index).unwrap_or_else(|| {
-                        fatal_error!(
-                            "ApplyBLockError {} : unknow UTXO in inputs : {:?} !",
-                            blockstamp,
-                            utxo_index
-                        )
-                    });
-                    // Get utxo conditions(=address)
-                    let conditions = &utxo.conditions.conditions;
-                    // Calculate new balances datas for "conditions" address
-                    let (mut balance, mut utxos_index) = recreated_adress
-                        .get(conditions)
-                        .cloned()
-                        .unwrap_or_default();
-                    balance = balance + *source_amount;
-                    utxos_index.insert(*utxo_index);
-                    // Write new balances datas for "conditions" address
-                    recreated_adress.insert(conditions.clone(), (balance, utxos_index));
-                } else if let SourceIndexV10::UD(pubkey, _block_id) = source_index {
-                    let address =
-                        UTXOConditionsGroup::Single(TransactionOutputCondition::Sig(*pubkey));
-                    let (mut balance, utxos_index) =
-                        recreated_adress.get(&address).cloned().unwrap_or_default();
-                    balance = balance + *source_amount;
-                    recreated_adress.insert(address, (balance, utxos_index));
-                }
-            }
-            recreated_adress
-        })?;
+    let mut recreated_adress: HashMap<UTXOConditionsGroup, (SourceAmount, HashSet<UTXOIndexV10>)> =
+        HashMap::new();
+    for (source_index, source_amount) in &recreated_sources {
+        if let SourceIndexV10::UTXO(utxo_index) = source_index {
+            // Get utxo
+            let utxo = dbs.utxos_db.get(&utxo_index)?.unwrap_or_else(|| {
+                fatal_error!(
+                    "ApplyBLockError {} : unknow UTXO in inputs : {:?} !",
+                    blockstamp,
+                    utxo_index
+                )
+            });
+            // Get utxo conditions(=address)
+            let conditions = utxo.conditions.conditions;
+            // Calculate new balances datas for "conditions" address
+            let (balance, utxos_index) = recreated_adress.entry(conditions).or_default();
+            *balance = *balance + *source_amount;
+            utxos_index.insert(*utxo_index);
+        } else if let SourceIndexV10::UD(pubkey, _block_id) = source_index {
+            let address = UTXOConditionsGroup::Single(TransactionOutputCondition::Sig(*pubkey));
+            let (balance, _) = recreated_adress.entry(address).or_default();
+            *balance = *balance + *source_amount;
+        }
+    }
     // Recalculate balance of recreated adress
-    let new_balances_recreated_adress = dbs.balances_db.read(|db| {
-        let mut new_balances_recreated_adress = Vec::new();
-        for (conditions, (amount_recreated, adress_recreated_sources)) in recreated_adress {
-            let (mut balance, mut sources_indexs) =
-                if let Some((balance, sources_indexs)) = db.get(&conditions) {
-                    (*balance, sources_indexs.clone())
-                } else {
-                    (SourceAmount::default(), HashSet::new())
-                };
-            // Apply recreated sources (inputs)
-            balance = balance + amount_recreated;
-            for s_index in adress_recreated_sources {
-                sources_indexs.insert(s_index);
-            }
-            // Recreate destroy sources
-            if let Some(address_sources_destroyed) = sources_destroyed.get(&conditions) {
-                for (utxo_index, s_amout) in address_sources_destroyed {
-                    balance = balance + *s_amout;
-                    sources_indexs.insert(*utxo_index);
-                }
+    let mut new_balances_recreated_adress = Vec::new();
+    for (conditions, (amount_recreated, adress_recreated_sources)) in recreated_adress {
+        let (mut balance, mut sources_indexs) =
+            dbs.balances_db.get(&conditions)?.unwrap_or_default();
+        // Apply recreated sources (inputs)
+        balance = balance + amount_recreated;
+        for s_index in adress_recreated_sources {
+            sources_indexs.insert(s_index);
+        }
+        // Recreate destroy sources
+        if let Some(address_sources_destroyed) = sources_destroyed.get(&conditions) {
+            for (utxo_index, s_amout) in address_sources_destroyed {
+                balance = balance + *s_amout;
+                sources_indexs.insert(*utxo_index);
             }
-            new_balances_recreated_adress.push((conditions.clone(), (balance, sources_indexs)));
         }
-        new_balances_recreated_adress
-    })?;
+        new_balances_recreated_adress.push((conditions, (balance, sources_indexs)));
+    }
     // Write new balance of recreated adress
-    dbs.balances_db.write(|db| {
-        for (conditions, (balance, sources_index)) in new_balances_recreated_adress {
-            db.insert(conditions, (balance, sources_index));
-        }
-    })?;
+    dbs.balances_db.extend(new_balances_recreated_adress)?;
     // Recreate recreated sources
     for s_index in recreated_sources.keys() {
         if let SourceIndexV10::UTXO(utxo_index) = s_index {
-            let utxo_content = dbs.tx_db.read(|db| {
-                db.get(&utxo_index.0)
-                    .expect("Fatal error : not found Source TX of this utxo !")
-                    .tx_doc
-                    .get_outputs()[(utxo_index.1).0]
-                    .clone()
-            })?;
-            dbs.utxos_db.write(|db| {
-                db.insert(*utxo_index, utxo_content);
-            })?;
+            let utxo_content = dbs
+                .tx_db
+                .get(&utxo_index.0)?
+                .expect("Fatal error : not found Source TX of this utxo !")
+                .tx_doc
+                .get_outputs()[(utxo_index.1).0]
+                .clone();
+            dbs.utxos_db.insert(utxo_index, &utxo_content)?;
         } else if let SourceIndexV10::UD(pubkey, block_id) = s_index {
-            let mut pubkey_dus: HashSet<BlockNumber> = dbs
-                .du_db
-                .read(|db| db.get(&pubkey).cloned().unwrap_or_default())?;
-            pubkey_dus.insert(*block_id);
-            dbs.du_db.write(|db| {
-                db.insert(*pubkey, pubkey_dus);
+            dbs.du_db.update(pubkey, |pubkey_dus| {
+                pubkey_dus
+                    .get_or_insert_with(Default::default)
+                    .insert(*block_id);
             })?;
         }
     }
@@ -253,6 +216,8 @@ pub fn apply_and_write_tx(
     let mut tx_doc = tx_doc.clone();
     let tx_hash = tx_doc.get_hash();
     let mut sources_destroyed = HashSet::new();
+    // Find pubkeys and address involved in tx (before consuming sources)
+    let (involved_pubkeys, involved_address) = get_tx_involved_accounts(dbs, &tx_doc)?;
     // Index consumed sources
     let consumed_sources: HashMap<SourceIndexV10, SourceAmount> = tx_doc
         .get_inputs()
@@ -269,84 +234,60 @@ pub fn apply_and_write_tx(
         })
         .collect();
     // Find adress of consumed sources
-    let consumed_adress: HashMap<UTXOConditionsGroup, (SourceAmount, HashSet<UTXOIndexV10>)> =
-        dbs.utxos_db.read(|db| {
-            let mut consumed_adress: HashMap<
-                UTXOConditionsGroup,
-                (SourceAmount, HashSet<UTXOIndexV10>),
-            > = HashMap::new();
-            for (source_index, source_amount) in &consumed_sources {
-                if let SourceIndexV10::UTXO(utxo_index) = source_index {
-                    // Get utxo
-                    let utxo = db.get(&utxo_index).unwrap_or_else(|| {
-                        debug!("apply_tx=\"{:#?}\"", tx_doc);
-                        fatal_error!(
-                            "ApplyBLockError {} : unknow UTXO in inputs : {:?} !",
-                            blockstamp,
-                            utxo_index
-                        )
-                    });
-                    // Get utxo conditions(=address)
-                    let conditions = &utxo.conditions.conditions;
-                    // Calculate new balances datas for "conditions" address
-                    let (mut balance, mut utxos_index) =
-                        consumed_adress.get(conditions).cloned().unwrap_or_default();
-                    balance = balance + *source_amount;
-                    utxos_index.insert(*utxo_index);
-                    // Write new balances datas for "conditions" address
-                    consumed_adress.insert(conditions.clone(), (balance, utxos_index));
-                } else if let SourceIndexV10::UD(pubkey, _block_id) = source_index {
-                    let address =
-                        UTXOConditionsGroup::Single(TransactionOutputCondition::Sig(*pubkey));
-                    let (mut balance, utxos_index) =
-                        consumed_adress.get(&address).cloned().unwrap_or_default();
-                    balance = balance + *source_amount;
-                    consumed_adress.insert(address, (balance, utxos_index));
-                }
-            }
-            consumed_adress
-        })?;
+    let mut consumed_adress: HashMap<UTXOConditionsGroup, (SourceAmount, HashSet<UTXOIndexV10>)> =
+        HashMap::new();
+    for (source_index, source_amount) in &consumed_sources {
+        if let SourceIndexV10::UTXO(utxo_index) = source_index {
+            // Get utxo
+            let utxo = dbs.utxos_db.get(&utxo_index)?.unwrap_or_else(|| {
+                debug!("apply_tx=\"{:#?}\"", tx_doc);
+                fatal_error!(
+                    "ApplyBLockError {} : unknow UTXO in inputs : {:?} !",
+                    blockstamp,
+                    utxo_index
+                )
+            });
+            // Get utxo conditions(=address)
+            let conditions = utxo.conditions.conditions;
+            // Calculate new balances datas for "conditions" address
+            let (balance, utxos_index) = consumed_adress.entry(conditions).or_default();
+            *balance = *balance + *source_amount;
+            utxos_index.insert(*utxo_index);
+        } else if let SourceIndexV10::UD(pubkey, _block_id) = source_index {
+            let address = UTXOConditionsGroup::Single(TransactionOutputCondition::Sig(*pubkey));
+            let (balance, _) = consumed_adress.entry(address).or_default();
+            *balance = *balance + *source_amount;
+        }
+    }
     // Recalculate balance of consumed adress
-    let new_balances_consumed_adress = dbs.balances_db.read(|db| {
-        let mut new_balances_consumed_adress = Vec::new();
-        for (conditions, (amount_consumed, adress_consumed_sources)) in consumed_adress {
-            if let Some((balance, sources)) = db.get(&conditions) {
-                let mut new_balance = *balance - amount_consumed;
-                if new_balance.0 < TxAmount(100) {
-                    sources_destroyed = sources.union(&sources_destroyed).cloned().collect();
-                    new_balance = SourceAmount(TxAmount(0), new_balance.1);
-                }
-                let mut new_sources_index = sources.clone();
-                for source in adress_consumed_sources {
-                    new_sources_index.remove(&source);
-                }
-                new_balances_consumed_adress
-                    .push((conditions.clone(), (new_balance, new_sources_index)));
-            } else {
-                fatal_error!("Apply Tx : try to consume a source, but the owner address is not found in balances db : {:?}", conditions)
+    let mut new_balances_consumed_adress = Vec::new();
+    for (conditions, (amount_consumed, adress_consumed_sources)) in consumed_adress {
+        if let Some((balance, sources)) = dbs.balances_db.get(&conditions)? {
+            let mut new_balance = balance - amount_consumed;
+            if new_balance.0 < TxAmount(100) {
+                sources_destroyed = sources.union(&sources_destroyed).cloned().collect();
+                new_balance = SourceAmount(TxAmount(0), new_balance.1);
+            }
+            let mut new_sources_index = sources;
+            for source in adress_consumed_sources {
+                new_sources_index.remove(&source);
             }
+            new_balances_consumed_adress.push((conditions, (new_balance, new_sources_index)));
+        } else {
+            fatal_error!("Apply Tx : try to consume a source, but the owner address is not found in balances db : {:?}", conditions)
         }
-        new_balances_consumed_adress
-    })?;
+    }
     // Write new balance of consumed adress
-    dbs.balances_db.write(|db| {
-        for (conditions, (balance, sources_index)) in new_balances_consumed_adress {
-            db.insert(conditions, (balance, sources_index));
-        }
-    })?;
+    dbs.balances_db.extend(new_balances_consumed_adress)?;
     // Remove consumed sources
     for source_index in consumed_sources.keys() {
         if let SourceIndexV10::UTXO(utxo_index) = source_index {
-            dbs.utxos_db.write(|db| {
-                db.remove(utxo_index);
-            })?;
+            dbs.utxos_db.delete(utxo_index)?;
         } else if let SourceIndexV10::UD(pubkey, block_id) = source_index {
-            let mut pubkey_dus: HashSet<BlockNumber> = dbs
-                .du_db
-                .read(|db| db.get(&pubkey).cloned().unwrap_or_default())?;
-            pubkey_dus.remove(block_id);
-            dbs.du_db.write(|db| {
-                db.insert(*pubkey, pubkey_dus);
+            dbs.du_db.update(pubkey, |pubkey_dus| {
+                pubkey_dus
+                    .get_or_insert_with(Default::default)
+                    .remove(block_id);
             })?;
         }
     }
@@ -367,60 +308,85 @@ pub fn apply_and_write_tx(
         .map(|(tx_index, output)| UTXOV10(UTXOIndexV10(tx_hash, TxIndex(tx_index)), output.clone()))
         .collect();
     // Recalculate balance of supplied adress
-    let new_balances_supplied_adress = dbs.balances_db.read(|db| {
-        let mut new_balances_supplied_adress: HashMap<
-            UTXOConditionsGroup,
-            (SourceAmount, HashSet<UTXOIndexV10>),
-        > = HashMap::new();
-        for source in &created_utxos {
-            let source_amount = source.get_amount();
-            let conditions = source.get_conditions();
-            let (balance, new_sources_index) = if let Some((balance, sources_index)) =
-                new_balances_supplied_adress.get(&conditions)
-            {
-                let mut new_sources_index = sources_index.clone();
-                new_sources_index.insert(source.0);
-                (*balance, new_sources_index)
-            } else if let Some((balance, sources_index)) = db.get(&conditions) {
-                let mut new_sources_index = sources_index.clone();
-                new_sources_index.insert(source.0);
-                (*balance, new_sources_index)
+    let mut new_balances_supplied_adress: HashMap<
+        UTXOConditionsGroup,
+        (SourceAmount, HashSet<UTXOIndexV10>),
+    > = HashMap::new();
+    for source in &created_utxos {
+        let source_amount = source.get_amount();
+        let conditions = source.get_conditions();
+        let (balance, mut new_sources_index) =
+            if let Some((balance, sources_index)) = new_balances_supplied_adress.get(&conditions) {
+                (*balance, sources_index.clone())
             } else {
-                let mut new_sources_index = HashSet::new();
-                new_sources_index.insert(source.0);
-                (SourceAmount::default(), new_sources_index)
+                dbs.balances_db.get(&conditions)?.unwrap_or_default()
             };
-            new_balances_supplied_adress
-                .insert(conditions, (balance + source_amount, new_sources_index));
-        }
+        new_sources_index.insert(source.0);
         new_balances_supplied_adress
-    })?;
+            .insert(conditions, (balance + source_amount, new_sources_index));
+    }
     // Insert created UTXOs
-    dbs.utxos_db.write(|db| {
-        for utxo_v10 in created_utxos {
-            db.insert(utxo_v10.0, utxo_v10.1);
-        }
-    })?;
+    dbs.utxos_db.extend(
+        created_utxos
+            .into_iter()
+            .map(|utxo_v10| (utxo_v10.0, utxo_v10.1)),
+    )?;
     // Write new balance of supplied adress
-    dbs.balances_db.write(|db| {
-        for (conditions, (balance, sources_index)) in new_balances_supplied_adress {
-            db.insert(conditions, (balance, sources_index));
-        }
-    })?;
+    dbs.balances_db.extend(new_balances_supplied_adress)?;
     // Write tx
     tx_doc.reduce();
-    dbs.tx_db.write(|db| {
-        db.insert(
-            tx_hash,
-            DALTxV10 {
-                tx_doc,
-                sources_destroyed,
-            },
-        );
-    })?;
+    dbs.tx_db.insert(
+        &tx_hash,
+        &DALTxV10 {
+            tx_doc,
+            sources_destroyed,
+            written_on: *blockstamp,
+        },
+    )?;
+    // Write tx in history of involved pubkeys and address
+    dbs.pubkeys_txs_db.extend(
+        involved_pubkeys
+            .into_iter()
+            .map(|pubkey| ((pubkey, blockstamp.id, tx_hash), ())),
+    )?;
+    dbs.addresses_txs_db.extend(
+        involved_address
+            .into_iter()
+            .map(|conditions| ((conditions, blockstamp.id, tx_hash), ())),
+    )?;
     Ok(())
 }
 
+/// Get the pubkeys (issuers and receivers) and the address (consumed and supplied) involved in a transaction
+fn get_tx_involved_accounts(
+    dbs: &CurrencyV10DBs,
+    tx_doc: &TransactionDocument,
+) -> Result<(HashSet<PubKey>, HashSet<UTXOConditionsGroup>), DALError> {
+    let mut pubkeys: HashSet<PubKey> = tx_doc.issuers().iter().cloned().collect();
+    let mut address = HashSet::new();
+    for output in tx_doc.get_outputs() {
+        pubkeys.extend(output.conditions.conditions.get_sig_pubkeys());
+        address.insert(output.conditions.conditions.clone());
+    }
+    for input in tx_doc.get_inputs() {
+        match *input {
+            TransactionInput::D(_, _, pubkey, _) => {
+                address.insert(UTXOConditionsGroup::Single(
+                    TransactionOutputCondition::Sig(pubkey),
+                ));
+            }
+            TransactionInput::T(_, _, hash, tx_index) => {
+                if let Some(dal_tx) = dbs.tx_db.get(&hash)? {
+                    if let Some(output) = dal_tx.tx_doc.get_outputs().get(tx_index.0) {
+                        address.insert(output.conditions.conditions.clone());
+                    }
+                }
+            }
+        }
+    }
+    Ok((pubkeys, address))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -471,7 +437,7 @@ mod tests {
     #[test]
     fn apply_and_revert_one_tx() {
         // Get document of first g1 transaction
-        let tx_doc = build_first_tx_of_g1();
+        let mut tx_doc = build_first_tx_of_g1();
         assert_eq!(tx_doc.verify_signatures(), VerificationResult::Valid());
         // Get pubkey of receiver
         let tortue_pubkey = PubKey::Ed25519(
@@ -493,23 +459,17 @@ mod tests {
         // Check members balance
         let cgeek_new_balance = currency_dbs
             .balances_db
-            .read(|db| {
-                db.get(&UTXOConditionsGroup::Single(
-                    TransactionOutputCondition::Sig(tx_doc.issuers()[0]),
-                ))
-                .cloned()
-            })
+            .get(&UTXOConditionsGroup::Single(
+                TransactionOutputCondition::Sig(tx_doc.issuers()[0]),
+            ))
             .expect("Fail to read cgeek new balance")
             .expect("Error : cgeek is not referenced in balances_db !");
         assert_eq!(cgeek_new_balance.0, SourceAmount(TxAmount(1000), TxBase(0)));
         let tortue_new_balance = currency_dbs
             .balances_db
-            .read(|db| {
-                db.get(&UTXOConditionsGroup::Single(
-                    TransactionOutputCondition::Sig(tortue_pubkey),
-                ))
-                .cloned()
-            })
+            .get(&UTXOConditionsGroup::Single(
+                TransactionOutputCondition::Sig(tortue_pubkey),
+            ))
             .expect("Fail to read receiver new balance")
             .expect("Error : receiver is not referenced in balances_db !");
         assert_eq!(
@@ -524,12 +484,9 @@ mod tests {
         // Check issuer new balance
         let cgeek_new_balance = currency_dbs
             .balances_db
-            .read(|db| {
-                db.get(&UTXOConditionsGroup::Single(
-                    TransactionOutputCondition::Sig(tx_doc.issuers()[0]),
-                ))
-                .cloned()
-            })
+            .get(&UTXOConditionsGroup::Single(
+                TransactionOutputCondition::Sig(tx_doc.issuers()[0]),
+            ))
             .expect("Fail to read cgeek new balance")
             .expect("Error : cgeek is not referenced in balances_db !");
         assert_eq!(cgeek_new_balance.0, SourceAmount(TxAmount(999), TxBase(0)));
@@ -537,12 +494,9 @@ mod tests {
         // Check receiver new balance
         let receiver_new_balance = currency_dbs
             .balances_db
-            .read(|db| {
-                db.get(&UTXOConditionsGroup::Single(
-                    TransactionOutputCondition::Sig(tortue_pubkey),
-                ))
-                .cloned()
-            })
+            .get(&UTXOConditionsGroup::Single(
+                TransactionOutputCondition::Sig(tortue_pubkey),
+            ))
             .expect("Fail to read receiver new balance")
             .expect("Error : receiver is not referenced in balances_db !");
         assert_eq!(
@@ -550,7 +504,44 @@ mod tests {
             SourceAmount(TxAmount(1001), TxBase(0))
         );
 
+        // Check txs history of issuer and receiver
+        let tx_hash = tx_doc.get_hash();
+        for pubkey in &[tx_doc.issuers()[0], tortue_pubkey] {
+            assert_eq!(
+                readers::transaction::get_pubkey_txs_history(
+                    &currency_dbs.pubkeys_txs_db,
+                    pubkey,
+                    filters::PagingFilter::default(),
+                    blockstamp.id,
+                )
+                .expect("Fail to read pubkey txs history"),
+                vec![(blockstamp.id, tx_hash)]
+            );
+            assert_eq!(
+                readers::transaction::get_address_txs_history(
+                    &currency_dbs.addresses_txs_db,
+                    &UTXOConditionsGroup::Single(TransactionOutputCondition::Sig(*pubkey)),
+                    filters::PagingFilter::default(),
+                    blockstamp.id,
+                )
+                .expect("Fail to read address txs history"),
+                vec![(blockstamp.id, tx_hash)]
+            );
+        }
+        assert!(readers::transaction::get_pubkey_txs_history(
+            &currency_dbs.pubkeys_txs_db,
+            &tx_doc.issuers()[0],
+            filters::PagingFilter {
+                to: Some(BlockNumber(51)),
+                ..filters::PagingFilter::default()
+            },
+            blockstamp.id,
+        )
+        .expect("Fail to read pubkey txs history")
+        .is_empty());
+
         // Revert first g1 tx
+        let written_on = blockstamp;
         let blockstamp = unwrap!(Blockstamp::from_string(
             "52-000057D4B29AF6DADB16F841F19C54C00EB244CECA9C8F2D4839D54E5F91451C"
         ));
@@ -560,19 +551,27 @@ mod tests {
             &DALTxV10 {
                 tx_doc: tx_doc.clone(),
                 sources_destroyed: HashSet::with_capacity(0),
+                written_on,
             },
         )
         .expect("Fail to revert first g1 tx");
 
+        // Check txs history is cleared
+        assert!(currency_dbs
+            .pubkeys_txs_db
+            .is_empty()
+            .expect("Fail to read pubkeys txs db"));
+        assert!(currency_dbs
+            .addresses_txs_db
+            .is_empty()
+            .expect("Fail to read addresses txs db"));
+
         // Check issuer new balance
         let cgeek_new_balance = currency_dbs
             .balances_db
-            .read(|db| {
-                db.get(&UTXOConditionsGroup::Single(
-                    TransactionOutputCondition::Sig(tx_doc.issuers()[0]),
-                ))
-                .cloned()
-            })
+            .get(&UTXOConditionsGroup::Single(
+                TransactionOutputCondition::Sig(tx_doc.issuers()[0]),
+            ))
             .expect("Fail to read cgeek new balance")
             .expect("Error : cgeek is not referenced in balances_db !");
         assert_eq!(cgeek_new_balance.0, SourceAmount(TxAmount(1000), TxBase(0)));
@@ -580,12 +579,9 @@ mod tests {
         // Check receiver new balance
         let receiver_new_balance = currency_dbs
             .balances_db
-            .read(|db| {
-                db.get(&UTXOConditionsGroup::Single(
-                    TransactionOutputCondition::Sig(tortue_pubkey),
-                ))
-                .cloned()
-            })
+            .get(&UTXOConditionsGroup::Single(
+                TransactionOutputCondition::Sig(tortue_pubkey),
+            ))
             .expect("Fail to read receiver new balance")
             .expect("Error : receiver is not referenced in balances_db !");
         assert_eq!(
@@ -15,9 +15,10 @@
 
 use crate::*;
 use dubp_documents::documents::transaction::*;
+use dup_crypto::hashs::Hash;
 use dup_crypto::keys::*;
+use durs_blockchain_dal::entities::fork_tree::ForkTree;
 use durs_module::DursConfTrait;
-use durs_wot::data::rusty::RustyWebOfTrust;
 use durs_wot::data::WebOfTrust;
 use durs_wot::operations::distance::{DistanceCalculator, WotDistance, WotDistanceParameters};
 use std::time::*;
@@ -33,6 +34,11 @@ pub enum DBExWotQuery {
     ListMembers(bool),
     /// Ask member datas
     MemberDatas(String),
+    /// Show the certifications issued and received by an identity (uid or pubkey)
+    Certs(String),
+    /// Search identities (the optional block number overrides the membership expiry time filter
+    /// with the median time of this block)
+    Identities(IdentitiesFilter, Option<BlockNumber>),
 }
 
 #[derive(Debug, Clone)]
@@ -40,6 +46,23 @@ pub enum DBExWotQuery {
 pub enum DBExTxQuery {
     /// Ask balance of an address (pubkey or uid)
     Balance(String),
+    /// Show a transaction (by hash)
+    Tx(String),
+    /// Show a page of the transactions history of an address (pubkey or uid)
+    History(String, PagingFilter),
+    /// Show the sources (UTXOs and UDs) of an address (pubkey or uid)
+    Sources(String),
+}
+
+#[derive(Debug, Clone)]
+/// Query for blocks databases explorer
+pub enum DBExBlockQuery {
+    /// Show a block (by number or hash)
+    Block(String),
+    /// Show the headers of a range of blocks
+    Headers(u32, u32),
+    /// Show the fork tree (or the orphan blocks if true)
+    ForkTree(bool),
 }
 
 #[derive(Debug, Clone)]
@@ -49,19 +72,322 @@ pub enum DBExQuery {
     WotQuery(DBExWotQuery),
     /// Tx query
     TxQuery(DBExTxQuery),
+    /// Block query
+    BlockQuery(DBExBlockQuery),
 }
 
 pub fn dbex<DC: DursConfTrait>(profile_path: PathBuf, conf: &DC, csv: bool, query: &DBExQuery) {
     match *query {
         DBExQuery::WotQuery(ref wot_query) => dbex_wot(profile_path, conf, csv, wot_query),
         DBExQuery::TxQuery(ref tx_query) => dbex_tx(profile_path, conf, csv, tx_query),
+        DBExQuery::BlockQuery(ref block_query) => dbex_block(profile_path, conf, csv, block_query),
+    }
+}
+
+/// Format an amount in cents
+fn format_amount(amount: isize) -> String {
+    let sign = if amount < 0 { "-" } else { "" };
+    format!("{}{}.{:02}", sign, amount.abs() / 100, amount.abs() % 100)
+}
+
+/// Quote a CSV field (the quotes it contains are doubled)
+fn csv_field<T: ToString>(field: T) -> String {
+    format!("\"{}\"", field.to_string().replace('"', "\"\""))
+}
+
+/// Print a CSV record of quoted fields
+fn print_csv_record(fields: &[String]) {
+    println!("{}", fields.join(","));
+}
+
+/// Print the header of a CSV output (comma separated column names)
+fn print_csv_header(columns: &str) {
+    print_csv_record(&columns.split(',').map(csv_field).collect::<Vec<String>>());
+}
+
+/// Get the amount of a source in cents
+fn source_amount(amount: TxAmount, base: TxBase) -> isize {
+    amount.0 * 10isize.pow(base.0 as u32)
+}
+
+/// Get the public key of an address (pubkey or uid)
+fn get_address_pubkey(wot_databases: &WotsV10DBs, address_str: &str) -> Option<PubKey> {
+    if let Ok(ed25519_pubkey) = ed25519::PublicKey::from_base58(address_str) {
+        Some(PubKey::Ed25519(ed25519_pubkey))
+    } else {
+        durs_blockchain_dal::readers::identity::get_pubkey_from_uid(
+            &wot_databases.idties_indexes_db,
+            address_str,
+        )
+        .expect("get_uid : DALError")
+    }
+}
+
+/// Print the header of a block
+fn print_block_header(block: &BlockDocument, csv: bool) {
+    if csv {
+        print_csv_record(&[
+            csv_field(block.number),
+            csv_field(block.hash.map(|hash| hash.to_string()).unwrap_or_default()),
+            csv_field(block.issuers[0]),
+            csv_field(block.median_time),
+            csv_field(block.members_count),
+            csv_field(block.dividend.map(|ud| ud.to_string()).unwrap_or_default()),
+            csv_field(block.unit_base),
+            csv_field(block.transactions.len()),
+        ]);
+    } else {
+        println!(
+            "#{} : hash={}, issuer={}, median_time={}, members={}, dividend={}, txs={}",
+            block.number,
+            block.hash.map(|hash| hash.to_string()).unwrap_or_default(),
+            block.issuers[0],
+            block.median_time,
+            block.members_count,
+            block
+                .dividend
+                .map(|ud| format!("{}e{}", ud, block.unit_base))
+                .unwrap_or_else(|| "none".to_owned()),
+            block.transactions.len(),
+        );
+    }
+}
+
+pub fn dbex_block<DC: DursConfTrait>(
+    profile_path: PathBuf,
+    conf: &DC,
+    csv: bool,
+    query: &DBExBlockQuery,
+) {
+    // Get db path
+    let db_path = durs_conf::get_blockchain_db_path(profile_path, &conf.currency());
+
+    // Open databases
+    let load_dbs_begin = SystemTime::now();
+    let blocks_databases = BlocksV10DBs::open(Some(&db_path));
+    let mut forks_databases = ForksDBs::open(Some(&db_path));
+    forks_databases.limits = get_forks_limits(conf);
+    let load_dbs_duration = SystemTime::now()
+        .duration_since(load_dbs_begin)
+        .expect("duration_since error !");
+    println!(
+        "Databases loaded in {}.{:03} seconds.",
+        load_dbs_duration.as_secs(),
+        load_dbs_duration.subsec_millis()
+    );
+    let current_blockstamp =
+        durs_blockchain_dal::readers::block::get_current_blockstamp(&blocks_databases)
+            .expect("get_current_blockstamp : DALError")
+            .unwrap_or_default();
+
+    match *query {
+        DBExBlockQuery::Block(ref block_id_or_hash) => {
+            let dal_block = if let Ok(block_number) = block_id_or_hash.parse::<u32>() {
+                durs_blockchain_dal::readers::block::get_block(
+                    &blocks_databases.blockchain_db,
+                    None,
+                    &Blockstamp {
+                        id: BlockNumber(block_number),
+                        hash: BlockHash::default(),
+                    },
+                )
+                .expect("get_block : DALError")
+            } else if let Ok(hash) = Hash::from_hex(block_id_or_hash) {
+                durs_blockchain_dal::readers::block::get_block_by_hash(
+                    &blocks_databases.blockchain_db,
+                    &forks_databases.fork_blocks_db,
+                    &BlockHash(hash),
+                )
+                .expect("get_block_by_hash : DALError")
+            } else {
+                println!("Invalid block number or hash \"{}\" !", block_id_or_hash);
+                return;
+            };
+            let block = if let Some(dal_block) = dal_block {
+                dal_block.block
+            } else {
+                println!("Block \"{}\" not found !", block_id_or_hash);
+                return;
+            };
+            let in_main_branch = block.number.0 <= current_blockstamp.id.0
+                && durs_blockchain_dal::readers::block::get_block_hash(
+                    &blocks_databases.blockchain_db,
+                    block.number,
+                )
+                .expect("get_block_hash : DALError")
+                    == block.hash;
+            if csv {
+                print_csv_header("number,hash,issuer,median_time,members_count,dividend,unit_base,txs_count,identities,joiners,actives,leavers,revoked,excluded,certifications,main_branch");
+                print_csv_record(&[
+                    csv_field(block.number),
+                    csv_field(block.hash.map(|hash| hash.to_string()).unwrap_or_default()),
+                    csv_field(block.issuers[0]),
+                    csv_field(block.median_time),
+                    csv_field(block.members_count),
+                    csv_field(block.dividend.map(|ud| ud.to_string()).unwrap_or_default()),
+                    csv_field(block.unit_base),
+                    csv_field(block.transactions.len()),
+                    csv_field(block.identities.len()),
+                    csv_field(block.joiners.len()),
+                    csv_field(block.actives.len()),
+                    csv_field(block.leavers.len()),
+                    csv_field(block.revoked.len()),
+                    csv_field(block.excluded.len()),
+                    csv_field(block.certifications.len()),
+                    csv_field(in_main_branch),
+                ]);
+            } else {
+                println!(
+                    "Block #{} ({}) :",
+                    block.number,
+                    if in_main_branch {
+                        "main branch"
+                    } else {
+                        "fork"
+                    }
+                );
+                println!(
+                    "  hash: {}",
+                    block.hash.map(|hash| hash.to_string()).unwrap_or_default()
+                );
+                println!("  previous hash: {}", block.previous_hash);
+                println!("  issuer: {}", block.issuers[0]);
+                println!("  time: {}", block.time);
+                println!("  median time: {}", block.median_time);
+                println!("  pow min: {}", block.pow_min);
+                println!("  members count: {}", block.members_count);
+                if let Some(dividend) = block.dividend {
+                    println!("  dividend: {}e{}", dividend, block.unit_base);
+                }
+                println!("  identities: {}", block.identities.len());
+                println!("  joiners: {}", block.joiners.len());
+                println!("  actives: {}", block.actives.len());
+                println!("  leavers: {}", block.leavers.len());
+                println!("  revoked: {}", block.revoked.len());
+                println!("  excluded: {}", block.excluded.len());
+                println!("  certifications: {}", block.certifications.len());
+                println!("  transactions: {}", block.transactions.len());
+            }
+        }
+        DBExBlockQuery::Headers(begin, end) => {
+            if begin > end {
+                println!("Invalid range #{}-#{} !", begin, end);
+                return;
+            }
+            let blocks = durs_blockchain_dal::readers::block::get_blocks_in_local_blockchain(
+                &blocks_databases.blockchain_db,
+                BlockNumber(begin),
+                end - begin + 1,
+            )
+            .expect("get_blocks_in_local_blockchain : DALError");
+            if csv {
+                print_csv_header(
+                    "number,hash,issuer,median_time,members_count,dividend,unit_base,txs_count",
+                );
+            }
+            for block in &blocks {
+                print_block_header(block, csv);
+            }
+        }
+        DBExBlockQuery::ForkTree(true) => {
+            let limits = forks_databases.limits;
+            let mut orphans: Vec<(Blockstamp, PreviousBlockstamp)> = forks_databases
+                .orphan_blocks_db
+                .entries()
+                .expect("Fail to read orphan blocks db")
+                .into_iter()
+                .flat_map(|(previous_blockstamp, orphan_blocks)| {
+                    orphan_blocks
+                        .into_iter()
+                        .map(move |orphan_block| (orphan_block.blockstamp(), previous_blockstamp))
+                })
+                .collect();
+            orphans.sort_by_key(|(blockstamp, _)| blockstamp.id);
+            if csv {
+                print_csv_header("orphan,previous_blockstamp");
+            } else {
+                println!(
+                    "{} orphan blocks (max count {}, max age {} blocks), current block {} :",
+                    orphans.len(),
+                    limits.max_orphans_count,
+                    limits.max_orphan_age,
+                    current_blockstamp
+                );
+            }
+            for (blockstamp, previous_blockstamp) in orphans {
+                if csv {
+                    print_csv_record(&[csv_field(blockstamp), csv_field(previous_blockstamp)]);
+                } else {
+                    println!("  {} (previous {})", blockstamp, previous_blockstamp);
+                }
+            }
+        }
+        DBExBlockQuery::ForkTree(false) => {
+            let fork_tree = forks_databases
+                .fork_tree_db
+                .read(ForkTree::clone)
+                .expect("Fail to read fork tree db");
+            let mut sheets = fork_tree.get_sheets();
+            sheets.sort_by_key(|(_, blockstamp)| blockstamp.id);
+            if csv {
+                print_csv_header("sheet,main_branch,branch_length,first_block");
+            } else {
+                println!(
+                    "Fork tree : {} blocks (fork window size {}), {} orphan blocks, current block {}.",
+                    fork_tree.size(),
+                    forks_databases.limits.fork_window_size,
+                    forks_databases
+                        .orphan_blocks_db
+                        .entries()
+                        .expect("Fail to read orphan blocks db")
+                        .iter()
+                        .map(|(_, orphan_blocks)| orphan_blocks.len())
+                        .sum::<usize>(),
+                    current_blockstamp
+                );
+            }
+            for (node_id, sheet_blockstamp) in sheets {
+                let in_main_branch = fork_tree.get_main_branch_block_hash(sheet_blockstamp.id)
+                    == Some(sheet_blockstamp.hash);
+                let branch = if in_main_branch {
+                    vec![sheet_blockstamp]
+                } else {
+                    fork_tree.get_fork_branch(node_id)
+                };
+                if csv {
+                    print_csv_record(&[
+                        csv_field(sheet_blockstamp),
+                        csv_field(in_main_branch),
+                        csv_field(branch.len()),
+                        csv_field(branch[0]),
+                    ]);
+                } else if in_main_branch {
+                    println!("Main branch : head {}", sheet_blockstamp);
+                } else {
+                    println!(
+                        "Fork branch : head {}, {} blocks since {} :",
+                        sheet_blockstamp,
+                        branch.len(),
+                        branch[0]
+                            .id
+                            .0
+                            .checked_sub(1)
+                            .map(|fork_point| format!("block #{}", fork_point))
+                            .unwrap_or_else(|| "genesis".to_owned())
+                    );
+                    for blockstamp in branch {
+                        println!("  {}", blockstamp);
+                    }
+                }
+            }
+        }
     }
 }
 
 pub fn dbex_tx<DC: DursConfTrait>(
     profile_path: PathBuf,
     conf: &DC,
-    _csv: bool,
+    csv: bool,
     query: &DBExTxQuery,
 ) {
     // Get db path
@@ -69,7 +395,7 @@ pub fn dbex_tx<DC: DursConfTrait>(
 
     // Open databases
     let load_dbs_begin = SystemTime::now();
-    //let blocks_databases = BlocksV10DBs::open(Some(&db_path));
+    let blocks_databases = BlocksV10DBs::open(Some(&db_path));
     let currency_databases = CurrencyV10DBs::open(Some(&db_path));
     let wot_databases = WotsV10DBs::open(Some(&db_path));
     let load_dbs_duration = SystemTime::now()
@@ -83,15 +409,7 @@ pub fn dbex_tx<DC: DursConfTrait>(
     let req_process_begin = SystemTime::now();
     match *query {
         DBExTxQuery::Balance(ref address_str) => {
-            let pubkey = if let Ok(ed25519_pubkey) = ed25519::PublicKey::from_base58(address_str) {
-                PubKey::Ed25519(ed25519_pubkey)
-            } else if let Some(pubkey) =
-                durs_blockchain_dal::readers::identity::get_pubkey_from_uid(
-                    &wot_databases.identities_db,
-                    address_str,
-                )
-                .expect("get_uid : DALError")
-            {
+            let pubkey = if let Some(pubkey) = get_address_pubkey(&wot_databases, address_str) {
                 pubkey
             } else {
                 println!("This address doesn't exist!");
@@ -104,11 +422,237 @@ pub fn dbex_tx<DC: DursConfTrait>(
             )
             .expect("get_address_balance : DALError")
             .expect("Address not found in balances DB.");
-            println!(
-                "Balance={},{} Ğ1",
-                (address_balance.0).0 / 100,
-                (address_balance.0).0 % 100
-            );
+            if csv {
+                print_csv_record(&[
+                    csv_field(pubkey),
+                    csv_field(format_amount((address_balance.0).0)),
+                ]);
+            } else {
+                println!(
+                    "Balance={},{} Ğ1",
+                    (address_balance.0).0 / 100,
+                    (address_balance.0).0 % 100
+                );
+            }
+        }
+        DBExTxQuery::Tx(ref hash_str) => {
+            let hash = if let Ok(hash) = Hash::from_hex(hash_str) {
+                hash
+            } else {
+                println!("Invalid transaction hash \"{}\" !", hash_str);
+                return;
+            };
+            let dal_tx = if let Some(dal_tx) =
+                durs_blockchain_dal::readers::transaction::get_tx(&currency_databases.tx_db, &hash)
+                    .expect("get_tx : DALError")
+            {
+                dal_tx
+            } else {
+                println!("Transaction {} not found !", hash);
+                return;
+            };
+            let tx_doc = &dal_tx.tx_doc;
+            let outputs_amount: isize = tx_doc
+                .get_outputs()
+                .iter()
+                .map(|output| source_amount(output.amount, output.base))
+                .sum();
+            if csv {
+                print_csv_header(
+                    "hash,written_on,blockstamp,issuers,inputs_count,outputs_count,amount,comment",
+                );
+                print_csv_record(&[
+                    csv_field(hash),
+                    csv_field(dal_tx.written_on),
+                    csv_field(tx_doc.blockstamp()),
+                    csv_field(
+                        tx_doc
+                            .issuers()
+                            .iter()
+                            .map(ToString::to_string)
+                            .collect::<Vec<String>>()
+                            .join(" "),
+                    ),
+                    csv_field(tx_doc.get_inputs().len()),
+                    csv_field(tx_doc.get_outputs().len()),
+                    csv_field(format_amount(outputs_amount)),
+                    csv_field(tx_doc.get_comment()),
+                ]);
+            } else {
+                println!("Transaction {} :", hash);
+                println!("  written on: {}", dal_tx.written_on);
+                println!("  blockstamp: {}", tx_doc.blockstamp());
+                println!("  issuers:");
+                for issuer in tx_doc.issuers() {
+                    println!("    {}", issuer);
+                }
+                println!("  inputs:");
+                for input in tx_doc.get_inputs() {
+                    println!("    {}", input.to_string());
+                }
+                println!("  unlocks:");
+                for unlock in tx_doc.get_unlocks() {
+                    println!("    {}", unlock.to_string());
+                }
+                println!("  outputs:");
+                for output in tx_doc.get_outputs() {
+                    println!("    {}", output.to_string());
+                }
+                println!("  amount: {}", format_amount(outputs_amount));
+                println!("  comment: {}", tx_doc.get_comment());
+            }
+        }
+        DBExTxQuery::History(ref address_str, paging) => {
+            let pubkey = if let Some(pubkey) = get_address_pubkey(&wot_databases, address_str) {
+                pubkey
+            } else {
+                println!("This address doesn't exist!");
+                return;
+            };
+            let current_blockstamp =
+                durs_blockchain_dal::readers::block::get_current_blockstamp(&blocks_databases)
+                    .expect("get_current_blockstamp : DALError")
+                    .unwrap_or_default();
+            let txs = durs_blockchain_dal::readers::transaction::get_pubkey_txs(
+                &currency_databases,
+                &pubkey,
+                paging,
+                current_blockstamp.id,
+            )
+            .expect("get_pubkey_txs : DALError");
+            if csv {
+                print_csv_header("block,median_time,hash,amount,counterparts,comment");
+            } else {
+                println!("{} transactions :", txs.len());
+            }
+            for (hash, dal_tx) in txs {
+                let tx_doc = &dal_tx.tx_doc;
+                let median_time =
+                    durs_blockchain_dal::readers::block::get_block_in_local_blockchain(
+                        &blocks_databases.blockchain_db,
+                        dal_tx.written_on.id,
+                    )
+                    .expect("get_block_in_local_blockchain : DALError")
+                    .map(|block| block.median_time)
+                    .unwrap_or_default();
+                // Sent amount (outputs to other accounts) or received amount (outputs to pubkey)
+                let is_issuer = tx_doc.issuers().contains(&pubkey);
+                let mut amount = 0;
+                let mut counterparts = Vec::new();
+                for output in tx_doc.get_outputs() {
+                    let to_pubkey =
+                        durs_blockchain_dal::readers::transaction::output_involve_pubkey(
+                            output, &pubkey,
+                        );
+                    if is_issuer && !to_pubkey {
+                        amount -= source_amount(output.amount, output.base);
+                        counterparts.push(output.conditions.to_string());
+                    } else if !is_issuer && to_pubkey {
+                        amount += source_amount(output.amount, output.base);
+                    }
+                }
+                if !is_issuer {
+                    counterparts = tx_doc.issuers().iter().map(ToString::to_string).collect();
+                }
+                if csv {
+                    print_csv_record(&[
+                        csv_field(dal_tx.written_on.id),
+                        csv_field(median_time),
+                        csv_field(hash),
+                        csv_field(format_amount(amount)),
+                        csv_field(counterparts.join(" ")),
+                        csv_field(tx_doc.get_comment()),
+                    ]);
+                } else {
+                    println!(
+                        "#{} ({}) {} : {} {} {} \"{}\"",
+                        dal_tx.written_on.id,
+                        median_time,
+                        hash,
+                        format_amount(amount),
+                        if is_issuer { "to" } else { "from" },
+                        counterparts.join(", "),
+                        tx_doc.get_comment(),
+                    );
+                }
+            }
+        }
+        DBExTxQuery::Sources(ref address_str) => {
+            let pubkey = if let Some(pubkey) = get_address_pubkey(&wot_databases, address_str) {
+                pubkey
+            } else {
+                println!("This address doesn't exist!");
+                return;
+            };
+            let utxos = durs_blockchain_dal::readers::sources::get_pubkey_utxos(
+                &currency_databases.utxos_db,
+                &pubkey,
+            )
+            .expect("get_pubkey_utxos : DALError");
+            let uds = durs_blockchain_dal::readers::sources::get_pubkey_uds(
+                &currency_databases.du_db,
+                &pubkey,
+            )
+            .expect("get_pubkey_uds : DALError");
+            if csv {
+                print_csv_header("type,identifier,amount,conditions");
+            } else {
+                println!("{} UTXOs and {} UDs :", utxos.len(), uds.len());
+            }
+            let mut total_amount = 0;
+            for utxo in utxos {
+                let amount = source_amount(utxo.1.amount, utxo.1.base);
+                total_amount += amount;
+                if csv {
+                    print_csv_record(&[
+                        csv_field("T"),
+                        csv_field(format!("{}:{}", (utxo.0).0, ((utxo.0).1).0)),
+                        csv_field(format_amount(amount)),
+                        csv_field(utxo.1.conditions.to_string()),
+                    ]);
+                } else {
+                    println!(
+                        "T:{}:{} : {} unlocked by {}",
+                        (utxo.0).0,
+                        ((utxo.0).1).0,
+                        format_amount(amount),
+                        utxo.1.conditions.to_string()
+                    );
+                }
+            }
+            for ud_block_number in uds {
+                let amount = durs_blockchain_dal::readers::block::get_block_in_local_blockchain(
+                    &blocks_databases.blockchain_db,
+                    ud_block_number,
+                )
+                .expect("get_block_in_local_blockchain : DALError")
+                .and_then(|block| {
+                    block
+                        .dividend
+                        .map(|ud| source_amount(TxAmount(ud as isize), TxBase(block.unit_base)))
+                })
+                .unwrap_or_default();
+                total_amount += amount;
+                if csv {
+                    print_csv_record(&[
+                        csv_field("D"),
+                        csv_field(format!("{}:{}", pubkey, ud_block_number)),
+                        csv_field(format_amount(amount)),
+                        csv_field(format!("SIG({})", pubkey)),
+                    ]);
+                } else {
+                    println!(
+                        "D:{}:{} : {} unlocked by SIG({})",
+                        pubkey,
+                        ud_block_number,
+                        format_amount(amount),
+                        pubkey
+                    );
+                }
+            }
+            if !csv {
+                println!("Total : {}", format_amount(total_amount));
+            }
         }
     }
 
@@ -161,17 +705,14 @@ pub fn dbex_wot<DC: DursConfTrait>(
     // get wot uid index
     let wot_uid_index: HashMap<NodeId, String> = wot_databases
         .identities_db
-        .read(|db| {
-            db.iter()
-                .map(|(_, idty)| (idty.wot_id, String::from(idty.idty_doc.username())))
-                .collect()
-        })
-        .expect("Fail to read IdentitiesDB !");
+        .entries()
+        .expect("Fail to read IdentitiesDB !")
+        .into_iter()
+        .map(|(_, idty)| (idty.wot_id, String::from(idty.idty_doc.username())))
+        .collect();
 
-    // Open wot db
-    let wot_db = BinDB::File(
-        open_file_db::<RustyWebOfTrust>(&db_path, "wot.db").expect("Fail to open WotDB !"),
-    );
+    // Get wot db
+    let wot_db = &wot_databases.wot_db;
 
     // Print wot blockstamp
     //println!("Wot : Current blockstamp = {}.", wot_blockstamp);
@@ -240,39 +781,27 @@ pub fn dbex_wot<DC: DursConfTrait>(
         }
         DBExWotQuery::ExpireMembers(ref reverse) => {
             // Open blockchain database
-            let blockchain_db = open_file_db::<LocalBlockchainV10Datas>(&db_path, "blockchain.db")
-                .expect("Fail to open blockchain db");
+            let blockchain_db = BlocksV10DBs::open(Some(&db_path)).blockchain_db;
             // Get blocks_times
-            let (current_bc_time, blocks_times): (u64, HashMap<BlockNumber, u64>) = blockchain_db
-                .read(|db| {
-                    (
-                        db[&BlockNumber(db.len() as u32 - 1)].block.median_time,
-                        db.iter()
-                            .map(|(block_id, dal_block)| (*block_id, dal_block.block.median_time))
-                            .collect(),
-                    )
-                })
-                .expect("Fail to read blockchain db");
+            let blocks_times: HashMap<BlockNumber, u64> = blockchain_db
+                .entries()
+                .expect("Fail to read blockchain db")
+                .into_iter()
+                .map(|(block_id, dal_block)| (block_id, dal_block.block.median_time))
+                .collect();
+            let current_bc_time = blocks_times[&BlockNumber(blocks_times.len() as u32 - 1)];
             // Get expire_dates
             let min_created_ms_time = current_bc_time - currency_params.ms_validity;
-            let mut expire_dates: Vec<(NodeId, u64)> = wot_databases
-                .ms_db
-                .read(|db| {
-                    let mut expire_dates = Vec::new();
-                    for (block_id, nodes_ids) in db {
-                        let created_ms_time = blocks_times[&block_id];
-                        if created_ms_time > min_created_ms_time {
-                            for node_id in nodes_ids {
-                                expire_dates.push((
-                                    *node_id,
-                                    created_ms_time + currency_params.ms_validity,
-                                ));
-                            }
-                        }
+            let mut expire_dates = Vec::new();
+            for (block_id, nodes_ids) in wot_databases.ms_db.entries().expect("Fail to read ms db")
+            {
+                let created_ms_time = blocks_times[&block_id];
+                if created_ms_time > min_created_ms_time {
+                    for node_id in nodes_ids {
+                        expire_dates.push((node_id, created_ms_time + currency_params.ms_validity));
                     }
-                    expire_dates
-                })
-                .expect("Fail to read ms db");
+                }
+            }
             if *reverse {
                 expire_dates.sort_unstable_by(|(_, d1), (_, d2)| d1.cmp(&d2));
             } else {
@@ -285,7 +814,7 @@ pub fn dbex_wot<DC: DursConfTrait>(
         DBExWotQuery::MemberDatas(ref uid) => {
             println!(" Members count = {}.", members_count);
             if let Some(pubkey) = durs_blockchain_dal::readers::identity::get_pubkey_from_uid(
-                &wot_databases.identities_db,
+                &wot_databases.idties_indexes_db,
                 uid,
             )
             .expect("get_pubkey_from_uid() : DALError !")
@@ -335,6 +864,147 @@ pub fn dbex_wot<DC: DursConfTrait>(
                 println!("Uid \"{}\" not found !", uid);
             }
         }
+        DBExWotQuery::Certs(ref address_str) => {
+            let pubkey = if let Some(pubkey) = get_address_pubkey(&wot_databases, address_str) {
+                pubkey
+            } else {
+                println!("This address doesn't exist!");
+                return;
+            };
+            let wot_id = if let Some(wot_id) = wot_index.get(&pubkey) {
+                *wot_id
+            } else {
+                println!("This identity doesn't exist!");
+                return;
+            };
+            // Open blockchain database
+            let blockchain_db = BlocksV10DBs::open(Some(&db_path)).blockchain_db;
+            let blocks_count = blockchain_db.len().expect("Fail to read blockchain db") as u32;
+            let current_median_time = blockchain_db
+                .get(&BlockNumber(blocks_count.saturating_sub(1)))
+                .expect("Fail to read blockchain db")
+                .map(|dal_block| dal_block.block.median_time)
+                .unwrap_or_default();
+            let certs = readers::certs::get_identity_certs_datas(
+                &wot_databases,
+                &blockchain_db,
+                currency_params.sig_validity,
+                wot_id,
+            )
+            .expect("get_identity_certs_datas : DALError");
+            if csv {
+                println!("direction,uid,written_on,expires_on,in_wot,chainable_on");
+            }
+            for (issued, title) in &[(true, "Issued"), (false, "Received")] {
+                let certs: Vec<_> = certs
+                    .iter()
+                    .filter(|cert_datas| (cert_datas.cert.source == wot_id) == *issued)
+                    .collect();
+                if !csv {
+                    println!("{} certifications : {}", title, certs.len());
+                }
+                for cert_datas in certs {
+                    let cert = &cert_datas.cert;
+                    let other_id = if *issued { cert.target } else { cert.source };
+                    let other_uid = wot_uid_index.get(&other_id).cloned().unwrap_or_default();
+                    let expires_on = cert_datas.expires_on.unwrap_or_default();
+                    let chainable_on = cert_datas.chainable_on.unwrap_or_default();
+                    if csv {
+                        println!(
+                            "{},{},{},{},{},{}",
+                            if *issued { "to" } else { "from" },
+                            other_uid,
+                            cert.written_on.id,
+                            expires_on,
+                            cert_datas.has_link,
+                            chainable_on,
+                        );
+                    } else {
+                        println!(
+                            "  {} {} : written in #{}, {} on {}, renewal {} {}",
+                            if *issued { "to" } else { "from" },
+                            other_uid,
+                            cert.written_on.id,
+                            if cert_datas.has_link && expires_on > current_median_time {
+                                "expires"
+                            } else {
+                                "expired"
+                            },
+                            expires_on,
+                            if chainable_on > current_median_time {
+                                "chainable on"
+                            } else {
+                                "chainable since"
+                            },
+                            chainable_on,
+                        );
+                    }
+                }
+            }
+        }
+        DBExWotQuery::Identities(ref filter, expire_before_block) => {
+            // Open blockchain database
+            let blockchain_db = BlocksV10DBs::open(Some(&db_path)).blockchain_db;
+            let current_block_id = BlockNumber(
+                (blockchain_db.len().expect("Fail to read blockchain db") as u32).saturating_sub(1),
+            );
+            let mut filter = filter.clone();
+            if let Some(block_id) = expire_before_block {
+                if let Some(block) =
+                    readers::block::get_block_in_local_blockchain(&blockchain_db, block_id)
+                        .expect("get_block_in_local_blockchain : DALError")
+                {
+                    filter.ms_expires_before = Some(block.median_time);
+                } else {
+                    println!("Block #{} not found in local blockchain !", block_id);
+                    return;
+                }
+            }
+            let identities = readers::identity::get_identities(
+                &wot_databases,
+                &blockchain_db,
+                currency_params.ms_validity,
+                &filter,
+                current_block_id,
+            )
+            .expect("get_identities : DALError");
+            if csv {
+                println!("uid,pubkey,state,ms_expires_on,certs_count");
+            } else {
+                println!("{} identities :", identities.len());
+            }
+            for idty in identities {
+                let ms_expires_on = blockchain_db
+                    .get(&idty.ms_created_block_id)
+                    .expect("Fail to read blockchain db")
+                    .map(|dal_block| dal_block.block.median_time)
+                    .map(|median_time| median_time + currency_params.ms_validity)
+                    .unwrap_or_default();
+                let certs_count = wot_db
+                    .read(|db| db.get_links_source(idty.wot_id))
+                    .expect("Fail to read WotDB")
+                    .map_or(0, |sources| sources.len());
+                if csv {
+                    println!(
+                        "{},{},{:?},{},{}",
+                        idty.idty_doc.username(),
+                        idty.idty_doc.issuers()[0],
+                        idty.state.kind(),
+                        ms_expires_on,
+                        certs_count,
+                    );
+                } else {
+                    println!(
+                        "{} ({}) : {:?}, membership expires on {}, {} certifications received",
+                        idty.idty_doc.username(),
+                        idty.idty_doc.issuers()[0],
+                        idty.state.kind(),
+                        ms_expires_on,
+                        certs_count,
+                    );
+                }
+            }
+        }
         _ => {}
     }
 }
@@ -20,9 +20,9 @@ use dubp_documents::documents::transaction::{TxAmount, TxBase};
 use dubp_documents::{BlockNumber, Document};
 use dup_crypto::keys::*;
 use durs_blockchain_dal::entities::block::DALBlock;
-use durs_blockchain_dal::entities::sources::SourceAmount;
+use durs_blockchain_dal::entities::sources::{DALUdState, SourceAmount};
+use durs_blockchain_dal::storage::KvValueDB;
 use durs_blockchain_dal::writers::requests::*;
-use durs_blockchain_dal::BinDB;
 use durs_common_tools::fatal_error;
 use durs_wot::data::NewLinkResult;
 use durs_wot::{NodeId, WebOfTrust};
@@ -47,8 +47,10 @@ pub enum ApplyValidBlockError {
 pub fn apply_valid_block<W: WebOfTrust>(
     mut block: BlockDocument,
     wot_index: &mut HashMap<PubKey, NodeId>,
-    wot_db: &BinDB<W>,
+    wot_db: &KvValueDB<W>,
     expire_certs: &HashMap<(NodeId, NodeId), BlockNumber>,
+    implicit_revocations: &[PubKey],
+    ud_state: DALUdState,
 ) -> Result<ValidBlockApplyReqs, ApplyValidBlockError> {
     debug!(
         "BlockchainModule : apply_valid_block({})",
@@ -132,8 +134,10 @@ pub fn apply_valid_block<W: WebOfTrust>(
             block.blockstamp(),
         ));
     }
+    let mut explicit_revocations = HashSet::with_capacity(block.revoked.len());
     for revocation in &block.revoked {
         let compact_revoc = revocation.to_compact_document();
+        explicit_revocations.insert(compact_revoc.issuer);
         let wot_id = if let Some(wot_id) = wot_index.get(&compact_revoc.issuer) {
             wot_id
         } else {
@@ -150,6 +154,19 @@ pub fn apply_valid_block<W: WebOfTrust>(
             true,
         ));
     }
+    for pubkey in implicit_revocations {
+        // An explicit revocation in the same block takes precedence
+        if !explicit_revocations.contains(pubkey) {
+            if !wot_index.contains_key(pubkey) {
+                return Err(ApplyValidBlockError::RevokeUnknowNodeId);
+            }
+            wot_dbs_requests.push(WotsDBsWriteQuery::RevokeIdentity(
+                *pubkey,
+                block.blockstamp(),
+                false,
+            ));
+        }
+    }
     for certification in &block.certifications {
         trace!("stack_up_valid_block: apply cert...");
         let compact_cert = certification.to_compact_document();
@@ -275,7 +292,7 @@ pub fn apply_valid_block<W: WebOfTrust>(
     };
     // Return DBs requests
     Ok(ValidBlockApplyReqs(
-        BlocksDBsWriteQuery::WriteBlock(dal_block),
+        BlocksDBsWriteQuery::WriteBlock(dal_block, ud_state),
         wot_dbs_requests,
         currency_dbs_requests,
     ))
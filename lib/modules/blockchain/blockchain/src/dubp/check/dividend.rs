@@ -0,0 +1,368 @@
+//  Copyright (C) 2018  The Duniter Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Universal dividend: amount, unit base and monetary mass after each block.
+
+use crate::dubp::check::rules::{BlockRuleDatas, BlockRuleError};
+use crate::dubp::check::InvalidBlockError;
+use dubp_documents::documents::block::BlockDocument;
+use dubp_documents::BlockNumber;
+use durs_blockchain_dal::entities::currency_params::CurrencyParameters;
+use durs_blockchain_dal::entities::sources::DALUdState;
+use durs_blockchain_dal::*;
+
+/// Maximum number of digits of the dividend (the unit base increases beyond)
+static NB_DIGITS_UD: &'static u32 = &6;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// Universal dividend state after a block
+pub struct UdState {
+    /// Amount of the dividend (in units of 10^unit_base)
+    pub dividend: usize,
+    /// Unit base (power of ten)
+    pub unit_base: usize,
+    /// Monetary mass
+    pub monetary_mass: usize,
+    /// Monetary mass at the last re-evaluation of the dividend
+    pub mass_reeval: usize,
+    /// Median time from which the next dividend is created
+    pub ud_time: u64,
+    /// Median time from which the dividend is re-evaluated
+    pub ud_reeval_time: u64,
+}
+
+impl UdState {
+    /// State after the genesis block
+    pub fn genesis(currency_params: &CurrencyParameters) -> UdState {
+        UdState {
+            dividend: currency_params.ud0,
+            unit_base: 0,
+            monetary_mass: 0,
+            mass_reeval: 0,
+            ud_time: currency_params.ud_time0,
+            ud_reeval_time: currency_params.ud_reeval_time0,
+        }
+    }
+    /// Compute the state after the next block and the dividend created by this block (if any)
+    pub fn next(
+        &self,
+        median_time: u64,
+        members_count: usize,
+        currency_params: &CurrencyParameters,
+    ) -> (UdState, Option<usize>) {
+        let reeval = self.ud_reeval_time <= median_time;
+        let mut new_state = UdState {
+            ud_time: if self.ud_time <= median_time {
+                self.ud_time + currency_params.dt
+            } else {
+                self.ud_time
+            },
+            ud_reeval_time: if reeval {
+                self.ud_reeval_time + currency_params.dt_reeval
+            } else {
+                self.ud_reeval_time
+            },
+            ..*self
+        };
+        if reeval {
+            let mass_reeval = (self.mass_reeval as f64 / 10f64.powi(self.unit_base as i32)).ceil();
+            let periods = currency_params.dt_reeval as f64 / currency_params.dt as f64;
+            new_state.dividend = (self.dividend as f64
+                + currency_params.c * currency_params.c * mass_reeval
+                    / members_count.max(1) as f64
+                    / periods)
+                .ceil() as usize;
+            new_state.mass_reeval = self.monetary_mass;
+        }
+        // An increase of the unit base always creates a dividend
+        let unit_base_increase = new_state.dividend >= 10usize.pow(*NB_DIGITS_UD);
+        if unit_base_increase {
+            new_state.dividend = (new_state.dividend as f64 / 10.0).ceil() as usize;
+            new_state.unit_base += 1;
+        }
+        let new_dividend = if unit_base_increase || new_state.ud_time != self.ud_time {
+            Some(new_state.dividend)
+        } else {
+            None
+        };
+        if let Some(dividend) = new_dividend {
+            new_state.monetary_mass +=
+                dividend * 10usize.pow(new_state.unit_base as u32) * members_count;
+        }
+        (new_state, new_dividend)
+    }
+    /// State after a block (from the block and the part of the state recorded with it)
+    pub fn from_dal(block: &BlockDocument, dal_ud_state: &DALUdState) -> UdState {
+        UdState {
+            dividend: dal_ud_state.dividend,
+            unit_base: block.unit_base,
+            monetary_mass: block.monetary_mass,
+            mass_reeval: dal_ud_state.mass_reeval,
+            ud_time: dal_ud_state.ud_time,
+            ud_reeval_time: dal_ud_state.ud_reeval_time,
+        }
+    }
+    /// Part of the state to record with the block (the block does not carry it)
+    pub fn to_dal(&self) -> DALUdState {
+        DALUdState {
+            dividend: self.dividend,
+            mass_reeval: self.mass_reeval,
+            ud_time: self.ud_time,
+            ud_reeval_time: self.ud_reeval_time,
+        }
+    }
+    /// State after the block `block_number` of the local blockchain
+    ///
+    /// Returns `None` if there is no such block, and an error if its state is not recorded.
+    pub fn local_state(
+        blockchain_db: &LocalBlockchainV10DB,
+        ud_states_db: &UdStatesV10DB,
+        block_number: BlockNumber,
+    ) -> Result<Option<UdState>, DALError> {
+        if let Some(dal_block) = blockchain_db.get(&block_number)? {
+            let dal_ud_state = ud_states_db
+                .get(&block_number)?
+                .ok_or(DALError::InconsistentDBs)?;
+            Ok(Some(UdState::from_dal(&dal_block.block, &dal_ud_state)))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Expected state after the block (from the state after the previous block)
+/// and dividend created by the block
+pub fn expected_ud_state(
+    block: &BlockDocument,
+    previous_state: Option<UdState>,
+    currency_params: &CurrencyParameters,
+) -> (UdState, Option<usize>) {
+    if let Some(previous_state) = previous_state {
+        previous_state.next(block.median_time, block.members_count, currency_params)
+    } else {
+        // The genesis block does not create any dividend
+        (UdState::genesis(currency_params), None)
+    }
+}
+
+/// dividend, unit_base and monetary_mass
+pub fn verify_dividend(datas: &BlockRuleDatas) -> Result<(), BlockRuleError> {
+    let previous_state = if datas.block.number.0 > 0 {
+        UdState::local_state(
+            datas.blockchain_db,
+            datas.ud_states_db,
+            BlockNumber(datas.block.number.0 - 1),
+        )?
+    } else {
+        None
+    };
+    let (expected_state, expected_dividend) =
+        expected_ud_state(datas.block, previous_state, &datas.currency_params);
+    let expected_unit_base = expected_state.unit_base;
+    if datas.block.dividend != expected_dividend {
+        Err(InvalidBlockError::InvalidDividend {
+            expected: expected_dividend,
+            found: datas.block.dividend,
+        }
+        .into())
+    } else if datas.block.unit_base != expected_unit_base {
+        Err(InvalidBlockError::InvalidUnitBase {
+            expected: expected_unit_base,
+            found: datas.block.unit_base,
+        }
+        .into())
+    } else if datas.block.monetary_mass != expected_state.monetary_mass {
+        Err(InvalidBlockError::InvalidMonetaryMass {
+            expected: expected_state.monetary_mass,
+            found: datas.block.monetary_mass,
+        }
+        .into())
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dubp_documents::{BlockHash, Blockstamp};
+    use dup_crypto::hashs::Hash;
+    use durs_blockchain_dal::entities::block::DALBlock;
+
+    fn currency_params() -> CurrencyParameters {
+        CurrencyParameters {
+            c: 0.1,
+            dt: 10,
+            dt_reeval: 100,
+            ud0: 100,
+            ud_time0: 10,
+            ud_reeval_time0: 100,
+            ..CurrencyParameters::default()
+        }
+    }
+
+    #[test]
+    fn test_ud_creation() {
+        let currency_params = currency_params();
+        let genesis = UdState::genesis(&currency_params);
+        let (state, dividend) = genesis.next(5, 2, &currency_params);
+        assert_eq!(None, dividend);
+        assert_eq!(genesis, state);
+        let (state, dividend) = state.next(10, 2, &currency_params);
+        assert_eq!(Some(100), dividend);
+        assert_eq!(20, state.ud_time);
+        assert_eq!(200, state.monetary_mass);
+        // Only one dividend per block, even after a long time without block
+        let (state, dividend) = state.next(45, 2, &currency_params);
+        assert_eq!(Some(100), dividend);
+        assert_eq!(30, state.ud_time);
+        assert_eq!(400, state.monetary_mass);
+    }
+
+    #[test]
+    fn test_ud_reevaluation() {
+        let currency_params = currency_params();
+        let state = UdState {
+            monetary_mass: 2_000,
+            mass_reeval: 1_000,
+            ud_time: 100,
+            ..UdState::genesis(&currency_params)
+        };
+        let (state, dividend) = state.next(100, 2, &currency_params);
+        // ceil(100 + 0.01 * 1000 / 2 / 10)
+        assert_eq!(Some(101), dividend);
+        assert_eq!(2_000, state.mass_reeval);
+        assert_eq!(200, state.ud_reeval_time);
+        assert_eq!(2_202, state.monetary_mass);
+    }
+
+    #[test]
+    fn test_unit_base_increase() {
+        let currency_params = currency_params();
+        let state = UdState {
+            dividend: 999_999,
+            mass_reeval: 100,
+            ud_time: 1_000,
+            ..UdState::genesis(&currency_params)
+        };
+        let (state, dividend) = state.next(100, 1, &currency_params);
+        // ceil(999_999 + 0.01 * 100 / 1 / 10) reaches 7 digits
+        assert_eq!(Some(100_000), dividend);
+        assert_eq!(1, state.unit_base);
+        assert_eq!(1_000_000, state.monetary_mass);
+    }
+
+    #[test]
+    fn test_expected_ud_state() {
+        let currency_params = currency_params();
+        let mut block = dubp_documents_tests_tools::mocks::gen_empty_timed_block(
+            Blockstamp::default(),
+            10,
+            Hash::default(),
+        );
+        block.members_count = 2;
+        let genesis = UdState::genesis(&currency_params);
+        assert_eq!(
+            (genesis, None),
+            expected_ud_state(&block, None, &currency_params)
+        );
+        let (state, dividend) = expected_ud_state(&block, Some(genesis), &currency_params);
+        assert_eq!(
+            (Some(100), 0, 200),
+            (dividend, state.unit_base, state.monetary_mass)
+        );
+        block.median_time = 5;
+        assert_eq!(
+            (genesis, None),
+            expected_ud_state(&block, Some(genesis), &currency_params)
+        );
+        let state = UdState {
+            dividend: 999_999,
+            mass_reeval: 100,
+            ..genesis
+        };
+        block.median_time = 100;
+        let (state, dividend) = expected_ud_state(&block, Some(state), &currency_params);
+        assert_eq!(
+            (Some(100_000), 1, 2_000_000),
+            (dividend, state.unit_base, state.monetary_mass)
+        );
+    }
+
+    #[test]
+    fn test_local_state() -> Result<(), DALError> {
+        let currency_params = currency_params();
+        let blocks_dbs = BlocksV10DBs::open(None);
+        let mut states = vec![UdState::genesis(&currency_params)];
+        for n in 0..3u32 {
+            let mut block = dubp_documents_tests_tools::mocks::gen_empty_timed_block(
+                Blockstamp {
+                    id: BlockNumber(n),
+                    hash: BlockHash(dup_crypto_tests_tools::mocks::hash_from_byte(n as u8)),
+                },
+                u64::from(n) * 10,
+                Hash::default(),
+            );
+            block.members_count = 2;
+            if n > 0 {
+                let (state, dividend) =
+                    expected_ud_state(&block, states.last().cloned(), &currency_params);
+                block.dividend = dividend;
+                block.monetary_mass = state.monetary_mass;
+                states.push(state);
+            }
+            blocks_dbs.blockchain_db.insert(
+                &BlockNumber(n),
+                &DALBlock {
+                    block,
+                    expire_certs: None,
+                },
+            )?;
+        }
+        // The state of the block 2 is not recorded
+        for n in 0..2u32 {
+            blocks_dbs
+                .ud_states_db
+                .insert(&BlockNumber(n), &states[n as usize].to_dal())?;
+        }
+        for n in 0..2u32 {
+            assert_eq!(
+                Some(states[n as usize]),
+                UdState::local_state(
+                    &blocks_dbs.blockchain_db,
+                    &blocks_dbs.ud_states_db,
+                    BlockNumber(n),
+                )?
+            );
+        }
+        assert_eq!(
+            Err(DALError::InconsistentDBs),
+            UdState::local_state(
+                &blocks_dbs.blockchain_db,
+                &blocks_dbs.ud_states_db,
+                BlockNumber(2),
+            )
+        );
+        assert_eq!(
+            None,
+            UdState::local_state(
+                &blocks_dbs.blockchain_db,
+                &blocks_dbs.ud_states_db,
+                BlockNumber(3),
+            )?
+        );
+        Ok(())
+    }
+}
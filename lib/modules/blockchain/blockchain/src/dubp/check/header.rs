@@ -0,0 +1,449 @@
+//  Copyright (C) 2018  The Duniter Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Global rules on the block header: issuers frame, members count, time and proof of work.
+
+use crate::dubp::check::InvalidBlockError;
+use dubp_documents::documents::block::BlockDocument;
+use dup_crypto::keys::PubKey;
+use durs_blockchain_dal::entities::currency_params::CurrencyParameters;
+use std::collections::{HashMap, HashSet};
+
+/// Acceleration factor of the proof of work difficulty
+static POW_ACCELERATION: &'static f64 = &1.189;
+
+/// Block version must not decrease
+pub fn verify_version(
+    block: &BlockDocument,
+    previous_blocks: &[BlockDocument],
+) -> Result<(), InvalidBlockError> {
+    if let Some(previous_block) = previous_blocks.last() {
+        if previous_block.version > block.version {
+            return Err(InvalidBlockError::VersionDecrease);
+        }
+    }
+    Ok(())
+}
+
+/// Compute median value (ceil rounded when there is an even number of values)
+pub fn median(values: &mut [u64]) -> u64 {
+    values.sort_unstable();
+    let len = values.len();
+    if len == 0 {
+        0
+    } else if len % 2 == 0 {
+        let sum = values[len / 2 - 1] + values[len / 2];
+        sum / 2 + sum % 2
+    } else {
+        values[len / 2]
+    }
+}
+
+/// Get issuers of the frame of the previous block (from the newest to the oldest)
+fn frame_issuers(previous_blocks: &[BlockDocument]) -> Vec<PubKey> {
+    if let Some(previous_block) = previous_blocks.last() {
+        let frame_size = previous_block.issuers_frame.max(0) as usize;
+        previous_blocks
+            .iter()
+            .rev()
+            .take(frame_size)
+            .map(|b| b.issuers[0])
+            .collect()
+    } else {
+        vec![]
+    }
+}
+
+/// previous_issuer is the issuer of the previous block
+pub fn verify_previous_issuer(
+    block: &BlockDocument,
+    previous_blocks: &[BlockDocument],
+) -> Result<(), InvalidBlockError> {
+    if let Some(previous_block) = previous_blocks.last() {
+        if block.previous_issuer != Some(previous_block.issuers[0]) {
+            return Err(InvalidBlockError::InvalidPreviousIssuer);
+        }
+    }
+    Ok(())
+}
+
+/// Compute expected issuers_count, issuers_frame and issuers_frame_var
+pub fn expected_issuers_frame(previous_blocks: &[BlockDocument]) -> (usize, isize, isize) {
+    if let Some(previous_block) = previous_blocks.last() {
+        let issuers_count = frame_issuers(previous_blocks)
+            .into_iter()
+            .collect::<HashSet<_>>()
+            .len();
+        let previous_var_sign = previous_block.issuers_frame_var.signum();
+        (
+            issuers_count,
+            previous_block.issuers_frame + previous_var_sign,
+            previous_block.issuers_frame_var
+                + 5 * (issuers_count as isize - previous_block.issuers_count as isize)
+                - previous_var_sign,
+        )
+    } else {
+        (0, 1, 0)
+    }
+}
+
+/// issuers_count, issuers_frame and issuers_frame_var
+pub fn verify_issuers_frame(
+    block: &BlockDocument,
+    previous_blocks: &[BlockDocument],
+) -> Result<(), InvalidBlockError> {
+    let (expected_count, expected_frame, expected_frame_var) =
+        expected_issuers_frame(previous_blocks);
+    if block.issuers_count != expected_count {
+        Err(InvalidBlockError::InvalidIssuersCount {
+            expected: expected_count,
+            found: block.issuers_count,
+        })
+    } else if block.issuers_frame != expected_frame {
+        Err(InvalidBlockError::InvalidIssuersFrame {
+            expected: expected_frame,
+            found: block.issuers_frame,
+        })
+    } else if block.issuers_frame_var != expected_frame_var {
+        Err(InvalidBlockError::InvalidIssuersFrameVar {
+            expected: expected_frame_var,
+            found: block.issuers_frame_var,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// Compute expected members_count: previous members_count + joiners - excluded
+pub fn expected_members_count(block: &BlockDocument, previous_blocks: &[BlockDocument]) -> usize {
+    let previous_members_count = previous_blocks.last().map_or(0, |b| b.members_count);
+    (previous_members_count + block.joiners.len()).saturating_sub(block.excluded.len())
+}
+
+/// members_count = previous members_count + joiners - excluded
+pub fn verify_members_count(
+    block: &BlockDocument,
+    previous_blocks: &[BlockDocument],
+) -> Result<(), InvalidBlockError> {
+    let expected = expected_members_count(block, previous_blocks);
+    if block.members_count == expected {
+        Ok(())
+    } else {
+        Err(InvalidBlockError::InvalidMembersCount {
+            expected,
+            found: block.members_count,
+        })
+    }
+}
+
+/// Compute expected median_time (`None` for the genesis block, whose median_time is its time)
+pub fn expected_median_time(
+    previous_blocks: &[BlockDocument],
+    currency_params: &CurrencyParameters,
+) -> Option<u64> {
+    let previous_block = previous_blocks.last()?;
+    let mut times: Vec<u64> = previous_blocks
+        .iter()
+        .rev()
+        .take(currency_params.median_time_blocks)
+        .map(|b| b.time)
+        .collect();
+    Some(median(&mut times).max(previous_block.median_time))
+}
+
+/// Maximum gap between the time and the median_time of a block
+pub fn max_time_acceleration(currency_params: &CurrencyParameters) -> u64 {
+    let max_gen_time = (currency_params.avg_gen_time as f64 * POW_ACCELERATION).ceil();
+    (max_gen_time * currency_params.median_time_blocks as f64).ceil() as u64
+}
+
+/// median_time and time bounds
+pub fn verify_time(
+    block: &BlockDocument,
+    previous_blocks: &[BlockDocument],
+    currency_params: &CurrencyParameters,
+) -> Result<(), InvalidBlockError> {
+    let expected_median_time =
+        expected_median_time(previous_blocks, currency_params).unwrap_or(block.time);
+    if block.median_time != expected_median_time {
+        return Err(InvalidBlockError::InvalidMedianTime {
+            expected: expected_median_time,
+            found: block.median_time,
+        });
+    }
+    let max_acceleration = max_time_acceleration(currency_params);
+    if block.time < block.median_time || block.time > block.median_time + max_acceleration {
+        Err(InvalidBlockError::TimeOutOfBounds)
+    } else {
+        Ok(())
+    }
+}
+
+/// Compute expected pow_min
+///
+/// pow_min is re-evaluated every dt_diff_eval blocks according to the speed of the blockchain.
+pub fn expected_pow_min(
+    block: &BlockDocument,
+    previous_blocks: &[BlockDocument],
+    currency_params: &CurrencyParameters,
+) -> Option<usize> {
+    let previous_block = previous_blocks.last()?;
+    let previous_pow_min = previous_block.pow_min;
+    if currency_params.dt_diff_eval == 0
+        || block.number.0 as usize % currency_params.dt_diff_eval != 0
+    {
+        return Some(previous_pow_min);
+    }
+    let range = currency_params.dt_diff_eval.min(block.number.0 as usize);
+    let ref_median_time = previous_blocks
+        .get(previous_blocks.len().checked_sub(range)?)?
+        .median_time;
+    let elapsed = block.median_time.saturating_sub(ref_median_time);
+    let speed = if elapsed == 0 {
+        100.0
+    } else {
+        range as f64 / elapsed as f64
+    };
+    let avg_gen_time = currency_params.avg_gen_time as f64;
+    let max_speed = 1.0 / (avg_gen_time / POW_ACCELERATION);
+    let min_speed = 1.0 / (avg_gen_time * POW_ACCELERATION);
+    Some(if speed >= max_speed {
+        if (previous_pow_min + 2) % 16 == 0 {
+            previous_pow_min + 2
+        } else {
+            previous_pow_min + 1
+        }
+    } else if speed <= min_speed {
+        if previous_pow_min % 16 == 0 {
+            previous_pow_min.saturating_sub(2)
+        } else {
+            previous_pow_min.saturating_sub(1)
+        }
+    } else {
+        previous_pow_min
+    })
+}
+
+/// pow_min is unchanged except on re-evaluation blocks
+pub fn verify_pow_min(
+    block: &BlockDocument,
+    previous_blocks: &[BlockDocument],
+    currency_params: &CurrencyParameters,
+) -> Result<(), InvalidBlockError> {
+    if let Some(expected) = expected_pow_min(block, previous_blocks, currency_params) {
+        if block.pow_min != expected {
+            return Err(InvalidBlockError::InvalidPowMin {
+                expected,
+                found: block.pow_min,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Compute the personalized difficulty of the block issuer
+pub fn issuer_difficulty(
+    issuer: &PubKey,
+    pow_min: usize,
+    previous_blocks: &[BlockDocument],
+    currency_params: &CurrencyParameters,
+) -> usize {
+    let frame_issuers = frame_issuers(previous_blocks);
+    let mut blocks_per_issuer: HashMap<PubKey, u64> = HashMap::new();
+    for frame_issuer in &frame_issuers {
+        *blocks_per_issuer.entry(*frame_issuer).or_insert(0) += 1;
+    }
+    let nb_personal_blocks = blocks_per_issuer.get(issuer).cloned().unwrap_or(0);
+    let median_of_blocks_in_frame = if blocks_per_issuer.is_empty() {
+        1
+    } else {
+        median(&mut blocks_per_issuer.values().cloned().collect::<Vec<u64>>())
+    };
+    let (nb_previous_issuers, nb_blocks_since) = if nb_personal_blocks == 0 {
+        (0, 0)
+    } else {
+        let previous_block = previous_blocks.last().expect("safe unwrap");
+        let last_personal_block = previous_blocks
+            .iter()
+            .rev()
+            .find(|b| b.issuers[0] == *issuer)
+            .expect("safe unwrap");
+        (
+            last_personal_block.issuers_count,
+            previous_block.number.0 - last_personal_block.number.0,
+        )
+    };
+    let personal_excess =
+        ((nb_personal_blocks + 1) as f64 / median_of_blocks_in_frame as f64 - 1.0).max(0.0);
+    let personal_handicap = ((1.0 + personal_excess).ln() / POW_ACCELERATION.ln()).floor() as usize;
+    let rotation_factor = (currency_params.percent_rot * nb_previous_issuers as f64
+        / (1.0 + f64::from(nb_blocks_since)))
+    .floor() as usize;
+    let mut difficulty = pow_min.max(pow_min * rotation_factor) + personal_handicap;
+    if (difficulty + 1) % 16 == 0 {
+        difficulty += 1;
+    }
+    difficulty
+}
+
+/// Check if a hash (in uppercase hexadecimal) reaches a difficulty
+pub fn hash_reaches_difficulty(hash_hex: &str, difficulty: usize) -> bool {
+    let nb_zeros = difficulty / 16;
+    let remainder = difficulty % 16;
+    let mut chars = hash_hex.chars();
+    for _ in 0..nb_zeros {
+        if chars.next() != Some('0') {
+            return false;
+        }
+    }
+    if remainder > 0 {
+        match chars.next().and_then(|c| c.to_digit(16)) {
+            Some(digit) => digit as usize <= 15 - remainder,
+            None => false,
+        }
+    } else {
+        true
+    }
+}
+
+/// The block hash reaches the personalized difficulty of its issuer
+pub fn verify_proof_of_work(
+    block: &BlockDocument,
+    previous_blocks: &[BlockDocument],
+    currency_params: &CurrencyParameters,
+) -> Result<(), InvalidBlockError> {
+    let required_difficulty = issuer_difficulty(
+        &block.issuers[0],
+        block.pow_min,
+        previous_blocks,
+        currency_params,
+    );
+    let hash_hex = block.hash.map(|h| h.0.to_hex()).unwrap_or_default();
+    if hash_reaches_difficulty(&hash_hex, required_difficulty) {
+        Ok(())
+    } else {
+        Err(InvalidBlockError::InsufficientProofOfWork {
+            required_difficulty,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dubp_documents::*;
+
+    fn gen_chain(issuers: &[char], time_step: u64) -> Vec<BlockDocument> {
+        let mut blocks: Vec<BlockDocument> =
+            dubp_documents_tests_tools::mocks::gen_empty_timed_blocks(issuers.len(), time_step);
+        for (i, block) in blocks.iter_mut().enumerate() {
+            block.issuers = vec![dup_crypto_tests_tools::mocks::pubkey(issuers[i])];
+            block.time = i as u64 * time_step;
+            block.median_time = block.time;
+            block.issuers_frame = 1;
+        }
+        blocks
+    }
+
+    #[test]
+    fn test_median() {
+        assert_eq!(0, median(&mut []));
+        assert_eq!(3, median(&mut [5, 1, 3]));
+        assert_eq!(3, median(&mut [1, 4, 2, 3]));
+        assert_eq!(2, median(&mut [1, 2]));
+    }
+
+    #[test]
+    fn test_hash_reaches_difficulty() {
+        assert!(hash_reaches_difficulty("00A", 32));
+        assert!(!hash_reaches_difficulty("0A0", 32));
+        assert!(hash_reaches_difficulty("0007", 56));
+        assert!(!hash_reaches_difficulty("0008", 56));
+        assert!(hash_reaches_difficulty("F", 0));
+    }
+
+    #[test]
+    fn test_issuers_frame() {
+        let currency_params = CurrencyParameters::default();
+        let previous_blocks = gen_chain(&['A'], 10);
+        let mut block = dubp_documents_tests_tools::mocks::gen_empty_timed_block(
+            Blockstamp {
+                id: BlockNumber(1),
+                hash: BlockHash(dup_crypto_tests_tools::mocks::hash('0')),
+            },
+            0,
+            dup_crypto_tests_tools::mocks::hash_from_byte(0),
+        );
+        block.issuers = vec![dup_crypto_tests_tools::mocks::pubkey('B')];
+        block.previous_issuer = Some(dup_crypto_tests_tools::mocks::pubkey('A'));
+        block.issuers_frame = 1;
+        block.issuers_frame_var = 5;
+        block.issuers_count = 1;
+        block.time = 10;
+        block.median_time = 0;
+        assert_eq!(Ok(()), verify_previous_issuer(&block, &previous_blocks));
+        assert_eq!(Ok(()), verify_issuers_frame(&block, &previous_blocks));
+        assert_eq!(Ok(()), verify_members_count(&block, &previous_blocks));
+        assert_eq!(
+            Ok(()),
+            verify_time(&block, &previous_blocks, &currency_params)
+        );
+        assert_eq!(
+            Ok(()),
+            verify_pow_min(&block, &previous_blocks, &currency_params)
+        );
+        assert_eq!(
+            Ok(()),
+            verify_proof_of_work(&block, &previous_blocks, &currency_params)
+        );
+
+        block.issuers_frame_var = 0;
+        assert_eq!(
+            Err(InvalidBlockError::InvalidIssuersFrameVar {
+                expected: 5,
+                found: 0
+            }),
+            verify_issuers_frame(&block, &previous_blocks)
+        );
+    }
+
+    #[test]
+    fn test_issuer_difficulty() {
+        let currency_params = CurrencyParameters::default();
+        let mut previous_blocks = gen_chain(&['A', 'A', 'B', 'A'], 10);
+        for block in previous_blocks.iter_mut() {
+            block.issuers_frame = 4;
+        }
+        // 'C' has never issued a block in the frame
+        assert_eq!(
+            70,
+            issuer_difficulty(
+                &dup_crypto_tests_tools::mocks::pubkey('C'),
+                70,
+                &previous_blocks,
+                &currency_params
+            )
+        );
+        // 'A' issued 3 blocks in the frame, so has an handicap
+        assert!(
+            issuer_difficulty(
+                &dup_crypto_tests_tools::mocks::pubkey('A'),
+                70,
+                &previous_blocks,
+                &currency_params
+            ) > 70
+        );
+    }
+}
@@ -0,0 +1,194 @@
+//  Copyright (C) 2018  The Duniter Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Local rules: rules that can be checked with the block alone.
+
+use crate::dubp::check::InvalidBlockError;
+use dubp_documents::documents::block::BlockDocument;
+use dubp_documents::Document;
+use std::collections::HashSet;
+
+/// Only the genesis block carries the currency parameters
+pub fn verify_parameters(block: &BlockDocument) -> Result<(), InvalidBlockError> {
+    if (block.number.0 == 0) == block.parameters.is_some() {
+        Ok(())
+    } else {
+        Err(InvalidBlockError::InvalidParameters)
+    }
+}
+
+/// A block has exactly one issuer and the genesis block has no previous issuer
+pub fn verify_issuers(block: &BlockDocument) -> Result<(), InvalidBlockError> {
+    if block.issuers.len() != 1 {
+        Err(InvalidBlockError::InvalidIssuersNumber)
+    } else if block.number.0 == 0 && block.previous_issuer.is_some() {
+        Err(InvalidBlockError::InvalidPreviousIssuer)
+    } else {
+        Ok(())
+    }
+}
+
+/// Each identity has its own pubkey, its own uid and a matching joiner
+pub fn verify_identities_unicity(block: &BlockDocument) -> Result<(), InvalidBlockError> {
+    let mut pubkeys = HashSet::with_capacity(block.identities.len());
+    let mut uids = HashSet::with_capacity(block.identities.len());
+    let joiners: HashSet<_> = block.joiners.iter().map(|ms| ms.issuers()[0]).collect();
+    for idty in &block.identities {
+        let pubkey = idty.issuers()[0];
+        if !pubkeys.insert(pubkey) {
+            return Err(InvalidBlockError::DuplicateIdentityPubkey(pubkey));
+        }
+        if !uids.insert(idty.username()) {
+            return Err(InvalidBlockError::DuplicateIdentityUid(pubkey));
+        }
+        if !joiners.contains(&pubkey) {
+            return Err(InvalidBlockError::IdentityWithoutJoiner(pubkey));
+        }
+    }
+    Ok(())
+}
+
+/// A pubkey appears at most once in joiners, actives and leavers
+pub fn verify_memberships_unicity(block: &BlockDocument) -> Result<(), InvalidBlockError> {
+    let mut pubkeys =
+        HashSet::with_capacity(block.joiners.len() + block.actives.len() + block.leavers.len());
+    for ms in block
+        .joiners
+        .iter()
+        .chain(block.actives.iter())
+        .chain(block.leavers.iter())
+    {
+        let pubkey = ms.issuers()[0];
+        if !pubkeys.insert(pubkey) {
+            return Err(InvalidBlockError::DuplicateMembership(pubkey));
+        }
+    }
+    Ok(())
+}
+
+/// No self certification and no duplicate certification
+pub fn verify_certifications(block: &BlockDocument) -> Result<(), InvalidBlockError> {
+    let mut links = HashSet::with_capacity(block.certifications.len());
+    for cert in &block.certifications {
+        let compact_cert = cert.to_compact_document();
+        if compact_cert.issuer == compact_cert.target {
+            return Err(InvalidBlockError::SelfCertification(compact_cert.issuer));
+        }
+        if !links.insert((compact_cert.issuer, compact_cert.target)) {
+            return Err(InvalidBlockError::DuplicateCertification(
+                compact_cert.issuer,
+                compact_cert.target,
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dubp_documents::documents::block::BlockV10Parameters;
+    use dubp_documents::documents::membership::MembershipType;
+    use dubp_documents::*;
+    use dup_crypto::hashs::Hash;
+
+    fn gen_block(number: u32) -> BlockDocument {
+        let mut block = dubp_documents_tests_tools::mocks::gen_empty_timed_block(
+            Blockstamp {
+                id: BlockNumber(number),
+                hash: BlockHash(dup_crypto_tests_tools::mocks::hash('A')),
+            },
+            0,
+            Hash::default(),
+        );
+        block.issuers = vec![dup_crypto_tests_tools::mocks::pubkey('A')];
+        if number == 0 {
+            block.parameters = Some(BlockV10Parameters::default());
+        }
+        block
+    }
+
+    #[test]
+    fn test_verify_parameters() {
+        assert_eq!(Ok(()), verify_parameters(&gen_block(0)));
+        assert_eq!(Ok(()), verify_parameters(&gen_block(1)));
+
+        let mut genesis = gen_block(0);
+        genesis.parameters = None;
+        assert_eq!(
+            Err(InvalidBlockError::InvalidParameters),
+            verify_parameters(&genesis)
+        );
+
+        let mut block = gen_block(1);
+        block.parameters = Some(BlockV10Parameters::default());
+        assert_eq!(
+            Err(InvalidBlockError::InvalidParameters),
+            verify_parameters(&block)
+        );
+    }
+
+    #[test]
+    fn test_verify_issuers() {
+        let mut block = gen_block(1);
+        block.issuers = vec![];
+        assert_eq!(
+            Err(InvalidBlockError::InvalidIssuersNumber),
+            verify_issuers(&block)
+        );
+
+        let mut genesis = gen_block(0);
+        genesis.previous_issuer = Some(dup_crypto_tests_tools::mocks::pubkey('B'));
+        assert_eq!(
+            Err(InvalidBlockError::InvalidPreviousIssuer),
+            verify_issuers(&genesis)
+        );
+    }
+
+    #[test]
+    fn test_verify_identities_unicity() {
+        let pubkey = dup_crypto_tests_tools::mocks::pubkey('B');
+        let idty =
+            dubp_documents_tests_tools::mocks::identity::gen_mock_idty(pubkey, BlockNumber(0));
+        let mut block = gen_block(1);
+        block.identities = vec![idty.clone()];
+        assert_eq!(
+            Err(InvalidBlockError::IdentityWithoutJoiner(pubkey)),
+            verify_identities_unicity(&block)
+        );
+
+        let joiner = dubp_documents_tests_tools::mocks::membership::gen_mock_membership(
+            pubkey,
+            MembershipType::In(),
+            Blockstamp::default(),
+            Blockstamp::default(),
+        );
+        block.joiners = vec![joiner.clone()];
+        assert_eq!(Ok(()), verify_identities_unicity(&block));
+
+        block.identities = vec![idty.clone(), idty];
+        assert_eq!(
+            Err(InvalidBlockError::DuplicateIdentityPubkey(pubkey)),
+            verify_identities_unicity(&block)
+        );
+
+        block.identities = vec![];
+        block.actives = vec![joiner];
+        assert_eq!(
+            Err(InvalidBlockError::DuplicateMembership(pubkey)),
+            verify_memberships_unicity(&block)
+        );
+    }
+}
@@ -15,48 +15,248 @@
 
 //! Sub-module checking if a block complies with all the rules of the (DUBP DUniter Blockchain Protocol).
 
+pub mod dividend;
 pub mod hashs;
+pub mod header;
+pub mod local;
+pub mod rules;
+pub mod tx;
+pub mod wot;
 
 use crate::dubp::BlockError;
 use dubp_documents::documents::block::BlockDocument;
-use dubp_documents::*;
+use dup_crypto::hashs::Hash;
 use dup_crypto::keys::PubKey;
+use durs_blockchain_dal::entities::currency_params::CurrencyParameters;
 use durs_blockchain_dal::*;
 use durs_wot::*;
+use rules::BlockRuleDatas;
 use std::collections::HashMap;
+use tx::InvalidTxError;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// Reason why a block does not comply with the protocol rules
 pub enum InvalidBlockError {
+    /// The previous block does not exist in the local blockchain
     NoPreviousBlock,
+    /// The block version is lower than the previous block version
     VersionDecrease,
+    /// The block version is not supported by this node
+    UnsupportedVersion(usize),
+    /// Only the genesis block carries the currency parameters
+    InvalidParameters,
+    /// The block must have exactly one issuer
+    InvalidIssuersNumber,
+    /// previous_issuer does not match the issuer of the previous block
+    InvalidPreviousIssuer,
+    /// The same pubkey is used by several identities of the block
+    DuplicateIdentityPubkey(PubKey),
+    /// The same uid is used by several identities of the block
+    DuplicateIdentityUid(PubKey),
+    /// An identity of the block does not have a matching joiner
+    IdentityWithoutJoiner(PubKey),
+    /// The same pubkey appears several times in joiners, actives and leavers
+    DuplicateMembership(PubKey),
+    /// A certification whose issuer is its target
+    SelfCertification(PubKey),
+    /// The same certification appears several times in the block
+    DuplicateCertification(PubKey, PubKey),
+    /// issuers_count does not match the number of distinct issuers of the current frame
+    InvalidIssuersCount {
+        /// Expected value
+        expected: usize,
+        /// Value in block
+        found: usize,
+    },
+    /// issuers_frame is not the expected one
+    InvalidIssuersFrame {
+        /// Expected value
+        expected: isize,
+        /// Value in block
+        found: isize,
+    },
+    /// issuers_frame_var is not the expected one
+    InvalidIssuersFrameVar {
+        /// Expected value
+        expected: isize,
+        /// Value in block
+        found: isize,
+    },
+    /// members_count is not the expected one
+    InvalidMembersCount {
+        /// Expected value
+        expected: usize,
+        /// Value in block
+        found: usize,
+    },
+    /// median_time is not the median of the previous blocks times
+    InvalidMedianTime {
+        /// Expected value
+        expected: u64,
+        /// Value in block
+        found: u64,
+    },
+    /// dividend is not the expected one
+    InvalidDividend {
+        /// Expected value
+        expected: Option<usize>,
+        /// Value in block
+        found: Option<usize>,
+    },
+    /// unit_base is not the expected one
+    InvalidUnitBase {
+        /// Expected value
+        expected: usize,
+        /// Value in block
+        found: usize,
+    },
+    /// monetary_mass is not the expected one
+    InvalidMonetaryMass {
+        /// Expected value
+        expected: usize,
+        /// Value in block
+        found: usize,
+    },
+    /// time is out of [median_time; median_time + max acceleration]
+    TimeOutOfBounds,
+    /// pow_min is not the expected one
+    InvalidPowMin {
+        /// Expected value
+        expected: usize,
+        /// Value in block
+        found: usize,
+    },
+    /// The block hash does not reach the personalized difficulty of its issuer
+    InsufficientProofOfWork {
+        /// Personalized difficulty of the issuer
+        required_difficulty: usize,
+    },
+    /// An identity of the block already exist
+    IdentityAlreadyExist(PubKey),
+    /// The uid of an identity of the block is already used
+    UidAlreadyUsed(PubKey),
+    /// The blockstamp of an identity is unknown or older than idty_window
+    IdentityOutOfWindow(PubKey),
+    /// A joiner has no identity (in the block or in the database)
+    JoinerWithoutIdentity(PubKey),
+    /// A joiner is already a member
+    JoinerAlreadyMember(PubKey),
+    /// A joiner identity is revoked
+    JoinerRevoked(PubKey),
+    /// The blockstamp of a membership is unknown or older than ms_window
+    MembershipOutOfWindow(PubKey),
+    /// A membership is written before the end of the ms_period of the previous one
+    MembershipNotChainable(PubKey),
+    /// A joiner does not receive enough certifications (sig_qty)
+    NotEnoughCertifications(PubKey),
+    /// A joiner does not respect the distance rule
+    Outdistanced(PubKey),
+    /// An active (renewal) does not respect the distance rule
+    ActiveOutdistanced(PubKey),
+    /// An active (renewal) is not a member
+    ActiveNotMember(PubKey),
+    /// A leaver is not a member
+    LeaverNotMember(PubKey),
+    /// The issuer of a certification is not a member
+    CertIssuerNotMember(PubKey),
+    /// The target of a certification is neither a member nor a joiner
+    CertTargetNotMember(PubKey),
+    /// The block of a certification is unknown or older than sig_window
+    CertOutOfWindow(PubKey, PubKey),
+    /// A certification issuer has written a certification less than sig_period ago
+    CertNotChainable(PubKey),
+    /// A certification issuer has already used all its sig_stock
+    CertStockExhausted(PubKey),
+    /// A certification renewal written before sig_renew_period
+    CertReplay(PubKey, PubKey),
+    /// A revocation targets an unknown identity
+    RevokedUnknownIdentity(PubKey),
+    /// A revocation targets an already revoked identity
+    AlreadyRevoked(PubKey),
+    /// The signature of a revocation is invalid
+    InvalidRevocationSignature(PubKey),
+    /// An excluded identity is not a member
+    ExcludedNotMember(PubKey),
+    /// A member whose membership has expired is not excluded
+    ExpiredMemberNotExcluded(PubKey),
+    /// A member receiving less than sig_qty certifications after the expiry of certifications
+    /// is not excluded
+    MemberLosingCertsNotExcluded(PubKey),
+    /// A member is excluded while its membership has not expired, it keeps enough
+    /// certifications and it is not revoked
+    UnexpectedExclusion(PubKey),
+    /// A transaction of the block is invalid
+    InvalidTransaction(Hash, InvalidTxError),
+    /// A transaction of the block is not provided (only its hash)
+    MissingTransaction(Hash),
 }
 
-pub fn verify_block_validity<W: WebOfTrust>(
-    block: &BlockDocument,
-    blockchain_db: &BinDB<LocalBlockchainV10Datas>,
-    _certs_db: &BinDB<CertsExpirV10Datas>,
-    _wot_index: &HashMap<PubKey, NodeId>,
-    _wot_db: &BinDB<W>,
-) -> Result<(), BlockError> {
-    // Rules that do not concern genesis block
-    if block.number.0 > 0 {
-        // Get previous block
-        let previous_block_opt = readers::block::get_block_in_local_blockchain(
-            blockchain_db,
-            BlockNumber(block.number.0 - 1),
-        )?;
-
-        // Previous block must exist
-        if previous_block_opt.is_none() {
-            return Err(BlockError::InvalidBlock(InvalidBlockError::NoPreviousBlock));
-        }
-        let previous_block = previous_block_opt.expect("safe unwrap");
+impl From<InvalidBlockError> for BlockError {
+    fn from(err: InvalidBlockError) -> Self {
+        BlockError::InvalidBlock(err)
+    }
+}
 
-        // Block version must not decrease
-        if previous_block.version > block.version {
-            return Err(BlockError::InvalidBlock(InvalidBlockError::VersionDecrease));
+/// Get currency parameters to use to check the block
+/// (the genesis block defines its own parameters)
+pub fn block_currency_params(
+    block: &BlockDocument,
+    currency_params: &CurrencyParameters,
+) -> Result<CurrencyParameters, InvalidBlockError> {
+    if block.number.0 == 0 {
+        if let Some(parameters) = block.parameters {
+            Ok(CurrencyParameters::from((
+                block.currency.clone(),
+                parameters,
+            )))
+        } else {
+            Err(InvalidBlockError::InvalidParameters)
         }
+    } else {
+        Ok(*currency_params)
     }
+}
 
-    Ok(())
+/// Verify that the block complies with all the local and global rules of the protocol
+pub fn verify_block_validity(
+    block: &BlockDocument,
+    currency_params: &CurrencyParameters,
+    blocks_dbs: &BlocksV10DBs,
+    wot_dbs: &WotsV10DBs,
+    currency_dbs: &CurrencyV10DBs,
+    wot_index: &HashMap<PubKey, NodeId>,
+) -> Result<(), BlockError> {
+    let currency_params = block_currency_params(block, currency_params)?;
+    let mut datas = BlockRuleDatas::new(
+        block,
+        currency_params,
+        blocks_dbs,
+        wot_dbs,
+        currency_dbs,
+        wot_index,
+    )?;
+    rules::apply_rules(&mut datas)
+}
+
+/// Verify that a block template generated by the local node complies with all the rules
+/// of the protocol, except the proof of work which is not yet computed
+pub fn verify_block_template_validity(
+    block: &BlockDocument,
+    currency_params: &CurrencyParameters,
+    blocks_dbs: &BlocksV10DBs,
+    wot_dbs: &WotsV10DBs,
+    currency_dbs: &CurrencyV10DBs,
+) -> Result<(), BlockError> {
+    let currency_params = block_currency_params(block, currency_params)?;
+    let wot_index = readers::identity::get_wot_index(&wot_dbs.identities_db)?;
+    let mut datas = BlockRuleDatas::new(
+        block,
+        currency_params,
+        blocks_dbs,
+        wot_dbs,
+        currency_dbs,
+        &wot_index,
+    )?;
+    datas.check_proof_of_work = false;
+    rules::apply_rules(&mut datas)
 }
@@ -0,0 +1,367 @@
+//  Copyright (C) 2018  The Duniter Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Block rules, numbered and versioned, applied by the rules engine.
+
+use crate::dubp::check::{dividend, header, local, tx, wot, InvalidBlockError};
+use crate::dubp::BlockError;
+use dubp_documents::documents::block::{BlockDocument, TxDocOrTxHash};
+use dubp_documents::*;
+use dup_crypto::keys::PubKey;
+use durs_blockchain_dal::entities::currency_params::CurrencyParameters;
+use durs_blockchain_dal::entities::identity::DALIdentity;
+use durs_blockchain_dal::*;
+use durs_common_tools::fatal_error;
+use durs_wot::NodeId;
+use failure::Fail;
+use maplit::btreemap;
+use rules_engine::rule::{Rule, RuleError, RuleFn, RuleFnRef, RuleNumber};
+use rules_engine::{EngineError, Protocol, ProtocolVersion, RulesEngine, RulesGroup};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Deref;
+
+#[derive(Debug)]
+/// Datas needed by the block rules
+pub struct BlockRuleDatas<'a> {
+    /// Block to check
+    pub block: &'a BlockDocument,
+    /// Currency parameters
+    pub currency_params: CurrencyParameters,
+    /// Previous blocks (from the oldest to the newest)
+    pub previous_blocks: Vec<BlockDocument>,
+    /// Local blockchain database
+    pub blockchain_db: &'a LocalBlockchainV10DB,
+    /// Universal dividend state after each block of the local blockchain
+    pub ud_states_db: &'a UdStatesV10DB,
+    /// Wot databases
+    pub wot_dbs: &'a WotsV10DBs,
+    /// Currency databases
+    pub currency_dbs: &'a CurrencyV10DBs,
+    /// Wot index
+    pub wot_index: &'a HashMap<PubKey, NodeId>,
+    /// Identities (in database) of all the pubkeys involved in the block
+    pub identities: HashMap<PubKey, DALIdentity>,
+    /// Pubkeys of the block identities
+    pub newcomers: HashSet<PubKey>,
+    /// Pubkeys of the block joiners
+    pub joiners: HashSet<PubKey>,
+    /// Check the proof of work (disabled for the block templates generated by the local node)
+    pub check_proof_of_work: bool,
+}
+
+impl<'a> BlockRuleDatas<'a> {
+    /// Collect the datas needed to check a block
+    pub fn new(
+        block: &'a BlockDocument,
+        currency_params: CurrencyParameters,
+        blocks_dbs: &'a BlocksV10DBs,
+        wot_dbs: &'a WotsV10DBs,
+        currency_dbs: &'a CurrencyV10DBs,
+        wot_index: &'a HashMap<PubKey, NodeId>,
+    ) -> Result<BlockRuleDatas<'a>, BlockError> {
+        let blockchain_db = &blocks_dbs.blockchain_db;
+        let previous_blocks = if block.number.0 > 0 {
+            // Previous block must exist
+            let previous_block = if let Some(previous_block) =
+                readers::block::get_block_in_local_blockchain(
+                    blockchain_db,
+                    BlockNumber(block.number.0 - 1),
+                )? {
+                previous_block
+            } else {
+                return Err(BlockError::InvalidBlock(InvalidBlockError::NoPreviousBlock));
+            };
+            let count = std::cmp::max(
+                std::cmp::max(
+                    currency_params.median_time_blocks,
+                    currency_params.dt_diff_eval,
+                ),
+                std::cmp::max(previous_block.issuers_frame, 0) as usize,
+            );
+            get_previous_blocks(blockchain_db, block.number, count)?
+        } else {
+            vec![]
+        };
+        Ok(BlockRuleDatas {
+            block,
+            currency_params,
+            previous_blocks,
+            blockchain_db,
+            ud_states_db: &blocks_dbs.ud_states_db,
+            wot_dbs,
+            currency_dbs,
+            wot_index,
+            identities: wot::get_block_identities(block, &wot_dbs.identities_db)?,
+            newcomers: block.identities.iter().map(|i| i.issuers()[0]).collect(),
+            joiners: block.joiners.iter().map(|ms| ms.issuers()[0]).collect(),
+            check_proof_of_work: true,
+        })
+    }
+
+    /// Median time of the previous block (`None` for the genesis block)
+    pub fn previous_median_time(&self) -> Option<u64> {
+        self.previous_blocks.last().map(|b| b.median_time)
+    }
+}
+
+/// Get the `count` blocks preceding the block `block_number` (from the oldest to the newest)
+fn get_previous_blocks(
+    blockchain_db: &LocalBlockchainV10DB,
+    block_number: BlockNumber,
+    count: usize,
+) -> Result<Vec<BlockDocument>, DALError> {
+    let first_number = block_number.0 - std::cmp::min(count as u32, block_number.0);
+    Ok(blockchain_db
+        .range(Some(&BlockNumber(first_number)), Some(&block_number))?
+        .into_iter()
+        .map(|(_, dal_block)| dal_block.block)
+        .collect())
+}
+
+#[derive(Debug, Copy, Clone, Eq, Fail, PartialEq)]
+/// Error returned by a block rule
+pub enum BlockRuleError {
+    #[fail(display = "invalid block: {:?}", _0)]
+    /// The block does not comply with the rule
+    InvalidBlock(InvalidBlockError),
+    #[fail(display = "fail to read databases: {:?}", _0)]
+    /// The rule could not be checked
+    DALError(DALError),
+}
+
+impl From<InvalidBlockError> for BlockRuleError {
+    fn from(err: InvalidBlockError) -> Self {
+        BlockRuleError::InvalidBlock(err)
+    }
+}
+
+impl From<DALError> for BlockRuleError {
+    fn from(err: DALError) -> Self {
+        BlockRuleError::DALError(err)
+    }
+}
+
+impl From<EngineError<BlockRuleError>> for BlockError {
+    fn from(err: EngineError<BlockRuleError>) -> Self {
+        match err {
+            EngineError::RuleError(RuleError {
+                rule_number,
+                cause: BlockRuleError::InvalidBlock(cause),
+            }) => BlockError::InvalidBlockRule(rule_number, cause),
+            EngineError::RuleError(RuleError {
+                cause: BlockRuleError::DALError(dal_err),
+                ..
+            }) => BlockError::DALError(dal_err),
+            EngineError::ProtocolVersionNotExist { protocol_version } => {
+                BlockError::InvalidBlock(InvalidBlockError::UnsupportedVersion(protocol_version.0))
+            }
+            _ => fatal_error!("Block rules are misconfigured: {}", err),
+        }
+    }
+}
+
+fn r1_version(datas: &BlockRuleDatas) -> Result<(), BlockRuleError> {
+    Ok(header::verify_version(datas.block, &datas.previous_blocks)?)
+}
+
+fn r2_parameters(datas: &BlockRuleDatas) -> Result<(), BlockRuleError> {
+    Ok(local::verify_parameters(datas.block)?)
+}
+
+fn r3_issuers(datas: &BlockRuleDatas) -> Result<(), BlockRuleError> {
+    Ok(local::verify_issuers(datas.block)?)
+}
+
+fn r4_identities_unicity(datas: &BlockRuleDatas) -> Result<(), BlockRuleError> {
+    Ok(local::verify_identities_unicity(datas.block)?)
+}
+
+fn r5_memberships_unicity(datas: &BlockRuleDatas) -> Result<(), BlockRuleError> {
+    Ok(local::verify_memberships_unicity(datas.block)?)
+}
+
+fn r6_certifications_unicity(datas: &BlockRuleDatas) -> Result<(), BlockRuleError> {
+    Ok(local::verify_certifications(datas.block)?)
+}
+
+fn r7_previous_issuer(datas: &BlockRuleDatas) -> Result<(), BlockRuleError> {
+    Ok(header::verify_previous_issuer(
+        datas.block,
+        &datas.previous_blocks,
+    )?)
+}
+
+fn r8_issuers_frame(datas: &BlockRuleDatas) -> Result<(), BlockRuleError> {
+    Ok(header::verify_issuers_frame(
+        datas.block,
+        &datas.previous_blocks,
+    )?)
+}
+
+fn r9_members_count(datas: &BlockRuleDatas) -> Result<(), BlockRuleError> {
+    Ok(header::verify_members_count(
+        datas.block,
+        &datas.previous_blocks,
+    )?)
+}
+
+fn r10_time(datas: &BlockRuleDatas) -> Result<(), BlockRuleError> {
+    Ok(header::verify_time(
+        datas.block,
+        &datas.previous_blocks,
+        &datas.currency_params,
+    )?)
+}
+
+fn r11_pow_min(datas: &BlockRuleDatas) -> Result<(), BlockRuleError> {
+    Ok(header::verify_pow_min(
+        datas.block,
+        &datas.previous_blocks,
+        &datas.currency_params,
+    )?)
+}
+
+fn r12_proof_of_work(datas: &BlockRuleDatas) -> Result<(), BlockRuleError> {
+    if !datas.check_proof_of_work {
+        return Ok(());
+    }
+    Ok(header::verify_proof_of_work(
+        datas.block,
+        &datas.previous_blocks,
+        &datas.currency_params,
+    )?)
+}
+
+fn r18_transactions(datas: &BlockRuleDatas) -> Result<(), BlockRuleError> {
+    let dbs = tx::TxCheckDBs::new(datas.blockchain_db, datas.currency_dbs);
+    let mut overlay = tx::TxsOverlay::default();
+    for tx_doc in &datas.block.transactions {
+        let tx_doc = match *tx_doc {
+            TxDocOrTxHash::TxDoc(ref tx_doc) => tx_doc,
+            TxDocOrTxHash::TxHash(hash) => {
+                return Err(InvalidBlockError::MissingTransaction(hash).into());
+            }
+        };
+        let tx_hash = tx_doc
+            .get_hash_opt()
+            .unwrap_or_else(|| tx_doc.deref().clone().compute_hash());
+        match tx::verify_transaction(
+            dbs,
+            &overlay,
+            tx_doc,
+            datas.block.median_time,
+            datas.currency_params.tx_window,
+        ) {
+            Ok(()) => overlay.apply(tx_doc, tx_hash),
+            Err(tx::TxCheckError::InvalidTx(err)) => {
+                return Err(InvalidBlockError::InvalidTransaction(tx_hash, err).into());
+            }
+            Err(tx::TxCheckError::DALError(err)) => return Err(err.into()),
+        }
+    }
+    Ok(())
+}
+
+/// Create a block rule implemented since the protocol V10
+fn rule<'a>(
+    rule_number: usize,
+    rule_fn: RuleFnRef<BlockRuleDatas<'a>, BlockRuleError>,
+) -> (RuleNumber, Rule<BlockRuleDatas<'a>, BlockRuleError>) {
+    let rule_number = RuleNumber(rule_number);
+    let rule = Rule::new(
+        rule_number,
+        btreemap![ProtocolVersion(10) => RuleFn::Ref(rule_fn)],
+    )
+    .unwrap_or_else(|_| fatal_error!("Fail to create block rule n°{}", rule_number));
+    (rule_number, rule)
+}
+
+/// Get all block rules
+pub fn get_rules<'a>() -> BTreeMap<RuleNumber, Rule<BlockRuleDatas<'a>, BlockRuleError>> {
+    vec![
+        rule(1, r1_version),
+        rule(2, r2_parameters),
+        rule(3, r3_issuers),
+        rule(4, r4_identities_unicity),
+        rule(5, r5_memberships_unicity),
+        rule(6, r6_certifications_unicity),
+        rule(7, r7_previous_issuer),
+        rule(8, r8_issuers_frame),
+        rule(9, r9_members_count),
+        rule(10, r10_time),
+        rule(11, r11_pow_min),
+        rule(12, r12_proof_of_work),
+        rule(13, wot::verify_identities),
+        rule(14, wot::verify_memberships),
+        rule(15, wot::verify_revocations_and_exclusions),
+        rule(16, wot::verify_certifications),
+        rule(17, wot::verify_joiners_certs_and_distance),
+        rule(18, r18_transactions),
+        rule(19, dividend::verify_dividend),
+        rule(20, wot::verify_memberships_expiry),
+    ]
+    .into_iter()
+    .collect()
+}
+
+/// Get the rules to apply for each version of the protocol
+///
+/// Local rules are applied first, then the global rules. Inside each group, rules are independent
+/// and applied in parallel.
+pub fn get_protocol() -> Protocol {
+    Protocol::new(btreemap![
+        ProtocolVersion(10) => vec![
+            RulesGroup::pr(vec![2, 3, 4, 5, 6]),
+            RulesGroup::pr(vec![1, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20]),
+        ].into(),
+    ])
+}
+
+/// Apply all the rules of the block protocol version
+pub fn apply_rules(datas: &mut BlockRuleDatas) -> Result<(), BlockError> {
+    let protocol_version = ProtocolVersion(datas.block.version as usize);
+    Ok(RulesEngine::new(get_rules()).apply_protocol(get_protocol(), protocol_version, datas)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rules_engine::ProtocolRules;
+
+    #[test]
+    fn test_all_protocol_rules_exist() {
+        let rules = get_rules();
+        let protocol = get_protocol();
+        let ProtocolRules(rules_groups) = protocol
+            .get(ProtocolVersion(10))
+            .expect("Protocol V10 must exist")
+            .clone();
+        let mut protocol_rules_numbers = Vec::new();
+        for rules_group in rules_groups {
+            if let RulesGroup::Par(groups) = rules_group {
+                for group in groups {
+                    if let RulesGroup::Ser(rules_numbers) = group {
+                        protocol_rules_numbers.extend(rules_numbers);
+                    }
+                }
+            }
+        }
+        protocol_rules_numbers.sort();
+        assert_eq!(
+            rules.keys().cloned().collect::<Vec<_>>(),
+            protocol_rules_numbers
+        );
+    }
+}
@@ -0,0 +1,690 @@
+//  Copyright (C) 2018  The Duniter Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Transactions rules: consumed sources, unlock conditions, amounts and blockstamp window.
+//!
+//! These rules are shared by the block checks and the mempool.
+
+use dubp_documents::documents::transaction::*;
+use dubp_documents::*;
+use dup_crypto::hashs::Hash;
+use dup_crypto::keys::PubKey;
+use durs_blockchain_dal::entities::sources::{SourceAmount, SourceIndexV10, UTXOIndexV10};
+use durs_blockchain_dal::*;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// Reason why a transaction is invalid
+pub enum InvalidTxError {
+    /// The signatures of the transaction are invalid
+    InvalidSignature,
+    /// The blockstamp of the transaction is unknown or older than tx_window
+    OutOfWindow,
+    /// The same source is consumed several times by the transaction
+    DuplicateInput(SourceIndexV10),
+    /// A consumed source does not exist or is already consumed
+    UnknownSource(SourceIndexV10),
+    /// The amount (or base) of an input differs from the amount of its source
+    WrongSourceAmount(SourceIndexV10),
+    /// An input has no unlock, or an unlock references an unknown input
+    InvalidUnlocks,
+    /// An issuer signs none of the inputs
+    IssuerWithoutInput(PubKey),
+    /// The unlock proofs of an input do not satisfy the conditions of its source
+    LockedSource(SourceIndexV10),
+    /// The outputs amount differs from the inputs amount
+    UnbalancedAmounts,
+    /// An output base is greater than the highest input base
+    InvalidOutputBase,
+    /// An amount, a base or a lock duration is too large to be computed
+    Overflow,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// Error returned by the transactions checker
+pub enum TxCheckError {
+    /// The transaction is invalid
+    InvalidTx(InvalidTxError),
+    /// Fail to read databases
+    DALError(DALError),
+}
+
+impl From<InvalidTxError> for TxCheckError {
+    fn from(err: InvalidTxError) -> Self {
+        TxCheckError::InvalidTx(err)
+    }
+}
+
+impl From<DALError> for TxCheckError {
+    fn from(err: DALError) -> Self {
+        TxCheckError::DALError(err)
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+/// Databases read by the transactions checker
+pub struct TxCheckDBs<'a> {
+    /// Local blockchain database
+    pub blockchain_db: &'a LocalBlockchainV10DB,
+    /// UDs sources database
+    pub du_db: &'a UDsV10DB,
+    /// UTXOs database
+    pub utxos_db: &'a UTXOsV10DB,
+    /// Transactions database
+    pub tx_db: &'a TxV10DB,
+}
+
+impl<'a> TxCheckDBs<'a> {
+    /// Databases of the blockchain module
+    pub fn new(
+        blockchain_db: &'a LocalBlockchainV10DB,
+        currency_dbs: &'a CurrencyV10DBs,
+    ) -> TxCheckDBs<'a> {
+        TxCheckDBs {
+            blockchain_db,
+            du_db: &currency_dbs.du_db,
+            utxos_db: &currency_dbs.utxos_db,
+            tx_db: &currency_dbs.tx_db,
+        }
+    }
+    fn block_median_time(&self, block_number: BlockNumber) -> Result<Option<u64>, DALError> {
+        Ok(self
+            .blockchain_db
+            .get(&block_number)?
+            .map(|b| b.block.median_time))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// Sources created and consumed by the transactions already checked in the same block
+pub struct TxsOverlay {
+    created: HashMap<UTXOIndexV10, TransactionOutput>,
+    consumed: HashSet<SourceIndexV10>,
+}
+
+impl TxsOverlay {
+    /// Take into account a valid transaction of the block
+    pub fn apply(&mut self, tx: &TransactionDocument, tx_hash: Hash) {
+        for input in tx.get_inputs() {
+            let source = SourceIndexV10::from(*input);
+            if let SourceIndexV10::UTXO(utxo_index) = source {
+                if self.created.remove(&utxo_index).is_some() {
+                    continue;
+                }
+            }
+            self.consumed.insert(source);
+        }
+        for (index, output) in tx.get_outputs().iter().enumerate() {
+            self.created
+                .insert(UTXOIndexV10(tx_hash, TxIndex(index)), output.clone());
+        }
+    }
+}
+
+/// Source consumed by an input
+struct ConsumedSource {
+    amount: SourceAmount,
+    conditions: UTXOConditionsGroup,
+    /// Median time of the block in which the source was created (`None` if unknown)
+    written_time: Option<u64>,
+}
+
+/// Find the source consumed by an input
+fn get_source(
+    dbs: TxCheckDBs,
+    overlay: &TxsOverlay,
+    source: SourceIndexV10,
+    median_time: u64,
+) -> Result<Option<ConsumedSource>, DALError> {
+    if overlay.consumed.contains(&source) {
+        return Ok(None);
+    }
+    match source {
+        SourceIndexV10::UD(pubkey, block_number) => {
+            let ud_exist = dbs
+                .du_db
+                .get(&pubkey)?
+                .map_or(false, |uds| uds.contains(&block_number));
+            if !ud_exist {
+                return Ok(None);
+            }
+            Ok(dbs.blockchain_db.get(&block_number)?.and_then(|dal_block| {
+                dal_block.block.dividend.map(|dividend| ConsumedSource {
+                    amount: SourceAmount(
+                        TxAmount(dividend as isize),
+                        TxBase(dal_block.block.unit_base),
+                    ),
+                    conditions: UTXOConditionsGroup::Single(TransactionOutputCondition::Sig(
+                        pubkey,
+                    )),
+                    written_time: Some(dal_block.block.median_time),
+                })
+            }))
+        }
+        SourceIndexV10::UTXO(utxo_index) => {
+            if let Some(output) = overlay.created.get(&utxo_index) {
+                return Ok(Some(ConsumedSource {
+                    amount: SourceAmount(output.amount, output.base),
+                    conditions: output.conditions.conditions.clone(),
+                    written_time: Some(median_time),
+                }));
+            }
+            let output = if let Some(output) = dbs.utxos_db.get(&utxo_index)? {
+                output
+            } else {
+                return Ok(None);
+            };
+            let written_on = dbs
+                .tx_db
+                .get(&utxo_index.0)?
+                .map(|dal_tx| dal_tx.written_on.id);
+            let written_time = if let Some(written_on) = written_on {
+                dbs.block_median_time(written_on)?
+            } else {
+                None
+            };
+            Ok(Some(ConsumedSource {
+                amount: SourceAmount(output.amount, output.base),
+                conditions: output.conditions.conditions,
+                written_time,
+            }))
+        }
+    }
+}
+
+/// Check if unlock proofs satisfy conditions
+fn conditions_unlocked(
+    conditions: &UTXOConditionsGroup,
+    proofs: &[TransactionUnlockProof],
+    issuers: &[PubKey],
+    median_time: u64,
+    written_time: Option<u64>,
+) -> Result<bool, InvalidTxError> {
+    match *conditions {
+        UTXOConditionsGroup::Single(ref condition) => match *condition {
+            TransactionOutputCondition::Sig(ref pubkey) => Ok(proofs.iter().any(|proof| {
+                if let TransactionUnlockProof::Sig(index) = *proof {
+                    issuers.get(index) == Some(pubkey)
+                } else {
+                    false
+                }
+            })),
+            TransactionOutputCondition::Xhx(ref hash) => Ok(proofs.iter().any(|proof| {
+                if let TransactionUnlockProof::Xhx(ref code) = *proof {
+                    Hash::compute_str(code) == *hash
+                } else {
+                    false
+                }
+            })),
+            TransactionOutputCondition::Cltv(timestamp) => Ok(median_time >= timestamp),
+            TransactionOutputCondition::Csv(duration) => {
+                if let Some(written_time) = written_time {
+                    let unlock_time = written_time
+                        .checked_add(duration)
+                        .ok_or(InvalidTxError::Overflow)?;
+                    Ok(median_time >= unlock_time)
+                } else {
+                    Ok(false)
+                }
+            }
+        },
+        UTXOConditionsGroup::Brackets(ref group) => {
+            conditions_unlocked(group, proofs, issuers, median_time, written_time)
+        }
+        UTXOConditionsGroup::And(ref group1, ref group2) => {
+            Ok(
+                conditions_unlocked(group1, proofs, issuers, median_time, written_time)?
+                    && conditions_unlocked(group2, proofs, issuers, median_time, written_time)?,
+            )
+        }
+        UTXOConditionsGroup::Or(ref group1, ref group2) => {
+            Ok(
+                conditions_unlocked(group1, proofs, issuers, median_time, written_time)?
+                    || conditions_unlocked(group2, proofs, issuers, median_time, written_time)?,
+            )
+        }
+    }
+}
+
+/// Amount in units of base 0
+fn amount_value(amount: TxAmount, base: TxBase) -> Result<i128, InvalidTxError> {
+    u32::try_from(base.0)
+        .ok()
+        .and_then(|base| 10i128.checked_pow(base))
+        .and_then(|power| (amount.0 as i128).checked_mul(power))
+        .ok_or(InvalidTxError::Overflow)
+}
+
+/// Sum of amounts in units of base 0
+fn amounts_sum<I: Iterator<Item = (TxAmount, TxBase)>>(
+    mut amounts: I,
+) -> Result<i128, InvalidTxError> {
+    amounts.try_fold(0i128, |sum, (amount, base)| {
+        sum.checked_add(amount_value(amount, base)?)
+            .ok_or(InvalidTxError::Overflow)
+    })
+}
+
+/// Check that inputs and outputs balance
+pub fn verify_amounts(tx: &TransactionDocument) -> Result<(), InvalidTxError> {
+    let max_input_base = tx
+        .get_inputs()
+        .iter()
+        .map(|input| match *input {
+            TransactionInput::D(_, base, _, _) | TransactionInput::T(_, base, _, _) => base,
+        })
+        .max()
+        .unwrap_or(TxBase(0));
+    if tx
+        .get_outputs()
+        .iter()
+        .any(|output| output.base > max_input_base)
+    {
+        return Err(InvalidTxError::InvalidOutputBase);
+    }
+    let inputs_value = amounts_sum(tx.get_inputs().iter().map(|input| match *input {
+        TransactionInput::D(amount, base, _, _) | TransactionInput::T(amount, base, _, _) => {
+            (amount, base)
+        }
+    }))?;
+    let outputs_value = amounts_sum(
+        tx.get_outputs()
+            .iter()
+            .map(|output| (output.amount, output.base)),
+    )?;
+    if inputs_value == outputs_value {
+        Ok(())
+    } else {
+        Err(InvalidTxError::UnbalancedAmounts)
+    }
+}
+
+/// Check that each issuer signs at least one input
+fn verify_issuers_sign_inputs(tx: &TransactionDocument) -> Result<(), InvalidTxError> {
+    let signing_issuers: HashSet<usize> = tx
+        .get_unlocks()
+        .iter()
+        .flat_map(|unlock| unlock.unlocks.iter())
+        .filter_map(|proof| {
+            if let TransactionUnlockProof::Sig(index) = *proof {
+                Some(index)
+            } else {
+                None
+            }
+        })
+        .collect();
+    if let Some((_, issuer)) = tx
+        .issuers()
+        .iter()
+        .enumerate()
+        .find(|(index, _)| !signing_issuers.contains(index))
+    {
+        Err(InvalidTxError::IssuerWithoutInput(*issuer))
+    } else {
+        Ok(())
+    }
+}
+
+/// Verify that a transaction can be written in a block of median time `median_time`
+///
+/// The sources consumed by the transaction must exist (in the databases or in `overlay`),
+/// be unlocked and be fully spent.
+pub fn verify_transaction(
+    dbs: TxCheckDBs,
+    overlay: &TxsOverlay,
+    tx: &TransactionDocument,
+    median_time: u64,
+    tx_window: u64,
+) -> Result<(), TxCheckError> {
+    if tx.verify_signatures() != VerificationResult::Valid() {
+        return Err(InvalidTxError::InvalidSignature.into());
+    }
+    let blockstamp = tx.blockstamp();
+    let blockstamp_median_time = dbs
+        .blockchain_db
+        .get(&blockstamp.id)?
+        .filter(|dal_block| dal_block.block.hash == Some(blockstamp.hash))
+        .map(|dal_block| dal_block.block.median_time);
+    match blockstamp_median_time {
+        Some(blockstamp_median_time)
+            if blockstamp_median_time.saturating_add(tx_window) >= median_time => {}
+        _ => return Err(InvalidTxError::OutOfWindow.into()),
+    }
+    let unlocks = tx.get_unlocks();
+    if unlocks
+        .iter()
+        .any(|unlock| unlock.index >= tx.get_inputs().len())
+    {
+        return Err(InvalidTxError::InvalidUnlocks.into());
+    }
+    verify_issuers_sign_inputs(tx)?;
+    let mut sources = HashSet::with_capacity(tx.get_inputs().len());
+    for (index, input) in tx.get_inputs().iter().enumerate() {
+        let source_index = SourceIndexV10::from(*input);
+        if !sources.insert(source_index) {
+            return Err(InvalidTxError::DuplicateInput(source_index).into());
+        }
+        let source = if let Some(source) = get_source(dbs, overlay, source_index, median_time)? {
+            source
+        } else {
+            return Err(InvalidTxError::UnknownSource(source_index).into());
+        };
+        let input_amount = match *input {
+            TransactionInput::D(amount, base, _, _) | TransactionInput::T(amount, base, _, _) => {
+                SourceAmount(amount, base)
+            }
+        };
+        if input_amount != source.amount {
+            return Err(InvalidTxError::WrongSourceAmount(source_index).into());
+        }
+        let proofs = if let Some(unlock) = unlocks.iter().find(|unlock| unlock.index == index) {
+            &unlock.unlocks
+        } else {
+            return Err(InvalidTxError::InvalidUnlocks.into());
+        };
+        if !conditions_unlocked(
+            &source.conditions,
+            proofs,
+            tx.issuers(),
+            median_time,
+            source.written_time,
+        )? {
+            return Err(InvalidTxError::LockedSource(source_index).into());
+        }
+    }
+    verify_amounts(tx)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dup_crypto::keys::*;
+    use durs_blockchain_dal::entities::block::DALBlock;
+    use std::str::FromStr;
+
+    fn build_tx(issuers: &[PubKey], unlocks: &[TransactionInputUnlocks]) -> TransactionDocument {
+        TransactionDocumentBuilder {
+            currency: "g1",
+            blockstamp: &Blockstamp::default(),
+            locktime: &0,
+            issuers: &issuers.to_vec(),
+            inputs: &vec![TransactionInput::from_str(
+                "1000:0:D:2ny7YAdmzReQxAayyJZsyVYwYhVyax2thKcGknmQy5nQ:1",
+            )
+            .expect("fail to parse input !")],
+            unlocks: &unlocks.to_vec(),
+            outputs: &vec![],
+            comment: "",
+            hash: None,
+        }
+        .build_with_signature(vec![])
+    }
+
+    fn build_first_tx_of_g1() -> TransactionDocument {
+        let pubkey = PubKey::Ed25519(
+            ed25519::PublicKey::from_base58("2ny7YAdmzReQxAayyJZsyVYwYhVyax2thKcGknmQy5nQ")
+                .expect("Fail to parse pubkey"),
+        );
+        let sig = Sig::Ed25519(ed25519::Signature::from_base64(
+            "fAH5Gor+8MtFzQZ++JaJO6U8JJ6+rkqKtPrRr/iufh3MYkoDGxmjzj6jCADQL+hkWBt8y8QzlgRkz0ixBcKHBw==",
+        ).expect("Fail to parse sig"));
+        let block = Blockstamp::from_string(
+            "50-00001DAA4559FEDB8320D1040B0F22B631459F36F237A0D9BC1EB923C12A12E7",
+        )
+        .expect("Fail to parse blockstamp");
+        let builder = TransactionDocumentBuilder {
+            currency: "g1",
+            blockstamp: &block,
+            locktime: &0,
+            issuers: &vec![pubkey],
+            inputs: &vec![TransactionInput::from_str(
+                "1000:0:D:2ny7YAdmzReQxAayyJZsyVYwYhVyax2thKcGknmQy5nQ:1",
+            )
+            .expect("fail to parse input !")],
+            unlocks: &vec![
+                TransactionInputUnlocks::from_str("0:SIG(0)").expect("fail to parse unlock !")
+            ],
+            outputs: &vec![
+                TransactionOutput::from_str(
+                    "1:0:SIG(Com8rJukCozHZyFao6AheSsfDQdPApxQRnz7QYFf64mm)",
+                )
+                .expect("fail to parse output !"),
+                TransactionOutput::from_str(
+                    "999:0:SIG(2ny7YAdmzReQxAayyJZsyVYwYhVyax2thKcGknmQy5nQ)",
+                )
+                .expect("fail to parse output !"),
+            ],
+            comment: "TEST",
+            hash: None,
+        };
+        builder.build_with_signature(vec![sig])
+    }
+
+    fn insert_block(
+        blockchain_db: &LocalBlockchainV10DB,
+        blockstamp: Blockstamp,
+        median_time: u64,
+        dividend: Option<usize>,
+    ) {
+        let mut block = dubp_documents_tests_tools::mocks::gen_empty_timed_block(
+            blockstamp,
+            median_time,
+            Hash::default(),
+        );
+        block.median_time = median_time;
+        block.dividend = dividend;
+        blockchain_db
+            .insert(
+                &blockstamp.id,
+                &DALBlock {
+                    block,
+                    expire_certs: None,
+                },
+            )
+            .expect("Fail to write blockchain db");
+    }
+
+    #[test]
+    fn test_verify_transaction() {
+        let tx = build_first_tx_of_g1();
+        let issuer = tx.issuers()[0];
+        let blockchain_db = BlocksV10DBs::open(None).blockchain_db;
+        let currency_dbs = CurrencyV10DBs::open(None);
+        let dbs = TxCheckDBs::new(&blockchain_db, &currency_dbs);
+        insert_block(
+            &blockchain_db,
+            Blockstamp {
+                id: BlockNumber(1),
+                hash: BlockHash(Hash::default()),
+            },
+            1_000,
+            Some(1_000),
+        );
+        insert_block(&blockchain_db, tx.blockstamp(), 2_000, None);
+        let ud = SourceIndexV10::UD(issuer, BlockNumber(1));
+
+        // The UD is not yet created
+        assert_eq!(
+            Err(TxCheckError::InvalidTx(InvalidTxError::UnknownSource(ud))),
+            verify_transaction(dbs, &TxsOverlay::default(), &tx, 2_000, 100)
+        );
+
+        currency_dbs
+            .du_db
+            .insert(&issuer, &vec![BlockNumber(1)].into_iter().collect())
+            .expect("Fail to write du db");
+        assert_eq!(
+            Ok(()),
+            verify_transaction(dbs, &TxsOverlay::default(), &tx, 2_000, 100)
+        );
+
+        // The tx blockstamp is too old
+        assert_eq!(
+            Err(TxCheckError::InvalidTx(InvalidTxError::OutOfWindow)),
+            verify_transaction(dbs, &TxsOverlay::default(), &tx, 2_101, 100)
+        );
+        assert_eq!(
+            Ok(()),
+            verify_transaction(
+                dbs,
+                &TxsOverlay::default(),
+                &tx,
+                u64::max_value(),
+                u64::max_value()
+            )
+        );
+
+        // The UD is already consumed by a transaction of the block
+        let mut overlay = TxsOverlay::default();
+        overlay.apply(&tx, Hash::default());
+        assert_eq!(
+            Err(TxCheckError::InvalidTx(InvalidTxError::UnknownSource(ud))),
+            verify_transaction(dbs, &overlay, &tx, 2_000, 100)
+        );
+        assert_eq!(Ok(()), verify_amounts(&tx));
+    }
+
+    #[test]
+    fn test_verify_issuers_sign_inputs() {
+        let issuers = [
+            dup_crypto_tests_tools::mocks::pubkey('A'),
+            dup_crypto_tests_tools::mocks::pubkey('B'),
+        ];
+        let unlocks = |proofs: Vec<TransactionUnlockProof>| {
+            vec![TransactionInputUnlocks {
+                index: 0,
+                unlocks: proofs,
+            }]
+        };
+        assert_eq!(
+            Ok(()),
+            verify_issuers_sign_inputs(&build_tx(
+                &issuers,
+                &unlocks(vec![
+                    TransactionUnlockProof::Sig(1),
+                    TransactionUnlockProof::Sig(0),
+                ])
+            ))
+        );
+        assert_eq!(
+            Err(InvalidTxError::IssuerWithoutInput(issuers[1])),
+            verify_issuers_sign_inputs(&build_tx(
+                &issuers,
+                &unlocks(vec![TransactionUnlockProof::Sig(0)])
+            ))
+        );
+        assert_eq!(
+            Err(InvalidTxError::IssuerWithoutInput(issuers[0])),
+            verify_issuers_sign_inputs(&build_tx(
+                &issuers[..1],
+                &unlocks(vec![TransactionUnlockProof::Xhx("secret".to_owned())])
+            ))
+        );
+    }
+
+    fn conditions(text: &str) -> UTXOConditionsGroup {
+        TransactionOutput::from_str(&format!("1:0:{}", text))
+            .expect("Fail to parse output")
+            .conditions
+            .conditions
+    }
+
+    #[test]
+    fn test_conditions_unlocked() {
+        let issuer = dup_crypto_tests_tools::mocks::pubkey('A');
+        let other = dup_crypto_tests_tools::mocks::pubkey('B');
+        let sig = conditions(&format!("SIG({})", issuer));
+        let proofs = vec![TransactionUnlockProof::Sig(0)];
+        assert_eq!(
+            Ok(true),
+            conditions_unlocked(&sig, &proofs, &[issuer], 0, None)
+        );
+        assert_eq!(
+            Ok(false),
+            conditions_unlocked(&sig, &proofs, &[other], 0, None)
+        );
+        assert_eq!(
+            Ok(false),
+            conditions_unlocked(&sig, &[], &[issuer], 0, None)
+        );
+
+        let xhx = conditions(&format!(
+            "(SIG({}) || XHX({}))",
+            other,
+            Hash::compute_str("secret")
+        ));
+        let xhx_proofs = vec![TransactionUnlockProof::Xhx("secret".to_owned())];
+        assert_eq!(
+            Ok(true),
+            conditions_unlocked(&xhx, &xhx_proofs, &[issuer], 0, None)
+        );
+        let wrong_proofs = vec![TransactionUnlockProof::Xhx("wrong".to_owned())];
+        assert_eq!(
+            Ok(false),
+            conditions_unlocked(&xhx, &wrong_proofs, &[issuer], 0, None)
+        );
+
+        let cltv = conditions(&format!("(SIG({}) && CLTV(1000))", issuer));
+        assert_eq!(
+            Ok(false),
+            conditions_unlocked(&cltv, &proofs, &[issuer], 999, None)
+        );
+        assert_eq!(
+            Ok(true),
+            conditions_unlocked(&cltv, &proofs, &[issuer], 1_000, None)
+        );
+
+        let csv = conditions(&format!("(SIG({}) && CSV(100))", issuer));
+        assert_eq!(
+            Ok(false),
+            conditions_unlocked(&csv, &proofs, &[issuer], 1_099, Some(1_000))
+        );
+        assert_eq!(
+            Ok(true),
+            conditions_unlocked(&csv, &proofs, &[issuer], 1_100, Some(1_000))
+        );
+        assert_eq!(
+            Ok(false),
+            conditions_unlocked(&csv, &proofs, &[issuer], 1_100, None)
+        );
+        assert_eq!(
+            Err(InvalidTxError::Overflow),
+            conditions_unlocked(&csv, &proofs, &[issuer], 1_100, Some(u64::max_value()))
+        );
+    }
+
+    #[test]
+    fn test_amounts_overflow() {
+        assert_eq!(Ok(1_200), amount_value(TxAmount(12), TxBase(2)));
+        assert_eq!(
+            Err(InvalidTxError::Overflow),
+            amount_value(TxAmount(1), TxBase(39))
+        );
+        assert_eq!(
+            Err(InvalidTxError::Overflow),
+            amount_value(TxAmount(isize::max_value()), TxBase(38))
+        );
+        assert_eq!(
+            Err(InvalidTxError::Overflow),
+            amount_value(TxAmount(1), TxBase(usize::max_value()))
+        );
+        assert_eq!(
+            Err(InvalidTxError::Overflow),
+            amounts_sum(vec![(TxAmount(1), TxBase(38)); 2].into_iter())
+        );
+    }
+}
@@ -0,0 +1,705 @@
+//  Copyright (C) 2018  The Duniter Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Global rules on the web of trust: identities, memberships, certifications, revocations and exclusions.
+
+use crate::dubp::check::rules::{BlockRuleDatas, BlockRuleError};
+use crate::dubp::check::InvalidBlockError;
+use crate::DISTANCE_CALCULATOR;
+use dubp_documents::documents::block::BlockDocument;
+use dubp_documents::documents::revocation::RevocationDocumentBuilder;
+use dubp_documents::*;
+use dup_crypto::keys::{PubKey, Sig};
+use durs_blockchain_dal::entities::identity::{DALIdentity, DALIdentityState};
+use durs_blockchain_dal::*;
+use durs_wot::data::HasLinkResult;
+use durs_wot::operations::distance::{DistanceCalculator, WotDistanceParameters};
+use durs_wot::{NodeId, WebOfTrust};
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+/// Check if a document blockstamp is within a window of time
+///
+/// The referenced block must exist (with the same hash if given)
+/// and be less than `window` seconds older than the previous block.
+fn is_in_window(
+    datas: &BlockRuleDatas,
+    block_number: BlockNumber,
+    block_hash: Option<BlockHash>,
+    window: u64,
+) -> Result<bool, DALError> {
+    let previous_median_time = if let Some(previous_median_time) = datas.previous_median_time() {
+        previous_median_time
+    } else {
+        // All documents of the genesis block are in window
+        return Ok(true);
+    };
+    Ok(
+        if let Some(dal_block) = datas.blockchain_db.get(&block_number)? {
+            (block_hash.is_none() || dal_block.block.hash == block_hash)
+                && dal_block.block.median_time + window >= previous_median_time
+        } else {
+            false
+        },
+    )
+}
+
+/// Get the identities (in database) of all the pubkeys involved in the block
+pub fn get_block_identities(
+    block: &BlockDocument,
+    identities_db: &IdentitiesV10DB,
+) -> Result<HashMap<PubKey, DALIdentity>, DALError> {
+    let mut pubkeys: HashSet<PubKey> = block
+        .identities
+        .iter()
+        .map(|i| i.issuers()[0])
+        .chain(
+            block
+                .joiners
+                .iter()
+                .chain(block.actives.iter())
+                .chain(block.leavers.iter())
+                .map(|ms| ms.issuers()[0]),
+        )
+        .chain(block.excluded.iter().cloned())
+        .collect();
+    for revocation in &block.revoked {
+        pubkeys.insert(revocation.to_compact_document().issuer);
+    }
+    for cert in &block.certifications {
+        let compact_cert = cert.to_compact_document();
+        pubkeys.insert(compact_cert.issuer);
+        pubkeys.insert(compact_cert.target);
+    }
+    let mut identities = HashMap::with_capacity(pubkeys.len());
+    for pubkey in pubkeys {
+        if let Some(idty) = identities_db.get(&pubkey)? {
+            identities.insert(pubkey, idty);
+        }
+    }
+    Ok(identities)
+}
+
+/// Check if an identity is member
+fn is_member(identities: &HashMap<PubKey, DALIdentity>, pubkey: &PubKey) -> bool {
+    if let Some(idty) = identities.get(pubkey) {
+        if let DALIdentityState::Member(_) = idty.state {
+            return true;
+        }
+    }
+    false
+}
+
+/// Check if an identity is revoked
+fn is_revoked(idty: &DALIdentity) -> bool {
+    match idty.state {
+        DALIdentityState::ExplicitRevoked(_)
+        | DALIdentityState::ExplicitExpireRevoked(_)
+        | DALIdentityState::ImplicitRevoked(_) => true,
+        DALIdentityState::Member(_) | DALIdentityState::ExpireMember(_) => false,
+    }
+}
+
+/// New identities: unique pubkey, unique uid and blockstamp in idty_window
+pub fn verify_identities(datas: &BlockRuleDatas) -> Result<(), BlockRuleError> {
+    let block = datas.block;
+    if block.identities.is_empty() {
+        return Ok(());
+    }
+    for idty in &block.identities {
+        if readers::identity::get_pubkey_from_uid(
+            &datas.wot_dbs.idties_indexes_db,
+            idty.username(),
+        )?
+        .is_some()
+        {
+            return Err(InvalidBlockError::UidAlreadyUsed(idty.issuers()[0]).into());
+        }
+    }
+    for idty in &block.identities {
+        let pubkey = idty.issuers()[0];
+        if datas.identities.contains_key(&pubkey) {
+            return Err(InvalidBlockError::IdentityAlreadyExist(pubkey).into());
+        }
+        let blockstamp = idty.blockstamp();
+        if !is_in_window(
+            datas,
+            blockstamp.id,
+            Some(blockstamp.hash),
+            datas.currency_params.idty_window,
+        )? {
+            return Err(InvalidBlockError::IdentityOutOfWindow(pubkey).into());
+        }
+    }
+    Ok(())
+}
+
+/// Joiners, actives and leavers
+pub fn verify_memberships(datas: &BlockRuleDatas) -> Result<(), BlockRuleError> {
+    let block = datas.block;
+    let identities = &datas.identities;
+    let previous_median_time = datas.previous_median_time();
+    let is_chainable =
+        |idty: &DALIdentity| match (previous_median_time, idty.ms_chainable_on.last()) {
+            (Some(previous_median_time), Some(chainable_on)) => {
+                *chainable_on <= previous_median_time
+            }
+            _ => true,
+        };
+    for ms in block
+        .joiners
+        .iter()
+        .chain(block.actives.iter())
+        .chain(block.leavers.iter())
+    {
+        let pubkey = ms.issuers()[0];
+        let blockstamp = ms.blockstamp();
+        if !is_in_window(
+            datas,
+            blockstamp.id,
+            Some(blockstamp.hash),
+            datas.currency_params.ms_window,
+        )? {
+            return Err(InvalidBlockError::MembershipOutOfWindow(pubkey).into());
+        }
+    }
+    for joiner in &block.joiners {
+        let pubkey = joiner.issuers()[0];
+        if datas.newcomers.contains(&pubkey) {
+            continue;
+        }
+        if let Some(idty) = identities.get(&pubkey) {
+            if is_revoked(idty) {
+                return Err(InvalidBlockError::JoinerRevoked(pubkey).into());
+            } else if is_member(identities, &pubkey) {
+                return Err(InvalidBlockError::JoinerAlreadyMember(pubkey).into());
+            } else if !is_chainable(idty) {
+                return Err(InvalidBlockError::MembershipNotChainable(pubkey).into());
+            }
+        } else {
+            return Err(InvalidBlockError::JoinerWithoutIdentity(pubkey).into());
+        }
+    }
+    for active in &block.actives {
+        let pubkey = active.issuers()[0];
+        if !is_member(identities, &pubkey) {
+            return Err(InvalidBlockError::ActiveNotMember(pubkey).into());
+        } else if !is_chainable(&identities[&pubkey]) {
+            return Err(InvalidBlockError::MembershipNotChainable(pubkey).into());
+        }
+    }
+    for leaver in &block.leavers {
+        let pubkey = leaver.issuers()[0];
+        if !is_member(identities, &pubkey) {
+            return Err(InvalidBlockError::LeaverNotMember(pubkey).into());
+        }
+    }
+    Ok(())
+}
+
+/// Check the signature of a revocation of the identity `idty`
+///
+/// The compact revocation of the block is completed with the identity it targets.
+fn revocation_signature_is_valid(currency: &str, idty: &DALIdentity, signature: Sig) -> bool {
+    let revocation = RevocationDocumentBuilder {
+        currency,
+        issuer: &idty.idty_doc.issuers()[0],
+        identity_username: idty.idty_doc.username(),
+        identity_blockstamp: &idty.idty_doc.blockstamp(),
+        identity_sig: &idty.idty_doc.signatures()[0],
+    }
+    .build_with_signature(vec![signature]);
+    revocation.verify_signatures() == VerificationResult::Valid()
+}
+
+/// Revoked identities exist, are not already revoked and their revocations are signed by them,
+/// excluded identities are members
+pub fn verify_revocations_and_exclusions(datas: &BlockRuleDatas) -> Result<(), BlockRuleError> {
+    let block = datas.block;
+    let identities = &datas.identities;
+    for revocation in &block.revoked {
+        let compact_revocation = revocation.to_compact_document();
+        let pubkey = compact_revocation.issuer;
+        if let Some(idty) = identities.get(&pubkey) {
+            if is_revoked(idty) {
+                return Err(InvalidBlockError::AlreadyRevoked(pubkey).into());
+            }
+            if !revocation_signature_is_valid(&block.currency.0, idty, compact_revocation.signature)
+            {
+                return Err(InvalidBlockError::InvalidRevocationSignature(pubkey).into());
+            }
+        } else {
+            return Err(InvalidBlockError::RevokedUnknownIdentity(pubkey).into());
+        }
+    }
+    for pubkey in &block.excluded {
+        if !is_member(identities, pubkey) {
+            return Err(InvalidBlockError::ExcludedNotMember(*pubkey).into());
+        }
+    }
+    Ok(())
+}
+
+/// Check the exclusions of a block: the members whose membership has expired (and who do not
+/// renew it) and the members losing certifications are excluded, the other excluded members
+/// are revoked in the block
+fn check_exclusions(
+    excluded: &[PubKey],
+    expired_members: &[PubKey],
+    renewers: &HashSet<PubKey>,
+    members_losing_certs: &[PubKey],
+    revoked: &HashSet<PubKey>,
+) -> Result<(), InvalidBlockError> {
+    let excluded: HashSet<PubKey> = excluded.iter().cloned().collect();
+    for pubkey in expired_members {
+        if !excluded.contains(pubkey) && !renewers.contains(pubkey) {
+            return Err(InvalidBlockError::ExpiredMemberNotExcluded(*pubkey));
+        }
+    }
+    for pubkey in members_losing_certs {
+        if !excluded.contains(pubkey) {
+            return Err(InvalidBlockError::MemberLosingCertsNotExcluded(*pubkey));
+        }
+    }
+    for pubkey in &excluded {
+        if !expired_members.contains(pubkey)
+            && !members_losing_certs.contains(pubkey)
+            && !revoked.contains(pubkey)
+        {
+            return Err(InvalidBlockError::UnexpectedExclusion(*pubkey));
+        }
+    }
+    Ok(())
+}
+
+/// Members whose membership expires at the block median time are excluded, unless they renew
+/// their membership in the block, members receiving less than sig_qty certifications once the
+/// expired certifications are removed are excluded, and no other member is excluded
+/// (except the members revoked in the block)
+pub fn verify_memberships_expiry(datas: &BlockRuleDatas) -> Result<(), BlockRuleError> {
+    let block = datas.block;
+    let expired_members = readers::identity::get_expired_members(
+        &datas.wot_dbs.idties_indexes_db,
+        datas.blockchain_db,
+        datas.currency_params.ms_validity,
+        block.median_time,
+    )?;
+    let blocks_expiring = readers::certs::get_blocks_expiring_certs(
+        datas.blockchain_db,
+        &datas.wot_dbs.certs_db,
+        datas.currency_params.sig_validity,
+        block.median_time,
+    )?;
+    let expire_certs = readers::certs::find_expire_certs(&datas.wot_dbs.certs_db, blocks_expiring)?;
+    let new_certs: Vec<(Option<NodeId>, NodeId)> = block
+        .certifications
+        .iter()
+        .filter_map(|cert| {
+            let compact_cert = cert.to_compact_document();
+            datas
+                .wot_index
+                .get(&compact_cert.target)
+                .map(|target| (datas.wot_index.get(&compact_cert.issuer).cloned(), *target))
+        })
+        .collect();
+    let members_losing_certs = readers::certs::get_members_losing_certs(
+        datas.wot_dbs,
+        &expire_certs,
+        &new_certs,
+        datas.currency_params.sig_qty,
+    )?;
+    let renewers: HashSet<PubKey> = block
+        .joiners
+        .iter()
+        .chain(block.actives.iter())
+        .map(|ms| ms.issuers()[0])
+        .collect();
+    let revoked: HashSet<PubKey> = block
+        .revoked
+        .iter()
+        .map(|revocation| revocation.to_compact_document().issuer)
+        .collect();
+    check_exclusions(
+        &block.excluded,
+        &expired_members,
+        &renewers,
+        &members_losing_certs,
+        &revoked,
+    )?;
+    Ok(())
+}
+
+/// Certifications: issuer and target membership, sig_window, sig_period, sig_renew_period and sig_stock
+pub fn verify_certifications(datas: &BlockRuleDatas) -> Result<(), BlockRuleError> {
+    let BlockRuleDatas {
+        block,
+        ref currency_params,
+        wot_dbs,
+        wot_index,
+        ref identities,
+        ref joiners,
+        ..
+    } = *datas;
+    let previous_median_time = datas.previous_median_time();
+    let mut new_certs_count: HashMap<PubKey, usize> = HashMap::new();
+    let mut renewals = Vec::new();
+    for cert in &block.certifications {
+        let compact_cert = cert.to_compact_document();
+        let (issuer, target) = (compact_cert.issuer, compact_cert.target);
+        if !is_member(identities, &issuer) && !joiners.contains(&issuer) {
+            return Err(InvalidBlockError::CertIssuerNotMember(issuer).into());
+        }
+        if !is_member(identities, &target) && !joiners.contains(&target) {
+            return Err(InvalidBlockError::CertTargetNotMember(target).into());
+        }
+        if !is_in_window(
+            datas,
+            compact_cert.block_number,
+            None,
+            currency_params.sig_window,
+        )? {
+            return Err(InvalidBlockError::CertOutOfWindow(issuer, target).into());
+        }
+        if let (Some(previous_median_time), Some(chainable_on)) = (
+            previous_median_time,
+            identities
+                .get(&issuer)
+                .and_then(|idty| idty.cert_chainable_on.last()),
+        ) {
+            if *chainable_on > previous_median_time {
+                return Err(InvalidBlockError::CertNotChainable(issuer).into());
+            }
+        }
+        // Renewal of an existing certification
+        if let (Some(source_id), Some(target_id)) = (wot_index.get(&issuer), wot_index.get(&target))
+        {
+            let has_link = wot_dbs
+                .wot_db
+                .read(|db| db.has_link(*source_id, *target_id))?;
+            if let HasLinkResult::Link(true) = has_link {
+                renewals.push((issuer, target, (*source_id, *target_id)));
+                continue;
+            }
+        }
+        *new_certs_count.entry(issuer).or_insert(0) += 1;
+    }
+    for (issuer, target, (source_id, target_id)) in renewals {
+        if let Some(created_block_id) =
+            readers::certs::get_cert_created_block(&wot_dbs.certs_links_db, source_id, target_id)?
+        {
+            let renewable_on = datas
+                .blockchain_db
+                .get(&created_block_id)?
+                .map(|b| b.block.median_time)
+                .unwrap_or(0)
+                + currency_params.sig_renew_period;
+            if renewable_on > block.median_time {
+                return Err(InvalidBlockError::CertReplay(issuer, target).into());
+            }
+        }
+    }
+    for (issuer, new_certs_count) in new_certs_count {
+        let issued_count = if let Some(issuer_id) = wot_index.get(&issuer) {
+            wot_dbs
+                .wot_db
+                .read(|db| db.issued_count(*issuer_id))?
+                .unwrap_or(0)
+        } else {
+            0
+        };
+        if issued_count + new_certs_count > currency_params.sig_stock {
+            return Err(InvalidBlockError::CertStockExhausted(issuer).into());
+        }
+    }
+    Ok(())
+}
+
+/// Compute the minimum number of links issued and received to be a sentry
+pub fn sentry_requirement(members_count: usize, step_max: usize) -> u32 {
+    let mut requirement: u32 = 1;
+    while (u64::from(requirement)).pow(step_max as u32) < members_count as u64 {
+        requirement += 1;
+    }
+    requirement
+}
+
+/// Find the first candidate (joiner or active) that does not respect the distance rule
+///
+/// The distances are computed in parallel.
+fn find_outdistanced<W: WebOfTrust + Sync>(
+    wot: &W,
+    candidates: &[(PubKey, NodeId, bool)],
+    sentry_requirement: u32,
+    step_max: u32,
+    x_percent: f64,
+) -> Option<InvalidBlockError> {
+    candidates
+        .par_iter()
+        .map(|&(pubkey, wot_id, is_joiner)| {
+            let outdistanced = DISTANCE_CALCULATOR
+                .is_outdistanced(
+                    wot,
+                    WotDistanceParameters {
+                        node: wot_id,
+                        sentry_requirement,
+                        step_max,
+                        x_percent,
+                    },
+                )
+                .unwrap_or(true);
+            if !outdistanced {
+                None
+            } else if is_joiner {
+                Some(InvalidBlockError::Outdistanced(pubkey))
+            } else {
+                Some(InvalidBlockError::ActiveOutdistanced(pubkey))
+            }
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .flatten()
+        .next()
+}
+
+/// Joiners receive at least sig_qty certifications and respect the distance rule, as the actives,
+/// in the web of trust `wot` (`newcomers_ids` completes the wot index with the new identities)
+fn verify_candidates<W: WebOfTrust + Sync>(
+    datas: &BlockRuleDatas,
+    wot: &W,
+    newcomers_ids: &HashMap<PubKey, NodeId>,
+) -> Result<(), BlockRuleError> {
+    let BlockRuleDatas {
+        block,
+        ref currency_params,
+        wot_index,
+        ..
+    } = *datas;
+    let get_wot_id = |pubkey: &PubKey| {
+        wot_index
+            .get(pubkey)
+            .or_else(|| newcomers_ids.get(pubkey))
+            .cloned()
+    };
+    let mut candidates = Vec::with_capacity(block.joiners.len() + block.actives.len());
+    for joiner in &block.joiners {
+        let pubkey = joiner.issuers()[0];
+        // Joiners without identity are rejected by the memberships rule
+        if let Some(wot_id) = get_wot_id(&pubkey) {
+            let received_certs_count = wot.get_links_source(wot_id).map_or(0, |l| l.len());
+            if received_certs_count < currency_params.sig_qty {
+                return Err(InvalidBlockError::NotEnoughCertifications(pubkey).into());
+            }
+            candidates.push((pubkey, wot_id, true));
+        }
+    }
+    for active in &block.actives {
+        let pubkey = active.issuers()[0];
+        // Actives that are not members are rejected by the memberships rule
+        if let Some(wot_id) = get_wot_id(&pubkey) {
+            candidates.push((pubkey, wot_id, false));
+        }
+    }
+    if let Some(err) = find_outdistanced(
+        wot,
+        &candidates,
+        sentry_requirement(block.members_count, currency_params.step_max),
+        currency_params.step_max as u32,
+        currency_params.x_percent,
+    ) {
+        return Err(err.into());
+    }
+    Ok(())
+}
+
+/// Joiners receive at least sig_qty certifications, joiners and actives respect the distance rule
+///
+/// The rules are evaluated on the web of trust as it will be after applying the block
+/// (the web of trust is copied only if the block modifies it).
+pub fn verify_joiners_certs_and_distance(datas: &BlockRuleDatas) -> Result<(), BlockRuleError> {
+    let BlockRuleDatas {
+        block,
+        wot_dbs,
+        wot_index,
+        ref newcomers,
+        ..
+    } = *datas;
+    if block.joiners.is_empty() && block.actives.is_empty() {
+        return Ok(());
+    }
+    if block.joiners.is_empty() && block.excluded.is_empty() && block.certifications.is_empty() {
+        return wot_dbs
+            .wot_db
+            .read(|wot| verify_candidates(datas, wot, &HashMap::new()))?;
+    }
+    let mut wot = wot_dbs.wot_db.read(Clone::clone)?;
+    let mut newcomers_ids = HashMap::with_capacity(newcomers.len());
+    for joiner in &block.joiners {
+        let pubkey = joiner.issuers()[0];
+        if newcomers.contains(&pubkey) {
+            newcomers_ids.insert(pubkey, wot.add_node());
+        } else if let Some(wot_id) = wot_index.get(&pubkey) {
+            wot.set_enabled(*wot_id, true);
+        }
+    }
+    for pubkey in &block.excluded {
+        if let Some(wot_id) = wot_index.get(pubkey) {
+            wot.set_enabled(*wot_id, false);
+        }
+    }
+    let get_wot_id = |pubkey: &PubKey| wot_index.get(pubkey).or_else(|| newcomers_ids.get(pubkey));
+    for cert in &block.certifications {
+        let compact_cert = cert.to_compact_document();
+        if let (Some(source), Some(target)) = (
+            get_wot_id(&compact_cert.issuer),
+            get_wot_id(&compact_cert.target),
+        ) {
+            wot.add_link(*source, *target);
+        }
+    }
+    verify_candidates(datas, &wot, &newcomers_ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dubp_documents::documents::identity::IdentityDocumentBuilder;
+    use dup_crypto::keys::{ed25519, KeyPair, PrivKey};
+
+    #[test]
+    fn test_sentry_requirement() {
+        assert_eq!(2, sentry_requirement(32, 5));
+        assert_eq!(3, sentry_requirement(33, 5));
+        assert_eq!(5, sentry_requirement(3_125, 5));
+        assert_eq!(6, sentry_requirement(3_126, 5));
+        assert_eq!(1, sentry_requirement(0, 5));
+    }
+
+    #[test]
+    fn test_find_outdistanced() {
+        // Three sentries certifying each other
+        let mut wot = WotDB::new(100);
+        let sentries: Vec<NodeId> = (0..3).map(|_| wot.add_node()).collect();
+        for i in 0..3 {
+            wot.add_link(sentries[i], sentries[(i + 1) % 3]);
+        }
+        let joiner = dup_crypto_tests_tools::mocks::pubkey('D');
+        let active = dup_crypto_tests_tools::mocks::pubkey('A');
+        let joiner_id = wot.add_node();
+        wot.add_link(sentries[0], joiner_id);
+
+        // The joiner reaches only one of the sentries in one step
+        let candidates = vec![(active, sentries[0], false), (joiner, joiner_id, true)];
+        assert_eq!(
+            Some(InvalidBlockError::Outdistanced(joiner)),
+            find_outdistanced(&wot, &candidates, 1, 1, 0.5)
+        );
+        assert_eq!(None, find_outdistanced(&wot, &candidates, 1, 2, 0.5));
+
+        // Certified by all the sentries
+        wot.add_link(sentries[1], joiner_id);
+        wot.add_link(sentries[2], joiner_id);
+        assert_eq!(None, find_outdistanced(&wot, &candidates, 1, 1, 0.5));
+
+        // A member whose certifications have expired
+        let active_id = wot.add_node();
+        wot.add_link(active_id, sentries[0]);
+        assert_eq!(
+            Some(InvalidBlockError::ActiveOutdistanced(active)),
+            find_outdistanced(&wot, &[(active, active_id, false)], 1, 1, 0.5)
+        );
+    }
+
+    #[test]
+    fn test_check_exclusions() {
+        let (a, b, c, d) = (
+            dup_crypto_tests_tools::mocks::pubkey('A'),
+            dup_crypto_tests_tools::mocks::pubkey('B'),
+            dup_crypto_tests_tools::mocks::pubkey('C'),
+            dup_crypto_tests_tools::mocks::pubkey('D'),
+        );
+        // A membership has expired, B renews its expired membership, C loses certifications
+        // and D is revoked
+        let expired = vec![a, b];
+        let renewers: HashSet<PubKey> = vec![b].into_iter().collect();
+        let losing_certs = vec![c];
+        let revoked: HashSet<PubKey> = vec![d].into_iter().collect();
+        let check = |excluded: &[PubKey]| {
+            check_exclusions(excluded, &expired, &renewers, &losing_certs, &revoked)
+        };
+
+        assert_eq!(Ok(()), check(&[a, c]));
+        assert_eq!(Ok(()), check(&[a, b, c, d]));
+        assert_eq!(
+            Err(InvalidBlockError::ExpiredMemberNotExcluded(a)),
+            check(&[c])
+        );
+        assert_eq!(
+            Err(InvalidBlockError::MemberLosingCertsNotExcluded(c)),
+            check(&[a])
+        );
+        // E keeps its membership and its certifications
+        let e = dup_crypto_tests_tools::mocks::pubkey('E');
+        assert_eq!(
+            Err(InvalidBlockError::UnexpectedExclusion(e)),
+            check(&[a, c, e])
+        );
+    }
+
+    #[test]
+    fn test_revocation_signature_is_valid() {
+        let keypair = ed25519::KeyPairFromSeedGenerator::generate(&[1u8; 32]);
+        let other_keypair = ed25519::KeyPairFromSeedGenerator::generate(&[2u8; 32]);
+        let pubkey = PubKey::Ed25519(keypair.public_key());
+        let blockstamp = Blockstamp::default();
+        let idty_doc = IdentityDocumentBuilder {
+            currency: "g1",
+            username: "alice",
+            blockstamp: &blockstamp,
+            issuer: &pubkey,
+        }
+        .build_and_sign(vec![PrivKey::Ed25519(keypair.private_key())]);
+        let idty = DALIdentity {
+            hash: String::new(),
+            state: DALIdentityState::Member(vec![]),
+            joined_on: blockstamp,
+            expired_on: None,
+            revoked_on: None,
+            idty_doc: idty_doc.clone(),
+            wot_id: NodeId(0),
+            ms_created_block_id: BlockNumber(0),
+            ms_chainable_on: vec![],
+            cert_chainable_on: vec![],
+        };
+        let revocation_builder = RevocationDocumentBuilder {
+            currency: "g1",
+            issuer: &pubkey,
+            identity_username: "alice",
+            identity_blockstamp: &blockstamp,
+            identity_sig: &idty_doc.signatures()[0],
+        };
+        let sign = |keypair: ed25519::KeyPair| {
+            revocation_builder
+                .build_and_sign(vec![PrivKey::Ed25519(keypair.private_key())])
+                .signatures()[0]
+        };
+        assert!(revocation_signature_is_valid("g1", &idty, sign(keypair)));
+        assert!(!revocation_signature_is_valid(
+            "g1",
+            &idty,
+            sign(other_keypair)
+        ));
+        assert!(!revocation_signature_is_valid("g2", &idty, sign(keypair)));
+    }
+}
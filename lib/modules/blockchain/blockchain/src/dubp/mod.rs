@@ -25,6 +25,7 @@ use dubp_documents::Blockstamp;
 use dubp_documents::Document;
 use durs_blockchain_dal::entities::block::DALBlock;
 use durs_blockchain_dal::*;
+use rules_engine::rule::RuleNumber;
 
 #[derive(Debug, Clone)]
 pub enum CheckAndApplyBlockReturn {
@@ -34,11 +35,19 @@ pub enum CheckAndApplyBlockReturn {
 }
 
 #[derive(Debug, Copy, Clone)]
+/// Reason why a block can't be stacked
 pub enum BlockError {
+    /// The block is already known or is out of the fork window
     AlreadyHaveBlockOrOutForkWindow,
+    /// Wrong inner hash, hash or signature
     VerifyBlockHashsError(VerifyBlockHashsError),
+    /// Fail to read or write databases
     DALError(DALError),
+    /// The block does not comply with the protocol
     InvalidBlock(InvalidBlockError),
+    /// The block does not comply with a protocol rule
+    InvalidBlockRule(RuleNumber, InvalidBlockError),
+    /// Fail to apply the block
     ApplyValidBlockError(ApplyValidBlockError),
 }
 
@@ -85,31 +94,63 @@ pub fn check_and_apply_block(
             block_doc.blockstamp()
         );
         // Detect expire_certs
-        let blocks_expiring = Vec::with_capacity(0);
-        let expire_certs = durs_blockchain_dal::readers::certs::find_expire_certs(
+        let blocks_expiring = readers::certs::get_blocks_expiring_certs(
+            &bc.blocks_databases.blockchain_db,
             &bc.wot_databases.certs_db,
-            blocks_expiring,
+            bc.currency_params.sig_validity,
+            block_doc.median_time,
+        )?;
+        let expire_certs =
+            readers::certs::find_expire_certs(&bc.wot_databases.certs_db, blocks_expiring)?;
+
+        // Detect implicit revocations
+        let implicit_revocations = readers::identity::get_implicitly_revoked(
+            &bc.wot_databases.idties_indexes_db,
+            &bc.blocks_databases.blockchain_db,
+            bc.currency_params.ms_validity,
+            block_doc.median_time,
         )?;
 
         // Verify block validity (check all protocol rule, very long !)
         verify_block_validity(
             &block_doc,
-            &bc.blocks_databases.blockchain_db,
-            &bc.wot_databases.certs_db,
+            &bc.currency_params,
+            &bc.blocks_databases,
+            &bc.wot_databases,
+            &bc.currency_databases,
             &bc.wot_index,
-            &bc.wot_databases.wot_db,
         )?;
 
+        // The genesis block defines the currency parameters
+        if block_doc.number.0 == 0 {
+            bc.currency_params = block_currency_params(&block_doc, &bc.currency_params)?;
+        }
+
+        // Universal dividend state after the block
+        let previous_ud_state = if block_doc.number.0 > 0 {
+            UdState::local_state(
+                &bc.blocks_databases.blockchain_db,
+                &bc.blocks_databases.ud_states_db,
+                BlockNumber(block_doc.number.0 - 1),
+            )?
+        } else {
+            None
+        };
+        let (ud_state, _) =
+            dividend::expected_ud_state(&block_doc, previous_ud_state, &bc.currency_params);
+
         Ok(CheckAndApplyBlockReturn::ValidBlock(apply_valid_block(
             block_doc,
             &mut bc.wot_index,
             &bc.wot_databases.wot_db,
             &expire_certs,
+            &implicit_revocations,
+            ud_state.to_dal(),
         )?))
     } else if !already_have_block
         && (block_doc.number.0 >= bc.current_blockstamp.id.0
             || (bc.current_blockstamp.id.0 - block_doc.number.0)
-                < *durs_blockchain_dal::constants::FORK_WINDOW_SIZE as u32)
+                < bc.forks_dbs.limits.fork_window_size as u32)
     {
         debug!(
             "stackable_block : block {} not chainable, store this for future !",
@@ -30,9 +30,7 @@ pub fn receive_bc_documents(bc: &mut BlockchainModule, network_documents: &[Bloc
 
 pub fn receive_blocks(bc: &mut BlockchainModule, blocks: Vec<BlockDocument>) {
     debug!("BlockchainModule : receive_blocks()");
-    let mut save_blocks_dbs = false;
-    let mut save_wots_dbs = false;
-    let mut save_currency_dbs = false;
+    let mut save_dbs = false;
     for block in blocks.into_iter() {
         let blockstamp = block.blockstamp();
         match check_and_apply_block(bc, block) {
@@ -46,7 +44,7 @@ pub fn receive_blocks(bc: &mut BlockchainModule, blocks: Vec<BlockDocument>) {
                     bc.current_blockstamp = new_current_block.blockstamp();
                     // Apply db requests
                     bc_db_query
-                        .apply(&bc.blocks_databases.blockchain_db, &bc.forks_dbs, None)
+                        .apply(&bc.blocks_databases, &bc.forks_dbs, None)
                         .expect("Fatal error : Fail to apply DBWriteRequest !");
                     for query in &wot_dbs_queries {
                         query
@@ -58,13 +56,7 @@ pub fn receive_blocks(bc: &mut BlockchainModule, blocks: Vec<BlockDocument>) {
                             .apply(&blockstamp, &bc.currency_databases)
                             .expect("Fatal error : Fail to apply CurrencyDBsWriteRequest !");
                     }
-                    save_blocks_dbs = true;
-                    if !wot_dbs_queries.is_empty() {
-                        save_wots_dbs = true;
-                    }
-                    if !tx_dbs_queries.is_empty() {
-                        save_currency_dbs = true;
-                    }
+                    save_dbs = true;
                     events::sent::send_event(
                         bc,
                         &BlockchainEvent::StackUpValidBlock(Box::new(new_current_block)),
@@ -72,6 +64,12 @@ pub fn receive_blocks(bc: &mut BlockchainModule, blocks: Vec<BlockDocument>) {
                 }
                 CheckAndApplyBlockReturn::ForkBlock => {
                     info!("new fork block({})", blockstamp);
+                    save_dbs = true;
+                    if let Ok(Some(fork_branch)) =
+                        fork_algo::get_fork_branch(&bc.forks_dbs, blockstamp)
+                    {
+                        events::sent::send_event(bc, &BlockchainEvent::NewFork(fork_branch));
+                    }
                     if let Ok(Some(new_bc_branch)) = fork_algo::fork_resolution_algo(
                         &bc.forks_dbs,
                         bc.current_blockstamp,
@@ -82,6 +80,7 @@ pub fn receive_blocks(bc: &mut BlockchainModule, blocks: Vec<BlockDocument>) {
                 }
                 CheckAndApplyBlockReturn::OrphanBlock => {
                     debug!("new orphan block({})", blockstamp);
+                    save_dbs = true;
                 }
             },
             Err(e) => match e {
@@ -89,6 +88,13 @@ pub fn receive_blocks(bc: &mut BlockchainModule, blocks: Vec<BlockDocument>) {
                     warn!("InvalidBlock({})", blockstamp.id.0);
                     crate::events::sent::send_event(bc, &BlockchainEvent::RefusedBlock(blockstamp));
                 }
+                BlockError::InvalidBlockRule(rule_number, e2) => {
+                    warn!(
+                        "InvalidBlock({}): break rule n°{}: {:?}",
+                        blockstamp.id.0, rule_number, e2
+                    );
+                    crate::events::sent::send_event(bc, &BlockchainEvent::RefusedBlock(blockstamp));
+                }
                 BlockError::ApplyValidBlockError(e2) => {
                     error!("ApplyValidBlockError({}): {:?}", blockstamp.id.0, e2);
                     crate::events::sent::send_event(bc, &BlockchainEvent::RefusedBlock(blockstamp));
@@ -104,14 +110,7 @@ pub fn receive_blocks(bc: &mut BlockchainModule, blocks: Vec<BlockDocument>) {
         }
     }
     // Save databases
-    if save_blocks_dbs {
-        bc.blocks_databases.save_dbs();
-        bc.forks_dbs.save_dbs();
-    }
-    if save_wots_dbs {
-        bc.wot_databases.save_dbs();
-    }
-    if save_currency_dbs {
-        bc.currency_databases.save_dbs(true, true);
+    if save_dbs {
+        bc.commit_dbs();
     }
 }
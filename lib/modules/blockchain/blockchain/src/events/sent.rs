@@ -24,6 +24,7 @@ use durs_module::ModuleEvent;
 pub fn send_event(bc: &BlockchainModule, event: &BlockchainEvent) {
     let module_event = match event {
         BlockchainEvent::StackUpValidBlock(_) => ModuleEvent::NewValidBlock,
+        BlockchainEvent::NewFork(_) => ModuleEvent::NewFork,
         BlockchainEvent::RevertBlocks(_) => ModuleEvent::RevertBlocks,
         _ => return,
     };
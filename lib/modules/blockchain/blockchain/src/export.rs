@@ -0,0 +1,140 @@
+//  Copyright (C) 2018  The Durs Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Export the local blockchain into Duniter json chunk files.
+
+use crate::constants::CHUNK_SIZE;
+use crate::*;
+use durs_module::DursConfTrait;
+use pbr::ProgressBar;
+use std::fs;
+use std::io::BufWriter;
+
+/// Export the blocks `start..=end` of the local blockchain (whole blockchain by default)
+/// into json chunk files readable by `durs sync -t ts`
+pub fn export<DC: DursConfTrait>(
+    profile_path: PathBuf,
+    conf: &DC,
+    output_path: &PathBuf,
+    start: Option<u32>,
+    end: Option<u32>,
+) {
+    // Get db path
+    let db_path = durs_conf::get_blockchain_db_path(profile_path, &conf.currency());
+
+    // Complete or discard an interrupted commit
+    recover_dbs_journal(&db_path);
+
+    // Open blocks databases
+    let blocks_dbs = BlocksV10DBs::open(Some(&db_path));
+
+    // Get local current blockstamp
+    let current_blockstamp =
+        match durs_blockchain_dal::readers::block::get_current_blockstamp(&blocks_dbs)
+            .expect("DALError : fail to get current blockstamp !")
+        {
+            Some(current_blockstamp) => current_blockstamp,
+            None => {
+                println!("The local blockchain is empty, nothing to export.");
+                return;
+            }
+        };
+
+    // Check range
+    let start = start.unwrap_or(0);
+    let end = end.unwrap_or(current_blockstamp.id.0);
+    if start > end || end > current_blockstamp.id.0 {
+        println!(
+            "Invalid range #{}-#{} : the local blockchain contains blocks #0 to #{}.",
+            start, end, current_blockstamp.id.0
+        );
+        return;
+    }
+
+    // Create output folder
+    if let Err(err) = fs::create_dir_all(output_path) {
+        println!("Fail to create folder {:?} : {}", output_path, err);
+        return;
+    }
+
+    let export_begin = SystemTime::now();
+    let first_chunk_number = start as usize / *CHUNK_SIZE;
+    let last_chunk_number = end as usize / *CHUNK_SIZE;
+    println!(
+        "Export blocks #{} to #{} in {:?} :",
+        start, end, output_path
+    );
+    let mut export_pb = ProgressBar::new((last_chunk_number + 1 - first_chunk_number) as u64);
+    export_pb.format("╢▌▌░╟");
+
+    for chunk_number in first_chunk_number..=last_chunk_number {
+        // Get chunk blocks
+        let chunk_start = std::cmp::max(start, (chunk_number * *CHUNK_SIZE) as u32);
+        let chunk_end = std::cmp::min(end, ((chunk_number + 1) * *CHUNK_SIZE - 1) as u32);
+        let blocks = blocks_dbs
+            .blockchain_db
+            .range(
+                Some(&BlockNumber(chunk_start)),
+                Some(&BlockNumber(chunk_end + 1)),
+            )
+            .expect("DALError : fail to read blockchain db !")
+            .into_iter()
+            .map(|(_, dal_block)| dal_block.block.to_duniter_json())
+            .collect::<Vec<_>>();
+        if blocks.len() != (chunk_end + 1 - chunk_start) as usize {
+            fatal_error!(
+                "Missing blocks between #{} and #{} in local blockchain, please reset data and resync !",
+                chunk_start,
+                chunk_end
+            );
+        }
+
+        // Write chunk file
+        let mut chunk_file_path = output_path.clone();
+        chunk_file_path.push(&format!(
+            "{}{}{}",
+            CHUNK_FILE_NAME_BEGIN, chunk_number, CHUNK_FILE_NAME_END
+        ));
+        let write_result = fs::File::create(&chunk_file_path).map(|file| {
+            serde_json::to_writer(
+                BufWriter::new(file),
+                &serde_json::json!({ "blocks": blocks }),
+            )
+        });
+        match write_result {
+            Ok(Ok(())) => export_pb.inc(),
+            Ok(Err(err)) => {
+                fatal_error!("Fail to write chunk file {:?} : {}", chunk_file_path, err)
+            }
+            Err(err) => fatal_error!("Fail to create chunk file {:?} : {}", chunk_file_path, err),
+        };
+    }
+
+    // Log export duration
+    let export_duration = SystemTime::now().duration_since(export_begin).unwrap();
+    println!();
+    println!(
+        "Export {} blocks in {}.{:03} seconds.",
+        end + 1 - start,
+        export_duration.as_secs(),
+        export_duration.subsec_millis(),
+    );
+    info!(
+        "Export {} blocks in {}.{:03} seconds.",
+        end + 1 - start,
+        export_duration.as_secs(),
+        export_duration.subsec_millis(),
+    );
+}
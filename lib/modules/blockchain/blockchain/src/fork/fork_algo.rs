@@ -16,6 +16,7 @@
 use dubp_documents::Blockstamp;
 use durs_blockchain_dal::entities::fork_tree::ForkTree;
 use durs_blockchain_dal::{DALError, ForksDBs};
+use durs_message::events::ForkBranch;
 use std::collections::HashSet;
 
 /// Number of advance blocks required
@@ -28,12 +29,12 @@ pub fn fork_resolution_algo(
     current_blockstamp: Blockstamp,
     invalid_blocks: &HashSet<Blockstamp>,
 ) -> Result<Option<Vec<Blockstamp>>, DALError> {
-    let current_bc_time = forks_dbs.fork_blocks_db.read(|db| {
-        db.get(&current_blockstamp)
-            .expect("safe unwrap")
-            .block
-            .median_time
-    })?;
+    let current_bc_time = forks_dbs
+        .fork_blocks_db
+        .get(&current_blockstamp)?
+        .expect("safe unwrap")
+        .block
+        .median_time;
 
     let mut sheets = forks_dbs.fork_tree_db.read(ForkTree::get_sheets)?;
 
@@ -50,15 +51,15 @@ pub fn fork_resolution_algo(
             }
 
             let branch_head_blockstamp = branch.last().expect("safe unwrap");
-            let branch_head_median_time = forks_dbs.fork_blocks_db.read(|db| {
-                db.get(&branch_head_blockstamp)
-                    .expect("safe unwrap")
-                    .block
-                    .median_time
-            })?;
+            let branch_head_median_time = forks_dbs
+                .fork_blocks_db
+                .get(&branch_head_blockstamp)?
+                .expect("safe unwrap")
+                .block
+                .median_time;
             if branch_head_blockstamp.id.0 >= current_blockstamp.id.0 + *ADVANCE_BLOCKS
                 && branch_head_median_time >= current_bc_time + *ADVANCE_TIME
-                && branch[0].id.0 + *durs_blockchain_dal::constants::FORK_WINDOW_SIZE as u32
+                && branch[0].id.0 + forks_dbs.limits.fork_window_size as u32
                     > current_blockstamp.id.0
             {
                 let mut valid_branch = true;
@@ -79,6 +80,32 @@ pub fn fork_resolution_algo(
     Ok(None)
 }
 
+/// Get the fork branch ending with the given block
+/// (None if the block is not in the fork tree)
+pub fn get_fork_branch(
+    forks_dbs: &ForksDBs,
+    blockstamp: Blockstamp,
+) -> Result<Option<ForkBranch>, DALError> {
+    let branch = forks_dbs.fork_tree_db.read(|fork_tree| {
+        if let Some(node_id) = fork_tree.find_node_with_blockstamp(&blockstamp) {
+            fork_tree.get_fork_branch(node_id)
+        } else {
+            vec![]
+        }
+    })?;
+
+    if let Some(first_blockstamp) = branch.first() {
+        if let Some(dal_block) = forks_dbs.fork_blocks_db.get(first_blockstamp)? {
+            return Ok(Some(ForkBranch {
+                fork_point: dal_block.block.previous_blockstamp(),
+                blockstamps: branch,
+            }));
+        }
+    }
+
+    Ok(None)
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -119,10 +146,7 @@ mod tests {
             fork_window_size,
             forks_dbs.fork_tree_db.read(|fork_tree| fork_tree.size())?
         );
-        assert_eq!(
-            fork_window_size,
-            forks_dbs.fork_blocks_db.read(|db| db.len())?
-        );
+        assert_eq!(fork_window_size, forks_dbs.fork_blocks_db.len()?);
 
         // Get current blockstamp
         let mut current_blockstamp = forks_dbs
@@ -166,6 +190,15 @@ mod tests {
             fork_resolution_algo(&forks_dbs, current_blockstamp, &invalid_blocks)?
         );
 
+        // Get the fork branch
+        assert_eq!(
+            Some(ForkBranch {
+                fork_point: fork_point.blockstamp(),
+                blockstamps: fork_blocks.iter().map(BlockDocument::blockstamp).collect(),
+            }),
+            get_fork_branch(&forks_dbs, fork_blocks[2].blockstamp())?
+        );
+
         // Add the determining fork block
         let determining_blockstamp = Blockstamp {
             id: BlockNumber(fork_point.number.0 + 4),
@@ -20,10 +20,12 @@ use dubp_documents::documents::transaction::{TxAmount, TxBase};
 use dubp_documents::Document;
 use dup_crypto::keys::*;
 use durs_blockchain_dal::entities::block::DALBlock;
+use durs_blockchain_dal::entities::identity::DALIdentityState;
 use durs_blockchain_dal::entities::sources::SourceAmount;
+use durs_blockchain_dal::storage::KvValueDB;
 use durs_blockchain_dal::writers::requests::*;
 use durs_blockchain_dal::writers::transaction::DALTxV10;
-use durs_blockchain_dal::{BinDB, DALError, TxV10Datas};
+use durs_blockchain_dal::{DALError, IdentitiesV10DB, TxV10DB};
 use durs_common_tools::fatal_error;
 use durs_wot::data::{NewLinkResult, RemLinkResult};
 use durs_wot::{NodeId, WebOfTrust};
@@ -54,8 +56,10 @@ impl From<DALError> for RevertValidBlockError {
 pub fn revert_block<W: WebOfTrust>(
     dal_block: &DALBlock,
     wot_index: &mut HashMap<PubKey, NodeId>,
-    wot_db: &BinDB<W>,
-    txs_db: &BinDB<TxV10Datas>,
+    wot_db: &KvValueDB<W>,
+    identities_db: &IdentitiesV10DB,
+    implicit_revocations: &[PubKey],
+    txs_db: &TxV10DB,
 ) -> Result<ValidBlockRevertReqs, RevertValidBlockError> {
     // Revert DALBlock
     let mut block = dal_block.block.clone();
@@ -70,7 +74,7 @@ pub fn revert_block<W: WebOfTrust>(
         .iter()
         .map(|tx_enum| match *tx_enum {
             TxDocOrTxHash::TxHash(ref tx_hash) => {
-                if let Ok(Some(tx)) = txs_db.read(|db| db.get(tx_hash).cloned()) {
+                if let Ok(Some(tx)) = txs_db.get(tx_hash) {
                     tx
                 } else {
                     fatal_error!("revert_block(): tx {} not found !", tx_hash);
@@ -165,6 +169,17 @@ pub fn revert_block<W: WebOfTrust>(
         ));
         trace!("stack_up_valid_block: apply cert...success.");
     }
+    // Revert implicit revocations
+    for pubkey in implicit_revocations {
+        if !wot_index.contains_key(pubkey) {
+            return Err(RevertValidBlockError::RevokeUnknowNodeId());
+        }
+        wot_dbs_requests.push(WotsDBsWriteQuery::RevertRevokeIdentity(
+            *pubkey,
+            block.blockstamp(),
+            false,
+        ));
+    }
     // Revert revocations
     for revocation in block.revoked.clone() {
         let compact_revoc = revocation.to_compact_document();
@@ -173,11 +188,24 @@ pub fn revert_block<W: WebOfTrust>(
         } else {
             return Err(RevertValidBlockError::RevokeUnknowNodeId());
         };
-        wot_db
-            .write(|db| {
-                db.set_enabled(*wot_id, false);
-            })
-            .expect("Fail to write in WotDB");
+        // Only a member revocation disabled the node in the wot
+        let revoked_member = identities_db
+            .get(&compact_revoc.issuer)
+            .expect("Fail to read IdentitiesDB")
+            .map_or(false, |idty| {
+                if let DALIdentityState::ExplicitRevoked(_) = idty.state {
+                    true
+                } else {
+                    false
+                }
+            });
+        if revoked_member {
+            wot_db
+                .write(|db| {
+                    db.set_enabled(*wot_id, true);
+                })
+                .expect("Fail to write in WotDB");
+        }
         wot_dbs_requests.push(WotsDBsWriteQuery::RevertRevokeIdentity(
             compact_revoc.issuer,
             block.blockstamp(),
@@ -193,7 +221,7 @@ pub fn revert_block<W: WebOfTrust>(
         };
         wot_db
             .write(|db| {
-                db.set_enabled(*wot_id, false);
+                db.set_enabled(*wot_id, true);
             })
             .expect("Fail to write in WotDB");
         wot_dbs_requests.push(WotsDBsWriteQuery::RevertExcludeIdentity(
@@ -16,6 +16,7 @@
 use crate::fork::revert_block::ValidBlockRevertReqs;
 use crate::*;
 use dubp_documents::Blockstamp;
+use durs_blockchain_dal::writers::requests::BlocksDBsWriteQuery;
 use durs_common_tools::fatal_error;
 
 pub fn apply_rollback(bc: &mut BlockchainModule, new_bc_branch: Vec<Blockstamp>) {
@@ -24,56 +25,19 @@ pub fn apply_rollback(bc: &mut BlockchainModule, new_bc_branch: Vec<Blockstamp>)
     }
 
     let old_current_blockstamp = bc.current_blockstamp;
-    let last_common_block_number = new_bc_branch[0].id.0;
+    // `None` if the new branch forks from the genesis block (the whole old branch is reverted)
+    let last_common_block_number = new_bc_branch[0].id.0.checked_sub(1);
 
     // Rollback (revert old branch)
-    while bc.current_blockstamp.id.0 > last_common_block_number {
-        if let Some(dal_block) = bc
-            .forks_dbs
-            .fork_blocks_db
-            .read(|db| db.get(&bc.current_blockstamp).cloned())
-            .unwrap_or_else(|_| {
-                fatal_error!("revert block {} fail !", bc.current_blockstamp);
-            })
-        {
-            let ValidBlockRevertReqs(bc_db_query, wot_dbs_queries, tx_dbs_queries) =
-                super::revert_block::revert_block(
-                    &dal_block,
-                    &mut bc.wot_index,
-                    &bc.wot_databases.wot_db,
-                    &bc.currency_databases.tx_db,
-                )
-                .unwrap_or_else(|_| {
-                    fatal_error!("revert block {} fail !", bc.current_blockstamp);
-                });
-            let blockstamp = dal_block.block.blockstamp();
-            // Apply db requests
-            bc_db_query
-                .apply(&bc.blocks_databases.blockchain_db, &bc.forks_dbs, None)
-                .expect("Fatal error : Fail to apply DBWriteRequest !");
-            for query in &wot_dbs_queries {
-                query
-                    .apply(&blockstamp, &bc.currency_params, &bc.wot_databases)
-                    .expect("Fatal error : Fail to apply WotsDBsWriteRequest !");
-            }
-            for query in &tx_dbs_queries {
-                query
-                    .apply(&blockstamp, &bc.currency_databases)
-                    .expect("Fatal error : Fail to apply CurrencyDBsWriteRequest !");
-            }
-        } else {
-            fatal_error!("apply_rollback(): Not found current block in forks blocks DB !");
-        }
-    }
+    let reverted_blocks = revert_old_branch(bc, last_common_block_number);
+
+    let fork_point = bc.current_blockstamp;
 
     // Apply new branch
+    let mut applied_blocks = Vec::with_capacity(new_bc_branch.len());
     let mut new_branch_is_valid = true;
     for blockstamp in &new_bc_branch {
-        if let Ok(Some(dal_block)) = bc
-            .forks_dbs
-            .fork_blocks_db
-            .read(|db| db.get(&blockstamp).cloned())
-        {
+        if let Ok(Some(dal_block)) = bc.forks_dbs.fork_blocks_db.get(blockstamp) {
             if let Ok(CheckAndApplyBlockReturn::ValidBlock(ValidBlockApplyReqs(
                 bc_db_query,
                 wot_dbs_queries,
@@ -81,10 +45,17 @@ pub fn apply_rollback(bc: &mut BlockchainModule, new_bc_branch: Vec<Blockstamp>)
             ))) = check_and_apply_block(bc, dal_block.block)
             {
                 bc.current_blockstamp = *blockstamp;
-                // Apply db requests
-                bc_db_query
-                    .apply(&bc.blocks_databases.blockchain_db, &bc.forks_dbs, None)
+                applied_blocks.push(bc_db_query.get_block_doc_copy());
+                // Apply db requests (the block is already in the fork tree)
+                if let BlocksDBsWriteQuery::WriteBlock(dal_block, ud_state) = bc_db_query {
+                    durs_blockchain_dal::writers::block::stack_up_fork_block(
+                        &bc.blocks_databases,
+                        &bc.forks_dbs,
+                        dal_block,
+                        ud_state,
+                    )
                     .expect("Fatal error : Fail to apply DBWriteRequest !");
+                }
                 for query in &wot_dbs_queries {
                     query
                         .apply(&blockstamp, &bc.currency_params, &bc.wot_databases)
@@ -119,31 +90,291 @@ pub fn apply_rollback(bc: &mut BlockchainModule, new_bc_branch: Vec<Blockstamp>)
         }
 
         // save dbs
-        bc.blocks_databases.save_dbs();
-        bc.forks_dbs.save_dbs();
-        bc.wot_databases.save_dbs();
-        bc.currency_databases.save_dbs(true, true);
-        // Send events stackUpValidBlock
-        let new_branch_blocks: Vec<BlockDocument> = new_bc_branch
-            .into_iter()
-            .map(|blockstamp| {
-                bc.forks_dbs
-                    .fork_blocks_db
-                    .read(|db| db.get(&blockstamp).cloned())
-                    .expect("safe unwrap")
-                    .expect("safe unwrap")
-                    .block
-            })
-            .collect();
-        for block in new_branch_blocks {
+        bc.commit_dbs();
+
+        let reorg = record_reorg(
+            &mut bc.forks_stats,
+            fork_point,
+            new_bc_branch,
+            reverted_blocks,
+            applied_blocks.clone(),
+        );
+        info!(
+            "Blockchain reorganization: {} -> {} (fork point {}, depth {})",
+            old_current_blockstamp,
+            bc.current_blockstamp,
+            fork_point,
+            reorg.depth()
+        );
+
+        // Send events revertBlocks and stackUpValidBlock
+        events::sent::send_event(bc, &BlockchainEvent::RevertBlocks(Box::new(reorg)));
+        for block in applied_blocks {
             events::sent::send_event(bc, &BlockchainEvent::StackUpValidBlock(Box::new(block)))
         }
     } else {
-        // reload dbs
-        let dbs_path = durs_conf::get_blockchain_db_path(bc.profile_path.clone(), &bc.currency);
-        bc.blocks_databases = BlocksV10DBs::open(Some(&dbs_path));
-        bc.forks_dbs = ForksDBs::open(Some(&dbs_path));
-        bc.wot_databases = WotsV10DBs::open(Some(&dbs_path));
-        bc.currency_databases = CurrencyV10DBs::open(Some(&dbs_path));
+        // discard the modifications not committed
+        let discard_result = bc
+            .blocks_databases
+            .discard()
+            .and_then(|_| bc.forks_dbs.discard())
+            .and_then(|_| bc.wot_databases.discard())
+            .and_then(|_| bc.currency_databases.discard());
+        if let Err(err) = discard_result {
+            fatal_error!("Fail to discard databases modifications: {:?}", err);
+        }
+        // restore current blockstamp and wot index
+        bc.current_blockstamp = old_current_blockstamp;
+        bc.wot_index = readers::identity::get_wot_index(&bc.wot_databases.identities_db)
+            .expect("Fatal error : get_wot_index : Fail to read blockchain databases");
+    }
+}
+
+/// Revert the blocks of the local blockchain following the block `last_common_block_number`
+/// (all of them if it is `None`), from the newest to the oldest
+fn revert_old_branch(
+    bc: &mut BlockchainModule,
+    last_common_block_number: Option<u32>,
+) -> Vec<BlockDocument> {
+    let mut reverted_blocks: Vec<BlockDocument> = Vec::new();
+    while match last_common_block_number {
+        Some(last_common_block_number) => bc.current_blockstamp.id.0 > last_common_block_number,
+        None => reverted_blocks
+            .last()
+            .map_or(true, |block| block.number.0 > 0),
+    } {
+        if let Some(dal_block) = bc
+            .forks_dbs
+            .fork_blocks_db
+            .get(&bc.current_blockstamp)
+            .unwrap_or_else(|_| {
+                fatal_error!("revert block {} fail !", bc.current_blockstamp);
+            })
+        {
+            let implicit_revocations =
+                durs_blockchain_dal::readers::identity::get_implicitly_revoked_on(
+                    &bc.wot_databases,
+                    bc.current_blockstamp,
+                )
+                .unwrap_or_else(|_| {
+                    fatal_error!("revert block {} fail !", bc.current_blockstamp);
+                });
+            let ValidBlockRevertReqs(bc_db_query, wot_dbs_queries, tx_dbs_queries) =
+                super::revert_block::revert_block(
+                    &dal_block,
+                    &mut bc.wot_index,
+                    &bc.wot_databases.wot_db,
+                    &bc.wot_databases.identities_db,
+                    &implicit_revocations,
+                    &bc.currency_databases.tx_db,
+                )
+                .unwrap_or_else(|_| {
+                    fatal_error!("revert block {} fail !", bc.current_blockstamp);
+                });
+            let blockstamp = dal_block.block.blockstamp();
+            // Apply db requests
+            bc_db_query
+                .apply(&bc.blocks_databases, &bc.forks_dbs, None)
+                .expect("Fatal error : Fail to apply DBWriteRequest !");
+            for query in &wot_dbs_queries {
+                query
+                    .apply(&blockstamp, &bc.currency_params, &bc.wot_databases)
+                    .expect("Fatal error : Fail to apply WotsDBsWriteRequest !");
+            }
+            for query in &tx_dbs_queries {
+                query
+                    .apply(&blockstamp, &bc.currency_databases)
+                    .expect("Fatal error : Fail to apply CurrencyDBsWriteRequest !");
+            }
+            bc.current_blockstamp = if dal_block.block.number.0 > 0 {
+                dal_block.block.previous_blockstamp()
+            } else {
+                Blockstamp::default()
+            };
+            reverted_blocks.push(dal_block.block);
+        } else {
+            fatal_error!("apply_rollback(): Not found current block in forks blocks DB !");
+        }
+    }
+    reverted_blocks
+}
+
+/// Describe the reorganization and take it into account in the forks statistics
+fn record_reorg(
+    forks_stats: &mut ForksStats,
+    fork_point: Blockstamp,
+    new_branch: Vec<Blockstamp>,
+    reverted_blocks: Vec<BlockDocument>,
+    applied_blocks: Vec<BlockDocument>,
+) -> BlockchainReorg {
+    let reorg = BlockchainReorg {
+        fork_point,
+        old_branch: reverted_blocks
+            .iter()
+            .rev()
+            .map(BlockDocument::blockstamp)
+            .collect(),
+        new_branch,
+        reverted_blocks,
+        applied_blocks,
+    };
+    forks_stats.add_reorg(reorg.depth());
+    reorg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dubp_documents::BlockHash;
+    use durs_blockchain_dal::entities::block::DALBlock;
+
+    fn gen_block(number: u32, hash_byte: u8, previous_hash_byte: u8) -> BlockDocument {
+        dubp_documents_tests_tools::mocks::gen_empty_timed_block(
+            Blockstamp {
+                id: BlockNumber(number),
+                hash: BlockHash(dup_crypto_tests_tools::mocks::hash_from_byte(hash_byte)),
+            },
+            u64::from(number),
+            dup_crypto_tests_tools::mocks::hash_from_byte(previous_hash_byte),
+        )
+    }
+
+    /// Blockchain module (with in-memory databases) whose local blockchain is `blocks`
+    fn gen_bc(blocks: &[BlockDocument]) -> BlockchainModule {
+        let (router_sender, _) = mpsc::channel();
+        let mut bc = BlockchainModule {
+            router_sender,
+            profile_path: PathBuf::new(),
+            currency: CurrencyName::default(),
+            dbs_path: PathBuf::new(),
+            blocks_databases: BlocksV10DBs::open(None),
+            forks_dbs: ForksDBs::open(None),
+            wot_index: HashMap::new(),
+            wot_databases: WotsV10DBs::open(None),
+            currency_databases: CurrencyV10DBs::open(None),
+            currency_params: CurrencyParameters::default(),
+            current_blockstamp: Blockstamp::default(),
+            consensus: Blockstamp::default(),
+            pending_block: None,
+            invalid_forks: HashSet::new(),
+            pending_network_requests: HashMap::new(),
+            forks_stats: ForksStats::default(),
+        };
+        for block in blocks {
+            durs_blockchain_dal::writers::block::insert_new_head_block(
+                &bc.blocks_databases.blockchain_db,
+                &bc.forks_dbs,
+                DALBlock {
+                    block: block.clone(),
+                    expire_certs: Some(HashMap::new()),
+                },
+            )
+            .expect("Fail to write blockchain db");
+            bc.current_blockstamp = block.blockstamp();
+        }
+        bc
+    }
+
+    #[test]
+    fn test_revert_old_branch() {
+        let mut blocks = [
+            gen_block(0, 10, 0),
+            gen_block(1, 11, 10),
+            gen_block(2, 12, 11),
+        ];
+        let issuer = dup_crypto_tests_tools::mocks::pubkey('A');
+        for block in &mut blocks {
+            block.issuers = vec![issuer];
+            if block.number.0 > 0 {
+                block.previous_issuer = Some(issuer);
+            }
+        }
+
+        // The new branch forks from the block 0
+        let mut bc = gen_bc(&blocks);
+        let reverted_blocks = revert_old_branch(&mut bc, Some(0));
+        assert_eq!(vec![blocks[2].clone(), blocks[1].clone()], reverted_blocks);
+        assert_eq!(blocks[0].blockstamp(), bc.current_blockstamp);
+        assert_eq!(Ok(1), bc.blocks_databases.blockchain_db.len(),);
+
+        // The new branch starts at the block 0: the whole old branch is reverted
+        let mut bc = gen_bc(&blocks);
+        let reverted_blocks = revert_old_branch(&mut bc, None);
+        assert_eq!(
+            blocks.iter().rev().cloned().collect::<Vec<BlockDocument>>(),
+            reverted_blocks
+        );
+        assert_eq!(Blockstamp::default(), bc.current_blockstamp);
+        assert_eq!(Ok(true), bc.blocks_databases.blockchain_db.is_empty());
+    }
+
+    #[test]
+    fn test_record_reorg() {
+        // Main branch 0-1-2-3, fork branch 1-2'-3'
+        let fork_point = gen_block(1, 1, 0).blockstamp();
+        let old_branch = [gen_block(2, 2, 1), gen_block(3, 3, 2)];
+        let new_branch = [gen_block(2, 12, 1), gen_block(3, 13, 12)];
+        let new_branch_blockstamps: Vec<Blockstamp> =
+            new_branch.iter().map(BlockDocument::blockstamp).collect();
+
+        let mut forks_stats = ForksStats::default();
+        let reorg = record_reorg(
+            &mut forks_stats,
+            fork_point,
+            new_branch_blockstamps.clone(),
+            old_branch.iter().rev().cloned().collect(),
+            new_branch.to_vec(),
+        );
+
+        assert_eq!(fork_point, reorg.fork_point);
+        assert_eq!(
+            vec![old_branch[0].blockstamp(), old_branch[1].blockstamp()],
+            reorg.old_branch
+        );
+        assert_eq!(new_branch_blockstamps, reorg.new_branch);
+        assert_eq!(
+            vec![old_branch[1].blockstamp(), old_branch[0].blockstamp()],
+            reorg
+                .reverted_blocks
+                .iter()
+                .map(BlockDocument::blockstamp)
+                .collect::<Vec<Blockstamp>>()
+        );
+        assert_eq!(
+            new_branch_blockstamps,
+            reorg
+                .applied_blocks
+                .iter()
+                .map(BlockDocument::blockstamp)
+                .collect::<Vec<Blockstamp>>()
+        );
+        assert_eq!(2, reorg.depth());
+        assert_eq!(
+            ForksStats {
+                reorgs_count: 1,
+                reverted_blocks_count: 2,
+                last_reorg_depth: 2,
+                max_reorg_depth: 2,
+            },
+            forks_stats
+        );
+
+        // A shallower reorganization does not change the maximum depth
+        record_reorg(
+            &mut forks_stats,
+            new_branch_blockstamps[0],
+            vec![old_branch[1].blockstamp()],
+            vec![new_branch[1].clone()],
+            vec![old_branch[1].clone()],
+        );
+        assert_eq!(
+            ForksStats {
+                reorgs_count: 2,
+                reverted_blocks_count: 3,
+                last_reorg_depth: 1,
+                max_reorg_depth: 2,
+            },
+            forks_stats
+        );
     }
 }
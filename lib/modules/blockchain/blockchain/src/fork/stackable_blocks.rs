@@ -43,7 +43,7 @@ pub fn apply_stackable_blocks(bc: &mut BlockchainModule) {
                     let blockstamp = new_current_block.blockstamp();
                     // Apply db requests
                     bc_db_query
-                        .apply(&bc.blocks_databases.blockchain_db, &bc.forks_dbs, None)
+                        .apply(&bc.blocks_databases, &bc.forks_dbs, None)
                         .expect("Fatal error : Fail to apply DBWriteRequest !");
                     for query in &wot_dbs_queries {
                         query
@@ -68,10 +68,7 @@ pub fn apply_stackable_blocks(bc: &mut BlockchainModule) {
                 }
             }
             // Save databases
-            bc.blocks_databases.save_dbs();
-            bc.forks_dbs.save_dbs();
-            bc.wot_databases.save_dbs();
-            bc.currency_databases.save_dbs(true, true);
+            bc.commit_dbs();
             break 'blockchain;
         }
     }
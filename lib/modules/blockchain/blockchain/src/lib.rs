@@ -38,6 +38,7 @@ mod dbex;
 mod dubp;
 mod dunp;
 mod events;
+mod export;
 mod fork;
 mod requests;
 mod responses;
@@ -50,14 +51,28 @@ use std::sync::mpsc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use crate::constants::*;
-pub use crate::dbex::{DBExQuery, DBExTxQuery, DBExWotQuery};
+pub use crate::dbex::{DBExBlockQuery, DBExQuery, DBExTxQuery, DBExWotQuery};
 use crate::dubp::apply::ValidBlockApplyReqs;
+pub use crate::dubp::check::dividend::UdState;
+pub use crate::dubp::check::header::{
+    expected_issuers_frame, expected_median_time, expected_members_count, expected_pow_min,
+    hash_reaches_difficulty, issuer_difficulty, max_time_acceleration,
+};
+pub use crate::dubp::check::tx::{
+    verify_transaction, InvalidTxError, TxCheckDBs, TxCheckError, TxsOverlay,
+};
+pub use crate::dubp::check::{verify_block_template_validity, InvalidBlockError};
+pub use crate::dubp::BlockError;
 use crate::dubp::*;
 use crate::fork::*;
 use dubp_documents::documents::block::BlockDocument;
 use dubp_documents::*;
 use dup_crypto::keys::*;
 use durs_blockchain_dal::entities::currency_params::CurrencyParameters;
+pub use durs_blockchain_dal::entities::identity::DALIdentityStateKind;
+pub use durs_blockchain_dal::filters::identities::{IdentitiesFilter, UidFilter};
+pub use durs_blockchain_dal::filters::PagingFilter;
+use durs_blockchain_dal::journal::JournalRecovery;
 use durs_blockchain_dal::*;
 use durs_common_tools::fatal_error;
 use durs_message::events::*;
@@ -93,6 +108,8 @@ pub struct BlockchainModule {
     pub profile_path: PathBuf,
     /// Currency
     pub currency: CurrencyName,
+    /// Path to the databases folder
+    pub dbs_path: PathBuf,
     /// Blocks Databases
     pub blocks_databases: BlocksV10DBs,
     /// Forks Databases
@@ -115,6 +132,8 @@ pub struct BlockchainModule {
     pub invalid_forks: HashSet<Blockstamp>,
     /// pending network requests
     pub pending_network_requests: HashMap<ModuleReqId, OldNetworkRequest>,
+    /// Statistics on the reorganizations of the local blockchain
+    pub forks_stats: ForksStats,
 }
 
 #[derive(Debug, Clone)]
@@ -178,6 +197,74 @@ pub enum VerifyBlockHashsError {
     InvalidVersion(),
 }
 
+/// Complete or discard an interrupted databases commit
+pub(crate) fn recover_dbs_journal(dbs_path: &PathBuf) {
+    match durs_blockchain_dal::journal::recover_dbs(dbs_path) {
+        Ok(JournalRecovery::Clean) => {}
+        Ok(JournalRecovery::RolledForward(blockstamp)) => {
+            warn!("Complete the interrupted commit of block {}.", blockstamp)
+        }
+        Ok(JournalRecovery::RolledBack) => warn!("Discard an incomplete databases commit."),
+        Err(err) => fatal_error!("Fail to recover databases journal: {:?}", err),
+    }
+}
+
+/// Get the forks databases limits from the `blockchain` section of the modules configuration
+pub(crate) fn get_forks_limits<DC: DursConfTrait>(conf: &DC) -> ForksLimits {
+    if let Some(blockchain_conf) = conf.modules().get(MODULE_NAME) {
+        match serde_json::from_value(blockchain_conf.clone()) {
+            Ok(forks_limits) => return forks_limits,
+            Err(err) => warn!(
+                "Invalid blockchain configuration, use default limits: {}",
+                err
+            ),
+        }
+    }
+    ForksLimits::default()
+}
+
+/// Check that all databases agree on the current block
+pub(crate) fn check_dbs_consistency(
+    blocks_dbs: &BlocksV10DBs,
+    forks_dbs: &ForksDBs,
+    wot_dbs: &WotsV10DBs,
+    currency_dbs: &CurrencyV10DBs,
+    current_blockstamp: Blockstamp,
+) {
+    if try_check_dbs_consistency(
+        blocks_dbs,
+        forks_dbs,
+        wot_dbs,
+        currency_dbs,
+        current_blockstamp,
+    )
+    .is_err()
+    {
+        fatal_error!(
+            "Databases disagree on the current block {}, please reset data and sync again !",
+            current_blockstamp
+        );
+    }
+}
+
+fn try_check_dbs_consistency(
+    blocks_dbs: &BlocksV10DBs,
+    forks_dbs: &ForksDBs,
+    wot_dbs: &WotsV10DBs,
+    currency_dbs: &CurrencyV10DBs,
+    current_blockstamp: Blockstamp,
+) -> Result<(), DALError> {
+    durs_blockchain_dal::journal::check_dbs_consistency(
+        &[
+            blocks_dbs.staged_db()?,
+            forks_dbs.staged_db()?,
+            wot_dbs.staged_db()?,
+            currency_dbs.staged_db()?,
+        ],
+        current_blockstamp,
+    )
+}
+
 impl BlockchainModule {
     /// Return module identifier
     pub fn name() -> ModuleStaticName {
@@ -193,9 +280,13 @@ impl BlockchainModule {
         // Get db path
         let dbs_path = durs_conf::get_blockchain_db_path(profile_path.clone(), &conf.currency());
 
+        // Complete or discard an interrupted commit
+        recover_dbs_journal(&dbs_path);
+
         // Open databases
         let blocks_databases = BlocksV10DBs::open(Some(&dbs_path));
-        let forks_dbs = ForksDBs::open(Some(&dbs_path));
+        let mut forks_dbs = ForksDBs::open(Some(&dbs_path));
+        forks_dbs.limits = get_forks_limits(conf);
         let wot_databases = WotsV10DBs::open(Some(&dbs_path));
         let currency_databases = CurrencyV10DBs::open(Some(&dbs_path));
 
@@ -205,6 +296,15 @@ impl BlockchainModule {
                 .expect("Fatal error : fail to read Blockchain DB !")
                 .unwrap_or_default();
 
+        // Check that all databases agree on the current block
+        check_dbs_consistency(
+            &blocks_databases,
+            &forks_dbs,
+            &wot_databases,
+            &currency_databases,
+            current_blockstamp,
+        );
+
         // Get currency parameters
         let currency_params = durs_blockchain_dal::readers::currency_params::get_currency_params(
             &blocks_databases.blockchain_db,
@@ -222,6 +322,7 @@ impl BlockchainModule {
             router_sender,
             profile_path,
             currency: conf.currency(),
+            dbs_path,
             currency_params,
             current_blockstamp,
             consensus: Blockstamp::default(),
@@ -233,16 +334,60 @@ impl BlockchainModule {
             pending_block: None,
             invalid_forks: HashSet::new(),
             pending_network_requests: HashMap::new(),
+            forks_stats: ForksStats::default(),
         }
     }
+    /// Save the modifications staged in the databases in a single atomic commit
+    pub fn commit_dbs(&self) {
+        info!(
+            "BLOCKCHAIN: Commit databases at {}.",
+            self.current_blockstamp
+        );
+        if let Err(err) = self.try_commit_dbs() {
+            fatal_error!("Fail to commit databases: {:?}", err);
+        }
+    }
+    /// Write the staged modifications of all databases in the journal, then apply them
+    fn try_commit_dbs(&self) -> Result<(), DALError> {
+        durs_blockchain_dal::journal::commit_dbs(
+            &self.dbs_path,
+            self.current_blockstamp,
+            &[
+                self.blocks_databases.staged_db()?,
+                self.forks_dbs.staged_db()?,
+                self.wot_databases.staged_db()?,
+                self.currency_databases.staged_db()?,
+            ],
+            vec![],
+        )
+    }
     /// Databases explorer
     pub fn dbex<DC: DursConfTrait>(profile_path: PathBuf, conf: &DC, csv: bool, req: &DBExQuery) {
         dbex::dbex(profile_path, conf, csv, req);
     }
+    /// Export the local blockchain into duniter json chunk files
+    pub fn export<DC: DursConfTrait>(
+        profile_path: PathBuf,
+        conf: &DC,
+        output_path: &PathBuf,
+        start: Option<u32>,
+        end: Option<u32>,
+    ) {
+        export::export(profile_path, conf, output_path, start, end);
+    }
     /// Synchronize blockchain from local duniter json files
     pub fn sync_ts<DC: DursConfTrait>(profile_path: PathBuf, conf: &DC, sync_opts: SyncOpt) {
         sync::local_sync(profile_path, conf, sync_opts);
     }
+    /// Synchronize blockchain from the blocks downloaded by the default sync module
+    pub fn sync_network<DC: DursConfTrait>(
+        profile_path: PathBuf,
+        conf: &DC,
+        sync_opts: SyncOpt,
+        network_receiver: mpsc::Receiver<RouterThreadMessage<DursMsg>>,
+    ) {
+        sync::network_sync(profile_path, conf, sync_opts, network_receiver);
+    }
     /// Start blockchain module.
     pub fn start_blockchain(&mut self, blockchain_receiver: &mpsc::Receiver<DursMsg>) {
         info!("BlockchainModule::start_blockchain()");
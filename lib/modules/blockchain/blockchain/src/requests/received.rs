@@ -160,8 +160,10 @@ pub fn receive_req(
             }
             BlockchainRequest::GetIdentities(filters) => {
                 let identities = durs_blockchain_dal::readers::identity::get_identities(
-                    &bc.wot_databases.identities_db,
-                    filters,
+                    &bc.wot_databases,
+                    &bc.blocks_databases.blockchain_db,
+                    bc.currency_params.ms_validity,
+                    &filters,
                     bc.current_blockstamp.id,
                 )
                 .expect("Fatal error : get_identities: Fail to read IdentitiesDB !")
@@ -175,6 +177,12 @@ pub fn receive_req(
                     &BlockchainResponse::Identities(identities),
                 );
             }
+            BlockchainRequest::ForksStats => responses::sent::send_req_response(
+                bc,
+                req_from,
+                req_id,
+                &BlockchainResponse::ForksStats(bc.forks_stats),
+            ),
         }
     }
 }
@@ -15,14 +15,14 @@
 
 use crate::sync::*;
 use pbr::ProgressBar;
-use std::sync::mpsc;
+use std::sync::{mpsc, Arc};
 
 pub fn execute(
     pool: &ThreadPool,
     sender_sync_thread: mpsc::Sender<MessForSyncThread>,
     recv: mpsc::Receiver<SyncJobsMess>,
-    blocks_dbs: BlocksV10DBs,
-    forks_db: ForksDBs,
+    blocks_dbs: Arc<BlocksV10DBs>,
+    forks_db: Arc<ForksDBs>,
     target_blockstamp: Blockstamp,
     mut apply_pb: ProgressBar<std::io::Stdout>,
 ) {
@@ -34,33 +34,35 @@ pub fn execute(
         let mut chunk_index = 0;
         let mut all_wait_duration = Duration::from_millis(0);
         let mut wait_begin = SystemTime::now();
-        while let Ok(SyncJobsMess::BlocksDBsWriteQuery(req)) = recv.recv() {
+        while let Ok(mess) = recv.recv() {
             all_wait_duration += SystemTime::now().duration_since(wait_begin).unwrap();
+            match mess {
+                SyncJobsMess::BlocksDBsWriteQuery(req) => {
+                    // Apply db request
+                    req.apply(&blocks_dbs, &forks_db, Some(target_blockstamp))
+                        .expect("Fatal error : Fail to apply DBWriteRequest !");
 
-            // Apply db request
-            req.apply(
-                &blocks_dbs.blockchain_db,
-                &forks_db,
-                Some(target_blockstamp),
-            )
-            .expect("Fatal error : Fail to apply DBWriteRequest !");
-
-            chunk_index += 1;
-            if chunk_index == 250 {
-                chunk_index = 0;
-                apply_pb.inc();
+                    chunk_index += 1;
+                    if chunk_index == 250 {
+                        chunk_index = 0;
+                        apply_pb.inc();
+                    }
+                }
+                SyncJobsMess::Checkpoint(sender_ack) => {
+                    // All the pending requests are staged in blockchain and forks databases
+                    sender_ack
+                        .send(())
+                        .expect("Fatal error : sync_thread unrechable !");
+                }
+                SyncJobsMess::End() => break,
+                _ => {}
             }
             wait_begin = SystemTime::now();
         }
 
         // Increment progress bar (last chunk)
         apply_pb.inc();
-        // Save blockchain, and fork databases
         println!();
-        println!("Write indexs in files...");
-        info!("Save blockchain and forks databases in files...");
-        blocks_dbs.save_dbs();
-        forks_db.save_dbs();
 
         // Send finish signal
         sender_sync_thread
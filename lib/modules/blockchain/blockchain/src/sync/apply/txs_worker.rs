@@ -14,35 +14,40 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::sync::*;
-use std::sync::mpsc;
+use std::sync::{mpsc, Arc};
 
 pub fn execute(
     pool: &ThreadPool,
-    profile_path: PathBuf,
-    currency: CurrencyName,
+    databases: Arc<CurrencyV10DBs>,
     sender_sync_thread: mpsc::Sender<MessForSyncThread>,
     recv: mpsc::Receiver<SyncJobsMess>,
 ) {
     // Launch tx_worker thread
     pool.execute(move || {
         let tx_job_begin = SystemTime::now();
-        // Open databases
-        let db_path = durs_conf::get_blockchain_db_path(profile_path, &currency);
-        let databases = CurrencyV10DBs::open(Some(&db_path));
 
         // Listen db requets
         let mut all_wait_duration = Duration::from_millis(0);
         let mut wait_begin = SystemTime::now();
-        while let Ok(SyncJobsMess::CurrencyDBsWriteQuery(blockstamp, req)) = recv.recv() {
+        while let Ok(mess) = recv.recv() {
             all_wait_duration += SystemTime::now().duration_since(wait_begin).unwrap();
-            // Apply db request
-            req.apply(&blockstamp, &databases)
-                .expect("Fatal error : Fail to apply DBWriteRequest !");
+            match mess {
+                SyncJobsMess::CurrencyDBsWriteQuery(blockstamp, req) => {
+                    // Apply db request
+                    req.apply(&blockstamp, &databases)
+                        .expect("Fatal error : Fail to apply DBWriteRequest !");
+                }
+                SyncJobsMess::Checkpoint(sender_ack) => {
+                    // All the pending requests are staged in tx and sources databases
+                    sender_ack
+                        .send(())
+                        .expect("Fatal error : sync_thread unrechable !");
+                }
+                SyncJobsMess::End() => break,
+                _ => {}
+            }
             wait_begin = SystemTime::now();
         }
-        // Save tx, utxo, du and balances databases
-        info!("Save tx and sources database in file...");
-        databases.save_dbs(true, true);
 
         // Send finish signal
         sender_sync_thread
@@ -15,21 +15,17 @@
 
 use crate::sync::*;
 use std::ops::Deref;
-use std::sync::mpsc;
+use std::sync::{mpsc, Arc};
 
 pub fn execute(
     pool: &ThreadPool,
-    profile_path: PathBuf,
-    currency: CurrencyName,
+    databases: Arc<WotsV10DBs>,
     sender_sync_thread: mpsc::Sender<MessForSyncThread>,
     recv: mpsc::Receiver<SyncJobsMess>,
 ) {
     // Launch wot_worker thread
     pool.execute(move || {
         let wot_job_begin = SystemTime::now();
-        // Open databases
-        let db_path = durs_conf::get_blockchain_db_path(profile_path, &currency);
-        let databases = WotsV10DBs::open(Some(&db_path));
 
         // Listen db requets
         let mut all_wait_duration = Duration::from_millis(0);
@@ -40,15 +36,17 @@ pub fn execute(
                 SyncJobsMess::WotsDBsWriteQuery(blockstamp, currency_params, req) => req
                     .apply(&blockstamp, &currency_params.deref(), &databases)
                     .expect("Fatal error : Fail to apply DBWriteRequest !"),
+                SyncJobsMess::Checkpoint(sender_ack) => {
+                    // All the pending requests are staged in wots databases
+                    sender_ack
+                        .send(())
+                        .expect("Fatal error : sync_thread unrechable !");
+                }
                 SyncJobsMess::End() => break,
                 _ => {}
             }
             wait_begin = SystemTime::now();
         }
-        // Save wots databases
-        info!("Save wots databases in files...");
-        databases.save_dbs_except_graph();
-
         // Send finish signal
         sender_sync_thread
             .send(MessForSyncThread::ApplyFinish())
@@ -17,7 +17,8 @@ use crate::sync::*;
 use dubp_documents::parsers::blocks::parse_json_block;
 use dubp_documents::Blockstamp;
 use durs_common_tools::fatal_error;
-use failure::Error;
+use durs_common_tools::fns::interrupt;
+use failure::{err_msg, Error};
 use rayon::prelude::*;
 use std::collections::HashSet;
 use std::io::Read;
@@ -46,14 +47,14 @@ pub fn json_reader_worker(
             fatal_error!("json_files_path directory is empty !");
         }
 
-        // Get max chunk number and max block id
-        let (max_chunk_number, max_block_id): (usize, u32) = if let Some(end) = end {
-            (end as usize / (*crate::constants::CHUNK_SIZE), end)
+        // Get max chunk number
+        let max_chunk_number: usize = if let Some(end) = end {
+            end as usize / (*crate::constants::CHUNK_SIZE)
         } else {
-            (
-                chunks_set.len() - 1,
-                (chunks_set.len() * (*crate::constants::CHUNK_SIZE) - 1) as u32,
-            )
+            *chunks_set
+                .iter()
+                .max()
+                .expect("safe unwrap because not empty")
         };
 
         // Verify if max chunk exist
@@ -80,9 +81,20 @@ pub fn json_reader_worker(
             fatal_error!("Last chunk is empty !");
         }
 
-        let last_block = last_chunk_blocks
-            .get(max_block_id as usize % *crate::constants::CHUNK_SIZE)
-            .expect("safe unwrap because not empty");
+        // Get max block (the chunk files may be incomplete, for example if they are exported by durs)
+        let last_block = if let Some(end) = end {
+            last_chunk_blocks.iter().find(|block| block.number.0 == end)
+        } else {
+            last_chunk_blocks.last()
+        };
+        let last_block = match last_block {
+            Some(last_block) => last_block,
+            None => fatal_error!(
+                "Chunk file n°{} don't contain the last block !",
+                max_chunk_number
+            ),
+        };
+        let max_block_id = last_block.number.0;
 
         // Send TargetBlockcstamp
         sender_sync_thread
@@ -108,24 +120,34 @@ pub fn json_reader_worker(
 
         // Parse chunks
         let mut begin_chunk_number = first_chunk_number;
-        while begin_chunk_number <= max_chunk_number {
+        'chunks: while begin_chunk_number <= max_chunk_number && !interrupt::is_interrupted() {
             let last_chunk_number = if begin_chunk_number + *CHUNKS_STEP < max_chunk_number + 1 {
                 begin_chunk_number + *CHUNKS_STEP
             } else {
                 max_chunk_number + 1
             };
             let chunks_numbers: Vec<_> = (begin_chunk_number..last_chunk_number).collect();
-            let mut chunks_blocks: HashMap<usize, Vec<BlockDocument>> = chunks_numbers
-                .par_iter()
-                .map(|chunk_number| treat_once_json_chunk(&json_chunks_path, *chunk_number))
-                .collect();
+            let mut chunks_blocks: HashMap<usize, Result<Vec<BlockDocument>, Error>> =
+                chunks_numbers
+                    .par_iter()
+                    .map(|chunk_number| treat_once_json_chunk(&json_chunks_path, *chunk_number))
+                    .collect();
 
             // Send blocks
             for chunk_number in chunks_numbers {
-                for block in chunks_blocks
+                let blocks = match chunks_blocks
                     .remove(&chunk_number)
                     .expect("Dev error: sync: chunk_blocks not contain key chunk_number !")
                 {
+                    Ok(blocks) => blocks,
+                    Err(e) => {
+                        // Stop here, the blocks already sent will be applied and saved
+                        println!("Fail to read chunk file n°{} : {}", chunk_number, e);
+                        error!("Fail to read chunk file n°{} : {}", chunk_number, e);
+                        break 'chunks;
+                    }
+                };
+                for block in blocks {
                     // Verify if the block number is within the expected interval
                     let block_id = block.blockstamp().id;
                     if (block_id > current_blockstamp.id && block_id.0 <= max_block_id)
@@ -160,22 +182,12 @@ pub fn json_reader_worker(
 fn treat_once_json_chunk(
     json_chunks_path: &PathBuf,
     chunk_number: usize,
-) -> (usize, Vec<BlockDocument>) {
-    // Open chunk file
-    let chunk_file_content_result = open_json_chunk_file(json_chunks_path, chunk_number);
-    if chunk_file_content_result.is_err() {
-        fatal_error!("Fail to open chunk file n°{}", chunk_number);
-    }
-
-    // Parse chunk file content
-    let blocks_result = parse_json_chunk(&chunk_file_content_result.expect("safe unwrap"));
-    let blocks = match blocks_result {
-        Ok(blocks) => blocks,
-        Err(e) => {
-            fatal_error!("Fail to parse chunk file n°{} : {}", chunk_number, e);
-        }
-    };
-    (chunk_number, blocks)
+) -> (usize, Result<Vec<BlockDocument>, Error>) {
+    // Open and parse chunk file
+    let blocks_result = open_json_chunk_file(json_chunks_path, chunk_number)
+        .map_err(Error::from)
+        .and_then(|chunk_file_content| parse_json_chunk(&chunk_file_content));
+    (chunk_number, blocks_result)
 }
 
 /// Parse json chunk into BlockDocument Vector
@@ -190,13 +202,13 @@ fn parse_json_chunk(json_chunk_content: &str) -> Result<Vec<BlockDocument>, Erro
                     block_doc_vec.push(parse_json_block(json_block)?);
                 }
             } else {
-                fatal_error!("Fail to parse json chunk : field \"blocks\" must be an array !");
+                return Err(err_msg("field \"blocks\" must be an array !"));
             }
         } else {
-            fatal_error!("Fail to parse json chunk : field \"blocks\" don't exist !");
+            return Err(err_msg("field \"blocks\" don't exist !"));
         }
     } else {
-        fatal_error!("Fail to parse json chunk : json root node must be an object !");
+        return Err(err_msg("json root node must be an object !"));
     }
 
     Ok(block_doc_vec)
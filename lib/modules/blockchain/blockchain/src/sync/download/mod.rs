@@ -14,3 +14,4 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 pub mod json_reader_worker;
+pub mod network_reader_worker;
@@ -0,0 +1,74 @@
+//  Copyright (C) 2018  The Durs Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::sync::*;
+use durs_common_tools::fatal_error;
+use std::sync::mpsc;
+use threadpool::ThreadPool;
+
+/// Network reader worker: transmit to the sync thread the target and the blocks
+/// downloaded by the default sync module
+pub fn network_reader_worker(
+    pool: &ThreadPool,
+    sender_sync_thread: mpsc::Sender<MessForSyncThread>,
+    network_receiver: mpsc::Receiver<RouterThreadMessage<DursMsg>>,
+) {
+    pool.execute(move || {
+        let network_job_begin = SystemTime::now();
+
+        while let Ok(msg) = network_receiver.recv() {
+            match msg {
+                RouterThreadMessage::ModuleMessage(DursMsg::Event {
+                    event_content: DursEvent::NetworkEvent(network_event),
+                    ..
+                }) => match network_event {
+                    NetworkEvent::SyncTarget(currency, target_blockstamp) => {
+                        if sender_sync_thread
+                            .send(MessForSyncThread::Target(currency, target_blockstamp))
+                            .is_err()
+                        {
+                            fatal_error!("Fatal error : sync_thread unrechable !");
+                        }
+                    }
+                    NetworkEvent::ReceiveBlocks(blocks) => {
+                        for block in blocks {
+                            if sender_sync_thread
+                                .send(MessForSyncThread::BlockDocument(block))
+                                .is_err()
+                            {
+                                // The sync thread stops when the target is reached
+                                return;
+                            }
+                        }
+                    }
+                    _ => {}
+                },
+                RouterThreadMessage::ModuleMessage(DursMsg::Stop) => break,
+                _ => {}
+            }
+        }
+
+        // The download is finished (or the sync module has stopped)
+        let _ = sender_sync_thread.send(MessForSyncThread::DownloadFinish());
+        let network_job_duration = SystemTime::now()
+            .duration_since(network_job_begin)
+            .expect("duration_since error");
+        info!(
+            "network_job_duration={},{:03} seconds.",
+            network_job_duration.as_secs(),
+            network_job_duration.subsec_millis()
+        );
+    });
+}
@@ -23,18 +23,25 @@ use dup_crypto::keys::*;
 use durs_blockchain_dal::entities::currency_params::CurrencyParameters;
 use durs_blockchain_dal::writers::requests::*;
 use durs_common_tools::fatal_error;
+use durs_common_tools::fns::interrupt;
 use durs_wot::NodeId;
 use pbr::ProgressBar;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::fs;
-use std::sync::mpsc;
-use std::thread;
+use std::sync::{mpsc, Arc};
 use std::time::SystemTime;
 use threadpool::ThreadPool;
 
 /// Number of sync jobs
 pub static NB_SYNC_JOBS: &'static usize = &4;
 
+/// Number of blocks applied between two checkpoints of the databases
+/// (must be a multiple of the chunk size)
+pub static SYNC_CHECKPOINT_INTERVAL: &'static u32 = &25_000;
+
+/// Interval between two checks of the interrupt signal while waiting blocks
+static SYNC_INTERRUPT_CHECK_FREQ_IN_MS: &'static u64 = &500;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 /// Block header
 pub struct BlockHeader {
@@ -58,9 +65,121 @@ pub enum SyncJobsMess {
     BlocksDBsWriteQuery(BlocksDBsWriteQuery),
     WotsDBsWriteQuery(Blockstamp, Box<CurrencyParameters>, WotsDBsWriteQuery),
     CurrencyDBsWriteQuery(Blockstamp, CurrencyDBsWriteQuery),
+    Checkpoint(mpsc::Sender<()>),
     End(),
 }
 
+#[derive(Debug, Clone)]
+/// Median times of the blocks whose certifications have not yet expired
+struct CertsExpiryWindow {
+    blocks_not_expiring: VecDeque<u64>,
+    last_block_expiring: isize,
+}
+
+impl CertsExpiryWindow {
+    /// Create an empty window
+    fn new() -> CertsExpiryWindow {
+        CertsExpiryWindow {
+            blocks_not_expiring: VecDeque::with_capacity(200_000),
+            last_block_expiring: -1,
+        }
+    }
+    /// First block whose certifications have not yet expired
+    fn first_block_not_expiring(&self) -> BlockNumber {
+        BlockNumber((self.last_block_expiring + 1) as u32)
+    }
+    /// Push the median time of the next block, returns the blocks whose certifications expire
+    fn push_block(&mut self, median_time: u64, sig_validity: u64) -> Vec<BlockNumber> {
+        self.blocks_not_expiring.push_back(median_time);
+        let mut blocks_expiring = Vec::new();
+        while self
+            .blocks_not_expiring
+            .front()
+            .map_or(false, |created_time| {
+                created_time + sig_validity <= median_time
+            })
+        {
+            self.last_block_expiring += 1;
+            blocks_expiring.push(BlockNumber(self.last_block_expiring as u32));
+            self.blocks_not_expiring.pop_front();
+        }
+        blocks_expiring
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// Created blocks of the current memberships of the not revoked identities
+struct MsRevocationWindow {
+    /// Median times of the blocks that can still be the created block of a current membership
+    median_times: BTreeMap<BlockNumber, u64>,
+    ms_created_blocks: BTreeMap<BlockNumber, HashSet<PubKey>>,
+    idties_ms: HashMap<PubKey, BlockNumber>,
+}
+
+impl MsRevocationWindow {
+    /// Set the created block of the current membership of an identity (`None` if revoked)
+    fn set_membership(&mut self, pubkey: PubKey, ms_created_block_id: Option<BlockNumber>) {
+        if let Some(old_block_id) = self.idties_ms.remove(&pubkey) {
+            let block_is_empty =
+                if let Some(pubkeys) = self.ms_created_blocks.get_mut(&old_block_id) {
+                    pubkeys.remove(&pubkey);
+                    pubkeys.is_empty()
+                } else {
+                    false
+                };
+            if block_is_empty {
+                self.ms_created_blocks.remove(&old_block_id);
+            }
+        }
+        if let Some(block_id) = ms_created_block_id {
+            self.idties_ms.insert(pubkey, block_id);
+            self.ms_created_blocks
+                .entry(block_id)
+                .or_insert_with(HashSet::new)
+                .insert(pubkey);
+        }
+    }
+    /// Push the median time of the next block
+    /// (and forget the blocks whose memberships are expired at this time)
+    fn push_median_time(&mut self, block_number: BlockNumber, median_time: u64, ms_validity: u64) {
+        self.median_times.insert(block_number, median_time);
+        while let Some((&block_id, &created_time)) = self.median_times.iter().next() {
+            if created_time + 2 * ms_validity > median_time {
+                break;
+            }
+            self.median_times.remove(&block_id);
+        }
+    }
+    /// Push the next block, returns the identities implicitly revoked by this block
+    fn push_block(&mut self, block: &BlockDocument, ms_validity: u64) -> Vec<PubKey> {
+        self.push_median_time(block.number, block.median_time, ms_validity);
+        for revocation in &block.revoked {
+            self.set_membership(revocation.to_compact_document().issuer, None);
+        }
+        for membership in block.joiners.iter().chain(block.actives.iter()) {
+            self.set_membership(membership.issuers()[0], Some(membership.blockstamp().id));
+        }
+        let mut implicit_revocations = Vec::new();
+        while let Some(block_id) = self.ms_created_blocks.keys().next().cloned() {
+            // The median times of the blocks whose memberships are expired are forgotten
+            let expired = self
+                .median_times
+                .get(&block_id)
+                .map_or(true, |created_time| {
+                    created_time + 2 * ms_validity <= block.median_time
+                });
+            if !expired {
+                break;
+            }
+            for pubkey in self.ms_created_blocks.remove(&block_id).unwrap_or_default() {
+                self.idties_ms.remove(&pubkey);
+                implicit_revocations.push(pubkey);
+            }
+        }
+        implicit_revocations
+    }
+}
+
 /// Get json files path
 fn get_json_files_path(source: Option<String>, currency: Option<String>) -> PathBuf {
     if let Some(ref path) = source {
@@ -121,28 +240,11 @@ pub fn local_sync<DC: DursConfTrait>(profile_path: PathBuf, conf: &DC, sync_opts
         fatal_error!("duniter json chunks folder don't exist !");
     }
 
-    // Get verification level
-    let _verif_level = if cautious {
-        println!("Start cautious sync...");
-        info!("Start cautious sync...");
-        SyncVerificationLevel::Cautious()
-    } else {
-        println!("Start fast sync...");
-        info!("Start fast sync...");
-        SyncVerificationLevel::FastSync()
-    };
-
     // Create sync_thread channels
     let (sender_sync_thread, recv_sync_thread) = mpsc::channel();
 
     // Create ThreadPool
-    let nb_cpus = num_cpus::get();
-    let nb_workers = if nb_cpus < *NB_SYNC_JOBS {
-        nb_cpus
-    } else {
-        *NB_SYNC_JOBS
-    };
-    let pool = ThreadPool::new(nb_workers);
+    let pool = create_sync_pool();
 
     if !json_files_path.is_dir() {
         error!("json_files_path must be a directory");
@@ -158,13 +260,151 @@ pub fn local_sync<DC: DursConfTrait>(profile_path: PathBuf, conf: &DC, sync_opts
         end,
     );
 
+    apply_sync_blocks(
+        &pool,
+        profile_path,
+        conf,
+        (sender_sync_thread, recv_sync_thread),
+        cautious,
+        verif_inner_hash,
+    );
+}
+
+/// Sync from the network (the blocks are downloaded by the default sync module)
+pub fn network_sync<DC: DursConfTrait>(
+    profile_path: PathBuf,
+    conf: &DC,
+    sync_opts: SyncOpt,
+    network_receiver: mpsc::Receiver<RouterThreadMessage<DursMsg>>,
+) {
+    let SyncOpt {
+        cautious_mode: cautious,
+        unsafe_mode: verif_inner_hash,
+        ..
+    } = sync_opts;
+
+    // Create sync_thread channels
+    let (sender_sync_thread, recv_sync_thread) = mpsc::channel();
+
+    // Create ThreadPool
+    let pool = create_sync_pool();
+
+    // Lauch network reader worker
+    download::network_reader_worker::network_reader_worker(
+        &pool,
+        sender_sync_thread.clone(),
+        network_receiver,
+    );
+
+    apply_sync_blocks(
+        &pool,
+        profile_path,
+        conf,
+        (sender_sync_thread, recv_sync_thread),
+        cautious,
+        verif_inner_hash,
+    );
+}
+
+/// Create sync jobs thread pool
+fn create_sync_pool() -> ThreadPool {
+    let nb_cpus = num_cpus::get();
+    let nb_workers = if nb_cpus < *NB_SYNC_JOBS {
+        nb_cpus
+    } else {
+        *NB_SYNC_JOBS
+    };
+    ThreadPool::new(nb_workers)
+}
+
+/// Databases written by the sync
+struct SyncDBs {
+    blocks_dbs: Arc<BlocksV10DBs>,
+    forks_dbs: Arc<ForksDBs>,
+    wot_databases: Arc<WotsV10DBs>,
+    currency_databases: Arc<CurrencyV10DBs>,
+    currency_params_db: BinDB<CurrencyParamsV10Datas>,
+}
+
+/// Save atomically all the databases of the sync at the blockstamp `current_blockstamp`
+fn checkpoint_dbs(
+    db_path: &PathBuf,
+    current_blockstamp: Blockstamp,
+    workers_senders: &[&mpsc::Sender<SyncJobsMess>],
+    dbs: &SyncDBs,
+) {
+    // Wait for each worker to stage its pending requests
+    let (sender_ack, recv_ack) = mpsc::channel();
+    for worker_sender in workers_senders {
+        worker_sender
+            .send(SyncJobsMess::Checkpoint(sender_ack.clone()))
+            .expect("Sync : Fail to send Checkpoint signal to worker !");
+    }
+    for _ in workers_senders {
+        recv_ack
+            .recv()
+            .expect("Sync : Fail to receive checkpoint acknowledgement from worker !");
+    }
+    let params_file = dbs
+        .currency_params_db
+        .to_db_file("params.db")
+        .expect("Fail to serialize params db");
+
+    // Commit databases
+    let commit_result = (|| -> Result<(), DALError> {
+        durs_blockchain_dal::journal::commit_dbs(
+            db_path,
+            current_blockstamp,
+            &[
+                dbs.blocks_dbs.staged_db()?,
+                dbs.forks_dbs.staged_db()?,
+                dbs.wot_databases.staged_db()?,
+                dbs.currency_databases.staged_db()?,
+            ],
+            vec![params_file],
+        )
+    })();
+    commit_result.expect("Fail to commit databases");
+    info!("Sync : checkpoint at block #{}.", current_blockstamp.id.0);
+}
+
+/// Apply the blocks received from a download worker
+fn apply_sync_blocks<DC: DursConfTrait>(
+    pool: &ThreadPool,
+    profile_path: PathBuf,
+    conf: &DC,
+    sync_thread_channel: (
+        mpsc::Sender<MessForSyncThread>,
+        mpsc::Receiver<MessForSyncThread>,
+    ),
+    cautious: bool,
+    verif_inner_hash: bool,
+) {
+    let (sender_sync_thread, recv_sync_thread) = sync_thread_channel;
+
+    // Stop cleanly on Ctrl-C (the databases are flushed at the last applied block)
+    interrupt::catch_interrupt_signals();
+
+    // Get verification level
+    let _verif_level = if cautious {
+        println!("Start cautious sync...");
+        info!("Start cautious sync...");
+        SyncVerificationLevel::Cautious()
+    } else {
+        println!("Start fast sync...");
+        info!("Start fast sync...");
+        SyncVerificationLevel::FastSync()
+    };
+
     // Get target blockstamp
     let (currency, target_blockstamp) =
         if let Ok(MessForSyncThread::Target(currency, target_blockstamp)) = recv_sync_thread.recv()
         {
             (currency, target_blockstamp)
         } else {
-            fatal_error!("Fatal error : no target blockstamp !");
+            println!("Fail to get sync target !");
+            error!("Fail to get sync target !");
+            return;
         };
 
     // Update DursConf
@@ -179,14 +419,22 @@ pub fn local_sync<DC: DursConfTrait>(profile_path: PathBuf, conf: &DC, sync_opts
     conf_path.push(durs_conf::constants::CONF_FILENAME);
     durs_conf::write_conf_file(conf_path.as_path(), &conf).expect("Fail to write new conf !");
 
+    // Complete or discard an interrupted commit
+    recover_dbs_journal(&db_path);
+
     // Open blocks databases
-    let blocks_dbs = BlocksV10DBs::open(Some(&db_path));
+    let blocks_dbs = Arc::new(BlocksV10DBs::open(Some(&db_path)));
 
     // Open forks databases
-    let forks_dbs = ForksDBs::open(Some(&db_path));
+    let mut forks_dbs = ForksDBs::open(Some(&db_path));
+    forks_dbs.limits = get_forks_limits(&conf);
+    let forks_dbs = Arc::new(forks_dbs);
 
     // Open wot databases
-    let wot_databases = WotsV10DBs::open(Some(&db_path));
+    let wot_databases = Arc::new(WotsV10DBs::open(Some(&db_path)));
+
+    // Open currency databases
+    let currency_databases = Arc::new(CurrencyV10DBs::open(Some(&db_path)));
 
     // Get local current blockstamp
     debug!("Get local current blockstamp...");
@@ -196,6 +444,15 @@ pub fn local_sync<DC: DursConfTrait>(profile_path: PathBuf, conf: &DC, sync_opts
             .unwrap_or_default();
     debug!("Success to get local current blockstamp.");
 
+    // Check that all databases agree on the current block
+    check_dbs_consistency(
+        &blocks_dbs,
+        &forks_dbs,
+        &wot_databases,
+        &currency_databases,
+        current_blockstamp,
+    );
+
     // Node is already synchronized ?
     if target_blockstamp.id.0 <= current_blockstamp.id.0 {
         println!("Your durs node is already synchronized.");
@@ -226,6 +483,68 @@ pub fn local_sync<DC: DursConfTrait>(profile_path: PathBuf, conf: &DC, sync_opts
         current_blockstamp.id.0, target_blockstamp.id.0
     );
 
+    // Open currency_params_db
+    let currency_params_db = BinDB::File(
+        open_file_db::<CurrencyParamsV10Datas>(&db_path, "params.db")
+            .expect("Fail to open params db"),
+    );
+
+    // Restore the state of an interrupted sync
+    let mut certs_expiry_window = CertsExpiryWindow::new();
+    let mut ms_revocation_window = MsRevocationWindow::default();
+    let certs_db = WotsV10DBs::open(None).certs_db;
+    let mut currency_params = CurrencyParameters::default();
+    let mut get_currency_params = false;
+    let mut ud_state = None;
+    if current_blockstamp != Blockstamp::default() {
+        if let Some(params) = durs_blockchain_dal::readers::currency_params::get_currency_params(
+            &blocks_dbs.blockchain_db,
+        )
+        .expect("DALError : fail to get currency params !")
+        {
+            currency_params = params;
+            get_currency_params = true;
+        }
+        ud_state = UdState::local_state(
+            &blocks_dbs.blockchain_db,
+            &blocks_dbs.ud_states_db,
+            current_blockstamp.id,
+        )
+        .expect("DALError : fail to get universal dividend state !");
+        durs_blockchain_dal::readers::block::for_each_block(
+            &blocks_dbs.blockchain_db,
+            current_blockstamp.id.0 + 1,
+            |dal_block| {
+                certs_expiry_window
+                    .push_block(dal_block.block.median_time, currency_params.sig_validity);
+                ms_revocation_window.push_median_time(
+                    dal_block.block.number,
+                    dal_block.block.median_time,
+                    currency_params.ms_validity,
+                );
+            },
+        )
+        .expect("DALError : fail to read blockchain db !");
+        // Only the certifications that have not yet expired are needed
+        certs_db
+            .extend(
+                wot_databases
+                    .certs_db
+                    .range(Some(&certs_expiry_window.first_block_not_expiring()), None)
+                    .expect("DALError : fail to read certs db !"),
+            )
+            .expect("Fail to write in memory certs_db");
+        for (pubkey, idty) in wot_databases
+            .identities_db
+            .entries()
+            .expect("DALError : fail to read identities db !")
+        {
+            if idty.state.kind() != DALIdentityStateKind::Revoked {
+                ms_revocation_window.set_membership(pubkey, Some(idty.ms_created_block_id));
+            }
+        }
+    }
+
     // Createprogess bar
     let mut apply_pb = ProgressBar::new(count_chunks.into());
     apply_pb.format("╢▌▌░╟");
@@ -237,57 +556,67 @@ pub fn local_sync<DC: DursConfTrait>(profile_path: PathBuf, conf: &DC, sync_opts
 
     // Launch blocks_worker thread
     apply::blocks_worker::execute(
-        &pool,
+        pool,
         sender_sync_thread.clone(),
         recv_blocks_thread,
-        blocks_dbs,
-        forks_dbs,
+        blocks_dbs.clone(),
+        forks_dbs.clone(),
         target_blockstamp,
         apply_pb,
     );
 
     // / Launch wot_worker thread
     apply::wot_worker::execute(
-        &pool,
-        profile_path.clone(),
-        currency.clone(),
+        pool,
+        wot_databases.clone(),
         sender_sync_thread.clone(),
         recv_wot_thread,
     );
 
     // Launch tx_worker thread
     apply::txs_worker::execute(
-        &pool,
-        profile_path.clone(),
-        currency.clone(),
+        pool,
+        currency_databases.clone(),
         sender_sync_thread.clone(),
         recv_tx_thread,
     );
 
     let main_job_begin = SystemTime::now();
 
-    // Open currency_params_db
-    let dbs_path = durs_conf::get_blockchain_db_path(profile_path, &conf.currency());
-    let currency_params_db = BinDB::File(
-        open_file_db::<CurrencyParamsV10Datas>(&dbs_path, "params.db")
-            .expect("Fail to open params db"),
-    );
-
     // Apply blocks
-    let mut blocks_not_expiring = VecDeque::with_capacity(200_000);
-    let mut last_block_expiring: isize = -1;
-    let certs_db =
-        BinDB::Mem(open_memory_db::<CertsExpirV10Datas>().expect("Fail to create memory certs_db"));
-    let mut currency_params = CurrencyParameters::default();
-    let mut get_currency_params = false;
     let mut certs_count = 0;
-
     let mut all_wait_duration = Duration::from_millis(0);
     let mut wait_begin = SystemTime::now();
     let mut all_verif_block_hashs_duration = Duration::from_millis(0);
     let mut all_apply_valid_block_duration = Duration::from_millis(0);
-    while let Ok(MessForSyncThread::BlockDocument(block_doc)) = recv_sync_thread.recv() {
+    let workers_senders = [&sender_blocks_thread, &sender_wot_thread, &sender_tx_thread];
+    let sync_dbs = SyncDBs {
+        blocks_dbs,
+        forks_dbs,
+        wot_databases,
+        currency_databases,
+        currency_params_db,
+    };
+    loop {
+        let block_doc = match recv_sync_thread
+            .recv_timeout(Duration::from_millis(*SYNC_INTERRUPT_CHECK_FREQ_IN_MS))
+        {
+            Ok(MessForSyncThread::BlockDocument(block_doc)) => block_doc,
+            Err(mpsc::RecvTimeoutError::Timeout) if !interrupt::is_interrupted() => continue,
+            _ => break,
+        };
         all_wait_duration += SystemTime::now().duration_since(wait_begin).unwrap();
+        if interrupt::is_interrupted() {
+            break;
+        }
+
+        // Ignore blocks already applied
+        if block_doc.number.0 <= current_blockstamp.id.0
+            && current_blockstamp != Blockstamp::default()
+        {
+            wait_begin = SystemTime::now();
+            continue;
+        }
 
         // Verify block hashs
         let verif_block_hashs_begin = SystemTime::now();
@@ -300,24 +629,24 @@ pub fn local_sync<DC: DursConfTrait>(profile_path: PathBuf, conf: &DC, sync_opts
             .unwrap();
         // Get and write currency params
         if !get_currency_params {
-            currency_params = get_and_write_currency_params(&currency_params_db, &block_doc);
+            currency_params =
+                get_and_write_currency_params(&sync_dbs.currency_params_db, &block_doc);
             get_currency_params = true;
         }
-        // Push block median_time in blocks_not_expiring
-        blocks_not_expiring.push_back(block_doc.median_time);
         // Get blocks_expiring
-        let mut blocks_expiring = Vec::new();
-        while blocks_not_expiring.front().cloned()
-            < Some(block_doc.median_time - currency_params.sig_validity)
-        {
-            last_block_expiring += 1;
-            blocks_expiring.push(BlockNumber(last_block_expiring as u32));
-            blocks_not_expiring.pop_front();
-        }
+        let blocks_expiring =
+            certs_expiry_window.push_block(block_doc.median_time, currency_params.sig_validity);
         // Find expire_certs
         let expire_certs =
             durs_blockchain_dal::readers::certs::find_expire_certs(&certs_db, blocks_expiring)
                 .expect("find_expire_certs() : DALError");
+        // Find implicit revocations
+        let implicit_revocations =
+            ms_revocation_window.push_block(&block_doc, currency_params.ms_validity);
+        // Get universal dividend state after the block
+        let (block_ud_state, _) =
+            dubp::check::dividend::expected_ud_state(&block_doc, ud_state, &currency_params);
+        ud_state = Some(block_ud_state);
         // Get block blockstamp
         let blockstamp = block_doc.blockstamp();
         // Apply block
@@ -326,8 +655,10 @@ pub fn local_sync<DC: DursConfTrait>(profile_path: PathBuf, conf: &DC, sync_opts
             apply_valid_block::<RustyWebOfTrust>(
                 block_doc,
                 &mut wot_index,
-                &wot_databases.wot_db,
+                &sync_dbs.wot_databases.wot_db,
                 &expire_certs,
+                &implicit_revocations,
+                block_ud_state.to_dal(),
             )
         {
             all_apply_valid_block_duration += SystemTime::now()
@@ -354,13 +685,12 @@ pub fn local_sync<DC: DursConfTrait>(profile_path: PathBuf, conf: &DC, sync_opts
                     certs_count += 1;
                     // Add cert in certs_db
                     certs_db
-                        .write(|db| {
-                            let mut created_certs =
-                                db.get(&created_block_id).cloned().unwrap_or_default();
-                            created_certs.insert((*source, *target));
-                            db.insert(*created_block_id, created_certs);
+                        .update(created_block_id, |created_certs| {
+                            created_certs
+                                .get_or_insert_with(HashSet::new)
+                                .insert((*source, *target));
                         })
-                        .expect("RustBreakError : please reset data and resync !");
+                        .expect("DALError : please reset data and resync !");
                 }
                 sender_wot_thread
                     .send(SyncJobsMess::WotsDBsWriteQuery(
@@ -384,6 +714,9 @@ pub fn local_sync<DC: DursConfTrait>(profile_path: PathBuf, conf: &DC, sync_opts
                     );
             }
             debug!("Success to apply block #{}", current_blockstamp.id.0);
+            if (current_blockstamp.id.0 + 1) % *SYNC_CHECKPOINT_INTERVAL == 0 {
+                checkpoint_dbs(&db_path, current_blockstamp, &workers_senders, &sync_dbs);
+            }
             if current_blockstamp.id.0 >= target_blockstamp.id.0 {
                 if current_blockstamp == target_blockstamp {
                     // Sync completed
@@ -400,6 +733,11 @@ pub fn local_sync<DC: DursConfTrait>(profile_path: PathBuf, conf: &DC, sync_opts
         }
         wait_begin = SystemTime::now();
     }
+    // Flush all databases at the last applied block
+    println!();
+    println!("Write indexs in files...");
+    checkpoint_dbs(&db_path, current_blockstamp, &workers_senders, &sync_dbs);
+
     // Send end signal to workers threads
     sender_blocks_thread
         .send(SyncJobsMess::End())
@@ -414,12 +752,6 @@ pub fn local_sync<DC: DursConfTrait>(profile_path: PathBuf, conf: &DC, sync_opts
         .expect("Sync : Fail to send End signal to writer worker !");
     info!("Sync : send End signal to tx job.");
 
-    // Save params db
-    currency_params_db.save().expect("Fail to save params db");
-
-    // Save wot db
-    wot_databases.wot_db.save().expect("Fail to save wot db");
-
     let main_job_duration =
         SystemTime::now().duration_since(main_job_begin).unwrap() - all_wait_duration;
     info!(
@@ -443,12 +775,23 @@ pub fn local_sync<DC: DursConfTrait>(profile_path: PathBuf, conf: &DC, sync_opts
     while wait_jobs > 0 {
         match recv_sync_thread.recv() {
             Ok(MessForSyncThread::ApplyFinish()) => wait_jobs -= 1,
-            Ok(_) => thread::sleep(Duration::from_millis(50)),
+            Ok(_) => {}
             Err(_) => wait_jobs -= 1,
         }
     }
     info!("All sync jobs finish.");
 
+    if current_blockstamp != target_blockstamp {
+        println!(
+            "Sync interrupted at block #{}, target #{} not reached. Run sync again to resume it.",
+            current_blockstamp.id.0, target_blockstamp.id.0
+        );
+        warn!(
+            "Sync interrupted at block #{}, target #{} not reached.",
+            current_blockstamp.id.0, target_blockstamp.id.0
+        );
+    }
+
     // Log sync duration
     debug!("certs_count={}", certs_count);
     let sync_duration = SystemTime::now().duration_since(sync_start_time).unwrap();
@@ -465,3 +808,48 @@ pub fn local_sync<DC: DursConfTrait>(profile_path: PathBuf, conf: &DC, sync_opts
         sync_duration.subsec_millis(),
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dup_crypto::hashs::Hash;
+
+    fn gen_block(number: u32, median_time: u64) -> BlockDocument {
+        dubp_documents_tests_tools::mocks::gen_empty_timed_block(
+            Blockstamp {
+                id: BlockNumber(number),
+                hash: BlockHash(dup_crypto_tests_tools::mocks::hash_from_byte(number as u8)),
+            },
+            median_time,
+            Hash::default(),
+        )
+    }
+
+    #[test]
+    fn test_ms_revocation_window() {
+        let ms_validity = 10;
+        let pubkey = dup_crypto_tests_tools::mocks::pubkey('A');
+        let mut window = MsRevocationWindow::default();
+        window.push_median_time(BlockNumber(0), 0, ms_validity);
+        window.set_membership(pubkey, Some(BlockNumber(0)));
+        assert!(window.push_block(&gen_block(1, 15), ms_validity).is_empty());
+        assert_eq!(
+            vec![pubkey],
+            window.push_block(&gen_block(2, 25), ms_validity)
+        );
+        // Only the median times of the blocks whose memberships are not expired are kept
+        assert_eq!(
+            vec![BlockNumber(1), BlockNumber(2)],
+            window.median_times.keys().cloned().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_certs_expiry_window() {
+        let mut window = CertsExpiryWindow::new();
+        assert!(window.push_block(0, 10).is_empty());
+        assert!(window.push_block(5, 10).is_empty());
+        assert_eq!(vec![BlockNumber(0)], window.push_block(12, 10));
+        assert_eq!(BlockNumber(1), window.first_block_not_expiring());
+    }
+}
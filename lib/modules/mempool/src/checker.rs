@@ -0,0 +1,635 @@
+//  Copyright (C) 2018  The Duniter Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Checks of the received documents against the current state of the local blockchain.
+
+use crate::pool::{tx_hash, MemPool};
+use dubp_documents::documents::certification::CertificationDocument;
+use dubp_documents::documents::identity::IdentityDocument;
+use dubp_documents::documents::membership::{MembershipDocument, MembershipType};
+use dubp_documents::documents::revocation::RevocationDocument;
+use dubp_documents::documents::transaction::TransactionDocument;
+use dubp_documents::documents::DUBPDocument;
+use dubp_documents::*;
+use dup_crypto::keys::PubKey;
+use durs_blockchain::{verify_transaction, InvalidTxError, TxCheckDBs, TxCheckError, TxsOverlay};
+use durs_blockchain_dal::entities::currency_params::CurrencyParameters;
+use durs_blockchain_dal::entities::identity::{DALIdentity, DALIdentityState, IdentitiesIndexesDB};
+use durs_blockchain_dal::entities::sources::SourceIndexV10;
+use durs_blockchain_dal::*;
+use std::path::PathBuf;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// Reason why a document is refused by the pool
+pub enum DocumentRefusal {
+    /// The local blockchain is empty, no document can be checked
+    NoBlockchain,
+    /// Block documents are not stored in pool
+    NotPoolDocument,
+    /// The document currency is not the local currency
+    WrongCurrency,
+    /// The document signature is invalid
+    InvalidSignature,
+    /// The referenced block does not exist in the local blockchain
+    UnknownBlockstamp,
+    /// The referenced block is too old (out of the document window)
+    OutOfWindow,
+    /// The document is already in pool
+    AlreadyInPool,
+    /// The pool is full
+    PoolFull,
+    /// The identity already exist in the blockchain
+    IdentityAlreadyExist,
+    /// The uid is already used
+    UidAlreadyUsed,
+    /// The document targets an unknown identity
+    UnknownIdentity(PubKey),
+    /// The document targets a revoked identity
+    RevokedIdentity(PubKey),
+    /// The issuer of a leaving membership is not a member
+    NotMember,
+    /// The issuer of the certification is not a member
+    CertIssuerNotMember,
+    /// A certification whose issuer is its target
+    SelfCertification,
+    /// The transaction is already written in the blockchain
+    TxAlreadyWritten,
+    /// A source consumed by the transaction does not exist
+    UnknownSource(SourceIndexV10),
+    /// A source consumed by the transaction is already consumed by a pending transaction
+    SourceAlreadySpent(SourceIndexV10),
+    /// The transaction does not respect the transaction rules
+    InvalidTransaction(InvalidTxError),
+    /// Fail to read databases
+    DALError(DALError),
+}
+
+impl From<DALError> for DocumentRefusal {
+    fn from(err: DALError) -> Self {
+        DocumentRefusal::DALError(err)
+    }
+}
+
+#[derive(Debug)]
+/// Databases read by the mempool (the blockchain module is the only writer)
+pub struct MemPoolDBs {
+    /// Local blockchain database
+    pub blockchain_db: LocalBlockchainV10DB,
+    /// Identities database
+    pub identities_db: IdentitiesV10DB,
+    /// Identities indexes database
+    pub idties_indexes_db: IdentitiesIndexesDB,
+    /// UDs sources database
+    pub du_db: UDsV10DB,
+    /// UTXOs database
+    pub utxos_db: UTXOsV10DB,
+    /// Transactions database
+    pub tx_db: TxV10DB,
+}
+
+impl MemPoolDBs {
+    /// Open databases from their respective files in read-only mode
+    pub fn open(db_path: Option<&PathBuf>) -> MemPoolDBs {
+        let wot_dbs = WotsV10DBs::open_read_only(db_path);
+        let currency_dbs = CurrencyV10DBs::open_read_only(db_path);
+        MemPoolDBs {
+            blockchain_db: BlocksV10DBs::open_read_only(db_path).blockchain_db,
+            identities_db: wot_dbs.identities_db,
+            idties_indexes_db: wot_dbs.idties_indexes_db,
+            du_db: currency_dbs.du_db,
+            utxos_db: currency_dbs.utxos_db,
+            tx_db: currency_dbs.tx_db,
+        }
+    }
+    /// Reload databases from their respective files
+    pub fn load(&self) -> Result<(), DALError> {
+        self.blockchain_db.refresh()?;
+        // Identities and their indexes are stored in the same file
+        self.identities_db.refresh()?;
+        // UDs, UTXOs and transactions are stored in the same file
+        self.du_db.refresh()
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+/// State of the local blockchain needed to check documents
+pub struct BlockchainState {
+    /// Current blockstamp
+    pub current_blockstamp: Blockstamp,
+    /// Median time of the current block
+    pub current_median_time: u64,
+    /// Currency parameters
+    pub currency_params: CurrencyParameters,
+}
+
+impl BlockchainState {
+    /// Read state in databases (`None` if the local blockchain is empty)
+    pub fn read(dbs: &MemPoolDBs) -> Result<Option<BlockchainState>, DALError> {
+        let currency_params = if let Some(currency_params) =
+            readers::currency_params::get_currency_params(&dbs.blockchain_db)?
+        {
+            currency_params
+        } else {
+            return Ok(None);
+        };
+        let current_blockstamp = if let Some(blockstamp) =
+            readers::block::get_blockchain_db_blockstamp(&dbs.blockchain_db)?
+        {
+            blockstamp
+        } else {
+            return Ok(None);
+        };
+        Ok(dbs
+            .blockchain_db
+            .get(&current_blockstamp.id)?
+            .map(|dal_block| BlockchainState {
+                current_blockstamp: dal_block.blockstamp(),
+                current_median_time: dal_block.block.median_time,
+                currency_params,
+            }))
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+/// Datas needed to check a document
+pub struct CheckDatas<'a> {
+    /// Local currency
+    pub currency: &'a CurrencyName,
+    /// Databases
+    pub dbs: &'a MemPoolDBs,
+    /// Pending documents
+    pub pool: &'a MemPool,
+    /// State of the local blockchain
+    pub state: BlockchainState,
+}
+
+impl<'a> CheckDatas<'a> {
+    /// Get the median time beyond which a document referencing `blockstamp` can no longer be written
+    fn expire_on(&self, blockstamp: Blockstamp, window: u64) -> Result<u64, DocumentRefusal> {
+        let median_time = self
+            .dbs
+            .blockchain_db
+            .get(&blockstamp.id)?
+            .filter(|dal_block| dal_block.block.hash == Some(blockstamp.hash))
+            .map(|dal_block| dal_block.block.median_time);
+        if let Some(median_time) = median_time {
+            if median_time + window >= self.state.current_median_time {
+                Ok(median_time + window)
+            } else {
+                Err(DocumentRefusal::OutOfWindow)
+            }
+        } else {
+            Err(DocumentRefusal::UnknownBlockstamp)
+        }
+    }
+    /// Get the identity of a pubkey in database
+    fn get_identity(&self, pubkey: &PubKey) -> Result<Option<DALIdentity>, DocumentRefusal> {
+        Ok(readers::identity::get_identity(
+            &self.dbs.identities_db,
+            pubkey,
+        )?)
+    }
+    /// Check that an identity exists (in database or in pool) and is not revoked
+    fn check_identity_exist(&self, pubkey: &PubKey) -> Result<(), DocumentRefusal> {
+        if let Some(dal_idty) = self.get_identity(pubkey)? {
+            if is_revoked(&dal_idty) {
+                Err(DocumentRefusal::RevokedIdentity(*pubkey))
+            } else {
+                Ok(())
+            }
+        } else if self.pool.get_identity(pubkey).is_some() {
+            Ok(())
+        } else {
+            Err(DocumentRefusal::UnknownIdentity(*pubkey))
+        }
+    }
+}
+
+/// Check if an identity is member
+fn is_member(idty: &DALIdentity) -> bool {
+    if let DALIdentityState::Member(_) = idty.state {
+        true
+    } else {
+        false
+    }
+}
+
+/// Check if an identity is revoked
+fn is_revoked(idty: &DALIdentity) -> bool {
+    match idty.state {
+        DALIdentityState::ExplicitRevoked(_)
+        | DALIdentityState::ExplicitExpireRevoked(_)
+        | DALIdentityState::ImplicitRevoked(_) => true,
+        DALIdentityState::Member(_) | DALIdentityState::ExpireMember(_) => false,
+    }
+}
+
+/// Check a document, return the median time beyond which it can no longer be written
+pub fn check_document(datas: &CheckDatas, doc: &DUBPDocument) -> Result<u64, DocumentRefusal> {
+    let (currency, signatures) = match *doc {
+        DUBPDocument::Block(_) => return Err(DocumentRefusal::NotPoolDocument),
+        DUBPDocument::Identity(ref idty) => (idty.currency(), idty.verify_signatures()),
+        DUBPDocument::Membership(ref ms) => (ms.currency(), ms.verify_signatures()),
+        DUBPDocument::Certification(ref cert) => (cert.currency(), cert.verify_signatures()),
+        DUBPDocument::Revocation(ref revoc) => (revoc.currency(), revoc.verify_signatures()),
+        DUBPDocument::Transaction(ref tx) => (tx.currency(), tx.verify_signatures()),
+    };
+    if currency != datas.currency.0 {
+        return Err(DocumentRefusal::WrongCurrency);
+    }
+    if let VerificationResult::Valid() = signatures {
+    } else {
+        return Err(DocumentRefusal::InvalidSignature);
+    }
+    match *doc {
+        DUBPDocument::Block(_) => Err(DocumentRefusal::NotPoolDocument),
+        DUBPDocument::Identity(ref idty) => check_identity(datas, idty),
+        DUBPDocument::Membership(ref ms) => check_membership(datas, ms),
+        DUBPDocument::Certification(ref cert) => check_certification(datas, cert),
+        DUBPDocument::Revocation(ref revoc) => check_revocation(datas, revoc),
+        DUBPDocument::Transaction(ref tx) => check_transaction(datas, tx),
+    }
+}
+
+/// New identity: unknown pubkey, unused uid and blockstamp in idty_window
+pub fn check_identity(datas: &CheckDatas, idty: &IdentityDocument) -> Result<u64, DocumentRefusal> {
+    let pubkey = idty.issuers()[0];
+    if datas.pool.get_identity(&pubkey).is_some() {
+        return Err(DocumentRefusal::AlreadyInPool);
+    }
+    if datas.get_identity(&pubkey)?.is_some() {
+        return Err(DocumentRefusal::IdentityAlreadyExist);
+    }
+    if datas.pool.uid_used(idty.username())
+        || readers::identity::get_pubkey_from_uid(&datas.dbs.idties_indexes_db, idty.username())?
+            .is_some()
+    {
+        return Err(DocumentRefusal::UidAlreadyUsed);
+    }
+    datas.expire_on(idty.blockstamp(), datas.state.currency_params.idty_window)
+}
+
+/// Membership: known and not revoked identity, leavers must be members, blockstamp in ms_window
+pub fn check_membership(
+    datas: &CheckDatas,
+    ms: &MembershipDocument,
+) -> Result<u64, DocumentRefusal> {
+    let pubkey = ms.issuers()[0];
+    if datas.pool.contains_membership(ms) {
+        return Err(DocumentRefusal::AlreadyInPool);
+    }
+    datas.check_identity_exist(&pubkey)?;
+    if let MembershipType::Out() = ms.membership() {
+        if !datas
+            .get_identity(&pubkey)?
+            .map_or(false, |idty| is_member(&idty))
+        {
+            return Err(DocumentRefusal::NotMember);
+        }
+    }
+    datas.expire_on(ms.blockstamp(), datas.state.currency_params.ms_window)
+}
+
+/// Certification: member issuer, known and not revoked target, blockstamp in sig_window
+pub fn check_certification(
+    datas: &CheckDatas,
+    cert: &CertificationDocument,
+) -> Result<u64, DocumentRefusal> {
+    let issuer = cert.issuers()[0];
+    let target = cert.target();
+    if issuer == *target {
+        return Err(DocumentRefusal::SelfCertification);
+    }
+    if datas.pool.contains_certification(&issuer, target) {
+        return Err(DocumentRefusal::AlreadyInPool);
+    }
+    if !datas
+        .get_identity(&issuer)?
+        .map_or(false, |idty| is_member(&idty))
+    {
+        return Err(DocumentRefusal::CertIssuerNotMember);
+    }
+    datas.check_identity_exist(target)?;
+    datas.expire_on(cert.blockstamp(), datas.state.currency_params.sig_window)
+}
+
+/// Revocation: known and not revoked identity
+///
+/// A revocation does not reference any block, it is kept `idty_window` seconds.
+pub fn check_revocation(
+    datas: &CheckDatas,
+    revocation: &RevocationDocument,
+) -> Result<u64, DocumentRefusal> {
+    let pubkey = revocation.issuers()[0];
+    if datas.pool.contains_revocation(&pubkey) {
+        return Err(DocumentRefusal::AlreadyInPool);
+    }
+    datas.check_identity_exist(&pubkey)?;
+    Ok(datas.state.current_median_time + datas.state.currency_params.idty_window)
+}
+
+impl From<TxCheckError> for DocumentRefusal {
+    fn from(err: TxCheckError) -> Self {
+        match err {
+            TxCheckError::InvalidTx(InvalidTxError::UnknownSource(source)) => {
+                DocumentRefusal::UnknownSource(source)
+            }
+            TxCheckError::InvalidTx(err) => DocumentRefusal::InvalidTransaction(err),
+            TxCheckError::DALError(err) => DocumentRefusal::DALError(err),
+        }
+    }
+}
+
+/// Transaction: not yet written, valid against the written sources, not spending pending sources
+pub fn check_transaction(
+    datas: &CheckDatas,
+    tx: &TransactionDocument,
+) -> Result<u64, DocumentRefusal> {
+    let hash = tx_hash(tx);
+    if datas.pool.contains_tx(&hash) {
+        return Err(DocumentRefusal::AlreadyInPool);
+    }
+    if datas.dbs.tx_db.contains_key(&hash)? {
+        return Err(DocumentRefusal::TxAlreadyWritten);
+    }
+    let tx_dbs = TxCheckDBs {
+        blockchain_db: &datas.dbs.blockchain_db,
+        du_db: &datas.dbs.du_db,
+        utxos_db: &datas.dbs.utxos_db,
+        tx_db: &datas.dbs.tx_db,
+    };
+    verify_transaction(
+        tx_dbs,
+        &TxsOverlay::default(),
+        tx,
+        datas.state.current_median_time,
+        datas.state.currency_params.tx_window,
+    )?;
+    for input in tx.get_inputs() {
+        let source = SourceIndexV10::from(*input);
+        if datas.pool.is_source_spent(source) {
+            return Err(DocumentRefusal::SourceAlreadySpent(source));
+        }
+    }
+    datas.expire_on(tx.blockstamp(), datas.state.currency_params.tx_window)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dubp_documents::documents::block::BlockV10Parameters;
+    use dubp_documents::documents::certification::CertificationDocumentBuilder;
+    use dubp_documents::documents::identity::IdentityDocumentBuilder;
+    use dup_crypto::hashs::Hash;
+    use dup_crypto::keys::{ed25519, Sig};
+    use durs_blockchain_dal::entities::block::DALBlock;
+    use durs_wot::NodeId;
+
+    fn blockstamp(number: u32) -> Blockstamp {
+        Blockstamp {
+            id: BlockNumber(number),
+            hash: BlockHash(dup_crypto_tests_tools::mocks::hash_from_byte(number as u8)),
+        }
+    }
+
+    fn gen_idty(pubkey: PubKey, username: &str, blockstamp: Blockstamp) -> IdentityDocument {
+        IdentityDocumentBuilder {
+            currency: "",
+            username,
+            blockstamp: &blockstamp,
+            issuer: &pubkey,
+        }
+        .build_with_signature(vec![])
+    }
+
+    fn gen_cert(issuer: PubKey, target: PubKey, blockstamp: Blockstamp) -> CertificationDocument {
+        CertificationDocumentBuilder {
+            currency: "",
+            issuer: &issuer,
+            blockstamp: &blockstamp,
+            target: &target,
+            identity_username: "",
+            identity_blockstamp: &Blockstamp::default(),
+            identity_sig: &Sig::Ed25519(ed25519::Signature([0u8; 64])),
+        }
+        .build_with_signature(vec![])
+    }
+
+    /// Blockchain of 3 blocks (median times 800, 950 and 1000) with one member identity 'A'
+    fn gen_dbs() -> (MemPoolDBs, BlockchainState) {
+        let dbs = MemPoolDBs::open(None);
+        for (number, median_time) in [800, 950, 1000].iter().enumerate() {
+            let block = dubp_documents_tests_tools::mocks::gen_empty_timed_block(
+                blockstamp(number as u32),
+                *median_time,
+                Hash::default(),
+            );
+            dbs.blockchain_db
+                .insert(
+                    &BlockNumber(number as u32),
+                    &DALBlock {
+                        block,
+                        expire_certs: None,
+                    },
+                )
+                .expect("Fail to write blockchain db");
+        }
+        let pubkey = dup_crypto_tests_tools::mocks::pubkey('A');
+        let idty = DALIdentity {
+            hash: String::new(),
+            state: DALIdentityState::Member(vec![]),
+            joined_on: blockstamp(0),
+            expired_on: None,
+            revoked_on: None,
+            idty_doc: gen_idty(pubkey, "alice", blockstamp(0)),
+            wot_id: NodeId(0),
+            ms_created_block_id: BlockNumber(0),
+            ms_chainable_on: vec![],
+            cert_chainable_on: vec![],
+        };
+        durs_blockchain_dal::writers::identity::update_identities_indexes(
+            &dbs.idties_indexes_db,
+            None,
+            Some(&idty),
+        )
+        .expect("Fail to write identities indexes db");
+        dbs.identities_db
+            .insert(&pubkey, &idty)
+            .expect("Fail to write identities db");
+        let state = BlockchainState {
+            current_blockstamp: blockstamp(2),
+            current_median_time: 1000,
+            currency_params: CurrencyParameters {
+                idty_window: 100,
+                ms_window: 100,
+                sig_window: 100,
+                tx_window: 100,
+                ..CurrencyParameters::default()
+            },
+        };
+        (dbs, state)
+    }
+
+    #[test]
+    fn test_read_blockchain_state() -> Result<(), DALError> {
+        let (dbs, _) = gen_dbs();
+        // The currency parameters are written in the genesis block
+        let mut genesis = dbs
+            .blockchain_db
+            .get(&BlockNumber(0))?
+            .expect("Genesis block must exist");
+        genesis.block.parameters = Some(BlockV10Parameters::default());
+        dbs.blockchain_db.insert(&BlockNumber(0), &genesis)?;
+        let state = BlockchainState::read(&dbs)?.expect("State must exist");
+        assert_eq!(
+            (blockstamp(2), 1000),
+            (state.current_blockstamp, state.current_median_time)
+        );
+
+        // The current block is the one of the last commit
+        let mut dbs_path = std::env::temp_dir();
+        dbs_path.push(format!("durs-mempool-state-{}", std::process::id()));
+        std::fs::create_dir_all(&dbs_path).expect("Fail to create dbs folder");
+        durs_blockchain_dal::journal::commit_dbs(
+            &dbs_path,
+            blockstamp(1),
+            &[dbs.blockchain_db.db()],
+            vec![],
+        )?;
+        let _ = std::fs::remove_dir_all(&dbs_path);
+        let state = BlockchainState::read(&dbs)?.expect("State must exist");
+        assert_eq!(
+            (blockstamp(1), 950),
+            (state.current_blockstamp, state.current_median_time)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_identity() {
+        let (dbs, state) = gen_dbs();
+        let mut pool = MemPool::new(10, 10);
+        let currency = CurrencyName::default();
+        let bob = dup_crypto_tests_tools::mocks::pubkey('B');
+        {
+            let datas = CheckDatas {
+                currency: &currency,
+                dbs: &dbs,
+                pool: &pool,
+                state,
+            };
+            assert_eq!(
+                Err(DocumentRefusal::IdentityAlreadyExist),
+                check_identity(
+                    &datas,
+                    &gen_idty(
+                        dup_crypto_tests_tools::mocks::pubkey('A'),
+                        "a",
+                        blockstamp(1)
+                    )
+                )
+            );
+            assert_eq!(
+                Err(DocumentRefusal::UidAlreadyUsed),
+                check_identity(&datas, &gen_idty(bob, "alice", blockstamp(1)))
+            );
+            assert_eq!(
+                Err(DocumentRefusal::OutOfWindow),
+                check_identity(&datas, &gen_idty(bob, "bob", blockstamp(0)))
+            );
+            assert_eq!(
+                Err(DocumentRefusal::UnknownBlockstamp),
+                check_identity(&datas, &gen_idty(bob, "bob", blockstamp(3)))
+            );
+            assert_eq!(
+                Ok(1050),
+                check_identity(&datas, &gen_idty(bob, "bob", blockstamp(1)))
+            );
+        }
+        pool.add_identity(gen_idty(bob, "bob", blockstamp(1)), 1050)
+            .expect("Fail to add identity");
+        let datas = CheckDatas {
+            currency: &currency,
+            dbs: &dbs,
+            pool: &pool,
+            state,
+        };
+        assert_eq!(
+            Err(DocumentRefusal::UidAlreadyUsed),
+            check_identity(
+                &datas,
+                &gen_idty(
+                    dup_crypto_tests_tools::mocks::pubkey('C'),
+                    "bob",
+                    blockstamp(1)
+                )
+            )
+        );
+    }
+
+    #[test]
+    fn test_check_certification() {
+        let (dbs, state) = gen_dbs();
+        let mut pool = MemPool::new(10, 10);
+        let currency = CurrencyName::default();
+        let alice = dup_crypto_tests_tools::mocks::pubkey('A');
+        let bob = dup_crypto_tests_tools::mocks::pubkey('B');
+        {
+            let datas = CheckDatas {
+                currency: &currency,
+                dbs: &dbs,
+                pool: &pool,
+                state,
+            };
+            assert_eq!(
+                Err(DocumentRefusal::SelfCertification),
+                check_certification(&datas, &gen_cert(alice, alice, blockstamp(2)))
+            );
+            assert_eq!(
+                Err(DocumentRefusal::CertIssuerNotMember),
+                check_certification(&datas, &gen_cert(bob, alice, blockstamp(2)))
+            );
+            assert_eq!(
+                Err(DocumentRefusal::UnknownIdentity(bob)),
+                check_certification(&datas, &gen_cert(alice, bob, blockstamp(2)))
+            );
+        }
+        // Bob is a pending identity
+        pool.add_identity(gen_idty(bob, "bob", blockstamp(1)), 1050)
+            .expect("Fail to add identity");
+        let cert = gen_cert(alice, bob, blockstamp(2));
+        {
+            let datas = CheckDatas {
+                currency: &currency,
+                dbs: &dbs,
+                pool: &pool,
+                state,
+            };
+            assert_eq!(Ok(1100), check_certification(&datas, &cert));
+        }
+        pool.add_certification(cert.clone(), 1100)
+            .expect("Fail to add certification");
+        let datas = CheckDatas {
+            currency: &currency,
+            dbs: &dbs,
+            pool: &pool,
+            state,
+        };
+        assert_eq!(
+            Err(DocumentRefusal::AlreadyInPool),
+            check_certification(&datas, &cert)
+        );
+    }
+}
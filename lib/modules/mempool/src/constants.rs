@@ -0,0 +1,26 @@
+//  Copyright (C) 2018  The Duniter Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+/// Module name
+pub static MODULE_NAME: &'static str = "mempool";
+
+/// Default maximum number of wot documents (identities, memberships, certifications and revocations) in pool
+pub static DEFAULT_WOT_POOL_MAX_SIZE: &'static usize = &10_000;
+
+/// Default maximum number of transactions in pool
+pub static DEFAULT_TX_POOL_MAX_SIZE: &'static usize = &5_000;
+
+/// Frequency of the mempool main loop when it does not receive any message
+pub static MEMPOOL_LOOP_FREQ_IN_MS: &'static u64 = &1_000;
@@ -0,0 +1,123 @@
+//  Copyright (C) 2018  The Duniter Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Sub-module managing the events received and sent by the mempool.
+
+use crate::*;
+use dubp_documents::documents::block::{BlockDocument, TxDocOrTxHash};
+use dubp_documents::text_document_traits::TextDocumentFormat;
+use durs_network::documents::BlockchainDocument;
+use std::ops::Deref;
+
+/// Receive an event from another module
+pub fn receive_event(datas: &mut MempoolModuleDatas, event_content: &DursEvent) {
+    match *event_content {
+        DursEvent::BlockchainEvent(ref blockchain_event) => match *blockchain_event.deref() {
+            BlockchainEvent::StackUpValidBlock(ref block) => {
+                datas.pool.remove_written_docs(block);
+                datas.pool.remove_expired_docs(block.median_time);
+                datas.expected_blockstamp = Some(block.blockstamp());
+            }
+            BlockchainEvent::RevertBlocks(ref reorg) => {
+                for block in &reorg.reverted_blocks {
+                    datas.reverted_docs.extend(get_block_pool_docs(block));
+                }
+                datas.expected_blockstamp = Some(reorg.fork_point);
+            }
+            _ => {}
+        },
+        DursEvent::NetworkEvent(NetworkEvent::ReceiveDocuments(ref network_docs)) => {
+            for network_doc in network_docs {
+                if let Some(doc) = network_doc_to_dubp_doc(network_doc) {
+                    datas.receive_document(doc);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Convert a network document into a pool document (blocks are not pool documents)
+fn network_doc_to_dubp_doc(network_doc: &BlockchainDocument) -> Option<DUBPDocument> {
+    match *network_doc {
+        BlockchainDocument::Block(_) => None,
+        BlockchainDocument::Identity(ref idty) => {
+            Some(DUBPDocument::Identity(idty.deref().clone()))
+        }
+        BlockchainDocument::Membership(ref ms) => {
+            Some(DUBPDocument::Membership(ms.deref().clone()))
+        }
+        BlockchainDocument::Certification(ref cert) => {
+            Some(DUBPDocument::Certification(cert.clone()))
+        }
+        BlockchainDocument::Revocation(ref revocation) => {
+            Some(DUBPDocument::Revocation(revocation.clone()))
+        }
+        BlockchainDocument::Transaction(ref tx) => Some(DUBPDocument::Transaction(tx.clone())),
+    }
+}
+
+/// Get the documents of a block that can be stored in pool again
+/// (compact documents are lost)
+fn get_block_pool_docs(block: &BlockDocument) -> Vec<DUBPDocument> {
+    let mut docs: Vec<DUBPDocument> = block
+        .identities
+        .iter()
+        .cloned()
+        .map(DUBPDocument::Identity)
+        .collect();
+    docs.extend(
+        block
+            .joiners
+            .iter()
+            .chain(block.actives.iter())
+            .chain(block.leavers.iter())
+            .cloned()
+            .map(DUBPDocument::Membership),
+    );
+    for cert in &block.certifications {
+        if let TextDocumentFormat::Complete(ref cert) = *cert {
+            docs.push(DUBPDocument::Certification(Box::new(cert.clone())));
+        }
+    }
+    for revocation in &block.revoked {
+        if let TextDocumentFormat::Complete(ref revocation) = *revocation {
+            docs.push(DUBPDocument::Revocation(Box::new(revocation.clone())));
+        }
+    }
+    for tx in &block.transactions {
+        if let TxDocOrTxHash::TxDoc(ref tx_doc) = *tx {
+            docs.push(DUBPDocument::Transaction(tx_doc.clone()));
+        }
+    }
+    docs
+}
+
+/// Send the event of a new document stored in pool
+pub fn send_event(datas: &MempoolModuleDatas, doc: DUBPDocument) {
+    let module_event = if let DUBPDocument::Transaction(_) = doc {
+        ModuleEvent::NewTxinPool
+    } else {
+        ModuleEvent::NewWotDocInPool
+    };
+    datas
+        .router_sender
+        .send(RouterThreadMessage::ModuleMessage(DursMsg::Event {
+            event_from: ModuleStaticName(MODULE_NAME),
+            event_type: module_event,
+            event_content: DursEvent::MemPoolEvent(MemPoolEvent::StoreNewDocInPool(Box::new(doc))),
+        }))
+        .unwrap_or_else(|_| fatal_error!("Fail to send MemPoolEvent to router"));
+}
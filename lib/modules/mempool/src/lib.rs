@@ -0,0 +1,292 @@
+//  Copyright (C) 2018  The Duniter Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Mempool module: checks the pending documents (identities, memberships, certifications,
+//! revocations and transactions) and keeps them until they are written in a block or expire.
+
+#![deny(
+    missing_docs,
+    missing_debug_implementations,
+    missing_copy_implementations,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_import_braces,
+    unused_qualifications
+)]
+
+#[macro_use]
+extern crate log;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate structopt;
+
+pub mod checker;
+mod constants;
+mod events;
+pub mod pool;
+mod requests;
+
+use crate::checker::*;
+use crate::constants::*;
+use crate::pool::MemPool;
+use dubp_documents::documents::DUBPDocument;
+use dubp_documents::*;
+use durs_common_tools::fatal_error;
+use durs_common_tools::traits::merge::Merge;
+use durs_conf::DuRsConf;
+use durs_message::events::*;
+use durs_message::requests::DursReqContent;
+use durs_message::*;
+use durs_module::*;
+use durs_network::events::NetworkEvent;
+use std::sync::mpsc;
+use std::time::Duration;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+/// Mempool Configuration
+pub struct MempoolConf {
+    /// Maximum number of wot documents in pool
+    pub wot_pool_max_size: usize,
+    /// Maximum number of transactions in pool
+    pub tx_pool_max_size: usize,
+}
+
+impl Default for MempoolConf {
+    fn default() -> Self {
+        MempoolConf {
+            wot_pool_max_size: *DEFAULT_WOT_POOL_MAX_SIZE,
+            tx_pool_max_size: *DEFAULT_TX_POOL_MAX_SIZE,
+        }
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// Mempool user Configuration
+pub struct MempoolUserConf {
+    /// Maximum number of wot documents in pool
+    pub wot_pool_max_size: Option<usize>,
+    /// Maximum number of transactions in pool
+    pub tx_pool_max_size: Option<usize>,
+}
+
+impl Merge for MempoolUserConf {
+    fn merge(self, other: Self) -> Self {
+        MempoolUserConf {
+            wot_pool_max_size: self.wot_pool_max_size.or(other.wot_pool_max_size),
+            tx_pool_max_size: self.tx_pool_max_size.or(other.tx_pool_max_size),
+        }
+    }
+}
+
+#[derive(StructOpt, Debug, Copy, Clone)]
+#[structopt(
+    name = "mempool",
+    raw(setting = "structopt::clap::AppSettings::ColoredHelp")
+)]
+/// Mempool subcommand options
+pub struct MempoolOpt {}
+
+#[derive(Debug, Copy, Clone)]
+/// Mempool module
+pub struct MempoolModule {}
+
+impl Default for MempoolModule {
+    fn default() -> MempoolModule {
+        MempoolModule {}
+    }
+}
+
+#[derive(Debug)]
+/// Data that the mempool module needs to cache
+pub struct MempoolModuleDatas {
+    /// Router sender
+    pub router_sender: mpsc::Sender<RouterThreadMessage<DursMsg>>,
+    /// Local currency
+    pub currency: CurrencyName,
+    /// Databases (written by the blockchain module)
+    pub dbs: MemPoolDBs,
+    /// State of the local blockchain (`None` if the local blockchain is empty)
+    pub state: Option<BlockchainState>,
+    /// Current blockstamp announced by the blockchain module, not yet reached by the databases
+    pub expected_blockstamp: Option<Blockstamp>,
+    /// Documents of the reverted blocks, to check again once the databases are up to date
+    pub reverted_docs: Vec<DUBPDocument>,
+    /// Pending documents
+    pub pool: MemPool,
+}
+
+impl MempoolModuleDatas {
+    /// Reload databases until they reach the current block announced by the blockchain module
+    pub fn refresh_state(&mut self) {
+        if let Some(expected_blockstamp) = self.expected_blockstamp {
+            // The blockchain module may not have saved its databases yet
+            if self.dbs.load().is_err() {
+                return;
+            }
+            match BlockchainState::read(&self.dbs) {
+                Ok(state) => {
+                    self.state = state;
+                    if state.map(|s| s.current_blockstamp) == Some(expected_blockstamp) {
+                        self.expected_blockstamp = None;
+                        let reverted_docs = std::mem::replace(&mut self.reverted_docs, vec![]);
+                        for doc in reverted_docs {
+                            self.receive_document(doc);
+                        }
+                    }
+                }
+                Err(e) => warn!("Mempool: fail to read databases: {:?}", e),
+            }
+        }
+    }
+    /// Check a document and store it in pool
+    pub fn receive_document(&mut self, doc: DUBPDocument) {
+        match self.check_and_store_document(doc.clone()) {
+            Ok(()) => events::send_event(self, doc),
+            Err(refusal) => debug!("Mempool: refuse document: {:?}", refusal),
+        }
+    }
+    fn check_and_store_document(&mut self, doc: DUBPDocument) -> Result<(), DocumentRefusal> {
+        let state = self.state.ok_or(DocumentRefusal::NoBlockchain)?;
+        let expire_on = check_document(
+            &CheckDatas {
+                currency: &self.currency,
+                dbs: &self.dbs,
+                pool: &self.pool,
+                state,
+            },
+            &doc,
+        )?;
+        match doc {
+            DUBPDocument::Block(_) => Err(DocumentRefusal::NotPoolDocument),
+            DUBPDocument::Identity(idty) => self.pool.add_identity(idty, expire_on),
+            DUBPDocument::Membership(ms) => self.pool.add_membership(ms, expire_on),
+            DUBPDocument::Certification(cert) => self.pool.add_certification(*cert, expire_on),
+            DUBPDocument::Revocation(revocation) => {
+                self.pool.add_revocation(*revocation, expire_on)
+            }
+            DUBPDocument::Transaction(tx) => self.pool.add_transaction(*tx, expire_on),
+        }
+    }
+}
+
+impl DursModule<DuRsConf, DursMsg> for MempoolModule {
+    type ModuleUserConf = MempoolUserConf;
+    type ModuleConf = MempoolConf;
+    type ModuleOpt = MempoolOpt;
+
+    fn name() -> ModuleStaticName {
+        ModuleStaticName(MODULE_NAME)
+    }
+    fn priority() -> ModulePriority {
+        ModulePriority::Essential()
+    }
+    fn ask_required_keys() -> RequiredKeys {
+        RequiredKeys::None()
+    }
+    fn generate_module_conf(
+        _global_conf: &<DuRsConf as DursConfTrait>::GlobalConf,
+        module_user_conf: Option<Self::ModuleUserConf>,
+    ) -> Result<(Self::ModuleConf, Option<Self::ModuleUserConf>), ModuleConfError> {
+        let mut conf = MempoolConf::default();
+
+        if let Some(module_user_conf) = module_user_conf {
+            if let Some(wot_pool_max_size) = module_user_conf.wot_pool_max_size {
+                conf.wot_pool_max_size = wot_pool_max_size;
+            }
+            if let Some(tx_pool_max_size) = module_user_conf.tx_pool_max_size {
+                conf.tx_pool_max_size = tx_pool_max_size;
+            }
+        }
+
+        Ok((conf, module_user_conf))
+    }
+    fn start(
+        soft_meta_datas: &SoftwareMetaDatas<DuRsConf>,
+        _keys: RequiredKeysContent,
+        conf: MempoolConf,
+        router_sender: mpsc::Sender<RouterThreadMessage<DursMsg>>,
+    ) -> Result<(), failure::Error> {
+        // Create module channel
+        let (module_sender, module_receiver) = mpsc::channel();
+
+        // Registration with the rooter
+        if router_sender
+            .send(RouterThreadMessage::ModuleRegistration {
+                static_name: ModuleStaticName(MODULE_NAME),
+                sender: module_sender,
+                roles: vec![ModuleRole::WotPool, ModuleRole::CurrencyPool],
+                events_subscription: vec![
+                    ModuleEvent::NewWotDocFromNetwork,
+                    ModuleEvent::NewTxFromNetwork,
+                    ModuleEvent::NewValidBlock,
+                    ModuleEvent::RevertBlocks,
+                ],
+                reserved_apis_parts: vec![],
+                endpoints: vec![],
+            })
+            .is_err()
+        {
+            fatal_error!("Mempool module fail to send registration to router !")
+        }
+
+        // Open databases
+        let currency = soft_meta_datas.conf.currency();
+        let dbs_path =
+            durs_conf::get_blockchain_db_path(soft_meta_datas.profile_path.clone(), &currency);
+        let dbs = MemPoolDBs::open(Some(&dbs_path));
+        let state = BlockchainState::read(&dbs)
+            .unwrap_or_else(|_| fatal_error!("Mempool: fail to read databases !"));
+
+        let mut datas = MempoolModuleDatas {
+            router_sender,
+            currency,
+            dbs,
+            state,
+            expected_blockstamp: None,
+            reverted_docs: Vec::new(),
+            pool: MemPool::new(conf.wot_pool_max_size, conf.tx_pool_max_size),
+        };
+
+        loop {
+            match module_receiver.recv_timeout(Duration::from_millis(*MEMPOOL_LOOP_FREQ_IN_MS)) {
+                Ok(durs_message) => match durs_message {
+                    DursMsg::Stop => break,
+                    DursMsg::Event {
+                        ref event_content, ..
+                    } => events::receive_event(&mut datas, event_content),
+                    DursMsg::Request {
+                        req_from,
+                        req_id,
+                        req_content: DursReqContent::MemPoolRequest(req),
+                        ..
+                    } => requests::receive_req(&datas, req_from, req_id, req),
+                    _ => {} // Others DursMsg variants
+                },
+                Err(e) => match e {
+                    mpsc::RecvTimeoutError::Disconnected => {
+                        fatal_error!("Disconnected mempool module !");
+                    }
+                    mpsc::RecvTimeoutError::Timeout => {}
+                },
+            }
+            datas.refresh_state();
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,463 @@
+//  Copyright (C) 2018  The Duniter Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Storage of the pending documents, waiting to be written in a block.
+
+use crate::checker::DocumentRefusal;
+use dubp_documents::documents::block::{BlockDocument, TxDocOrTxHash};
+use dubp_documents::documents::certification::CertificationDocument;
+use dubp_documents::documents::identity::IdentityDocument;
+use dubp_documents::documents::membership::MembershipDocument;
+use dubp_documents::documents::revocation::RevocationDocument;
+use dubp_documents::documents::transaction::TransactionDocument;
+use dubp_documents::Document;
+use dup_crypto::hashs::Hash;
+use dup_crypto::keys::PubKey;
+use durs_blockchain_dal::entities::sources::SourceIndexV10;
+use durs_message::responses::{PendingDocs, PendingIdtyDatas};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, PartialEq)]
+/// Document waiting to be written in a block
+pub struct PendingDoc<D> {
+    /// Document
+    pub doc: D,
+    /// Median time beyond which the document can no longer be written
+    pub expire_on: u64,
+}
+
+/// Compute the hash of an identity (sha256 of uid, blockstamp and pubkey)
+pub fn identity_hash(idty: &IdentityDocument) -> Hash {
+    Hash::compute_str(&format!(
+        "{}{}{}",
+        idty.username(),
+        idty.blockstamp(),
+        idty.issuers()[0]
+    ))
+}
+
+/// Get the hash of a transaction
+pub fn tx_hash(tx: &TransactionDocument) -> Hash {
+    if let Some(hash) = tx.get_hash_opt() {
+        hash
+    } else {
+        tx.clone().compute_hash()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// Pool of pending documents
+pub struct MemPool {
+    /// Maximum number of wot documents
+    wot_max_size: usize,
+    /// Maximum number of transactions
+    tx_max_size: usize,
+    /// Pending identities indexed by pubkey
+    identities: HashMap<PubKey, PendingDoc<IdentityDocument>>,
+    /// Pending memberships indexed by issuer
+    memberships: HashMap<PubKey, Vec<PendingDoc<MembershipDocument>>>,
+    /// Pending certifications indexed by target
+    certifications: HashMap<PubKey, Vec<PendingDoc<CertificationDocument>>>,
+    /// Pending revocations indexed by revoked pubkey
+    revocations: HashMap<PubKey, PendingDoc<RevocationDocument>>,
+    /// Pending transactions indexed by hash
+    transactions: HashMap<Hash, PendingDoc<TransactionDocument>>,
+}
+
+impl MemPool {
+    /// Create an empty pool
+    pub fn new(wot_max_size: usize, tx_max_size: usize) -> MemPool {
+        MemPool {
+            wot_max_size,
+            tx_max_size,
+            ..MemPool::default()
+        }
+    }
+    /// Number of wot documents in pool
+    pub fn wot_docs_count(&self) -> usize {
+        self.identities.len()
+            + self.memberships.values().map(Vec::len).sum::<usize>()
+            + self.certifications.values().map(Vec::len).sum::<usize>()
+            + self.revocations.len()
+    }
+    /// Number of transactions in pool
+    pub fn txs_count(&self) -> usize {
+        self.transactions.len()
+    }
+    /// Get pending identity of a pubkey
+    pub fn get_identity(&self, pubkey: &PubKey) -> Option<&IdentityDocument> {
+        self.identities.get(pubkey).map(|p| &p.doc)
+    }
+    /// Check if an uid is used by a pending identity
+    pub fn uid_used(&self, uid: &str) -> bool {
+        self.identities.values().any(|p| p.doc.username() == uid)
+    }
+    /// Check if a membership is pending
+    pub fn contains_membership(&self, ms: &MembershipDocument) -> bool {
+        if let Some(memberships) = self.memberships.get(&ms.issuers()[0]) {
+            memberships.iter().any(|p| p.doc == *ms)
+        } else {
+            false
+        }
+    }
+    /// Check if a certification from `issuer` to `target` is pending
+    pub fn contains_certification(&self, issuer: &PubKey, target: &PubKey) -> bool {
+        if let Some(certs) = self.certifications.get(target) {
+            certs.iter().any(|p| p.doc.issuers()[0] == *issuer)
+        } else {
+            false
+        }
+    }
+    /// Check if the revocation of an identity is pending
+    pub fn contains_revocation(&self, pubkey: &PubKey) -> bool {
+        self.revocations.contains_key(pubkey)
+    }
+    /// Check if a transaction is pending
+    pub fn contains_tx(&self, hash: &Hash) -> bool {
+        self.transactions.contains_key(hash)
+    }
+    /// Check if a source is consumed by a pending transaction
+    pub fn is_source_spent(&self, source: SourceIndexV10) -> bool {
+        self.transactions.values().any(|p| {
+            p.doc
+                .get_inputs()
+                .iter()
+                .any(|input| SourceIndexV10::from(*input) == source)
+        })
+    }
+    fn check_wot_pool_size(&self) -> Result<(), DocumentRefusal> {
+        if self.wot_docs_count() >= self.wot_max_size {
+            Err(DocumentRefusal::PoolFull)
+        } else {
+            Ok(())
+        }
+    }
+    /// Add an identity in pool
+    pub fn add_identity(
+        &mut self,
+        idty: IdentityDocument,
+        expire_on: u64,
+    ) -> Result<(), DocumentRefusal> {
+        let pubkey = idty.issuers()[0];
+        if self.identities.contains_key(&pubkey) {
+            return Err(DocumentRefusal::AlreadyInPool);
+        }
+        self.check_wot_pool_size()?;
+        self.identities.insert(
+            pubkey,
+            PendingDoc {
+                doc: idty,
+                expire_on,
+            },
+        );
+        Ok(())
+    }
+    /// Add a membership in pool
+    pub fn add_membership(
+        &mut self,
+        ms: MembershipDocument,
+        expire_on: u64,
+    ) -> Result<(), DocumentRefusal> {
+        if self.contains_membership(&ms) {
+            return Err(DocumentRefusal::AlreadyInPool);
+        }
+        self.check_wot_pool_size()?;
+        self.memberships
+            .entry(ms.issuers()[0])
+            .or_insert_with(Vec::new)
+            .push(PendingDoc { doc: ms, expire_on });
+        Ok(())
+    }
+    /// Add a certification in pool
+    pub fn add_certification(
+        &mut self,
+        cert: CertificationDocument,
+        expire_on: u64,
+    ) -> Result<(), DocumentRefusal> {
+        if self.contains_certification(&cert.issuers()[0], cert.target()) {
+            return Err(DocumentRefusal::AlreadyInPool);
+        }
+        self.check_wot_pool_size()?;
+        self.certifications
+            .entry(*cert.target())
+            .or_insert_with(Vec::new)
+            .push(PendingDoc {
+                doc: cert,
+                expire_on,
+            });
+        Ok(())
+    }
+    /// Add a revocation in pool
+    pub fn add_revocation(
+        &mut self,
+        revocation: RevocationDocument,
+        expire_on: u64,
+    ) -> Result<(), DocumentRefusal> {
+        let pubkey = revocation.issuers()[0];
+        if self.revocations.contains_key(&pubkey) {
+            return Err(DocumentRefusal::AlreadyInPool);
+        }
+        self.check_wot_pool_size()?;
+        self.revocations.insert(
+            pubkey,
+            PendingDoc {
+                doc: revocation,
+                expire_on,
+            },
+        );
+        Ok(())
+    }
+    /// Add a transaction in pool
+    pub fn add_transaction(
+        &mut self,
+        tx: TransactionDocument,
+        expire_on: u64,
+    ) -> Result<(), DocumentRefusal> {
+        let hash = tx_hash(&tx);
+        if self.transactions.contains_key(&hash) {
+            return Err(DocumentRefusal::AlreadyInPool);
+        }
+        if self.transactions.len() >= self.tx_max_size {
+            return Err(DocumentRefusal::PoolFull);
+        }
+        self.transactions
+            .insert(hash, PendingDoc { doc: tx, expire_on });
+        Ok(())
+    }
+    /// Remove the documents written in a block
+    /// (and the transactions that consume the same sources)
+    pub fn remove_written_docs(&mut self, block: &BlockDocument) {
+        for idty in &block.identities {
+            self.identities.remove(&idty.issuers()[0]);
+        }
+        for ms in block
+            .joiners
+            .iter()
+            .chain(block.actives.iter())
+            .chain(block.leavers.iter())
+        {
+            self.memberships.remove(&ms.issuers()[0]);
+        }
+        for cert in &block.certifications {
+            let compact_cert = cert.to_compact_document();
+            if let Some(certs) = self.certifications.get_mut(&compact_cert.target) {
+                certs.retain(|p| p.doc.issuers()[0] != compact_cert.issuer);
+            }
+        }
+        for revocation in &block.revoked {
+            self.revocations
+                .remove(&revocation.to_compact_document().issuer);
+        }
+        let mut consumed_sources = HashSet::new();
+        for tx in &block.transactions {
+            match *tx {
+                TxDocOrTxHash::TxDoc(ref tx_doc) => {
+                    self.transactions.remove(&tx_hash(tx_doc));
+                    consumed_sources.extend(
+                        tx_doc
+                            .get_inputs()
+                            .iter()
+                            .map(|input| SourceIndexV10::from(*input)),
+                    );
+                }
+                TxDocOrTxHash::TxHash(ref hash) => {
+                    self.transactions.remove(hash);
+                }
+            }
+        }
+        if !consumed_sources.is_empty() {
+            self.transactions.retain(|_, p| {
+                !p.doc
+                    .get_inputs()
+                    .iter()
+                    .any(|input| consumed_sources.contains(&SourceIndexV10::from(*input)))
+            });
+        }
+        self.remove_empty_entries();
+    }
+    /// Remove the documents that can no longer be written
+    pub fn remove_expired_docs(&mut self, median_time: u64) {
+        self.identities.retain(|_, p| p.expire_on >= median_time);
+        for memberships in self.memberships.values_mut() {
+            memberships.retain(|p| p.expire_on >= median_time);
+        }
+        for certs in self.certifications.values_mut() {
+            certs.retain(|p| p.expire_on >= median_time);
+        }
+        self.revocations.retain(|_, p| p.expire_on >= median_time);
+        self.transactions.retain(|_, p| p.expire_on >= median_time);
+        self.remove_empty_entries();
+    }
+    fn remove_empty_entries(&mut self) {
+        self.memberships.retain(|_, v| !v.is_empty());
+        self.certifications.retain(|_, v| !v.is_empty());
+    }
+    /// Get pending wot datas of an identity
+    pub fn get_pending_idty_datas(
+        &self,
+        idty: IdentityDocument,
+        with_certs: bool,
+    ) -> PendingIdtyDatas {
+        let pubkey = idty.issuers()[0];
+        let certs: Vec<CertificationDocument> = self
+            .certifications
+            .get(&pubkey)
+            .map(|certs| certs.iter().map(|p| p.doc.clone()).collect())
+            .unwrap_or_default();
+        PendingIdtyDatas {
+            idty,
+            memberships: self
+                .memberships
+                .get(&pubkey)
+                .map(|memberships| memberships.iter().map(|p| p.doc.clone()).collect())
+                .unwrap_or_default(),
+            certs_count: certs.len(),
+            certs: if with_certs { certs } else { vec![] },
+            revocation: self.revocations.get(&pubkey).map(|p| p.doc.clone()),
+        }
+    }
+    /// Get pending wot datas of pending identities (at most `limit` identities)
+    pub fn get_pending_identities(
+        &self,
+        limit: usize,
+        with_certs: bool,
+    ) -> HashMap<Hash, PendingIdtyDatas> {
+        self.identities
+            .values()
+            .take(limit)
+            .map(|p| {
+                (
+                    identity_hash(&p.doc),
+                    self.get_pending_idty_datas(p.doc.clone(), with_certs),
+                )
+            })
+            .collect()
+    }
+    /// Get all pending documents
+    pub fn get_all_docs(&self) -> PendingDocs {
+        PendingDocs {
+            identities: self.identities.values().map(|p| p.doc.clone()).collect(),
+            memberships: self
+                .memberships
+                .values()
+                .flatten()
+                .map(|p| p.doc.clone())
+                .collect(),
+            certifications: self
+                .certifications
+                .values()
+                .flatten()
+                .map(|p| p.doc.clone())
+                .collect(),
+            revocations: self.revocations.values().map(|p| p.doc.clone()).collect(),
+            transactions: self.transactions.values().map(|p| p.doc.clone()).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dubp_documents::documents::membership::MembershipType;
+    use dubp_documents::*;
+
+    fn blockstamp(number: u32) -> Blockstamp {
+        Blockstamp {
+            id: BlockNumber(number),
+            hash: BlockHash(dup_crypto_tests_tools::mocks::hash('A')),
+        }
+    }
+
+    #[test]
+    fn test_add_and_expire_docs() {
+        let mut pool = MemPool::new(2, 1);
+        let pubkey = dup_crypto_tests_tools::mocks::pubkey('A');
+        let idty =
+            dubp_documents_tests_tools::mocks::identity::gen_mock_idty(pubkey, BlockNumber(0));
+
+        assert_eq!(Ok(()), pool.add_identity(idty.clone(), 100));
+        assert_eq!(
+            Err(DocumentRefusal::AlreadyInPool),
+            pool.add_identity(idty.clone(), 100)
+        );
+        assert_eq!(Some(&idty), pool.get_identity(&pubkey));
+        assert!(pool.uid_used(""));
+
+        let ms = dubp_documents_tests_tools::mocks::membership::gen_mock_membership(
+            pubkey,
+            MembershipType::In(),
+            blockstamp(1),
+            blockstamp(0),
+        );
+        assert_eq!(Ok(()), pool.add_membership(ms.clone(), 200));
+        assert_eq!(2, pool.wot_docs_count());
+
+        // The pool is full
+        let other_ms = dubp_documents_tests_tools::mocks::membership::gen_mock_membership(
+            dup_crypto_tests_tools::mocks::pubkey('B'),
+            MembershipType::In(),
+            blockstamp(1),
+            blockstamp(0),
+        );
+        assert_eq!(
+            Err(DocumentRefusal::PoolFull),
+            pool.add_membership(other_ms, 200)
+        );
+
+        let datas = pool.get_pending_identities(10, true);
+        assert_eq!(1, datas.len());
+        let idty_datas = &datas[&identity_hash(&idty)];
+        assert_eq!(vec![ms.clone()], idty_datas.memberships);
+        assert_eq!(0, idty_datas.certs_count);
+        let all_docs = pool.get_all_docs();
+        assert_eq!(vec![idty.clone()], all_docs.identities);
+        assert_eq!(vec![ms.clone()], all_docs.memberships);
+
+        // The identity expires first
+        pool.remove_expired_docs(150);
+        assert_eq!(None, pool.get_identity(&pubkey));
+        assert!(pool.contains_membership(&ms));
+        pool.remove_expired_docs(201);
+        assert_eq!(0, pool.wot_docs_count());
+    }
+
+    #[test]
+    fn test_remove_written_docs() {
+        let mut pool = MemPool::new(10, 10);
+        let pubkey = dup_crypto_tests_tools::mocks::pubkey('A');
+        let idty =
+            dubp_documents_tests_tools::mocks::identity::gen_mock_idty(pubkey, BlockNumber(0));
+        let ms = dubp_documents_tests_tools::mocks::membership::gen_mock_membership(
+            pubkey,
+            MembershipType::In(),
+            blockstamp(1),
+            blockstamp(0),
+        );
+        pool.add_identity(idty.clone(), 100)
+            .expect("Fail to add identity");
+        pool.add_membership(ms.clone(), 100)
+            .expect("Fail to add membership");
+
+        let mut block = dubp_documents_tests_tools::mocks::gen_empty_timed_block(
+            blockstamp(2),
+            0,
+            Hash::default(),
+        );
+        block.identities = vec![idty];
+        block.joiners = vec![ms];
+        pool.remove_written_docs(&block);
+        assert_eq!(0, pool.wot_docs_count());
+    }
+}
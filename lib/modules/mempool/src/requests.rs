@@ -0,0 +1,98 @@
+//  Copyright (C) 2018  The Duniter Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Sub-module answering the requests of the other modules.
+
+use crate::*;
+use durs_message::requests::MemPoolRequest;
+use durs_message::responses::{DursResContent, MemPoolResponse};
+
+/// Answer a request of another module
+pub fn receive_req(
+    datas: &MempoolModuleDatas,
+    req_from: ModuleStaticName,
+    req_id: ModuleReqId,
+    req: MemPoolRequest,
+) {
+    match req {
+        MemPoolRequest::AllPendingIdentities(limit) => send_response(
+            datas,
+            req_from,
+            req_id,
+            MemPoolResponse::AllPendingIdentities(
+                req_id,
+                datas.pool.get_pending_identities(limit, true),
+            ),
+        ),
+        MemPoolRequest::AllPendingIdentitiesWithoutCerts(limit) => send_response(
+            datas,
+            req_from,
+            req_id,
+            MemPoolResponse::AllPendingIdentitiesWithoutCerts(
+                req_id,
+                datas.pool.get_pending_identities(limit, false),
+            ),
+        ),
+        MemPoolRequest::PendingWotDatasForPubkey(pubkey) => {
+            let idty_opt = if let Some(idty) = datas.pool.get_identity(&pubkey) {
+                Some(idty.clone())
+            } else {
+                durs_blockchain_dal::readers::identity::get_identity(
+                    &datas.dbs.identities_db,
+                    &pubkey,
+                )
+                .unwrap_or_else(|_| fatal_error!("Mempool: fail to read IdentitiesDB !"))
+                .map(|dal_idty| dal_idty.idty_doc)
+            };
+            if let Some(idty) = idty_opt {
+                send_response(
+                    datas,
+                    req_from,
+                    req_id,
+                    MemPoolResponse::PendingWotDatasForPubkey(
+                        req_id,
+                        Box::new(datas.pool.get_pending_idty_datas(idty, true)),
+                    ),
+                );
+            } else {
+                debug!("Mempool: Req: not found identity {}", pubkey);
+            }
+        }
+        MemPoolRequest::AllPendingDocs => send_response(
+            datas,
+            req_from,
+            req_id,
+            MemPoolResponse::AllPendingDocs(req_id, Box::new(datas.pool.get_all_docs())),
+        ),
+    }
+}
+
+/// Send the response to a request
+fn send_response(
+    datas: &MempoolModuleDatas,
+    requester: ModuleStaticName,
+    req_id: ModuleReqId,
+    response: MemPoolResponse,
+) {
+    datas
+        .router_sender
+        .send(RouterThreadMessage::ModuleMessage(DursMsg::Response {
+            res_from: ModuleStaticName(MODULE_NAME),
+            res_to: requester,
+            req_id,
+            res_content: DursResContent::MemPoolResponse(response),
+        }))
+        .unwrap_or_else(|_| fatal_error!("Fail to send ReqRes to router"));
+}
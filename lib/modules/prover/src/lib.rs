@@ -0,0 +1,259 @@
+//  Copyright (C) 2018  The Duniter Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Prover module: computes the proof of work of the blocks generated by the local node.
+
+#![deny(
+    missing_docs,
+    missing_debug_implementations,
+    missing_copy_implementations,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_import_braces,
+    unused_qualifications
+)]
+
+#[macro_use]
+extern crate log;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate structopt;
+
+mod constants;
+pub mod pow;
+
+use crate::constants::*;
+use crate::pow::ProverJob;
+use dup_crypto::keys::*;
+use durs_common_tools::fatal_error;
+use durs_common_tools::traits::merge::Merge;
+use durs_conf::{DuRsConf, ResourceUsage};
+use durs_message::events::*;
+use durs_message::requests::{DursReqContent, ProverRequest};
+use durs_message::*;
+use durs_module::*;
+use std::sync::mpsc;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+/// Prover Configuration
+pub struct ProverConf {
+    /// Number of cpus available for the proof of work
+    pub cpus: usize,
+}
+
+impl Default for ProverConf {
+    fn default() -> Self {
+        ProverConf {
+            cpus: num_cpus::get(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// Prover user Configuration
+pub struct ProverUserConf {
+    /// Number of cpus available for the proof of work
+    pub cpus: Option<usize>,
+}
+
+impl Merge for ProverUserConf {
+    fn merge(self, other: Self) -> Self {
+        ProverUserConf {
+            cpus: self.cpus.or(other.cpus),
+        }
+    }
+}
+
+#[derive(StructOpt, Debug, Copy, Clone)]
+#[structopt(
+    name = "prover",
+    raw(setting = "structopt::clap::AppSettings::ColoredHelp")
+)]
+/// Prover subcommand options
+pub struct ProverOpt {}
+
+#[derive(Debug, Copy, Clone)]
+/// Prover module
+pub struct ProverModule {}
+
+impl Default for ProverModule {
+    fn default() -> ProverModule {
+        ProverModule {}
+    }
+}
+
+#[derive(Debug)]
+/// Data that the prover module needs to cache
+pub struct ProverModuleDatas {
+    /// Router sender
+    pub router_sender: mpsc::Sender<RouterThreadMessage<DursMsg>>,
+    /// Member keypair (`None` if the node is not a member node)
+    pub keypair: Option<KeyPairEnum>,
+    /// Number of threads used by a proof of work
+    pub threads: usize,
+    /// Proof of work in progress
+    pub job: Option<ProverJob>,
+}
+
+impl ProverModuleDatas {
+    /// Cancel the proof of work in progress
+    pub fn cancel_job(&mut self) {
+        if let Some(job) = self.job.take() {
+            job.cancel();
+        }
+    }
+    /// Start the proof of work of a block template (cancels the previous one)
+    pub fn receive_req(&mut self, req: ProverRequest) {
+        self.cancel_job();
+        let keypair = if let Some(keypair) = self.keypair {
+            keypair
+        } else {
+            warn!("Prover: can't prove a block without member keypair !");
+            return;
+        };
+        if req.block.issuers != vec![keypair.public_key()] {
+            warn!(
+                "Prover: refuse block #{}: the local node is not its issuer !",
+                req.block.number.0
+            );
+            return;
+        }
+        let difficulty = req.difficulty.max(req.block.pow_min);
+        info!(
+            "Prover: start proof of work of block #{} (difficulty {}, {} threads)",
+            req.block.number.0, difficulty, self.threads
+        );
+        self.job = Some(ProverJob::start(
+            *req.block,
+            difficulty,
+            keypair.private_key(),
+            self.threads,
+            &self.router_sender,
+        ));
+    }
+    /// Cancel the proof of work in progress when the local blockchain makes it stale
+    pub fn receive_event(&mut self, event: &DursEvent) {
+        if let DursEvent::BlockchainEvent(ref blockchain_event) = *event {
+            match **blockchain_event {
+                BlockchainEvent::StackUpValidBlock(ref block) => {
+                    if let Some(ref job) = self.job {
+                        if block.number >= job.block_number {
+                            self.cancel_job();
+                        }
+                    }
+                }
+                BlockchainEvent::RevertBlocks(_) => self.cancel_job(),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl DursModule<DuRsConf, DursMsg> for ProverModule {
+    type ModuleUserConf = ProverUserConf;
+    type ModuleConf = ProverConf;
+    type ModuleOpt = ProverOpt;
+
+    fn name() -> ModuleStaticName {
+        ModuleStaticName(MODULE_NAME)
+    }
+    fn priority() -> ModulePriority {
+        ModulePriority::Recommended()
+    }
+    fn ask_required_keys() -> RequiredKeys {
+        RequiredKeys::MemberKeyPair()
+    }
+    fn generate_module_conf(
+        _global_conf: &<DuRsConf as DursConfTrait>::GlobalConf,
+        module_user_conf: Option<Self::ModuleUserConf>,
+    ) -> Result<(Self::ModuleConf, Option<Self::ModuleUserConf>), ModuleConfError> {
+        let mut conf = ProverConf::default();
+
+        if let Some(module_user_conf) = module_user_conf {
+            if let Some(cpus) = module_user_conf.cpus {
+                conf.cpus = cpus;
+            }
+        }
+
+        Ok((conf, module_user_conf))
+    }
+    fn start(
+        soft_meta_datas: &SoftwareMetaDatas<DuRsConf>,
+        keys: RequiredKeysContent,
+        conf: ProverConf,
+        router_sender: mpsc::Sender<RouterThreadMessage<DursMsg>>,
+    ) -> Result<(), failure::Error> {
+        // Create module channel
+        let (module_sender, module_receiver) = mpsc::channel();
+
+        // Registration with the rooter
+        if router_sender
+            .send(RouterThreadMessage::ModuleRegistration {
+                static_name: ModuleStaticName(MODULE_NAME),
+                sender: module_sender,
+                roles: vec![ModuleRole::Prover],
+                events_subscription: vec![ModuleEvent::NewValidBlock, ModuleEvent::RevertBlocks],
+                reserved_apis_parts: vec![],
+                endpoints: vec![],
+            })
+            .is_err()
+        {
+            fatal_error!("Prover module fail to send registration to router !")
+        }
+
+        let keypair = if let RequiredKeysContent::MemberKeyPair(keypair) = keys {
+            keypair
+        } else {
+            None
+        };
+        let cpu_usage: ResourceUsage = soft_meta_datas
+            .conf
+            .get_global_conf()
+            .ressources_usage()
+            .cpu_usage;
+
+        let mut datas = ProverModuleDatas {
+            router_sender,
+            keypair,
+            threads: pow::threads_count(conf.cpus, cpu_usage),
+            job: None,
+        };
+
+        loop {
+            match module_receiver.recv() {
+                Ok(durs_message) => match durs_message {
+                    DursMsg::Stop => {
+                        datas.cancel_job();
+                        break;
+                    }
+                    DursMsg::Event {
+                        ref event_content, ..
+                    } => datas.receive_event(event_content),
+                    DursMsg::Request {
+                        req_content: DursReqContent::ProverRequest(req),
+                        ..
+                    } => datas.receive_req(req),
+                    _ => {} // Others DursMsg variants
+                },
+                Err(_) => fatal_error!("Disconnected prover module !"),
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,194 @@
+//  Copyright (C) 2018  The Duniter Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Nonce search: each thread tries its own sequence of nonces until the hash of the block reaches
+//! the expected difficulty or the job is cancelled.
+
+use crate::constants::*;
+use dubp_documents::documents::block::BlockDocument;
+use dubp_documents::BlockNumber;
+use dup_crypto::keys::*;
+use durs_blockchain::hash_reaches_difficulty;
+use durs_common_tools::fatal_error;
+use durs_conf::ResourceUsage;
+use durs_message::events::{DursEvent, MemPoolEvent};
+use durs_message::DursMsg;
+use durs_module::{ModuleEvent, ModuleStaticName, RouterThreadMessage};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+/// Get the number of threads to use according to the cpu usage allowed by the user
+pub fn threads_count(cpus: usize, cpu_usage: ResourceUsage) -> usize {
+    let threads = match cpu_usage {
+        ResourceUsage::Minimal => 1,
+        ResourceUsage::Medium => cpus / 2,
+        ResourceUsage::Large => cpus * 3 / 4,
+        ResourceUsage::Infinite => cpus,
+    };
+    threads.max(1)
+}
+
+/// Try the nonces `first_nonce`, `first_nonce + nonce_step`, ... until the block hash reaches
+/// `difficulty` (`None` if `stop` is set before)
+pub fn find_nonce(
+    mut block: BlockDocument,
+    difficulty: usize,
+    privkey: PrivKey,
+    first_nonce: u64,
+    nonce_step: u64,
+    stop: &AtomicBool,
+) -> Option<BlockDocument> {
+    let mut nonce = first_nonce;
+    while !stop.load(Ordering::Relaxed) {
+        block.nonce = nonce;
+        block.sign(privkey);
+        block.compute_hash();
+        let hash_hex = block.hash.map(|h| h.0.to_hex()).unwrap_or_default();
+        if hash_reaches_difficulty(&hash_hex, difficulty) {
+            return Some(block);
+        }
+        nonce += nonce_step;
+    }
+    None
+}
+
+#[derive(Debug)]
+/// Proof of work in progress
+pub struct ProverJob {
+    /// Number of the block to prove
+    pub block_number: BlockNumber,
+    /// Stop signal shared by all the threads of the job
+    stop: Arc<AtomicBool>,
+}
+
+impl ProverJob {
+    /// Start a proof of work on `threads` threads, the found block is sent to the router
+    pub fn start(
+        block: BlockDocument,
+        difficulty: usize,
+        privkey: PrivKey,
+        threads: usize,
+        router_sender: &mpsc::Sender<RouterThreadMessage<DursMsg>>,
+    ) -> ProverJob {
+        let block_number = block.number;
+        let stop = Arc::new(AtomicBool::new(false));
+        for thread_index in 0..threads {
+            let block = block.clone();
+            let stop = Arc::clone(&stop);
+            let router_sender = router_sender.clone();
+            thread::spawn(move || {
+                if let Some(block) = find_nonce(
+                    block,
+                    difficulty,
+                    privkey,
+                    thread_index as u64,
+                    threads as u64,
+                    &stop,
+                ) {
+                    // Only the first thread to find a nonce sends the block
+                    if !stop.swap(true, Ordering::SeqCst) {
+                        info!(
+                            "Prover: find proof of work of block #{} (nonce {})",
+                            block.number.0, block.nonce
+                        );
+                        send_block(&router_sender, block);
+                    }
+                }
+            });
+        }
+        ProverJob { block_number, stop }
+    }
+    /// Stop all the threads of the job
+    pub fn cancel(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Send the found block to the blockchain module
+fn send_block(router_sender: &mpsc::Sender<RouterThreadMessage<DursMsg>>, block: BlockDocument) {
+    router_sender
+        .send(RouterThreadMessage::ModuleMessage(DursMsg::Event {
+            event_from: ModuleStaticName(MODULE_NAME),
+            event_type: ModuleEvent::NewBlockFromSelf,
+            event_content: DursEvent::MemPoolEvent(MemPoolEvent::FindNextBlock(Box::new(block))),
+        }))
+        .unwrap_or_else(|_| fatal_error!("Prover: fail to send found block to router"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dubp_documents::*;
+    use dup_crypto::hashs::Hash;
+
+    fn gen_template(keypair: &ed25519::KeyPair) -> BlockDocument {
+        let mut block = dubp_documents_tests_tools::mocks::gen_empty_timed_block(
+            Blockstamp::default(),
+            0,
+            Hash::default(),
+        );
+        block.issuers = vec![PubKey::Ed25519(keypair.public_key())];
+        block.compute_inner_hash();
+        block
+    }
+
+    #[test]
+    fn test_threads_count() {
+        assert_eq!(1, threads_count(8, ResourceUsage::Minimal));
+        assert_eq!(4, threads_count(8, ResourceUsage::Medium));
+        assert_eq!(6, threads_count(8, ResourceUsage::Large));
+        assert_eq!(8, threads_count(8, ResourceUsage::Infinite));
+        assert_eq!(1, threads_count(1, ResourceUsage::Medium));
+    }
+
+    #[test]
+    fn test_find_nonce() {
+        let keypair = ed25519::KeyPairFromSeedGenerator::generate(&[0u8; 32]);
+        let privkey = PrivKey::Ed25519(keypair.private_key());
+        let block = find_nonce(
+            gen_template(&keypair),
+            20,
+            privkey,
+            0,
+            1,
+            &AtomicBool::new(false),
+        )
+        .expect("find_nonce must find a nonce");
+        assert!(block.verify_hash());
+        assert_eq!(VerificationResult::Valid(), block.verify_signatures());
+        assert!(hash_reaches_difficulty(
+            &block.hash.expect("safe unwrap").0.to_hex(),
+            20
+        ));
+    }
+
+    #[test]
+    fn test_find_nonce_cancelled() {
+        let keypair = ed25519::KeyPairFromSeedGenerator::generate(&[0u8; 32]);
+        let privkey = PrivKey::Ed25519(keypair.private_key());
+        assert_eq!(
+            None,
+            find_nonce(
+                gen_template(&keypair),
+                1_000,
+                privkey,
+                0,
+                1,
+                &AtomicBool::new(true),
+            )
+        );
+    }
+}
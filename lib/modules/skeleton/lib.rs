@@ -277,7 +277,7 @@ impl DursModule<DuRsConf, DursMsg> for SkeletonModule {
                                         BlockchainEvent::StackUpValidBlock(ref _block) => {
                                             // Do something when the node has stacked a new block at its local blockchain
                                         }
-                                        BlockchainEvent::RevertBlocks(ref _blocks) => {
+                                        BlockchainEvent::RevertBlocks(ref _reorg) => {
                                             // Do something when the node has destacked blocks from its local blockchain (roll back)
                                         }
                                         _ => {} // Do nothing for events that don't concern your module.
@@ -533,7 +533,7 @@ impl DursModule<DuRsConf, DursMsg> for TuiModule {
                         } => match *event_content {
                             DursEvent::BlockchainEvent(ref dal_event) => match *dal_event.deref() {
                                 BlockchainEvent::StackUpValidBlock(ref _block) => {}
-                                BlockchainEvent::RevertBlocks(ref _blocks) => {}
+                                BlockchainEvent::RevertBlocks(ref _reorg) => {}
                                 _ => {}
                             },
                             DursEvent::NetworkEvent(ref network_event_box) => {
@@ -66,7 +66,7 @@ pub fn receive_event(
                     })
                     .collect();
             }
-            BlockchainEvent::RevertBlocks(ref _blocks) => {}
+            BlockchainEvent::RevertBlocks(ref _reorg) => {}
             _ => {}
         }
     }
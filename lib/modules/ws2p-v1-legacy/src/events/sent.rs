@@ -34,7 +34,9 @@ pub fn send_network_event(ws2p_module: &mut WS2Pv1Module, event: NetworkEvent) {
         NetworkEvent::ConnectionStateChange(_, _, _, _) => {
             ModuleEvent::ConnectionsChangeNodeNetwork
         }
-        NetworkEvent::ReceiveBlocks(_) => ModuleEvent::NewBlockFromNetwork,
+        NetworkEvent::ReceiveBlocks(_) | NetworkEvent::SyncTarget(_, _) => {
+            ModuleEvent::NewBlockFromNetwork
+        }
         NetworkEvent::ReceiveDocuments(ref network_docs) => {
             if !network_docs.is_empty() {
                 match network_docs[0] {
@@ -27,17 +27,24 @@ impl WS2PFeatures {
         }
         true
     }
+    /// Check a flag of the first byte (all flags are disabled if there is no byte)
+    fn flag(&self, mask: u8) -> bool {
+        self.0
+            .get(0)
+            .map(|byte| byte | mask == 255u8)
+            .unwrap_or(false)
+    }
     /// Check flag DEF
     pub fn def(&self) -> bool {
-        self.0[0] | 0b1111_1110 == 255u8
+        self.flag(0b1111_1110)
     }
     /// Check flag LOW
     pub fn low(&self) -> bool {
-        self.0[0] | 0b1111_1101 == 255u8
+        self.flag(0b1111_1101)
     }
     /// Check flag ABF
     pub fn abf(&self) -> bool {
-        self.0[0] | 0b1111_1011 == 255u8
+        self.flag(0b1111_1011)
     }
     /// Check features compatibility
     pub fn check_features_compatibility(
@@ -110,6 +110,17 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_ws2p_message_inventory() {
+        test_ws2p_message(WS2Pv2MessagePayload::Inventory(vec![
+            Hash::random(),
+            Hash::random(),
+        ]));
+        test_ws2p_message(WS2Pv2MessagePayload::GetInventoryItems(
+            vec![Hash::random()],
+        ));
+    }
+
     #[test]
     fn test_ws2p_message_peers() {
         let keypair1 = keypair1();
@@ -70,4 +70,9 @@ pub enum WS2Pv2MessagePayload {
     PendingRevocations(Vec<RevocationDocument>),
     /// PENDING_TXS Message
     PendingTxs(Vec<TransactionDocument>),
+    /// INVENTORY Message: hashs of the pending documents and heads that the sender can provide
+    /// (sent instead of the full documents to the nodes that demand the LOW flow)
+    Inventory(Vec<Hash>),
+    /// GET_INVENTORY_ITEMS Message: ask the items of a previously received inventory
+    GetInventoryItems(Vec<Hash>),
 }
@@ -29,9 +29,16 @@ impl WS2Pv2SecretFlags {
         }
         true
     }
+    /// Create secret flags
+    pub fn new(low_flow_demand: bool) -> Self {
+        WS2Pv2SecretFlags(vec![if low_flow_demand { 1u8 } else { 0u8 }])
+    }
     /// Check flag LOW_FLOW_DEMAND
-    pub fn _low_flow_demand(&self) -> bool {
-        self.0[0] | 0b1111_1110 == 255u8
+    pub fn low_flow_demand(&self) -> bool {
+        self.0
+            .get(0)
+            .map(|byte| byte | 0b1111_1110 == 255u8)
+            .unwrap_or(false)
     }
 }
 
@@ -81,4 +88,12 @@ mod tests {
         };
         test_ws2p_message(WS2Pv2MessagePayload::SecretFlags(msg));
     }
+
+    #[test]
+    fn test_low_flow_demand() {
+        assert!(!WS2Pv2SecretFlags(vec![]).low_flow_demand());
+        assert!(!WS2Pv2SecretFlags::new(false).low_flow_demand());
+        assert!(WS2Pv2SecretFlags::new(false).is_empty());
+        assert!(WS2Pv2SecretFlags::new(true).low_flow_demand());
+    }
 }
@@ -0,0 +1,222 @@
+//  Copyright (C) 2018  The Duniter Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! WS2P v2 DEF feature: deflate compression of the binary frames.
+//!
+//! A compressed frame is made of the magic bytes `DEF\0`, the size of the uncompressed message
+//! (u32 little endian) and the zlib stream. The hash and the signature of the message are
+//! computed over the uncompressed bytes, so the frames that are not worth compressing can be
+//! sent raw: a raw WS2P v2 message always starts with its version (`2u32`).
+
+use crate::constants;
+use failure::Fail;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
+
+/// Magic bytes of a compressed frame
+pub static DEF_FRAME_MAGIC: &'static [u8; 4] = b"DEF\0";
+
+/// Size of the header of a compressed frame
+const DEF_FRAME_HEADER_LEN: usize = 8;
+
+#[derive(Copy, Clone, Debug, Eq, Fail, PartialEq)]
+/// Error when decompressing a frame
+pub enum WS2PDeflateError {
+    /// The frame header is truncated
+    #[fail(display = "truncated compressed frame")]
+    TruncatedFrame,
+    /// The announced size exceeds the limit
+    #[fail(display = "compressed frame too large: {} bytes", _0)]
+    TooLarge(usize),
+    /// The zlib stream is invalid
+    #[fail(display = "invalid compressed frame: corrupted zlib stream")]
+    InvalidStream,
+    /// The decompressed size does not match the announced size
+    #[fail(display = "compressed frame size mismatch")]
+    SizeMismatch,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+/// Compressor of the frames of a connection.
+/// Only messages larger than the threshold are compressed, and the threshold adapts to the
+/// compression ratio actually obtained: it increases when compression is not worth it and
+/// decreases when it is effective.
+pub struct WS2PDeflate {
+    /// Current threshold (in bytes)
+    pub threshold: usize,
+}
+
+impl Default for WS2PDeflate {
+    fn default() -> Self {
+        WS2PDeflate {
+            threshold: *constants::WS2P_DEF_INITIAL_THRESHOLD,
+        }
+    }
+}
+
+impl WS2PDeflate {
+    /// Compress the binary message if it is worth it
+    pub fn encode(&mut self, bin_msg: Vec<u8>) -> Vec<u8> {
+        if bin_msg.len() < self.threshold {
+            return bin_msg;
+        }
+        match deflate(&bin_msg) {
+            Some(compressed) => {
+                let ratio = compressed.len() * 100 / bin_msg.len();
+                if ratio > 90 {
+                    self.threshold = std::cmp::min(
+                        self.threshold.saturating_mul(2),
+                        *constants::WS2P_DEF_MAX_THRESHOLD,
+                    );
+                } else if ratio < 50 {
+                    self.threshold =
+                        std::cmp::max(self.threshold / 2, *constants::WS2P_DEF_MIN_THRESHOLD);
+                }
+                if compressed.len() + DEF_FRAME_HEADER_LEN < bin_msg.len() {
+                    let mut frame = Vec::with_capacity(DEF_FRAME_HEADER_LEN + compressed.len());
+                    frame.extend_from_slice(DEF_FRAME_MAGIC);
+                    frame.extend_from_slice(&(bin_msg.len() as u32).to_le_bytes());
+                    frame.extend(compressed);
+                    frame
+                } else {
+                    bin_msg
+                }
+            }
+            None => bin_msg,
+        }
+    }
+}
+
+/// Is the frame compressed ?
+pub fn is_compressed(frame: &[u8]) -> bool {
+    frame.starts_with(DEF_FRAME_MAGIC)
+}
+
+/// Decompress the frame if it is compressed
+pub fn decode(frame: Vec<u8>) -> Result<Vec<u8>, WS2PDeflateError> {
+    if !is_compressed(&frame) {
+        return Ok(frame);
+    }
+    if frame.len() < DEF_FRAME_HEADER_LEN {
+        return Err(WS2PDeflateError::TruncatedFrame);
+    }
+    let mut size_bytes = [0u8; 4];
+    size_bytes.copy_from_slice(&frame[4..DEF_FRAME_HEADER_LEN]);
+    let size = u32::from_le_bytes(size_bytes) as usize;
+    if size > *constants::WS2P_DEF_MAX_MSG_SIZE {
+        return Err(WS2PDeflateError::TooLarge(size));
+    }
+    let bin_msg = inflate(&frame[DEF_FRAME_HEADER_LEN..], size)?;
+    if bin_msg.len() == size {
+        Ok(bin_msg)
+    } else {
+        Err(WS2PDeflateError::SizeMismatch)
+    }
+}
+
+/// Compress datas in a zlib stream
+fn deflate(datas: &[u8]) -> Option<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(datas).ok()?;
+    encoder.finish().ok()
+}
+
+/// Decompress a zlib stream of exactly `size` bytes.
+/// The stream is read through a bounded reader, so a lying peer cannot make us allocate
+/// more than the size it announces (which is itself capped).
+fn inflate(datas: &[u8], size: usize) -> Result<Vec<u8>, WS2PDeflateError> {
+    let mut output = Vec::with_capacity(std::cmp::min(size, datas.len().saturating_mul(4)));
+    // Read one more byte than announced to detect longer streams
+    ZlibDecoder::new(datas)
+        .take(size as u64 + 1)
+        .read_to_end(&mut output)
+        .map_err(|_| WS2PDeflateError::InvalidStream)?;
+    if output.len() > size {
+        Err(WS2PDeflateError::SizeMismatch)
+    } else {
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_small_msg_not_compressed() {
+        let mut deflate = WS2PDeflate::default();
+        let bin_msg = vec![2u8, 0, 0, 0, 1, 2, 3];
+        assert_eq!(bin_msg, deflate.encode(bin_msg.clone()));
+        assert_eq!(Ok(bin_msg.clone()), decode(bin_msg));
+    }
+
+    #[test]
+    fn test_compress_and_decompress() {
+        let mut deflate = WS2PDeflate::default();
+        let mut bin_msg = vec![2u8, 0, 0, 0];
+        bin_msg.extend((0..10_000).map(|i| (i % 7) as u8));
+        let frame = deflate.encode(bin_msg.clone());
+        assert!(is_compressed(&frame));
+        assert!(frame.len() < bin_msg.len() / 10);
+        // Effective compression lowers the threshold
+        assert!(deflate.threshold < *constants::WS2P_DEF_INITIAL_THRESHOLD);
+        assert_eq!(Ok(bin_msg), decode(frame));
+    }
+
+    #[test]
+    fn test_uncompressible_msg_raises_threshold() {
+        let mut deflate = WS2PDeflate::default();
+        // Pseudo random bytes
+        let mut seed = 42u32;
+        let bin_msg: Vec<u8> = (0..4_096)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                (seed >> 16) as u8
+            })
+            .collect();
+        assert_eq!(bin_msg, deflate.encode(bin_msg.clone()));
+        assert!(deflate.threshold > *constants::WS2P_DEF_INITIAL_THRESHOLD);
+    }
+
+    #[test]
+    fn test_decode_invalid_frames() {
+        assert_eq!(
+            Err(WS2PDeflateError::TruncatedFrame),
+            decode(b"DEF\0\x01".to_vec())
+        );
+        let mut too_large = DEF_FRAME_MAGIC.to_vec();
+        too_large.extend_from_slice(&u32::max_value().to_le_bytes());
+        assert_eq!(
+            Err(WS2PDeflateError::TooLarge(u32::max_value() as usize)),
+            decode(too_large)
+        );
+        let mut invalid = DEF_FRAME_MAGIC.to_vec();
+        invalid.extend_from_slice(&10u32.to_le_bytes());
+        invalid.extend_from_slice(&[1, 2, 3]);
+        assert!(decode(invalid).is_err());
+    }
+
+    #[test]
+    fn test_decode_stream_longer_than_announced() {
+        // 1 MB of zeros announced as 1 KB: decompression must stop at the announced size
+        let compressed = deflate(&vec![0u8; 1_000_000]).expect("fail to compress");
+        let mut frame = DEF_FRAME_MAGIC.to_vec();
+        frame.extend_from_slice(&1_024u32.to_le_bytes());
+        frame.extend(compressed);
+        assert_eq!(Err(WS2PDeflateError::SizeMismatch), decode(frame));
+    }
+}
@@ -51,3 +51,26 @@ pub enum WS2PConnectionState {
     /// Connection successfully established
     Established,
 }
+
+impl WS2PConnectionState {
+    /// Get connection state code (shared with WS2P v1 connections states codes)
+    pub fn to_u32(self) -> u32 {
+        match self {
+            WS2PConnectionState::NeverTry => 0,
+            WS2PConnectionState::TryToOpenWS => 1,
+            WS2PConnectionState::WSError => 2,
+            WS2PConnectionState::TryToSendConnectMsg => 3,
+            WS2PConnectionState::Unreachable => 4,
+            WS2PConnectionState::WaitingConnectMsg => 5,
+            WS2PConnectionState::NoResponse | WS2PConnectionState::NegociationTimeout => 6,
+            WS2PConnectionState::ConnectMessOk => 7,
+            WS2PConnectionState::OkMsgOkWaitingAckMsg => 8,
+            WS2PConnectionState::AckMsgOk
+            | WS2PConnectionState::SecretFlagsOkWaitingAckMsg
+            | WS2PConnectionState::SecretFlagsOk => 9,
+            WS2PConnectionState::Denial => 10,
+            WS2PConnectionState::Close => 11,
+            WS2PConnectionState::Established => 12,
+        }
+    }
+}
@@ -30,5 +30,17 @@ pub static WS2P_SPAM_LIMIT: &'static usize = &6;
 /// Rest time in a situation of proven spam
 pub static WS2P_SPAM_SLEEP_TIME_IN_SEC: &'static u64 = &100;
 
+/// Initial size from which messages are compressed (when DEF feature is negotiated)
+pub static WS2P_DEF_INITIAL_THRESHOLD: &'static usize = &1_024;
+
+/// Minimum size from which messages are compressed
+pub static WS2P_DEF_MIN_THRESHOLD: &'static usize = &256;
+
+/// Maximum size from which messages are compressed
+pub static WS2P_DEF_MAX_THRESHOLD: &'static usize = &65_536;
+
+/// Maximum size of a decompressed message (a chunk of blocks fits largely in it)
+pub static WS2P_DEF_MAX_MSG_SIZE: &'static usize = &10_000_000;
+
 /// Number of invalid messages tolerated
 pub static WS2P_INVALID_MSGS_LIMIT: &'static usize = &5;
@@ -21,12 +21,14 @@ mod on_message;
 mod on_open;
 
 use self::meta_datas::WS2PControllerMetaDatas;
+use crate::compression::{self, WS2PDeflateError};
 use crate::connection_state::WS2PConnectionState;
 use crate::constants;
 use crate::orchestrator::OrchestratorMsg;
 use crate::websocket::{WebsocketAction, WebsocketIncomingEvent};
 use durs_module::ModuleMessage;
 use durs_network_documents::NodeFullId;
+use durs_ws2p_messages::v2::api_features::WS2PFeatures;
 use durs_ws2p_messages::v2::connect::WS2Pv2ConnectType;
 use durs_ws2p_messages::WS2PMessage;
 use failure::Fail;
@@ -75,6 +77,8 @@ pub enum WS2PControllerEvent {
         conn_type: WS2Pv2ConnectType,
         /// Remote node full id
         remote_full_id: NodeFullId,
+        /// The remote node demand LOW flow
+        low_flow: bool,
     },
     /// Connection state change
     StateChange {
@@ -100,6 +104,8 @@ pub struct WS2PController<M: ModuleMessage> {
     pub meta_datas: WS2PControllerMetaDatas,
     /// Controller receiver
     pub receiver: Receiver<WebsocketActionOrder>,
+    /// Controller sender (used to order actions that follow the returned action)
+    pub self_sender: Sender<WebsocketActionOrder>,
 }
 
 #[derive(Copy, Clone, Debug, Fail)]
@@ -180,27 +186,65 @@ impl<M: ModuleMessage> WS2PController<M> {
     ) -> Result<WS2PController<M>, SendError<OrchestratorMsg<M>>> {
         let (sender, receiver) = std::sync::mpsc::channel();
 
-        orchestrator_sender.send(OrchestratorMsg::ControllerSender(sender))?;
+        orchestrator_sender.send(OrchestratorMsg::ControllerSender(sender.clone()))?;
 
         Ok(WS2PController {
             id,
             meta_datas,
             orchestrator_sender,
             receiver,
+            self_sender: sender,
         })
     }
 
-    /// Get orchestrator sender
+    /// Get the websocket actions ordered by the orchestrator (without blocking)
     pub fn get_pending_ws_actions(&self) -> Vec<WebsocketActionOrder> {
         let mut ws_actions = Vec::new();
 
-        while let Ok(ws_action) = self.receiver.recv() {
+        while let Ok(ws_action) = self.receiver.try_recv() {
             ws_actions.push(ws_action);
         }
 
         ws_actions
     }
 
+    /// Is DEF feature negotiated ?
+    fn def_negotiated(&self) -> bool {
+        self.meta_datas
+            .features
+            .as_ref()
+            .map(WS2PFeatures::def)
+            .unwrap_or(false)
+    }
+
+    /// Is LOW feature negotiated ?
+    fn low_negotiated(&self) -> bool {
+        self.meta_datas
+            .features
+            .as_ref()
+            .map(WS2PFeatures::low)
+            .unwrap_or(false)
+    }
+
+    /// Compress the binary message to send if DEF feature is negotiated
+    /// (the negotiation messages are never compressed)
+    pub fn encode_bin_msg(&mut self, bin_msg: Vec<u8>) -> Vec<u8> {
+        if self.meta_datas.state == WS2PConnectionState::Established && self.def_negotiated() {
+            self.meta_datas.deflate.encode(bin_msg)
+        } else {
+            bin_msg
+        }
+    }
+
+    /// Decompress the binary message received if DEF feature is negotiated
+    pub fn decode_bin_msg(&self, bin_msg: Vec<u8>) -> Result<Vec<u8>, WS2PDeflateError> {
+        if self.def_negotiated() {
+            compression::decode(bin_msg)
+        } else {
+            Ok(bin_msg)
+        }
+    }
+
     /// Process a websocket incoming event
     pub fn process(
         &mut self,
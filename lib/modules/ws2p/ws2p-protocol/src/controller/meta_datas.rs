@@ -15,6 +15,7 @@
 
 //! Sub module define WS2P controller meta datas
 
+use crate::compression::WS2PDeflate;
 use crate::connection_state::WS2PConnectionState;
 use crate::MySelfWs2pNode;
 use dubp_documents::{Blockstamp, CurrencyName};
@@ -38,6 +39,8 @@ pub struct WS2PControllerMetaDatas {
     pub currency: CurrencyName,
     /// Controller creation time
     pub creation_time: SystemTime,
+    /// Compressor of the outgoing frames (used if DEF feature is negotiated)
+    pub deflate: WS2PDeflate,
     /// Connection features
     pub features: Option<WS2PFeatures>,
     /// Timestamp of last received message
@@ -48,6 +51,8 @@ pub struct WS2PControllerMetaDatas {
     pub remote_connect_type: Option<WS2Pv2ConnectType>,
     /// Remote node datas
     pub remote_node: Option<Ws2pRemoteNodeDatas>,
+    /// Remote node demand LOW flow (sent in SECRET_FLAGS message)
+    pub remote_low_flow_demand: bool,
     /// Indicator required for the anti-spam mechanism
     pub spam_interval: bool,
     /// Indicator required for the anti-spam mechanism
@@ -70,11 +75,13 @@ impl WS2PControllerMetaDatas {
             count_invalid_msgs: 0,
             currency,
             creation_time: SystemTime::now(),
+            deflate: WS2PDeflate::default(),
             features: None,
             last_mess_time: SystemTime::now(),
             local_node,
             remote_connect_type: None,
             remote_node: None,
+            remote_low_flow_demand: false,
             spam_interval: false,
             spam_counter: 0,
             state: WS2PConnectionState::TryToOpenWS,
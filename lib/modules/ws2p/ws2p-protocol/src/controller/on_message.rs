@@ -69,6 +69,13 @@ pub fn process<M: ModuleMessage>(
 
     if let WebsocketMessage::Bin(bin_msg) = msg {
         log::debug!("Receive new bin message there is not a spam !");
+        let bin_msg = match controller.decode_bin_msg(bin_msg) {
+            Ok(bin_msg) => bin_msg,
+            Err(e) => {
+                log::warn!("Compressed frame is invalid : {}", e);
+                return Ok(count_invalid_msg(controller));
+            }
+        };
         match WS2PMessage::parse_and_check_bin_message(&bin_msg) {
             Ok(valid_msg) => match valid_msg {
                 WS2PMessage::V2(ref msg_v2) => {
@@ -127,15 +134,7 @@ pub fn process<M: ModuleMessage>(
             },
             Err(ws2p_msg_err) => {
                 log::warn!("Message is invalid : {:?}", ws2p_msg_err);
-                controller.meta_datas.count_invalid_msgs += 1;
-                if controller.meta_datas.count_invalid_msgs >= *constants::WS2P_INVALID_MSGS_LIMIT {
-                    Ok(close_with_reason(
-                        "Receive several invalid messages !",
-                        WS2PConnectionState::Denial,
-                    ))
-                } else {
-                    Ok(None)
-                }
+                Ok(count_invalid_msg(controller))
             }
         }
     } else {
@@ -146,6 +145,20 @@ pub fn process<M: ModuleMessage>(
     }
 }
 
+fn count_invalid_msg<M: ModuleMessage>(
+    controller: &mut WS2PController<M>,
+) -> Option<WebsocketActionOrder> {
+    controller.meta_datas.count_invalid_msgs += 1;
+    if controller.meta_datas.count_invalid_msgs >= *constants::WS2P_INVALID_MSGS_LIMIT {
+        close_with_reason(
+            "Receive several invalid messages !",
+            WS2PConnectionState::Denial,
+        )
+    } else {
+        None
+    }
+}
+
 fn close_with_reason(reason: &str, new_state: WS2PConnectionState) -> Option<WebsocketActionOrder> {
     Some(WebsocketActionOrder {
         ws_action: WebsocketAction::CloseConnection {
@@ -24,9 +24,9 @@ use durs_module::ModuleMessage;
 use durs_network_documents::NodeFullId;
 use durs_ws2p_messages::v2::connect::{WS2Pv2ConnectMsg, WS2Pv2ConnectType};
 use durs_ws2p_messages::v2::payload_container::WS2Pv2MessagePayload;
+use durs_ws2p_messages::v2::secret_flags::{WS2Pv2SecretFlags, WS2Pv2SecretFlagsMsg};
 use durs_ws2p_messages::v2::WS2Pv2Message;
 use log::error;
-use unwrap::unwrap;
 
 /// Process WS2P v2+ CONNECT Message
 pub fn process_ws2p_v2p_connect_msg<M: ModuleMessage>(
@@ -59,17 +59,18 @@ pub fn process_ws2p_v2p_connect_msg<M: ModuleMessage>(
                 connect_msg.chunkstamp,
             ));
         } else {
-            let expected_full_id = unwrap!(controller.id.expected_remote_full_id());
-            log::info!("TMP DEBUG: remote_full_id={}", remote_full_id);
-            log::info!("TMP DEBUG: expected_full_id={}", expected_full_id);
-            if remote_full_id == expected_full_id {
-                controller.meta_datas.remote_node = Some(remote_node_datas);
-            } else {
-                return Ok(super::close_with_reason(
-                    "Unexpected PUBKEY or NODE_ID !",
-                    WS2PConnectionState::Denial,
-                ));
+            // Without expected remote node (synchronization from an url), any remote node is accepted
+            if let Some(expected_full_id) = controller.id.expected_remote_full_id() {
+                log::info!("TMP DEBUG: remote_full_id={}", remote_full_id);
+                log::info!("TMP DEBUG: expected_full_id={}", expected_full_id);
+                if remote_full_id != expected_full_id {
+                    return Ok(super::close_with_reason(
+                        "Unexpected PUBKEY or NODE_ID !",
+                        WS2PConnectionState::Denial,
+                    ));
+                }
             }
+            controller.meta_datas.remote_node = Some(remote_node_datas);
             // Flags not allowed from incoming node
             if !connect_msg.flags_queries.is_empty() {
                 super::close_with_reason(
@@ -97,6 +98,12 @@ pub fn process_ws2p_v2p_connect_msg<M: ModuleMessage>(
         }
     }
 
+    // Order the sending of a SECRET_FLAGS message just after the ACK message
+    // (before our OK message) if we demand LOW flow
+    if controller.meta_datas.local_node.low_flow_demand && controller.low_negotiated() {
+        send_low_flow_demand(controller);
+    }
+
     // Encapsulate and binarize ACK message
     if let Ok((_, bin_ack_msg)) = WS2Pv2Message::encapsulate_payload(
         controller.meta_datas.currency.clone(),
@@ -118,3 +125,27 @@ pub fn process_ws2p_v2p_connect_msg<M: ModuleMessage>(
         fatal_error!("Dev error: Fail to sign own ack message !")
     }
 }
+
+/// Order the sending of a SECRET_FLAGS message with LOW_FLOW_DEMAND flag
+fn send_low_flow_demand<M: ModuleMessage>(controller: &WS2PController<M>) {
+    if let Ok((_, bin_secret_flags_msg)) = WS2Pv2Message::encapsulate_payload(
+        controller.meta_datas.currency.clone(),
+        controller.meta_datas.local_node.my_node_id,
+        controller.meta_datas.local_node.my_key_pair,
+        WS2Pv2MessagePayload::SecretFlags(WS2Pv2SecretFlagsMsg {
+            secret_flags: WS2Pv2SecretFlags::new(true),
+            member_proof: None,
+        }),
+    ) {
+        // The controller receiver lives as long as the controller
+        let _ = controller.self_sender.send(WebsocketActionOrder {
+            ws_action: WebsocketAction::SendMessage {
+                msg: WebsocketMessage::Bin(bin_secret_flags_msg),
+            },
+            new_state_if_success: None,
+            new_state_if_fail: WS2PConnectionState::Unreachable,
+        });
+    } else {
+        fatal_error!("Dev error: Fail to sign own secret flags message !")
+    }
+}
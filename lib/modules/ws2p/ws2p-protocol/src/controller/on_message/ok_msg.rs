@@ -49,6 +49,7 @@ pub fn process_ws2p_v2p_ok_msg<M: ModuleMessage>(
                 } else {
                     fatal_error!("remote_node must be valued in process_ws2p_v2p_ok_msg() !")
                 },
+                low_flow: controller.meta_datas.remote_low_flow_demand,
             })?;
             Ok(None)
         }
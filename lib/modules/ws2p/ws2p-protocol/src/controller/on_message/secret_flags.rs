@@ -51,9 +51,11 @@ pub fn process_ws2p_v2p_secret_flags_msg<M: ModuleMessage>(
 
 fn process<M: ModuleMessage>(
     controller: &mut WS2PController<M>,
-    _secret_flags: &WS2Pv2SecretFlagsMsg,
+    secret_flags: &WS2Pv2SecretFlagsMsg,
     success_state: WS2PConnectionState,
 ) -> Result<(), WS2PControllerProcessError> {
-    // TODO .. traitement des secrets flags
+    // LOW flow demand is ignored if LOW feature is not negotiated
+    controller.meta_datas.remote_low_flow_demand =
+        secret_flags.secret_flags.low_flow_demand() && controller.low_negotiated();
     controller.update_conn_state(success_state)
 }
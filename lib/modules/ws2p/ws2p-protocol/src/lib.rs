@@ -28,6 +28,7 @@
     unused_qualifications
 )]
 
+pub mod compression;
 pub mod connection_state;
 pub mod constants;
 pub mod controller;
@@ -47,6 +48,9 @@ pub struct MySelfWs2pNode {
     pub my_key_pair: KeyPairEnum,
     /// Local node WWS2PFeatures
     pub my_features: WS2PFeatures,
+    /// Ask the remote nodes to send only inventories of pending documents and heads
+    /// (applied only if LOW feature is negotiated)
+    pub low_flow_demand: bool,
 }
 
 impl MySelfWs2pNode {
@@ -18,11 +18,57 @@ pub static MODULE_NAME: &'static str = "ws2p";
 
 pub static WS2P_DEFAULT_OUTCOMING_QUOTA: &'static usize = &10;
 
+pub static WS2P_DEFAULT_LISTEN_HOST: &'static str = "localhost";
+pub static WS2P_DEFAULT_LISTEN_PORT: &'static u16 = &20_901;
+
+pub static WS2P_ENDPOINT_RETRY_DELAY_IN_SECS: &'static u64 = &300;
+pub static WS2P_ENDPOINT_MAX_RETRY_DELAY_IN_SECS: &'static u64 = &3_600;
+
 /*pub static WS2P_OUTCOMING_INTERVAL_AT_STARTUP: &'static u64 = &75;
 pub static WS2P_OUTCOMING_INTERVAL: &'static u64 = &300;*/
 pub static WS2P_RECV_SERVICE_FREQ_IN_MS: &'static u64 = &1_000;
+
+/// Number of blocks in a sync chunk
+pub static SYNC_CHUNK_SIZE: &'static u32 = &250;
+/// Maximum number of pending chunk requests per sync connection
+pub static SYNC_MAX_PENDING_REQS_PER_CONN: &'static usize = &4;
+/// Maximum number of chunks requested or received but not yet transmitted to the blockchain module
+pub static SYNC_MAX_BUFFERED_CHUNKS: &'static usize = &64;
+/// Maximum number of attempts for a same chunk
+pub static SYNC_MAX_CHUNK_ATTEMPTS: &'static usize = &5;
+/// Sync request timeout
+pub static SYNC_REQUEST_TIMEOUT_IN_SECS: &'static u64 = &30;
+/// Maximum time to wait for the remote currents before choosing the sync target
+pub static SYNC_WAIT_CURRENTS_TIMEOUT_IN_SECS: &'static u64 = &20;
+/// Maximum number of blocks that a remote node can request in a chunk
+pub static WS2P_REQ_MAX_CHUNK_SIZE: &'static u16 = &500;
+/// Maximum number of blocks hashs that a remote node can request
+pub static WS2P_REQ_MAX_BLOCKS_HASHS: &'static u16 = &1_000;
+/// Maximum number of wot pool folders that a remote node can request
+pub static WS2P_REQ_MAX_WOT_POOL_FOLDERS: &'static u16 = &100;
+/// Maximum time to wait for the response of a local module to a remote request
+pub static WS2P_MODULE_REQUEST_TIMEOUT_IN_SECS: &'static u64 = &10;
+/// Maximum time to wait for the response of a remote node to a local request
+pub static WS2P_REQUEST_TIMEOUT_IN_SECS: &'static u64 = &30;
+/// Maximum number of items in a gossip message
+pub static WS2P_GOSSIP_MAX_ITEMS_PER_MSG: &'static usize = &50;
+/// Lifetime of the items of the gossip seen cache
+pub static WS2P_GOSSIP_SEEN_CACHE_TTL_IN_SECS: &'static u64 = &1_800;
+/// Maximum number of items in the gossip seen cache
+pub static WS2P_GOSSIP_SEEN_CACHE_MAX_SIZE: &'static usize = &100_000;
+/// Maximum number of items in the outgoing gossip queue of a connection
+pub static WS2P_OUTGOING_QUEUE_MAX_LEN: &'static usize = &2_000;
+/// Maximum number of gossip items sent per second on a connection
+pub static WS2P_OUTGOING_MAX_ITEMS_PER_SEC: &'static usize = &500;
+/// Lifetime of the items kept to answer the inventory requests of the LOW flow connections
+pub static WS2P_INVENTORY_TTL_IN_SECS: &'static u64 = &600;
+/// Maximum number of items kept to answer the inventory requests of the LOW flow connections
+pub static WS2P_INVENTORY_MAX_SIZE: &'static usize = &10_000;
+/// Maximum number of hashs processed in an INVENTORY or GET_INVENTORY_ITEMS message
+pub static WS2P_INVENTORY_MAX_ITEMS_PER_REQ: &'static usize = &100;
+/// Delay before requesting again an inventory item that has not been received
+pub static WS2P_INVENTORY_REQUEST_TIMEOUT_IN_SECS: &'static u64 = &30;
 /*
-pub static WS2P_REQUEST_TIMEOUT: &'static u64 = &30_000;
 pub static DURATION_BEFORE_RECORDING_ENDPOINT: &'static u64 = &180;
 pub static BLOCKS_REQUEST_INTERVAL: &'static u64 = &60;
 pub static PENDING_IDENTITIES_REQUEST_INTERVAL: &'static u64 = &40;
@@ -49,7 +49,9 @@ impl Ws2pConnectionHandler {
             }
             WebsocketAction::SendMessage { msg } => {
                 let ws_msg = match msg {
-                    WebsocketMessage::Bin(bin_msg) => Message::binary(bin_msg),
+                    WebsocketMessage::Bin(bin_msg) => {
+                        Message::binary(self.controller.encode_bin_msg(bin_msg))
+                    }
                     WebsocketMessage::Str(str_msg) => Message::text(str_msg),
                 };
                 match self.ws.0.send(ws_msg) {
@@ -111,6 +113,13 @@ impl Ws2pConnectionHandler {
             },
         }
     }
+    /// Execute the websocket actions ordered by the orchestrator
+    fn exec_pending_ws_actions(&mut self) -> ws::Result<()> {
+        for ws_action_order in self.controller.get_pending_ws_actions() {
+            self.exec_ws_action(ws_action_order)?;
+        }
+        Ok(())
+    }
 }
 
 // We implement the Handler trait for Ws2pConnectionHandler so that we can get more
@@ -161,10 +170,9 @@ impl Handler for Ws2pConnectionHandler {
         {
             Ok(ws_action_order_opt) => {
                 if let Some(ws_action_order) = ws_action_order_opt {
-                    self.exec_ws_action(ws_action_order)
-                } else {
-                    Ok(())
+                    self.exec_ws_action(ws_action_order)?;
                 }
+                self.exec_pending_ws_actions()
             }
             Err(e) => self.exec_ws_action(WebsocketActionOrder {
                 ws_action: WebsocketAction::CloseConnection {
@@ -178,10 +186,9 @@ impl Handler for Ws2pConnectionHandler {
     fn on_timeout(&mut self, _event: Token) -> ws::Result<()> {
         self.ws.0.timeout(1_000, RECV_SERVICE)?;
         if let Some(ws_action_order) = self.controller.check_timeouts() {
-            self.exec_ws_action(ws_action_order)
-        } else {
-            Ok(())
+            self.exec_ws_action(ws_action_order)?;
         }
+        self.exec_pending_ws_actions()
     }
     /*fn on_frame(&mut self, frame: Frame) -> ws::Result<Option<Frame>> {
         Ok(Some(frame))
@@ -37,6 +37,27 @@ pub fn listen_on_ws2p_v2_endpoint(
     host: &str,
     port: u16,
 ) -> ws::Result<()> {
+    listen_on_ws2p_v2_endpoint_with(
+        currency,
+        || orchestrator_sender.clone(),
+        self_node,
+        host,
+        port,
+    )
+}
+
+/// Listen on WSPv2 host:port, each incoming connection gets
+/// its own orchestrator sender
+pub fn listen_on_ws2p_v2_endpoint_with<F>(
+    currency: &CurrencyName,
+    mut new_orchestrator_sender: F,
+    self_node: &MySelfWs2pNode,
+    host: &str,
+    port: u16,
+) -> ws::Result<()>
+where
+    F: FnMut() -> mpsc::Sender<OrchestratorMsg<DursMsg>>,
+{
     // Get endpoint url
     let ws_url = format!("{}:{}", host, port);
 
@@ -51,7 +72,7 @@ pub fn listen_on_ws2p_v2_endpoint(
                 currency.clone(),
                 self_node.clone(),
             ),
-            orchestrator_sender.clone(),
+            new_orchestrator_sender(),
         ) {
             Ok(controller) => DeflateBuilder::new().build(Ws2pConnectionHandler {
                 ws: WsSender(ws),
@@ -39,6 +39,7 @@ pub fn connect_to_ws2p_v2_endpoint(
     self_node: &MySelfWs2pNode,
     expected_remote_full_id: Option<NodeFullId>,
     endpoint: &EndpointEnum,
+    connect_type: WS2Pv2ConnectType,
 ) -> ws::Result<()> {
     // Get endpoint url
     let ws_url = endpoint.get_url(true, false).expect("Endpoint unreachable");
@@ -54,7 +55,7 @@ pub fn connect_to_ws2p_v2_endpoint(
             },
             WS2PControllerMetaDatas::new(
                 Hash::random(),
-                WS2Pv2ConnectType::OutgoingServer,
+                connect_type,
                 currency.clone(),
                 self_node.clone(),
             ),
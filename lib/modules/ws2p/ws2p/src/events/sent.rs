@@ -0,0 +1,64 @@
+//  Copyright (C) 2018  The Durs Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Sub-module managing the events emitted by the WS2P module.
+
+use crate::constants;
+use durs_common_tools::fatal_error;
+use durs_message::events::DursEvent;
+use durs_message::*;
+use durs_module::{ModuleEvent, ModuleStaticName, RouterThreadMessage};
+use durs_network::documents::BlockchainDocument;
+use durs_network::events::NetworkEvent;
+use std::sync::mpsc;
+
+/// Send network event to router
+pub fn send_network_event(
+    router_sender: &mpsc::Sender<RouterThreadMessage<DursMsg>>,
+    event: NetworkEvent,
+) {
+    let module_event = match event {
+        NetworkEvent::ConnectionStateChange(_, _, _, _) => {
+            ModuleEvent::ConnectionsChangeNodeNetwork
+        }
+        NetworkEvent::ReceiveBlocks(_) | NetworkEvent::SyncTarget(_, _) => {
+            ModuleEvent::NewBlockFromNetwork
+        }
+        NetworkEvent::ReceiveDocuments(ref network_docs) => {
+            if !network_docs.is_empty() {
+                match network_docs[0] {
+                    BlockchainDocument::Block(_) => ModuleEvent::NewBlockFromNetwork,
+                    BlockchainDocument::Transaction(_) => ModuleEvent::NewTxFromNetwork,
+                    _ => ModuleEvent::NewWotDocFromNetwork,
+                }
+            } else {
+                return;
+            }
+        }
+        NetworkEvent::ReceiveHeads(_) => ModuleEvent::NewValidHeadFromNetwork,
+        NetworkEvent::ReceivePeers(_) => ModuleEvent::NewValidPeerFromNodeNetwork,
+        NetworkEvent::NewSelfPeer(_) => ModuleEvent::NewSelfPeer,
+    };
+    if router_sender
+        .send(RouterThreadMessage::ModuleMessage(DursMsg::Event {
+            event_from: ModuleStaticName(constants::MODULE_NAME),
+            event_type: module_event,
+            event_content: DursEvent::NetworkEvent(event),
+        }))
+        .is_err()
+    {
+        fatal_error!("WS2P module fail to send network event to router !");
+    }
+}
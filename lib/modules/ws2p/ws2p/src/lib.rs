@@ -36,24 +36,39 @@ extern crate structopt;
 mod constants;
 pub mod controllers;
 mod errors;
+mod events;
 mod generate_peer;
+pub mod requests;
+pub mod responses;
 pub mod services;
+mod sync;
+pub mod ws2p_db;
 
 use crate::errors::WS2PError;
+use crate::services::WS2PService;
 use durs_common_tools::fatal_error;
 use durs_common_tools::traits::merge::Merge;
-use durs_conf::DuRsConf;
+use durs_conf::{DuRsConf, ResourceUsage};
 use durs_message::DursMsg;
 use durs_module::*;
 use durs_network::cli::sync::SyncOpt;
 use durs_network::*;
 use durs_network_documents::network_endpoint::*;
+use durs_network_documents::NodeId;
+use durs_ws2p_messages::v2::api_features::WS2PFeatures;
+use durs_ws2p_protocol::MySelfWs2pNode;
 use maplit::hashset;
+use std::fs;
 use std::sync::mpsc;
+use std::thread;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 /// WS2P Configuration
 pub struct WS2PConf {
+    /// Listen host
+    pub host: String,
+    /// Listen port
+    pub port: u16,
     /// Limit of outcoming connections
     pub outcoming_quota: usize,
     /// Default WS2P endpoints provides by configuration file
@@ -63,6 +78,10 @@ pub struct WS2PConf {
 #[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 /// WS2P Configuration
 pub struct WS2PUserConf {
+    /// Listen host
+    pub host: Option<String>,
+    /// Listen port
+    pub port: Option<u16>,
     /// Limit of outcoming connections
     pub outcoming_quota: Option<usize>,
     /// Default WS2P endpoints provides by configuration file
@@ -72,6 +91,8 @@ pub struct WS2PUserConf {
 impl Merge for WS2PUserConf {
     fn merge(self, other: Self) -> Self {
         WS2PUserConf {
+            host: self.host.or(other.host),
+            port: self.port.or(other.port),
             outcoming_quota: self.outcoming_quota.or(other.outcoming_quota),
             sync_endpoints: self.sync_endpoints.or(other.sync_endpoints),
         }
@@ -81,6 +102,8 @@ impl Merge for WS2PUserConf {
 impl Default for WS2PConf {
     fn default() -> Self {
         WS2PConf {
+            host: String::from(constants::WS2P_DEFAULT_LISTEN_HOST),
+            port: *constants::WS2P_DEFAULT_LISTEN_PORT,
             outcoming_quota: *constants::WS2P_DEFAULT_OUTCOMING_QUOTA,
             sync_endpoints: vec![
                 EndpointV2::parse_from_raw("WS2P 2 g1.durs.info 443 ws2p").unwrap(),
@@ -135,13 +158,13 @@ impl ApiModule<DuRsConf, DursMsg> for WS2PModule {
 
 impl NetworkModule<DuRsConf, DursMsg> for WS2PModule {
     fn sync(
-        _soft_meta_datas: &SoftwareMetaDatas<DuRsConf>,
-        _keys: RequiredKeysContent,
-        _conf: WS2PConf,
-        _main_sender: mpsc::Sender<RouterThreadMessage<DursMsg>>,
-        _sync_params: SyncOpt,
+        soft_meta_datas: &SoftwareMetaDatas<DuRsConf>,
+        keys: RequiredKeysContent,
+        conf: WS2PConf,
+        main_sender: mpsc::Sender<RouterThreadMessage<DursMsg>>,
+        sync_params: SyncOpt,
     ) -> Result<(), SyncError> {
-        unimplemented!()
+        sync::sync(soft_meta_datas, keys, &conf, main_sender, sync_params)
     }
 }
 
@@ -177,6 +200,12 @@ impl DursModule<DuRsConf, DursMsg> for WS2PModule {
         let mut conf = WS2PConf::default();
 
         if let Some(module_user_conf) = module_user_conf.clone() {
+            if let Some(host) = module_user_conf.host {
+                conf.host = host;
+            }
+            if let Some(port) = module_user_conf.port {
+                conf.port = port;
+            }
             if let Some(outcoming_quota) = module_user_conf.outcoming_quota {
                 conf.outcoming_quota = outcoming_quota;
             }
@@ -198,13 +227,13 @@ impl DursModule<DuRsConf, DursMsg> for WS2PModule {
         None
     }
     fn start(
-        _soft_meta_datas: &SoftwareMetaDatas<DuRsConf>,
+        soft_meta_datas: &SoftwareMetaDatas<DuRsConf>,
         keys: RequiredKeysContent,
-        _conf: WS2PConf,
+        conf: WS2PConf,
         router_sender: mpsc::Sender<RouterThreadMessage<DursMsg>>,
     ) -> Result<(), failure::Error> {
         // Get key_pair
-        let _key_pair = if let RequiredKeysContent::NetworkKeyPair(key_pair) = keys {
+        let key_pair = if let RequiredKeysContent::NetworkKeyPair(key_pair) = keys {
             key_pair
         } else {
             return Err(WS2PError::UnexpectedKeys.into());
@@ -235,11 +264,65 @@ impl DursModule<DuRsConf, DursMsg> for WS2PModule {
             fatal_error!("WS2P module fail to send registration to router !")
         }
 
-        while let Ok(msg) = module_receiver.recv() {
-            if let DursMsg::Stop = msg {
-                break;
-            }
+        // Get endpoints file path
+        let currency = soft_meta_datas.conf.currency();
+        let mut ep_file_path =
+            durs_conf::datas_path(soft_meta_datas.profile_path.clone(), &currency);
+        ep_file_path.push("ws2pv2");
+        if !ep_file_path.exists() {
+            fs::create_dir(ep_file_path.as_path()).expect("Impossible to create ws2pv2 dir !");
         }
+        ep_file_path.push("endpoints.bin");
+
+        // Create WS2P service
+        let network_usage = soft_meta_datas
+            .conf
+            .get_global_conf()
+            .ressources_usage()
+            .network_usage;
+        let self_node = MySelfWs2pNode {
+            my_node_id: NodeId(soft_meta_datas.conf.my_node_id()),
+            my_key_pair: key_pair,
+            my_features: WS2PFeatures(vec![3u8]),
+            low_flow_demand: match network_usage {
+                ResourceUsage::Minimal | ResourceUsage::Medium => true,
+                ResourceUsage::Large | ResourceUsage::Infinite => false,
+            },
+        };
+        let mut ws2p_service = WS2PService::new(
+            currency.clone(),
+            self_node.clone(),
+            &conf,
+            Some(ep_file_path),
+            router_sender,
+        );
+
+        // Launch a proxy thread that transmit the router messages to the service
+        let service_sender = ws2p_service.sender.clone();
+        thread::spawn(move || {
+            while let Ok(msg) = module_receiver.recv() {
+                let stop = if let DursMsg::Stop = msg { true } else { false };
+                if service_sender
+                    .send(services::WS2PServiceMsg::Router(Box::new(msg)))
+                    .is_err()
+                    || stop
+                {
+                    break;
+                }
+            }
+        });
+
+        // Listen incoming connections
+        services::incoming::listen(
+            currency,
+            self_node,
+            conf.host.clone(),
+            conf.port,
+            ws2p_service.sender.clone(),
+        );
+
+        // Run the service (until the node stops)
+        ws2p_service.run();
 
         Ok(())
     }
@@ -0,0 +1,207 @@
+//  Copyright (C) 2018  The Durs Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Sub-module managing the requests received from remote nodes.
+
+use crate::constants;
+use crate::requests::sent::send_module_request;
+use crate::responses::sent::send_req_response;
+use crate::services::{WS2PConnId, WS2PService};
+use dubp_documents::{BlockNumber, Blockstamp};
+use durs_message::requests::{BlockchainRequest, DursReqContent, MemPoolRequest};
+use durs_module::{ModuleReqId, ModuleRole};
+use durs_ws2p_messages::v2::req_responses::{WS2Pv2ReqRes, WS2Pv2ReqResBody};
+use durs_ws2p_messages::v2::requests::{WS2Pv2Request, WS2Pv2RequestBody};
+use std::time::{Duration, SystemTime};
+
+/// Request of a remote node waiting for the response of a local module
+#[derive(Debug, Copy, Clone)]
+pub struct PendingRemoteReq {
+    /// Connection on which the request was received
+    pub conn_id: WS2PConnId,
+    /// Request received
+    pub request: WS2Pv2Request,
+    /// Time at which the request was transmitted to the local module
+    pub time: SystemTime,
+}
+
+/// Process a request received from a remote node
+pub fn receive_request(service: &mut WS2PService, conn_id: WS2PConnId, request: WS2Pv2Request) {
+    match check_request(&request.body) {
+        Ok((req_to, req_content)) => {
+            let req_id = send_module_request(service, req_to, req_content);
+            service.pending_remote_reqs.insert(
+                req_id,
+                PendingRemoteReq {
+                    conn_id,
+                    request,
+                    time: SystemTime::now(),
+                },
+            );
+        }
+        Err(reason) => {
+            debug!("WS2P: receive bad request #{}: {}", request.id, reason);
+            send_req_response(
+                service,
+                conn_id,
+                WS2Pv2ReqRes {
+                    id: request.id,
+                    body: WS2Pv2ReqResBody::BadRequest(reason),
+                },
+            );
+        }
+    }
+}
+
+/// Forget the requests received on the given connection
+pub fn cancel_conn_requests(service: &mut WS2PService, conn_id: WS2PConnId) {
+    service
+        .pending_remote_reqs
+        .retain(|_, pending_req| pending_req.conn_id != conn_id);
+}
+
+/// Answer an empty response to the requests that the local modules have not answered in time
+pub fn check_requests_timeouts(service: &mut WS2PService) {
+    let now = SystemTime::now();
+    let expired_reqs: Vec<ModuleReqId> = service
+        .pending_remote_reqs
+        .iter()
+        .filter(|(_, pending_req)| {
+            now.duration_since(pending_req.time).unwrap_or_default()
+                > Duration::from_secs(*constants::WS2P_MODULE_REQUEST_TIMEOUT_IN_SECS)
+        })
+        .map(|(req_id, _)| *req_id)
+        .collect();
+    for req_id in expired_reqs {
+        if let Some(pending_req) = service.pending_remote_reqs.remove(&req_id) {
+            send_req_response(
+                service,
+                pending_req.conn_id,
+                WS2Pv2ReqRes {
+                    id: pending_req.request.id,
+                    body: WS2Pv2ReqResBody::None,
+                },
+            );
+        }
+    }
+}
+
+/// Check a remote request and convert it into a request for a local module
+pub fn check_request(body: &WS2Pv2RequestBody) -> Result<(ModuleRole, DursReqContent), String> {
+    match *body {
+        WS2Pv2RequestBody::None => Err("Empty request.".to_owned()),
+        WS2Pv2RequestBody::Current => Ok((
+            ModuleRole::BlockchainDatas,
+            DursReqContent::BlockchainRequest(BlockchainRequest::CurrentBlockstamp()),
+        )),
+        WS2Pv2RequestBody::BlocksHashs(first_block_number, count) => {
+            check_count(count, *constants::WS2P_REQ_MAX_BLOCKS_HASHS)?;
+            Ok(chunk_request(first_block_number, u32::from(count)))
+        }
+        WS2Pv2RequestBody::Chunk(first_block_number, count) => {
+            check_count(count, *constants::WS2P_REQ_MAX_CHUNK_SIZE)?;
+            Ok(chunk_request(first_block_number, u32::from(count)))
+        }
+        WS2Pv2RequestBody::ChunkByHash(blockstamp) => {
+            let (first_block_number, count) = chunk_by_hash_range(blockstamp);
+            Ok(chunk_request(first_block_number, count))
+        }
+        WS2Pv2RequestBody::WotPool(folders_count, _) => {
+            check_count(folders_count, *constants::WS2P_REQ_MAX_WOT_POOL_FOLDERS)?;
+            Ok((
+                ModuleRole::WotPool,
+                DursReqContent::MemPoolRequest(MemPoolRequest::AllPendingDocs),
+            ))
+        }
+    }
+}
+
+/// Range of the chunk containing the given block (first block number, number of blocks)
+pub fn chunk_by_hash_range(blockstamp: Blockstamp) -> (BlockNumber, u32) {
+    let first_block_number =
+        (blockstamp.id.0 / *constants::SYNC_CHUNK_SIZE) * *constants::SYNC_CHUNK_SIZE;
+    (
+        BlockNumber(first_block_number),
+        blockstamp.id.0 - first_block_number + 1,
+    )
+}
+
+fn check_count(count: u16, max: u16) -> Result<(), String> {
+    if count == 0 {
+        Err("Requested count must be strictly positive.".to_owned())
+    } else if count > max {
+        Err(format!("Requested count exceeds the limit ({}).", max))
+    } else {
+        Ok(())
+    }
+}
+
+fn chunk_request(first_block_number: BlockNumber, count: u32) -> (ModuleRole, DursReqContent) {
+    (
+        ModuleRole::BlockchainDatas,
+        DursReqContent::BlockchainRequest(BlockchainRequest::Chunk {
+            first_block_number,
+            count,
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dubp_documents::BlockHash;
+    use dup_crypto::hashs::Hash;
+
+    #[test]
+    fn test_check_request() {
+        assert!(check_request(&WS2Pv2RequestBody::None).is_err());
+        assert!(check_request(&WS2Pv2RequestBody::Chunk(BlockNumber(0), 0)).is_err());
+        assert!(check_request(&WS2Pv2RequestBody::Chunk(BlockNumber(0), 501)).is_err());
+        assert!(check_request(&WS2Pv2RequestBody::BlocksHashs(BlockNumber(0), 1_001)).is_err());
+        assert!(check_request(&WS2Pv2RequestBody::WotPool(101, 0)).is_err());
+        match check_request(&WS2Pv2RequestBody::Chunk(BlockNumber(10), 500)) {
+            Ok((
+                ModuleRole::BlockchainDatas,
+                DursReqContent::BlockchainRequest(BlockchainRequest::Chunk {
+                    first_block_number,
+                    count,
+                }),
+            )) => {
+                assert_eq!(BlockNumber(10), first_block_number);
+                assert_eq!(500, count);
+            }
+            res => panic!("unexpected result: {:?}", res),
+        }
+        match check_request(&WS2Pv2RequestBody::WotPool(10, 3)) {
+            Ok((
+                ModuleRole::WotPool,
+                DursReqContent::MemPoolRequest(MemPoolRequest::AllPendingDocs),
+            )) => {}
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn test_chunk_by_hash_range() {
+        let blockstamp = |id| Blockstamp {
+            id: BlockNumber(id),
+            hash: BlockHash(Hash::default()),
+        };
+        assert_eq!((BlockNumber(0), 1), chunk_by_hash_range(blockstamp(0)));
+        assert_eq!((BlockNumber(0), 250), chunk_by_hash_range(blockstamp(249)));
+        assert_eq!((BlockNumber(250), 1), chunk_by_hash_range(blockstamp(250)));
+        assert_eq!((BlockNumber(500), 18), chunk_by_hash_range(blockstamp(517)));
+    }
+}
@@ -0,0 +1,183 @@
+//  Copyright (C) 2018  The Durs Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Sub-module managing the requests sent to the local modules and to the remote nodes.
+
+use crate::constants;
+use crate::responses::sent::send_network_response;
+use crate::services::{WS2PConnId, WS2PService};
+use durs_common_tools::fatal_error;
+use durs_message::requests::DursReqContent;
+use durs_message::*;
+use durs_module::{
+    ModuleReqFullId, ModuleReqId, ModuleRole, ModuleStaticName, RouterThreadMessage,
+};
+use durs_network::requests::{
+    NetworkRequest, NetworkRequestContent, NetworkResponse, OldNetworkRequestError,
+};
+use durs_network_documents::NodeFullId;
+use durs_ws2p_messages::v2::payload_container::WS2Pv2MessagePayload;
+use durs_ws2p_messages::v2::requests::{WS2Pv2Request, WS2Pv2RequestBody};
+use std::time::{Duration, SystemTime};
+
+/// Request of a local module waiting for the response of a remote node
+#[derive(Debug, Copy, Clone)]
+pub struct PendingLocalReq {
+    /// Requester full identifier
+    pub req_full_id: ModuleReqFullId,
+    /// Remote node that must answer
+    pub receiver: NodeFullId,
+    /// Request content
+    pub content: NetworkRequestContent,
+    /// Time at which the request was sent
+    pub time: SystemTime,
+}
+
+/// Send a request to a local module, returns the request identifier
+pub fn send_module_request(
+    service: &mut WS2PService,
+    req_to: ModuleRole,
+    req_content: DursReqContent,
+) -> ModuleReqId {
+    let req_id = ModuleReqId(service.next_module_req_id);
+    service.next_module_req_id = service.next_module_req_id.wrapping_add(1);
+
+    if service
+        .router_sender
+        .send(RouterThreadMessage::ModuleMessage(DursMsg::Request {
+            req_from: ModuleStaticName(constants::MODULE_NAME),
+            req_to,
+            req_id,
+            req_content,
+        }))
+        .is_err()
+    {
+        fatal_error!("WS2P module fail to send request to router !");
+    }
+
+    req_id
+}
+
+/// Send the request of a local module to a remote node
+pub fn send_network_request(
+    service: &mut WS2PService,
+    req_full_id: ModuleReqFullId,
+    request: NetworkRequest,
+) {
+    let target = service
+        .connections
+        .iter()
+        .filter_map(|(conn_id, conn)| conn.remote_full_id.map(|full_id| (*conn_id, full_id)))
+        .find(|(_, full_id)| request.receiver.is_none() || request.receiver == Some(*full_id));
+    let (conn_id, receiver) = if let Some(target) = target {
+        target
+    } else {
+        send_network_response(
+            &service.router_sender,
+            NetworkResponse::Error(req_full_id, OldNetworkRequestError::ReceiverUnreachable()),
+        );
+        return;
+    };
+
+    let ws2p_req_id = service.next_ws2p_req_id;
+    service.next_ws2p_req_id = service.next_ws2p_req_id.wrapping_add(1);
+
+    if service.send_payload(
+        conn_id,
+        WS2Pv2MessagePayload::Request(WS2Pv2Request {
+            id: ws2p_req_id,
+            body: request_body(request.content),
+        }),
+    ) {
+        service.pending_local_reqs.insert(
+            (conn_id, ws2p_req_id),
+            PendingLocalReq {
+                req_full_id,
+                receiver,
+                content: request.content,
+                time: SystemTime::now(),
+            },
+        );
+    } else {
+        send_network_response(
+            &service.router_sender,
+            NetworkResponse::Error(req_full_id, OldNetworkRequestError::ReceiverUnreachable()),
+        );
+    }
+}
+
+/// Remove the requests sent on the given connection, and notify the requesters
+pub fn cancel_conn_requests(service: &mut WS2PService, conn_id: WS2PConnId) {
+    let canceled_reqs: Vec<(WS2PConnId, u32)> = service
+        .pending_local_reqs
+        .keys()
+        .filter(|(req_conn_id, _)| *req_conn_id == conn_id)
+        .cloned()
+        .collect();
+    for key in canceled_reqs {
+        if let Some(pending_req) = service.pending_local_reqs.remove(&key) {
+            send_network_response(
+                &service.router_sender,
+                NetworkResponse::Error(
+                    pending_req.req_full_id,
+                    OldNetworkRequestError::ReceiverUnreachable(),
+                ),
+            );
+        }
+    }
+}
+
+/// Remove the requests that have not been answered in time, and notify the requesters
+pub fn check_requests_timeouts(service: &mut WS2PService) {
+    let now = SystemTime::now();
+    let expired_reqs: Vec<(WS2PConnId, u32)> = service
+        .pending_local_reqs
+        .iter()
+        .filter(|(_, pending_req)| {
+            now.duration_since(pending_req.time).unwrap_or_default()
+                > Duration::from_secs(*constants::WS2P_REQUEST_TIMEOUT_IN_SECS)
+        })
+        .map(|(key, _)| *key)
+        .collect();
+    for key in expired_reqs {
+        if let Some(pending_req) = service.pending_local_reqs.remove(&key) {
+            debug!(
+                "WS2P: request #{} to {} timeout.",
+                key.1, pending_req.receiver
+            );
+            send_network_response(
+                &service.router_sender,
+                NetworkResponse::Error(
+                    pending_req.req_full_id,
+                    OldNetworkRequestError::NoResponse(),
+                ),
+            );
+        }
+    }
+}
+
+/// Convert the content of a local request into a WS2P request body
+fn request_body(content: NetworkRequestContent) -> WS2Pv2RequestBody {
+    match content {
+        NetworkRequestContent::GetCurrent => WS2Pv2RequestBody::Current,
+        NetworkRequestContent::GetBlocksHashs { from, count } => {
+            WS2Pv2RequestBody::BlocksHashs(from, count)
+        }
+        NetworkRequestContent::GetChunk { from, count } => WS2Pv2RequestBody::Chunk(from, count),
+        NetworkRequestContent::GetChunkByHash(blockstamp) => {
+            WS2Pv2RequestBody::ChunkByHash(blockstamp)
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//  Copyright (C) 2018  The Durs Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Sub-module managing the responses received from the local modules and from the remote nodes.
+
+use crate::constants;
+use crate::requests::sent::PendingLocalReq;
+use crate::responses::sent::{send_network_response, send_req_response};
+use crate::services::{WS2PConnId, WS2PService};
+use dubp_documents::documents::membership::{CompactPoolMembershipDoc, MembershipType};
+use dubp_documents::text_document_traits::TextDocument;
+use dubp_documents::Document;
+use dup_crypto::keys::PubKey;
+use durs_message::responses::{BlockchainResponse, DursResContent, MemPoolResponse, PendingDocs};
+use durs_module::ModuleReqId;
+use durs_network::requests::{NetworkRequestContent, NetworkResponse, OldNetworkRequestError};
+use durs_ws2p_messages::v2::req_responses::{WS2Pv2ReqRes, WS2Pv2ReqResBody, WotPoolFolder};
+use durs_ws2p_messages::v2::requests::WS2Pv2RequestBody;
+use std::collections::HashSet;
+
+/// Process the response of a local module to a request of a remote node
+pub fn receive_module_response(
+    service: &mut WS2PService,
+    req_id: ModuleReqId,
+    res_content: DursResContent,
+) {
+    if let Some(pending_req) = service.pending_remote_reqs.remove(&req_id) {
+        send_req_response(
+            service,
+            pending_req.conn_id,
+            WS2Pv2ReqRes {
+                id: pending_req.request.id,
+                body: module_response_body(&pending_req.request.body, res_content),
+            },
+        );
+    }
+}
+
+/// Process the response of a remote node to a request of a local module
+pub fn receive_req_res(service: &mut WS2PService, conn_id: WS2PConnId, req_res: WS2Pv2ReqRes) {
+    if let Some(pending_req) = service.pending_local_reqs.remove(&(conn_id, req_res.id)) {
+        send_network_response(
+            &service.router_sender,
+            network_response(&pending_req, req_res.body),
+        );
+    } else {
+        debug!("WS2P: receive response to unknown request #{}.", req_res.id);
+    }
+}
+
+/// Build the response to a remote request from the response of a local module
+pub fn module_response_body(
+    request: &WS2Pv2RequestBody,
+    res_content: DursResContent,
+) -> WS2Pv2ReqResBody {
+    match (*request, res_content) {
+        (
+            WS2Pv2RequestBody::Current,
+            DursResContent::BlockchainResponse(BlockchainResponse::CurrentBlockstamp(blockstamp)),
+        ) => WS2Pv2ReqResBody::Current(blockstamp),
+        (
+            WS2Pv2RequestBody::BlocksHashs(_, _),
+            DursResContent::BlockchainResponse(BlockchainResponse::Chunk(blocks)),
+        ) => WS2Pv2ReqResBody::BlocksHashs(
+            blocks
+                .into_iter()
+                .filter_map(|block| block.hash)
+                .map(|block_hash| block_hash.0)
+                .collect(),
+        ),
+        (
+            WS2Pv2RequestBody::Chunk(_, _),
+            DursResContent::BlockchainResponse(BlockchainResponse::Chunk(blocks)),
+        ) => WS2Pv2ReqResBody::Chunk(blocks),
+        (
+            WS2Pv2RequestBody::ChunkByHash(blockstamp),
+            DursResContent::BlockchainResponse(BlockchainResponse::Chunk(blocks)),
+        ) => {
+            // The requested block must be the last block of the chunk
+            if blocks.last().map(Document::blockstamp) == Some(blockstamp) {
+                WS2Pv2ReqResBody::Chunk(blocks)
+            } else {
+                WS2Pv2ReqResBody::None
+            }
+        }
+        (
+            WS2Pv2RequestBody::WotPool(folders_count, min_cert),
+            DursResContent::MemPoolResponse(MemPoolResponse::AllPendingDocs(_, pending_docs)),
+        ) => wot_pool_body(&pending_docs, folders_count, min_cert),
+        _ => WS2Pv2ReqResBody::None,
+    }
+}
+
+/// Build the response to a wot pool request: the folders of the identities having at least
+/// `min_cert` pending certifications, and the pending certifications of the other identities
+fn wot_pool_body(pending_docs: &PendingDocs, folders_count: u16, min_cert: u8) -> WS2Pv2ReqResBody {
+    let folders_count = std::cmp::min(folders_count, *constants::WS2P_REQ_MAX_WOT_POOL_FOLDERS);
+    let mut folders = Vec::new();
+    let mut folders_pubkeys = HashSet::new();
+    for idty in &pending_docs.identities {
+        if folders.len() >= folders_count as usize {
+            break;
+        }
+        let pubkey: PubKey = idty.issuers()[0];
+        let membership = pending_docs.memberships.iter().find(|membership| {
+            membership.issuers()[0] == pubkey
+                && membership.membership() == MembershipType::In()
+                && membership.identity_username() == idty.username()
+        });
+        if let Some(membership) = membership {
+            let certs: Vec<_> = pending_docs
+                .certifications
+                .iter()
+                .filter(|cert| *cert.target() == pubkey)
+                .map(TextDocument::to_compact_document)
+                .collect();
+            if certs.len() >= min_cert as usize {
+                folders_pubkeys.insert(pubkey);
+                folders.push(WotPoolFolder {
+                    idty: idty.to_compact_document(),
+                    membership: CompactPoolMembershipDoc {
+                        blockstamp: membership.blockstamp(),
+                        signature: membership.signatures()[0],
+                    },
+                    certs,
+                });
+            }
+        }
+    }
+    let other_certs = pending_docs
+        .certifications
+        .iter()
+        .filter(|cert| !folders_pubkeys.contains(cert.target()))
+        .map(TextDocument::to_compact_document)
+        .collect();
+
+    WS2Pv2ReqResBody::WotPool(other_certs, folders)
+}
+
+/// Build the response to a local request from the response of the remote node
+fn network_response(pending_req: &PendingLocalReq, body: WS2Pv2ReqResBody) -> NetworkResponse {
+    let req_full_id = pending_req.req_full_id;
+    let receiver = pending_req.receiver;
+    match (pending_req.content, body) {
+        (NetworkRequestContent::GetCurrent, WS2Pv2ReqResBody::Current(blockstamp)) => {
+            NetworkResponse::Current(req_full_id, receiver, blockstamp)
+        }
+        (NetworkRequestContent::GetBlocksHashs { .. }, WS2Pv2ReqResBody::BlocksHashs(hashs)) => {
+            NetworkResponse::BlocksHashs(req_full_id, receiver, hashs)
+        }
+        (NetworkRequestContent::GetChunk { .. }, WS2Pv2ReqResBody::Chunk(blocks))
+        | (NetworkRequestContent::GetChunkByHash(_), WS2Pv2ReqResBody::Chunk(blocks)) => {
+            NetworkResponse::Chunk(req_full_id, receiver, blocks)
+        }
+        (_, WS2Pv2ReqResBody::None) | (_, WS2Pv2ReqResBody::BadRequest(_)) => {
+            NetworkResponse::Error(req_full_id, OldNetworkRequestError::UnknowError())
+        }
+        _ => NetworkResponse::Error(req_full_id, OldNetworkRequestError::WrongFormat()),
+    }
+}
@@ -0,0 +1,54 @@
+//  Copyright (C) 2018  The Durs Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Sub-module managing the responses sent to the remote nodes and to the local modules.
+
+use crate::constants;
+use crate::services::{WS2PConnId, WS2PService};
+use durs_common_tools::fatal_error;
+use durs_message::responses::DursResContent;
+use durs_message::*;
+use durs_module::{ModuleStaticName, RouterThreadMessage};
+use durs_network::requests::NetworkResponse;
+use durs_ws2p_messages::v2::payload_container::WS2Pv2MessagePayload;
+use durs_ws2p_messages::v2::req_responses::WS2Pv2ReqRes;
+use std::sync::mpsc;
+
+/// Send the response to a request of a remote node
+pub fn send_req_response(service: &WS2PService, conn_id: WS2PConnId, req_res: WS2Pv2ReqRes) {
+    let req_id = req_res.id;
+    if !service.send_payload(conn_id, WS2Pv2MessagePayload::ReqRes(req_res)) {
+        debug!("WS2P: fail to send response to request #{}.", req_id);
+    }
+}
+
+/// Send the response to a network request of a local module
+pub fn send_network_response(
+    router_sender: &mpsc::Sender<RouterThreadMessage<DursMsg>>,
+    response: NetworkResponse,
+) {
+    let req_full_id = response.get_req_full_id();
+    if router_sender
+        .send(RouterThreadMessage::ModuleMessage(DursMsg::Response {
+            res_from: ModuleStaticName(constants::MODULE_NAME),
+            res_to: req_full_id.0,
+            req_id: req_full_id.1,
+            res_content: DursResContent::NetworkResponse(response),
+        }))
+        .is_err()
+    {
+        fatal_error!("WS2P module fail to send response to router !");
+    }
+}
@@ -0,0 +1,578 @@
+//  Copyright (C) 2018  The Durs Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Gossip of the blocks, documents, peers and heads between the WS2P v2 connections.
+
+use crate::constants;
+use crate::services::WS2PConnId;
+use dubp_documents::documents::block::BlockDocument;
+use dubp_documents::documents::certification::CertificationDocument;
+use dubp_documents::documents::identity::IdentityDocument;
+use dubp_documents::documents::membership::MembershipDocument;
+use dubp_documents::documents::revocation::RevocationDocument;
+use dubp_documents::documents::transaction::TransactionDocument;
+use dubp_documents::documents::DUBPDocument;
+use dup_crypto::hashs::Hash;
+use dup_crypto::keys::text_signable::TextSignable;
+use durs_common_tools::fatal_error;
+use durs_network_documents::network_head::NetworkHead;
+use durs_network_documents::network_head_v2::{NetworkHeadMessage, NetworkHeadV2};
+use durs_network_documents::network_head_v3::NetworkHeadV3;
+use durs_network_documents::network_peer::PeerCardV11;
+use durs_ws2p_messages::v2::payload_container::WS2Pv2MessagePayload;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::mem;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Item that can be gossiped
+#[derive(Debug, Clone, PartialEq)]
+pub enum GossipItem {
+    /// Block
+    Block(Box<BlockDocument>),
+    /// Pending identity
+    Identity(Box<IdentityDocument>),
+    /// Pending membership
+    Membership(Box<MembershipDocument>),
+    /// Pending certification
+    Certification(Box<CertificationDocument>),
+    /// Pending revocation
+    Revocation(Box<RevocationDocument>),
+    /// Pending transaction
+    Transaction(Box<TransactionDocument>),
+    /// Peer card
+    Peer(Box<PeerCardV11>),
+    /// Head v2
+    HeadV2(Box<NetworkHeadV2>),
+    /// Head v3
+    HeadV3(Box<NetworkHeadV3>),
+    /// Key of an item announced to a LOW flow connection
+    InventoryHash(Hash),
+}
+
+impl GossipItem {
+    /// Key of the item in the seen cache.
+    /// Items are identified by a hash of their whole content: a key claimed by the sender
+    /// (block hash, signatures) is not verified at receipt, so a forged item could otherwise
+    /// shadow the genuine one.
+    pub fn key(&self) -> Hash {
+        match *self {
+            GossipItem::Block(ref block) => hash_of(block),
+            GossipItem::Identity(ref doc) => hash_of(doc),
+            GossipItem::Membership(ref doc) => hash_of(doc),
+            GossipItem::Certification(ref doc) => hash_of(doc),
+            GossipItem::Revocation(ref doc) => hash_of(doc),
+            GossipItem::Transaction(ref doc) => hash_of(doc),
+            GossipItem::Peer(ref peer) => hash_of(peer),
+            GossipItem::HeadV2(ref head) => hash_of(head),
+            GossipItem::HeadV3(ref head) => hash_of(head),
+            GossipItem::InventoryHash(hash) => hash,
+        }
+    }
+    /// Convert a document stored in the local pool into a gossip item
+    pub fn from_pool_doc(doc: DUBPDocument) -> Option<GossipItem> {
+        match doc {
+            DUBPDocument::Block(_) => None,
+            DUBPDocument::Identity(doc) => Some(GossipItem::Identity(Box::new(doc))),
+            DUBPDocument::Membership(doc) => Some(GossipItem::Membership(Box::new(doc))),
+            DUBPDocument::Certification(doc) => Some(GossipItem::Certification(doc)),
+            DUBPDocument::Revocation(doc) => Some(GossipItem::Revocation(doc)),
+            DUBPDocument::Transaction(doc) => Some(GossipItem::Transaction(doc)),
+        }
+    }
+    /// Is the item relayed as soon as it is received ?
+    /// (the blocks and the pending documents are relayed only after their local validation)
+    pub fn relay_on_receipt(&self) -> bool {
+        match *self {
+            GossipItem::Peer(_) | GossipItem::HeadV2(_) | GossipItem::HeadV3(_) => true,
+            _ => false,
+        }
+    }
+    /// Is the signature of the item valid ?
+    /// Only the peer cards and the heads are checked here, because they are relayed (and their
+    /// endpoints dialed) on receipt: the other items are checked by the module that validates them.
+    pub fn verify_signature(&self) -> bool {
+        match *self {
+            GossipItem::Peer(ref peer) => peer.verify().is_ok(),
+            GossipItem::HeadV2(ref head) => match (&head.message, &head.message_v2) {
+                (NetworkHeadMessage::V2(_), NetworkHeadMessage::V2(_)) => {
+                    NetworkHead::V2(head.clone()).verify()
+                }
+                _ => false,
+            },
+            GossipItem::HeadV3(ref head) => head.verify().is_ok(),
+            _ => true,
+        }
+    }
+    /// Is the item only announced to the LOW flow connections ?
+    /// (the blocks and the peer cards are always sent entirely)
+    pub fn announced_in_low_flow(&self) -> bool {
+        match *self {
+            GossipItem::Block(_) | GossipItem::Peer(_) | GossipItem::InventoryHash(_) => false,
+            _ => true,
+        }
+    }
+}
+
+fn hash_of<T: Serialize>(datas: &T) -> Hash {
+    match bincode::serialize(datas) {
+        Ok(bin_datas) => Hash::compute(&bin_datas),
+        Err(_) => fatal_error!("Dev error: fail to serialize gossip item !"),
+    }
+}
+
+/// Split a payload into gossip items (empty if the payload can not be gossiped)
+pub fn split_payload(payload: WS2Pv2MessagePayload) -> Vec<GossipItem> {
+    match payload {
+        WS2Pv2MessagePayload::Blocks(blocks) => blocks
+            .into_iter()
+            .map(|block| GossipItem::Block(Box::new(block)))
+            .collect(),
+        WS2Pv2MessagePayload::PendingIdentities(docs) => docs
+            .into_iter()
+            .map(|doc| GossipItem::Identity(Box::new(doc)))
+            .collect(),
+        WS2Pv2MessagePayload::PendingMemberships(docs) => docs
+            .into_iter()
+            .map(|doc| GossipItem::Membership(Box::new(doc)))
+            .collect(),
+        WS2Pv2MessagePayload::PendingCerts(docs) => docs
+            .into_iter()
+            .map(|doc| GossipItem::Certification(Box::new(doc)))
+            .collect(),
+        WS2Pv2MessagePayload::PendingRevocations(docs) => docs
+            .into_iter()
+            .map(|doc| GossipItem::Revocation(Box::new(doc)))
+            .collect(),
+        WS2Pv2MessagePayload::PendingTxs(docs) => docs
+            .into_iter()
+            .map(|doc| GossipItem::Transaction(Box::new(doc)))
+            .collect(),
+        WS2Pv2MessagePayload::Peers(peers) => peers
+            .into_iter()
+            .map(|peer| GossipItem::Peer(Box::new(peer)))
+            .collect(),
+        WS2Pv2MessagePayload::Headsv2(heads) => heads
+            .into_iter()
+            .map(|head| GossipItem::HeadV2(Box::new(head)))
+            .collect(),
+        WS2Pv2MessagePayload::Heads3(heads) => heads
+            .into_iter()
+            .map(|head| GossipItem::HeadV3(Box::new(head)))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Group gossip items into payloads (consecutive items of the same kind share the same payload)
+pub fn group_items<I: IntoIterator<Item = GossipItem>>(items: I) -> Vec<WS2Pv2MessagePayload> {
+    let max = *constants::WS2P_GOSSIP_MAX_ITEMS_PER_MSG;
+    let mut groups: Vec<Vec<GossipItem>> = Vec::new();
+    for item in items {
+        let same_group = groups
+            .last()
+            .map(|group| {
+                group.len() < max && mem::discriminant(&group[0]) == mem::discriminant(&item)
+            })
+            .unwrap_or(false);
+        if let (true, Some(group)) = (same_group, groups.last_mut()) {
+            group.push(item);
+        } else {
+            groups.push(vec![item]);
+        }
+    }
+    groups.into_iter().map(group_to_payload).collect()
+}
+
+macro_rules! unbox_items {
+    ($items:expr, $variant:path) => {
+        $items
+            .into_iter()
+            .filter_map(|item| {
+                if let $variant(item) = item {
+                    Some(*item)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    };
+}
+
+/// Convert a non-empty group of items of the same kind into a payload
+fn group_to_payload(group: Vec<GossipItem>) -> WS2Pv2MessagePayload {
+    match group[0] {
+        GossipItem::Block(_) => {
+            WS2Pv2MessagePayload::Blocks(unbox_items!(group, GossipItem::Block))
+        }
+        GossipItem::Identity(_) => {
+            WS2Pv2MessagePayload::PendingIdentities(unbox_items!(group, GossipItem::Identity))
+        }
+        GossipItem::Membership(_) => {
+            WS2Pv2MessagePayload::PendingMemberships(unbox_items!(group, GossipItem::Membership))
+        }
+        GossipItem::Certification(_) => {
+            WS2Pv2MessagePayload::PendingCerts(unbox_items!(group, GossipItem::Certification))
+        }
+        GossipItem::Revocation(_) => {
+            WS2Pv2MessagePayload::PendingRevocations(unbox_items!(group, GossipItem::Revocation))
+        }
+        GossipItem::Transaction(_) => {
+            WS2Pv2MessagePayload::PendingTxs(unbox_items!(group, GossipItem::Transaction))
+        }
+        GossipItem::Peer(_) => WS2Pv2MessagePayload::Peers(unbox_items!(group, GossipItem::Peer)),
+        GossipItem::HeadV2(_) => {
+            WS2Pv2MessagePayload::Headsv2(unbox_items!(group, GossipItem::HeadV2))
+        }
+        GossipItem::HeadV3(_) => {
+            WS2Pv2MessagePayload::Heads3(unbox_items!(group, GossipItem::HeadV3))
+        }
+        GossipItem::InventoryHash(_) => {
+            WS2Pv2MessagePayload::Inventory(group.iter().map(GossipItem::key).collect())
+        }
+    }
+}
+
+/// Cache of the gossip items already exchanged with each connection
+#[derive(Debug, Default)]
+pub struct SeenCache {
+    /// For each item key: time of first sight and connections that already know the item
+    items: HashMap<Hash, (u64, HashSet<WS2PConnId>)>,
+    /// Time of the last purge
+    last_purge_time: u64,
+}
+
+impl SeenCache {
+    /// Is the item already known ?
+    pub fn contains(&self, key: &Hash) -> bool {
+        self.items.contains_key(key)
+    }
+    /// Record that the connection knows the item, returns false if it was already known
+    pub fn mark(&mut self, key: Hash, conn_id: WS2PConnId) -> bool {
+        self.items
+            .entry(key)
+            .or_insert_with(|| (now_in_secs(), HashSet::new()))
+            .1
+            .insert(conn_id)
+    }
+    /// Forget that the connection knows the item
+    pub fn unmark(&mut self, key: &Hash, conn_id: WS2PConnId) {
+        if let Some((_, conns)) = self.items.get_mut(key) {
+            conns.remove(&conn_id);
+        }
+    }
+    /// Remove expired items (at most once per second)
+    pub fn purge(&mut self) {
+        let now = now_in_secs();
+        if now == self.last_purge_time {
+            return;
+        }
+        self.last_purge_time = now;
+        purge_timed_map(
+            &mut self.items,
+            now,
+            *constants::WS2P_GOSSIP_SEEN_CACHE_TTL_IN_SECS,
+            *constants::WS2P_GOSSIP_SEEN_CACHE_MAX_SIZE,
+        );
+    }
+}
+
+/// Remove the expired entries of a map, then the oldest ones if it is still too large
+fn purge_timed_map<V>(map: &mut HashMap<Hash, (u64, V)>, now: u64, ttl: u64, max_size: usize) {
+    map.retain(|_, (time, _)| *time + ttl > now);
+    if map.len() > max_size {
+        let mut times: Vec<u64> = map.values().map(|(time, _)| *time).collect();
+        times.sort_unstable();
+        let min_time = times[map.len() - max_size];
+        map.retain(|_, (time, _)| *time >= min_time);
+    }
+}
+
+/// Inventory of the LOW flow mode: the items announced to the LOW flow connections
+/// (to answer their GET_INVENTORY_ITEMS messages) and the items requested to the remote nodes
+#[derive(Debug, Default)]
+pub struct Inventory {
+    /// Announced items with the time of their announcement
+    items: HashMap<Hash, (u64, GossipItem)>,
+    /// Time of the requests sent for the items announced by the remote nodes
+    requested: HashMap<Hash, (u64, ())>,
+    /// Time of the last purge
+    last_purge_time: u64,
+}
+
+impl Inventory {
+    /// Keep an announced item
+    pub fn insert(&mut self, key: Hash, item: GossipItem) {
+        self.items.insert(key, (now_in_secs(), item));
+    }
+    /// Get an announced item
+    pub fn get(&self, key: &Hash) -> Option<&GossipItem> {
+        self.items.get(key).map(|(_, item)| item)
+    }
+    /// Record the request of a remote item, returns false if the item is already requested
+    pub fn request(&mut self, key: Hash) -> bool {
+        let now = now_in_secs();
+        let timeout = *constants::WS2P_INVENTORY_REQUEST_TIMEOUT_IN_SECS;
+        match self.requested.get(&key) {
+            Some((request_time, _)) if *request_time + timeout > now => false,
+            _ => {
+                self.requested.insert(key, (now, ()));
+                true
+            }
+        }
+    }
+    /// Remove expired items and requests (at most once per second)
+    pub fn purge(&mut self) {
+        let now = now_in_secs();
+        if now == self.last_purge_time {
+            return;
+        }
+        self.last_purge_time = now;
+        let max_size = *constants::WS2P_INVENTORY_MAX_SIZE;
+        purge_timed_map(
+            &mut self.items,
+            now,
+            *constants::WS2P_INVENTORY_TTL_IN_SECS,
+            max_size,
+        );
+        purge_timed_map(
+            &mut self.requested,
+            now,
+            *constants::WS2P_INVENTORY_REQUEST_TIMEOUT_IN_SECS,
+            max_size,
+        );
+    }
+}
+
+fn now_in_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("SystemTime before UNIX EPOCH!")
+        .as_secs()
+}
+
+/// Outgoing gossip queue of a connection.
+/// The queue is bounded (the oldest items are dropped when it is full)
+/// and the number of items sent per second is limited.
+#[derive(Debug, Default)]
+pub struct OutgoingQueue {
+    items: VecDeque<GossipItem>,
+    current_sec: u64,
+    sent_in_current_sec: usize,
+}
+
+impl OutgoingQueue {
+    /// Queue an item, returns the item dropped if the queue is full
+    pub fn push(&mut self, item: GossipItem) -> Option<GossipItem> {
+        self.items.push_back(item);
+        if self.items.len() > *constants::WS2P_OUTGOING_QUEUE_MAX_LEN {
+            self.items.pop_front()
+        } else {
+            None
+        }
+    }
+    /// Number of queued items
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+    /// Is the queue empty ?
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+    /// Take the payloads that can be sent now
+    pub fn pop_payloads(&mut self) -> Vec<WS2Pv2MessagePayload> {
+        let now = now_in_secs();
+        if now != self.current_sec {
+            self.current_sec = now;
+            self.sent_in_current_sec = 0;
+        }
+        let budget =
+            (*constants::WS2P_OUTGOING_MAX_ITEMS_PER_SEC).saturating_sub(self.sent_in_current_sec);
+        if budget == 0 || self.items.is_empty() {
+            return vec![];
+        }
+        let items = if self.items.len() > budget {
+            let remaining_items = self.items.split_off(budget);
+            mem::replace(&mut self.items, remaining_items)
+        } else {
+            mem::replace(&mut self.items, VecDeque::new())
+        };
+        self.sent_in_current_sec += items.len();
+        group_items(items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dubp_documents::{Blockstamp, CurrencyName};
+    use dubp_documents_tests_tools::mocks::gen_empty_timed_blocks;
+    use dup_crypto::keys::{ed25519, KeyPair, PrivKey, PubKey};
+    use durs_network_documents::NodeId;
+
+    fn blocks_items(count: usize) -> Vec<GossipItem> {
+        gen_empty_timed_blocks(count, 0)
+            .into_iter()
+            .map(|block| GossipItem::Block(Box::new(block)))
+            .collect()
+    }
+
+    #[test]
+    fn test_split_and_group_items() {
+        let blocks = gen_empty_timed_blocks(3, 0);
+        let items = split_payload(WS2Pv2MessagePayload::Blocks(blocks.clone()));
+        assert_eq!(3, items.len());
+        assert_eq!(
+            vec![WS2Pv2MessagePayload::Blocks(blocks)],
+            group_items(items)
+        );
+        assert!(split_payload(WS2Pv2MessagePayload::Ko(100)).is_empty());
+
+        let max = *constants::WS2P_GOSSIP_MAX_ITEMS_PER_MSG;
+        let payloads = group_items(blocks_items(max + 1));
+        assert_eq!(2, payloads.len());
+        match &payloads[1] {
+            WS2Pv2MessagePayload::Blocks(blocks) => assert_eq!(1, blocks.len()),
+            payload => panic!("unexpected payload: {:?}", payload),
+        }
+    }
+
+    #[test]
+    fn test_forged_block_key() {
+        let block = gen_empty_timed_blocks(1, 0).remove(0);
+        let mut forged_block = block.clone();
+        forged_block.median_time += 1;
+        assert_eq!(block.hash, forged_block.hash);
+        assert_ne!(
+            GossipItem::Block(Box::new(block)).key(),
+            GossipItem::Block(Box::new(forged_block)).key()
+        );
+    }
+
+    #[test]
+    fn test_seen_cache() {
+        let key = Hash::default();
+        let mut seen_cache = SeenCache::default();
+        assert!(!seen_cache.contains(&key));
+        assert!(seen_cache.mark(key, WS2PConnId::Incoming(0)));
+        assert!(!seen_cache.mark(key, WS2PConnId::Incoming(0)));
+        assert!(seen_cache.mark(key, WS2PConnId::Outgoing(0)));
+        seen_cache.unmark(&key, WS2PConnId::Outgoing(0));
+        assert!(seen_cache.mark(key, WS2PConnId::Outgoing(0)));
+        assert!(seen_cache.contains(&key));
+    }
+
+    #[test]
+    fn test_inventory() {
+        let item = blocks_items(1).pop().expect("safe unwrap");
+        let key = item.key();
+        let mut inventory = Inventory::default();
+        assert_eq!(None, inventory.get(&key));
+        inventory.insert(key, item.clone());
+        assert_eq!(Some(&item), inventory.get(&key));
+        assert!(inventory.request(key));
+        assert!(!inventory.request(key));
+
+        let keys = vec![key, Hash::default()];
+        assert_eq!(
+            vec![WS2Pv2MessagePayload::Inventory(keys.clone())],
+            group_items(keys.into_iter().map(GossipItem::InventoryHash))
+        );
+    }
+
+    #[test]
+    fn test_outgoing_queue() {
+        let max_len = *constants::WS2P_OUTGOING_QUEUE_MAX_LEN;
+        let mut queue = OutgoingQueue::default();
+        let items = blocks_items(max_len + 1);
+        let mut dropped = vec![];
+        for item in items.iter().cloned() {
+            if let Some(item) = queue.push(item) {
+                dropped.push(item);
+            }
+        }
+        assert_eq!(vec![items[0].clone()], dropped);
+        assert_eq!(max_len, queue.len());
+
+        let max_per_sec = *constants::WS2P_OUTGOING_MAX_ITEMS_PER_SEC;
+        let sent_items: usize = queue
+            .pop_payloads()
+            .into_iter()
+            .map(|payload| split_payload(payload).len())
+            .sum();
+        // Items not sent this second stay in the queue
+        assert!(sent_items <= max_per_sec);
+        assert_eq!(max_len, sent_items + queue.len());
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let keypair = ed25519::KeyPairFromSeedGenerator::generate(&[1u8; 32]);
+        let privkey = PrivKey::Ed25519(keypair.private_key());
+        let pubkey = PubKey::Ed25519(keypair.public_key());
+
+        let mut peer = PeerCardV11 {
+            currency_name: CurrencyName(String::from("g1")),
+            issuer: pubkey,
+            node_id: NodeId(0),
+            blockstamp: Blockstamp::default(),
+            endpoints: vec![],
+            endpoints_str: vec![String::from("WS2P 2 localhost 20901")],
+            sig: None,
+        };
+        assert!(!GossipItem::Peer(Box::new(peer.clone())).verify_signature());
+        peer.sign(privkey).expect("Fail to sign peer card");
+        assert!(GossipItem::Peer(Box::new(peer.clone())).verify_signature());
+        // Endpoints replaced by a forger
+        peer.endpoints_str = vec![String::from("WS2P 2 forged.host 20901")];
+        assert!(!GossipItem::Peer(Box::new(peer)).verify_signature());
+
+        let mut head = NetworkHeadV3 {
+            currency_name: CurrencyName(String::from("g1")),
+            api_outgoing_conf: 0,
+            api_incoming_conf: 0,
+            free_member_rooms: 0,
+            free_mirror_rooms: 0,
+            node_id: NodeId(0),
+            pubkey,
+            blockstamp: Blockstamp::default(),
+            software: String::from("durs"),
+            soft_version: String::from("0.2.0"),
+            signature: None,
+            step: 0,
+        };
+        head.sign(privkey).expect("Fail to sign head");
+        assert!(GossipItem::HeadV3(Box::new(head.clone())).verify_signature());
+        head.blockstamp.id.0 += 1;
+        assert!(!GossipItem::HeadV3(Box::new(head)).verify_signature());
+
+        let head_v2 = NetworkHeadV2 {
+            message: NetworkHeadMessage::Other(),
+            sig: fake_sig(),
+            message_v2: NetworkHeadMessage::Other(),
+            sig_v2: fake_sig(),
+            step: 0,
+            uid: None,
+        };
+        assert!(!GossipItem::HeadV2(Box::new(head_v2)).verify_signature());
+
+        // The other items are checked by the modules that validate them
+        assert!(blocks_items(1)[0].verify_signature());
+    }
+
+    fn fake_sig() -> dup_crypto::keys::Sig {
+        dup_crypto::keys::Sig::Ed25519(ed25519::Signature([0u8; 64]))
+    }
+}
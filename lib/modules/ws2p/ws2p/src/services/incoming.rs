@@ -0,0 +1,49 @@
+//  Copyright (C) 2018  The Durs Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! WS2P incoming Services
+
+use crate::controllers::incoming_connections::listen_on_ws2p_v2_endpoint_with;
+use crate::services::{forward_controller_msgs, WS2PConnId, WS2PServiceMsg};
+use dubp_documents::CurrencyName;
+use durs_ws2p_protocol::MySelfWs2pNode;
+use std::sync::mpsc;
+use std::thread;
+
+/// Listen incoming WS2P v2 connections in a dedicated thread
+pub fn listen(
+    currency: CurrencyName,
+    self_node: MySelfWs2pNode,
+    host: String,
+    port: u16,
+    service_sender: mpsc::Sender<WS2PServiceMsg>,
+) {
+    thread::spawn(move || {
+        let mut next_conn_id = 0;
+        if let Err(e) = listen_on_ws2p_v2_endpoint_with(
+            &currency,
+            || {
+                let conn_id = WS2PConnId::Incoming(next_conn_id);
+                next_conn_id += 1;
+                forward_controller_msgs(conn_id, service_sender.clone())
+            },
+            &self_node,
+            &host,
+            port,
+        ) {
+            error!("WS2P: fail to listen on {}:{} : {}", host, port, e);
+        }
+    });
+}
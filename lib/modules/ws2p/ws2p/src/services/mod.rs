@@ -15,12 +15,42 @@
 
 //! WS2P Services
 
-use dup_crypto::keys::KeyPairEnum;
-use durs_network_documents::*;
-use durs_ws2p_messages::v2::api_features::WS2PFeatures;
-
+pub mod gossip;
+pub mod incoming;
 pub mod outgoing;
 
+use crate::constants;
+use crate::events::sent::send_network_event;
+use crate::requests::received::PendingRemoteReq;
+use crate::requests::sent::PendingLocalReq;
+use crate::services::gossip::{GossipItem, Inventory, OutgoingQueue, SeenCache};
+use crate::services::outgoing::WS2POutgoingOrchestrator;
+use crate::*;
+use dubp_documents::CurrencyName;
+use dup_crypto::hashs::Hash;
+use durs_message::events::{BlockchainEvent, DursEvent, MemPoolEvent};
+use durs_message::requests::DursReqContent;
+use durs_message::DursMsg;
+use durs_module::{ModuleReqFullId, ModuleReqId};
+use durs_network::documents::BlockchainDocument;
+use durs_network::events::NetworkEvent;
+use durs_network_documents::network_head::NetworkHead;
+use durs_network_documents::network_peer::PeerCard;
+use durs_network_documents::NodeFullId;
+use durs_ws2p_messages::v2::payload_container::WS2Pv2MessagePayload;
+use durs_ws2p_messages::v2::WS2Pv2Message;
+use durs_ws2p_messages::WS2PMessage;
+use durs_ws2p_protocol::connection_state::WS2PConnectionState;
+use durs_ws2p_protocol::controller::{WS2PControllerEvent, WebsocketActionOrder};
+use durs_ws2p_protocol::orchestrator::OrchestratorMsg;
+use durs_ws2p_protocol::websocket::{WebsocketAction, WebsocketMessage};
+use durs_ws2p_protocol::MySelfWs2pNode;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
 /// Websocket Error
 #[derive(Debug, Copy, Clone)]
 pub enum WsError {
@@ -28,13 +58,559 @@ pub enum WsError {
     UnknownError,
 }
 
-/// Store self WS2P properties
-#[derive(Debug, Clone, PartialEq)]
-pub struct MySelfWs2pNode {
-    /// Local node id
-    pub my_node_id: NodeId,
-    /// Local network keypair
-    pub my_key_pair: KeyPairEnum,
-    /// Local node WWS2PFeatures
-    pub my_features: WS2PFeatures,
+/// WS2P connection identifier
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum WS2PConnId {
+    /// Incoming connection
+    Incoming(usize),
+    /// Outgoing connection
+    Outgoing(usize),
+}
+
+/// Message received by the WS2P service
+#[derive(Debug)]
+pub enum WS2PServiceMsg {
+    /// Message from the controller of a connection
+    Controller(WS2PConnId, Box<OrchestratorMsg<DursMsg>>),
+    /// The connection is ended
+    ConnectionEnded(WS2PConnId),
+    /// Message from the router
+    Router(Box<DursMsg>),
+}
+
+/// Forward the messages of a connection controller to the WS2P service,
+/// returns the orchestrator sender to give to the controller
+pub fn forward_controller_msgs(
+    conn_id: WS2PConnId,
+    service_sender: mpsc::Sender<WS2PServiceMsg>,
+) -> mpsc::Sender<OrchestratorMsg<DursMsg>> {
+    let (orchestrator_sender, orchestrator_receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for msg in orchestrator_receiver {
+            if service_sender
+                .send(WS2PServiceMsg::Controller(conn_id, Box::new(msg)))
+                .is_err()
+            {
+                return;
+            }
+        }
+        let _ = service_sender.send(WS2PServiceMsg::ConnectionEnded(conn_id));
+    });
+    orchestrator_sender
+}
+
+/// WS2P connection
+#[derive(Debug)]
+pub struct WS2PConnection {
+    /// Controller channel
+    pub controller: Option<mpsc::Sender<WebsocketActionOrder>>,
+    /// Remote node full id (known once the connection is established)
+    pub remote_full_id: Option<NodeFullId>,
+    /// Connection state
+    pub state: WS2PConnectionState,
+    /// Outgoing gossip queue
+    pub queue: OutgoingQueue,
+    /// The remote node demand LOW flow (only inventories of pending documents and heads)
+    pub low_flow: bool,
+}
+
+impl Default for WS2PConnection {
+    fn default() -> Self {
+        WS2PConnection {
+            controller: None,
+            remote_full_id: None,
+            state: WS2PConnectionState::TryToOpenWS,
+            queue: OutgoingQueue::default(),
+            low_flow: false,
+        }
+    }
+}
+
+impl WS2PConnection {
+    /// Send an order to the connection controller
+    pub fn send_order(&self, order: WebsocketActionOrder) -> bool {
+        if let Some(ref controller) = self.controller {
+            controller.send(order).is_ok()
+        } else {
+            false
+        }
+    }
+}
+
+/// WS2P service: manage all the WS2P v2 connections of the local node
+#[derive(Debug)]
+pub struct WS2PService {
+    /// Currency name
+    pub currency: CurrencyName,
+    /// Local node datas
+    pub self_node: MySelfWs2pNode,
+    /// Router sender
+    pub router_sender: mpsc::Sender<RouterThreadMessage<DursMsg>>,
+    /// Service sender
+    pub sender: mpsc::Sender<WS2PServiceMsg>,
+    /// Service receiver
+    receiver: mpsc::Receiver<WS2PServiceMsg>,
+    /// Pending and established connections
+    pub connections: HashMap<WS2PConnId, WS2PConnection>,
+    /// Outgoing connections orchestrator
+    pub outgoing: WS2POutgoingOrchestrator,
+    /// Time of the last attempt to open new outgoing connections
+    last_connect_time: u64,
+    /// Next identifier of the requests sent to the local modules
+    pub next_module_req_id: u32,
+    /// Next identifier of the requests sent to the remote nodes
+    pub next_ws2p_req_id: u32,
+    /// Requests of remote nodes waiting for the response of a local module
+    pub pending_remote_reqs: HashMap<ModuleReqId, PendingRemoteReq>,
+    /// Requests of local modules waiting for the response of a remote node
+    pub pending_local_reqs: HashMap<(WS2PConnId, u32), PendingLocalReq>,
+    /// Gossip items already exchanged with each connection
+    pub seen_cache: SeenCache,
+    /// Items announced to the LOW flow connections and items requested to the remote nodes
+    pub inventory: Inventory,
+}
+
+impl WS2PService {
+    /// Instantiate WS2P service
+    pub fn new(
+        currency: CurrencyName,
+        self_node: MySelfWs2pNode,
+        conf: &WS2PConf,
+        ep_file_path: Option<PathBuf>,
+        router_sender: mpsc::Sender<RouterThreadMessage<DursMsg>>,
+    ) -> WS2PService {
+        let (sender, receiver) = mpsc::channel();
+        let outgoing = WS2POutgoingOrchestrator::new(
+            currency.clone(),
+            conf,
+            self_node.clone(),
+            ep_file_path,
+            sender.clone(),
+        );
+
+        WS2PService {
+            currency,
+            self_node,
+            router_sender,
+            sender,
+            receiver,
+            connections: HashMap::new(),
+            outgoing,
+            last_connect_time: 0,
+            next_module_req_id: 0,
+            next_ws2p_req_id: 0,
+            pending_remote_reqs: HashMap::new(),
+            pending_local_reqs: HashMap::new(),
+            seen_cache: SeenCache::default(),
+            inventory: Inventory::default(),
+        }
+    }
+    /// Service main loop (until the router send the stop message)
+    pub fn run(&mut self) {
+        loop {
+            self.connect_to_new_endpoints();
+            match self.receiver.recv_timeout(Duration::from_millis(
+                *constants::WS2P_RECV_SERVICE_FREQ_IN_MS,
+            )) {
+                Ok(WS2PServiceMsg::Controller(conn_id, msg)) => {
+                    self.process_controller_msg(conn_id, *msg)
+                }
+                Ok(WS2PServiceMsg::ConnectionEnded(conn_id)) => self.connection_ended(conn_id),
+                Ok(WS2PServiceMsg::Router(msg)) => match *msg {
+                    DursMsg::Stop => break,
+                    DursMsg::Event { event_content, .. } => self.receive_event(event_content),
+                    DursMsg::Request {
+                        req_from,
+                        req_id,
+                        req_content: DursReqContent::NetworkRequest(request),
+                        ..
+                    } => requests::sent::send_network_request(
+                        self,
+                        ModuleReqFullId(req_from, req_id),
+                        request,
+                    ),
+                    DursMsg::Response {
+                        req_id,
+                        res_content,
+                        ..
+                    } => responses::received::receive_module_response(self, req_id, res_content),
+                    _ => {}
+                },
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+            requests::received::check_requests_timeouts(self);
+            requests::sent::check_requests_timeouts(self);
+            self.seen_cache.purge();
+            self.inventory.purge();
+            self.flush_queues();
+        }
+
+        // Close all connections
+        for conn in self.connections.values() {
+            conn.send_order(WebsocketActionOrder::close_with_reason(Some(
+                "Node shutdown.".to_owned(),
+            )));
+        }
+        self.outgoing.save_endpoints();
+    }
+    /// Open new outgoing connections (at most once per second)
+    fn connect_to_new_endpoints(&mut self) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("SystemTime before UNIX EPOCH!")
+            .as_secs();
+        if now > self.last_connect_time {
+            self.last_connect_time = now;
+            self.outgoing.connect_to_new_endpoints(now);
+        }
+    }
+    /// Process a message of a connection controller
+    fn process_controller_msg(&mut self, conn_id: WS2PConnId, msg: OrchestratorMsg<DursMsg>) {
+        match msg {
+            OrchestratorMsg::ControllerSender(controller) => {
+                self.connections.entry(conn_id).or_default().controller = Some(controller);
+            }
+            OrchestratorMsg::ControllerEvent { event, .. } => match event {
+                WS2PControllerEvent::NewConnEstablished {
+                    remote_full_id,
+                    low_flow,
+                    ..
+                } => self.connection_established(conn_id, remote_full_id, low_flow),
+                WS2PControllerEvent::StateChange { new_state } => {
+                    self.connections.entry(conn_id).or_default().state = new_state;
+                }
+                WS2PControllerEvent::RecvValidMsg {
+                    ws2p_msg: WS2PMessage::V2(msg_v2),
+                } => {
+                    if self.is_established(conn_id) {
+                        self.process_payload(conn_id, msg_v2.payload);
+                    }
+                }
+                WS2PControllerEvent::RecvValidMsg { .. } => {}
+            },
+            OrchestratorMsg::ModuleMessage(_) => {}
+        }
+    }
+    /// Check if the connection is established
+    fn is_established(&self, conn_id: WS2PConnId) -> bool {
+        self.connections
+            .get(&conn_id)
+            .map(|conn| conn.remote_full_id.is_some())
+            .unwrap_or(false)
+    }
+    /// New connection established
+    fn connection_established(
+        &mut self,
+        conn_id: WS2PConnId,
+        remote_full_id: NodeFullId,
+        low_flow: bool,
+    ) {
+        // Only one connection per remote node
+        let already_connected = self.connections.iter().any(|(other_conn_id, conn)| {
+            *other_conn_id != conn_id && conn.remote_full_id == Some(remote_full_id)
+        });
+        if already_connected {
+            if let Some(conn) = self.connections.get(&conn_id) {
+                conn.send_order(WebsocketActionOrder::close_with_reason(Some(
+                    "Already connected.".to_owned(),
+                )));
+            }
+            return;
+        }
+
+        info!(
+            "WS2P: connection established with {}{}.",
+            remote_full_id,
+            if low_flow { " (LOW flow)" } else { "" }
+        );
+        let conn = self.connections.entry(conn_id).or_default();
+        conn.remote_full_id = Some(remote_full_id);
+        conn.low_flow = low_flow;
+        conn.state = WS2PConnectionState::Established;
+        self.outgoing.connection_established(conn_id);
+        self.send_conn_state_change(conn_id, remote_full_id, WS2PConnectionState::Established);
+    }
+    /// The connection is ended
+    fn connection_ended(&mut self, conn_id: WS2PConnId) {
+        let established = if let Some(conn) = self.connections.remove(&conn_id) {
+            if let Some(remote_full_id) = conn.remote_full_id {
+                info!("WS2P: connection with {} closed.", remote_full_id);
+                self.send_conn_state_change(conn_id, remote_full_id, WS2PConnectionState::Close);
+                true
+            } else {
+                false
+            }
+        } else {
+            false
+        };
+        self.outgoing.connection_ended(conn_id, established);
+        requests::received::cancel_conn_requests(self, conn_id);
+        requests::sent::cancel_conn_requests(self, conn_id);
+    }
+    /// Notify the other modules of a connection state change
+    fn send_conn_state_change(
+        &self,
+        conn_id: WS2PConnId,
+        remote_full_id: NodeFullId,
+        state: WS2PConnectionState,
+    ) {
+        let url = self
+            .outgoing
+            .connections
+            .get(&conn_id)
+            .and_then(|endpoint| endpoint.get_url(true, false))
+            .unwrap_or_default();
+        send_network_event(
+            &self.router_sender,
+            NetworkEvent::ConnectionStateChange(remote_full_id, state.to_u32(), None, url),
+        );
+    }
+    /// Send a payload on an established connection
+    pub fn send_payload(&self, conn_id: WS2PConnId, payload: WS2Pv2MessagePayload) -> bool {
+        let conn = if let Some(conn) = self.connections.get(&conn_id) {
+            conn
+        } else {
+            return false;
+        };
+        let bin_msg = match WS2Pv2Message::encapsulate_payload(
+            self.currency.clone(),
+            self.self_node.my_node_id,
+            self.self_node.my_key_pair,
+            payload,
+        ) {
+            Ok((_, bin_msg)) => bin_msg,
+            Err(_) => fatal_error!("Dev error: fail to sign own message !"),
+        };
+        conn.send_order(WebsocketActionOrder {
+            ws_action: WebsocketAction::SendMessage {
+                msg: WebsocketMessage::Bin(bin_msg),
+            },
+            new_state_if_success: None,
+            new_state_if_fail: WS2PConnectionState::Unreachable,
+        })
+    }
+    /// Process the payload of a message received on an established connection
+    fn process_payload(&mut self, conn_id: WS2PConnId, payload: WS2Pv2MessagePayload) {
+        match payload {
+            WS2Pv2MessagePayload::Request(request) => {
+                requests::received::receive_request(self, conn_id, request)
+            }
+            WS2Pv2MessagePayload::ReqRes(req_res) => {
+                responses::received::receive_req_res(self, conn_id, req_res)
+            }
+            WS2Pv2MessagePayload::Inventory(keys) => self.receive_inventory(conn_id, keys),
+            WS2Pv2MessagePayload::GetInventoryItems(keys) => {
+                self.send_inventory_items(conn_id, keys)
+            }
+            payload => {
+                // Ignore the items already received
+                let mut new_items = Vec::new();
+                for item in gossip::split_payload(payload) {
+                    // A forged peer card or head must be neither relayed nor dialed
+                    if !item.verify_signature() {
+                        debug!("WS2P: drop a gossip item with an invalid signature.");
+                        continue;
+                    }
+                    let key = item.key();
+                    let already_seen = self.seen_cache.contains(&key);
+                    self.seen_cache.mark(key, conn_id);
+                    if !already_seen {
+                        new_items.push(item);
+                    }
+                }
+
+                let my_pubkey = self.self_node.get_full_id().1;
+                self.outgoing.add_endpoints(
+                    new_items
+                        .iter()
+                        .filter_map(|item| match *item {
+                            GossipItem::Peer(ref peer) if peer.issuer != my_pubkey => Some(peer),
+                            _ => None,
+                        })
+                        .flat_map(|peer| peer.endpoints.iter().cloned().map(EndpointEnum::V2)),
+                );
+                self.gossip(
+                    new_items
+                        .iter()
+                        .filter(|item| item.relay_on_receipt())
+                        .cloned()
+                        .collect(),
+                );
+                for payload in gossip::group_items(new_items) {
+                    for event in payload_to_network_events(payload) {
+                        send_network_event(&self.router_sender, event);
+                    }
+                }
+            }
+        }
+    }
+    /// Request the items of a remote inventory that are not known yet
+    fn receive_inventory(&mut self, conn_id: WS2PConnId, keys: Vec<Hash>) {
+        let mut unknown_keys = Vec::new();
+        for key in keys
+            .into_iter()
+            .take(*constants::WS2P_INVENTORY_MAX_ITEMS_PER_REQ)
+        {
+            if self.seen_cache.contains(&key) {
+                self.seen_cache.mark(key, conn_id);
+            } else if self.inventory.request(key) {
+                unknown_keys.push(key);
+            }
+        }
+        if !unknown_keys.is_empty() {
+            self.send_payload(
+                conn_id,
+                WS2Pv2MessagePayload::GetInventoryItems(unknown_keys),
+            );
+        }
+    }
+    /// Queue the announced items requested by a remote node
+    fn send_inventory_items(&mut self, conn_id: WS2PConnId, keys: Vec<Hash>) {
+        let inventory = &self.inventory;
+        let items: Vec<GossipItem> = keys
+            .iter()
+            .take(*constants::WS2P_INVENTORY_MAX_ITEMS_PER_REQ)
+            .filter_map(|key| inventory.get(key).cloned())
+            .collect();
+        if let Some(conn) = self.connections.get_mut(&conn_id) {
+            for item in items {
+                if let Some(dropped_item) = conn.queue.push(item) {
+                    debug!("WS2P: outgoing queue full, drop the oldest item.");
+                    self.seen_cache.unmark(&dropped_item.key(), conn_id);
+                }
+            }
+        }
+    }
+    /// Process an event of a local module
+    fn receive_event(&mut self, event: DursEvent) {
+        let item = match event {
+            DursEvent::BlockchainEvent(blockchain_event) => {
+                if let BlockchainEvent::StackUpValidBlock(block) = *blockchain_event {
+                    Some(GossipItem::Block(block))
+                } else {
+                    None
+                }
+            }
+            DursEvent::MemPoolEvent(MemPoolEvent::StoreNewDocInPool(doc)) => {
+                GossipItem::from_pool_doc(*doc)
+            }
+            _ => None,
+        };
+        if let Some(item) = item {
+            self.gossip(vec![item]);
+        }
+    }
+    /// Queue the items for all the established connections that do not know them yet.
+    /// The LOW flow connections only receive the keys of the pending documents and heads.
+    fn gossip(&mut self, items: Vec<GossipItem>) {
+        for item in items {
+            let key = item.key();
+            let mut announced = false;
+            for (conn_id, conn) in self.connections.iter_mut() {
+                if conn.remote_full_id.is_some() && self.seen_cache.mark(key, *conn_id) {
+                    let queued_item = if conn.low_flow && item.announced_in_low_flow() {
+                        announced = true;
+                        GossipItem::InventoryHash(key)
+                    } else {
+                        item.clone()
+                    };
+                    if let Some(dropped_item) = conn.queue.push(queued_item) {
+                        debug!("WS2P: outgoing queue full, drop the oldest item.");
+                        self.seen_cache.unmark(&dropped_item.key(), *conn_id);
+                    }
+                }
+            }
+            if announced {
+                self.inventory.insert(key, item);
+            }
+        }
+    }
+    /// Send the queued gossip items allowed by the rate limit of each connection
+    fn flush_queues(&mut self) {
+        let payloads: Vec<(WS2PConnId, WS2Pv2MessagePayload)> = self
+            .connections
+            .iter_mut()
+            .filter(|(_, conn)| conn.remote_full_id.is_some() && !conn.queue.is_empty())
+            .flat_map(|(conn_id, conn)| {
+                let conn_id = *conn_id;
+                conn.queue
+                    .pop_payloads()
+                    .into_iter()
+                    .map(move |payload| (conn_id, payload))
+            })
+            .collect();
+        for (conn_id, payload) in payloads {
+            self.send_payload(conn_id, payload);
+        }
+    }
+}
+
+/// Convert a WS2P v2 message payload into network events
+pub fn payload_to_network_events(payload: WS2Pv2MessagePayload) -> Vec<NetworkEvent> {
+    match payload {
+        WS2Pv2MessagePayload::Peers(peers) => vec![NetworkEvent::ReceivePeers(
+            peers.into_iter().map(PeerCard::V11).collect(),
+        )],
+        WS2Pv2MessagePayload::Headsv2(heads) => vec![NetworkEvent::ReceiveHeads(
+            heads
+                .into_iter()
+                .map(|head| NetworkHead::V2(Box::new(head)))
+                .collect(),
+        )],
+        WS2Pv2MessagePayload::Heads3(heads) => vec![NetworkEvent::ReceiveHeads(
+            heads
+                .into_iter()
+                .map(|head| NetworkHead::V3(Box::new(head)))
+                .collect(),
+        )],
+        WS2Pv2MessagePayload::Blocks(blocks) => vec![NetworkEvent::ReceiveBlocks(blocks)],
+        WS2Pv2MessagePayload::PendingIdentities(docs) => vec![NetworkEvent::ReceiveDocuments(
+            docs.into_iter()
+                .map(|doc| BlockchainDocument::Identity(Box::new(doc)))
+                .collect(),
+        )],
+        WS2Pv2MessagePayload::PendingMemberships(docs) => vec![NetworkEvent::ReceiveDocuments(
+            docs.into_iter()
+                .map(|doc| BlockchainDocument::Membership(Box::new(doc)))
+                .collect(),
+        )],
+        WS2Pv2MessagePayload::PendingCerts(docs) => vec![NetworkEvent::ReceiveDocuments(
+            docs.into_iter()
+                .map(|doc| BlockchainDocument::Certification(Box::new(doc)))
+                .collect(),
+        )],
+        WS2Pv2MessagePayload::PendingRevocations(docs) => vec![NetworkEvent::ReceiveDocuments(
+            docs.into_iter()
+                .map(|doc| BlockchainDocument::Revocation(Box::new(doc)))
+                .collect(),
+        )],
+        WS2Pv2MessagePayload::PendingTxs(docs) => vec![NetworkEvent::ReceiveDocuments(
+            docs.into_iter()
+                .map(|doc| BlockchainDocument::Transaction(Box::new(doc)))
+                .collect(),
+        )],
+        _ => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload_to_network_events() {
+        let block = dubp_documents_tests_tools::mocks::gen_empty_timed_blocks(1, 0)
+            .pop()
+            .expect("safe unwrap");
+        match &payload_to_network_events(WS2Pv2MessagePayload::Blocks(vec![block.clone()]))[..] {
+            [NetworkEvent::ReceiveBlocks(blocks)] => assert_eq!(&vec![block], blocks),
+            events => panic!("unexpected events: {:?}", events),
+        }
+        match &payload_to_network_events(WS2Pv2MessagePayload::PendingTxs(vec![]))[..] {
+            [NetworkEvent::ReceiveDocuments(docs)] => assert!(docs.is_empty()),
+            events => panic!("unexpected events: {:?}", events),
+        }
+        assert!(payload_to_network_events(WS2Pv2MessagePayload::Ko(100)).is_empty());
+    }
 }
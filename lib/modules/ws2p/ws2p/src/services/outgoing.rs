@@ -15,37 +15,20 @@
 
 //! WS2P outgoing Services
 
-use crate::services::WsError;
+use crate::constants;
+use crate::services::{forward_controller_msgs, WS2PConnId, WS2PServiceMsg};
+use crate::ws2p_db::{self, DbEndpoint};
 use crate::*;
 use dubp_documents::CurrencyName;
-use durs_network_documents::{NodeFullId, NodeId};
-use durs_ws2p_protocol::connection_state::WS2PConnectionState;
-use durs_ws2p_protocol::controller::WebsocketActionOrder;
-use durs_ws2p_protocol::orchestrator::OrchestratorMsg;
+use durs_ws2p_messages::v2::connect::WS2Pv2ConnectType;
 use durs_ws2p_protocol::MySelfWs2pNode;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use std::sync::mpsc;
-
-#[derive(Debug, Clone)]
-/// Data allowing the service to manage an outgoing connection
-pub struct OutgoingConnection {
-    /// Endpoint
-    pub endpoint: EndpointEnum,
-    /// Controller channel
-    pub controller: mpsc::Sender<WebsocketActionOrder>,
-}
-
-#[derive(Debug, Copy, Clone)]
-/// Endpoind whose last connection attempt failed
-pub struct EndpointInError {
-    /// Last attemp time
-    pub last_attempt_time: u64,
-    /// Error status
-    pub error: WS2PConnectionState,
-}
+use std::thread;
 
 #[derive(Debug)]
-/// Outgoing connection management service
+/// Orchestrator of the outgoing connections
 pub struct WS2POutgoingOrchestrator {
     /// Currency Name
     pub currency: CurrencyName,
@@ -53,16 +36,16 @@ pub struct WS2POutgoingOrchestrator {
     pub self_node: MySelfWs2pNode,
     /// Outgoing connections quota
     pub quota: usize,
-    /// List of established connections
-    pub connections: HashMap<NodeFullId, OutgoingConnection>,
-    /// List of endpoinds whose last connection attempt failed
-    pub endpoints_in_error: HashMap<NodeFullId, EndpointInError>,
-    /// List of endpoints that have never been contacted
-    pub never_try_endpoints: Vec<EndpointEnum>,
-    /// Service receiver
-    pub receiver: mpsc::Receiver<OrchestratorMsg<DursMsg>>,
-    /// Orchestrator sender
-    pub sender: mpsc::Sender<OrchestratorMsg<DursMsg>>,
+    /// Known endpoints
+    pub endpoints: HashMap<EndpointEnum, DbEndpoint>,
+    /// Endpoints database file path (None for in-memory endpoints)
+    pub ep_file_path: Option<PathBuf>,
+    /// Endpoints of the pending or established outgoing connections
+    pub connections: HashMap<WS2PConnId, EndpointEnum>,
+    /// Next outgoing connection id
+    next_conn_id: usize,
+    /// Service sender
+    pub service_sender: mpsc::Sender<WS2PServiceMsg>,
 }
 
 impl WS2POutgoingOrchestrator {
@@ -71,42 +54,203 @@ impl WS2POutgoingOrchestrator {
         currency: CurrencyName,
         ws2p_conf: &WS2PConf,
         self_node: MySelfWs2pNode,
+        ep_file_path: Option<PathBuf>,
+        service_sender: mpsc::Sender<WS2PServiceMsg>,
     ) -> WS2POutgoingOrchestrator {
-        // Create service channel
-        let (sender, receiver) = mpsc::channel();
+        // Get endpoints in file
+        let endpoints = if let Some(ref ep_file_path) = ep_file_path {
+            ws2p_db::get_endpoints(ep_file_path).unwrap_or_else(|err| {
+                fatal_error!("WS2P: fail to load endpoints from DB: {:?}", err)
+            })
+        } else {
+            HashMap::new()
+        };
+        info!("Load {} endpoints from DB !", endpoints.len());
 
-        WS2POutgoingOrchestrator {
+        let mut orchestrator = WS2POutgoingOrchestrator {
             currency,
             quota: ws2p_conf.outcoming_quota,
+            endpoints,
+            ep_file_path,
             connections: HashMap::with_capacity(ws2p_conf.outcoming_quota),
-            endpoints_in_error: HashMap::new(),
-            never_try_endpoints: Vec::new(),
+            next_conn_id: 0,
             self_node,
-            receiver,
-            sender,
+            service_sender,
+        };
+        orchestrator.add_endpoints(ws2p_conf.sync_endpoints.iter().cloned());
+        orchestrator
+    }
+
+    /// Add the unknown WS2P v2 endpoints
+    pub fn add_endpoints<I: IntoIterator<Item = EndpointEnum>>(&mut self, endpoints: I) {
+        for endpoint in endpoints {
+            if let EndpointEnum::V2(ref ep) = endpoint {
+                if ep.api.0 != constants::API_NAME || ep.api_version != 2 {
+                    continue;
+                }
+            } else {
+                continue;
+            }
+            self.endpoints
+                .entry(endpoint)
+                .or_insert_with(DbEndpoint::default);
         }
     }
 
-    /// Connect to WSPv2 Endpoint
-    pub fn connect_to_ws2p_v2_endpoint(
-        &self,
-        endpoint: &EndpointEnum,
-        remote_node_id: Option<NodeId>,
-    ) -> Result<(), WsError> {
-        let expected_remote_full_id = if let Some(remote_node_id) = remote_node_id {
-            Some(NodeFullId(remote_node_id, endpoint.pubkey()))
-        } else {
-            None
+    /// Open new outgoing connections until the quota is reached
+    pub fn connect_to_new_endpoints(&mut self, now: u64) {
+        if self.connections.len() >= self.quota {
+            return;
+        }
+        let endpoints = {
+            let excluded: HashSet<&EndpointEnum> = self.connections.values().collect();
+            select_endpoints(
+                &self.endpoints,
+                &excluded,
+                self.quota - self.connections.len(),
+                now,
+            )
         };
-        match controllers::outgoing_connections::connect_to_ws2p_v2_endpoint(
-            &self.currency,
-            &self.sender,
-            &self.self_node,
-            expected_remote_full_id,
-            endpoint,
-        ) {
-            Ok(_) => Ok(()),
-            Err(_) => Err(WsError::UnknownError),
+        for endpoint in endpoints {
+            if let Some(db_endpoint) = self.endpoints.get_mut(&endpoint) {
+                db_endpoint.last_check = now;
+            }
+            self.connect_to_ws2p_v2_endpoint(endpoint);
+        }
+    }
+
+    /// Connect to WSPv2 Endpoint in a dedicated thread
+    pub fn connect_to_ws2p_v2_endpoint(&mut self, endpoint: EndpointEnum) -> WS2PConnId {
+        let conn_id = WS2PConnId::Outgoing(self.next_conn_id);
+        self.next_conn_id += 1;
+        self.connections.insert(conn_id, endpoint.clone());
+
+        let orchestrator_sender = forward_controller_msgs(conn_id, self.service_sender.clone());
+        let currency = self.currency.clone();
+        let self_node = self.self_node.clone();
+        thread::spawn(move || {
+            if let Err(e) = controllers::outgoing_connections::connect_to_ws2p_v2_endpoint(
+                &currency,
+                &orchestrator_sender,
+                &self_node,
+                None,
+                &endpoint,
+                WS2Pv2ConnectType::OutgoingServer,
+            ) {
+                warn!("Fail to connect to {} : {}", endpoint.to_string(), e);
+            }
+        });
+
+        conn_id
+    }
+
+    /// The outgoing connection is established
+    pub fn connection_established(&mut self, conn_id: WS2PConnId) {
+        if let Some(endpoint) = self.connections.get(&conn_id) {
+            if let Some(db_endpoint) = self.endpoints.get_mut(endpoint) {
+                db_endpoint.failures = 0;
+            }
         }
     }
+
+    /// The outgoing connection is ended
+    pub fn connection_ended(&mut self, conn_id: WS2PConnId, established: bool) {
+        if let Some(endpoint) = self.connections.remove(&conn_id) {
+            if !established {
+                if let Some(db_endpoint) = self.endpoints.get_mut(&endpoint) {
+                    db_endpoint.failures += 1;
+                }
+            }
+        }
+    }
+
+    /// Save endpoints in database
+    pub fn save_endpoints(&self) {
+        if let Some(ref ep_file_path) = self.ep_file_path {
+            if let Err(err) = ws2p_db::write_endpoints(ep_file_path, &self.endpoints) {
+                error!("WS2P: fail to write endpoints in DB: {:?}", err);
+            }
+        }
+    }
+}
+
+/// Select the endpoints to connect to (the least failed endpoints first),
+/// an endpoint in error is retried after a delay proportional to its failures count
+fn select_endpoints(
+    endpoints: &HashMap<EndpointEnum, DbEndpoint>,
+    excluded: &HashSet<&EndpointEnum>,
+    count: usize,
+    now: u64,
+) -> Vec<EndpointEnum> {
+    let mut candidates: Vec<(&EndpointEnum, &DbEndpoint)> = endpoints
+        .iter()
+        .filter(|(endpoint, db_endpoint)| {
+            let retry_delay = std::cmp::min(
+                u64::from(db_endpoint.failures) * *constants::WS2P_ENDPOINT_RETRY_DELAY_IN_SECS,
+                *constants::WS2P_ENDPOINT_MAX_RETRY_DELAY_IN_SECS,
+            );
+            !excluded.contains(endpoint) && now >= db_endpoint.last_check + retry_delay
+        })
+        .collect();
+    candidates.sort_by_key(|(_, db_endpoint)| (db_endpoint.failures, db_endpoint.last_check));
+
+    candidates
+        .into_iter()
+        .take(count)
+        .map(|(endpoint, _)| endpoint.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoint(host: &str) -> EndpointEnum {
+        EndpointV2::parse_from_raw(&format!("WS2P V2 {} 20901", host)).expect("parse error")
+    }
+
+    #[test]
+    fn test_select_endpoints() {
+        let mut endpoints = HashMap::new();
+        endpoints.insert(endpoint("never.try"), DbEndpoint::default());
+        endpoints.insert(
+            endpoint("one.failure"),
+            DbEndpoint {
+                failures: 1,
+                last_check: 1_000,
+            },
+        );
+        endpoints.insert(
+            endpoint("two.failures"),
+            DbEndpoint {
+                failures: 2,
+                last_check: 1_000,
+            },
+        );
+        endpoints.insert(
+            endpoint("connected"),
+            DbEndpoint {
+                failures: 0,
+                last_check: 1_000,
+            },
+        );
+        let connected = endpoint("connected");
+        let excluded: HashSet<&EndpointEnum> = vec![&connected].into_iter().collect();
+
+        // Endpoints in error must wait before a new attempt
+        assert_eq!(
+            vec![endpoint("never.try")],
+            select_endpoints(&endpoints, &excluded, 10, 1_100)
+        );
+        assert_eq!(
+            vec![endpoint("never.try"), endpoint("one.failure")],
+            select_endpoints(&endpoints, &excluded, 10, 1_300)
+        );
+        // The least failed endpoints first
+        assert_eq!(
+            vec![endpoint("never.try"), endpoint("one.failure")],
+            select_endpoints(&endpoints, &excluded, 2, 1_600)
+        );
+        assert_eq!(3, select_endpoints(&endpoints, &excluded, 10, 1_600).len());
+    }
 }
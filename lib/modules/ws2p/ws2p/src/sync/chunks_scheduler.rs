@@ -0,0 +1,366 @@
+//  Copyright (C) 2018  The Durs Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Schedule the download of the chunks to be synchronized.
+
+use crate::constants;
+use dubp_documents::documents::block::BlockDocument;
+use dubp_documents::{BlockHash, BlockNumber, Blockstamp};
+use durs_ws2p_messages::v2::requests::WS2Pv2RequestBody;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Chunk identifier (number of the chunk containing the block `chunk_id * chunk_size`)
+pub type ChunkId = u32;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Reasons to refuse a received chunk
+pub enum ChunkError {
+    /// The chunk has not been requested or has already been received
+    UnexpectedChunk(ChunkId),
+    /// The chunk does not contain the expected number of blocks
+    InvalidBlocksCount {
+        /// Expected number of blocks
+        expected: usize,
+        /// Number of blocks found
+        found: usize,
+    },
+    /// Blocks are not consecutive
+    UnexpectedBlockNumber {
+        /// Expected block number
+        expected: BlockNumber,
+        /// Block number found
+        found: BlockNumber,
+    },
+    /// A block has no hash
+    MissingBlockHash(BlockNumber),
+    /// The previous hash of a block does not match the hash of its predecessor
+    BrokenChain(BlockNumber),
+    /// The last block of the last chunk is not the sync target
+    UnexpectedTarget(Blockstamp),
+}
+
+#[derive(Debug)]
+/// Chunks scheduler
+pub struct ChunksScheduler {
+    /// First block to be synchronized
+    first_block: BlockNumber,
+    /// Sync target
+    target: Blockstamp,
+    /// Chunks to be requested
+    to_request: BTreeSet<ChunkId>,
+    /// Chunks requested and not yet received
+    requested: BTreeSet<ChunkId>,
+    /// Chunks received and not yet flushed
+    received: BTreeMap<ChunkId, Vec<BlockDocument>>,
+    /// Number of attempts for each chunk
+    attempts: HashMap<ChunkId, usize>,
+    /// Next chunk to be flushed
+    next_chunk_to_flush: ChunkId,
+    /// Hash of the last flushed block (initially the local current hash,
+    /// none if the synchronization starts from the genesis block)
+    last_flushed_hash: Option<BlockHash>,
+}
+
+impl ChunksScheduler {
+    /// Instantiate a scheduler to synchronize from `current` (or from the genesis block) to `target`
+    pub fn new(current: Option<Blockstamp>, target: Blockstamp) -> ChunksScheduler {
+        let first_block = match current {
+            Some(current) => BlockNumber(current.id.0 + 1),
+            None => BlockNumber(0),
+        };
+        let first_chunk = first_block.0 / *constants::SYNC_CHUNK_SIZE;
+        let last_chunk = target.id.0 / *constants::SYNC_CHUNK_SIZE;
+        let to_request = if first_block.0 <= target.id.0 {
+            (first_chunk..=last_chunk).collect()
+        } else {
+            BTreeSet::new()
+        };
+        ChunksScheduler {
+            first_block,
+            target,
+            to_request,
+            requested: BTreeSet::new(),
+            received: BTreeMap::new(),
+            attempts: HashMap::new(),
+            next_chunk_to_flush: first_chunk,
+            last_flushed_hash: current.map(|current| current.hash),
+        }
+    }
+    /// Number of blocks to be synchronized
+    pub fn blocks_count(&self) -> u32 {
+        if self.first_block.0 <= self.target.id.0 {
+            self.target.id.0 + 1 - self.first_block.0
+        } else {
+            0
+        }
+    }
+    /// Range of the blocks expected in the chunk
+    fn chunk_range(&self, chunk_id: ChunkId) -> (BlockNumber, BlockNumber) {
+        let begin = chunk_id * *constants::SYNC_CHUNK_SIZE;
+        let end = begin + *constants::SYNC_CHUNK_SIZE - 1;
+        (
+            BlockNumber(std::cmp::max(begin, self.first_block.0)),
+            BlockNumber(std::cmp::min(end, self.target.id.0)),
+        )
+    }
+    /// Request body allowing to download the chunk
+    pub fn request_body(&self, chunk_id: ChunkId) -> WS2Pv2RequestBody {
+        if chunk_id == self.target.id.0 / *constants::SYNC_CHUNK_SIZE {
+            WS2Pv2RequestBody::ChunkByHash(self.target)
+        } else {
+            let (begin, end) = self.chunk_range(chunk_id);
+            WS2Pv2RequestBody::Chunk(begin, (end.0 + 1 - begin.0) as u16)
+        }
+    }
+    /// Get the next chunk to be requested.
+    /// Returns none if all chunks are requested or if too many chunks are waiting to be flushed.
+    pub fn next_chunk(&mut self) -> Option<ChunkId> {
+        if self.requested.len() + self.received.len() >= *constants::SYNC_MAX_BUFFERED_CHUNKS {
+            return None;
+        }
+        let chunk_id = *self.to_request.iter().next()?;
+        self.to_request.remove(&chunk_id);
+        self.requested.insert(chunk_id);
+        *self.attempts.entry(chunk_id).or_insert(0) += 1;
+        Some(chunk_id)
+    }
+    /// Put a requested chunk back in the queue (timeout, bad response, etc).
+    /// Returns false if the chunk reached the maximum number of attempts.
+    pub fn requeue(&mut self, chunk_id: ChunkId) -> bool {
+        self.requested.remove(&chunk_id);
+        self.to_request.insert(chunk_id);
+        self.attempts.get(&chunk_id).cloned().unwrap_or(0) < *constants::SYNC_MAX_CHUNK_ATTEMPTS
+    }
+    /// Receive the blocks of a requested chunk.
+    /// In case of error, the chunk must be requeued by the caller.
+    pub fn receive(
+        &mut self,
+        chunk_id: ChunkId,
+        blocks: Vec<BlockDocument>,
+    ) -> Result<(), ChunkError> {
+        if !self.requested.contains(&chunk_id) {
+            return Err(ChunkError::UnexpectedChunk(chunk_id));
+        }
+        let (begin, end) = self.chunk_range(chunk_id);
+
+        // Ignore blocks outside of the chunk range
+        let blocks: Vec<BlockDocument> = blocks
+            .into_iter()
+            .filter(|block| block.number >= begin && block.number <= end)
+            .collect();
+
+        let expected_count = (end.0 + 1 - begin.0) as usize;
+        if blocks.len() != expected_count {
+            return Err(ChunkError::InvalidBlocksCount {
+                expected: expected_count,
+                found: blocks.len(),
+            });
+        }
+
+        let mut previous_hash: Option<BlockHash> = None;
+        for (i, block) in blocks.iter().enumerate() {
+            let expected_number = BlockNumber(begin.0 + i as u32);
+            if block.number != expected_number {
+                return Err(ChunkError::UnexpectedBlockNumber {
+                    expected: expected_number,
+                    found: block.number,
+                });
+            }
+            if block.hash.is_none() {
+                return Err(ChunkError::MissingBlockHash(block.number));
+            }
+            if let Some(previous_hash) = previous_hash {
+                if block.previous_hash != previous_hash.0 {
+                    return Err(ChunkError::BrokenChain(block.number));
+                }
+            }
+            previous_hash = block.hash;
+        }
+
+        if end == self.target.id && previous_hash != Some(self.target.hash) {
+            let last_block = &blocks[blocks.len() - 1];
+            return Err(ChunkError::UnexpectedTarget(Blockstamp {
+                id: last_block.number,
+                hash: last_block.hash.expect("safe unwrap"),
+            }));
+        }
+
+        self.requested.remove(&chunk_id);
+        self.received.insert(chunk_id, blocks);
+        Ok(())
+    }
+    /// Get the blocks of the received chunks that can be applied in order.
+    /// If a chunk does not follow the previous one, it is removed and its id is returned with the error,
+    /// it must then be requeued by the caller.
+    pub fn flush(&mut self) -> (Vec<Vec<BlockDocument>>, Option<(ChunkId, ChunkError)>) {
+        let mut chunks = Vec::new();
+        while let Some(blocks) = self.received.remove(&self.next_chunk_to_flush) {
+            let chunk_id = self.next_chunk_to_flush;
+            if let Some(last_flushed_hash) = self.last_flushed_hash {
+                if blocks[0].previous_hash != last_flushed_hash.0 {
+                    self.requested.insert(chunk_id);
+                    return (
+                        chunks,
+                        Some((chunk_id, ChunkError::BrokenChain(blocks[0].number))),
+                    );
+                }
+            }
+            self.last_flushed_hash = blocks[blocks.len() - 1].hash;
+            self.next_chunk_to_flush += 1;
+            chunks.push(blocks);
+        }
+        (chunks, None)
+    }
+    /// Check if all chunks have been flushed
+    pub fn is_finished(&self) -> bool {
+        self.to_request.is_empty() && self.requested.is_empty() && self.received.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dubp_documents::Document;
+    use dubp_documents_tests_tools::mocks::gen_empty_timed_blocks;
+
+    fn blocks_range(blocks: &[BlockDocument], begin: u32, end: u32) -> Vec<BlockDocument> {
+        blocks[begin as usize..=end as usize].to_vec()
+    }
+
+    #[test]
+    fn test_sync_from_genesis() {
+        let blocks = gen_empty_timed_blocks(600, 10);
+        let target = blocks[599].blockstamp();
+        let mut scheduler = ChunksScheduler::new(None, target);
+        assert_eq!(600, scheduler.blocks_count());
+
+        assert_eq!(Some(0), scheduler.next_chunk());
+        assert_eq!(Some(1), scheduler.next_chunk());
+        assert_eq!(Some(2), scheduler.next_chunk());
+        assert_eq!(None, scheduler.next_chunk());
+        assert_eq!(
+            WS2Pv2RequestBody::Chunk(BlockNumber(250), 250),
+            scheduler.request_body(1)
+        );
+        assert_eq!(
+            WS2Pv2RequestBody::ChunkByHash(target),
+            scheduler.request_body(2)
+        );
+
+        // Receive the chunks in disorder
+        assert_eq!(
+            Ok(()),
+            scheduler.receive(2, blocks_range(&blocks, 500, 599))
+        );
+        let (chunks, error) = scheduler.flush();
+        assert!(chunks.is_empty());
+        assert_eq!(None, error);
+        assert_eq!(Ok(()), scheduler.receive(0, blocks_range(&blocks, 0, 249)));
+        assert_eq!(
+            Ok(()),
+            scheduler.receive(1, blocks_range(&blocks, 250, 499))
+        );
+        let (chunks, error) = scheduler.flush();
+        assert_eq!(None, error);
+        assert_eq!(3, chunks.len());
+        assert_eq!(BlockNumber(500), chunks[2][0].number);
+        assert!(scheduler.is_finished());
+    }
+
+    #[test]
+    fn test_sync_from_current() {
+        let blocks = gen_empty_timed_blocks(300, 10);
+        let mut scheduler =
+            ChunksScheduler::new(Some(blocks[119].blockstamp()), blocks[299].blockstamp());
+        assert_eq!(180, scheduler.blocks_count());
+
+        assert_eq!(Some(0), scheduler.next_chunk());
+        assert_eq!(
+            WS2Pv2RequestBody::Chunk(BlockNumber(120), 130),
+            scheduler.request_body(0)
+        );
+        // The remote node can send more blocks than requested
+        assert_eq!(Ok(()), scheduler.receive(0, blocks_range(&blocks, 0, 249)));
+        let (chunks, _) = scheduler.flush();
+        assert_eq!(1, chunks.len());
+        assert_eq!(130, chunks[0].len());
+        assert_eq!(BlockNumber(120), chunks[0][0].number);
+        assert!(!scheduler.is_finished());
+    }
+
+    #[test]
+    fn test_reject_invalid_chunks() {
+        let blocks = gen_empty_timed_blocks(300, 10);
+        let mut scheduler = ChunksScheduler::new(None, blocks[299].blockstamp());
+        assert_eq!(Some(0), scheduler.next_chunk());
+        assert_eq!(Some(1), scheduler.next_chunk());
+
+        // Missing blocks
+        assert_eq!(
+            Err(ChunkError::InvalidBlocksCount {
+                expected: 250,
+                found: 249
+            }),
+            scheduler.receive(0, blocks_range(&blocks, 0, 248))
+        );
+
+        // Blocks not chained
+        let mut wrong_blocks = blocks_range(&blocks, 0, 249);
+        wrong_blocks[10].previous_hash = dup_crypto_tests_tools::mocks::hash('A');
+        assert_eq!(
+            Err(ChunkError::BrokenChain(BlockNumber(10))),
+            scheduler.receive(0, wrong_blocks)
+        );
+
+        // Not the target
+        let mut wrong_blocks = blocks_range(&blocks, 250, 299);
+        wrong_blocks[49].hash = Some(BlockHash(dup_crypto_tests_tools::mocks::hash('A')));
+        assert_eq!(
+            Err(ChunkError::UnexpectedTarget(Blockstamp {
+                id: BlockNumber(299),
+                hash: BlockHash(dup_crypto_tests_tools::mocks::hash('A')),
+            })),
+            scheduler.receive(1, wrong_blocks)
+        );
+
+        // Chunks 1 does not follow chunk 0
+        assert!(scheduler.requeue(0));
+        assert_eq!(Some(0), scheduler.next_chunk());
+        let mut wrong_blocks = blocks_range(&blocks, 0, 249);
+        wrong_blocks[249].hash = Some(BlockHash(dup_crypto_tests_tools::mocks::hash('A')));
+        assert_eq!(Ok(()), scheduler.receive(0, wrong_blocks));
+        assert_eq!(
+            Ok(()),
+            scheduler.receive(1, blocks_range(&blocks, 250, 299))
+        );
+        let (chunks, error) = scheduler.flush();
+        assert_eq!(1, chunks.len());
+        assert_eq!(Some((1, ChunkError::BrokenChain(BlockNumber(250)))), error);
+        assert!(scheduler.requeue(1));
+        assert!(!scheduler.is_finished());
+    }
+
+    #[test]
+    fn test_max_attempts() {
+        let blocks = gen_empty_timed_blocks(10, 10);
+        let mut scheduler = ChunksScheduler::new(None, blocks[9].blockstamp());
+        for _ in 1..*constants::SYNC_MAX_CHUNK_ATTEMPTS {
+            assert_eq!(Some(0), scheduler.next_chunk());
+            assert!(scheduler.requeue(0));
+        }
+        assert_eq!(Some(0), scheduler.next_chunk());
+        assert!(!scheduler.requeue(0));
+    }
+}
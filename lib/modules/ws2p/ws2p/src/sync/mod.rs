@@ -0,0 +1,697 @@
+//  Copyright (C) 2018  The Durs Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! WS2P v2 network synchronization.
+//! Open several sync connections, download the chunks in parallel
+//! and transmit them in order to the blockchain module.
+
+mod chunks_scheduler;
+
+use self::chunks_scheduler::{ChunkId, ChunksScheduler};
+use crate::constants;
+use crate::controllers::outgoing_connections::connect_to_ws2p_v2_endpoint;
+use crate::WS2PConf;
+use dubp_documents::documents::block::BlockDocument;
+use dubp_documents::{BlockNumber, Blockstamp, CurrencyName};
+use durs_blockchain_dal::BlocksV10DBs;
+use durs_common_tools::fatal_error;
+use durs_common_tools::fns::interrupt;
+use durs_conf::DuRsConf;
+use durs_message::events::DursEvent;
+use durs_message::DursMsg;
+use durs_module::*;
+use durs_network::cli::sync::SyncOpt;
+use durs_network::events::NetworkEvent;
+use durs_network::SyncError;
+use durs_network_documents::network_endpoint::{EndpointEnum, EndpointV2};
+use durs_network_documents::NodeId;
+use durs_ws2p_messages::v2::api_features::WS2PFeatures;
+use durs_ws2p_messages::v2::connect::WS2Pv2ConnectType;
+use durs_ws2p_messages::v2::payload_container::WS2Pv2MessagePayload;
+use durs_ws2p_messages::v2::req_responses::{WS2Pv2ReqRes, WS2Pv2ReqResBody};
+use durs_ws2p_messages::v2::requests::{WS2Pv2Request, WS2Pv2RequestBody};
+use durs_ws2p_messages::v2::WS2Pv2Message;
+use durs_ws2p_messages::WS2PMessage;
+use durs_ws2p_protocol::connection_state::WS2PConnectionState;
+use durs_ws2p_protocol::controller::{WS2PControllerEvent, WebsocketActionOrder};
+use durs_ws2p_protocol::orchestrator::OrchestratorMsg;
+use durs_ws2p_protocol::websocket::{WebsocketAction, WebsocketMessage};
+use durs_ws2p_protocol::MySelfWs2pNode;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// Event received by the sync orchestrator
+#[derive(Debug)]
+enum SyncEvent {
+    /// Message from the controller of a sync connection
+    Orchestrator(usize, Box<OrchestratorMsg<DursMsg>>),
+    /// The sync connection is ended
+    ConnectionEnded(usize),
+}
+
+/// Request sent on a sync connection
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum SyncRequest {
+    /// Request the remote current blockstamp
+    Current,
+    /// Request the block chosen as sync target
+    TargetBlock(BlockNumber),
+    /// Request a chunk
+    Chunk(ChunkId),
+}
+
+/// Sync connection
+#[derive(Debug)]
+struct SyncConnection {
+    /// Remote endpoint
+    endpoint: EndpointEnum,
+    /// Controller channel
+    controller: Option<mpsc::Sender<WebsocketActionOrder>>,
+    /// The connection is established
+    established: bool,
+    /// The connection is closed (or must not be used anymore)
+    closed: bool,
+    /// Current blockstamp of the remote node
+    current: Option<Blockstamp>,
+    /// Pending requests
+    pending_reqs: HashMap<u32, (SyncRequest, SystemTime)>,
+}
+
+impl SyncConnection {
+    fn new(endpoint: EndpointEnum) -> SyncConnection {
+        SyncConnection {
+            endpoint,
+            controller: None,
+            established: false,
+            closed: false,
+            current: None,
+            pending_reqs: HashMap::new(),
+        }
+    }
+    fn is_usable(&self) -> bool {
+        self.established && !self.closed && self.controller.is_some()
+    }
+}
+
+/// Parse sync source (raw WS2P v2 endpoint or url `[ws[s]://]host[:port][/path]`)
+fn parse_sync_source(source: &str) -> Result<EndpointEnum, SyncError> {
+    let invalid_source = || SyncError::InvalidSource {
+        source: source.to_owned(),
+    };
+    if source.starts_with("WS2P ") {
+        return EndpointV2::parse_from_raw(source).map_err(|_| invalid_source());
+    }
+
+    let (default_port, url) = if source.starts_with("wss://") {
+        (443, &source[6..])
+    } else if source.starts_with("ws://") {
+        (80, &source[5..])
+    } else {
+        (443, source)
+    };
+    let (host_and_port, path) = match url.find('/') {
+        Some(pos) => (&url[..pos], url[pos + 1..].trim_end_matches('/')),
+        None => (url, ""),
+    };
+    let (host, port) = match host_and_port.find(':') {
+        Some(pos) => (
+            &host_and_port[..pos],
+            host_and_port[pos + 1..]
+                .parse::<u16>()
+                .map_err(|_| invalid_source())?,
+        ),
+        None => (host_and_port, default_port),
+    };
+    if host.is_empty() {
+        return Err(invalid_source());
+    }
+
+    let raw_endpoint = if path.is_empty() {
+        format!("WS2P V2 {} {}", host, port)
+    } else {
+        format!("WS2P V2 {} {} {}", host, port, path)
+    };
+    EndpointV2::parse_from_raw(&raw_endpoint).map_err(|_| invalid_source())
+}
+
+/// Get local current blockstamp
+fn get_local_current(profile_path: PathBuf, currency: &CurrencyName) -> Option<Blockstamp> {
+    let db_path = durs_conf::get_blockchain_db_path(profile_path, currency);
+    let blocks_dbs = BlocksV10DBs::open(Some(&db_path));
+    durs_blockchain_dal::readers::block::get_current_blockstamp(&blocks_dbs)
+        .unwrap_or_else(|_| fatal_error!("Fail to read local current blockstamp !"))
+}
+
+/// Synchronize the blockchain from the network
+pub fn sync(
+    soft_meta_datas: &SoftwareMetaDatas<DuRsConf>,
+    keys: RequiredKeysContent,
+    conf: &WS2PConf,
+    main_sender: mpsc::Sender<RouterThreadMessage<DursMsg>>,
+    sync_params: SyncOpt,
+) -> Result<(), SyncError> {
+    // Get key_pair
+    let key_pair = if let RequiredKeysContent::NetworkKeyPair(key_pair) = keys {
+        key_pair
+    } else {
+        fatal_error!("WS2P module: unexpected keys for synchronization !");
+    };
+
+    // Get sync endpoints
+    let (source, endpoints) = if let Some(ref source) = sync_params.source {
+        (source.clone(), vec![parse_sync_source(source)?])
+    } else {
+        (String::from("sync endpoints"), conf.sync_endpoints.clone())
+    };
+
+    // Get currency and local current blockstamp
+    let currency = sync_params
+        .currency
+        .clone()
+        .map(CurrencyName)
+        .unwrap_or_else(|| soft_meta_datas.conf.currency());
+    let local_current = get_local_current(soft_meta_datas.profile_path.clone(), &currency);
+
+    let mut ws2p_sync = WS2PSync {
+        currency,
+        self_node: MySelfWs2pNode {
+            my_node_id: NodeId(soft_meta_datas.conf.my_node_id()),
+            my_key_pair: key_pair,
+            my_features: WS2PFeatures(vec![3u8]),
+            low_flow_demand: false,
+        },
+        source,
+        end: sync_params.end,
+        local_current,
+        connections: Vec::new(),
+        next_req_id: 0,
+        start_time: SystemTime::now(),
+        waiting_target_block: false,
+        scheduler: None,
+        main_sender,
+    };
+
+    // Open sync connections
+    let (events_sender, events_receiver) = mpsc::channel();
+    for endpoint in endpoints.into_iter().take(conf.outcoming_quota) {
+        ws2p_sync.open_connection(endpoint, events_sender.clone());
+    }
+    drop(events_sender);
+
+    ws2p_sync.run(&events_receiver)
+}
+
+/// WS2P v2 sync orchestrator
+#[derive(Debug)]
+struct WS2PSync {
+    /// Currency name
+    currency: CurrencyName,
+    /// Local node datas
+    self_node: MySelfWs2pNode,
+    /// Sync source (for errors display)
+    source: String,
+    /// Requested end block
+    end: Option<u32>,
+    /// Local current blockstamp
+    local_current: Option<Blockstamp>,
+    /// Sync connections
+    connections: Vec<SyncConnection>,
+    /// Next request id
+    next_req_id: u32,
+    /// Sync start time
+    start_time: SystemTime,
+    /// Waiting for the block chosen as sync target
+    waiting_target_block: bool,
+    /// Chunks scheduler (exist as soon as the target is known)
+    scheduler: Option<ChunksScheduler>,
+    /// Router sender
+    main_sender: mpsc::Sender<RouterThreadMessage<DursMsg>>,
+}
+
+impl WS2PSync {
+    /// Open a new sync connection in a dedicated thread
+    fn open_connection(&mut self, endpoint: EndpointEnum, events_sender: mpsc::Sender<SyncEvent>) {
+        let conn_index = self.connections.len();
+        self.connections.push(SyncConnection::new(endpoint.clone()));
+
+        let (conn_sender, conn_receiver) = mpsc::channel();
+        let currency = self.currency.clone();
+        let self_node = self.self_node.clone();
+        let connect_type = WS2Pv2ConnectType::Sync(self.local_current);
+        thread::spawn(move || {
+            if let Err(e) = connect_to_ws2p_v2_endpoint(
+                &currency,
+                &conn_sender,
+                &self_node,
+                None,
+                &endpoint,
+                connect_type,
+            ) {
+                warn!("Fail to connect to {} : {}", endpoint.to_string(), e);
+            }
+        });
+        // Forward controller messages until the connection is ended
+        thread::spawn(move || {
+            for msg in conn_receiver {
+                if events_sender
+                    .send(SyncEvent::Orchestrator(conn_index, Box::new(msg)))
+                    .is_err()
+                {
+                    return;
+                }
+            }
+            let _ = events_sender.send(SyncEvent::ConnectionEnded(conn_index));
+        });
+    }
+    /// Sync main loop
+    fn run(&mut self, events_receiver: &mpsc::Receiver<SyncEvent>) -> Result<(), SyncError> {
+        loop {
+            match events_receiver.recv_timeout(Duration::from_millis(
+                *constants::WS2P_RECV_SERVICE_FREQ_IN_MS,
+            )) {
+                Ok(SyncEvent::Orchestrator(conn_index, msg)) => {
+                    self.process_orchestrator_msg(conn_index, *msg)?
+                }
+                Ok(SyncEvent::ConnectionEnded(conn_index)) => {
+                    self.close_connection(conn_index, None)?
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    for conn_index in 0..self.connections.len() {
+                        self.close_connection(conn_index, None)?;
+                    }
+                }
+            }
+            self.check_requests_timeouts()?;
+
+            if interrupt::is_interrupted() {
+                info!("WS2P sync interrupted.");
+                break;
+            }
+            if let Some(ref scheduler) = self.scheduler {
+                if scheduler.is_finished() {
+                    break;
+                }
+                self.send_chunks_requests()?;
+            } else if !self.waiting_target_block {
+                self.try_to_choose_target()?;
+            }
+
+            if self.connections.iter().all(|conn| conn.closed) {
+                return Err(SyncError::UnreachableSource {
+                    source: self.source.clone(),
+                });
+            }
+        }
+
+        // Close sync connections
+        for conn_index in 0..self.connections.len() {
+            self.close_connection(conn_index, Some("Sync ended."))?;
+        }
+        // Notify the blockchain module that the download is finished
+        let _ = self
+            .main_sender
+            .send(RouterThreadMessage::ModuleMessage(DursMsg::Stop));
+
+        Ok(())
+    }
+    /// Process a message of a connection controller
+    fn process_orchestrator_msg(
+        &mut self,
+        conn_index: usize,
+        msg: OrchestratorMsg<DursMsg>,
+    ) -> Result<(), SyncError> {
+        match msg {
+            OrchestratorMsg::ControllerSender(controller) => {
+                self.connections[conn_index].controller = Some(controller);
+            }
+            OrchestratorMsg::ControllerEvent { event, .. } => match event {
+                WS2PControllerEvent::NewConnEstablished { .. }
+                | WS2PControllerEvent::StateChange {
+                    new_state: WS2PConnectionState::Established,
+                } => {
+                    if !self.connections[conn_index].established {
+                        info!(
+                            "Sync connection established with {}.",
+                            self.connections[conn_index].endpoint.to_string()
+                        );
+                        self.connections[conn_index].established = true;
+                        if !self.send_request(conn_index, SyncRequest::Current) {
+                            self.close_connection(conn_index, None)?;
+                        }
+                    }
+                }
+                WS2PControllerEvent::StateChange { .. } => {}
+                WS2PControllerEvent::RecvValidMsg {
+                    ws2p_msg: WS2PMessage::V2(msg_v2),
+                } => {
+                    if let WS2Pv2MessagePayload::ReqRes(req_res) = msg_v2.payload {
+                        self.process_req_res(conn_index, req_res)?;
+                    }
+                }
+                WS2PControllerEvent::RecvValidMsg { .. } => {}
+            },
+            OrchestratorMsg::ModuleMessage(_) => {}
+        }
+        Ok(())
+    }
+    /// Send a request on a sync connection, returns false if the connection is unusable
+    fn send_request(&mut self, conn_index: usize, request: SyncRequest) -> bool {
+        let body = match request {
+            SyncRequest::Current => WS2Pv2RequestBody::Current,
+            SyncRequest::TargetBlock(block_number) => WS2Pv2RequestBody::Chunk(block_number, 1),
+            SyncRequest::Chunk(chunk_id) => {
+                if let Some(ref scheduler) = self.scheduler {
+                    scheduler.request_body(chunk_id)
+                } else {
+                    fatal_error!("Dev error: request chunk before choosing the sync target !")
+                }
+            }
+        };
+        let req_id = self.next_req_id;
+        self.next_req_id = self.next_req_id.wrapping_add(1);
+
+        let bin_msg = match WS2Pv2Message::encapsulate_payload(
+            self.currency.clone(),
+            self.self_node.my_node_id,
+            self.self_node.my_key_pair,
+            WS2Pv2MessagePayload::Request(WS2Pv2Request { id: req_id, body }),
+        ) {
+            Ok((_, bin_msg)) => bin_msg,
+            Err(_) => fatal_error!("Dev error: fail to sign own request message !"),
+        };
+        let conn = &mut self.connections[conn_index];
+        if let Some(ref controller) = conn.controller {
+            if controller
+                .send(WebsocketActionOrder {
+                    ws_action: WebsocketAction::SendMessage {
+                        msg: WebsocketMessage::Bin(bin_msg),
+                    },
+                    new_state_if_success: None,
+                    new_state_if_fail: WS2PConnectionState::Unreachable,
+                })
+                .is_ok()
+            {
+                conn.pending_reqs
+                    .insert(req_id, (request, SystemTime::now()));
+                return true;
+            }
+        }
+        false
+    }
+    /// Process a request response
+    fn process_req_res(
+        &mut self,
+        conn_index: usize,
+        req_res: WS2Pv2ReqRes,
+    ) -> Result<(), SyncError> {
+        let request = match self.connections[conn_index]
+            .pending_reqs
+            .remove(&req_res.id)
+        {
+            Some((request, _)) => request,
+            None => {
+                warn!("Sync: receive response to unknown request #{}.", req_res.id);
+                return Ok(());
+            }
+        };
+        match (request, req_res.body) {
+            (SyncRequest::Current, WS2Pv2ReqResBody::Current(blockstamp)) => {
+                debug!("Sync: remote current = {}", blockstamp);
+                self.connections[conn_index].current = Some(blockstamp);
+                Ok(())
+            }
+            (SyncRequest::TargetBlock(block_number), WS2Pv2ReqResBody::Chunk(blocks)) => {
+                match blocks
+                    .iter()
+                    .find(|block| block.number == block_number && block.hash.is_some())
+                {
+                    Some(block) => {
+                        let target = Blockstamp {
+                            id: block.number,
+                            hash: block.hash.expect("safe unwrap"),
+                        };
+                        self.waiting_target_block = false;
+                        self.start_download(target);
+                        Ok(())
+                    }
+                    None => {
+                        self.requeue_request(request)?;
+                        self.close_connection(conn_index, Some("Invalid response."))
+                    }
+                }
+            }
+            (SyncRequest::Chunk(chunk_id), WS2Pv2ReqResBody::Chunk(blocks)) => {
+                self.receive_chunk(conn_index, chunk_id, blocks)
+            }
+            (request, body) => {
+                if let WS2Pv2ReqResBody::BadRequest(reason) = body {
+                    warn!("Sync: bad request {:?} : {}", request, reason);
+                } else {
+                    warn!("Sync: unexpected response to request {:?}.", request);
+                }
+                self.requeue_request(request)?;
+                self.close_connection(conn_index, Some("Invalid response."))
+            }
+        }
+    }
+    /// Receive a chunk and transmit the blocks that can be applied
+    fn receive_chunk(
+        &mut self,
+        conn_index: usize,
+        chunk_id: ChunkId,
+        blocks: Vec<BlockDocument>,
+    ) -> Result<(), SyncError> {
+        let scheduler = self
+            .scheduler
+            .as_mut()
+            .unwrap_or_else(|| fatal_error!("Dev error: receive chunk without scheduler !"));
+        if let Err(e) = scheduler.receive(chunk_id, blocks) {
+            warn!("Sync: receive invalid chunk #{} : {:?}", chunk_id, e);
+            self.requeue_request(SyncRequest::Chunk(chunk_id))?;
+            return self.close_connection(conn_index, Some("Invalid chunk."));
+        }
+        let (chunks, flush_error) = scheduler.flush();
+        for blocks in chunks {
+            self.send_network_event(NetworkEvent::ReceiveBlocks(blocks));
+        }
+        if let Some((chunk_id, e)) = flush_error {
+            warn!(
+                "Sync: chunk #{} does not follow previous chunk : {:?}",
+                chunk_id, e
+            );
+            self.requeue_request(SyncRequest::Chunk(chunk_id))?;
+        }
+        Ok(())
+    }
+    /// Put a failed request back in the queue
+    fn requeue_request(&mut self, request: SyncRequest) -> Result<(), SyncError> {
+        match request {
+            SyncRequest::Current => {}
+            SyncRequest::TargetBlock(_) => self.waiting_target_block = false,
+            SyncRequest::Chunk(chunk_id) => {
+                if let Some(ref mut scheduler) = self.scheduler {
+                    if !scheduler.requeue(chunk_id) {
+                        return Err(SyncError::InvalidSourceDatas {
+                            source: self.source.clone(),
+                            reason: format!("fail to download chunk #{}", chunk_id),
+                        });
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+    /// Close a sync connection and requeue its pending requests
+    fn close_connection(
+        &mut self,
+        conn_index: usize,
+        reason: Option<&str>,
+    ) -> Result<(), SyncError> {
+        let conn = &mut self.connections[conn_index];
+        if !conn.closed {
+            if let Some(ref controller) = conn.controller {
+                let _ = controller.send(WebsocketActionOrder::close_with_reason(
+                    reason.map(ToOwned::to_owned),
+                ));
+            }
+            conn.closed = true;
+        }
+        let pending_reqs: Vec<SyncRequest> = conn
+            .pending_reqs
+            .drain()
+            .map(|(_, (request, _))| request)
+            .collect();
+        for request in pending_reqs {
+            self.requeue_request(request)?;
+        }
+        Ok(())
+    }
+    /// Requeue timed out requests and close the corresponding connections
+    fn check_requests_timeouts(&mut self) -> Result<(), SyncError> {
+        let now = SystemTime::now();
+        for conn_index in 0..self.connections.len() {
+            let timeout =
+                self.connections[conn_index]
+                    .pending_reqs
+                    .values()
+                    .any(|(_, send_time)| {
+                        now.duration_since(*send_time).unwrap_or_default()
+                            > Duration::from_secs(*constants::SYNC_REQUEST_TIMEOUT_IN_SECS)
+                    });
+            if timeout {
+                warn!(
+                    "Sync: request timeout with {}.",
+                    self.connections[conn_index].endpoint.to_string()
+                );
+                self.close_connection(conn_index, Some("Request timeout."))?;
+            }
+        }
+        Ok(())
+    }
+    /// Choose the sync target among the remote currents
+    fn try_to_choose_target(&mut self) -> Result<(), SyncError> {
+        let all_currents_received = self
+            .connections
+            .iter()
+            .all(|conn| conn.closed || conn.current.is_some());
+        let wait_timeout = SystemTime::now()
+            .duration_since(self.start_time)
+            .unwrap_or_default()
+            > Duration::from_secs(*constants::SYNC_WAIT_CURRENTS_TIMEOUT_IN_SECS);
+        if !all_currents_received && !wait_timeout {
+            return Ok(());
+        }
+
+        // The target is the most shared current (or the highest in case of equality)
+        let mut votes: HashMap<Blockstamp, usize> = HashMap::new();
+        for conn in &self.connections {
+            if let Some(current) = conn.current {
+                if !conn.closed {
+                    *votes.entry(current).or_insert(0) += 1;
+                }
+            }
+        }
+        let best_current = match votes
+            .into_iter()
+            .max_by_key(|(blockstamp, count)| (*count, blockstamp.id))
+        {
+            Some((blockstamp, _)) => blockstamp,
+            None => return Ok(()),
+        };
+
+        match self.end {
+            Some(end) if end < best_current.id.0 => {
+                // Request the target block to a node that has it
+                let conn_index = self
+                    .connections
+                    .iter()
+                    .position(|conn| {
+                        conn.is_usable() && conn.current.map(|c| c.id.0 >= end) == Some(true)
+                    })
+                    .unwrap_or_else(|| fatal_error!("Dev error: voter connection not found !"));
+                if self.send_request(conn_index, SyncRequest::TargetBlock(BlockNumber(end))) {
+                    self.waiting_target_block = true;
+                } else {
+                    self.close_connection(conn_index, None)?;
+                }
+            }
+            _ => self.start_download(best_current),
+        }
+        Ok(())
+    }
+    /// Send the sync target to the blockchain module and start downloading chunks
+    fn start_download(&mut self, target: Blockstamp) {
+        let scheduler = ChunksScheduler::new(self.local_current, target);
+        info!(
+            "Sync target = {} ({} blocks to download)",
+            target,
+            scheduler.blocks_count()
+        );
+        self.send_network_event(NetworkEvent::SyncTarget(self.currency.clone(), target));
+        self.scheduler = Some(scheduler);
+    }
+    /// Fill the sync connections with chunk requests
+    fn send_chunks_requests(&mut self) -> Result<(), SyncError> {
+        for conn_index in 0..self.connections.len() {
+            while self.connections[conn_index].is_usable()
+                && self.connections[conn_index].pending_reqs.len()
+                    < *constants::SYNC_MAX_PENDING_REQS_PER_CONN
+            {
+                let chunk_id = match self
+                    .scheduler
+                    .as_mut()
+                    .and_then(ChunksScheduler::next_chunk)
+                {
+                    Some(chunk_id) => chunk_id,
+                    None => return Ok(()),
+                };
+                if !self.send_request(conn_index, SyncRequest::Chunk(chunk_id)) {
+                    self.requeue_request(SyncRequest::Chunk(chunk_id))?;
+                    self.close_connection(conn_index, None)?;
+                }
+            }
+        }
+        Ok(())
+    }
+    /// Send a network event to the blockchain module
+    fn send_network_event(&self, event: NetworkEvent) {
+        if self
+            .main_sender
+            .send(RouterThreadMessage::ModuleMessage(DursMsg::Event {
+                event_from: ModuleStaticName(constants::MODULE_NAME),
+                event_type: ModuleEvent::NewBlockFromNetwork,
+                event_content: DursEvent::NetworkEvent(event),
+            }))
+            .is_err()
+        {
+            fatal_error!("Sync: blockchain module unreachable !");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sync_source() {
+        assert_eq!(
+            EndpointV2::parse_from_raw("WS2P V2 g1.durs.info 443 ws2p").expect("parse error"),
+            parse_sync_source("g1.durs.info/ws2p").expect("invalid source"),
+        );
+        assert_eq!(
+            EndpointV2::parse_from_raw("WS2P V2 g1.durs.info 443 ws2p").expect("parse error"),
+            parse_sync_source("wss://g1.durs.info/ws2p/").expect("invalid source"),
+        );
+        assert_eq!(
+            EndpointV2::parse_from_raw("WS2P V2 localhost 20900").expect("parse error"),
+            parse_sync_source("ws://localhost:20900").expect("invalid source"),
+        );
+        assert_eq!(
+            EndpointV2::parse_from_raw("WS2P V2 192.168.1.1 80").expect("parse error"),
+            parse_sync_source("ws://192.168.1.1").expect("invalid source"),
+        );
+        assert_eq!(
+            Err(SyncError::InvalidSource {
+                source: String::from("ws://localhost:port")
+            }),
+            parse_sync_source("ws://localhost:port"),
+        );
+    }
+}
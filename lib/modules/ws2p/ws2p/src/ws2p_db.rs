@@ -0,0 +1,79 @@
+//  Copyright (C) 2018  The Durs Project Developers.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! WS2P v2 endpoints database.
+
+use durs_network_documents::network_endpoint::EndpointEnum;
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+
+/// Endpoints database error
+#[derive(Debug)]
+pub enum Ws2pEndpointsDbError {
+    /// I/O error
+    IoErr(std::io::Error),
+    /// Serialization error
+    SerdeErr(bincode::Error),
+}
+
+impl From<std::io::Error> for Ws2pEndpointsDbError {
+    fn from(e: std::io::Error) -> Self {
+        Ws2pEndpointsDbError::IoErr(e)
+    }
+}
+
+impl From<bincode::Error> for Ws2pEndpointsDbError {
+    fn from(e: bincode::Error) -> Self {
+        Ws2pEndpointsDbError::SerdeErr(e)
+    }
+}
+
+/// Connection attempts history of an endpoint
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+pub struct DbEndpoint {
+    /// Number of consecutive failed connection attempts
+    pub failures: u32,
+    /// Timestamp of the last connection attempt
+    pub last_check: u64,
+}
+
+/// Read endpoints in database file
+pub fn get_endpoints(
+    file_path: &Path,
+) -> Result<HashMap<EndpointEnum, DbEndpoint>, Ws2pEndpointsDbError> {
+    if file_path.exists() {
+        let bin_endpoints = durs_common_tools::fns::bin_file::read_bin_file(file_path)?;
+        if bin_endpoints.is_empty() {
+            Ok(HashMap::new())
+        } else {
+            Ok(bincode::deserialize(&bin_endpoints[..])?)
+        }
+    } else {
+        File::create(file_path)?;
+        Ok(HashMap::new())
+    }
+}
+
+/// Write endpoints in database file
+pub fn write_endpoints<S: std::hash::BuildHasher>(
+    file_path: &Path,
+    endpoints: &HashMap<EndpointEnum, DbEndpoint, S>,
+) -> Result<(), Ws2pEndpointsDbError> {
+    let bin_endpoints = bincode::serialize(&endpoints)?;
+    durs_common_tools::fns::bin_file::write_bin_file(file_path, &bin_endpoints)?;
+
+    Ok(())
+}
@@ -58,6 +58,7 @@ fn server_node() -> MySelfWs2pNode {
         my_node_id: NodeId(0),
         my_key_pair: KeyPairEnum::Ed25519(keypair1()),
         my_features: WS2PFeatures(vec![5u8]),
+        low_flow_demand: false,
     }
 }
 
@@ -66,6 +67,7 @@ fn client_node() -> MySelfWs2pNode {
         my_node_id: NodeId(1),
         my_key_pair: KeyPairEnum::Ed25519(keypair2()),
         my_features: WS2PFeatures(vec![5u8]),
+        low_flow_demand: false,
     }
 }
 
@@ -114,6 +116,7 @@ fn test_connection_negociation_denial() {
             )),
             &EndpointV2::parse_from_raw(&format!("WS2P V2 localhost {}", *PORT + 1))
                 .expect("Fail to parse endpoint"),
+            WS2Pv2ConnectType::OutgoingServer,
         )
     });
 
@@ -193,6 +196,7 @@ fn test_connection_negociation_success() {
             Some(server_node_clone.get_full_id()),
             &EndpointV2::parse_from_raw(&format!("WS2P V2 localhost {}", *PORT))
                 .expect("Fail to parse endpoint"),
+            WS2Pv2ConnectType::OutgoingServer,
         )
     });
 
@@ -246,6 +250,7 @@ fn test_connection_negociation_success() {
         WS2PControllerEvent::NewConnEstablished {
             conn_type: WS2Pv2ConnectType::OutgoingServer,
             remote_full_id: server_node.get_full_id(),
+            low_flow: false,
         },
     );
     // Established for server
@@ -254,6 +259,7 @@ fn test_connection_negociation_success() {
         WS2PControllerEvent::NewConnEstablished {
             conn_type: WS2Pv2ConnectType::OutgoingServer,
             remote_full_id: client_node.get_full_id(),
+            low_flow: false,
         },
     );
 }
@@ -16,6 +16,7 @@
 //! Mocks for projects use dubp-documents
 
 pub mod identity;
+pub mod membership;
 
 use dubp_documents::documents::block::BlockDocument;
 use dubp_documents::*;
@@ -0,0 +1,52 @@
+//  Copyright (C) 2019  Éloïs SANCHEZ
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Catch interruption signals (Ctrl-C), so that long tasks can stop cleanly.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set when an interruption signal is received
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(unix)]
+extern "C" fn on_interrupt_signal(_signal: libc::c_int) {
+    if INTERRUPTED.swap(true, Ordering::SeqCst) {
+        // Second interruption: the user really wants to stop now
+        #[allow(unsafe_code)]
+        unsafe {
+            libc::_exit(130);
+        }
+    }
+}
+
+/// Catch the interruption signals (SIGINT and SIGTERM).
+///
+/// After this call, the first interruption no longer kills the process: the long tasks must poll
+/// `is_interrupted()` and stop by themselves. A second interruption kills the process immediately.
+pub fn catch_interrupt_signals() {
+    #[cfg(unix)]
+    #[allow(unsafe_code)]
+    unsafe {
+        let handler = on_interrupt_signal as libc::sighandler_t;
+        libc::signal(libc::SIGINT, handler);
+        libc::signal(libc::SIGTERM, handler);
+    }
+}
+
+#[inline]
+/// Check if an interruption signal has been received
+pub fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
@@ -16,5 +16,6 @@
 //! Common rust functions for DURS project.
 
 pub mod bin_file;
+pub mod interrupt;
 pub mod str_escape;
 pub mod time;
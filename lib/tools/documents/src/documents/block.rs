@@ -723,6 +723,154 @@ impl ToStringObject for BlockDocument {
     }
 }
 
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+/// Block document in the json format of Duniter chunk files
+pub struct BlockDocumentDuniterJson {
+    /// Version
+    pub version: u64,
+    /// Nonce
+    pub nonce: u64,
+    /// number
+    pub number: u64,
+    /// Minimal proof of work difficulty
+    pub pow_min: u64,
+    /// Local time of the block issuer
+    pub time: u64,
+    /// Average time
+    pub median_time: u64,
+    /// Members count
+    pub members_count: u64,
+    /// Monetary mass
+    pub monetary_mass: u64,
+    /// Unit base (power of ten)
+    #[serde(rename = "unitbase")]
+    pub unit_base: u64,
+    /// Number of compute members in the current frame
+    pub issuers_count: u64,
+    /// Current frame size (in blocks)
+    pub issuers_frame: i64,
+    /// Current frame variation buffer
+    pub issuers_frame_var: i64,
+    /// Currency.
+    pub currency: String,
+    /// Document issuer
+    pub issuer: String,
+    /// Document signature
+    pub signature: String,
+    /// Block hash
+    pub hash: Option<String>,
+    /// Currency parameters (empty string except in genesis block)
+    pub parameters: String,
+    /// Hash of the previous block (None for genesis block)
+    pub previous_hash: Option<String>,
+    /// Issuer of the previous block (None for genesis block)
+    pub previous_issuer: Option<String>,
+    /// Hash of the deterministic content of the block
+    #[serde(rename = "inner_hash")]
+    pub inner_hash: Option<String>,
+    /// Amount of new dividend created at this block, None if no dividend is created at this block
+    pub dividend: Option<u64>,
+    /// Identities (compact format)
+    pub identities: Vec<String>,
+    /// joiners (compact format)
+    pub joiners: Vec<String>,
+    /// Actives (compact format)
+    pub actives: Vec<String>,
+    /// Leavers (compact format)
+    pub leavers: Vec<String>,
+    /// Revokeds (compact format)
+    pub revoked: Vec<String>,
+    /// Excludeds
+    pub excluded: Vec<String>,
+    /// Certifications (compact format)
+    pub certifications: Vec<String>,
+    /// Transactions
+    pub transactions: Vec<TransactionDocumentStringified>,
+}
+
+impl BlockDocument {
+    /// Transforms the block into the json format of Duniter chunk files
+    pub fn to_duniter_json(&self) -> BlockDocumentDuniterJson {
+        let genesis = self.number.0 == 0;
+        BlockDocumentDuniterJson {
+            version: u64::from(self.version),
+            nonce: self.nonce,
+            number: u64::from(self.number.0),
+            pow_min: self.pow_min as u64,
+            time: self.time,
+            median_time: self.median_time,
+            members_count: self.members_count as u64,
+            monetary_mass: self.monetary_mass as u64,
+            unit_base: self.unit_base as u64,
+            issuers_count: self.issuers_count as u64,
+            issuers_frame: self.issuers_frame as i64,
+            issuers_frame_var: self.issuers_frame_var as i64,
+            currency: self.currency.to_string(),
+            issuer: self.issuers[0].to_string(),
+            signature: self.signatures[0].to_string(),
+            hash: self.hash.map(|hash| hash.to_string()),
+            parameters: self
+                .parameters
+                .map(|parameters| parameters.to_string())
+                .unwrap_or_default(),
+            previous_hash: if genesis {
+                None
+            } else {
+                Some(self.previous_hash.to_string())
+            },
+            previous_issuer: if genesis {
+                None
+            } else {
+                self.previous_issuer.map(|p| p.to_string())
+            },
+            inner_hash: self.inner_hash.map(|hash| hash.to_string()),
+            dividend: self.dividend.map(|dividend| dividend as u64),
+            identities: self
+                .identities
+                .iter()
+                .map(|idty| idty.to_compact_document().as_compact_text())
+                .collect(),
+            joiners: self
+                .joiners
+                .iter()
+                .map(CompactTextDocument::as_compact_text)
+                .collect(),
+            actives: self
+                .actives
+                .iter()
+                .map(CompactTextDocument::as_compact_text)
+                .collect(),
+            leavers: self
+                .leavers
+                .iter()
+                .map(CompactTextDocument::as_compact_text)
+                .collect(),
+            revoked: self
+                .revoked
+                .iter()
+                .map(CompactTextDocument::as_compact_text)
+                .collect(),
+            excluded: self.excluded.iter().map(ToString::to_string).collect(),
+            certifications: self
+                .certifications
+                .iter()
+                .map(CompactTextDocument::as_compact_text)
+                .collect(),
+            transactions: self
+                .transactions
+                .iter()
+                .map(|tx_doc_or_tx_hash| match tx_doc_or_tx_hash {
+                    TxDocOrTxHash::TxDoc(tx_doc) => tx_doc.to_string_object(),
+                    TxDocOrTxHash::TxHash(_) => {
+                        fatal_error!("Try to export block without their tx documents")
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::certification::CertificationDocumentParser;
@@ -390,6 +390,24 @@ impl UTXOConditionsGroup {
             _ => fatal_error!("unexpected rule: {:?}", pair.as_rule()), // Grammar ensures that we never reach this line
         }
     }
+    /// Get the public keys whose signature is required by at least one SIG condition
+    pub fn get_sig_pubkeys(&self) -> Vec<PubKey> {
+        match *self {
+            UTXOConditionsGroup::Single(TransactionOutputCondition::Sig(pubkey)) => vec![pubkey],
+            UTXOConditionsGroup::Single(_) => vec![],
+            UTXOConditionsGroup::Brackets(ref condition_group) => condition_group.get_sig_pubkeys(),
+            UTXOConditionsGroup::And(ref condition_group_1, ref condition_group_2)
+            | UTXOConditionsGroup::Or(ref condition_group_1, ref condition_group_2) => {
+                let mut pubkeys = condition_group_1.get_sig_pubkeys();
+                for pubkey in condition_group_2.get_sig_pubkeys() {
+                    if !pubkeys.contains(&pubkey) {
+                        pubkeys.push(pubkey);
+                    }
+                }
+                pubkeys
+            }
+        }
+    }
 }
 
 impl ToString for UTXOConditionsGroup {
@@ -636,6 +654,14 @@ impl TransactionDocument {
     pub fn get_outputs(&self) -> &[TransactionOutput] {
         &self.outputs
     }
+    /// Get transaction inputs unlocks
+    pub fn get_unlocks(&self) -> &[TransactionInputUnlocks] {
+        &self.unlocks
+    }
+    /// Get transaction comment
+    pub fn get_comment(&self) -> &str {
+        &self.comment
+    }
     /// Lightens the transaction (for example to store it while minimizing the space required)
     pub fn reduce(&mut self) {
         self.text = None;
@@ -1122,4 +1148,25 @@ e3LpgB2RZ/E/BCxPJsn+TDDyxGYzrIsMyDt//KhJCjIQD6pNUxr5M5jrq2OwQZgwmz91YcmoQ2XRQAUD
 w69bYgiQxDmCReB0Dugt9BstXlAKnwJkKCdWvCeZ9KnUCv0FJys6klzYk/O/b9t74tYhWZSX0bhETWHiwfpWBw=="
         );
     }
+
+    #[test]
+    fn get_utxo_conditions_sig_pubkeys() {
+        let output = TransactionOutput::from_str(
+            "10:0:(SIG(38MEAZN68Pz1DTvT3tqgxx4yQP6snJCQhPqEFxbDk4aE) && XHX(3EB4702F2AC2FD3FA4FDC46A4FC05AE8CDEE1A85F2AC2FD3FA4FDC46A4FC01CA)) || SIG(FEkbc4BfJukSWnCU6Hed6dgwwTuPFTVdgz5LpL4iHr9J)",
+        )
+        .expect("fail to parse output !");
+        assert_eq!(
+            vec![
+                PubKey::Ed25519(
+                    ed25519::PublicKey::from_base58("38MEAZN68Pz1DTvT3tqgxx4yQP6snJCQhPqEFxbDk4aE")
+                        .unwrap()
+                ),
+                PubKey::Ed25519(
+                    ed25519::PublicKey::from_base58("FEkbc4BfJukSWnCU6Hed6dgwwTuPFTVdgz5LpL4iHr9J")
+                        .unwrap()
+                ),
+            ],
+            output.conditions.conditions.get_sig_pubkeys()
+        );
+    }
 }
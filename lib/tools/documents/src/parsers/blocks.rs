@@ -352,5 +352,15 @@ mod tests {
             parse_json_block(&block_json_value).expect("Fail to parse block_json_value !")
         );
         assert!(expected_block.verify_inner_hash());
+
+        // Export block in duniter json format and parse it again
+        let exported_json_block = serde_json::to_string(&expected_block.to_duniter_json())
+            .expect("Fail to export block !");
+        let exported_json_value = json_pest_parser::parse_json_string(&exported_json_block)
+            .expect("Fail to parse exported json block !");
+        assert_eq!(
+            expected_block,
+            parse_json_block(&exported_json_value).expect("Fail to parse exported_json_value !")
+        );
     }
 }